@@ -0,0 +1,5 @@
+fn main() {
+    // links the Node.js addon, whose symbols are resolved by node when it is loaded
+    #[cfg(feature = "node")]
+    napi_build::setup();
+}
@@ -0,0 +1,107 @@
+/*
+ * C ABI of chiquito: the compiler, the witness checker, the mock prover and the bn256 prover, see
+ * src/frontend/ffi.rs. Built into the cdylib of the crate with the `ffi` feature:
+ *
+ *     cargo build --release --no-default-features --features ffi
+ *
+ * Circuits are the JSON of the Python frontend, and witnesses the JSON of a TraceWitness or its
+ * binary format, told apart by its magic bytes. Every function returns a ChiquitoStatus, and its
+ * results through the out pointers, that are only written on success. The message of the last
+ * error of the thread is returned by chiquito_last_error. Strings and bytes returned are owned by
+ * the caller, and freed with chiquito_string_free and chiquito_bytes_free. A circuit can be used
+ * from any thread, but not from several at once.
+ */
+
+#ifndef CHIQUITO_H
+#define CHIQUITO_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum ChiquitoStatus {
+    CHIQUITO_OK = 0,
+    /* JSON or binary witness that does not deserialize to the expected chiquito type. */
+    CHIQUITO_DESERIALIZATION_ERROR = 1,
+    /* Circuit that chiquito fails to compile, or to lay out in a halo2 table. */
+    CHIQUITO_COMPILATION_ERROR = 2,
+    /* Invalid argument, like a null pointer or inconsistent compiler options. */
+    CHIQUITO_VALUE_ERROR = 3,
+    /* Params that cannot be read. */
+    CHIQUITO_IO_ERROR = 4,
+    /* Failure to generate the keys or a proof of a circuit. */
+    CHIQUITO_PROVING_ERROR = 5,
+    /* Panic of chiquito, a bug. */
+    CHIQUITO_PANIC = 6,
+} ChiquitoStatus;
+
+/* Bytes owned by the caller, freed with chiquito_bytes_free. */
+typedef struct ChiquitoBytes {
+    uint8_t *data;
+    size_t len;
+} ChiquitoBytes;
+
+/* Circuit compiled from the JSON of its AST, freed with chiquito_circuit_free. */
+typedef struct ChiquitoCircuit ChiquitoCircuit;
+
+/* Message of the last error of the thread, empty if none, valid until the next call that fails. */
+const char *chiquito_last_error(void);
+
+/* Compiles the JSON of a circuit with the JSON of its CompilerOptions, or the defaults if null. */
+ChiquitoStatus chiquito_compile(const char *ast_json, const char *options_json,
+                                ChiquitoCircuit **out);
+
+void chiquito_circuit_free(ChiquitoCircuit *circuit);
+
+/* AST of the circuit as parsed, serialized back to JSON. */
+ChiquitoStatus chiquito_ast_json(ChiquitoCircuit *circuit, char **out);
+
+/* Plonkish IR the circuit was compiled to, as JSON, with the placement of its signals. */
+ChiquitoStatus chiquito_plonkish_json(ChiquitoCircuit *circuit, char **out);
+
+/* Size of the circuit in the halo2 table, as JSON, and the smallest k that fits it, if not null. */
+ChiquitoStatus chiquito_stats(ChiquitoCircuit *circuit, char **out, uint32_t *min_k);
+
+/* JSON array of the violations of a witness found by the native checker, empty if none. */
+ChiquitoStatus chiquito_check_witness(ChiquitoCircuit *circuit, const uint8_t *witness,
+                                      size_t witness_len, char **out);
+
+/* JSON of the result of MockProver, { "ok": bool, "failures": [string] }, with the smallest k
+ * that fits the circuit if k is 0. */
+ChiquitoStatus chiquito_mock_prove(ChiquitoCircuit *circuit, const uint8_t *witness,
+                                   size_t witness_len, uint32_t k, char **out);
+
+/* Proof of a bn256 circuit with the KZG params of params_path, and its public inputs as the JSON
+ * of their decimal strings, by instance column. With evm, the proof uses the Keccak256 transcript
+ * of the verifier of chiquito_solidity_verifier. */
+ChiquitoStatus chiquito_prove(ChiquitoCircuit *circuit, const uint8_t *witness,
+                              size_t witness_len, const char *params_path, bool evm,
+                              ChiquitoBytes *proof_out, char **instance_out);
+
+/* Verifies a proof with its public inputs, as chiquito_prove returns them, and the KZG params of
+ * params_path, or those the circuit was proven with if null. */
+ChiquitoStatus chiquito_verify(ChiquitoCircuit *circuit, const uint8_t *proof, size_t proof_len,
+                               const char *instance_json, const char *params_path, bool evm,
+                               bool *out);
+
+/* Verifying key of a bn256 circuit, in the raw bytes format of halo2. */
+ChiquitoStatus chiquito_verifying_key(ChiquitoCircuit *circuit, const char *params_path,
+                                      ChiquitoBytes *out);
+
+/* Source of a Solidity contract that verifies the proofs of a bn256 circuit generated with evm. */
+ChiquitoStatus chiquito_solidity_verifier(ChiquitoCircuit *circuit, const char *params_path,
+                                          char **out);
+
+void chiquito_string_free(char *string);
+
+void chiquito_bytes_free(ChiquitoBytes bytes);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* CHIQUITO_H */
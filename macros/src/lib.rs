@@ -0,0 +1,123 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_macro_input, BinOp, Error, Expr, LitStr, Token, UnOp,
+};
+
+/// Input of `constraint!`: an expression and an optional annotation.
+struct ConstraintInput {
+    expr: Expr,
+    annotation: Option<LitStr>,
+}
+
+impl Parse for ConstraintInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let expr = input.parse()?;
+        let annotation = if input.parse::<Option<Token![,]>>()?.is_some() {
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
+        Ok(ConstraintInput { expr, annotation })
+    }
+}
+
+/// Builds a `Constraint` from infix syntax, with `+`, `-`, `*`, unary `-` and parentheses. An
+/// expression `lhs == rhs` constrains both sides to be equal, as `cb::eq`, and any other
+/// expression is constrained to be zero. The operands are anything that converts into a
+/// `Constraint`, like signals, expressions or integers.
+///
+/// The annotation is the source of the constraint, unless one is given after a comma:
+///
+/// ```ignore
+/// ctx.constr(constraint!(a + 2 * b == c.next()));
+/// ctx.constr(constraint!(bit * (1 - bit), "bit is boolean"));
+/// ```
+#[proc_macro]
+pub fn constraint(input: TokenStream) -> TokenStream {
+    let ConstraintInput { expr, annotation } = parse_macro_input!(input as ConstraintInput);
+
+    match expand(&expr, annotation) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(expr: &Expr, annotation: Option<LitStr>) -> syn::Result<TokenStream2> {
+    let (poly, typing) = match expr {
+        Expr::Binary(binary) if matches!(binary.op, BinOp::Eq(_)) => {
+            let (lhs, rhs) = (lower(&binary.left)?, lower(&binary.right)?);
+            (quote!((#lhs) - (#rhs)), quote!(AntiBooly))
+        }
+        _ => (lower(expr)?, quote!(Unknown)),
+    };
+    let annotation = match annotation {
+        Some(annotation) => annotation.value(),
+        None => source(expr),
+    };
+
+    Ok(quote! {
+        ::chiquito::frontend::dsl::cb::annotate(
+            ::std::string::String::from(#annotation),
+            #poly,
+            ::chiquito::frontend::dsl::cb::Typing::#typing,
+        )
+    })
+}
+
+/// Returns the tokens of the `PIR` of an arithmetic expression.
+fn lower(expr: &Expr) -> syn::Result<TokenStream2> {
+    match expr {
+        Expr::Binary(binary) => {
+            let (lhs, rhs) = (lower(&binary.left)?, lower(&binary.right)?);
+            match binary.op {
+                BinOp::Add(_) => Ok(quote!((#lhs + #rhs))),
+                BinOp::Sub(_) => Ok(quote!((#lhs - #rhs))),
+                BinOp::Mul(_) => Ok(quote!((#lhs * #rhs))),
+                BinOp::Eq(_) => Err(Error::new_spanned(
+                    binary,
+                    "`==` can only be used once, at the top of the constraint",
+                )),
+                _ => Err(Error::new_spanned(
+                    binary.op,
+                    "unsupported operator in constraint, expected `+`, `-`, `*` or `==`",
+                )),
+            }
+        }
+        Expr::Unary(unary) if matches!(unary.op, UnOp::Neg(_)) => {
+            let inner = lower(&unary.expr)?;
+            Ok(quote!((-#inner)))
+        }
+        Expr::Paren(paren) => lower(&paren.expr),
+        Expr::Group(group) => lower(&group.expr),
+        _ => Ok(quote!(::chiquito::frontend::dsl::cb::Constraint::from(#expr).expr)),
+    }
+}
+
+/// Returns the source of an expression, with the spacing of the operators normalized.
+fn source(expr: &Expr) -> String {
+    match expr {
+        Expr::Binary(binary) => format!(
+            "{} {} {}",
+            source(&binary.left),
+            binary.op.to_token_stream(),
+            source(&binary.right)
+        ),
+        Expr::Unary(unary) => format!("{}{}", unary.op.to_token_stream(), source(&unary.expr)),
+        Expr::Paren(paren) => format!("({})", source(&paren.expr)),
+        Expr::Group(group) => source(&group.expr),
+        _ => {
+            let mut source = expr.to_token_stream().to_string();
+            for (spaced, compact) in [(" . ", "."), (" (", "("), ("( ", "("), (" )", ")")] {
+                source = source.replace(spaced, compact);
+            }
+            source
+                .replace(" ::", "::")
+                .replace(":: ", "::")
+                .replace(" ,", ",")
+        }
+    }
+}
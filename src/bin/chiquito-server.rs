@@ -0,0 +1,53 @@
+//! HTTP server of the compiler and the prover, see `chiquito::frontend::server`.
+
+use std::{env, process, str::FromStr};
+
+use chiquito::frontend::server::{serve, ServerConfig};
+
+const USAGE: &str = "usage: chiquito-server [--addr HOST:PORT] [--params-dir DIR] [--threads N] \
+                     [--max-circuits N] [--max-k K] [--max-body-bytes N]";
+
+fn main() {
+    let config = match parse_args(env::args().skip(1)) {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    println!("chiquito-server listening on {}", config.addr);
+    if let Err(e) = serve(config) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<ServerConfig, String> {
+    let mut config = ServerConfig::default();
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--addr" => config.addr = value()?,
+            "--params-dir" => config.params_dir = Some(value()?.into()),
+            "--threads" => config.threads = parse_number(&arg, value()?)?,
+            "--max-circuits" => config.max_circuits = parse_number(&arg, value()?)?,
+            "--max-k" => config.max_k = parse_number(&arg, value()?)?,
+            "--max-body-bytes" => config.max_body_bytes = parse_number(&arg, value()?)?,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ => return Err(format!("unknown argument {}", arg)),
+        }
+    }
+
+    Ok(config)
+}
+
+fn parse_number<T: FromStr>(arg: &str, value: String) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} {} is not a number", arg, value))
+}
@@ -48,3 +48,16 @@ pub trait Field:
     /// exponent.
     fn pow<S: AsRef<[u64]>>(&self, exp: S) -> Self;
 }
+
+impl<T: ff::Field + From<u64>> Field for T {
+    const ZERO: Self = <Self as ff::Field>::ZERO;
+    const ONE: Self = <Self as ff::Field>::ONE;
+
+    fn mi(&self) -> Self {
+        self.invert().unwrap_or(Self::ZERO)
+    }
+
+    fn pow<S: AsRef<[u64]>>(&self, exp: S) -> Self {
+        ff::Field::pow(self, exp)
+    }
+}
@@ -11,6 +11,8 @@ use super::{
     StepTypeHandler,
 };
 
+pub use chiquito_macros::constraint;
+
 /// Represents a constraint with an associated annotation and expression.
 #[derive(Clone)]
 pub struct Constraint<F> {
@@ -67,6 +69,12 @@ impl<F: Field + From<u64> + Debug> From<i32> for Constraint<F> {
     }
 }
 
+impl<F: Field + From<u64> + Debug> From<i64> for Constraint<F> {
+    fn from(v: i64) -> Self {
+        v.expr().into()
+    }
+}
+
 macro_rules! impl_cb_like {
     ($type:ty) => {
         impl<F: From<u64> + Debug> From<$type> for Constraint<F> {
@@ -763,4 +771,31 @@ mod tests {
         assert!(matches!(constraint.expr, Expr::Const(c) if c == 1u64.field()));
         assert!(matches!(constraint.typing, Typing::Unknown));
     }
+
+    #[test]
+    fn test_constraint_macro() {
+        use crate::sbpir::{ForwardSignal, InternalSignal};
+        use std::collections::HashMap;
+
+        let a: Queriable<Fr> = Queriable::Internal(InternalSignal::new("a"));
+        let b: Queriable<Fr> = Queriable::Internal(InternalSignal::new("b"));
+        let c: Queriable<Fr> =
+            Queriable::Forward(ForwardSignal::new_with_phase(0, "c".to_string()), false);
+        let assignments =
+            HashMap::from([(a, Fr::from(3)), (b, Fr::from(4)), (c.next(), Fr::from(11))]);
+
+        let constraint = constraint!(a + 2 * b == c.next());
+        assert_eq!(constraint.annotation, "a + 2 * b == c.next()");
+        assert_eq!(constraint.typing, Typing::AntiBooly);
+        assert_eq!(constraint.expr.eval(&assignments), Some(Fr::ZERO));
+
+        let constraint = constraint!(-(a - b) * 5 - 5);
+        assert_eq!(constraint.annotation, "-(a - b) * 5 - 5");
+        assert_eq!(constraint.typing, Typing::Unknown);
+        assert_eq!(constraint.expr.eval(&assignments), Some(Fr::ZERO));
+
+        let constraint = constraint!(a * b == 13, "product");
+        assert_eq!(constraint.annotation, "product");
+        assert_eq!(constraint.expr.eval(&assignments), Some(-Fr::ONE));
+    }
 }
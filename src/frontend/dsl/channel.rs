@@ -0,0 +1,100 @@
+//! Typed channels between the sub-circuits of a super circuit. A sub-circuit publishes tuples of
+//! signals on a channel, and other sub-circuits receive tuples from it with lookups into them.
+//! Both sides give the values of a tuple as arrays of the arity of the channel, so an arity
+//! mismatch is a compile error, and the received values are annotated with the field names of the
+//! channel.
+
+use std::fmt::Debug;
+
+use crate::{
+    sbpir::Lookup,
+    util::{uuid, UUID},
+};
+
+use super::{cb::Constraint, lb::LookupBuilder, StepTypeSetupContext};
+
+/// Channel of tuples of `N` fields, see `SuperCircuitContext::channel`.
+#[derive(Clone, Debug)]
+pub struct Channel<const N: usize> {
+    uuid: UUID,
+    name: String,
+    fields: [String; N],
+}
+
+impl<const N: usize> Channel<N> {
+    pub(crate) fn new(name: &str, fields: [&str; N]) -> Self {
+        Self {
+            uuid: uuid(),
+            name: name.to_string(),
+            fields: fields.map(|field| field.to_string()),
+        }
+    }
+
+    /// UUID of the lookup table of the published tuples.
+    pub fn uuid(&self) -> UUID {
+        self.uuid
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn fields(&self) -> &[String; N] {
+        &self.fields
+    }
+
+    /// Receives a tuple from the channel: a lookup of `values` into the published tuples, to add
+    /// to a step type with `StepTypeSetupContext::add_lookup`. The channel must be published by a
+    /// sub-circuit defined before.
+    pub fn receive<F, C: Into<Constraint<F>>>(&self, values: [C; N]) -> ChannelReceiver<F> {
+        ChannelReceiver {
+            uuid: self.uuid,
+            name: self.name.clone(),
+            src: self
+                .fields
+                .iter()
+                .zip(values)
+                .map(|(field, value)| {
+                    let value = value.into();
+                    Constraint {
+                        annotation: format!("{}.{} <- {}", self.name, field, value.annotation),
+                        ..value
+                    }
+                })
+                .collect(),
+            enable: None,
+        }
+    }
+}
+
+/// Lookup of a tuple received from a channel, see `Channel::receive`.
+pub struct ChannelReceiver<F> {
+    uuid: UUID,
+    name: String,
+    src: Vec<Constraint<F>>,
+    enable: Option<Constraint<F>>,
+}
+
+impl<F> ChannelReceiver<F> {
+    /// Receives the tuple only when `enable` is not zero.
+    pub fn when<C: Into<Constraint<F>>>(mut self, enable: C) -> Self {
+        if self.enable.is_some() {
+            panic!("Cannot use when operator in channel receive more than once.")
+        }
+
+        self.enable = Some(enable.into());
+
+        self
+    }
+}
+
+impl<F: Clone + Debug> LookupBuilder<F> for ChannelReceiver<F> {
+    fn build(self, ctx: &StepTypeSetupContext<F>) -> Lookup<F> {
+        let table = ctx
+            .tables
+            .try_get(self.uuid)
+            .unwrap_or_else(|| panic!("channel {} is received before it is published", self.name));
+
+        table.build(self.src, self.enable)
+    }
+}
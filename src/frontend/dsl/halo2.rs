@@ -0,0 +1,446 @@
+//! Conversion of halo2 gate expressions to chiquito expressions, to port existing halo2 gates to
+//! step types, and import of halo2 lookup tables, like those of zkevm-circuits, to look up from
+//! step types.
+
+use std::{collections::HashMap, fmt};
+
+use halo2_proofs::plonk::{Advice, Any, Challenge, Column, Expression, Fixed, Selector};
+
+use crate::{
+    frontend::dsl::lb::{LookupTable, LookupTableStore},
+    poly::Expr,
+    sbpir::{query::Queriable, PIR},
+};
+
+/// Column, selector or challenge of a halo2 expression without a chiquito counterpart, or a query
+/// that the signal of its column cannot express.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ImportError {
+    UnmappedAdvice(usize),
+    UnmappedFixed(usize),
+    UnmappedSelector(usize),
+    UnmappedChallenge(usize),
+    /// Instance columns are exposed signals in chiquito, and cannot be queried in constraints.
+    Instance(usize),
+    /// The signal of a column cannot be queried with the rotation, like an internal signal with
+    /// any rotation but 0 or a forward signal with any rotation but 0 and 1.
+    Rotation(String, i32),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::UnmappedAdvice(index) => {
+                write!(f, "advice column {} is not mapped", index)
+            }
+            ImportError::UnmappedFixed(index) => write!(f, "fixed column {} is not mapped", index),
+            ImportError::UnmappedSelector(index) => write!(f, "selector {} is not mapped", index),
+            ImportError::UnmappedChallenge(index) => {
+                write!(f, "challenge {} is not mapped", index)
+            }
+            ImportError::Instance(index) => {
+                write!(f, "instance column {} cannot be queried", index)
+            }
+            ImportError::Rotation(signal, rotation) => {
+                write!(f, "{} cannot be queried with rotation {}", signal, rotation)
+            }
+        }
+    }
+}
+
+/// Maps the columns, selectors and challenges of halo2 gates to chiquito signals and expressions,
+/// and converts the gate expressions with them.
+///
+/// Columns map to the signal queried with rotation 0; other rotations rotate the signal, so the
+/// next row of a forward signal is its `next`. Selectors usually map to 1, as the step selector
+/// of the step type the gate is ported to enables its constraints.
+pub struct Halo2Importer<F> {
+    advice: HashMap<usize, Queriable<F>>,
+    fixed: HashMap<usize, Queriable<F>>,
+    selectors: HashMap<usize, PIR<F>>,
+    challenges: HashMap<usize, PIR<F>>,
+}
+
+impl<F> Default for Halo2Importer<F> {
+    fn default() -> Self {
+        Self {
+            advice: Default::default(),
+            fixed: Default::default(),
+            selectors: Default::default(),
+            challenges: Default::default(),
+        }
+    }
+}
+
+impl<F> Halo2Importer<F> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advice(mut self, column: Column<Advice>, signal: Queriable<F>) -> Self {
+        self.advice.insert(column.index(), signal);
+
+        self
+    }
+
+    pub fn fixed(mut self, column: Column<Fixed>, signal: Queriable<F>) -> Self {
+        self.fixed.insert(column.index(), signal);
+
+        self
+    }
+
+    pub fn selector<E: Into<PIR<F>>>(mut self, selector: Selector, expr: E) -> Self {
+        self.selectors.insert(selector.index(), expr.into());
+
+        self
+    }
+
+    /// Maps a halo2 challenge to a chiquito one, see `CircuitContext::challenge`.
+    pub fn challenge(mut self, challenge: Challenge, expr: PIR<F>) -> Self {
+        self.challenges.insert(challenge.index(), expr);
+
+        self
+    }
+}
+
+impl<F: Clone> Halo2Importer<F> {
+    /// Converts `expr` to a chiquito expression.
+    pub fn import(&self, expr: &Expression<F>) -> Result<PIR<F>, ImportError> {
+        Ok(match expr {
+            Expression::Constant(v) => Expr::Const(v.clone()),
+            Expression::Selector(selector) => self
+                .selectors
+                .get(&selector.index())
+                .cloned()
+                .ok_or(ImportError::UnmappedSelector(selector.index()))?,
+            Expression::Fixed(query) => {
+                let signal = self
+                    .fixed
+                    .get(&query.column_index())
+                    .ok_or(ImportError::UnmappedFixed(query.column_index()))?;
+                Expr::Query(rotate(signal, query.rotation().0)?)
+            }
+            Expression::Advice(query) => {
+                let signal = self
+                    .advice
+                    .get(&query.column_index())
+                    .ok_or(ImportError::UnmappedAdvice(query.column_index()))?;
+                Expr::Query(rotate(signal, query.rotation().0)?)
+            }
+            Expression::Instance(query) => return Err(ImportError::Instance(query.column_index())),
+            Expression::Challenge(challenge) => self
+                .challenges
+                .get(&challenge.index())
+                .cloned()
+                .ok_or(ImportError::UnmappedChallenge(challenge.index()))?,
+            Expression::Negated(e) => -self.import(e)?,
+            Expression::Sum(a, b) => self.import(a)? + self.import(b)?,
+            Expression::Product(a, b) => self.import(a)? * self.import(b)?,
+            Expression::Scaled(e, v) => self.import(e)? * Expr::Const(v.clone()),
+        })
+    }
+}
+
+/// Columns of a halo2 lookup table with their annotations, as the `LookupTable` trait of
+/// zkevm-circuits. The table configs of zkevm-circuits implement it by calling the methods of the
+/// same name of `LookupTable`, or are built as the `ByteTable`, `KeccakTable` and `TxTable` below
+/// from their columns.
+pub trait Halo2Table {
+    fn columns(&self) -> Vec<Column<Any>>;
+    fn annotations(&self) -> Vec<String>;
+}
+
+/// Byte table of zkevm-circuits: a fixed column with the values from 0 to 255.
+#[derive(Clone, Copy, Debug)]
+pub struct ByteTable {
+    pub value: Column<Fixed>,
+}
+
+impl Halo2Table for ByteTable {
+    fn columns(&self) -> Vec<Column<Any>> {
+        vec![self.value.into()]
+    }
+
+    fn annotations(&self) -> Vec<String> {
+        vec!["value".to_string()]
+    }
+}
+
+/// Keccak table of zkevm-circuits, with the RLC of the input bytes, their number and the RLC of
+/// the hash in the rows where `is_final` is 1. Lookups are of the columns in the order of the
+/// fields, with 1 for `q_enable` and `is_final`.
+#[derive(Clone, Copy, Debug)]
+pub struct KeccakTable {
+    pub q_enable: Column<Fixed>,
+    pub is_final: Column<Advice>,
+    pub input_rlc: Column<Advice>,
+    pub input_len: Column<Advice>,
+    pub output_rlc: Column<Advice>,
+}
+
+impl Halo2Table for KeccakTable {
+    fn columns(&self) -> Vec<Column<Any>> {
+        vec![
+            self.q_enable.into(),
+            self.is_final.into(),
+            self.input_rlc.into(),
+            self.input_len.into(),
+            self.output_rlc.into(),
+        ]
+    }
+
+    fn annotations(&self) -> Vec<String> {
+        [
+            "q_enable",
+            "is_final",
+            "input_rlc",
+            "input_len",
+            "output_rlc",
+        ]
+        .map(String::from)
+        .to_vec()
+    }
+}
+
+/// Transaction table of zkevm-circuits, with a row for each field `tag` of each transaction
+/// `tx_id`, and for each byte `index` of the call data. Lookups are of the columns in the order of
+/// the fields, with 1 for `q_enable`.
+#[derive(Clone, Copy, Debug)]
+pub struct TxTable {
+    pub q_enable: Column<Fixed>,
+    pub tx_id: Column<Advice>,
+    pub tag: Column<Fixed>,
+    pub index: Column<Advice>,
+    pub value: Column<Advice>,
+}
+
+impl Halo2Table for TxTable {
+    fn columns(&self) -> Vec<Column<Any>> {
+        vec![
+            self.q_enable.into(),
+            self.tx_id.into(),
+            self.tag.into(),
+            self.index.into(),
+            self.value.into(),
+        ]
+    }
+
+    fn annotations(&self) -> Vec<String> {
+        ["q_enable", "tx_id", "tag", "index", "value"]
+            .map(String::from)
+            .to_vec()
+    }
+}
+
+/// Halo2 table imported into a circuit by `CircuitContext::import_halo2_table`. Its columns are
+/// imported as the halo2 columns of the circuit, annotated `{table}.{column}`, and queried by the
+/// annotations of the table.
+#[derive(Clone, Debug)]
+pub struct ImportedHalo2Table<F> {
+    name: String,
+    columns: Vec<(String, Queriable<F>)>,
+    table: LookupTable,
+}
+
+impl<F: Clone> ImportedHalo2Table<F> {
+    pub(super) fn new(
+        name: &str,
+        columns: Vec<(String, Queriable<F>)>,
+        table: LookupTable,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            columns,
+            table,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the column of the table annotated `annotation`, queried with rotation 0.
+    pub fn column(&self, annotation: &str) -> Queriable<F> {
+        self.columns
+            .iter()
+            .find(|(column, _)| column == annotation)
+            .map(|(_, query)| query.clone())
+            .unwrap_or_else(|| panic!("table {} has no column {}", self.name, annotation))
+    }
+
+    /// Lookup table of all the columns of the table, in their order.
+    pub fn table(&self) -> LookupTable {
+        self.table
+    }
+
+    /// Returns a lookup table of the columns annotated `annotations`, in their order, to add to
+    /// the circuit with `CircuitContext::new_table`, for lookups of some of the columns.
+    pub fn store(&self, annotations: &[&str]) -> LookupTableStore<F> {
+        annotations
+            .iter()
+            .fold(LookupTableStore::default(), |store, annotation| {
+                store.add(self.column(annotation))
+            })
+    }
+}
+
+/// Returns `signal` queried `rotation` rows after it.
+fn rotate<F: Clone>(signal: &Queriable<F>, rotation: i32) -> Result<Queriable<F>, ImportError> {
+    if rotation == 0 {
+        return Ok(signal.clone());
+    }
+
+    match signal {
+        Queriable::Forward(s, false) if rotation == 1 => Ok(Queriable::Forward(*s, true)),
+        Queriable::Shared(s, rot) => Ok(Queriable::Shared(*s, rot + rotation)),
+        Queriable::Fixed(s, rot) => Ok(Queriable::Fixed(*s, rot + rotation)),
+        Queriable::Halo2AdviceQuery(s, rot) => Ok(Queriable::Halo2AdviceQuery(*s, rot + rotation)),
+        Queriable::Halo2FixedQuery(s, rot) => Ok(Queriable::Halo2FixedQuery(*s, rot + rotation)),
+        _ => Err(ImportError::Rotation(signal.annotation(), rotation)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{halo2curves::bn256::Fr, plonk::ConstraintSystem, poly::Rotation};
+
+    use super::*;
+    use crate::{
+        frontend::dsl::{cb::eq, circuit},
+        plonkish::{
+            compiler::{
+                cell_manager::SingleRowCellManager, compile, config,
+                step_selector::SimpleStepSelectorBuilder,
+            },
+            ir::ColumnType,
+        },
+        sbpir::{FixedSignal, ForwardSignal, InternalSignal},
+    };
+
+    #[test]
+    fn test_import() {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let a_column = meta.advice_column();
+        let f_column = meta.fixed_column();
+        let selector = meta.selector();
+        meta.create_gate("gate", |meta| {
+            let s = meta.query_selector(selector);
+            let a = meta.query_advice(a_column, Rotation::cur());
+            let a_next = meta.query_advice(a_column, Rotation::next());
+            let f = meta.query_fixed(f_column, Rotation::cur());
+            vec![s * (a_next - a * f)]
+        });
+        let gate = meta.gates()[0].polynomials()[0].clone();
+
+        let a = Queriable::Forward(ForwardSignal::new_with_phase(0, "a".to_string()), false);
+        let f = Queriable::Fixed(FixedSignal::new("f".to_string()), 0);
+        let importer = Halo2Importer::new()
+            .advice(a_column, a)
+            .fixed(f_column, f)
+            .selector(selector, 1u64);
+
+        assert_eq!(
+            format!("{:?}", importer.import(&gate).unwrap()),
+            format!("{:?}", PIR::from(1u64) * (a.next() - a * f))
+        );
+
+        let b = Queriable::Internal(InternalSignal::new("b"));
+        let importer = Halo2Importer::new()
+            .advice(a_column, b)
+            .fixed(f_column, f)
+            .selector(selector, 1u64);
+        assert_eq!(
+            importer.import(&gate).unwrap_err(),
+            ImportError::Rotation("b".to_string(), 1)
+        );
+        assert_eq!(
+            Halo2Importer::new().import(&gate).unwrap_err(),
+            ImportError::UnmappedSelector(selector.index())
+        );
+    }
+
+    #[test]
+    fn test_import_table() {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let keccak_table = KeccakTable {
+            q_enable: meta.fixed_column(),
+            is_final: meta.advice_column(),
+            input_rlc: meta.advice_column(),
+            input_len: meta.advice_column(),
+            output_rlc: meta.advice_column(),
+        };
+
+        let ast = circuit::<Fr, (), _>("hash", |ctx| {
+            let keccak = ctx.import_halo2_table("keccak", &keccak_table);
+            assert_eq!(keccak.column("input_len").annotation(), "keccak.input_len");
+            let lookup_table = keccak.table();
+            let output_table = ctx.new_table(keccak.store(&["output_rlc", "is_final"]));
+
+            ctx.step_type_def("hash", |ctx| {
+                let input_rlc = ctx.internal("input_rlc");
+                let input_len = ctx.internal("input_len");
+                let output_rlc = ctx.internal("output_rlc");
+                ctx.setup(move |ctx| {
+                    ctx.constr(eq(input_len, 32));
+                    ctx.add_lookup(
+                        lookup_table
+                            .apply(1)
+                            .apply(1)
+                            .apply(input_rlc)
+                            .apply(input_len)
+                            .apply(output_rlc),
+                    );
+                    ctx.add_lookup(output_table.apply(output_rlc).apply(1));
+                });
+                ctx.wg(|_, _: ()| {})
+            });
+            ctx.pragma_num_steps(1);
+        });
+
+        assert_eq!(ast.halo2_advice.len(), 4);
+        assert_eq!(ast.halo2_fixed.len(), 1);
+        let step_type = ast.step_types.values().next().unwrap();
+        let lookups: Vec<_> = step_type.lookups.iter().map(|l| l.exprs.len()).collect();
+        assert_eq!(lookups, [5, 2]);
+
+        let (circuit, _) = compile(
+            config(SingleRowCellManager {}, SimpleStepSelectorBuilder {}),
+            &ast,
+        );
+        let imported: Vec<_> = circuit
+            .columns
+            .iter()
+            .filter(|column| {
+                matches!(
+                    column.ctype,
+                    ColumnType::Halo2Advice | ColumnType::Halo2Fixed
+                )
+            })
+            .map(|column| column.annotation.as_str())
+            .collect();
+        assert_eq!(
+            imported,
+            [
+                "keccak.is_final",
+                "keccak.input_rlc",
+                "keccak.input_len",
+                "keccak.output_rlc",
+                "keccak.q_enable"
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "table keccak has no column output")]
+    fn test_import_table_unknown_column() {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let byte_table = ByteTable {
+            value: meta.fixed_column(),
+        };
+
+        circuit::<Fr, (), _>("bytes", |ctx| {
+            let bytes = ctx.import_halo2_table("keccak", &byte_table);
+            bytes.column("output");
+        });
+    }
+}
@@ -77,6 +77,13 @@ impl<F> Default for LookupTableStore<F> {
 }
 
 impl<F> LookupTableStore<F> {
+    pub(super) fn with_uuid(id: UUID) -> Self {
+        Self {
+            id,
+            dest: Default::default(),
+        }
+    }
+
     #[allow(clippy::should_implement_trait)]
     pub fn add<E: Into<PIR<F>>>(mut self, expr: E) -> Self {
         self.dest.push(expr.into());
@@ -90,7 +97,7 @@ impl<F> LookupTableStore<F> {
 }
 
 impl<F: Debug + Clone> LookupTableStore<F> {
-    fn build(self, src: Vec<Constraint<F>>, enable: Option<Constraint<F>>) -> Lookup<F> {
+    pub(super) fn build(self, src: Vec<Constraint<F>>, enable: Option<Constraint<F>>) -> Lookup<F> {
         assert_eq!(
             self.dest.len(),
             src.len(),
@@ -130,12 +137,20 @@ impl<F> LookupTableRegistry<F> {
     pub fn add(&self, table: LookupTableStore<F>) {
         self.0.lock().as_mut().unwrap().insert(table.uuid(), table);
     }
+
+    pub fn contains(&self, uuid: UUID) -> bool {
+        self.0.lock().unwrap().contains_key(&uuid)
+    }
 }
 
 impl<F: Clone> LookupTableRegistry<F> {
     pub fn get(&self, uuid: UUID) -> LookupTableStore<F> {
         (*self.0.lock().unwrap().get(&uuid).unwrap()).clone()
     }
+
+    pub fn try_get(&self, uuid: UUID) -> Option<LookupTableStore<F>> {
+        self.0.lock().unwrap().get(&uuid).cloned()
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
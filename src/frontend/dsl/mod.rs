@@ -1,17 +1,20 @@
 use crate::{
     field::Field,
-    sbpir::{query::Queriable, ExposeOffset, StepType, StepTypeUUID, PIR, SBPIR},
+    poly::Expr,
+    sbpir::{query::Queriable, ExposeOffset, GadgetCall, StepType, StepTypeUUID, PIR, SBPIR},
     util::{uuid, UUID},
     wit_gen::{FixedGenContext, StepInstance, TraceContext},
 };
 
-use halo2_proofs::plonk::{Advice, Column as Halo2Column, Fixed};
+#[cfg(feature = "halo2")]
+use halo2_proofs::plonk::{Advice, Any, Column as Halo2Column, Fixed};
 
 use core::{fmt::Debug, hash::Hash};
 use std::marker::PhantomData;
 
 use self::{
     cb::{eq, Constraint, Typing},
+    channel::Channel,
     lb::{LookupBuilder, LookupTable, LookupTableRegistry, LookupTableStore},
 };
 
@@ -59,19 +62,43 @@ impl<F, TraceArgs> CircuitContext<F, TraceArgs> {
         Queriable::Fixed(self.circuit.add_fixed(name), 0)
     }
 
+    /// Adds a verifier challenge to the circuit with a name string, drawn after the signals of
+    /// `phase` are committed, and returns it as an expression. It can constrain signals of later
+    /// phases, as in random linear combinations, but its value is not known in witness generation.
+    pub fn challenge(&mut self, name: &str, phase: usize) -> PIR<F> {
+        let id = uuid();
+        self.circuit.annotations.insert(id, name.to_string());
+
+        Expr::Challenge(id, phase)
+    }
+
     /// Exposes the first step instance value of a forward signal as public.
     pub fn expose(&mut self, queriable: Queriable<F>, offset: ExposeOffset) {
         self.circuit.expose(queriable, offset);
     }
 
+    /// Replaces the name of a signal, given its UUID. Internal signals can be renamed once their
+    /// step type is defined.
+    pub fn rename(&mut self, uuid: UUID, name: &str) {
+        self.circuit.rename(uuid, name);
+    }
+
+    /// Adds a name to a signal or step type, given its UUID, which is shown in the annotations of
+    /// the compiled circuit.
+    pub fn alias(&mut self, uuid: UUID, alias: &str) {
+        self.circuit.add_alias(uuid, alias);
+    }
+
     /// Imports a halo2 advice column with a name string into the circuit and returns a
     /// `Queriable` instance representing the imported column.
+    #[cfg(feature = "halo2")]
     pub fn import_halo2_advice(&mut self, name: &str, column: Halo2Column<Advice>) -> Queriable<F> {
         Queriable::Halo2AdviceQuery(self.circuit.add_halo2_advice(name, column), 0)
     }
 
     /// Imports a halo2 fixed column with a name string into the circuit and returns a
     /// `Queriable` instance representing the imported column.
+    #[cfg(feature = "halo2")]
     pub fn import_halo2_fixed(&mut self, name: &str, column: Halo2Column<Fixed>) -> Queriable<F> {
         Queriable::Halo2FixedQuery(self.circuit.add_halo2_fixed(name, column), 0)
     }
@@ -142,6 +169,20 @@ impl<F, TraceArgs> CircuitContext<F, TraceArgs> {
         LookupTable { uuid }
     }
 
+    /// Publishes a tuple of `values` on `channel` in every row of the circuit, for other
+    /// sub-circuits to receive with `Channel::receive`. A channel is published by one
+    /// sub-circuit.
+    pub fn publish<E: Into<PIR<F>>, const N: usize>(&self, channel: &Channel<N>, values: [E; N]) {
+        if self.tables.contains(channel.uuid()) {
+            panic!("channel {} is already published", channel.name());
+        }
+
+        self.tables.add(values.into_iter().fold(
+            LookupTableStore::with_uuid(channel.uuid()),
+            |table, value| table.add(value),
+        ));
+    }
+
     /// Enforce the type of the first step by adding a constraint to the circuit. Takes a
     /// `StepTypeHandler` parameter that represents the step type.
     pub fn pragma_first_step<STH: Into<StepTypeHandler>>(&mut self, step_type: STH) {
@@ -154,6 +195,16 @@ impl<F, TraceArgs> CircuitContext<F, TraceArgs> {
         self.circuit.last_step = Some(step_type.into().uuid());
     }
 
+    /// Sets the step type that fills the remaining rows when the trace adds fewer than `num_steps`
+    /// step instances, so `num_steps` becomes the maximum. The padding steps are added
+    /// automatically, with the forward and shared signals of the previous step, and the exposed
+    /// signals with `ExposeOffset::Last` keep the values of the last step instance of the trace.
+    /// The transition constraints of that instance are disabled, and it must be of the last step
+    /// type when there is one.
+    pub fn pragma_padding_step<STH: Into<StepTypeHandler>>(&mut self, step_type: STH) {
+        self.circuit.padding_step = Some(step_type.into().uuid());
+    }
+
     /// Enforce the number of step instances by adding a constraint to the circuit. Takes a `usize`
     /// parameter that represents the total number of steps.
     pub fn pragma_num_steps(&mut self, num_steps: usize) {
@@ -184,6 +235,54 @@ impl<F: Field + Hash, TraceArgs> CircuitContext<F, TraceArgs> {
 
         self.circuit.set_fixed_assignments(assignments);
     }
+
+    /// Imports the columns of a halo2 lookup table, like a table of zkevm-circuits, into the
+    /// circuit with the name of the table, and returns them with a lookup table of all of them.
+    /// The columns are annotated `{name}.{annotation}`, so that their cells are named after the
+    /// table in the failures of halo2.
+    #[cfg(feature = "halo2")]
+    pub fn import_halo2_table<T: halo2::Halo2Table>(
+        &mut self,
+        name: &str,
+        table: &T,
+    ) -> halo2::ImportedHalo2Table<F> {
+        let columns = table.columns();
+        let annotations = table.annotations();
+        assert_eq!(
+            columns.len(),
+            annotations.len(),
+            "table {} has {} columns and {} annotations",
+            name,
+            columns.len(),
+            annotations.len()
+        );
+
+        let columns: Vec<_> = columns
+            .into_iter()
+            .zip(annotations)
+            .map(|(column, annotation)| {
+                let column_name = format!("{}.{}", name, annotation);
+                let query = match column.column_type() {
+                    Any::Advice(_) => self.import_halo2_advice(
+                        &column_name,
+                        column.try_into().expect("advice column"),
+                    ),
+                    Any::Fixed => self
+                        .import_halo2_fixed(&column_name, column.try_into().expect("fixed column")),
+                    Any::Instance => panic!("cannot look up instance column {}", column_name),
+                };
+                (annotation, query)
+            })
+            .collect();
+        let store = columns
+            .iter()
+            .fold(LookupTableStore::default(), |store, (_, query)| {
+                store.add(*query)
+            });
+        let lookup_table = self.new_table(store);
+
+        halo2::ImportedHalo2Table::new(name, columns, lookup_table)
+    }
 }
 
 pub enum StepTypeDefInput {
@@ -234,6 +333,7 @@ impl<F> StepTypeContext<F> {
 
     /// DEPRECATED
     // #[deprecated(note = "use step types setup for constraints instead")]
+    #[track_caller]
     pub fn constr<C: Into<Constraint<F>>>(&mut self, constraint: C) {
         println!("DEPRECATED constr: use setup for constraints in step types");
 
@@ -245,6 +345,7 @@ impl<F> StepTypeContext<F> {
 
     /// DEPRECATED
     #[deprecated(note = "use step types setup for constraints instead")]
+    #[track_caller]
     pub fn transition<C: Into<Constraint<F>>>(&mut self, constraint: C) {
         println!("DEPRECATED transition: use setup for constraints in step types");
 
@@ -262,6 +363,7 @@ impl<F> StepTypeContext<F> {
         let mut ctx = StepTypeSetupContext {
             step_type: &mut self.step_type,
             tables: self.tables.clone(),
+            feature: None,
         };
 
         def(&mut ctx);
@@ -285,32 +387,108 @@ impl<F> StepTypeContext<F> {
 pub struct StepTypeSetupContext<'a, F> {
     step_type: &'a mut StepType<F>,
     tables: LookupTableRegistry<F>,
+    feature: Option<String>,
 }
 
 impl<'a, F> StepTypeSetupContext<'a, F> {
     /// Adds a constraint to the step type. Involves internal signal(s) and forward signals without
     /// SuperRotation only. Chiquito provides syntax sugar for defining complex constraints.
     /// Refer to the `cb` (constraint builder) module for more information.
+    #[track_caller]
     pub fn constr<C: Into<Constraint<F>>>(&mut self, constraint: C) {
         let constraint = constraint.into();
         Self::enforce_constraint_typing(&constraint);
 
         self.step_type
             .add_constr(constraint.annotation, constraint.expr);
+
+        if let Some(constraint) = self.step_type.constraints.last_mut() {
+            constraint.feature.clone_from(&self.feature);
+        }
     }
 
     /// Adds a transition constraint to the step type. It’s the same as a regular constraint except
     /// that it can involve forward signal(s) with SuperRotation as well. Chiquito provides syntax
     /// sugar for defining complex constraints. Refer to the `cb` (constraint builder) module
     /// for more information.
+    #[track_caller]
     pub fn transition<C: Into<Constraint<F>>>(&mut self, constraint: C) {
         let constraint = constraint.into();
         Self::enforce_constraint_typing(&constraint);
 
         self.step_type
             .add_transition(constraint.annotation, constraint.expr);
+
+        if let Some(constraint) = self.step_type.transition_constraints.last_mut() {
+            constraint.feature.clone_from(&self.feature);
+        }
+    }
+
+    /// Adds the constraints and lookups of `def` to the feature `name`, so they are only compiled
+    /// when the feature is enabled in the compiler config, as for debugging checks that are left
+    /// out of production circuits. Features cannot be nested.
+    pub fn feature<D: FnOnce(&mut Self)>(&mut self, name: &str, def: D) {
+        if let Some(feature) = &self.feature {
+            panic!("feature {} cannot be nested in feature {}", name, feature);
+        }
+
+        self.feature = Some(name.to_string());
+        def(self);
+        self.feature = None;
     }
 
+    /// Delegates the constraints between `inputs` and `outputs` to the halo2 gadget registered
+    /// with the name `gadget` in the halo2 backend, like a chip of halo2-lib, see `Halo2Gadget`.
+    /// The gadget is called with the values of the input signals in every step of the step type,
+    /// and the output signals are constrained to equal its outputs, so they must be assigned the
+    /// same values in witness generation. Only the halo2 backend supports gadget calls.
+    ///
+    /// The halo2 backend reserves a call of the gadget for every step of the circuit, see
+    /// `gadget_with_max_calls` to reserve fewer.
+    pub fn gadget<I, O>(&mut self, gadget: &str, inputs: I, outputs: O)
+    where
+        I: IntoIterator<Item = Queriable<F>>,
+        O: IntoIterator<Item = Queriable<F>>,
+    {
+        self.add_gadget_call(gadget, inputs, outputs, None);
+    }
+
+    /// Like `gadget`, with the halo2 backend reserving `max_calls` calls of the gadget, so a
+    /// witness cannot have more than `max_calls` steps of the step type.
+    pub fn gadget_with_max_calls<I, O>(
+        &mut self,
+        gadget: &str,
+        inputs: I,
+        outputs: O,
+        max_calls: usize,
+    ) where
+        I: IntoIterator<Item = Queriable<F>>,
+        O: IntoIterator<Item = Queriable<F>>,
+    {
+        self.add_gadget_call(gadget, inputs, outputs, Some(max_calls));
+    }
+
+    fn add_gadget_call<I, O>(
+        &mut self,
+        gadget: &str,
+        inputs: I,
+        outputs: O,
+        max_calls: Option<usize>,
+    ) where
+        I: IntoIterator<Item = Queriable<F>>,
+        O: IntoIterator<Item = Queriable<F>>,
+    {
+        self.step_type.gadget_calls.push(GadgetCall {
+            gadget: gadget.to_string(),
+            annotation: format!("gadget {}", gadget),
+            inputs: inputs.into_iter().collect(),
+            outputs: outputs.into_iter().collect(),
+            max_calls,
+            feature: self.feature.clone(),
+        });
+    }
+
+    #[track_caller]
     fn enforce_constraint_typing(constraint: &Constraint<F>) {
         if constraint.typing != Typing::AntiBooly {
             panic!(
@@ -333,10 +511,59 @@ impl<'a, F: Eq + PartialEq + Hash + Debug + Clone> StepTypeSetupContext<'a, F> {
     }
 }
 
+impl<'a, F: Field + Hash> StepTypeSetupContext<'a, F> {
+    /// Returns a new internal signal with the multiplicative inverse of `expr`, assigned during
+    /// witness generation, and constrains `expr * inv == 1`, so `expr` cannot be zero.
+    #[track_caller]
+    pub fn inv<E: Into<PIR<F>>>(&mut self, expr: E) -> Queriable<F> {
+        let expr = expr.into();
+        let inv = self.inv_signal(&expr);
+
+        self.constr(eq(expr * inv, 1));
+
+        inv
+    }
+
+    /// Returns a new internal signal with the multiplicative inverse of `expr`, or zero if `expr`
+    /// is zero, assigned during witness generation. It is constrained with `expr * (1 - expr *
+    /// inv) == 0` and `inv * (1 - expr * inv) == 0`, so `1 - expr * inv` is one when `expr` is
+    /// zero and zero otherwise.
+    #[track_caller]
+    pub fn inv_or_zero<E: Into<PIR<F>>>(&mut self, expr: E) -> Queriable<F> {
+        let expr = expr.into();
+        let inv = self.inv_signal(&expr);
+        let is_zero = Expr::Const(F::ONE) - expr.clone() * inv;
+
+        self.constr(eq(expr * is_zero.clone(), 0));
+        self.constr(eq(is_zero * inv, 0));
+
+        inv
+    }
+
+    /// Returns `num / den`, as `num` times the inverse of `den` from `inv`, so `den` cannot be
+    /// zero.
+    #[track_caller]
+    pub fn div<N: Into<PIR<F>>, D: Into<PIR<F>>>(&mut self, num: N, den: D) -> PIR<F> {
+        let inv = self.inv(den);
+
+        num.into() * inv
+    }
+
+    fn inv_signal(&mut self, expr: &PIR<F>) -> Queriable<F> {
+        let inv = Queriable::Internal(self.step_type.add_signal(format!("inv({:?})", expr)));
+        self.auto(inv, Expr::MI(Box::new(expr.clone())));
+
+        inv
+    }
+}
+
 impl<'a, F: Debug + Clone> StepTypeSetupContext<'a, F> {
     /// Adds a lookup to the step type.
     pub fn add_lookup<LB: LookupBuilder<F>>(&mut self, lookup_builder: LB) {
-        self.step_type.lookups.push(lookup_builder.build(self));
+        let mut lookup = lookup_builder.build(self);
+        lookup.feature.clone_from(&self.feature);
+
+        self.step_type.lookups.push(lookup);
     }
 }
 
@@ -429,6 +656,9 @@ where
 }
 
 pub mod cb;
+pub mod channel;
+#[cfg(feature = "halo2")]
+pub mod halo2;
 pub mod lb;
 pub mod sc;
 
@@ -667,6 +897,151 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_constraint_location() {
+        let mut context = setup_circuit_context::<i32, i32>();
+        let a: Queriable<i32> = context.forward("a");
+
+        let line = line!() + 3;
+        let step = context.step_type_def("step", |context| {
+            context.setup(move |context| {
+                context.constr(cb::eq(a, a));
+                context.transition(cb::eq(a, a.next()));
+            });
+            context.wg(|_, _: u32| {})
+        });
+
+        let step_type = &context.circuit.step_types[&step.uuid()];
+        let location = step_type.constraints[0].location.unwrap();
+        assert_eq!((location.file, location.line), (file!(), line));
+        let location = step_type.transition_constraints[0].location.unwrap();
+        assert_eq!((location.file, location.line), (file!(), line + 1));
+    }
+
+    #[test]
+    fn test_feature() {
+        let mut context = setup_circuit_context::<i32, i32>();
+        let a: Queriable<i32> = context.forward("a");
+
+        let step = context.step_type_def("step", |context| {
+            context.setup(move |context| {
+                context.constr(cb::eq(a, a.next()));
+                context.feature("debug", |context| {
+                    context.constr(cb::eq(a, a));
+                    context.transition(cb::eq(a, a.next()));
+                });
+                context.transition(cb::eq(a.next(), a));
+            });
+            context.wg(|_, _: u32| {})
+        });
+
+        let step_type = &context.circuit.step_types[&step.uuid()];
+        let debug = Some("debug".to_string());
+        let features: Vec<_> = step_type.constraints.iter().map(|c| &c.feature).collect();
+        assert_eq!(features, vec![&None, &debug]);
+        let features: Vec<_> = step_type
+            .transition_constraints
+            .iter()
+            .map(|c| &c.feature)
+            .collect();
+        assert_eq!(features, vec![&debug, &None]);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot be nested")]
+    fn test_nested_feature() {
+        let mut context = setup_circuit_context::<i32, i32>();
+
+        context.step_type_def("step", |context| {
+            context.setup(|context| {
+                context.feature("debug", |context| context.feature("trace", |_| {}));
+            });
+            context.wg(|_, _: u32| {})
+        });
+    }
+
+    #[test]
+    fn test_rename_and_alias() {
+        let mut context = setup_circuit_context::<i32, i32>();
+        let a: Queriable<i32> = context.forward("a");
+
+        let mut b = None;
+        let step = context.step_type_def("step", |context| {
+            b = Some(context.internal("b"));
+            context.wg(|_, _: u32| {})
+        });
+        let b = b.unwrap();
+
+        context.rename(a.uuid(), "sum");
+        context.rename(b.uuid(), "carry");
+        context.alias(b.uuid(), "c");
+        context.alias(step.uuid(), "add");
+        context.alias(step.uuid(), "adder");
+
+        assert_eq!(context.circuit.annotation(a.uuid()).unwrap(), "sum");
+        assert_eq!(context.circuit.annotation(b.uuid()).unwrap(), "carry");
+
+        let annotations = context.circuit.aliased_annotations();
+        assert_eq!(annotations[&a.uuid()], "sum");
+        assert_eq!(annotations[&b.uuid()], "carry (c)");
+        assert_eq!(annotations[&step.uuid()], "step (add, adder)");
+    }
+
+    #[test]
+    #[should_panic(expected = "step types cannot be renamed")]
+    fn test_rename_step_type() {
+        let mut context = setup_circuit_context::<i32, i32>();
+        let step = context.step_type_def("step", |context| context.wg(|_, _: u32| {}));
+
+        context.rename(step.uuid(), "other");
+    }
+
+    #[test]
+    fn test_inv() {
+        use halo2_proofs::halo2curves::bn256::Fr;
+
+        use crate::wit_gen::AutoTraceGenerator;
+
+        let circuit: SBPIR<Fr, ()> = circuit("inv", |ctx| {
+            let a = ctx.forward("a");
+            ctx.step_type_def("step", |ctx| {
+                ctx.setup(move |ctx| {
+                    ctx.inv(a);
+                    ctx.inv_or_zero(a * 2);
+                });
+                ctx.wg(|_, _: ()| {})
+            });
+        });
+        let step_type = circuit.step_types.values().next().unwrap();
+        let (inv, inv_or_zero) = (
+            Queriable::Internal(step_type.signals[0]),
+            Queriable::Internal(step_type.signals[1]),
+        );
+        assert_eq!(step_type.constraints.len(), 3);
+
+        let a = Queriable::Forward(circuit.forward_signals[0], false);
+        let auto = AutoTraceGenerator::from(&circuit);
+        for value in [Fr::from(3), Fr::ZERO] {
+            let mut step_instance = StepInstance::new(step_type.uuid());
+            step_instance.assign(a, value);
+            auto.generate_step(&mut step_instance);
+
+            assert_eq!(step_instance.assignments[&inv], value.mi());
+            assert_eq!(
+                step_instance.assignments[&inv_or_zero],
+                (value * Fr::from(2)).mi()
+            );
+            let satisfied = step_type.constraints.iter().map(|constraint| {
+                constraint.expr.eval(&step_instance.assignments) == Some(Fr::ZERO)
+            });
+            // only the inverse of zero is not satisfied
+            assert_eq!(
+                satisfied.collect::<Vec<_>>(),
+                vec![value != Fr::ZERO, true, true]
+            );
+        }
+    }
+
     #[test]
     fn test_trace() {
         let mut context = setup_circuit_context::<i32, i32>();
@@ -5,24 +5,34 @@ use crate::field::Field;
 use crate::{
     plonkish::{
         compiler::{
-            cell_manager::CellManager, compile_phase1, compile_phase2,
-            step_selector::StepSelectorBuilder, unit::CompilationUnit, CompilerConfig,
+            cell_manager::{CellManager, SingleRowCellManager},
+            compile_phase1, compile_phase2, config, signal_cell,
+            step_selector::{SimpleStepSelectorBuilder, StepSelectorBuilder},
+            unit::CompilationUnit,
+            CompilerConfig,
         },
         ir::{
             assignments::AssignmentGenerator,
             sc::{MappingContext, SuperCircuit},
+            Column,
         },
     },
-    sbpir::SBPIR,
+    sbpir::{query::Queriable, ExposeOffset, SBPIR},
 };
 
-use super::{lb::LookupTableRegistry, CircuitContext};
+use super::{
+    cb::table,
+    channel::Channel,
+    lb::{LookupTable, LookupTableRegistry, LookupTableStore},
+    CircuitContext,
+};
 
 #[derive(Debug)]
 pub struct SuperCircuitContext<F, MappingArgs> {
     super_circuit: SuperCircuit<F, MappingArgs>,
     sub_circuit_phase1: Vec<CompilationUnit<F>>,
     pub tables: LookupTableRegistry<F>,
+    copies: Vec<((Queriable<F>, ExposeOffset), (Queriable<F>, ExposeOffset))>,
 }
 
 impl<F, MappingArgs> Default for SuperCircuitContext<F, MappingArgs> {
@@ -31,6 +41,7 @@ impl<F, MappingArgs> Default for SuperCircuitContext<F, MappingArgs> {
             super_circuit: Default::default(),
             sub_circuit_phase1: Default::default(),
             tables: LookupTableRegistry::default(),
+            copies: Default::default(),
         }
     }
 }
@@ -44,7 +55,7 @@ impl<F: Clone, MappingArgs> SuperCircuitContext<F, MappingArgs> {
 impl<F: Field + Hash, MappingArgs> SuperCircuitContext<F, MappingArgs> {
     pub fn sub_circuit<CM: CellManager, SSB: StepSelectorBuilder, TraceArgs, Imports, Exports, D>(
         &mut self,
-        config: CompilerConfig<CM, SSB>,
+        config: CompilerConfig<F, CM, SSB>,
         sub_circuit_def: D,
         imports: Imports,
     ) -> (AssignmentGenerator<F, TraceArgs>, Exports)
@@ -72,7 +83,7 @@ impl<F: Field + Hash, MappingArgs> SuperCircuitContext<F, MappingArgs> {
 
     pub fn sub_circuit_with_ast<CM: CellManager, SSB: StepSelectorBuilder, TraceArgs>(
         &mut self,
-        config: CompilerConfig<CM, SSB>,
+        config: CompilerConfig<F, CM, SSB>,
         sub_circuit: SBPIR<F, TraceArgs>, // directly input ast
     ) -> AssignmentGenerator<F, TraceArgs> {
         let (unit, assignment) = compile_phase1(config, &sub_circuit);
@@ -83,11 +94,87 @@ impl<F: Field + Hash, MappingArgs> SuperCircuitContext<F, MappingArgs> {
         assignment
     }
 
+    /// Declares a lookup table of fixed columns, named `columns`, with `rows` as its rows. The
+    /// table is compiled once, to the fixed columns of a sub-circuit of its own, and any
+    /// sub-circuit can look up into it with the returned `LookupTable`.
+    pub fn fixed_table(&mut self, name: &str, columns: &[&str], rows: Vec<Vec<F>>) -> LookupTable {
+        assert!(!rows.is_empty(), "lookup table {} has no rows", name);
+        assert!(
+            rows.iter().all(|row| row.len() == columns.len()),
+            "rows of lookup table {} must have {} values",
+            name,
+            columns.len()
+        );
+
+        let (_, table) = self.sub_circuit(
+            config(SingleRowCellManager {}, SimpleStepSelectorBuilder {}),
+            |ctx: &mut CircuitContext<F, ()>, _| {
+                ctx.pragma_num_steps(rows.len());
+
+                let signals: Vec<_> = columns
+                    .iter()
+                    .map(|column| ctx.fixed(&format!("{} {}", name, column)))
+                    .collect();
+
+                let values = rows.clone();
+                let assigned = signals.clone();
+                ctx.fixed_gen(move |ctx| {
+                    for (offset, row) in values.iter().enumerate() {
+                        for (signal, value) in assigned.iter().zip(row.iter()) {
+                            ctx.assign(offset, *signal, *value);
+                        }
+                    }
+                });
+
+                ctx.new_table(
+                    signals
+                        .into_iter()
+                        .fold(table(), |table, signal| table.add(signal)),
+                )
+            },
+            (),
+        );
+
+        table
+    }
+
+    /// Declares a lookup table over signals of the sub-circuits, like the signals exported by one
+    /// of them, that any sub-circuit can look up into with the returned `LookupTable`.
+    pub fn new_table(&self, table: LookupTableStore<F>) -> LookupTable {
+        let uuid = table.uuid();
+        self.tables.add(table);
+
+        LookupTable { uuid }
+    }
+
+    /// Creates a channel of tuples with `fields`, that a sub-circuit publishes with
+    /// `CircuitContext::publish` and the sub-circuits defined after it receive with
+    /// `Channel::receive`.
+    pub fn channel<const N: usize>(&self, name: &str, fields: [&str; N]) -> Channel<N> {
+        Channel::new(name, fields)
+    }
+
+    /// Constrains the value of a forward or shared signal of a sub-circuit at an offset, as in
+    /// `CircuitContext::expose`, to be equal to the value of a signal of another sub-circuit, with
+    /// a copy constraint of the permutation argument instead of a lookup. The witnesses of both
+    /// sub-circuits must assign the same value.
+    pub fn copy(&mut self, from: (Queriable<F>, ExposeOffset), to: (Queriable<F>, ExposeOffset)) {
+        self.copies.push((from, to));
+    }
+
     pub fn mapping<D: Fn(&mut MappingContext<F>, MappingArgs) + 'static>(&mut self, def: D) {
         self.super_circuit.set_mapping(def);
     }
 
     pub fn compile(mut self) -> SuperCircuit<F, MappingArgs> {
+        for ((from, from_offset), (to, to_offset)) in std::mem::take(&mut self.copies) {
+            let (from_unit, from_cell) = self.find_cell(&from, &from_offset);
+            let (_, to_cell) = self.find_cell(&to, &to_offset);
+            self.sub_circuit_phase1[from_unit]
+                .copies
+                .push((from_cell, to_cell));
+        }
+
         let other = Rc::new(self.sub_circuit_phase1.clone());
         // let columns = other
         // .iter()
@@ -108,6 +195,23 @@ impl<F: Field + Hash, MappingArgs> SuperCircuitContext<F, MappingArgs> {
     }
 }
 
+impl<F, MappingArgs> SuperCircuitContext<F, MappingArgs> {
+    /// Returns the index of the sub-circuit that places `signal`, and the cell of its value at
+    /// `offset`.
+    fn find_cell(&self, signal: &Queriable<F>, offset: &ExposeOffset) -> (usize, (Column, i32)) {
+        self.sub_circuit_phase1
+            .iter()
+            .enumerate()
+            .find_map(|(index, unit)| Some((index, signal_cell(unit, signal, offset)?)))
+            .unwrap_or_else(|| {
+                panic!(
+                    "copied signal {} not in any sub-circuit",
+                    signal.annotation()
+                )
+            })
+    }
+}
+
 pub fn super_circuit<F: Field + Hash, MappingArgs, D>(
     _name: &str,
     def: D,
@@ -267,6 +371,147 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_super_circuit_fixed_table() {
+        let mut ctx = SuperCircuitContext::<Fr, ()>::default();
+        let bits = ctx.fixed_table("bits", &["bit"], vec![vec![Fr::from(0)], vec![Fr::from(1)]]);
+
+        fn bit_circuit(ctx: &mut CircuitContext<Fr, ()>, bits: LookupTable) {
+            let x = ctx.forward("x");
+            ctx.step_type_def("bit", |ctx| {
+                ctx.setup(move |ctx| ctx.add_lookup(bits.apply(x)));
+
+                ctx.wg(move |ctx, value: u32| ctx.assign(x, value.field()))
+            });
+            ctx.pragma_num_steps(1);
+        }
+
+        let config = config(SingleRowCellManager {}, SimpleStepSelectorBuilder {});
+        ctx.sub_circuit(config.clone(), bit_circuit, bits);
+        ctx.sub_circuit(config, bit_circuit, bits);
+
+        let super_circuit = ctx.compile();
+        let sub_circuits = super_circuit.get_sub_circuits();
+
+        assert_eq!(sub_circuits.len(), 3);
+        let table_column = sub_circuits[0]
+            .columns
+            .iter()
+            .find(|column| column.annotation.contains("bits bit"))
+            .unwrap();
+        for sub_circuit in &sub_circuits[1..] {
+            let (_, dest) = &sub_circuit.lookups[0].exprs[0];
+            assert!(
+                matches!(dest, crate::poly::Expr::Query((column, _, _)) if column == table_column)
+            );
+            assert!(!sub_circuit
+                .columns
+                .iter()
+                .any(|column| column.annotation.contains("bits bit")));
+        }
+    }
+
+    #[test]
+    fn test_super_circuit_channel() {
+        let mut ctx = SuperCircuitContext::<Fr, ()>::default();
+        let pairs = ctx.channel("pairs", ["first", "second"]);
+
+        fn publisher(ctx: &mut CircuitContext<Fr, ()>, pairs: Channel<2>) {
+            let x = ctx.forward("x");
+            let y = ctx.forward("y");
+            ctx.publish(&pairs, [x, y]);
+            ctx.step_type_def("publish", |ctx| {
+                ctx.wg(move |ctx, _: ()| ctx.assign(x, 1.field()))
+            });
+            ctx.pragma_num_steps(1);
+        }
+
+        fn receiver(ctx: &mut CircuitContext<Fr, ()>, pairs: Channel<2>) {
+            let a = ctx.forward("a");
+            ctx.step_type_def("receive", |ctx| {
+                ctx.setup(move |ctx| ctx.add_lookup(pairs.receive([a, a])));
+
+                ctx.wg(move |ctx, _: ()| ctx.assign(a, 1.field()))
+            });
+            ctx.pragma_num_steps(1);
+        }
+
+        let config = config(SingleRowCellManager {}, SimpleStepSelectorBuilder {});
+        ctx.sub_circuit(config.clone(), publisher, pairs.clone());
+        ctx.sub_circuit(config, receiver, pairs);
+
+        let sub_circuits = ctx.compile().get_sub_circuits();
+        let lookup = &sub_circuits[1].lookups[0];
+        assert_eq!(lookup.exprs.len(), 2);
+        assert!(lookup.annotation.contains("pairs.first <- a"));
+        assert!(lookup.annotation.contains("pairs.second <- a"));
+        let (_, dest) = &lookup.exprs[1];
+        assert!(format!("{:?}", dest).contains("srcm forward y"));
+    }
+
+    #[test]
+    #[should_panic(expected = "channel pairs is received before it is published")]
+    fn test_super_circuit_channel_unpublished() {
+        let mut ctx = SuperCircuitContext::<Fr, ()>::default();
+        let pairs = ctx.channel("pairs", ["first", "second"]);
+
+        ctx.sub_circuit(
+            config(SingleRowCellManager {}, SimpleStepSelectorBuilder {}),
+            |ctx: &mut CircuitContext<Fr, ()>, pairs: Channel<2>| {
+                let a = ctx.forward("a");
+                ctx.step_type_def("receive", |ctx| {
+                    ctx.setup(move |ctx| ctx.add_lookup(pairs.receive([a, a])));
+
+                    ctx.wg(move |ctx, _: ()| ctx.assign(a, 1.field()))
+                });
+            },
+            pairs,
+        );
+    }
+
+    #[test]
+    fn test_super_circuit_copy() {
+        let mut ctx = SuperCircuitContext::<Fr, ()>::default();
+
+        fn signal_circuit(ctx: &mut CircuitContext<Fr, ()>, name: &str) -> Queriable<Fr> {
+            let x = ctx.forward(name);
+            ctx.step_type_def("step", |ctx| {
+                ctx.wg(move |ctx, value: u32| ctx.assign(x, value.field()))
+            });
+            ctx.pragma_num_steps(2);
+
+            x
+        }
+
+        let config = config(SingleRowCellManager {}, SimpleStepSelectorBuilder {});
+        let (_, a) = ctx.sub_circuit(config.clone(), signal_circuit, "a");
+        let (_, b) = ctx.sub_circuit(config, signal_circuit, "b");
+        ctx.copy((a, ExposeOffset::Last), (b, ExposeOffset::First));
+
+        let sub_circuits = ctx.compile().get_sub_circuits();
+
+        assert_eq!(sub_circuits[0].copies.len(), 1);
+        assert!(sub_circuits[1].copies.is_empty());
+        let ((from, from_row), (to, to_row)) = &sub_circuits[0].copies[0];
+        assert_eq!(from.annotation, "srcm forward a");
+        assert_eq!(*from_row, 1);
+        assert_eq!(to.annotation, "srcm forward b");
+        assert_eq!(*to_row, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "copied signal c not in any sub-circuit")]
+    fn test_super_circuit_copy_unplaced() {
+        let mut ctx = SuperCircuitContext::<Fr, ()>::default();
+        let c = Queriable::Forward(
+            crate::sbpir::ForwardSignal::new_with_phase(0, "c".to_string()),
+            false,
+        );
+        ctx.copy((c, ExposeOffset::First), (c, ExposeOffset::Last));
+
+        ctx.compile();
+    }
+
     #[test]
     fn test_super_circuit_sub_circuit_with_ast() {
         use crate::frontend::dsl::circuit;
@@ -0,0 +1,660 @@
+//! C ABI of the compiler, the witness checker, the mock prover and the bn256 prover, for Go, C++
+//! and the other ecosystems that embed chiquito, declared in `include/chiquito.h`. Circuits are the
+//! JSON of the Python frontend, and witnesses the JSON of a `TraceWitness` or its binary format of
+//! `wit_gen::binary`, told apart by its magic bytes.
+//!
+//! Every function returns a `ChiquitoStatus`, and its results through the out pointers, that are
+//! only written on success. The message of the last error of the thread is returned by
+//! `chiquito_last_error`. Strings and bytes returned are owned by the caller, and freed with
+//! `chiquito_string_free` and `chiquito_bytes_free`. Panics are caught, instead of unwinding into
+//! the caller. A circuit can be used from any thread, but not from several at once.
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+    sync::Arc,
+};
+
+use halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::VerifyingKey,
+    poly::kzg::commitment::ParamsKZG,
+    SerdeFormat,
+};
+use serde_json::{json, Value};
+
+use crate::{
+    frontend::{
+        interchange::{
+            check_witness, circuit_stats_to_json, field_from_decimal, field_to_decimal, from_json,
+            mock_prover, panic_message, plonkish_to_json, sbpir_to_json, violation_to_json,
+            with_field, witness_from_bytes, CompilerOptions, InterchangeError, InterchangeField,
+            StoredCircuit,
+        },
+        proving::{create_halo2_proof, load_params, solidity_verifier, verify_halo2_proof},
+    },
+    plonkish::{backend::halo2::ChiquitoHalo2Circuit, ir::assignments::Assignments},
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+/// Status of a call, `Ok` or the kind of its error.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChiquitoStatus {
+    Ok = 0,
+    /// JSON or binary witness that does not deserialize to the expected chiquito type.
+    DeserializationError = 1,
+    /// Circuit that chiquito fails to compile, or to lay out in a halo2 table.
+    CompilationError = 2,
+    /// Invalid argument, like a null pointer or inconsistent compiler options.
+    ValueError = 3,
+    /// Params that cannot be read.
+    IoError = 4,
+    /// Failure to generate the keys or a proof of a circuit.
+    ProvingError = 5,
+    /// Panic of chiquito, a bug.
+    Panic = 6,
+}
+
+impl From<&InterchangeError> for ChiquitoStatus {
+    fn from(error: &InterchangeError) -> Self {
+        match error {
+            InterchangeError::Deserialization(_) => ChiquitoStatus::DeserializationError,
+            InterchangeError::Compilation(_) => ChiquitoStatus::CompilationError,
+            InterchangeError::Value(_) => ChiquitoStatus::ValueError,
+            InterchangeError::Io(_) => ChiquitoStatus::IoError,
+            InterchangeError::Proving(_) => ChiquitoStatus::ProvingError,
+        }
+    }
+}
+
+/// Bytes owned by the caller, freed with `chiquito_bytes_free`.
+#[repr(C)]
+pub struct ChiquitoBytes {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl From<Vec<u8>> for ChiquitoBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        let len = bytes.len();
+        let data = Box::into_raw(bytes.into_boxed_slice()) as *mut u8;
+
+        ChiquitoBytes { data, len }
+    }
+}
+
+/// Circuit compiled from the JSON of its AST, owned by the caller until `chiquito_circuit_free`.
+pub struct ChiquitoCircuit(StoredCircuit);
+
+fn assignments<F: InterchangeField>(
+    circuit: &StoredCircuit,
+    witness: &[u8],
+) -> Result<Option<Assignments<F>>, InterchangeError> {
+    let witness = witness_from_bytes::<F>(witness)?;
+
+    Ok(circuit.compiled::<F>()?.assignments(witness))
+}
+
+/// Returns the params of `params_path`, or those the circuit was proven with if `None`, and the
+/// verifying key of the circuit with them.
+fn params_and_verifying_key(
+    circuit: &StoredCircuit,
+    params_path: Option<&str>,
+) -> Result<(Arc<ParamsKZG<Bn256>>, Arc<VerifyingKey<G1Affine>>), InterchangeError> {
+    let compiled = &circuit.compiled::<Fr>()?.halo2;
+
+    circuit
+        .keys()
+        .params_and_verifying_key(params_path, compiled)
+}
+
+/// Runs `call`, returning its status, and keeping the message of its error or panic for
+/// `chiquito_last_error`.
+fn ffi_call(call: impl FnOnce() -> Result<(), InterchangeError>) -> ChiquitoStatus {
+    let (status, message) = match panic::catch_unwind(AssertUnwindSafe(call)) {
+        Ok(Ok(())) => return ChiquitoStatus::Ok,
+        Ok(Err(error)) => ((&error).into(), error.to_string()),
+        Err(payload) => (ChiquitoStatus::Panic, panic_message(payload)),
+    };
+    let message = CString::new(message.replace('\0', "")).expect("message without nul bytes");
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = message);
+
+    status
+}
+
+/// Returns the string of `ptr`, named `name` in the error.
+unsafe fn str_arg<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, InterchangeError> {
+    if ptr.is_null() {
+        return Err(InterchangeError::Value(format!("{} is null", name)));
+    }
+
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map_err(|e| InterchangeError::Value(format!("{} is not UTF-8: {}", name, e)))
+}
+
+/// Returns the string of `ptr`, or `None` if it is null.
+unsafe fn optional_str_arg<'a>(
+    ptr: *const c_char,
+    name: &str,
+) -> Result<Option<&'a str>, InterchangeError> {
+    if ptr.is_null() {
+        Ok(None)
+    } else {
+        str_arg(ptr, name).map(Some)
+    }
+}
+
+/// Returns the `len` bytes of `ptr`, that is only null if there are none.
+unsafe fn bytes_arg<'a>(
+    ptr: *const u8,
+    len: usize,
+    name: &str,
+) -> Result<&'a [u8], InterchangeError> {
+    match (ptr.is_null(), len) {
+        (true, 0) => Ok(&[]),
+        (true, _) => Err(InterchangeError::Value(format!("{} is null", name))),
+        (false, _) => Ok(slice::from_raw_parts(ptr, len)),
+    }
+}
+
+unsafe fn circuit_arg<'a>(
+    circuit: *mut ChiquitoCircuit,
+) -> Result<&'a StoredCircuit, InterchangeError> {
+    circuit
+        .as_ref()
+        .map(|circuit| &circuit.0)
+        .ok_or_else(|| InterchangeError::Value("circuit is null".to_string()))
+}
+
+/// Writes `value` to `out`, that fails if it is null.
+unsafe fn write_out<T>(out: *mut T, value: T) -> Result<(), InterchangeError> {
+    if out.is_null() {
+        return Err(InterchangeError::Value("out pointer is null".to_string()));
+    }
+    out.write(value);
+
+    Ok(())
+}
+
+fn string_out(string: String) -> *mut c_char {
+    CString::new(string.replace('\0', ""))
+        .expect("string without nul bytes")
+        .into_raw()
+}
+
+fn json_out(json: Result<Value, String>) -> Result<*mut c_char, InterchangeError> {
+    json.and_then(|json| serde_json::to_string_pretty(&json).map_err(|e| e.to_string()))
+        .map(string_out)
+        .map_err(InterchangeError::Value)
+}
+
+/// Returns the message of the last error of the thread, empty if none. It is valid until the next
+/// call that fails on the thread.
+#[no_mangle]
+pub extern "C" fn chiquito_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ptr())
+}
+
+/// Parses the JSON of a circuit and compiles it with the JSON of `CompilerOptions`, or with the
+/// defaults if `options_json` is null, to `out`.
+///
+/// # Safety
+///
+/// `ast_json` and `options_json` are null or nul-terminated strings, and `out` is null or valid
+/// for writes.
+#[no_mangle]
+pub unsafe extern "C" fn chiquito_compile(
+    ast_json: *const c_char,
+    options_json: *const c_char,
+    out: *mut *mut ChiquitoCircuit,
+) -> ChiquitoStatus {
+    ffi_call(|| {
+        let ast_json = str_arg(ast_json, "ast_json")?;
+        let options = CompilerOptions::from_json(optional_str_arg(options_json, "options_json")?)?;
+
+        let circuit = ChiquitoCircuit(StoredCircuit::compile(ast_json, &options)?);
+
+        write_out(out, Box::into_raw(Box::new(circuit)))
+    })
+}
+
+/// Frees a circuit of `chiquito_compile`.
+///
+/// # Safety
+///
+/// `circuit` is null or a circuit of `chiquito_compile` that is not freed.
+#[no_mangle]
+pub unsafe extern "C" fn chiquito_circuit_free(circuit: *mut ChiquitoCircuit) {
+    if !circuit.is_null() {
+        drop(Box::from_raw(circuit));
+    }
+}
+
+/// Returns the AST of the circuit as Rust parsed it, serialized back to JSON.
+///
+/// # Safety
+///
+/// `circuit` is null or a circuit of `chiquito_compile`, and `out` is null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn chiquito_ast_json(
+    circuit: *mut ChiquitoCircuit,
+    out: *mut *mut c_char,
+) -> ChiquitoStatus {
+    ffi_call(|| {
+        let circuit = circuit_arg(circuit)?;
+        let json =
+            with_field!(circuit.field(), F => json_out(sbpir_to_json(&circuit.ast::<F>()?))?);
+
+        write_out(out, json)
+    })
+}
+
+/// Returns the plonkish IR the circuit was compiled to, as JSON, with the placement of its signals
+/// in the columns.
+///
+/// # Safety
+///
+/// `circuit` is null or a circuit of `chiquito_compile`, and `out` is null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn chiquito_plonkish_json(
+    circuit: *mut ChiquitoCircuit,
+    out: *mut *mut c_char,
+) -> ChiquitoStatus {
+    ffi_call(|| {
+        let circuit = circuit_arg(circuit)?;
+        let json = with_field!(circuit.field(), F => {
+            let compiled = circuit.compiled::<F>()?;
+            let placement = compiled.assigner.as_ref().map(|assigner| assigner.placement());
+            json_out(plonkish_to_json(compiled.halo2.circuit(), placement))?
+        });
+
+        write_out(out, json)
+    })
+}
+
+/// Returns the size of the circuit in the halo2 table, as JSON, and the smallest k of a table that
+/// fits it to `min_k`, if not null.
+///
+/// # Safety
+///
+/// `circuit` is null or a circuit of `chiquito_compile`, `out` is null or valid for writes, and
+/// `min_k` too, or null.
+#[no_mangle]
+pub unsafe extern "C" fn chiquito_stats(
+    circuit: *mut ChiquitoCircuit,
+    out: *mut *mut c_char,
+    min_k: *mut u32,
+) -> ChiquitoStatus {
+    ffi_call(|| {
+        let circuit = circuit_arg(circuit)?;
+        let stats = with_field!(circuit.field(), F => circuit.compiled::<F>()?.halo2.stats());
+
+        write_out(out, string_out(circuit_stats_to_json(&stats).to_string()))?;
+        if !min_k.is_null() {
+            min_k.write(stats.min_k);
+        }
+
+        Ok(())
+    })
+}
+
+/// Checks a witness against the AST of the circuit with the native `Checker`, without running
+/// `MockProver`. Returns the JSON array of the violations in step order, with the keys of the
+/// Python frontend, empty if the witness satisfies the circuit.
+///
+/// # Safety
+///
+/// `circuit` is null or a circuit of `chiquito_compile`, `witness` is null or valid for reads of
+/// `witness_len` bytes, and `out` is null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn chiquito_check_witness(
+    circuit: *mut ChiquitoCircuit,
+    witness: *const u8,
+    witness_len: usize,
+    out: *mut *mut c_char,
+) -> ChiquitoStatus {
+    ffi_call(|| {
+        let circuit = circuit_arg(circuit)?;
+        let witness = bytes_arg(witness, witness_len, "witness")?;
+
+        let violations = with_field!(circuit.field(), F => {
+            check_witness(&circuit.ast::<F>()?, &witness_from_bytes::<F>(witness)?)?
+        });
+        let violations: Vec<_> = violations.iter().map(violation_to_json).collect();
+
+        write_out(out, string_out(Value::from(violations).to_string()))
+    })
+}
+
+/// Runs `MockProver` for a witness, with `k`, or the smallest k that fits the circuit if it is 0.
+/// Returns the JSON of the result, `{ "ok": bool, "failures": [string] }`, with the failures of
+/// `MockProver::verify` as displayed by halo2.
+///
+/// # Safety
+///
+/// `circuit` is null or a circuit of `chiquito_compile`, `witness` is null or valid for reads of
+/// `witness_len` bytes, and `out` is null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn chiquito_mock_prove(
+    circuit: *mut ChiquitoCircuit,
+    witness: *const u8,
+    witness_len: usize,
+    k: u32,
+    out: *mut *mut c_char,
+) -> ChiquitoStatus {
+    ffi_call(|| {
+        let circuit = circuit_arg(circuit)?;
+        let witness = bytes_arg(witness, witness_len, "witness")?;
+
+        let failures: Vec<String> = with_field!(circuit.field(), F => {
+            let compiled = circuit.compiled::<F>()?;
+            let assignments = assignments::<F>(circuit, witness)?;
+            let halo2_circuit = ChiquitoHalo2Circuit::new(compiled.halo2.clone(), assignments);
+
+            let k = (k > 0).then_some(k as usize);
+            let min_k = compiled.halo2.stats().min_k;
+            let instance = halo2_circuit.instance();
+            match mock_prover(k, min_k, &halo2_circuit, instance)?.verify() {
+                Ok(()) => Vec::new(),
+                Err(failures) => failures.iter().map(ToString::to_string).collect(),
+            }
+        });
+        let result = json!({ "ok": failures.is_empty(), "failures": failures });
+
+        write_out(out, string_out(result.to_string()))
+    })
+}
+
+/// Generates a proof of a bn256 circuit with a witness, with the KZG params read from
+/// `params_path`, whose k is the one of the circuit. The params and the proving key of each params
+/// are cached, so only the first proof reads the params and generates the key. Returns the proof
+/// to `proof_out`, and the public inputs to `instance_out`, as the JSON of their decimal strings,
+/// by instance column. With `evm`, the proof uses the Keccak256 transcript of the verifier of
+/// `chiquito_solidity_verifier`, instead of Blake2b.
+///
+/// # Safety
+///
+/// `circuit` is null or a circuit of `chiquito_compile`, `witness` is null or valid for reads of
+/// `witness_len` bytes, `params_path` is null or a nul-terminated string, and `proof_out` and
+/// `instance_out` are null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn chiquito_prove(
+    circuit: *mut ChiquitoCircuit,
+    witness: *const u8,
+    witness_len: usize,
+    params_path: *const c_char,
+    evm: bool,
+    proof_out: *mut ChiquitoBytes,
+    instance_out: *mut *mut c_char,
+) -> ChiquitoStatus {
+    ffi_call(|| {
+        let circuit = circuit_arg(circuit)?;
+        let witness = bytes_arg(witness, witness_len, "witness")?;
+        let params_path = str_arg(params_path, "params_path")?;
+        if proof_out.is_null() || instance_out.is_null() {
+            return Err(InterchangeError::Value("out pointer is null".to_string()));
+        }
+
+        let compiled = circuit.compiled::<Fr>()?.halo2.clone();
+        let assignments = assignments::<Fr>(circuit, witness)?;
+        let params = load_params(params_path)?;
+        let pk = circuit
+            .keys()
+            .proving_key(params_path, &params, &compiled)?;
+        let halo2_circuit = ChiquitoHalo2Circuit::new(compiled, assignments);
+        let (proof, instance) = create_halo2_proof(&params, &pk, halo2_circuit, evm)?;
+
+        let instance: Vec<Vec<String>> = instance
+            .iter()
+            .map(|column| column.iter().map(field_to_decimal).collect())
+            .collect();
+        write_out(instance_out, string_out(json!(instance).to_string()))?;
+        write_out(proof_out, proof.into())
+    })
+}
+
+/// Verifies a `proof` of a bn256 circuit with its public inputs, the JSON of their decimal strings
+/// by instance column, and the KZG params read from `params_path`. If `params_path` is null, the
+/// params that the circuit was proven with are used. Returns if the proof is valid to `out`. With
+/// `evm`, the proof is one of the Keccak256 transcript, see `chiquito_prove`.
+///
+/// # Safety
+///
+/// `circuit` is null or a circuit of `chiquito_compile`, `proof` is null or valid for reads of
+/// `proof_len` bytes, `instance_json` and `params_path` are null or nul-terminated strings, and
+/// `out` is null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn chiquito_verify(
+    circuit: *mut ChiquitoCircuit,
+    proof: *const u8,
+    proof_len: usize,
+    instance_json: *const c_char,
+    params_path: *const c_char,
+    evm: bool,
+    out: *mut bool,
+) -> ChiquitoStatus {
+    ffi_call(|| {
+        let circuit = circuit_arg(circuit)?;
+        let proof = bytes_arg(proof, proof_len, "proof")?;
+        let instance: Vec<Vec<String>> =
+            from_json(str_arg(instance_json, "instance_json")?, "instance")?;
+        let instance = instance
+            .iter()
+            .map(|column| {
+                column
+                    .iter()
+                    .map(|value| field_from_decimal(value))
+                    .collect()
+            })
+            .collect::<Result<Vec<Vec<Fr>>, _>>()?;
+
+        let (params, vk) =
+            params_and_verifying_key(circuit, optional_str_arg(params_path, "params_path")?)?;
+
+        write_out(
+            out,
+            verify_halo2_proof(&params, &vk, proof, &instance, evm)?,
+        )
+    })
+}
+
+/// Returns the verifying key of a bn256 circuit with the params of `params_path`, or those it was
+/// proven with if null, serialized in the raw bytes format of halo2.
+///
+/// # Safety
+///
+/// `circuit` is null or a circuit of `chiquito_compile`, `params_path` is null or a nul-terminated
+/// string, and `out` is null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn chiquito_verifying_key(
+    circuit: *mut ChiquitoCircuit,
+    params_path: *const c_char,
+    out: *mut ChiquitoBytes,
+) -> ChiquitoStatus {
+    ffi_call(|| {
+        let circuit = circuit_arg(circuit)?;
+        let (_, vk) =
+            params_and_verifying_key(circuit, optional_str_arg(params_path, "params_path")?)?;
+
+        write_out(out, vk.to_bytes(SerdeFormat::RawBytes).into())
+    })
+}
+
+/// Returns the source of a Solidity contract that verifies the proofs of a bn256 circuit generated
+/// with `evm`, with the params of `params_path`, or those it was proven with if null. The
+/// verifying key is embedded in the contract.
+///
+/// # Safety
+///
+/// `circuit` is null or a circuit of `chiquito_compile`, `params_path` is null or a nul-terminated
+/// string, and `out` is null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn chiquito_solidity_verifier(
+    circuit: *mut ChiquitoCircuit,
+    params_path: *const c_char,
+    out: *mut *mut c_char,
+) -> ChiquitoStatus {
+    ffi_call(|| {
+        let circuit = circuit_arg(circuit)?;
+        let (params, vk) =
+            params_and_verifying_key(circuit, optional_str_arg(params_path, "params_path")?)?;
+        let verifier = solidity_verifier(&params, &vk, &circuit.compiled::<Fr>()?.halo2)?;
+
+        write_out(out, string_out(verifier))
+    })
+}
+
+/// Frees a string returned by chiquito.
+///
+/// # Safety
+///
+/// `string` is null or a string returned by chiquito that is not freed.
+#[no_mangle]
+pub unsafe extern "C" fn chiquito_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Frees bytes returned by chiquito.
+///
+/// # Safety
+///
+/// `bytes` are bytes returned by chiquito that are not freed, or null.
+#[no_mangle]
+pub unsafe extern "C" fn chiquito_bytes_free(bytes: ChiquitoBytes) {
+    if !bytes.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            bytes.data, bytes.len,
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::interchange::tests::circuit_with_fixed_assignments;
+
+    unsafe fn take_string(string: *mut c_char) -> String {
+        let value = CStr::from_ptr(string).to_str().unwrap().to_string();
+        chiquito_string_free(string);
+
+        value
+    }
+
+    unsafe fn last_error() -> String {
+        CStr::from_ptr(chiquito_last_error())
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_compile_and_mock_prove() {
+        unsafe {
+            let json = CString::new(circuit_with_fixed_assignments("null")).unwrap();
+            let mut circuit = ptr::null_mut();
+            let status = chiquito_compile(json.as_ptr(), ptr::null(), &mut circuit);
+            assert_eq!(status, ChiquitoStatus::Ok);
+
+            let mut stats = ptr::null_mut();
+            let mut min_k = 0;
+            assert_eq!(
+                chiquito_stats(circuit, &mut stats, &mut min_k),
+                ChiquitoStatus::Ok
+            );
+            let stats: Value = serde_json::from_str(&take_string(stats)).unwrap();
+            assert_eq!(stats["min_k"], min_k);
+
+            let mut ast = ptr::null_mut();
+            assert_eq!(chiquito_ast_json(circuit, &mut ast), ChiquitoStatus::Ok);
+            let ast: Value = serde_json::from_str(&take_string(ast)).unwrap();
+            assert_eq!(ast["fixed_signals"][0]["annotation"], "byte");
+
+            let witness = br#"{ "step_instances": [] }"#;
+            let mut result = ptr::null_mut();
+            let status =
+                chiquito_mock_prove(circuit, witness.as_ptr(), witness.len(), 0, &mut result);
+            assert_eq!(status, ChiquitoStatus::Ok);
+            let result: Value = serde_json::from_str(&take_string(result)).unwrap();
+            assert_eq!(result["ok"], true);
+
+            let status = chiquito_mock_prove(
+                circuit,
+                witness.as_ptr(),
+                witness.len(),
+                min_k - 1,
+                &mut result,
+            );
+            assert_eq!(status, ChiquitoStatus::CompilationError);
+            assert!(last_error().contains("too small"));
+
+            let mut violations = ptr::null_mut();
+            let status =
+                chiquito_check_witness(circuit, witness.as_ptr(), witness.len(), &mut violations);
+            assert_eq!(status, ChiquitoStatus::Ok);
+            assert_eq!(take_string(violations), "[]");
+
+            chiquito_circuit_free(circuit);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        unsafe {
+            let mut circuit = ptr::null_mut();
+            let json = CString::new("{").unwrap();
+            let status = chiquito_compile(json.as_ptr(), ptr::null(), &mut circuit);
+            assert_eq!(status, ChiquitoStatus::DeserializationError);
+            assert!(circuit.is_null());
+            assert!(last_error().starts_with("Json deserialization to Circuit failed"));
+
+            let status = chiquito_compile(ptr::null(), ptr::null(), &mut circuit);
+            assert_eq!(status, ChiquitoStatus::ValueError);
+            assert_eq!(last_error(), "ast_json is null");
+
+            let json = CString::new(circuit_with_fixed_assignments("null")).unwrap();
+            let options = CString::new(r#"{ "field": "pasta_fp" }"#).unwrap();
+            let status = chiquito_compile(json.as_ptr(), options.as_ptr(), &mut circuit);
+            assert_eq!(status, ChiquitoStatus::Ok);
+
+            // only bn256 circuits are proven
+            let params_path = CString::new("/nonexistent/params").unwrap();
+            let mut proof = ChiquitoBytes {
+                data: ptr::null_mut(),
+                len: 0,
+            };
+            let mut instance = ptr::null_mut();
+            let status = chiquito_prove(
+                circuit,
+                ptr::null(),
+                0,
+                params_path.as_ptr(),
+                false,
+                &mut proof,
+                &mut instance,
+            );
+            assert_eq!(status, ChiquitoStatus::ValueError);
+            assert!(proof.data.is_null() && instance.is_null());
+
+            let mut stats = ptr::null_mut();
+            assert_eq!(
+                chiquito_stats(circuit, ptr::null_mut(), ptr::null_mut()),
+                ChiquitoStatus::ValueError
+            );
+            assert_eq!(
+                chiquito_stats(ptr::null_mut(), &mut stats, ptr::null_mut()),
+                ChiquitoStatus::ValueError
+            );
+            assert_eq!(last_error(), "circuit is null");
+            chiquito_circuit_free(circuit);
+        }
+    }
+}
@@ -0,0 +1,3114 @@
+use std::{
+    any::Any,
+    collections::HashMap,
+    fmt,
+    hash::Hash,
+    marker::PhantomData,
+    panic::{self, AssertUnwindSafe},
+    rc::Rc,
+    sync::{Arc, OnceLock},
+};
+
+use halo2_proofs::{
+    dev::MockProver,
+    halo2curves::{
+        bn256::Fr,
+        ff::{Field as _, FromUniformBytes, PrimeField},
+        pasta,
+    },
+    plonk::Circuit as h2Circuit,
+};
+use num_bigint::BigUint;
+use serde::de::{self, Deserialize, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde_json::{json, Value};
+
+use crate::{
+    field::Field,
+    frontend::{
+        dsl::{StepTypeHandler, SuperCircuitContext},
+        proving::CircuitKeys,
+    },
+    plonkish::{
+        backend::halo2::{chiquito2Halo2, ChiquitoHalo2, CircuitStats},
+        compiler::{
+            cell_manager::{
+                CellManager, MaxWidthCellManager, Placement, SignalPlacement, SingleRowCellManager,
+            },
+            compile, config,
+            step_selector::{
+                LogNSelectorBuilder, SimpleStepSelectorBuilder, StepSelectorBuilder,
+                TwoStepsSelectorBuilder,
+            },
+            unit::CompilationUnit,
+            CompilerConfig,
+        },
+        ir::{
+            assignments::{AssignmentGenerator, Assignments, WitnessAssigner},
+            sc::SuperCircuit,
+            Circuit, Column, ColumnType, PolyExpr,
+        },
+    },
+    poly::Expr,
+    sbpir::{
+        checker::{Checker, Violation, ViolationKind},
+        query::Queriable,
+        Constraint, ExposeOffset, FixedSignal, ForwardSignal, InternalSignal, Lookup, SharedSignal,
+        StepType, StepTypeUUID, TransitionConstraint, SBPIR,
+    },
+    util::UUID,
+    wit_gen::{binary, FixedAssignment, StepInstance, TraceContext, TraceWitness},
+};
+
+/// Error of the interchange of circuits and witnesses with a frontend, that each binding raises as
+/// its own error type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InterchangeError {
+    /// JSON or binary witness that does not deserialize to the expected chiquito type.
+    Deserialization(String),
+    /// Circuit that chiquito fails to compile, or to lay out in a halo2 table.
+    Compilation(String),
+    /// Invalid argument, like inconsistent compiler options.
+    Value(String),
+    /// Params that cannot be read.
+    Io(String),
+    /// Failure to generate the keys or a proof of a circuit.
+    Proving(String),
+}
+
+impl fmt::Display for InterchangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterchangeError::Deserialization(message)
+            | InterchangeError::Compilation(message)
+            | InterchangeError::Value(message)
+            | InterchangeError::Io(message)
+            | InterchangeError::Proving(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for InterchangeError {}
+
+/// Field that circuits from a frontend are compiled to, selected by `FieldOption`.
+pub trait InterchangeField: PrimeField + FromUniformBytes<64> + Ord + Hash {
+    const FIELD: FieldOption;
+
+    /// Inverse of 2^256, the Montgomery factor of the field elements in the JSON of the frontends.
+    fn montgomery_inv() -> Self;
+}
+
+macro_rules! impl_interchange_field {
+    ($($type:ty => $field:ident),*) => {
+        $(
+            impl InterchangeField for $type {
+                const FIELD: FieldOption = FieldOption::$field;
+
+                fn montgomery_inv() -> Self {
+                    static INV: OnceLock<$type> = OnceLock::new();
+                    *INV.get_or_init(|| Self::from(2u64).pow_vartime([256]).invert().unwrap())
+                }
+            }
+        )*
+    };
+}
+
+impl_interchange_field!(Fr => Bn256, pasta::Fp => PastaFp, pasta::Fq => PastaFq);
+
+/// Runs `$body` with `$F` as the `InterchangeField` of the `FieldOption` `$field`.
+macro_rules! with_field {
+    ($field:expr, $F:ident => $body:expr) => {
+        match $field {
+            $crate::frontend::interchange::FieldOption::Bn256 => {
+                type $F = halo2_proofs::halo2curves::bn256::Fr;
+                $body
+            }
+            $crate::frontend::interchange::FieldOption::PastaFp => {
+                type $F = halo2_proofs::halo2curves::pasta::Fp;
+                $body
+            }
+            $crate::frontend::interchange::FieldOption::PastaFq => {
+                type $F = halo2_proofs::halo2curves::pasta::Fq;
+                $body
+            }
+        }
+    };
+}
+
+pub(crate) use with_field;
+
+/// Parses a witness in the binary format of `wit_gen::binary`, the whole of `bytes`.
+pub(crate) fn witness_from_binary<F: InterchangeField>(
+    mut bytes: &[u8],
+) -> Result<TraceWitness<F>, InterchangeError> {
+    let witness = binary::read(&mut bytes).map_err(|e| witness_error(&e.to_string()))?;
+    if !bytes.is_empty() {
+        return Err(witness_error("trailing bytes after the signals"));
+    }
+
+    Ok(witness)
+}
+
+/// Parses a witness in bytes, in the binary format of `wit_gen::binary` if it starts with its
+/// magic bytes, or else as the JSON of a `TraceWitness`.
+pub(crate) fn witness_from_bytes<F: InterchangeField>(
+    witness: &[u8],
+) -> Result<TraceWitness<F>, InterchangeError> {
+    if witness.starts_with(binary::MAGIC) {
+        witness_from_binary(witness)
+    } else {
+        let json = std::str::from_utf8(witness).map_err(|e| {
+            InterchangeError::Deserialization(format!("witness is not UTF-8 JSON: {}", e))
+        })?;
+        from_json(json, "TraceWitness")
+    }
+}
+
+fn witness_error(message: &str) -> InterchangeError {
+    InterchangeError::Deserialization(format!(
+        "Binary witness deserialization failed: {}",
+        message
+    ))
+}
+
+/// Deserializes `json` to a `T`, named `name` in the error.
+pub(crate) fn from_json<'de, T: Deserialize<'de>>(
+    json: &'de str,
+    name: &str,
+) -> Result<T, InterchangeError> {
+    serde_json::from_str(json).map_err(|e| {
+        InterchangeError::Deserialization(format!("Json deserialization to {} failed: {}", name, e))
+    })
+}
+
+/// Runs `compile`, returning the panics of the compiler on an invalid circuit, like a query of a
+/// signal that is not in it, as an `InterchangeError::Compilation` instead of aborting.
+pub(crate) fn catch_compilation<T>(compile: impl FnOnce() -> T) -> Result<T, InterchangeError> {
+    panic::catch_unwind(AssertUnwindSafe(compile))
+        .map_err(|payload| InterchangeError::Compilation(panic_message(payload)))
+}
+
+pub(crate) fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "panicked without a message".to_string(),
+        },
+    }
+}
+
+/// Runs `MockProver` with `k`, or with `min_k`, the smallest k that fits the circuit, if it is
+/// `None`. Fails for a `k` below `min_k`, on which halo2 would panic laying out the circuit, or
+/// over `u32::MAX`.
+pub(crate) fn mock_prover<F: InterchangeField, C: h2Circuit<F>>(
+    k: Option<usize>,
+    min_k: u32,
+    circuit: &C,
+    instance: Vec<Vec<F>>,
+) -> Result<MockProver<F>, InterchangeError> {
+    let k = match k {
+        Some(k) => u32::try_from(k).map_err(|_| {
+            InterchangeError::Value(format!("k = {} is over the maximum of {}", k, u32::MAX))
+        })?,
+        None => min_k,
+    };
+    if k < min_k {
+        return Err(InterchangeError::Compilation(format!(
+            "k = {} is too small for the rows of the circuit, that needs at least k = {}",
+            k, min_k
+        )));
+    }
+
+    MockProver::<F>::run(k, circuit, instance).map_err(|e| {
+        InterchangeError::Compilation(format!("MockProver failed to run with k = {}: {:?}", k, e))
+    })
+}
+
+/// Compiler configuration from a frontend, as JSON. The missing fields take the defaults: a single
+/// row cell manager, the simple step selector, no optimizations and no degree limit.
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CompilerOptions {
+    pub cell_manager: CellManagerOption,
+    /// Columns of the max width cell manager.
+    pub max_width: Option<usize>,
+    /// Places every step of the max width cell manager in the same rows.
+    pub same_height: bool,
+    pub step_selector: StepSelectorOption,
+    /// 0 for none, 1 to simplify the constraints to normal form, see `CompilerConfig::simplify`, 2
+    /// to also lower the field operations of evaluating the gates, see
+    /// `ChiquitoHalo2::optimize_evaluation`.
+    pub optimization_level: u8,
+    /// Highest degree of the polys and lookups of the compiled circuit, above which the
+    /// compilation fails.
+    pub max_degree: Option<usize>,
+    pub field: FieldOption,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CellManagerOption {
+    #[default]
+    SingleRow,
+    MaxWidth,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepSelectorOption {
+    #[default]
+    Simple,
+    LogN,
+    TwoSteps,
+}
+
+/// Field of a circuit, as its `InterchangeField`. The circuits of every field are mock proven, but
+/// only the bn256 ones are proven, with KZG over bn256. Other fields, like Goldilocks, are added as
+/// halo2 supports them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldOption {
+    /// Scalar field of bn256.
+    #[default]
+    Bn256,
+    /// Base field of pallas, the scalar field of vesta.
+    PastaFp,
+    /// Base field of vesta, the scalar field of pallas.
+    PastaFq,
+}
+
+impl FieldOption {
+    /// Name of the field in the JSON of the options.
+    pub fn name(self) -> &'static str {
+        match self {
+            FieldOption::Bn256 => "bn256",
+            FieldOption::PastaFp => "pasta_fp",
+            FieldOption::PastaFq => "pasta_fq",
+        }
+    }
+
+    /// Parses the name of a field, see `name`.
+    pub fn from_name(name: &str) -> Result<Self, InterchangeError> {
+        from_json(&Value::from(name).to_string(), "FieldOption")
+    }
+}
+
+/// Cell manager chosen at runtime, see `CompilerOptions`.
+#[derive(Clone)]
+pub enum AnyCellManager {
+    SingleRow(SingleRowCellManager),
+    MaxWidth(MaxWidthCellManager),
+}
+
+impl CellManager for AnyCellManager {
+    fn place<F>(&self, unit: &mut CompilationUnit<F>) {
+        match self {
+            AnyCellManager::SingleRow(cell_manager) => cell_manager.place(unit),
+            AnyCellManager::MaxWidth(cell_manager) => cell_manager.place(unit),
+        }
+    }
+}
+
+/// Step selector builder chosen at runtime, see `CompilerOptions`.
+#[derive(Clone)]
+pub enum AnyStepSelectorBuilder {
+    Simple(SimpleStepSelectorBuilder),
+    LogN(LogNSelectorBuilder),
+    TwoSteps(TwoStepsSelectorBuilder),
+}
+
+impl StepSelectorBuilder for AnyStepSelectorBuilder {
+    fn build<F: Field>(&self, unit: &mut CompilationUnit<F>) {
+        match self {
+            AnyStepSelectorBuilder::Simple(builder) => builder.build(unit),
+            AnyStepSelectorBuilder::LogN(builder) => builder.build(unit),
+            AnyStepSelectorBuilder::TwoSteps(builder) => builder.build(unit),
+        }
+    }
+}
+
+impl CompilerOptions {
+    /// Parses the JSON of the options, or returns the defaults without it.
+    pub fn from_json(json: Option<&str>) -> Result<Self, InterchangeError> {
+        json.map_or_else(
+            || Ok(Self::default()),
+            |json| from_json(json, "CompilerOptions"),
+        )
+    }
+
+    pub fn config<F>(
+        &self,
+    ) -> Result<CompilerConfig<F, AnyCellManager, AnyStepSelectorBuilder>, InterchangeError> {
+        let cell_manager = match self.cell_manager {
+            CellManagerOption::SingleRow => AnyCellManager::SingleRow(SingleRowCellManager {}),
+            CellManagerOption::MaxWidth => match self.max_width {
+                Some(max_width) if max_width > 0 => {
+                    AnyCellManager::MaxWidth(MaxWidthCellManager::new(max_width, self.same_height))
+                }
+                _ => {
+                    return Err(InterchangeError::Value(
+                        "the max_width cell manager needs a positive max_width".to_string(),
+                    ))
+                }
+            },
+        };
+        let step_selector_builder = match self.step_selector {
+            StepSelectorOption::Simple => AnyStepSelectorBuilder::Simple(Default::default()),
+            StepSelectorOption::LogN => AnyStepSelectorBuilder::LogN(Default::default()),
+            StepSelectorOption::TwoSteps => AnyStepSelectorBuilder::TwoSteps(Default::default()),
+        };
+
+        let config = config(cell_manager, step_selector_builder);
+        Ok(if self.optimization_level >= 1 {
+            config.simplify()
+        } else {
+            config
+        })
+    }
+
+    /// Applies the backend options to a compiled circuit and checks its degree.
+    pub(crate) fn apply<F: InterchangeField>(
+        &self,
+        chiquito_halo2: &mut ChiquitoHalo2<F>,
+    ) -> Result<(), InterchangeError> {
+        chiquito_halo2.optimize_evaluation = self.optimization_level >= 2;
+
+        if let Some(max_degree) = self.max_degree {
+            let degree = chiquito_halo2.stats().max_degree;
+            if degree > max_degree {
+                return Err(InterchangeError::Compilation(format!(
+                    "circuit of degree {} above the max_degree {}",
+                    degree, max_degree
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses the JSON of a circuit and compiles it with `options`, to the halo2 backend in `F`.
+/// Returns the compiled circuit, and the assigner of its witnesses if it has an assignment
+/// generator.
+pub fn compile_ast<F: InterchangeField>(
+    ast_json: &str,
+    options: &CompilerOptions,
+) -> Result<(ChiquitoHalo2<F>, Option<Arc<WitnessAssigner<F>>>), InterchangeError> {
+    let circuit: SBPIR<F, ()> = from_json(ast_json, "Circuit")?;
+
+    let config = options.config()?;
+    let (chiquito, assignment_generator) = catch_compilation(|| compile(config, &circuit))?;
+    let mut chiquito_halo2 = chiquito2Halo2(chiquito);
+    options.apply(&mut chiquito_halo2)?;
+    let assigner = assignment_generator.map(|generator| generator.assigner());
+
+    Ok((chiquito_halo2, assigner))
+}
+
+/// Compiled circuit and the assigner of its witnesses, kept instead of its assignment generator,
+/// which is not `Send`.
+#[derive(Clone)]
+pub(crate) struct CompiledCircuit<F: InterchangeField> {
+    pub(crate) halo2: ChiquitoHalo2<F>,
+    pub(crate) assigner: Option<Arc<WitnessAssigner<F>>>,
+}
+
+impl<F: InterchangeField> CompiledCircuit<F> {
+    /// Returns the assignments of `witness`, none if the circuit has no assignment generator.
+    pub(crate) fn assignments(&self, witness: TraceWitness<F>) -> Option<Assignments<F>> {
+        self.assigner
+            .as_ref()
+            .map(|assigner| assigner.generate_with_witness(witness))
+    }
+}
+
+/// Circuit compiled by the bindings, in the field of its options. The AST is kept as its JSON, to
+/// check witnesses against, and the compiled circuit as the `CompiledCircuit` of its field. The
+/// keys of a bn256 circuit are cached with it, and shared with the threads that prove it.
+pub(crate) struct StoredCircuit {
+    field: FieldOption,
+    ast_json: String,
+    compiled: Box<dyn Any + Send>,
+    keys: Arc<CircuitKeys>,
+}
+
+impl StoredCircuit {
+    /// Parses the JSON of a circuit and compiles it with `options`, see `compile_ast`.
+    pub(crate) fn compile(
+        ast_json: &str,
+        options: &CompilerOptions,
+    ) -> Result<Self, InterchangeError> {
+        with_field!(options.field, F => {
+            let (halo2, assigner) = compile_ast::<F>(ast_json, options)?;
+
+            Ok(Self::new(ast_json, halo2, assigner))
+        })
+    }
+
+    pub(crate) fn new<F: InterchangeField>(
+        ast_json: &str,
+        halo2: ChiquitoHalo2<F>,
+        assigner: Option<Arc<WitnessAssigner<F>>>,
+    ) -> Self {
+        Self {
+            field: F::FIELD,
+            ast_json: ast_json.to_string(),
+            compiled: Box::new(CompiledCircuit { halo2, assigner }),
+            keys: Default::default(),
+        }
+    }
+
+    pub(crate) fn field(&self) -> FieldOption {
+        self.field
+    }
+
+    pub(crate) fn ast_json(&self) -> &str {
+        &self.ast_json
+    }
+
+    pub(crate) fn ast<F: InterchangeField>(&self) -> Result<SBPIR<F, ()>, InterchangeError> {
+        from_json(&self.ast_json, "Circuit")
+    }
+
+    /// Returns the compiled circuit, that fails if it is not of `F`.
+    pub(crate) fn compiled<F: InterchangeField>(
+        &self,
+    ) -> Result<&CompiledCircuit<F>, InterchangeError> {
+        let field = self.field;
+        self.compiled
+            .downcast_ref()
+            .ok_or_else(|| wrong_field::<F>(field))
+    }
+
+    pub(crate) fn compiled_mut<F: InterchangeField>(
+        &mut self,
+    ) -> Result<&mut CompiledCircuit<F>, InterchangeError> {
+        let field = self.field;
+        self.compiled
+            .downcast_mut()
+            .ok_or_else(|| wrong_field::<F>(field))
+    }
+
+    /// Keys of the circuit, shared with the threads that prove it.
+    pub(crate) fn keys(&self) -> Arc<CircuitKeys> {
+        self.keys.clone()
+    }
+}
+
+fn wrong_field<F: InterchangeField>(field: FieldOption) -> InterchangeError {
+    InterchangeError::Value(format!(
+        "circuit of the field {}, not {}",
+        field.name(),
+        F::FIELD.name()
+    ))
+}
+
+/// Checks `witness` against `circuit` with the native `Checker`, see `Checker::check`. Step
+/// instances of step types that are not in the circuit fail, instead of panicking.
+pub fn check_witness<F: InterchangeField>(
+    circuit: &SBPIR<F, ()>,
+    witness: &TraceWitness<F>,
+) -> Result<Vec<Violation>, InterchangeError> {
+    panic::catch_unwind(AssertUnwindSafe(|| Checker::new(circuit).check(witness)))
+        .map_err(|payload| InterchangeError::Value(panic_message(payload)))
+}
+
+/// Name of the kind of a violation in the frontends.
+pub(crate) fn violation_kind_name(kind: ViolationKind) -> &'static str {
+    match kind {
+        ViolationKind::Constraint => "constraint",
+        ViolationKind::Transition => "transition",
+        ViolationKind::Lookup => "lookup",
+        ViolationKind::Padding => "padding",
+        ViolationKind::FirstStep => "first_step",
+        ViolationKind::LastStep => "last_step",
+        ViolationKind::NumSteps => "num_steps",
+        ViolationKind::StepType => "step_type",
+    }
+}
+
+/// Serializes a violation of `check_witness` with the same keys as the dicts of Python.
+pub(crate) fn violation_to_json(violation: &Violation) -> Value {
+    json!({
+        "message": violation.to_string(),
+        "kind": violation_kind_name(violation.kind),
+        "step": violation.step,
+        "step_type": violation.step_type,
+        "annotation": violation.annotation,
+        "location": violation.location.map(|location| location.to_string()),
+        "explanation": violation.explanation,
+    })
+}
+
+/// Formats a field element as a decimal integer, as the public inputs of a proof are returned to
+/// the frontends.
+pub(crate) fn field_to_decimal<F: InterchangeField>(value: &F) -> String {
+    BigUint::from_bytes_le(value.to_repr().as_ref()).to_string()
+}
+
+/// Parses a decimal integer below the modulus as a field element.
+pub(crate) fn field_from_decimal<F: InterchangeField>(digits: &str) -> Result<F, InterchangeError> {
+    let invalid = || InterchangeError::Value(format!("{} is not an element of the field", digits));
+
+    let value = BigUint::parse_bytes(digits.as_bytes(), 10).ok_or_else(invalid)?;
+    let mut repr = F::Repr::default();
+    let bytes = value.to_bytes_le();
+    if bytes.len() > repr.as_ref().len() {
+        return Err(invalid());
+    }
+    repr.as_mut()[..bytes.len()].copy_from_slice(&bytes);
+
+    Option::from(F::from_repr(repr)).ok_or_else(invalid)
+}
+
+/// Serializes the stats of a compiled circuit, see `ChiquitoHalo2::stats`.
+pub(crate) fn circuit_stats_to_json(stats: &CircuitStats) -> Value {
+    json!({
+        "advice_columns": stats.advice_columns,
+        "fixed_columns": stats.fixed_columns,
+        "rows": stats.rows,
+        "polys": stats.polys,
+        "lookups": stats.lookups,
+        "max_degree": stats.max_degree,
+        "max_advice_queries": stats.max_advice_queries,
+        "min_k": stats.min_k,
+    })
+}
+
+/// Whole super circuit AST, that a frontend serializes to a single JSON document: its
+/// sub-circuits and the lookup tables they share. Tables are sub-circuits without step types, with
+/// only fixed signals and their assignments. Sub-circuits query the signals of the others, and look
+/// up into the fixed signals of the tables, by their UUIDs.
+pub struct SuperCircuitAst<F> {
+    pub sub_circuits: Vec<SBPIR<F, ()>>,
+    pub tables: Vec<SBPIR<F, ()>>,
+}
+
+impl<F: InterchangeField> SuperCircuitAst<F> {
+    /// Compiles the sub-circuits and the tables to a super circuit with `config`. Returns the
+    /// assignment generators of the sub-circuits, in order.
+    pub fn compile<CM: CellManager, SSB: StepSelectorBuilder>(
+        self,
+        config: CompilerConfig<F, CM, SSB>,
+    ) -> (SuperCircuit<F, ()>, Vec<AssignmentGenerator<F, ()>>) {
+        let mut super_circuit_ctx = SuperCircuitContext::<F, ()>::default();
+
+        for table in self.tables {
+            super_circuit_ctx.sub_circuit_with_ast(config.clone(), table);
+        }
+        let assignment_generators = self
+            .sub_circuits
+            .into_iter()
+            .map(|sub_circuit| super_circuit_ctx.sub_circuit_with_ast(config.clone(), sub_circuit))
+            .collect();
+
+        (super_circuit_ctx.compile(), assignment_generators)
+    }
+}
+
+struct CircuitVisitor<F>(PhantomData<F>);
+
+impl<'de, F: InterchangeField> Visitor<'de> for CircuitVisitor<F> {
+    type Value = SBPIR<F, ()>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("struct Cricuit")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<SBPIR<F, ()>, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut step_types = None;
+        let mut forward_signals = None;
+        let mut shared_signals = None;
+        let mut fixed_signals = None;
+        let mut exposed = None;
+        let mut annotations = None;
+        let mut aliases = None;
+        let mut fixed_assignments = None;
+        let mut first_step = None;
+        let mut last_step = None;
+        let mut num_steps = None;
+        let mut q_enable = None;
+        let mut id = None;
+
+        println!("------ Visiting map -------");
+
+        while let Some(key) = map.next_key::<String>()? {
+            println!("key = {}", key);
+            match key.as_str() {
+                "step_types" => {
+                    println!("------ Visiting step_types -------");
+                    if step_types.is_some() {
+                        return Err(de::Error::duplicate_field("step_types"));
+                    }
+                    step_types = Some(map.next_value::<HashMap<UUID, StepType<F>>>()?);
+                    println!("step_types = {:#?}", step_types);
+                }
+                "forward_signals" => {
+                    if forward_signals.is_some() {
+                        return Err(de::Error::duplicate_field("forward_signals"));
+                    }
+                    forward_signals = Some(map.next_value::<Vec<ForwardSignal>>()?);
+                }
+                "shared_signals" => {
+                    if shared_signals.is_some() {
+                        return Err(de::Error::duplicate_field("shared_signals"));
+                    }
+                    shared_signals = Some(map.next_value::<Vec<SharedSignal>>()?);
+                }
+                "fixed_signals" => {
+                    if fixed_signals.is_some() {
+                        return Err(de::Error::duplicate_field("fixed_signals"));
+                    }
+                    fixed_signals = Some(map.next_value::<Vec<FixedSignal>>()?);
+                }
+                "exposed" => {
+                    if exposed.is_some() {
+                        return Err(de::Error::duplicate_field("exposed"));
+                    }
+                    exposed = Some(map.next_value::<Vec<(Queriable<F>, ExposeOffset)>>()?);
+                }
+                "annotations" => {
+                    if annotations.is_some() {
+                        return Err(de::Error::duplicate_field("annotations"));
+                    }
+                    annotations = Some(map.next_value::<HashMap<UUID, String>>()?);
+                }
+                "aliases" => {
+                    if aliases.is_some() {
+                        return Err(de::Error::duplicate_field("aliases"));
+                    }
+                    aliases = Some(map.next_value::<HashMap<UUID, Vec<String>>>()?);
+                }
+                "fixed_assignments" => {
+                    if fixed_assignments.is_some() {
+                        return Err(de::Error::duplicate_field("fixed_assignments"));
+                    }
+                    fixed_assignments = Some(map.next_value::<Option<
+                        HashMap<UUID, (Queriable<F>, Vec<FieldValue<F>>)>,
+                    >>()?);
+                }
+                "first_step" => {
+                    if first_step.is_some() {
+                        return Err(de::Error::duplicate_field("first_step"));
+                    }
+                    let first_step_opt: Option<String> = map.next_value()?; // Deserialize the value as an optional string
+                    first_step = Some(first_step_opt.map_or(Ok(None), |first_step_str| {
+                        StepTypeUUID::from_str_radix(&first_step_str, 10)
+                            .map(Some)
+                            .map_err(|e| {
+                                de::Error::custom(format!(
+                                    "Failed to parse first_step '{}': {}",
+                                    first_step_str, e
+                                ))
+                            })
+                    })?);
+                }
+                "last_step" => {
+                    if last_step.is_some() {
+                        return Err(de::Error::duplicate_field("last_step"));
+                    }
+                    let last_step_opt: Option<String> = map.next_value()?; // Deserialize the value as an optional string
+                    last_step = Some(last_step_opt.map_or(Ok(None), |last_step_str| {
+                        StepTypeUUID::from_str_radix(&last_step_str, 10)
+                            .map(Some)
+                            .map_err(|e| {
+                                de::Error::custom(format!(
+                                    "Failed to parse last_step '{}': {}",
+                                    last_step_str, e
+                                ))
+                            })
+                    })?);
+                }
+                "num_steps" => {
+                    if num_steps.is_some() {
+                        return Err(de::Error::duplicate_field("num_steps"));
+                    }
+                    num_steps = Some(map.next_value::<usize>()?);
+                }
+                "q_enable" => {
+                    if q_enable.is_some() {
+                        return Err(de::Error::duplicate_field("q_enable"));
+                    }
+                    q_enable = Some(map.next_value::<bool>()?);
+                }
+                "id" => {
+                    if id.is_some() {
+                        return Err(de::Error::duplicate_field("id"));
+                    }
+                    let id_str: String = map.next_value()?;
+                    id = Some(id_str.parse::<u128>().map_err(|e| {
+                        de::Error::custom(format!("Failed to parse id '{}': {}", id_str, e))
+                    })?);
+                }
+                _ => {
+                    return Err(de::Error::unknown_field(
+                        &key,
+                        &[
+                            "step_types",
+                            "forward_signals",
+                            "shared_signals",
+                            "fixed_signals",
+                            "exposed",
+                            "annotations",
+                            "aliases",
+                            "fixed_assignments",
+                            "first_step",
+                            "last_step",
+                            "num_steps",
+                            "q_enable",
+                            "id",
+                        ],
+                    ))
+                }
+            }
+        }
+        let step_types = step_types
+            .ok_or_else(|| de::Error::missing_field("step_types"))?
+            .into_iter()
+            .map(|(k, v)| (k, Rc::new(v)))
+            .collect();
+        let forward_signals =
+            forward_signals.ok_or_else(|| de::Error::missing_field("forward_signals"))?;
+        let shared_signals =
+            shared_signals.ok_or_else(|| de::Error::missing_field("shared_signals"))?;
+        let fixed_signals =
+            fixed_signals.ok_or_else(|| de::Error::missing_field("fixed_signals"))?;
+        let exposed = exposed.ok_or_else(|| de::Error::missing_field("exposed"))?;
+        let annotations = annotations.ok_or_else(|| de::Error::missing_field("annotations"))?;
+        // aliases are optional, as most circuits have none
+        let aliases = aliases.unwrap_or_default();
+        let first_step = first_step.ok_or_else(|| de::Error::missing_field("first_step"))?;
+        let last_step = last_step.ok_or_else(|| de::Error::missing_field("last_step"))?;
+        let num_steps = num_steps.ok_or_else(|| de::Error::missing_field("num_steps"))?;
+        let fixed_assignments = fixed_assignments
+            .ok_or_else(|| de::Error::missing_field("fixed_assignments"))?
+            .map(|inner| fixed_assignment(inner, &fixed_signals, num_steps))
+            .transpose()
+            .map_err(de::Error::custom)?;
+        let q_enable = q_enable.ok_or_else(|| de::Error::missing_field("q_enable"))?;
+        let id = id.ok_or_else(|| de::Error::missing_field("id"))?;
+
+        Ok(SBPIR {
+            step_types,
+            forward_signals,
+            shared_signals,
+            fixed_signals,
+            halo2_advice: Default::default(),
+            halo2_fixed: Default::default(),
+            exposed,
+            num_steps,
+            annotations,
+            aliases,
+            trace: Some(Rc::new(|_: &mut TraceContext<_>, _: _| {})),
+            fixed_assignments,
+            first_step,
+            last_step,
+            padding_step: None,
+            q_enable,
+            id,
+        })
+    }
+}
+
+/// Checks the fixed assignments of a circuit from a frontend, that the compiler would otherwise
+/// panic on: every column is a fixed signal of the circuit without rotation, keyed by its id, with
+/// a value per step.
+fn fixed_assignment<F: InterchangeField>(
+    assignments: HashMap<UUID, (Queriable<F>, Vec<FieldValue<F>>)>,
+    fixed_signals: &[FixedSignal],
+    num_steps: usize,
+) -> Result<FixedAssignment<F>, String> {
+    assignments
+        .into_iter()
+        .map(|(id, (queriable, values))| {
+            let signal = match queriable {
+                Queriable::Fixed(signal, 0) => signal,
+                Queriable::Fixed(signal, rotation) => {
+                    return Err(format!(
+                        "fixed assignment of {} with rotation {}, instead of 0",
+                        signal.annotation(),
+                        rotation
+                    ))
+                }
+                _ => {
+                    return Err(format!(
+                        "fixed assignment of the non-fixed signal {}",
+                        queriable.annotation()
+                    ))
+                }
+            };
+            if signal.uuid() != id {
+                return Err(format!(
+                    "fixed assignment of {} under the id {} of another signal",
+                    signal.annotation(),
+                    id
+                ));
+            }
+            if !fixed_signals.iter().any(|fixed| fixed.uuid() == id) {
+                return Err(format!(
+                    "fixed assignment of {}, that is not a fixed signal of the circuit",
+                    signal.annotation()
+                ));
+            }
+            if values.len() != num_steps {
+                return Err(format!(
+                    "fixed assignment of {} with {} values, instead of one per step of the {}",
+                    signal.annotation(),
+                    values.len(),
+                    num_steps
+                ));
+            }
+
+            let values = values.into_iter().map(|FieldValue(value)| value).collect();
+            Ok((queriable, values))
+        })
+        .collect()
+}
+
+struct SuperCircuitAstVisitor<F>(PhantomData<F>);
+
+impl<'de, F: InterchangeField> Visitor<'de> for SuperCircuitAstVisitor<F> {
+    type Value = SuperCircuitAst<F>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("struct SuperCircuitAst")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<SuperCircuitAst<F>, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut sub_circuits = None;
+        let mut tables = None;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "sub_circuits" => {
+                    if sub_circuits.is_some() {
+                        return Err(de::Error::duplicate_field("sub_circuits"));
+                    }
+                    sub_circuits = Some(map.next_value::<Vec<SBPIR<F, ()>>>()?);
+                }
+                "tables" => {
+                    if tables.is_some() {
+                        return Err(de::Error::duplicate_field("tables"));
+                    }
+                    tables = Some(map.next_value::<Vec<SBPIR<F, ()>>>()?);
+                }
+                _ => return Err(de::Error::unknown_field(&key, &["sub_circuits", "tables"])),
+            }
+        }
+        let sub_circuits = sub_circuits.ok_or_else(|| de::Error::missing_field("sub_circuits"))?;
+        // tables are optional, as most super circuits have none
+        let tables: Vec<SBPIR<F, ()>> = tables.unwrap_or_default();
+        if let Some(table) = tables.iter().find(|table| !table.step_types.is_empty()) {
+            return Err(de::Error::custom(format!(
+                "table {} has step types",
+                table.id
+            )));
+        }
+
+        Ok(SuperCircuitAst {
+            sub_circuits,
+            tables,
+        })
+    }
+}
+
+struct StepTypeVisitor<F>(PhantomData<F>);
+
+impl<'de, F: InterchangeField> Visitor<'de> for StepTypeVisitor<F> {
+    type Value = StepType<F>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("struct StepType")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<StepType<F>, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut id = None;
+        let mut name = None;
+        let mut signals = None;
+        let mut constraints = None;
+        let mut transition_constraints = None;
+        let mut lookups = None;
+        let mut annotations = None;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "id" => {
+                    if id.is_some() {
+                        return Err(de::Error::duplicate_field("id"));
+                    }
+                    let id_str: String = map.next_value()?;
+                    id = Some(id_str.parse::<u128>().map_err(|e| {
+                        de::Error::custom(format!("Failed to parse id '{}': {}", id_str, e))
+                    })?);
+                }
+                "name" => {
+                    if name.is_some() {
+                        return Err(de::Error::duplicate_field("name"));
+                    }
+                    name = Some(map.next_value::<String>()?);
+                }
+                "signals" => {
+                    if signals.is_some() {
+                        return Err(de::Error::duplicate_field("signals"));
+                    }
+                    signals = Some(map.next_value::<Vec<InternalSignal>>()?);
+                }
+                "constraints" => {
+                    if constraints.is_some() {
+                        return Err(de::Error::duplicate_field("constraints"));
+                    }
+                    constraints = Some(map.next_value::<Vec<Constraint<F>>>()?);
+                }
+                "transition_constraints" => {
+                    if transition_constraints.is_some() {
+                        return Err(de::Error::duplicate_field("transition_constraints"));
+                    }
+                    transition_constraints =
+                        Some(map.next_value::<Vec<TransitionConstraint<F>>>()?);
+                }
+                "lookups" => {
+                    if lookups.is_some() {
+                        return Err(de::Error::duplicate_field("lookups"));
+                    }
+                    lookups = Some(map.next_value::<Vec<Lookup<F>>>()?);
+                }
+                "annotations" => {
+                    if annotations.is_some() {
+                        return Err(de::Error::duplicate_field("annotations"));
+                    }
+                    annotations = Some(map.next_value::<HashMap<UUID, String>>()?);
+                }
+                _ => {
+                    return Err(de::Error::unknown_field(
+                        &key,
+                        &[
+                            "id",
+                            "name",
+                            "signals",
+                            "constraints",
+                            "transition_constraints",
+                            "lookups",
+                            "annotations",
+                        ],
+                    ))
+                }
+            }
+        }
+        let id = id.ok_or_else(|| de::Error::missing_field("id"))?;
+        let name = name.ok_or_else(|| de::Error::missing_field("name"))?;
+        let signals = signals.ok_or_else(|| de::Error::missing_field("signals"))?;
+        let constraints = constraints.ok_or_else(|| de::Error::missing_field("constraints"))?;
+        let transition_constraints = transition_constraints
+            .ok_or_else(|| de::Error::missing_field("transition_constraints"))?;
+        let lookups = lookups.ok_or_else(|| de::Error::missing_field("lookups"))?;
+        let annotations = annotations.ok_or_else(|| de::Error::missing_field("annotations"))?;
+
+        let mut step_type = StepType::<F>::new(id, name);
+        step_type.signals = signals;
+        step_type.constraints = constraints;
+        step_type.transition_constraints = transition_constraints;
+        step_type.lookups = lookups;
+        step_type.annotations = annotations;
+
+        Ok(step_type)
+    }
+}
+
+macro_rules! impl_visitor_constraint_transition {
+    ($name:ident, $type:ty, $display:expr) => {
+        struct $name<F>(PhantomData<F>);
+
+        impl<'de, F: InterchangeField> Visitor<'de> for $name<F> {
+            type Value = $type;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str($display)
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<$type, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut annotation = None;
+                let mut expr = None;
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "annotation" => {
+                            if annotation.is_some() {
+                                return Err(de::Error::duplicate_field("annotation"));
+                            }
+                            annotation = Some(map.next_value::<String>()?);
+                        }
+                        "expr" => {
+                            if expr.is_some() {
+                                return Err(de::Error::duplicate_field("expr"));
+                            }
+                            expr = Some(map.next_value::<Expr<F, Queriable<F>>>()?);
+                        }
+                        _ => return Err(de::Error::unknown_field(&key, &["annotation", "expr"])),
+                    }
+                }
+                let annotation =
+                    annotation.ok_or_else(|| de::Error::missing_field("annotation"))?;
+                let expr = expr.ok_or_else(|| de::Error::missing_field("expr"))?;
+                Ok(Self::Value {
+                    annotation,
+                    expr,
+                    location: None,
+                    feature: None,
+                })
+            }
+        }
+    };
+}
+
+impl_visitor_constraint_transition!(ConstraintVisitor, Constraint<F>, "struct Constraint");
+impl_visitor_constraint_transition!(
+    TransitionConstraintVisitor,
+    TransitionConstraint<F>,
+    "struct TransitionConstraint"
+);
+
+struct LookupVisitor<F>(PhantomData<F>);
+
+impl<'de, F: InterchangeField> Visitor<'de> for LookupVisitor<F> {
+    type Value = Lookup<F>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("struct Lookup")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Lookup<F>, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut annotation = None;
+        let mut exprs = None;
+        let mut enable = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "annotation" => {
+                    if annotation.is_some() {
+                        return Err(de::Error::duplicate_field("annotation"));
+                    }
+                    annotation = Some(map.next_value::<String>()?);
+                }
+                "exprs" => {
+                    if exprs.is_some() {
+                        return Err(de::Error::duplicate_field("exprs"));
+                    }
+                    exprs = Some(map.next_value::<Vec<(Constraint<F>, Expr<F, Queriable<F>>)>>()?);
+                }
+                "enable" => {
+                    if enable.is_some() {
+                        return Err(de::Error::duplicate_field("enable"));
+                    }
+                    enable = Some(map.next_value::<Option<Constraint<F>>>()?);
+                }
+                _ => {
+                    return Err(de::Error::unknown_field(
+                        &key,
+                        &["annotation", "exprs", "enable"],
+                    ))
+                }
+            }
+        }
+        let annotation = annotation.ok_or_else(|| de::Error::missing_field("annotation"))?;
+        let exprs = exprs.ok_or_else(|| de::Error::missing_field("exprs"))?;
+        let enable = enable.ok_or_else(|| de::Error::missing_field("enable"))?;
+        Ok(Self::Value {
+            annotation,
+            exprs,
+            enable,
+            feature: None,
+        })
+    }
+}
+
+struct ExprVisitor<F>(PhantomData<F>);
+
+impl<'de, F: InterchangeField> Visitor<'de> for ExprVisitor<F> {
+    type Value = Expr<F, Queriable<F>>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("enum Expr")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Expr<F, Queriable<F>>, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let key: String = map
+            .next_key()?
+            .ok_or_else(|| de::Error::custom("map is empty"))?;
+        match key.as_str() {
+            "Const" => map.next_value().map(|FieldValue(value)| Expr::Const(value)),
+            "Sum" => map.next_value().map(Expr::Sum),
+            "Mul" => map.next_value().map(Expr::Mul),
+            "Neg" => map.next_value().map(Expr::Neg),
+            "Pow" => map.next_value().map(|(expr, pow)| Expr::Pow(expr, pow)),
+            "Challenge" => map
+                .next_value()
+                .map(|(id, phase)| Expr::Challenge(id, phase)),
+            "Internal" => map
+                .next_value()
+                .map(|signal| Expr::Query(Queriable::Internal(signal))),
+            "Forward" => map
+                .next_value()
+                .map(|(signal, rotation)| Expr::Query(Queriable::Forward(signal, rotation))),
+            "Shared" => map
+                .next_value()
+                .map(|(signal, rotation)| Expr::Query(Queriable::Shared(signal, rotation))),
+            "Fixed" => map
+                .next_value()
+                .map(|(signal, rotation)| Expr::Query(Queriable::Fixed(signal, rotation))),
+            "StepTypeNext" => map
+                .next_value()
+                .map(|step_type| Expr::Query(Queriable::StepTypeNext(step_type))),
+            _ => Err(de::Error::unknown_variant(
+                &key,
+                &[
+                    "Const",
+                    "Sum",
+                    "Mul",
+                    "Neg",
+                    "Pow",
+                    "Challenge",
+                    "Internal",
+                    "Forward",
+                    "Shared",
+                    "Fixed",
+                    "StepTypeNext",
+                ],
+            )),
+        }
+    }
+}
+
+struct QueriableVisitor<F>(PhantomData<F>);
+
+impl<'de, F: InterchangeField> Visitor<'de> for QueriableVisitor<F> {
+    type Value = Queriable<F>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("enum Queriable")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Queriable<F>, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let key: String = map
+            .next_key()?
+            .ok_or_else(|| de::Error::custom("map is empty"))?;
+
+        match key.as_str() {
+            "Internal" => map.next_value().map(Queriable::Internal),
+            "Forward" => map
+                .next_value()
+                .map(|(signal, rotation)| Queriable::Forward(signal, rotation)),
+            "Shared" => map
+                .next_value()
+                .map(|(signal, rotation)| Queriable::Shared(signal, rotation)),
+            "Fixed" => {
+                println!("Processing Fixed");
+                map.next_value()
+                    .map(|(signal, rotation)| Queriable::Fixed(signal, rotation))
+            }
+            "StepTypeNext" => map.next_value().map(Queriable::StepTypeNext),
+            _ => Err(de::Error::unknown_variant(
+                &key,
+                &["Internal", "Forward", "Shared", "Fixed", "StepTypeNext"],
+            )),
+        }
+    }
+}
+
+struct ExposeOffsetVisitor;
+
+impl<'de> Visitor<'de> for ExposeOffsetVisitor {
+    type Value = ExposeOffset;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("enum ExposeOffset")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<ExposeOffset, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let key: String = map
+            .next_key()?
+            .ok_or_else(|| de::Error::custom("map is empty"))?;
+        match key.as_str() {
+            "First" => {
+                let _ = map.next_value::<IgnoredAny>()?;
+                Ok(ExposeOffset::First)
+            }
+            "Last" => {
+                let _ = map.next_value::<IgnoredAny>()?;
+                Ok(ExposeOffset::Last)
+            }
+            "Step" => map.next_value().map(ExposeOffset::Step),
+            _ => Err(de::Error::unknown_variant(&key, &["First", "Last", "Step"])),
+        }
+    }
+}
+
+macro_rules! impl_visitor_internal_fixed_steptypehandler {
+    ($name:ident, $type:ty, $display:expr) => {
+        struct $name;
+
+        impl<'de> Visitor<'de> for $name {
+            type Value = $type;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str($display)
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<$type, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut id = None;
+                let mut annotation = None;
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "id" => {
+                            if id.is_some() {
+                                return Err(de::Error::duplicate_field("id"));
+                            }
+                            let id_str: String = map.next_value()?; // Get the UUID as a string
+                            id = Some(id_str.parse::<u128>().map_err(|e| {
+                                de::Error::custom(format!("Failed to parse id '{}': {}", id_str, e))
+                            })?);
+                        }
+                        "annotation" => {
+                            if annotation.is_some() {
+                                return Err(de::Error::duplicate_field("annotation"));
+                            }
+                            annotation = Some(map.next_value::<String>()?);
+                        }
+                        _ => return Err(de::Error::unknown_field(&key, &["id", "annotation"])),
+                    }
+                }
+                let id = id.ok_or_else(|| de::Error::missing_field("id"))?;
+                let annotation =
+                    annotation.ok_or_else(|| de::Error::missing_field("annotation"))?;
+                Ok(<$type>::new_with_id(id, annotation))
+            }
+        }
+    };
+}
+
+impl_visitor_internal_fixed_steptypehandler!(
+    InternalSignalVisitor,
+    InternalSignal,
+    "struct InternalSignal"
+);
+impl_visitor_internal_fixed_steptypehandler!(FixedSignalVisitor, FixedSignal, "struct FixedSignal");
+impl_visitor_internal_fixed_steptypehandler!(
+    StepTypeHandlerVisitor,
+    StepTypeHandler,
+    "struct StepTypeHandler"
+);
+
+macro_rules! impl_visitor_forward_shared {
+    ($name:ident, $type:ty, $display:expr) => {
+        struct $name;
+
+        impl<'de> Visitor<'de> for $name {
+            type Value = $type;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str($display)
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<$type, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut id = None;
+                let mut phase = None;
+                let mut annotation = None;
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "id" => {
+                            if id.is_some() {
+                                return Err(de::Error::duplicate_field("id"));
+                            }
+                            let id_str: String = map.next_value()?; // Get the UUID as a string
+                            id = Some(id_str.parse::<u128>().map_err(|e| {
+                                de::Error::custom(format!("Failed to parse id '{}': {}", id_str, e))
+                            })?);
+                        }
+                        "phase" => {
+                            if phase.is_some() {
+                                return Err(de::Error::duplicate_field("phase"));
+                            }
+                            phase = Some(map.next_value()?);
+                        }
+                        "annotation" => {
+                            if annotation.is_some() {
+                                return Err(de::Error::duplicate_field("annotation"));
+                            }
+                            annotation = Some(map.next_value::<String>()?);
+                        }
+                        _ => {
+                            return Err(de::Error::unknown_field(
+                                &key,
+                                &["id", "phase", "annotation"],
+                            ))
+                        }
+                    }
+                }
+                let id = id.ok_or_else(|| de::Error::missing_field("id"))?;
+                let phase = phase.ok_or_else(|| de::Error::missing_field("phase"))?;
+                let annotation =
+                    annotation.ok_or_else(|| de::Error::missing_field("annotation"))?;
+                Ok(<$type>::new_with_id(id, phase, annotation))
+            }
+        }
+    };
+}
+
+impl_visitor_forward_shared!(ForwardSignalVisitor, ForwardSignal, "struct ForwardSignal");
+impl_visitor_forward_shared!(SharedSignalVisitor, SharedSignal, "struct SharedSignal");
+
+struct TraceWitnessVisitor<F>(PhantomData<F>);
+
+impl<'de, F: InterchangeField> Visitor<'de> for TraceWitnessVisitor<F> {
+    type Value = TraceWitness<F>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("struct TraceWitness")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<TraceWitness<F>, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut step_instances = None;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "step_instances" => {
+                    if step_instances.is_some() {
+                        return Err(de::Error::duplicate_field("step_instances"));
+                    }
+                    step_instances = Some(map.next_value()?);
+                }
+                _ => return Err(de::Error::unknown_field(&key, &["step_instances"])),
+            }
+        }
+        let step_instances =
+            step_instances.ok_or_else(|| de::Error::missing_field("step_instances"))?;
+        Ok(Self::Value { step_instances })
+    }
+}
+
+struct StepInstanceVisitor<F>(PhantomData<F>);
+
+impl<'de, F: InterchangeField> Visitor<'de> for StepInstanceVisitor<F> {
+    type Value = StepInstance<F>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("struct StepInstance")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<StepInstance<F>, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut step_type_uuid = None;
+        let mut assignments = None;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "step_type_uuid" => {
+                    if step_type_uuid.is_some() {
+                        return Err(de::Error::duplicate_field("step_type_uuid"));
+                    }
+                    let uuid_str: String = map.next_value()?; // Get the UUID as a string
+                    step_type_uuid = Some(
+                        uuid_str
+                            .parse::<u128>() // Assuming the string is in decimal format
+                            .map_err(de::Error::custom)?,
+                    );
+                }
+                "assignments" => {
+                    if assignments.is_some() {
+                        return Err(de::Error::duplicate_field("assignments"));
+                    }
+                    assignments =
+                        Some(map.next_value::<HashMap<UUID, (Queriable<F>, FieldValue<F>)>>()?);
+                }
+                _ => {
+                    return Err(de::Error::unknown_field(
+                        &key,
+                        &["step_type_uuid", "assignments"],
+                    ))
+                }
+            }
+        }
+        let step_type_uuid =
+            step_type_uuid.ok_or_else(|| de::Error::missing_field("step_type_uuid"))?;
+
+        let assignments: HashMap<Queriable<F>, F> = assignments
+            .ok_or_else(|| de::Error::missing_field("assignments"))?
+            .into_values()
+            .map(|(queriable, FieldValue(value))| (queriable, value))
+            .collect();
+
+        Ok(Self::Value {
+            step_type_uuid,
+            assignments,
+        })
+    }
+}
+
+macro_rules! impl_deserialize {
+    ($name:ident, $type:ty) => {
+        impl<'de> Deserialize<'de> for $type {
+            fn deserialize<D>(deserializer: D) -> Result<$type, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                deserializer.deserialize_map($name)
+            }
+        }
+    };
+    ($name:ident<F>, $type:ty) => {
+        impl<'de, F: InterchangeField> Deserialize<'de> for $type {
+            fn deserialize<D>(deserializer: D) -> Result<$type, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                deserializer.deserialize_map($name(PhantomData))
+            }
+        }
+    };
+}
+
+impl_deserialize!(ExprVisitor<F>, Expr<F, Queriable<F>>);
+impl_deserialize!(QueriableVisitor<F>, Queriable<F>);
+impl_deserialize!(ExposeOffsetVisitor, ExposeOffset);
+impl_deserialize!(InternalSignalVisitor, InternalSignal);
+impl_deserialize!(FixedSignalVisitor, FixedSignal);
+impl_deserialize!(ForwardSignalVisitor, ForwardSignal);
+impl_deserialize!(SharedSignalVisitor, SharedSignal);
+impl_deserialize!(StepTypeHandlerVisitor, StepTypeHandler);
+impl_deserialize!(ConstraintVisitor<F>, Constraint<F>);
+impl_deserialize!(TransitionConstraintVisitor<F>, TransitionConstraint<F>);
+impl_deserialize!(StepTypeVisitor<F>, StepType<F>);
+impl_deserialize!(TraceWitnessVisitor<F>, TraceWitness<F>);
+impl_deserialize!(StepInstanceVisitor<F>, StepInstance<F>);
+impl_deserialize!(LookupVisitor<F>, Lookup<F>);
+impl_deserialize!(SuperCircuitAstVisitor<F>, SuperCircuitAst<F>);
+
+impl<'de, F: InterchangeField> Deserialize<'de> for SBPIR<F, ()> {
+    fn deserialize<D>(deserializer: D) -> Result<SBPIR<F, ()>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(CircuitVisitor(PhantomData))
+    }
+}
+
+/// Field element in the JSON of the frontends: the hex of the little endian bytes of its Montgomery
+/// form, with a Montgomery factor of 2^256.
+pub(crate) struct FieldValue<F>(pub(crate) F);
+
+impl<'de, F: InterchangeField> Deserialize<'de> for FieldValue<F> {
+    fn deserialize<D>(deserializer: D) -> Result<FieldValue<F>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let hex = String::deserialize(deserializer)?;
+        let invalid =
+            || -> D::Error { de::Error::custom(format!("invalid field element '{}'", hex)) };
+
+        let mut repr = F::Repr::default();
+        if hex.len() != 2 * repr.as_ref().len() {
+            return Err(invalid());
+        }
+        for (byte, digits) in repr.as_mut().iter_mut().zip(hex.as_bytes().chunks(2)) {
+            *byte = std::str::from_utf8(digits)
+                .ok()
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .ok_or_else(invalid)?;
+        }
+        let montgomery: F = Option::from(F::from_repr(repr)).ok_or_else(invalid)?;
+
+        Ok(FieldValue(montgomery * F::montgomery_inv()))
+    }
+}
+
+impl<F: InterchangeField> FieldValue<F> {
+    /// Returns the JSON of the value, as `Deserialize` takes it.
+    pub(crate) fn to_hex(&self) -> String {
+        let montgomery = self.0 * F::montgomery_inv().invert().unwrap();
+
+        montgomery
+            .to_repr()
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+// Serialization of the types above back to the JSON they are deserialized from. The locations
+// and features of constraints and lookups are not in the JSON, so they are dropped, and halo2
+// expressions, queries and multiplicative inverses, that the frontends cannot express, fail.
+
+pub(crate) fn sbpir_to_json<F: InterchangeField>(circuit: &SBPIR<F, ()>) -> Result<Value, String> {
+    let step_types = circuit
+        .step_types
+        .iter()
+        .map(|(id, step_type)| Ok((id.to_string(), step_type_to_json(step_type)?)))
+        .collect::<Result<serde_json::Map<_, _>, String>>()?;
+    let exposed = circuit
+        .exposed
+        .iter()
+        .map(|(queriable, offset)| {
+            let offset = match offset {
+                ExposeOffset::First => json!({ "First": 0 }),
+                ExposeOffset::Last => json!({ "Last": -1 }),
+                ExposeOffset::Step(step) => json!({ "Step": step }),
+            };
+            Ok(json!([queriable_to_json(queriable)?, offset]))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let fixed_assignments = circuit
+        .fixed_assignments
+        .as_ref()
+        .map(|assignments| {
+            assignments
+                .iter()
+                .map(|(queriable, values)| {
+                    let values: Vec<_> = values.iter().map(|v| FieldValue(*v).to_hex()).collect();
+                    Ok((
+                        queriable.uuid().to_string(),
+                        json!([queriable_to_json(queriable)?, values]),
+                    ))
+                })
+                .collect::<Result<serde_json::Map<_, _>, String>>()
+        })
+        .transpose()?;
+
+    Ok(json!({
+        "step_types": step_types,
+        "forward_signals": to_json_vec(&circuit.forward_signals, forward_signal_to_json),
+        "shared_signals": to_json_vec(&circuit.shared_signals, shared_signal_to_json),
+        "fixed_signals": to_json_vec(&circuit.fixed_signals, fixed_signal_to_json),
+        "exposed": exposed,
+        "annotations": ids_to_json(&circuit.annotations),
+        "aliases": ids_to_json(&circuit.aliases),
+        "fixed_assignments": fixed_assignments,
+        "first_step": circuit.first_step.map(|id| id.to_string()),
+        "last_step": circuit.last_step.map(|id| id.to_string()),
+        "num_steps": circuit.num_steps,
+        "q_enable": circuit.q_enable,
+        "id": circuit.id.to_string(),
+    }))
+}
+
+fn step_type_to_json<F: InterchangeField>(step_type: &StepType<F>) -> Result<Value, String> {
+    let constraints = step_type
+        .constraints
+        .iter()
+        .map(|constraint| constraint_to_json(&constraint.annotation, &constraint.expr))
+        .collect::<Result<Vec<_>, String>>()?;
+    let transition_constraints = step_type
+        .transition_constraints
+        .iter()
+        .map(|constraint| constraint_to_json(&constraint.annotation, &constraint.expr))
+        .collect::<Result<Vec<_>, String>>()?;
+    let lookups = step_type
+        .lookups
+        .iter()
+        .map(|lookup| {
+            let exprs = lookup
+                .exprs
+                .iter()
+                .map(|(constraint, expr)| {
+                    Ok(json!([
+                        constraint_to_json(&constraint.annotation, &constraint.expr)?,
+                        sbpir_expr_to_json(expr)?
+                    ]))
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            let enable = lookup
+                .enable
+                .as_ref()
+                .map(|enable| constraint_to_json(&enable.annotation, &enable.expr))
+                .transpose()?;
+            Ok(json!({
+                "annotation": lookup.annotation,
+                "exprs": exprs,
+                "enable": enable,
+            }))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(json!({
+        "id": step_type.uuid().to_string(),
+        "name": step_type.name,
+        "signals": to_json_vec(&step_type.signals, internal_signal_to_json),
+        "constraints": constraints,
+        "transition_constraints": transition_constraints,
+        "lookups": lookups,
+        "annotations": ids_to_json(&step_type.annotations),
+    }))
+}
+
+fn constraint_to_json<F: InterchangeField>(
+    annotation: &str,
+    expr: &Expr<F, Queriable<F>>,
+) -> Result<Value, String> {
+    Ok(json!({
+        "annotation": annotation,
+        "expr": sbpir_expr_to_json(expr)?,
+    }))
+}
+
+pub(crate) fn sbpir_expr_to_json<F: InterchangeField>(
+    expr: &Expr<F, Queriable<F>>,
+) -> Result<Value, String> {
+    expr_to_json(expr, &queriable_to_json)
+}
+
+/// Serializes `expr`, with `query` serializing its queries.
+fn expr_to_json<F: InterchangeField, V>(
+    expr: &Expr<F, V>,
+    query: &impl Fn(&V) -> Result<Value, String>,
+) -> Result<Value, String> {
+    let exprs_to_json = |exprs: &[Expr<F, V>]| {
+        exprs
+            .iter()
+            .map(|expr| expr_to_json(expr, query))
+            .collect::<Result<Vec<_>, String>>()
+    };
+
+    Ok(match expr {
+        Expr::Const(value) => json!({ "Const": FieldValue(*value).to_hex() }),
+        Expr::Sum(exprs) => json!({ "Sum": exprs_to_json(exprs)? }),
+        Expr::Mul(exprs) => json!({ "Mul": exprs_to_json(exprs)? }),
+        Expr::Neg(expr) => json!({ "Neg": expr_to_json(expr, query)? }),
+        Expr::Pow(expr, pow) => json!({ "Pow": [expr_to_json(expr, query)?, pow] }),
+        Expr::Query(queriable) => query(queriable)?,
+        Expr::Challenge(id, phase) => {
+            // JSON numbers of serde_json are at most 64 bits
+            let id = u64::try_from(*id)
+                .map_err(|_| format!("cannot serialize challenge {}, above 64 bits", id))?;
+            json!({ "Challenge": [id, phase] })
+        }
+        Expr::Halo2Expr(expr) => {
+            return Err(format!("cannot serialize halo2 expression {:?}", expr))
+        }
+        Expr::MI(_) => return Err("cannot serialize multiplicative inverse".to_string()),
+    })
+}
+
+fn queriable_to_json<F>(queriable: &Queriable<F>) -> Result<Value, String> {
+    Ok(match queriable {
+        Queriable::Internal(signal) => json!({ "Internal": internal_signal_to_json(signal) }),
+        Queriable::Forward(signal, next) => {
+            json!({ "Forward": [forward_signal_to_json(signal), next] })
+        }
+        Queriable::Shared(signal, rotation) => {
+            json!({ "Shared": [shared_signal_to_json(signal), rotation] })
+        }
+        Queriable::Fixed(signal, rotation) => {
+            json!({ "Fixed": [fixed_signal_to_json(signal), rotation] })
+        }
+        Queriable::StepTypeNext(step_type) => json!({
+            "StepTypeNext": {
+                "id": step_type.uuid().to_string(),
+                "annotation": step_type.annotation(),
+            }
+        }),
+        _ => {
+            return Err(format!(
+                "cannot serialize halo2 query {}",
+                queriable.annotation()
+            ))
+        }
+    })
+}
+
+fn internal_signal_to_json(signal: &InternalSignal) -> Value {
+    json!({ "id": signal.uuid().to_string(), "annotation": signal.annotation() })
+}
+
+fn fixed_signal_to_json(signal: &FixedSignal) -> Value {
+    json!({ "id": signal.uuid().to_string(), "annotation": signal.annotation() })
+}
+
+fn forward_signal_to_json(signal: &ForwardSignal) -> Value {
+    json!({
+        "id": signal.uuid().to_string(),
+        "phase": signal.phase(),
+        "annotation": signal.annotation(),
+    })
+}
+
+fn shared_signal_to_json(signal: &SharedSignal) -> Value {
+    json!({
+        "id": signal.uuid().to_string(),
+        "phase": signal.phase(),
+        "annotation": signal.annotation(),
+    })
+}
+
+fn to_json_vec<T>(items: &[T], to_json: impl Fn(&T) -> Value) -> Vec<Value> {
+    items.iter().map(to_json).collect()
+}
+
+/// Serializes a map by UUID, whose keys are strings in JSON.
+fn ids_to_json<T: serde::Serialize>(map: &HashMap<UUID, T>) -> Value {
+    map.iter()
+        .map(|(id, value)| (id.to_string(), json!(value)))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+/// Serializes the plonkish IR of a compiled circuit, and the placement of its signals, `null` if it
+/// has no assigner. Columns are referred to by their ids in the polys, lookups, fixed assignments,
+/// exposed cells, copies and placement.
+pub(crate) fn plonkish_to_json<F: InterchangeField>(
+    circuit: &Circuit<F>,
+    placement: Option<&Placement>,
+) -> Result<Value, String> {
+    let columns: Vec<_> = circuit
+        .columns
+        .iter()
+        .map(|column| {
+            let ctype = match column.ctype {
+                ColumnType::Advice => "advice",
+                ColumnType::Fixed => "fixed",
+                ColumnType::Halo2Advice => "halo2_advice",
+                ColumnType::Halo2Fixed => "halo2_fixed",
+            };
+            json!({
+                "id": column.uuid().to_string(),
+                "annotation": column.annotation,
+                "ctype": ctype,
+                "phase": column.phase,
+            })
+        })
+        .collect();
+    let polys = circuit
+        .polys
+        .iter()
+        .map(|poly| {
+            Ok(json!({
+                "annotation": poly.annotation,
+                "expr": poly_expr_to_json(&poly.expr)?,
+            }))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let lookups = circuit
+        .lookups
+        .iter()
+        .map(|lookup| {
+            let exprs = lookup
+                .exprs
+                .iter()
+                .map(|(src, dest)| Ok(json!([poly_expr_to_json(src)?, poly_expr_to_json(dest)?])))
+                .collect::<Result<Vec<_>, String>>()?;
+            Ok(json!({
+                "annotation": lookup.annotation,
+                "exprs": exprs,
+            }))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let fixed_assignments: serde_json::Map<_, _> = circuit
+        .fixed_assignments
+        .iter()
+        .map(|(column, values)| {
+            let values: Vec<_> = values.iter().map(|v| FieldValue(*v).to_hex()).collect();
+            (column.uuid().to_string(), json!(values))
+        })
+        .collect();
+    let cell = |(column, row): &(Column, i32)| json!([column.uuid().to_string(), row]);
+
+    Ok(json!({
+        "columns": columns,
+        "exposed": to_json_vec(&circuit.exposed, cell),
+        "polys": polys,
+        "lookups": lookups,
+        "fixed_assignments": fixed_assignments,
+        "num_rows": circuit.num_rows,
+        "copies": to_json_vec(&circuit.copies, |(from, to)| json!([cell(from), cell(to)])),
+        "id": circuit.id.to_string(),
+        "ast_id": circuit.ast_id.to_string(),
+        "placement": placement.map(placement_to_json),
+    }))
+}
+
+/// Serializes the placement of the signals, each by its id, with its annotation, column and
+/// rotation from the first row of the step. The internal signals are by step type, with its height
+/// in rows.
+fn placement_to_json(placement: &Placement) -> Value {
+    let steps: serde_json::Map<_, _> = placement
+        .steps
+        .keys()
+        .map(|step_uuid| {
+            let signals = placement
+                .internal_signals(*step_uuid)
+                .into_iter()
+                .map(|internal| {
+                    let cell = placement.find_internal_signal_placement(*step_uuid, &internal);
+                    (internal.uuid(), internal.annotation(), cell)
+                });
+            let step = json!({
+                "height": placement.step_height(*step_uuid),
+                "signals": signal_placements_to_json(signals),
+            });
+            (step_uuid.to_string(), step)
+        })
+        .collect();
+    let forward = placement
+        .forward
+        .iter()
+        .map(|(forward, cell)| (forward.uuid(), forward.annotation(), cell.clone()));
+    let shared = placement
+        .shared
+        .iter()
+        .map(|(shared, cell)| (shared.uuid(), shared.annotation(), cell.clone()));
+    let fixed = placement
+        .fixed
+        .iter()
+        .map(|(fixed, cell)| (fixed.uuid(), fixed.annotation(), cell.clone()));
+
+    json!({
+        "forward": signal_placements_to_json(forward),
+        "shared": signal_placements_to_json(shared),
+        "fixed": signal_placements_to_json(fixed),
+        "steps": steps,
+        "base_height": placement.base_height,
+    })
+}
+
+fn signal_placements_to_json(
+    signals: impl Iterator<Item = (UUID, String, SignalPlacement)>,
+) -> Value {
+    signals
+        .map(|(id, annotation, cell)| {
+            let cell = json!({
+                "annotation": annotation,
+                "column": cell.column.uuid().to_string(),
+                "rotation": cell.rotation,
+            });
+            (id.to_string(), cell)
+        })
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+fn poly_expr_to_json<F: InterchangeField>(expr: &PolyExpr<F>) -> Result<Value, String> {
+    expr_to_json(expr, &|(column, rotation, annotation)| {
+        Ok(json!({ "Query": [column.uuid().to_string(), rotation, annotation] }))
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore]
+    fn test_trace_witness() {
+        let json = r#"
+        {
+            "step_instances": [
+                {
+                    "step_type_uuid": "270606747459021742275781620564109167114",
+                    "assignments": {
+                        "270606737951642240564318377467548666378": [
+                            {
+                                "Forward": [
+                                    {
+                                        "id": "270606737951642240564318377467548666378",
+                                        "phase": 0,
+                                        "annotation": "a"
+                                    },
+                                    false
+                                ]
+                            },
+                            "0000000000000000000000000000000000000000000000000000000000000055"
+                        ],
+                        "270606743497613616562965561253747624458": [
+                            {
+                                "Forward": [
+                                    {
+                                        "id": "270606743497613616562965561253747624458",
+                                        "phase": 0,
+                                        "annotation": "b"
+                                    },
+                                    false
+                                ]
+                            },
+                            "0000000000000000000000000000000000000000000000000000000000000089"
+                        ],
+                        "270606753004993118272949371872716917258": [
+                            {
+                                "Internal": {
+                                    "id": "270606753004993118272949371872716917258",
+                                    "annotation": "c"
+                                }
+                            },
+                            "0000000000000000000000000000000000000000000000000000000000000144"
+                        ]
+                    }
+                },
+                {
+                    "step_type_uuid": "270606783111694873693576112554652600842",
+                    "assignments": {
+                        "270606737951642240564318377467548666378": [
+                            {
+                                "Forward": [
+                                    {
+                                        "id": "270606737951642240564318377467548666378",
+                                        "phase": 0,
+                                        "annotation": "a"
+                                    },
+                                    false
+                                ]
+                            },
+                            "0000000000000000000000000000000000000000000000000000000000000089"
+                        ],
+                        "270606743497613616562965561253747624458": [
+                            {
+                                "Forward": [
+                                    {
+                                        "id": "270606743497613616562965561253747624458",
+                                        "phase": 0,
+                                        "annotation": "b"
+                                    },
+                                    false
+                                ]
+                            },
+                            "0000000000000000000000000000000000000000000000000000000000000144"
+                        ],
+                        "270606786280821374261518951164072823306": [
+                            {
+                                "Internal": {
+                                    "id": "270606786280821374261518951164072823306",
+                                    "annotation": "c"
+                                }
+                            },
+                            "0000000000000000000000000000000000000000000000000000000000000233"
+                        ]
+                    }
+                }
+            ]
+        }
+        "#;
+        let trace_witness: TraceWitness<Fr> = serde_json::from_str(json).unwrap();
+        println!("{:?}", trace_witness);
+    }
+
+    #[test]
+    fn test_expose_offset() {
+        let mut json = r#"
+        {
+            "Step": 1
+        }
+        "#;
+        let _: ExposeOffset = serde_json::from_str(json).unwrap();
+        json = r#"
+        {
+            "Last": -1
+        }
+        "#;
+        let _: ExposeOffset = serde_json::from_str(json).unwrap();
+        json = r#"
+        {
+            "First": 1
+        }
+        "#;
+        let _: ExposeOffset = serde_json::from_str(json).unwrap();
+    }
+
+    #[test]
+    fn test_circuit() {
+        let json = r#"
+        {
+            "step_types": {
+                "258869595755756204079859764249309612554": {
+                    "id": "258869595755756204079859764249309612554",
+                    "name": "fibo_first_step",
+                    "signals": [
+                        {
+                            "id": "258869599717164329791616633222308956682",
+                            "annotation": "c"
+                        }
+                    ],
+                    "constraints": [
+                        {
+                            "annotation": "(a == 1)",
+                            "expr": {
+                                "Sum": [
+                                    {
+                                        "Forward": [
+                                            {
+                                                "id": "258869580702405326369584955980151130634",
+                                                "phase": 0,
+                                                "annotation": "a"
+                                            },
+                                            false
+                                        ]
+                                    },
+                                    {
+                                        "Neg": {
+                                            "Const": "0000000000000000000000000000000000000000000000000000000000000001"
+                                        }
+                                    }
+                                ]
+                            }
+                        },
+                        {
+                            "annotation": "(b == 1)",
+                            "expr": {
+                                "Sum": [
+                                    {
+                                        "Forward": [
+                                            {
+                                                "id": "258869587040658327507391136965088381450",
+                                                "phase": 0,
+                                                "annotation": "b"
+                                            },
+                                            false
+                                        ]
+                                    },
+                                    {
+                                        "Neg": {
+                                            "Const": "0000000000000000000000000000000000000000000000000000000000000001"
+                                        }
+                                    }
+                                ]
+                            }
+                        },
+                        {
+                            "annotation": "((a + b) == c)",
+                            "expr": {
+                                "Sum": [
+                                    {
+                                        "Forward": [
+                                            {
+                                                "id": "258869580702405326369584955980151130634",
+                                                "phase": 0,
+                                                "annotation": "a"
+                                            },
+                                            false
+                                        ]
+                                    },
+                                    {
+                                        "Forward": [
+                                            {
+                                                "id": "258869587040658327507391136965088381450",
+                                                "phase": 0,
+                                                "annotation": "b"
+                                            },
+                                            false
+                                        ]
+                                    },
+                                    {
+                                        "Neg": {
+                                            "Internal": {
+                                                "id": "258869599717164329791616633222308956682",
+                                                "annotation": "c"
+                                            }
+                                        }
+                                    }
+                                ]
+                            }
+                        }
+                    ],
+                    "transition_constraints": [
+                        {
+                            "annotation": "(b == next(a))",
+                            "expr": {
+                                "Sum": [
+                                    {
+                                        "Forward": [
+                                            {
+                                                "id": "258869587040658327507391136965088381450",
+                                                "phase": 0,
+                                                "annotation": "b"
+                                            },
+                                            false
+                                        ]
+                                    },
+                                    {
+                                        "Neg": {
+                                            "Forward": [
+                                                {
+                                                    "id": "258869580702405326369584955980151130634",
+                                                    "phase": 0,
+                                                    "annotation": "a"
+                                                },
+                                                true
+                                            ]
+                                        }
+                                    }
+                                ]
+                            }
+                        },
+                        {
+                            "annotation": "(c == next(b))",
+                            "expr": {
+                                "Sum": [
+                                    {
+                                        "Internal": {
+                                            "id": "258869599717164329791616633222308956682",
+                                            "annotation": "c"
+                                        }
+                                    },
+                                    {
+                                        "Neg": {
+                                            "Forward": [
+                                                {
+                                                    "id": "258869587040658327507391136965088381450",
+                                                    "phase": 0,
+                                                    "annotation": "b"
+                                                },
+                                                true
+                                            ]
+                                        }
+                                    }
+                                ]
+                            }
+                        },
+                        {
+                            "annotation": "(n == next(n))",
+                            "expr": {
+                                "Sum": [
+                                    {
+                                        "Forward": [
+                                            {
+                                                "id": "258869589417503202934383108674030275082",
+                                                "phase": 0,
+                                                "annotation": "n"
+                                            },
+                                            false
+                                        ]
+                                    },
+                                    {
+                                        "Neg": {
+                                            "Forward": [
+                                                {
+                                                    "id": "258869589417503202934383108674030275082",
+                                                    "phase": 0,
+                                                    "annotation": "n"
+                                                },
+                                                true
+                                            ]
+                                        }
+                                    }
+                                ]
+                            }
+                        }
+                    ],
+                    "lookups": [],
+                    "annotations": {
+                        "258869599717164329791616633222308956682": "c"
+                    }
+                },
+                "258869628239302834927102989021255174666": {
+                    "id": "258869628239302834927102989021255174666",
+                    "name": "fibo_step",
+                    "signals": [
+                        {
+                            "id": "258869632200710960639812650790420089354",
+                            "annotation": "c"
+                        }
+                    ],
+                    "constraints": [
+                        {
+                            "annotation": "((a + b) == c)",
+                            "expr": {
+                                "Sum": [
+                                    {
+                                        "Forward": [
+                                            {
+                                                "id": "258869580702405326369584955980151130634",
+                                                "phase": 0,
+                                                "annotation": "a"
+                                            },
+                                            false
+                                        ]
+                                    },
+                                    {
+                                        "Forward": [
+                                            {
+                                                "id": "258869587040658327507391136965088381450",
+                                                "phase": 0,
+                                                "annotation": "b"
+                                            },
+                                            false
+                                        ]
+                                    },
+                                    {
+                                        "Neg": {
+                                            "Internal": {
+                                                "id": "258869632200710960639812650790420089354",
+                                                "annotation": "c"
+                                            }
+                                        }
+                                    }
+                                ]
+                            }
+                        }
+                    ],
+                    "transition_constraints": [
+                        {
+                            "annotation": "(b == next(a))",
+                            "expr": {
+                                "Sum": [
+                                    {
+                                        "Forward": [
+                                            {
+                                                "id": "258869587040658327507391136965088381450",
+                                                "phase": 0,
+                                                "annotation": "b"
+                                            },
+                                            false
+                                        ]
+                                    },
+                                    {
+                                        "Neg": {
+                                            "Forward": [
+                                                {
+                                                    "id": "258869580702405326369584955980151130634",
+                                                    "phase": 0,
+                                                    "annotation": "a"
+                                                },
+                                                true
+                                            ]
+                                        }
+                                    }
+                                ]
+                            }
+                        },
+                        {
+                            "annotation": "(c == next(b))",
+                            "expr": {
+                                "Sum": [
+                                    {
+                                        "Internal": {
+                                            "id": "258869632200710960639812650790420089354",
+                                            "annotation": "c"
+                                        }
+                                    },
+                                    {
+                                        "Neg": {
+                                            "Forward": [
+                                                {
+                                                    "id": "258869587040658327507391136965088381450",
+                                                    "phase": 0,
+                                                    "annotation": "b"
+                                                },
+                                                true
+                                            ]
+                                        }
+                                    }
+                                ]
+                            }
+                        },
+                        {
+                            "annotation": "(n == next(n))",
+                            "expr": {
+                                "Sum": [
+                                    {
+                                        "Forward": [
+                                            {
+                                                "id": "258869589417503202934383108674030275082",
+                                                "phase": 0,
+                                                "annotation": "n"
+                                            },
+                                            false
+                                        ]
+                                    },
+                                    {
+                                        "Neg": {
+                                            "Forward": [
+                                                {
+                                                    "id": "258869589417503202934383108674030275082",
+                                                    "phase": 0,
+                                                    "annotation": "n"
+                                                },
+                                                true
+                                            ]
+                                        }
+                                    }
+                                ]
+                            }
+                        }
+                    ],
+                    "lookups": [],
+                    "annotations": {
+                        "258869632200710960639812650790420089354": "c"
+                    }
+                },
+                "258869646461780213207493341245063432714": {
+                    "id": "258869646461780213207493341245063432714",
+                    "name": "padding",
+                    "signals": [],
+                    "constraints": [],
+                    "transition_constraints": [
+                        {
+                            "annotation": "(b == next(b))",
+                            "expr": {
+                                "Sum": [
+                                    {
+                                        "Forward": [
+                                            {
+                                                "id": "258869587040658327507391136965088381450",
+                                                "phase": 0,
+                                                "annotation": "b"
+                                            },
+                                            false
+                                        ]
+                                    },
+                                    {
+                                        "Neg": {
+                                            "Forward": [
+                                                {
+                                                    "id": "258869587040658327507391136965088381450",
+                                                    "phase": 0,
+                                                    "annotation": "b"
+                                                },
+                                                true
+                                            ]
+                                        }
+                                    }
+                                ]
+                            }
+                        },
+                        {
+                            "annotation": "(n == next(n))",
+                            "expr": {
+                                "Sum": [
+                                    {
+                                        "Forward": [
+                                            {
+                                                "id": "258869589417503202934383108674030275082",
+                                                "phase": 0,
+                                                "annotation": "n"
+                                            },
+                                            false
+                                        ]
+                                    },
+                                    {
+                                        "Neg": {
+                                            "Forward": [
+                                                {
+                                                    "id": "258869589417503202934383108674030275082",
+                                                    "phase": 0,
+                                                    "annotation": "n"
+                                                },
+                                                true
+                                            ]
+                                        }
+                                    }
+                                ]
+                            }
+                        }
+                    ],
+                    "lookups": [],
+                    "annotations": {}
+                }
+            },
+            "forward_signals": [
+                {
+                    "id": "258869580702405326369584955980151130634",
+                    "phase": 0,
+                    "annotation": "a"
+                },
+                {
+                    "id": "258869587040658327507391136965088381450",
+                    "phase": 0,
+                    "annotation": "b"
+                },
+                {
+                    "id": "258869589417503202934383108674030275082",
+                    "phase": 0,
+                    "annotation": "n"
+                }
+            ],
+            "shared_signals": [],
+            "fixed_signals": [],
+            "exposed": [
+                [
+                    {
+                        "Forward": [
+                            {
+                                "id": "258869587040658327507391136965088381450",
+                                "phase": 0,
+                                "annotation": "b"
+                            },
+                            false
+                        ]
+                    },
+                    {
+                        "Last": -1
+                    }
+                ],
+                [
+                    {
+                        "Forward": [
+                            {
+                                "id": "258869589417503202934383108674030275082",
+                                "phase": 0,
+                                "annotation": "n"
+                            },
+                            false
+                        ]
+                    },
+                    {
+                        "Last": -1
+                    }
+                ]
+            ],
+            "annotations": {
+                "258869580702405326369584955980151130634": "a",
+                "258869587040658327507391136965088381450": "b",
+                "258869589417503202934383108674030275082": "n",
+                "258869595755756204079859764249309612554": "fibo_first_step",
+                "258869628239302834927102989021255174666": "fibo_step",
+                "258869646461780213207493341245063432714": "padding"
+            },
+            "fixed_assignments": null,
+            "first_step": "258869595755756204079859764249309612554",
+            "last_step": "258869646461780213207493341245063432714",
+            "num_steps": 10,
+            "q_enable": true,
+            "id": "258867373405797678961444396351437277706"
+        }
+        "#;
+        let circuit: SBPIR<Fr, ()> = serde_json::from_str(json).unwrap();
+        println!("{:?}", circuit);
+    }
+
+    #[test]
+    fn test_step_type() {
+        let json = r#"
+        {
+            "id":"1",
+            "name":"fibo",
+            "signals":[
+                {
+                    "id":"1",
+                    "annotation":"a"
+                },
+                {
+                    "id":"2",
+                    "annotation":"b"
+                }
+            ],
+            "constraints":[
+                {
+                    "annotation":"constraint",
+                    "expr":{
+                        "Sum":[
+                            {
+                                "Const": "0000000000000000000000000000000000000000000000000000000000000001"
+                            },
+                            {
+                                "Mul":[
+                                    {
+                                        "Internal":{
+                                            "id":"3",
+                                            "annotation":"c"
+                                        }
+                                    },
+                                    {
+                                        "Const": "0000000000000000000000000000000000000000000000000000000000000003"
+                                    }
+                                ]
+                            }
+                        ]
+                    }
+                },
+                {
+                    "annotation":"constraint",
+                    "expr":{
+                        "Sum":[
+                            {
+                                "Const": "0000000000000000000000000000000000000000000000000000000000000001"
+                            },
+                            {
+                                "Mul":[
+                                    {
+                                        "Shared":[
+                                            {
+                                                "id":"4",
+                                                "phase":2,
+                                                "annotation":"d"
+                                            },
+                                            1
+                                        ]
+                                    },
+                                    {
+                                        "Const": "0000000000000000000000000000000000000000000000000000000000000003"
+                                    }
+                                ]
+                            }
+                        ]
+                    }
+                }
+            ],
+            "transition_constraints":[
+                {
+                    "annotation":"trans",
+                    "expr":{
+                        "Sum":[
+                            {
+                                "Const": "0000000000000000000000000000000000000000000000000000000000000001"
+                            },
+                            {
+                                "Mul":[
+                                    {
+                                        "Forward":[
+                                            {
+                                                "id":"5",
+                                                "phase":1,
+                                                "annotation":"e"
+                                            },
+                                            true
+                                        ]
+                                    },
+                                    {
+                                        "Const": "0000000000000000000000000000000000000000000000000000000000000003"
+                                    }
+                                ]
+                            }
+                        ]
+                    }
+                },
+                {
+                    "annotation":"trans",
+                    "expr":{
+                        "Sum":[
+                            {
+                                "Const": "0000000000000000000000000000000000000000000000000000000000000001"
+                            },
+                            {
+                                "Mul":[
+                                    {
+                                        "Fixed":[
+                                            {
+                                                "id":"6",
+                                                "annotation":"e"
+                                            },
+                                            2
+                                        ]
+                                    },
+                                    {
+                                        "Const": "0000000000000000000000000000000000000000000000000000000000000003"
+                                    }
+                                ]
+                            }
+                        ]
+                    }
+                }
+            ],
+            "lookups":[],
+            "annotations":{
+                "5":"a",
+                "6":"b",
+                "7":"c"
+            }
+        }
+        "#;
+        let step_type: StepType<Fr> = serde_json::from_str(json).unwrap();
+        println!("{:?}", step_type);
+    }
+
+    #[test]
+    fn test_constraint() {
+        let json = r#"
+        {"annotation": "constraint",
+        "expr": 
+        {
+            "Sum": [
+                {
+                "Internal": {
+                    "id": "27",
+                    "annotation": "a"
+                }
+                },
+                {
+                "Fixed": [
+                    {
+                        "id": "28",
+                        "annotation": "b"
+                    },
+                    1
+                ]
+                },
+                {
+                "Shared": [
+                    {
+                        "id": "29",
+                        "phase": 1,
+                        "annotation": "c"
+                    },
+                    2
+                ]
+                },
+                {
+                "Forward": [
+                    {
+                        "id": "30",
+                        "phase": 2,
+                        "annotation": "d"
+                    },
+                    true
+                ]
+                },
+                {
+                "StepTypeNext": {
+                    "id": "31",
+                    "annotation": "e"
+                }
+                },
+                {
+                "Const": "0000000000000000000000000000000000000000000000000000000000000003"
+                },
+                {
+                "Mul": [
+                    {
+                    "Const": "0000000000000000000000000000000000000000000000000000000000000004"
+                    },
+                    {
+                    "Const": "0000000000000000000000000000000000000000000000000000000000000005"
+                    }
+                ]
+                },
+                {
+                "Neg": {
+                    "Const": "0000000000000000000000000000000000000000000000000000000000000002"
+                }
+                },
+                {
+                "Pow": [
+                    {
+                    "Const": "0000000000000000000000000000000000000000000000000000000000000003"
+                    },
+                    4
+                ]
+                }
+            ]
+            }
+        }"#;
+        let constraint: Constraint<Fr> = serde_json::from_str(json).unwrap();
+        println!("{:?}", constraint);
+        let transition_constraint: TransitionConstraint<Fr> = serde_json::from_str(json).unwrap();
+        println!("{:?}", transition_constraint);
+    }
+
+    #[test]
+    fn test_expr() {
+        let json = r#"
+        {
+            "Sum": [
+                {
+                "Internal": {
+                    "id": "27",
+                    "annotation": "a"
+                }
+                },
+                {
+                "Fixed": [
+                    {
+                        "id": "28",
+                        "annotation": "b"
+                    },
+                    1
+                ]
+                },
+                {
+                "Shared": [
+                    {
+                        "id": "29",
+                        "phase": 1,
+                        "annotation": "c"
+                    },
+                    2
+                ]
+                },
+                {
+                "Forward": [
+                    {
+                        "id": "30",
+                        "phase": 2,
+                        "annotation": "d"
+                    },
+                    true
+                ]
+                },
+                {
+                "StepTypeNext": {
+                    "id": "31",
+                    "annotation": "e"
+                }
+                },
+                {
+                "Const": "0000000000000000000000000000000000000000000000000000000000000003"
+                },
+                {
+                "Mul": [
+                    {
+                    "Const": "0000000000000000000000000000000000000000000000000000000000000004"
+                    },
+                    {
+                    "Const": "0000000000000000000000000000000000000000000000000000000000000005"
+                    }
+                ]
+                },
+                {
+                "Neg": {
+                    "Const": "0000000000000000000000000000000000000000000000000000000000000002"
+                }
+                },
+                {
+                "Pow": [
+                    {
+                    "Const": "0000000000000000000000000000000000000000000000000000000000000003"
+                    },
+                    4
+                ]
+                }
+            ]
+            }"#;
+        let expr: Expr<Fr, Queriable<Fr>> = serde_json::from_str(json).unwrap();
+        println!("{:?}", expr);
+    }
+
+    #[test]
+    fn test_super_circuit_ast() {
+        let json = r#"
+        {
+            "sub_circuits": [
+                {
+                    "step_types": {},
+                    "forward_signals": [
+                        {
+                            "id": "1",
+                            "phase": 0,
+                            "annotation": "a"
+                        }
+                    ],
+                    "shared_signals": [],
+                    "fixed_signals": [],
+                    "exposed": [],
+                    "annotations": {},
+                    "fixed_assignments": null,
+                    "first_step": null,
+                    "last_step": null,
+                    "num_steps": 0,
+                    "q_enable": true,
+                    "id": "2"
+                }
+            ],
+            "tables": [
+                {
+                    "step_types": {},
+                    "forward_signals": [],
+                    "shared_signals": [],
+                    "fixed_signals": [
+                        {
+                            "id": "3",
+                            "annotation": "byte"
+                        }
+                    ],
+                    "exposed": [],
+                    "annotations": {},
+                    "fixed_assignments": null,
+                    "first_step": null,
+                    "last_step": null,
+                    "num_steps": 256,
+                    "q_enable": false,
+                    "id": "4"
+                }
+            ]
+        }"#;
+        let ast: SuperCircuitAst<Fr> = serde_json::from_str(json).unwrap();
+        assert_eq!(ast.sub_circuits.len(), 1);
+        assert_eq!(ast.sub_circuits[0].id, 2);
+        assert_eq!(ast.tables.len(), 1);
+        assert_eq!(ast.tables[0].fixed_signals[0].annotation(), "byte");
+
+        let json = r#"{ "tables": [] }"#;
+        assert!(serde_json::from_str::<SuperCircuitAst<Fr>>(json).is_err());
+    }
+
+    #[test]
+    fn test_compiler_options() {
+        let options = CompilerOptions::from_json(Some(
+            r#"{
+                "cell_manager": "max_width",
+                "max_width": 4,
+                "step_selector": "log_n",
+                "optimization_level": 2,
+                "max_degree": 5
+            }"#,
+        ))
+        .unwrap();
+        assert_eq!(options.cell_manager, CellManagerOption::MaxWidth);
+        assert_eq!(options.max_width, Some(4));
+        assert!(!options.same_height);
+        assert_eq!(options.step_selector, StepSelectorOption::LogN);
+        assert_eq!(options.max_degree, Some(5));
+        assert!(options.config::<Fr>().is_ok());
+
+        let options = CompilerOptions::from_json(None).unwrap();
+        assert_eq!(options.cell_manager, CellManagerOption::SingleRow);
+        assert_eq!(options.optimization_level, 0);
+
+        let options = CompilerOptions::from_json(Some(r#"{ "cell_manager": "max_width" }"#));
+        assert!(options.unwrap().config::<Fr>().is_err());
+        assert!(CompilerOptions::from_json(Some(r#"{ "max_widht": 4 }"#)).is_err());
+
+        let options = CompilerOptions::from_json(Some(r#"{ "field": "pasta_fp" }"#)).unwrap();
+        assert_eq!(options.field, FieldOption::PastaFp);
+        assert_eq!(options.field.name(), "pasta_fp");
+        assert_eq!(CompilerOptions::default().field, FieldOption::Bn256);
+        assert!(CompilerOptions::from_json(Some(r#"{ "field": "goldilocks" }"#)).is_err());
+    }
+
+    pub(crate) fn field_value_json<F: InterchangeField>(value: F) -> String {
+        format!("\"{}\"", FieldValue(value).to_hex())
+    }
+
+    #[test]
+    fn test_field_value() {
+        let json = field_value_json(Fr::from(5));
+        let value: FieldValue<Fr> = serde_json::from_str(&json).unwrap();
+        assert_eq!(value.0, Fr::from(5));
+
+        let json = field_value_json(pasta::Fp::from(5));
+        let value: FieldValue<pasta::Fp> = serde_json::from_str(&json).unwrap();
+        assert_eq!(value.0, pasta::Fp::from(5));
+
+        assert!(serde_json::from_str::<FieldValue<Fr>>(r#""05""#).is_err());
+        assert!(
+            serde_json::from_str::<FieldValue<Fr>>(&format!("\"{}\"", "zz".repeat(32))).is_err()
+        );
+        // bytes above the modulus, that are not a canonical field element
+        assert!(
+            serde_json::from_str::<FieldValue<Fr>>(&format!("\"{}\"", "ff".repeat(32))).is_err()
+        );
+    }
+
+    #[test]
+    fn test_field_decimal() {
+        let value = -Fr::from(5);
+        let digits = field_to_decimal(&value);
+        assert_eq!(
+            digits,
+            "21888242871839275222246405745257275088548364400416034343698204186575808495612"
+        );
+        assert_eq!(field_from_decimal::<Fr>(&digits).unwrap(), value);
+        assert_eq!(field_from_decimal::<Fr>("0").unwrap(), Fr::from(0));
+
+        // the modulus, and digits that are not a decimal integer
+        assert!(field_from_decimal::<Fr>(
+            "21888242871839275222246405745257275088548364400416034343698204186575808495617"
+        )
+        .is_err());
+        assert!(field_from_decimal::<Fr>("0x5").is_err());
+        assert!(field_from_decimal::<Fr>("-1").is_err());
+
+        assert_eq!(
+            FieldOption::from_name("pasta_fq").unwrap(),
+            FieldOption::PastaFq
+        );
+        assert!(FieldOption::from_name("goldilocks").is_err());
+    }
+
+    pub(crate) fn circuit_with_fixed_assignments(fixed_assignments: &str) -> String {
+        format!(
+            r#"
+            {{
+                "step_types": {{}},
+                "forward_signals": [],
+                "shared_signals": [],
+                "fixed_signals": [
+                    {{
+                        "id": "1",
+                        "annotation": "byte"
+                    }}
+                ],
+                "exposed": [],
+                "annotations": {{}},
+                "fixed_assignments": {},
+                "first_step": null,
+                "last_step": null,
+                "num_steps": 2,
+                "q_enable": false,
+                "id": "3"
+            }}"#,
+            fixed_assignments
+        )
+    }
+
+    #[test]
+    fn test_fixed_assignments() {
+        let values = [Fr::from(7), Fr::from(8)].map(field_value_json).join(", ");
+        let assignment = |id: &str, signal_id: &str, rotation: i32, values: &str| {
+            circuit_with_fixed_assignments(&format!(
+                r#"{{ "{}": [ {{ "Fixed": [ {{ "id": "{}", "annotation": "byte" }}, {} ] }}, [{}] ] }}"#,
+                id, signal_id, rotation, values
+            ))
+        };
+
+        let circuit: SBPIR<Fr, ()> =
+            serde_json::from_str(&assignment("1", "1", 0, &values)).unwrap();
+        let fixed_assignments = circuit.fixed_assignments.unwrap();
+        let byte = Queriable::Fixed(FixedSignal::new_with_id(1, "byte".to_string()), 0);
+        assert_eq!(
+            fixed_assignments.get(&byte),
+            Some(&vec![Fr::from(7), Fr::from(8)])
+        );
+
+        let invalid = [
+            // a value less than the steps
+            assignment("1", "1", 0, &field_value_json(Fr::from(7))),
+            // rotated
+            assignment("1", "1", 1, &values),
+            // not a fixed signal of the circuit
+            assignment("2", "2", 0, &values),
+            // under the id of another signal
+            assignment("2", "1", 0, &values),
+        ];
+        for json in invalid {
+            assert!(serde_json::from_str::<SBPIR<Fr, ()>>(&json).is_err());
+        }
+    }
+
+    #[test]
+    fn test_to_json() {
+        let json = r#"
+        {
+            "step_types": {
+                "10": {
+                    "id": "10",
+                    "name": "step",
+                    "signals": [{ "id": "11", "annotation": "c" }],
+                    "constraints": [
+                        {
+                            "annotation": "c == a + 1",
+                            "expr": {
+                                "Sum": [
+                                    { "Internal": { "id": "11", "annotation": "c" } },
+                                    {
+                                        "Neg": {
+                                            "Forward": [
+                                                { "id": "1", "phase": 0, "annotation": "a" },
+                                                false
+                                            ]
+                                        }
+                                    },
+                                    { "Const": "ONE" }
+                                ]
+                            }
+                        }
+                    ],
+                    "transition_constraints": [
+                        {
+                            "annotation": "next",
+                            "expr": {
+                                "Mul": [
+                                    {
+                                        "Forward": [
+                                            { "id": "1", "phase": 0, "annotation": "a" },
+                                            true
+                                        ]
+                                    },
+                                    {
+                                        "Pow": [
+                                            {
+                                                "Shared": [
+                                                    { "id": "2", "phase": 1, "annotation": "b" },
+                                                    -1
+                                                ]
+                                            },
+                                            2
+                                        ]
+                                    },
+                                    { "StepTypeNext": { "id": "10", "annotation": "step" } }
+                                ]
+                            }
+                        }
+                    ],
+                    "lookups": [
+                        {
+                            "annotation": "byte",
+                            "exprs": [
+                                [
+                                    {
+                                        "annotation": "c",
+                                        "expr": {
+                                            "Internal": { "id": "11", "annotation": "c" }
+                                        }
+                                    },
+                                    { "Fixed": [{ "id": "3", "annotation": "byte" }, 0] }
+                                ]
+                            ],
+                            "enable": null
+                        }
+                    ],
+                    "annotations": { "11": "c" }
+                }
+            },
+            "forward_signals": [{ "id": "1", "phase": 0, "annotation": "a" }],
+            "shared_signals": [{ "id": "2", "phase": 1, "annotation": "b" }],
+            "fixed_signals": [{ "id": "3", "annotation": "byte" }],
+            "exposed": [
+                [
+                    { "Forward": [{ "id": "1", "phase": 0, "annotation": "a" }, false] },
+                    { "First": 0 }
+                ],
+                [
+                    { "Shared": [{ "id": "2", "phase": 1, "annotation": "b" }, 0] },
+                    { "Step": 1 }
+                ]
+            ],
+            "annotations": { "1": "a", "2": "b", "3": "byte", "10": "step" },
+            "aliases": { "1": ["x"] },
+            "fixed_assignments": {
+                "3": [{ "Fixed": [{ "id": "3", "annotation": "byte" }, 0] }, ["ONE", "ONE"]]
+            },
+            "first_step": "10",
+            "last_step": null,
+            "num_steps": 2,
+            "q_enable": true,
+            "id": "20"
+        }"#
+        .replace("ONE", &FieldValue(Fr::from(1)).to_hex());
+
+        let circuit: SBPIR<Fr, ()> = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            sbpir_to_json(&circuit).unwrap(),
+            serde_json::from_str::<Value>(&json).unwrap()
+        );
+
+        let inverse = Expr::MI(Box::new(Expr::Const(Fr::from(2))));
+        assert!(sbpir_expr_to_json(&inverse).is_err());
+    }
+}
@@ -1,2 +1,17 @@
 pub mod dsl;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "halo2")]
+pub mod interchange;
+#[cfg(feature = "node")]
+pub mod node;
+#[cfg(feature = "halo2")]
+pub mod proving;
+#[cfg(feature = "python")]
 pub mod python;
+pub mod schema;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod visualize;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,384 @@
+//! Node.js bindings, with the entry points of the Python bridge, for a TypeScript frontend that
+//! produces the same JSON of the SBPIR. Circuits are compiled, checked and mock proven in the
+//! field of their options, and bn256 circuits are also proven and verified, with KZG params read
+//! from a file, and exported as their verifying key or a Solidity verifier. Witnesses are the JSON
+//! of a `TraceWitness` as a string, or its binary format of `wit_gen::binary` as a `Buffer`, and
+//! field elements are decimal strings.
+//!
+//! Build with the `build` script of `package.json`, which runs `napi build` with the `node`
+//! feature and without the default ones.
+
+use std::sync::Arc;
+
+use halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::VerifyingKey,
+    poly::kzg::commitment::ParamsKZG,
+    SerdeFormat,
+};
+use napi::{
+    bindgen_prelude::{AsyncTask, Buffer, Either},
+    Env, Error, Status, Task,
+};
+use napi_derive::napi;
+
+use crate::{
+    frontend::{
+        interchange::{
+            self, check_witness, field_from_decimal, field_to_decimal, from_json, mock_prover,
+            plonkish_to_json, sbpir_to_json, violation_kind_name, with_field, CompilerOptions,
+            FieldOption, InterchangeError, InterchangeField, StoredCircuit,
+        },
+        proving::{
+            create_halo2_proof, load_params, solidity_verifier, verify_halo2_proof, CircuitKeys,
+        },
+    },
+    plonkish::{
+        backend::halo2::{ChiquitoHalo2, ChiquitoHalo2Circuit, CircuitStats},
+        ir::assignments::Assignments,
+    },
+    sbpir::{checker::Violation, SBPIR},
+    wit_gen::TraceWitness,
+};
+
+type Result<T> = napi::Result<T>;
+
+impl From<InterchangeError> for Error {
+    fn from(error: InterchangeError) -> Self {
+        let status = match error {
+            InterchangeError::Deserialization(_) | InterchangeError::Value(_) => Status::InvalidArg,
+            InterchangeError::Compilation(_)
+            | InterchangeError::Io(_)
+            | InterchangeError::Proving(_) => Status::GenericFailure,
+        };
+
+        Error::new(status, error.to_string())
+    }
+}
+
+/// Circuit compiled from the JSON of its AST. Its keys are shared with its proving tasks.
+#[napi]
+pub struct Circuit {
+    circuit: StoredCircuit,
+}
+
+/// Size of a circuit in the halo2 table, see `ChiquitoHalo2::stats`.
+#[napi(object, js_name = "CircuitStats")]
+pub struct JsCircuitStats {
+    pub advice_columns: u32,
+    pub fixed_columns: u32,
+    pub rows: u32,
+    pub polys: u32,
+    pub lookups: u32,
+    pub max_degree: u32,
+    pub max_advice_queries: u32,
+    pub min_k: u32,
+}
+
+impl From<CircuitStats> for JsCircuitStats {
+    fn from(stats: CircuitStats) -> Self {
+        Self {
+            advice_columns: stats.advice_columns as u32,
+            fixed_columns: stats.fixed_columns as u32,
+            rows: stats.rows as u32,
+            polys: stats.polys as u32,
+            lookups: stats.lookups as u32,
+            max_degree: stats.max_degree as u32,
+            max_advice_queries: stats.max_advice_queries as u32,
+            min_k: stats.min_k,
+        }
+    }
+}
+
+/// Violation of a witness found by `Circuit.checkWitness`, with the keys of the Python frontend.
+#[napi(object, js_name = "Violation")]
+pub struct JsViolation {
+    pub message: String,
+    pub kind: String,
+    pub step: u32,
+    pub step_type: String,
+    pub annotation: String,
+    pub location: Option<String>,
+    pub explanation: Vec<String>,
+}
+
+impl From<Violation> for JsViolation {
+    fn from(violation: Violation) -> Self {
+        Self {
+            message: violation.to_string(),
+            kind: violation_kind_name(violation.kind).to_string(),
+            step: violation.step as u32,
+            step_type: violation.step_type,
+            annotation: violation.annotation,
+            location: violation.location.map(|location| location.to_string()),
+            explanation: violation.explanation,
+        }
+    }
+}
+
+/// Result of mock proving a witness: the failures of `MockProver::verify`, as displayed by halo2,
+/// none if the witness satisfies the circuit.
+#[napi(object)]
+pub struct MockProofResult {
+    pub ok: bool,
+    pub failures: Vec<String>,
+}
+
+/// Proof of a bn256 circuit and its public inputs, by instance column.
+#[napi(object)]
+pub struct Proof {
+    pub proof: Buffer,
+    pub instance: Vec<Vec<String>>,
+}
+
+#[napi]
+impl Circuit {
+    /// Parses the JSON of a circuit and compiles it with the JSON of `CompilerOptions`, or with
+    /// the defaults without it.
+    #[napi(constructor)]
+    pub fn new(ast_json: String, options_json: Option<String>) -> Result<Self> {
+        let options = CompilerOptions::from_json(options_json.as_deref())?;
+
+        Ok(Circuit {
+            circuit: StoredCircuit::compile(&ast_json, &options)?,
+        })
+    }
+
+    /// Name of the field of the circuit, as in the JSON of the options.
+    #[napi(getter)]
+    pub fn field(&self) -> String {
+        self.circuit.field().name().to_string()
+    }
+
+    /// Returns the AST of the circuit as Rust parsed it, serialized back to JSON.
+    #[napi]
+    pub fn ast_json(&self) -> Result<String> {
+        with_field!(self.circuit.field(), F => {
+            to_json_string(sbpir_to_json(&self.circuit.ast::<F>()?))
+        })
+    }
+
+    /// Returns the plonkish IR the circuit was compiled to, as JSON, with the placement of its
+    /// signals in the columns.
+    #[napi]
+    pub fn plonkish_json(&self) -> Result<String> {
+        with_field!(self.circuit.field(), F => {
+            let compiled = self.circuit.compiled::<F>()?;
+            let placement = compiled.assigner.as_ref().map(|assigner| assigner.placement());
+            to_json_string(plonkish_to_json(compiled.halo2.circuit(), placement))
+        })
+    }
+
+    /// Returns the size of the circuit in the halo2 table.
+    #[napi]
+    pub fn stats(&self) -> Result<JsCircuitStats> {
+        with_field!(self.circuit.field(), F => {
+            Ok(self.circuit.compiled::<F>()?.halo2.stats().into())
+        })
+    }
+
+    /// Returns the smallest k of a halo2 table that fits the circuit.
+    #[napi]
+    pub fn estimate_k(&self) -> Result<u32> {
+        Ok(self.stats()?.min_k)
+    }
+
+    /// Checks a witness against the AST of the circuit with the native `Checker`, without running
+    /// `MockProver`. Returns the violations in step order, none if the witness satisfies the
+    /// circuit.
+    #[napi]
+    pub fn check_witness(&self, witness: Either<String, Buffer>) -> Result<Vec<JsViolation>> {
+        with_field!(self.circuit.field(), F => {
+            let ast = self.circuit.ast::<F>()?;
+            let violations = check_witness(&ast, &parse_witness::<F>(&witness)?)?;
+
+            Ok(violations.into_iter().map(JsViolation::from).collect())
+        })
+    }
+
+    /// Runs `MockProver` for a witness, with the k of `estimateK` if `k` is not given. Fails if the
+    /// witness cannot be assigned, or for a `k` too small.
+    #[napi]
+    pub fn mock_prove(
+        &self,
+        witness: Either<String, Buffer>,
+        k: Option<u32>,
+    ) -> Result<MockProofResult> {
+        with_field!(self.circuit.field(), F => {
+            let compiled = self.circuit.compiled::<F>()?;
+            let assignments = compiled.assignments(parse_witness::<F>(&witness)?);
+            let circuit = ChiquitoHalo2Circuit::new(compiled.halo2.clone(), assignments);
+
+            let min_k = compiled.halo2.stats().min_k;
+            let prover = mock_prover(k.map(|k| k as usize), min_k, &circuit, circuit.instance())?;
+            let failures: Vec<_> = match prover.verify() {
+                Ok(()) => Vec::new(),
+                Err(failures) => failures.iter().map(ToString::to_string).collect(),
+            };
+
+            Ok(MockProofResult {
+                ok: failures.is_empty(),
+                failures,
+            })
+        })
+    }
+
+    /// Generates a proof of the circuit with a witness, on a thread of the libuv pool, with the KZG
+    /// params read from `params_path`, whose k is the one of the circuit. The params and the
+    /// proving key of each params are cached, so only the first proof reads the params and
+    /// generates the key. Only bn256 circuits are proven. With `evm`, the proof uses the Keccak256
+    /// transcript of the verifier of `solidityVerifier`, instead of Blake2b.
+    #[napi]
+    pub fn prove(
+        &self,
+        witness: Either<String, Buffer>,
+        params_path: String,
+        evm: Option<bool>,
+    ) -> Result<AsyncTask<ProvingTask>> {
+        let compiled = self.circuit.compiled::<Fr>()?;
+        let assignments = compiled.assignments(parse_witness::<Fr>(&witness)?);
+
+        Ok(AsyncTask::new(ProvingTask {
+            compiled: compiled.halo2.clone(),
+            assignments,
+            params_path,
+            evm: evm.unwrap_or(false),
+            keys: self.circuit.keys(),
+        }))
+    }
+
+    /// Verifies a `proof` of the circuit with its public inputs `instance`, by instance column,
+    /// and the KZG params read from `params_path`. Without `params_path`, the params that the
+    /// circuit was proven with are used. Returns if the proof is valid. With `evm`, the proof is
+    /// one of the Keccak256 transcript, see `prove`.
+    #[napi]
+    pub fn verify(
+        &self,
+        proof: Buffer,
+        instance: Vec<Vec<String>>,
+        params_path: Option<String>,
+        evm: Option<bool>,
+    ) -> Result<bool> {
+        let (params, vk) = self.params_and_verifying_key(params_path)?;
+        let instance = instance
+            .iter()
+            .map(|column| {
+                column
+                    .iter()
+                    .map(|value| field_from_decimal(value))
+                    .collect::<std::result::Result<Vec<_>, _>>()
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(verify_halo2_proof(
+            &params,
+            &vk,
+            &proof,
+            &instance,
+            evm.unwrap_or(false),
+        )?)
+    }
+
+    /// Returns the verifying key of the circuit with the params of `params_path`, or those it was
+    /// proven with if not given, serialized in the raw bytes format of halo2.
+    #[napi]
+    pub fn verifying_key(&self, params_path: Option<String>) -> Result<Buffer> {
+        let (_, vk) = self.params_and_verifying_key(params_path)?;
+
+        Ok(vk.to_bytes(SerdeFormat::RawBytes).into())
+    }
+
+    /// Returns the source of a Solidity contract that verifies the proofs of the circuit generated
+    /// with `evm`, with the params of `params_path`, or those it was proven with if not given. The
+    /// verifying key is embedded in the contract.
+    #[napi]
+    pub fn solidity_verifier(&self, params_path: Option<String>) -> Result<String> {
+        let (params, vk) = self.params_and_verifying_key(params_path)?;
+
+        Ok(solidity_verifier(
+            &params,
+            &vk,
+            &self.circuit.compiled::<Fr>()?.halo2,
+        )?)
+    }
+}
+
+impl Circuit {
+    /// Returns the params of `params_path`, or those the circuit was proven with if `None`, and
+    /// the verifying key of the circuit with them.
+    fn params_and_verifying_key(
+        &self,
+        params_path: Option<String>,
+    ) -> Result<(Arc<ParamsKZG<Bn256>>, Arc<VerifyingKey<G1Affine>>)> {
+        let compiled = &self.circuit.compiled::<Fr>()?.halo2;
+
+        Ok(self
+            .circuit
+            .keys()
+            .params_and_verifying_key(params_path.as_deref(), compiled)?)
+    }
+}
+
+/// Proof generated by `Circuit.prove` on a thread of the libuv pool, with the witness already
+/// assigned.
+pub struct ProvingTask {
+    compiled: ChiquitoHalo2<Fr>,
+    assignments: Option<Assignments<Fr>>,
+    params_path: String,
+    evm: bool,
+    keys: Arc<CircuitKeys>,
+}
+
+impl Task for ProvingTask {
+    type Output = (Vec<u8>, Vec<Vec<Fr>>);
+    type JsValue = Proof;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let params = load_params(&self.params_path)?;
+        let pk = self
+            .keys
+            .proving_key(&self.params_path, &params, &self.compiled)?;
+        let circuit = ChiquitoHalo2Circuit::new(self.compiled.clone(), self.assignments.take());
+
+        Ok(create_halo2_proof(&params, &pk, circuit, self.evm)?)
+    }
+
+    fn resolve(&mut self, _env: Env, (proof, instance): Self::Output) -> Result<Self::JsValue> {
+        Ok(Proof {
+            proof: proof.into(),
+            instance: instance
+                .iter()
+                .map(|column| column.iter().map(field_to_decimal).collect())
+                .collect(),
+        })
+    }
+}
+
+/// Parses the JSON of a circuit in `field`, `bn256` if it is not given, and returns it serialized
+/// back to JSON, as Rust parsed it, without compiling it.
+#[napi]
+pub fn parse_ast(ast_json: String, field: Option<String>) -> Result<String> {
+    let field = match field {
+        Some(field) => FieldOption::from_name(&field)?,
+        None => FieldOption::default(),
+    };
+
+    with_field!(field, F => {
+        let ast: SBPIR<F, ()> = from_json(&ast_json, "Circuit")?;
+        to_json_string(sbpir_to_json(&ast))
+    })
+}
+
+/// Parses a witness from Node: the JSON of a `TraceWitness` as a string, or its binary format as a
+/// `Buffer`.
+fn parse_witness<F: InterchangeField>(witness: &Either<String, Buffer>) -> Result<TraceWitness<F>> {
+    match witness {
+        Either::A(json) => Ok(from_json(json, "TraceWitness")?),
+        Either::B(bytes) => Ok(interchange::witness_from_binary(bytes)?),
+    }
+}
+
+fn to_json_string(json: std::result::Result<serde_json::Value, String>) -> Result<String> {
+    json.and_then(|json| serde_json::to_string_pretty(&json).map_err(|e| e.to_string()))
+        .map_err(|message| Error::new(Status::InvalidArg, message))
+}
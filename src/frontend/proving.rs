@@ -0,0 +1,323 @@
+//! Proving of compiled circuits with halo2 and KZG over bn256, shared by the bindings. The keys of
+//! each circuit are cached in its `CircuitKeys`, and the params here, by the path they are read
+//! from.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError},
+};
+
+use halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::{create_proof, keygen_pk, keygen_vk, verify_proof, ProvingKey, VerifyingKey},
+    poly::{
+        commitment::Params,
+        kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::{ProverSHPLONK, VerifierSHPLONK},
+            strategy::SingleStrategy,
+        },
+    },
+    transcript::{
+        Blake2bRead, Blake2bWrite, Challenge255, EncodedChallenge, TranscriptRead,
+        TranscriptReadBuffer, TranscriptWriterBuffer,
+    },
+};
+#[cfg(feature = "evm")]
+use halo2_solidity_verifier::{BatchOpenScheme, Keccak256Transcript, SolidityGenerator};
+use rand_core::OsRng;
+
+use crate::{
+    frontend::interchange::InterchangeError,
+    plonkish::backend::halo2::{ChiquitoHalo2, ChiquitoHalo2Circuit},
+};
+
+/// KZG params by the path they are read from, shared by the threads of the bindings.
+static PARAMS: OnceLock<Mutex<HashMap<String, Arc<ParamsKZG<Bn256>>>>> = OnceLock::new();
+
+/// Returns the KZG params read from `path`, from the cache after the first time.
+pub(crate) fn load_params(path: &str) -> Result<Arc<ParamsKZG<Bn256>>, InterchangeError> {
+    let cache = PARAMS.get_or_init(Default::default);
+    if let Some(params) = cache
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(path)
+        .cloned()
+    {
+        return Ok(params);
+    }
+
+    let file = File::open(path)
+        .map_err(|e| InterchangeError::Io(format!("cannot open params {}: {}", path, e)))?;
+    let params = ParamsKZG::<Bn256>::read(&mut BufReader::new(file))
+        .map_err(|e| InterchangeError::Io(format!("cannot read params {}: {}", path, e)))?;
+    let params = Arc::new(params);
+    cache
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(path.to_string(), params.clone());
+
+    Ok(params)
+}
+
+/// Writes KZG params of `k` to `path`, drawing their toxic waste here. Whoever runs this can forge
+/// proofs verified with them, so they are only meant for tests and development.
+pub(crate) fn write_unsafe_params(k: u32, path: &str) -> Result<(), InterchangeError> {
+    let params = ParamsKZG::<Bn256>::setup(k, OsRng);
+    let write_error = |e| InterchangeError::Io(format!("cannot write params {}: {}", path, e));
+
+    let mut writer = BufWriter::new(File::create(path).map_err(write_error)?);
+    params.write(&mut writer).map_err(write_error)?;
+    writer.flush().map_err(write_error)
+}
+
+/// Keys of a bn256 circuit by the path of the params they were generated with, shared by the
+/// threads that prove it. Keys are generated without the lock, so generating one does not block
+/// the other uses of the circuit.
+#[derive(Default)]
+pub(crate) struct CircuitKeys {
+    keys: Mutex<KeyMaps>,
+}
+
+#[derive(Default)]
+struct KeyMaps {
+    proving_keys: HashMap<String, Arc<ProvingKey<G1Affine>>>,
+    /// Verifying keys of the params the circuit was not proven with.
+    verifying_keys: HashMap<String, Arc<VerifyingKey<G1Affine>>>,
+}
+
+impl CircuitKeys {
+    /// Returns the proving key of `compiled` with the params of `params_path`, generating it the
+    /// first time.
+    pub(crate) fn proving_key(
+        &self,
+        params_path: &str,
+        params: &ParamsKZG<Bn256>,
+        compiled: &ChiquitoHalo2<Fr>,
+    ) -> Result<Arc<ProvingKey<G1Affine>>, InterchangeError> {
+        if let Some(pk) = self.keys().proving_keys.get(params_path).cloned() {
+            return Ok(pk);
+        }
+
+        let pk = Arc::new(generate_proving_key(params, compiled)?);
+        self.keys()
+            .proving_keys
+            .insert(params_path.to_string(), pk.clone());
+
+        Ok(pk)
+    }
+
+    /// Returns the verifying key of `compiled` with the params of `params_path`, the one of its
+    /// proving key if it was proven with them, or else generating it the first time.
+    pub(crate) fn verifying_key(
+        &self,
+        params_path: &str,
+        params: &ParamsKZG<Bn256>,
+        compiled: &ChiquitoHalo2<Fr>,
+    ) -> Result<Arc<VerifyingKey<G1Affine>>, InterchangeError> {
+        let cached = {
+            let keys = self.keys();
+            match keys.proving_keys.get(params_path) {
+                Some(pk) => Some(Arc::new(pk.get_vk().clone())),
+                None => keys.verifying_keys.get(params_path).cloned(),
+            }
+        };
+        if let Some(vk) = cached {
+            return Ok(vk);
+        }
+
+        let vk = Arc::new(generate_verifying_key(params, compiled)?);
+        self.keys()
+            .verifying_keys
+            .insert(params_path.to_string(), vk.clone());
+
+        Ok(vk)
+    }
+
+    /// Returns the params of `params_path`, or those `compiled` was proven with if `None`, and its
+    /// verifying key with them.
+    pub(crate) fn params_and_verifying_key(
+        &self,
+        params_path: Option<&str>,
+        compiled: &ChiquitoHalo2<Fr>,
+    ) -> Result<(Arc<ParamsKZG<Bn256>>, Arc<VerifyingKey<G1Affine>>), InterchangeError> {
+        let params_path = match params_path {
+            Some(params_path) => params_path.to_string(),
+            None => self.proven_params_path()?,
+        };
+        let params = load_params(&params_path)?;
+        let vk = self.verifying_key(&params_path, &params, compiled)?;
+
+        Ok((params, vk))
+    }
+
+    /// Returns the path of the params that the circuit was proven with, if only one.
+    pub(crate) fn proven_params_path(&self) -> Result<String, InterchangeError> {
+        let keys = self.keys();
+        let paths: Vec<_> = keys.proving_keys.keys().collect();
+
+        match paths.as_slice() {
+            [path] => Ok(path.to_string()),
+            [] => Err(InterchangeError::Value(
+                "circuit was not proven in this process, the params_path is needed".to_string(),
+            )),
+            _ => Err(InterchangeError::Value(
+                "circuit was proven with several params, the params_path is needed".to_string(),
+            )),
+        }
+    }
+
+    fn keys(&self) -> MutexGuard<'_, KeyMaps> {
+        self.keys.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+pub(crate) fn generate_verifying_key(
+    params: &ParamsKZG<Bn256>,
+    compiled: &ChiquitoHalo2<Fr>,
+) -> Result<VerifyingKey<G1Affine>, InterchangeError> {
+    let circuit = ChiquitoHalo2Circuit::new(compiled.clone(), None);
+
+    keygen_vk(params, &circuit)
+        .map_err(|e| InterchangeError::Proving(format!("verifying key generation failed: {:?}", e)))
+}
+
+pub(crate) fn generate_proving_key(
+    params: &ParamsKZG<Bn256>,
+    compiled: &ChiquitoHalo2<Fr>,
+) -> Result<ProvingKey<G1Affine>, InterchangeError> {
+    let vk = generate_verifying_key(params, compiled)?;
+    let circuit = ChiquitoHalo2Circuit::new(compiled.clone(), None);
+
+    keygen_pk(params, vk, &circuit)
+        .map_err(|e| InterchangeError::Proving(format!("proving key generation failed: {:?}", e)))
+}
+
+/// Generates a proof of `circuit`, with its witness. Returns the proof and the public inputs, by
+/// instance column. With `evm`, the proof uses the Keccak256 transcript of the verifier of
+/// `solidity_verifier`, instead of Blake2b.
+pub(crate) fn create_halo2_proof(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: ChiquitoHalo2Circuit<Fr>,
+    evm: bool,
+) -> Result<(Vec<u8>, Vec<Vec<Fr>>), InterchangeError> {
+    let instance = circuit.instance();
+    let instance_slices: Vec<&[Fr]> = instance.iter().map(Vec::as_slice).collect();
+
+    let proof = if evm {
+        #[cfg(feature = "evm")]
+        {
+            let transcript = Keccak256Transcript::new(vec![]);
+            create_proof_with(params, pk, circuit, &instance_slices, transcript)?
+        }
+        #[cfg(not(feature = "evm"))]
+        return Err(evm_disabled());
+    } else {
+        let transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+        create_proof_with(params, pk, circuit, &instance_slices, transcript)?
+    };
+
+    Ok((proof, instance))
+}
+
+fn create_proof_with<E, T>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: ChiquitoHalo2Circuit<Fr>,
+    instance: &[&[Fr]],
+    mut transcript: T,
+) -> Result<Vec<u8>, InterchangeError>
+where
+    E: EncodedChallenge<G1Affine>,
+    T: TranscriptWriterBuffer<Vec<u8>, G1Affine, E>,
+{
+    create_proof::<KZGCommitmentScheme<Bn256>, ProverSHPLONK<'_, Bn256>, _, _, _, _>(
+        params,
+        pk,
+        &[circuit],
+        &[instance],
+        OsRng,
+        &mut transcript,
+    )
+    .map_err(|e| InterchangeError::Proving(format!("proof generation failed: {:?}", e)))?;
+
+    Ok(transcript.finalize())
+}
+
+/// Verifies a `proof` with its public inputs `instance`, by instance column. Returns if the proof
+/// is valid. With `evm`, the proof is one of the Keccak256 transcript, see `create_halo2_proof`.
+pub(crate) fn verify_halo2_proof(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    proof: &[u8],
+    instance: &[Vec<Fr>],
+    evm: bool,
+) -> Result<bool, InterchangeError> {
+    let instance_slices: Vec<&[Fr]> = instance.iter().map(Vec::as_slice).collect();
+    if evm {
+        #[cfg(feature = "evm")]
+        return Ok(verify_proof_with(
+            params,
+            vk,
+            &instance_slices,
+            Keccak256Transcript::new(proof),
+        ));
+        #[cfg(not(feature = "evm"))]
+        return Err(evm_disabled());
+    }
+    let transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(proof);
+
+    Ok(verify_proof_with(params, vk, &instance_slices, transcript))
+}
+
+fn verify_proof_with<E, T>(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    instance: &[&[Fr]],
+    mut transcript: T,
+) -> bool
+where
+    E: EncodedChallenge<G1Affine>,
+    T: TranscriptRead<G1Affine, E>,
+{
+    let strategy = SingleStrategy::new(params);
+
+    verify_proof::<KZGCommitmentScheme<Bn256>, VerifierSHPLONK<'_, Bn256>, _, _, _>(
+        params,
+        vk,
+        strategy,
+        &[instance],
+        &mut transcript,
+    )
+    .is_ok()
+}
+
+/// Returns the source of a Solidity contract that verifies the proofs of `compiled` generated with
+/// `evm`, see `create_halo2_proof`. The verifying key is embedded in the contract.
+pub(crate) fn solidity_verifier(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    compiled: &ChiquitoHalo2<Fr>,
+) -> Result<String, InterchangeError> {
+    #[cfg(feature = "evm")]
+    {
+        let num_instances = compiled.circuit().exposed.len();
+
+        SolidityGenerator::new(params, vk, BatchOpenScheme::Bdfg21, num_instances)
+            .render()
+            .map_err(|e| InterchangeError::Proving(format!("verifier generation failed: {:?}", e)))
+    }
+    #[cfg(not(feature = "evm"))]
+    {
+        let _ = (params, vk, compiled);
+        Err(evm_disabled())
+    }
+}
+
+#[cfg(not(feature = "evm"))]
+fn evm_disabled() -> InterchangeError {
+    InterchangeError::Proving("chiquito is built without the evm feature".to_string())
+}
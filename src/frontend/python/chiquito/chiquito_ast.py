@@ -4,6 +4,7 @@ from dataclasses import dataclass, field, asdict
 
 # from chiquito import wit_gen, expr, query, util
 
+from chiquito import rust_chiquito
 from chiquito.wit_gen import FixedAssignment, TraceWitness
 from chiquito.expr import Expr
 from chiquito.util import uuid, F
@@ -33,8 +34,12 @@ from chiquito.query import Queriable, Fixed
 
 @dataclass
 class ASTSuperCircuit:
-    sub_circuits: Dict[int, ASTCircuit] = field(default_factory=dict)
-    super_witness: Dict[int, TraceWitness] = field(default_factory=dict)
+    sub_circuits: Dict[rust_chiquito.CircuitHandle, ASTCircuit] = field(
+        default_factory=dict
+    )
+    super_witness: Dict[rust_chiquito.CircuitHandle, TraceWitness] = field(
+        default_factory=dict
+    )
 
 
 @dataclass
@@ -154,14 +159,26 @@ class ASTCircuit:
         self.annotations[step_type.id] = name
         self.step_types[step_type.id] = step_type
 
+    # the column of a fixed signal of the circuit has a value per step, of which the one at offset
+    # is placed in the first row of the offset-th step instance
     def add_fixed_assignment(self: ASTCircuit, offset: int, lhs: Queriable, rhs: F):
         if not isinstance(lhs, Fixed):
             raise ValueError(f"Cannot assign to non-fixed signal.")
-        if lhs in self.fixed_assignments.keys():
-            self.fixed_assignments[lhs][offset] = rhs
-        else:
-            self.fixed_assignments[lhs] = [F.zero()] * self.num_steps
-            self.fixed_assignments[lhs][offset] = rhs
+        if lhs.rotation != 0:
+            raise ValueError(f"Cannot assign to rotated fixed signal {lhs}.")
+        if all(signal.id != lhs.uuid() for signal in self.fixed_signals):
+            raise ValueError(f"Cannot assign to fixed signal {lhs} of another circuit.")
+        if not 0 <= offset < self.num_steps:
+            raise ValueError(
+                f"Cannot assign to {lhs} at offset {offset}, outside of the {self.num_steps} steps."
+            )
+        for assigned, column in self.fixed_assignments.items():
+            if assigned.uuid() == lhs.uuid():
+                column[offset] = rhs
+                return
+        column = [F.zero()] * self.num_steps
+        column[offset] = rhs
+        self.fixed_assignments[lhs] = column
 
     def get_step_type(self, uuid: int) -> ASTStepType:
         if uuid in self.step_types.keys():
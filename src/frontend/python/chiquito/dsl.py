@@ -1,7 +1,8 @@
 from __future__ import annotations
-from typing import List, Dict, Union
+from typing import List, Dict, Optional, Tuple, Union
 from enum import Enum
-from typing import Callable, Any
+from typing import Callable, Any, Iterable
+from itertools import cycle
 
 from chiquito import rust_chiquito
 import json
@@ -18,7 +19,40 @@ from chiquito.cb import (
     LookupTableBuilder,
     InPlaceLookupBuilder,
 )
-from chiquito.util import CustomEncoder, F
+from chiquito.util import CustomEncoder, F, FIELD_MODULI, set_field
+from chiquito.visualize import Visualization
+
+
+# Options of the chiquito compiler. cell_manager is "single_row" or "max_width", with max_width
+# columns, step_selector is "simple", "log_n" or "two_steps", optimization_level is 0 for none, 1
+# to simplify the constraints and 2 to also optimize the evaluation of the gates, and a circuit of
+# a degree above max_degree fails to compile. field is the field the circuit is compiled to,
+# "bn256", "pasta_fp" or "pasta_fq", of which only "bn256" circuits are proven.
+class CompilerConfig:
+    def __init__(
+        self: CompilerConfig,
+        cell_manager: str = "single_row",
+        max_width: Optional[int] = None,
+        same_height: bool = False,
+        step_selector: str = "simple",
+        optimization_level: int = 0,
+        max_degree: Optional[int] = None,
+        field: str = "bn256",
+    ):
+        if field not in FIELD_MODULI:
+            raise ValueError(
+                f"CompilerConfig: unknown field {field}, expected one of {', '.join(FIELD_MODULI)}."
+            )
+        self.cell_manager = cell_manager
+        self.max_width = max_width
+        self.same_height = same_height
+        self.step_selector = step_selector
+        self.optimization_level = optimization_level
+        self.max_degree = max_degree
+        self.field = field
+
+    def get_json(self: CompilerConfig) -> str:
+        return json.dumps(self.__dict__)
 
 
 class SuperCircuitMode(Enum):
@@ -30,8 +64,11 @@ class SuperCircuitMode(Enum):
 class SuperCircuit:
     def __init__(
         self: SuperCircuit,
+        config: Optional[CompilerConfig] = None,
         **kwargs,  # **kwargs is intended for arbitrary names for imports
     ):
+        self.config = config
+        set_field(config.field if config is not None else "bn256")
         for key, value in kwargs.items():
             setattr(self, key, value)
         self.ast = ASTSuperCircuit()
@@ -42,12 +79,12 @@ class SuperCircuit:
     # called under setup()
     def sub_circuit(self: SuperCircuit, sub_circuit: Circuit) -> Circuit:
         assert self.mode == SuperCircuitMode.SETUP
-        if sub_circuit.rust_id != 0:
+        if sub_circuit.rust_id is not None:
             raise ValueError(
                 "SuperCircuit: sub_circuit() cannot be called twice on the same circuit."
             )
         ast_json: str = sub_circuit.get_ast_json()
-        sub_circuit.rust_id: int = rust_chiquito.ast_map_store(ast_json)
+        sub_circuit.rust_id = rust_chiquito.ast_map_store(ast_json)
         self.ast.sub_circuits[sub_circuit.rust_id] = sub_circuit.ast
         return sub_circuit
 
@@ -56,7 +93,7 @@ class SuperCircuit:
     def map(self: SuperCircuit, sub_circuit: Circuit, *args: Any) -> TraceWitness:
         assert self.mode == SuperCircuitMode.Mapping
         witness: TraceWitness = sub_circuit.gen_witness(*args)
-        if sub_circuit.rust_id == 0:
+        if sub_circuit.rust_id is None:
             raise ValueError(
                 "SuperCircuit: must call sub_circuit() before calling map() on a Circuit."
             )
@@ -65,30 +102,90 @@ class SuperCircuit:
 
     # called at the outermost level
     # generates TraceWitness mapping
-    def gen_witness(self: SuperCircuit, *args: Any) -> Dict[int, TraceWitness]:
+    def gen_witness(
+        self: SuperCircuit, *args: Any
+    ) -> Dict[rust_chiquito.CircuitHandle, TraceWitness]:
         self.mode = SuperCircuitMode.Mapping
         self.mapping(*args)
         self.mode = SuperCircuitMode.NoMode
-        super_witness: Dict[int, TraceWitness] = self.ast.super_witness
+        super_witness: Dict[
+            rust_chiquito.CircuitHandle, TraceWitness
+        ] = self.ast.super_witness
         del (
             self.ast.super_witness
         )  # so that we can generate different witness mapping in the next gen_witness() call
         return super_witness
 
+    # the whole super circuit as a single JSON document, with the sub-circuits in order
+    def get_ast_json(self: SuperCircuit) -> str:
+        return json.dumps(
+            {"sub_circuits": list(self.ast.sub_circuits.values())},
+            cls=CustomEncoder,
+            indent=4,
+        )
+
+    # k is the smallest that fits the super circuit if None
     def halo2_mock_prover(
-        self: SuperCircuit, super_witness: Dict[int, TraceWitness], k: int = 16
+        self: SuperCircuit,
+        super_witness: Dict[rust_chiquito.CircuitHandle, TraceWitness],
+        k: Optional[int] = None,
     ):
-        witness_json = {}
+        rust_ids = list(self.ast.sub_circuits.keys())
+        witness_bytes = {}
         for rust_id, witness in super_witness.items():
             if rust_id not in self.ast.sub_circuits:
                 raise ValueError(
                     f"SuperCircuit.halo2_mock_prover(): TraceWitness with rust_id {rust_id} not found in sub_circuits."
                 )
+            witness_bytes[rust_ids.index(rust_id)] = witness.get_witness_bytes()
+        rust_chiquito.super_circuit_ast_halo2_mock_prover(
+            self.get_ast_json(), witness_bytes, k, get_config_json(self.config)
+        )
+
+    # the PIL of all sub-circuits in a single file, each in the namespace circuit_name_<index>
+    def to_pil(
+        self: SuperCircuit,
+        super_witness: Dict[rust_chiquito.CircuitHandle, TraceWitness],
+        circuit_name: str = "SuperCircuit",
+    ) -> str:
+        witness_json = {}
+        for rust_id, witness in super_witness.items():
+            if rust_id not in self.ast.sub_circuits:
+                raise ValueError(
+                    f"SuperCircuit.to_pil(): TraceWitness with rust_id {rust_id} not found in sub_circuits."
+                )
             witness_json[rust_id] = witness.get_witness_json()
-        rust_chiquito.super_circuit_halo2_mock_prover(
-            list(self.ast.sub_circuits.keys()), witness_json, k
+        return rust_chiquito.super_circuit_to_pil(
+            list(self.ast.sub_circuits.keys()), witness_json, circuit_name
+        )
+
+
+# Witness of a circuit sent to Rust in batches of step instances, which Rust assigns as they
+# arrive, so a huge trace is never materialized whole. After finish(), it is passed instead of a
+# TraceWitness to Circuit.halo2_mock_prover or Circuit.halo2_prove, which consume it.
+class WitnessStream:
+    # witness_id is that of a witness already begun in Rust, like the finished witness of
+    # Circuit.witness_from_steps
+    def __init__(
+        self: WitnessStream,
+        rust_id: rust_chiquito.CircuitHandle,
+        witness_id: Optional[int] = None,
+    ):
+        if witness_id is None:
+            witness_id = rust_chiquito.witness_begin(rust_id)
+        self.witness_id: int = witness_id
+
+    # returns the step instances added so far
+    def add_steps(self: WitnessStream, step_instances: List[StepInstance]) -> int:
+        witness = TraceWitness(step_instances)
+        return rust_chiquito.witness_add_steps(
+            self.witness_id, witness.get_witness_bytes()
         )
 
+    def finish(self: WitnessStream) -> WitnessStream:
+        rust_chiquito.witness_finish(self.witness_id)
+        return self
+
 
 class CircuitMode(Enum):
     NoMode = 0
@@ -101,12 +198,18 @@ class Circuit:
     def __init__(
         self: Circuit,
         super_circuit: SuperCircuit = None,
+        config: Optional[CompilerConfig] = None,
         **kwargs,  # **kwargs is intended for arbitrary names for imports
     ):
         self.ast = ASTCircuit()
+        self.config = config
         self.witness = TraceWitness()
-        self.rust_id = 0
+        # handle of the circuit compiled in Rust, freed with the circuit
+        self.rust_id: Optional[rust_chiquito.CircuitHandle] = None
         self.super_circuit = super_circuit
+        # sub-circuits are in the field of their super circuit
+        field_config = super_circuit.config if super_circuit is not None else config
+        set_field(field_config.field if field_config is not None else "bn256")
         for key, value in kwargs.items():
             setattr(self, key, value)
         self.mode = CircuitMode.SETUP
@@ -206,6 +309,41 @@ class Circuit:
             )
         self.ast.add_fixed_assignment(offset, lhs, F(rhs))
 
+    # called under fixed_gen()
+    # assigns the values from the first offset, stopping at num_steps, so the offsets after fewer
+    # values keep theirs
+    def assign_iter(self: Circuit, lhs: Queriable, values: Iterable[Any]):
+        for offset, value in zip(range(self.ast.num_steps), values):
+            self.assign(offset, lhs, value)
+
+    # called under fixed_gen()
+    # assigns value(offset) to every offset
+    def assign_with(self: Circuit, lhs: Queriable, value: Callable[[int], Any]):
+        self.assign_iter(lhs, map(value, range(self.ast.num_steps)))
+
+    # called under fixed_gen()
+    # assigns pattern repeatedly, so offset i has pattern[i % len(pattern)], as for a table of
+    # the steps of a cycle
+    def assign_repeat(self: Circuit, lhs: Queriable, pattern: List[Any]):
+        if len(pattern) == 0:
+            raise ValueError("Circuit.assign_repeat(): cannot repeat an empty pattern.")
+        self.assign_iter(lhs, cycle(pattern))
+
+    # called under fixed_gen()
+    # assigns the values of the sources in turns, so offset i has the next value of
+    # sources[i % len(sources)], stopping at num_steps or at the first exhausted source
+    def assign_interleave(self: Circuit, lhs: Queriable, sources: List[Iterable[Any]]):
+        if len(sources) == 0:
+            raise ValueError("Circuit.assign_interleave(): cannot interleave without sources.")
+        iterators = [iter(source) for source in sources]
+        values = []
+        for offset in range(self.ast.num_steps):
+            value = next(iterators[offset % len(iterators)], None)
+            if value is None:
+                break
+            values.append(value)
+        self.assign_iter(lhs, values)
+
     def gen_witness(self: Circuit, *args) -> TraceWitness:
         self.mode = CircuitMode.Trace
         self.witness = TraceWitness()
@@ -218,21 +356,172 @@ class Circuit:
     def get_ast_json(self: Circuit) -> str:
         return json.dumps(self.ast, cls=CustomEncoder, indent=4)
 
-    def halo2_mock_prover(self: Circuit, witness: TraceWitness, k: int = 16):
-        if self.rust_id == 0:
-            ast_json: str = self.get_ast_json()
-            self.rust_id: int = rust_chiquito.ast_to_halo2(ast_json)
-        witness_json: str = witness.get_witness_json()
-        rust_chiquito.halo2_mock_prover(witness_json, self.rust_id, k)
+    # Compiles the circuit in Rust the first time it is needed, and returns its handle.
+    def _ensure_compiled(self: Circuit) -> rust_chiquito.CircuitHandle:
+        if self.rust_id is None:
+            self.rust_id = rust_chiquito.ast_to_halo2(
+                self.get_ast_json(), get_config_json(self.config)
+            )
+        return self.rust_id
+
+    # Begins a witness sent to Rust in batches of step instances, see WitnessStream.
+    def witness_stream(self: Circuit) -> WitnessStream:
+        return WitnessStream(self._ensure_compiled())
+
+    # Generates the witness in Rust a step instance at a time, so the trace is never whole in
+    # Python. step_fn(index, previous) returns the step type of the step instance at index
+    # followed by the args of its wg, or None once the trace is complete, where previous maps the
+    # annotations of the signals of the previous step instance, like "a" or "next(a)", to their
+    # values, and is None for the first one. Returns the finished WitnessStream, padded like the
+    # witness of gen_witness().
+    def witness_from_steps(
+        self: Circuit,
+        step_fn: Callable[[int, Optional[Dict[str, F]]], Optional[Tuple[StepType, ...]]],
+    ) -> WitnessStream:
+        rust_id = self._ensure_compiled()
+
+        def step(index: int, previous: Optional[Dict[str, int]]) -> Optional[bytes]:
+            if index >= self.ast.num_steps:
+                return None
+            if previous is not None:
+                previous = {signal: F(value) for signal, value in previous.items()}
+            next_step = step_fn(index, previous)
+            if next_step is None:
+                return None
+            step_type, *args = next_step
+            step_instance = step_type.gen_step_instance(*args)
+            return TraceWitness([step_instance]).get_witness_bytes()
+
+        witness_id = rust_chiquito.witness_from_steps(rust_id, step)
+        return WitnessStream(rust_id, witness_id)
+
+    # k is that of estimate_k() if None
+    def halo2_mock_prover(
+        self: Circuit,
+        witness: Union[TraceWitness, WitnessStream],
+        k: Optional[int] = None,
+    ):
+        rust_chiquito.halo2_mock_prover(
+            get_witness_data(witness), self._ensure_compiled(), k
+        )
+
+    # Checks the witness against the constraints, transitions and lookups of the circuit natively,
+    # much faster than the mock prover. Returns the violations in step order, empty if the witness
+    # satisfies the circuit, each a dict with its "message", "kind", "step", "step_type",
+    # "annotation", "location" and "explanation", the values of the failing expression by line.
+    def check_witness(self: Circuit, witness: TraceWitness) -> List[Dict[str, Any]]:
+        return rust_chiquito.check_witness(
+            self._ensure_compiled(), witness.get_witness_bytes()
+        )
+
+    # Mock proves each witness, in parallel, and returns a dict per witness, in order, with "ok"
+    # and the "failures" of the mock prover as strings. k is that of estimate_k() if None.
+    def halo2_mock_prove_many(
+        self: Circuit,
+        witnesses: List[Union[TraceWitness, WitnessStream]],
+        k: Optional[int] = None,
+    ) -> List[Dict[str, Any]]:
+        return rust_chiquito.halo2_mock_prove_many(
+            self._ensure_compiled(), [get_witness_data(witness) for witness in witnesses], k
+        )
+
+    # The AST as Rust parsed it, serialized back to JSON, to compare with get_ast_json().
+    def get_rust_ast_json(self: Circuit) -> str:
+        return rust_chiquito.ast_to_json(self._ensure_compiled())
+
+    # The plonkish IR the circuit is compiled to, as JSON: its columns, polys, lookups, fixed
+    # assignments, exposed cells and number of rows, and the placement of its signals in the
+    # columns, the internal ones by step type. Sub-circuits are compiled with their super circuit,
+    # so they have none.
+    def get_plonkish_json(self: Circuit) -> str:
+        return rust_chiquito.plonkish_to_json(self._ensure_compiled())
+
+    # get_plonkish_json() parsed, to assert on in tests.
+    def get_plonkish(self: Circuit) -> Dict[str, Any]:
+        return json.loads(self.get_plonkish_json())
+
+    # The smallest k of a halo2 table that fits the compiled circuit, with its rows and the
+    # blinding rows of halo2. Params of this k prove it, and MockProver fails with a smaller one.
+    def estimate_k(self: Circuit) -> int:
+        return rust_chiquito.estimate_k(self._ensure_compiled())
+
+    # Size of the compiled circuit in the halo2 table: columns, rows, polys, lookups, max degree
+    # and the smallest k that fits it.
+    def stats(self: Circuit) -> Dict[str, int]:
+        return rust_chiquito.circuit_stats(self._ensure_compiled())
+
+    # Diagram of the step types and the step types that can follow each one, as allowed by the
+    # transition constraints that only query the next step type. Displayed inline in notebooks.
+    def step_diagram(self: Circuit) -> Visualization:
+        return Visualization(rust_chiquito.step_diagram(self._ensure_compiled()))
+
+    # Heat map of the cells the signals of each step type take in each column, and those of the
+    # forward, shared and fixed signals. Displayed inline in notebooks.
+    def column_layout(self: Circuit) -> Visualization:
+        return Visualization(rust_chiquito.column_layout(self._ensure_compiled()))
+
+    # The constraints, transition constraints and lookups of each step type, with their degrees
+    # before the step selector. Displayed inline in notebooks.
+    def constraint_list(self: Circuit) -> Visualization:
+        return Visualization(rust_chiquito.constraint_list(self._ensure_compiled()))
+
+    # params_path is a file of KZG params (SRS) for bn256, of the k of the circuit. Returns the
+    # proof and the public inputs, by instance column. With evm, the proof is verifiable by the
+    # contract of solidity_verifier.
+    def halo2_prove(
+        self: Circuit,
+        witness: Union[TraceWitness, WitnessStream],
+        params_path: str,
+        evm: bool = False,
+    ) -> Tuple[bytes, List[List[int]]]:
+        return rust_chiquito.halo2_prove(
+            self._ensure_compiled(), get_witness_data(witness), params_path, evm
+        )
+
+    # Like halo2_prove, but proves on a thread of its own, and returns a ProvingTask at once. Its
+    # progress() is the (phase, percent) of the proof, done() is whether it finished, and result()
+    # waits for it. progress, if given, is called with the phase and percent as each phase begins,
+    # from the thread of the task.
+    def halo2_prove_async(
+        self: Circuit,
+        witness: Union[TraceWitness, WitnessStream],
+        params_path: str,
+        evm: bool = False,
+        progress: Optional[Callable[[str, int], None]] = None,
+    ):
+        return rust_chiquito.halo2_prove_async(
+            self._ensure_compiled(), get_witness_data(witness), params_path, evm, progress
+        )
+
+    # Verifies a proof of the circuit, possibly generated elsewhere, with its public inputs by
+    # instance column. Without params_path, uses the params that halo2_prove was called with.
+    def halo2_verify(
+        self: Circuit,
+        proof: bytes,
+        instances: List[List[int]],
+        params_path: Optional[str] = None,
+        evm: bool = False,
+    ) -> bool:
+        return rust_chiquito.halo2_verify(
+            self._ensure_compiled(), proof, instances, params_path, evm
+        )
+
+    # Returns the verifying key of the circuit, serialized in the raw bytes format of halo2.
+    # Without params_path, uses the params that halo2_prove was called with.
+    def halo2_verifying_key(self: Circuit, params_path: Optional[str] = None) -> bytes:
+        return rust_chiquito.halo2_verifying_key(self._ensure_compiled(), params_path)
+
+    # Returns the source of a Solidity contract verifying the proofs of the circuit generated by
+    # halo2_prove with evm=True. Needs chiquito built with the evm feature. Without params_path,
+    # uses the params that halo2_prove was called with.
+    def solidity_verifier(self: Circuit, params_path: Optional[str] = None) -> str:
+        return rust_chiquito.solidity_verifier(self._ensure_compiled(), params_path)
 
     def to_pil(
         self: Circuit, witness: TraceWitness, circuit_name: str = "Circuit"
     ) -> str:
-        if self.rust_id == 0:
-            ast_json: str = self.get_ast_json()
-            self.rust_id: int = rust_chiquito.ast_to_halo2(ast_json)
         witness_json: str = witness.get_witness_json()
-        rust_chiquito.to_pil(witness_json, self.rust_id, circuit_name)
+        rust_chiquito.to_pil(witness_json, self._ensure_compiled(), circuit_name)
 
     def __str__(self: Circuit) -> str:
         return self.ast.__str__()
@@ -296,3 +585,14 @@ class StepType:
 
 
 LookupBuilder = Union[LookupTableBuilder, InPlaceLookupBuilder]
+
+
+def get_config_json(config: Optional[CompilerConfig]) -> Optional[str]:
+    return None if config is None else config.get_json()
+
+
+# a finished WitnessStream by its id, or a TraceWitness in binary
+def get_witness_data(witness: Union[TraceWitness, WitnessStream]) -> Union[int, bytes]:
+    if isinstance(witness, WitnessStream):
+        return witness.witness_id
+    return witness.get_witness_bytes()
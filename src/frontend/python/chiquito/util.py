@@ -4,15 +4,21 @@ from uuid import uuid1
 import json
 
 
+# moduli of the fields a circuit is compiled to, by the name of CompilerConfig.field
+FIELD_MODULI = {
+    "bn256": 21888242871839275222246405745257275088548364400416034343698204186575808495617,
+    "pasta_fp": 0x40000000000000000000000000000000224698FC094CF91B992D30ED00000001,
+    "pasta_fq": 0x40000000000000000000000000000000224698FC0994A8DD8C46EB2100000001,
+}
+
+
 class F(bn128.FQ):
-    field_modulus = (
-        21888242871839275222246405745257275088548364400416034343698204186575808495617
-    )
+    field_modulus = FIELD_MODULI["bn256"]
 
     def __json__(self: F):
         R = 2**256
         # Convert the integer to a byte array
-        montgomery_form = self.n * R % F.field_modulus
+        montgomery_form = self.n * R % type(self).field_modulus
         byte_array = montgomery_form.to_bytes(32, "little")
         
         # return the hex string
@@ -26,6 +32,15 @@ class CustomEncoder(json.JSONEncoder):
         return super().default(obj)
 
 
+# sets the field of the F elements of the circuits defined from now on
+def set_field(name: str):
+    if name not in FIELD_MODULI:
+        raise ValueError(
+            f"Unknown field {name}, expected one of {', '.join(FIELD_MODULI)}."
+        )
+    F.field_modulus = FIELD_MODULI[name]
+
+
 # int field is the u128 version of uuid.
 def uuid() -> str:
     return uuid1(node=int.from_bytes([10, 10, 10, 10, 10, 10], byteorder="little")).int.__str__()
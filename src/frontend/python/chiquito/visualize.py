@@ -0,0 +1,20 @@
+from __future__ import annotations
+from typing import Any, Dict, Tuple
+import json
+
+
+# A visualization of a circuit, displayed inline by Jupyter from its HTML, which has no scripts.
+# data is the JSON it is rendered from, parsed, to plot it otherwise.
+class Visualization:
+    def __init__(self: Visualization, json_and_html: Tuple[str, str]):
+        data_json, html = json_and_html
+        self.data: Dict[str, Any] = json.loads(data_json)
+        self.html: str = html
+
+    def _repr_html_(self: Visualization) -> str:
+        return self.html
+
+    # Writes the visualization to a standalone HTML page, to open outside of a notebook.
+    def save_html(self: Visualization, path: str):
+        with open(path, "w") as file:
+            file.write(f"<!DOCTYPE html>\n<html><body>{self.html}</body></html>\n")
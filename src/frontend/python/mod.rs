@@ -1,107 +1,448 @@
 use pyo3::{
+    create_exception,
+    exceptions::{PyException, PyIOError, PyValueError},
     prelude::*,
-    types::{PyDict, PyList, PyLong, PyString},
+    types::{PyBytes, PyDict, PyList, PyLong, PyString},
 };
-use serde_json::{from_str, Value};
 
 use crate::{
-    frontend::dsl::{StepTypeHandler, SuperCircuitContext},
-    pil::backend::powdr_pil::chiquito2Pil,
+    frontend::{
+        dsl::SuperCircuitContext,
+        interchange::{
+            self, catch_compilation, check_witness, field_from_decimal, field_to_decimal,
+            from_json, mock_prover, panic_message, plonkish_to_json, sbpir_to_json,
+            violation_kind_name, with_field, InterchangeError, StoredCircuit,
+        },
+        proving::{
+            create_halo2_proof, load_params, solidity_verifier, verify_halo2_proof,
+            write_unsafe_params, CircuitKeys,
+        },
+        schema, visualize,
+    },
+    pil::backend::powdr_pil::{chiquito2Pil, chiquitoSuperCircuit2Pil},
     plonkish::{
         backend::halo2::{
             chiquito2Halo2, chiquitoSuperCircuit2Halo2, ChiquitoHalo2, ChiquitoHalo2Circuit,
-            ChiquitoHalo2SuperCircuit,
+            ChiquitoHalo2SuperCircuit, CircuitStats,
         },
         compiler::{
             cell_manager::SingleRowCellManager, compile, config,
             step_selector::SimpleStepSelectorBuilder,
         },
-        ir::{assignments::AssignmentGenerator, sc::MappingContext},
-    },
-    poly::Expr,
-    sbpir::{
-        query::Queriable, Constraint, ExposeOffset, FixedSignal, ForwardSignal, InternalSignal,
-        Lookup, SharedSignal, StepType, StepTypeUUID, TransitionConstraint, SBPIR,
+        ir::{
+            assignments::{
+                AssignmentGenerator, Assignments, StreamingAssignments, WitnessAssigner,
+            },
+            sc::MappingContext,
+        },
     },
+    sbpir::{checker::Violation, SBPIR},
     util::{uuid, UUID},
-    wit_gen::{StepInstance, TraceContext, TraceWitness},
+    wit_gen::{StepInstance, TraceWitness},
 };
 
 use core::result::Result;
-use halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
-use serde::de::{self, Deserialize, Deserializer, IgnoredAny, MapAccess, Visitor};
-use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
-
-type CircuitMapStore = (
-    SBPIR<Fr, ()>,
-    ChiquitoHalo2<Fr>,
-    Option<AssignmentGenerator<Fr, ()>>,
+use halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::VerifyingKey,
+    poly::kzg::commitment::ParamsKZG,
+    SerdeFormat,
+};
+
+pub use crate::frontend::interchange::{
+    AnyCellManager, AnyStepSelectorBuilder, CellManagerOption, CompilerOptions, FieldOption,
+    InterchangeField as PyField, StepSelectorOption, SuperCircuitAst,
+};
+use serde_json::Value;
+use std::{
+    any::Any,
+    collections::HashMap,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError},
+    thread::{self, JoinHandle},
+};
+
+/// Circuits stored for Python, by rust_id, each owned by a `CircuitHandle`. Shared by the threads
+/// of the interpreter and those of `ProvingTask`.
+static CIRCUITS: OnceLock<Mutex<HashMap<UUID, StoredCircuit>>> = OnceLock::new();
+
+/// Witnesses streamed from Python, by the id returned by `chiquito_witness_begin`, with their
+/// field, as the `WitnessStream` of it. Shared by the threads of the interpreter, like the
+/// circuits, so a witness can be streamed on one thread and proven on another.
+static WITNESS_STREAMS: OnceLock<Mutex<WitnessStreams>> = OnceLock::new();
+
+type WitnessStreams = HashMap<UUID, (FieldOption, Box<dyn Any + Send>)>;
+
+fn circuits() -> MutexGuard<'static, HashMap<UUID, StoredCircuit>> {
+    CIRCUITS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+fn witness_streams() -> MutexGuard<'static, WitnessStreams> {
+    WITNESS_STREAMS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Stores `circuit` under a new rust_id, and returns it.
+fn store_circuit(circuit: StoredCircuit) -> UUID {
+    let rust_id = uuid();
+    circuits().insert(rust_id, circuit);
+
+    rust_id
+}
+
+/// Runs `f` on the stored circuit of `rust_id`, holding the lock of the store.
+fn with_circuit<T>(rust_id: UUID, f: impl FnOnce(&mut StoredCircuit) -> T) -> PyResult<T> {
+    circuits()
+        .get_mut(&rust_id)
+        .map(f)
+        .ok_or_else(|| unknown_rust_id(rust_id))
+}
+
+/// Returns the compiled circuit of `rust_id` and its assigner, if it has one. Fails if the circuit
+/// is not of `F`.
+fn compiled_circuit<F: PyField>(
+    rust_id: UUID,
+) -> PyResult<(ChiquitoHalo2<F>, Option<Arc<WitnessAssigner<F>>>)> {
+    with_circuit(rust_id, |circuit| {
+        let compiled = circuit.compiled::<F>()?;
+        Ok::<_, PyErr>((compiled.halo2.clone(), compiled.assigner.clone()))
+    })?
+}
+
+fn circuit_field(rust_id: UUID) -> PyResult<FieldOption> {
+    with_circuit(rust_id, |circuit| circuit.field())
+}
+
+/// Returns the keys of the circuit of `rust_id`, kept after it is freed by the proofs that use
+/// them.
+fn circuit_keys(rust_id: UUID) -> PyResult<Arc<CircuitKeys>> {
+    with_circuit(rust_id, |circuit| circuit.keys())
+}
+
+/// Removes the circuit of `rust_id` and its keys. Removing a circuit that is not stored does
+/// nothing.
+pub fn chiquito_free(rust_id: UUID) {
+    circuits().remove(&rust_id);
+}
+
+/// Handle of a circuit stored in Rust, returned to Python. The circuit is freed when the handle is
+/// garbage collected, or before with `free`.
+#[pyclass(module = "rust_chiquito")]
+pub struct CircuitHandle {
+    rust_id: UUID,
+}
+
+#[pymethods]
+impl CircuitHandle {
+    #[getter]
+    fn rust_id(&self) -> UUID {
+        self.rust_id
+    }
+
+    /// Frees the circuit now. Using the handle afterwards raises `UnknownCircuitId`.
+    fn free(&self) {
+        chiquito_free(self.rust_id);
+    }
+
+    fn __repr__(&self) -> String {
+        format!("CircuitHandle({})", self.rust_id)
+    }
+}
+
+impl Drop for CircuitHandle {
+    fn drop(&mut self) {
+        chiquito_free(self.rust_id);
+    }
+}
+
+/// Witness of the circuit of a rust_id streamed from Python in batches of step instances.
+enum WitnessStream<F: PyField> {
+    Open(UUID, StreamingAssignments<F>),
+    Finished(UUID, Assignments<F>),
+}
+
+create_exception!(
+    rust_chiquito,
+    DeserializationError,
+    PyException,
+    "JSON from Python that does not deserialize to the expected chiquito type."
+);
+create_exception!(
+    rust_chiquito,
+    UnknownCircuitId,
+    PyException,
+    "rust_id or sub-circuit index of a circuit that was not stored in Rust."
+);
+create_exception!(
+    rust_chiquito,
+    UnknownWitnessId,
+    PyException,
+    "Id of a streamed witness that was not begun, or that was already finished or used."
+);
+create_exception!(
+    rust_chiquito,
+    CompilationError,
+    PyException,
+    "Circuit that chiquito fails to compile, or to lay out in a halo2 table."
+);
+create_exception!(
+    rust_chiquito,
+    ProvingError,
+    PyException,
+    "Failure of halo2 to generate the keys or a proof of a circuit."
 );
-type CircuitMap = RefCell<HashMap<UUID, CircuitMapStore>>;
 
-thread_local! {
-    pub static CIRCUIT_MAP: CircuitMap = RefCell::new(HashMap::new());
+impl From<InterchangeError> for PyErr {
+    fn from(error: InterchangeError) -> Self {
+        match error {
+            InterchangeError::Deserialization(message) => DeserializationError::new_err(message),
+            InterchangeError::Compilation(message) => CompilationError::new_err(message),
+            InterchangeError::Value(message) => PyValueError::new_err(message),
+            InterchangeError::Io(message) => PyIOError::new_err(message),
+            InterchangeError::Proving(message) => ProvingError::new_err(message),
+        }
+    }
 }
 
-/// Parses JSON into `ast::Circuit` and compile. Generates a Rust UUID. Inserts tuple of
-/// (`ast::Circuit`, `ChiquitoHalo2`, `AssignmentGenerator`, _) to `CIRCUIT_MAP` with the Rust UUID
-/// as the key. Return the Rust UUID to Python. The last field of the tuple, `TraceWitness`, is left
-/// as None, for `chiquito_add_witness_to_rust_id` to insert.
-pub fn chiquito_ast_to_halo2(ast_json: &str) -> UUID {
-    let value: Value = from_str(ast_json).expect("Invalid JSON");
-    // Attempt to convert `Value` into `SBPIR`
-    let circuit: SBPIR<Fr, ()> =
-        serde_json::from_value(value).expect("Deserialization to Circuit failed.");
+/// Witness from Python, as the JSON of a `TraceWitness` or, for large witnesses, in the binary
+/// format of `wit_gen::binary`, which is faster to produce and to parse, or as the id of a finished
+/// streamed witness, see `chiquito_witness_begin`. Extracted from a Python `str`, `bytes` or
+/// `int`.
+#[derive(Clone, Copy, Debug)]
+pub enum WitnessData<'a> {
+    Json(&'a str),
+    Binary(&'a [u8]),
+    Streamed(UUID),
+}
 
-    let config = config(SingleRowCellManager {}, SimpleStepSelectorBuilder {});
-    let (chiquito, assignment_generator) = compile(config, &circuit);
-    let chiquito_halo2 = chiquito2Halo2(chiquito);
-    let uuid = uuid();
+impl<'a> WitnessData<'a> {
+    pub fn parse<F: PyField>(self) -> PyResult<TraceWitness<F>> {
+        match self {
+            WitnessData::Json(json) => Ok(from_json(json, "TraceWitness")?),
+            WitnessData::Binary(bytes) => Ok(interchange::witness_from_binary(bytes)?),
+            WitnessData::Streamed(_) => Err(PyValueError::new_err(
+                "a streamed witness is only accepted to prove a single circuit",
+            )),
+        }
+    }
+
+    /// Returns the assignments of the witness for the circuit of `rust_id`, consuming it if it is
+    /// streamed.
+    fn assignments<F: PyField>(
+        self,
+        rust_id: UUID,
+        assigner: Option<Arc<WitnessAssigner<F>>>,
+    ) -> PyResult<Option<Assignments<F>>> {
+        match self {
+            WitnessData::Streamed(witness_id) => finished_witness(witness_id, rust_id).map(Some),
+            witness => {
+                let trace_witness = witness.parse()?;
+                Ok(assigner.map(|assigner| assigner.generate_with_witness(trace_witness)))
+            }
+        }
+    }
+}
+
+impl<'a> FromPyObject<'a> for WitnessData<'a> {
+    fn extract(witness: &'a PyAny) -> PyResult<Self> {
+        if let Ok(bytes) = witness.downcast::<PyBytes>() {
+            Ok(WitnessData::Binary(bytes.as_bytes()))
+        } else if let Ok(witness_id) = witness.downcast::<PyLong>() {
+            Ok(WitnessData::Streamed(witness_id.extract()?))
+        } else {
+            Ok(WitnessData::Json(witness.downcast::<PyString>()?.to_str()?))
+        }
+    }
+}
+
+/// Parses JSON into `ast::Circuit` and compile with `options`, to their field. Generates a Rust
+/// UUID. Stores the AST, the compiled `ChiquitoHalo2` and the assigner of its `AssignmentGenerator`
+/// with the Rust UUID as the key, until `chiquito_free`. Return the Rust UUID to Python.
+pub fn chiquito_ast_to_halo2(ast_json: &str, options: &CompilerOptions) -> PyResult<UUID> {
+    with_field!(options.field, F => compile_ast::<F>(ast_json, options))
+}
 
-    CIRCUIT_MAP.with(|circuit_map| {
-        circuit_map
-            .borrow_mut()
-            .insert(uuid, (circuit, chiquito_halo2, assignment_generator));
-    });
+fn compile_ast<F: PyField>(ast_json: &str, options: &CompilerOptions) -> PyResult<UUID> {
+    let (chiquito_halo2, assigner) = interchange::compile_ast::<F>(ast_json, options)?;
 
-    uuid
+    Ok(store_circuit(StoredCircuit::new(
+        ast_json,
+        chiquito_halo2,
+        assigner,
+    )))
 }
 
 // Internal function called by `sub_circuit` function in Python frontend. Used in conjunction with
-// the super circuit only. Parses AST JSON and stores AST in `CIRCUIT_MAP` without compiling it.
-// Compilation is done by `chiquito_super_circuit_halo2_mock_prover`.
-pub fn chiquito_ast_map_store(ast_json: &str) -> UUID {
-    let circuit: SBPIR<Fr, ()> =
-        serde_json::from_str(ast_json).expect("Json deserialization to Circuit failed.");
+// the super circuit only. Parses AST JSON and stores AST without compiling it. Compilation is done
+// by `chiquito_super_circuit_halo2_mock_prover`, to bn256.
+pub fn chiquito_ast_map_store(ast_json: &str) -> PyResult<UUID> {
+    // parsed again from the stored JSON when compiled
+    from_json::<SBPIR<Fr, ()>>(ast_json, "Circuit")?;
+
+    Ok(store_circuit(StoredCircuit::new::<Fr>(
+        ast_json,
+        ChiquitoHalo2::default(),
+        None,
+    )))
+}
+
+pub fn chiquito_ast_to_pil(
+    witness: WitnessData,
+    rust_id: UUID,
+    circuit_name: &str,
+) -> PyResult<String> {
+    with_field!(circuit_field(rust_id)?, F => {
+        let trace_witness = witness.parse::<F>()?;
+        let ast = with_circuit(rust_id, |circuit| circuit.ast::<F>())??;
+
+        Ok(catch_compilation(|| {
+            chiquito2Pil(ast, Some(trace_witness), circuit_name.to_string())
+        })?)
+    })
+}
+
+/// Generates the PIL of the super circuit of the sub-circuits of `rust_ids`, in one file, with the
+/// `TraceWitness` of each sub-circuit that has one, by rust_id. The namespace of each
+/// sub-circuit is `name` followed by its index.
+pub fn chiquito_super_circuit_to_pil(
+    rust_ids: Vec<UUID>,
+    super_witness: HashMap<UUID, WitnessData>,
+    name: &str,
+) -> PyResult<String> {
+    let mut asts = Vec::new();
+    for rust_id in rust_ids.iter() {
+        let mut ast = with_circuit(*rust_id, |circuit| circuit.ast::<Fr>())??;
+        // the ids of the ASTs from Python are not unique, the rust_ids are
+        ast.id = *rust_id;
+        asts.push(ast);
+    }
+
+    let mut witnesses = HashMap::new();
+    for (rust_id, witness) in super_witness {
+        if !rust_ids.contains(&rust_id) {
+            return Err(UnknownCircuitId::new_err(format!(
+                "sub-circuit {} not in the super circuit",
+                rust_id
+            )));
+        }
+        witnesses.insert(rust_id, witness.parse::<Fr>()?);
+    }
+    let ast_id_to_ir_id_mapping = rust_ids.iter().map(|id| (*id, *id)).collect();
+    let circuit_names = (0..rust_ids.len())
+        .map(|index| format!("{}_{}", name, index))
+        .collect();
+
+    Ok(catch_compilation(|| {
+        chiquitoSuperCircuit2Pil::<Fr, (), ()>(
+            asts,
+            witnesses,
+            ast_id_to_ir_id_mapping,
+            circuit_names,
+        )
+    })?)
+}
 
-    let uuid = uuid();
+/// Returns the size in the halo2 table of the circuit of `rust_id`, as compiled.
+pub fn chiquito_circuit_stats(rust_id: UUID) -> PyResult<CircuitStats> {
+    with_field!(circuit_field(rust_id)?, F => {
+        let (compiled, _) = compiled_circuit::<F>(rust_id)?;
+        Ok(compiled.stats())
+    })
+}
+
+/// Returns the AST of the circuit of `rust_id` as Rust parsed it, serialized back to the JSON of
+/// Python.
+pub fn chiquito_ast_to_json(rust_id: UUID) -> PyResult<String> {
+    with_field!(circuit_field(rust_id)?, F => {
+        let ast = with_circuit(rust_id, |circuit| circuit.ast::<F>())??;
+        to_json_string(sbpir_to_json(&ast))
+    })
+}
+
+/// Returns the plonkish IR the circuit of `rust_id` was compiled to, as JSON, with the placement of
+/// its signals in the columns. Fails for the sub-circuits of a super circuit, which are compiled
+/// with it.
+pub fn chiquito_plonkish_to_json(rust_id: UUID) -> PyResult<String> {
+    with_field!(circuit_field(rust_id)?, F => {
+        let (compiled, assigner) = compiled_circuit::<F>(rust_id)?;
+        // circuits stored by `chiquito_ast_map_store` keep a default circuit, of id 0
+        if compiled.circuit().id == 0 {
+            return Err(PyValueError::new_err(format!(
+                "circuit of rust_id {} is not compiled, as it is a sub-circuit",
+                rust_id
+            )));
+        }
+        let placement = assigner.as_ref().map(|assigner| assigner.placement());
+        to_json_string(plonkish_to_json(compiled.circuit(), placement))
+    })
+}
+
+/// Returns the diagram of the transitions between the step types of the circuit of `rust_id`, see
+/// `visualize::step_diagram`, as its JSON and its HTML.
+pub fn chiquito_step_diagram(rust_id: UUID) -> PyResult<(String, String)> {
+    with_field!(circuit_field(rust_id)?, F => {
+        let ast = with_circuit(rust_id, |circuit| circuit.ast::<F>())??;
+        let diagram = visualize::step_diagram(&ast);
+        Ok((diagram.to_json().to_string(), diagram.to_html()))
+    })
+}
 
-    CIRCUIT_MAP.with(|circuit_map| {
-        circuit_map
-            .borrow_mut()
-            .insert(uuid, (circuit, ChiquitoHalo2::default(), None));
-    });
+/// Returns the layout of the signals of the circuit of `rust_id` in its columns, see
+/// `visualize::column_layout`, as its JSON and its HTML. Fails for the sub-circuits of a super
+/// circuit, as `chiquito_plonkish_to_json`.
+pub fn chiquito_column_layout(rust_id: UUID) -> PyResult<(String, String)> {
+    with_field!(circuit_field(rust_id)?, F => {
+        let ast = with_circuit(rust_id, |circuit| circuit.ast::<F>())??;
+        let (compiled, assigner) = compiled_circuit::<F>(rust_id)?;
+        let assigner = match assigner {
+            Some(assigner) if compiled.circuit().id != 0 => assigner,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "circuit of rust_id {} has no placement of its signals",
+                    rust_id
+                )))
+            }
+        };
+        let layout = visualize::column_layout(&ast, compiled.circuit(), assigner.placement());
+        Ok((layout.to_json().to_string(), layout.to_html()))
+    })
+}
 
-    uuid
+/// Returns the constraints of the circuit of `rust_id` with their degrees, see
+/// `visualize::constraint_list`, as their JSON and their HTML.
+pub fn chiquito_constraint_list(rust_id: UUID) -> PyResult<(String, String)> {
+    with_field!(circuit_field(rust_id)?, F => {
+        let ast = with_circuit(rust_id, |circuit| circuit.ast::<F>())??;
+        let list = visualize::constraint_list(&ast);
+        Ok((list.to_json().to_string(), list.to_html()))
+    })
 }
 
-pub fn chiquito_ast_to_pil(witness_json: &str, rust_id: UUID, circuit_name: &str) -> String {
-    let trace_witness: TraceWitness<Fr> =
-        serde_json::from_str(witness_json).expect("Json deserialization to TraceWitness failed.");
-    let (ast, _, _) = rust_id_to_halo2(rust_id);
+fn to_json_string(json: Result<Value, String>) -> PyResult<String> {
+    json.and_then(|json| serde_json::to_string_pretty(&json).map_err(|e| e.to_string()))
+        .map_err(PyValueError::new_err)
+}
 
-    chiquito2Pil(ast, Some(trace_witness), circuit_name.to_string())
+/// Returns the smallest k of a halo2 table that fits the compiled circuit of `rust_id`: its rows,
+/// including those of its fixed tables, and the blinding rows halo2 reserves after them. Proving
+/// with a smaller k fails, and a larger one only costs more.
+pub fn chiquito_estimate_k(rust_id: UUID) -> PyResult<u32> {
+    Ok(chiquito_circuit_stats(rust_id)?.min_k)
 }
 
 fn add_assignment_generator_to_rust_id(
     assignment_generator: AssignmentGenerator<Fr, ()>,
     rust_id: UUID,
-) {
-    CIRCUIT_MAP.with(|circuit_map| {
-        let mut circuit_map = circuit_map.borrow_mut();
-        let circuit_map_store = circuit_map.get_mut(&rust_id).unwrap();
-        circuit_map_store.2 = Some(assignment_generator);
-    });
+) -> PyResult<()> {
+    with_circuit(rust_id, |circuit| {
+        circuit.compiled_mut::<Fr>()?.assigner = Some(assignment_generator.assigner());
+        Ok::<_, PyErr>(())
+    })?
 }
 
 /// Compile a `ChiquitoHalo2SuperCircuit` object from a list of `rust_ids`, each corresponding to a
@@ -110,32 +451,31 @@ fn add_assignment_generator_to_rust_id(
 /// invoking this function.
 pub fn chiquito_super_circuit_halo2_mock_prover(
     rust_ids: Vec<UUID>,
-    super_witness: HashMap<UUID, &str>,
-    k: usize,
-) {
+    super_witness: HashMap<UUID, WitnessData>,
+    k: Option<usize>,
+) -> PyResult<()> {
     let mut super_circuit_ctx = SuperCircuitContext::<Fr, ()>::default();
 
     // super_circuit def
     let config = config(SingleRowCellManager {}, SimpleStepSelectorBuilder {});
     for rust_id in rust_ids.clone() {
-        let circuit_map_store = rust_id_to_halo2(rust_id);
-        let (circuit, _, _) = circuit_map_store;
-        let assignment = super_circuit_ctx.sub_circuit_with_ast(config.clone(), circuit);
-        add_assignment_generator_to_rust_id(assignment, rust_id);
+        let circuit = with_circuit(rust_id, |circuit| circuit.ast::<Fr>())??;
+        let assignment =
+            catch_compilation(|| super_circuit_ctx.sub_circuit_with_ast(config.clone(), circuit))?;
+        add_assignment_generator_to_rust_id(assignment, rust_id)?;
     }
 
-    let super_circuit = super_circuit_ctx.compile();
+    let super_circuit = catch_compilation(|| super_circuit_ctx.compile())?;
     let compiled = chiquitoSuperCircuit2Halo2(&super_circuit);
 
     let mut mapping_ctx = MappingContext::default();
     for rust_id in rust_ids {
-        let circuit_map_store = rust_id_to_halo2(rust_id);
-        let (_, _, assignment_generator) = circuit_map_store;
+        let (_, assigner) = compiled_circuit::<Fr>(rust_id)?;
 
-        if let Some(witness_json) = super_witness.get(&rust_id) {
-            let witness: TraceWitness<Fr> = serde_json::from_str(witness_json)
-                .expect("Json deserialization to TraceWitness failed.");
-            mapping_ctx.map_with_witness(&assignment_generator.unwrap(), witness);
+        if let Some(witness) = super_witness.get(&rust_id) {
+            let witness = witness.parse()?;
+            let assigner = assigner.ok_or_else(|| unknown_rust_id(rust_id))?;
+            mapping_ctx.map_with_assigner(assigner, witness);
         }
     }
 
@@ -143,7 +483,8 @@ pub fn chiquito_super_circuit_halo2_mock_prover(
 
     let circuit = ChiquitoHalo2SuperCircuit::new(compiled, super_assignments);
 
-    let prover = MockProver::<Fr>::run(k as u32, &circuit, circuit.instance()).unwrap();
+    let min_k = circuit.stats().total.min_k;
+    let prover = mock_prover(k, min_k, &circuit, circuit.instance())?;
 
     let result = prover.verify();
 
@@ -154,1789 +495,1291 @@ pub fn chiquito_super_circuit_halo2_mock_prover(
             println!("{}", failure);
         }
     }
+
+    Ok(())
 }
 
-/// Returns the (`ast::Circuit`, `ChiquitoHalo2`, `AssignmentGenerator`, `TraceWitness`) tuple
-/// corresponding to `rust_id`.
-fn rust_id_to_halo2(uuid: UUID) -> CircuitMapStore {
-    CIRCUIT_MAP.with(|circuit_map| {
-        let circuit_map = circuit_map.borrow();
-        circuit_map.get(&uuid).unwrap().clone()
+/// Parses the JSON of a `SuperCircuitAst` and compiles it with `options`, to their field, in one
+/// call. The compiled super circuit is then passed to `MockProver` with the `TraceWitness` of each
+/// sub-circuit, by its index in `sub_circuits`, with the smallest k that fits it if `k` is `None`.
+pub fn chiquito_super_circuit_ast_halo2_mock_prover(
+    ast_json: &str,
+    super_witness: HashMap<usize, WitnessData>,
+    k: Option<usize>,
+    options: &CompilerOptions,
+) -> PyResult<()> {
+    with_field!(options.field, F => {
+        mock_prove_super_circuit_ast::<F>(ast_json, super_witness, k, options)
     })
 }
 
-/// Runs `MockProver` for a single circuit given JSON of `TraceWitness` and `rust_id` of the
-/// circuit.
-pub fn chiquito_halo2_mock_prover(witness_json: &str, rust_id: UUID, k: usize) {
-    let trace_witness: TraceWitness<Fr> =
-        serde_json::from_str(witness_json).expect("Json deserialization to TraceWitness failed.");
-    let (_, compiled, assignment_generator) = rust_id_to_halo2(rust_id);
-    let circuit: ChiquitoHalo2Circuit<_> = ChiquitoHalo2Circuit::new(
-        compiled,
-        assignment_generator.map(|g| g.generate_with_witness(trace_witness)),
-    );
+fn mock_prove_super_circuit_ast<F: PyField>(
+    ast_json: &str,
+    super_witness: HashMap<usize, WitnessData>,
+    k: Option<usize>,
+    options: &CompilerOptions,
+) -> PyResult<()> {
+    let ast: SuperCircuitAst<F> = from_json(ast_json, "SuperCircuitAst")?;
+    let config = options.config()?;
+    let (super_circuit, assignment_generators) = catch_compilation(|| ast.compile(config))?;
+    let mut compiled = chiquitoSuperCircuit2Halo2(&super_circuit);
+    for sub_circuit in compiled.iter_mut() {
+        options.apply(sub_circuit)?;
+    }
+
+    let mut mapping_ctx = MappingContext::default();
+    for (index, witness) in super_witness {
+        let assignment_generator = assignment_generators.get(index).ok_or_else(|| {
+            UnknownCircuitId::new_err(format!("sub-circuit {} not in the super circuit", index))
+        })?;
+        mapping_ctx.map_with_witness(assignment_generator, witness.parse()?);
+    }
 
-    let prover = MockProver::<Fr>::run(k as u32, &circuit, circuit.instance()).unwrap();
+    let super_assignments = mapping_ctx.get_super_assignments();
+
+    let circuit = ChiquitoHalo2SuperCircuit::new(compiled, super_assignments);
+
+    let min_k = circuit.stats().total.min_k;
+    let prover = mock_prover(k, min_k, &circuit, circuit.instance())?;
 
     let result = prover.verify();
 
-    println!("{:#?}", result);
+    println!("result = {:#?}", result);
 
     if let Err(failures) = &result {
         for failure in failures.iter() {
             println!("{}", failure);
         }
     }
-}
-
-struct CircuitVisitor;
 
-impl<'de> Visitor<'de> for CircuitVisitor {
-    type Value = SBPIR<Fr, ()>;
+    Ok(())
+}
 
-    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("struct Cricuit")
-    }
+fn unknown_rust_id(rust_id: UUID) -> PyErr {
+    UnknownCircuitId::new_err(format!("no circuit with rust_id {}", rust_id))
+}
 
-    fn visit_map<A>(self, mut map: A) -> Result<SBPIR<Fr, ()>, A::Error>
-    where
-        A: MapAccess<'de>,
-    {
-        let mut step_types = None;
-        let mut forward_signals = None;
-        let mut shared_signals = None;
-        let mut fixed_signals = None;
-        let mut exposed = None;
-        let mut annotations = None;
-        let mut fixed_assignments = None;
-        let mut first_step = None;
-        let mut last_step = None;
-        let mut num_steps = None;
-        let mut q_enable = None;
-        let mut id = None;
-
-        println!("------ Visiting map -------");
-
-        while let Some(key) = map.next_key::<String>()? {
-            println!("key = {}", key);
-            match key.as_str() {
-                "step_types" => {
-                    println!("------ Visiting step_types -------");
-                    if step_types.is_some() {
-                        return Err(de::Error::duplicate_field("step_types"));
-                    }
-                    step_types = Some(map.next_value::<HashMap<UUID, StepType<Fr>>>()?);
-                    println!("step_types = {:#?}", step_types);
-                }
-                "forward_signals" => {
-                    if forward_signals.is_some() {
-                        return Err(de::Error::duplicate_field("forward_signals"));
-                    }
-                    forward_signals = Some(map.next_value::<Vec<ForwardSignal>>()?);
-                }
-                "shared_signals" => {
-                    if shared_signals.is_some() {
-                        return Err(de::Error::duplicate_field("shared_signals"));
-                    }
-                    shared_signals = Some(map.next_value::<Vec<SharedSignal>>()?);
-                }
-                "fixed_signals" => {
-                    if fixed_signals.is_some() {
-                        return Err(de::Error::duplicate_field("fixed_signals"));
-                    }
-                    fixed_signals = Some(map.next_value::<Vec<FixedSignal>>()?);
-                }
-                "exposed" => {
-                    if exposed.is_some() {
-                        return Err(de::Error::duplicate_field("exposed"));
-                    }
-                    exposed = Some(map.next_value::<Vec<(Queriable<Fr>, ExposeOffset)>>()?);
-                }
-                "annotations" => {
-                    if annotations.is_some() {
-                        return Err(de::Error::duplicate_field("annotations"));
-                    }
-                    annotations = Some(map.next_value::<HashMap<UUID, String>>()?);
-                }
-                "fixed_assignments" => {
-                    if fixed_assignments.is_some() {
-                        return Err(de::Error::duplicate_field("fixed_assignments"));
-                    }
-                    fixed_assignments =
-                        Some(map.next_value::<Option<HashMap<UUID, (Queriable<Fr>, Vec<Fr>)>>>()?);
-                }
-                "first_step" => {
-                    if first_step.is_some() {
-                        return Err(de::Error::duplicate_field("first_step"));
-                    }
-                    let first_step_opt: Option<String> = map.next_value()?; // Deserialize the value as an optional string
-                    first_step = Some(first_step_opt.map_or(Ok(None), |first_step_str| {
-                        StepTypeUUID::from_str_radix(&first_step_str, 10)
-                            .map(Some)
-                            .map_err(|e| {
-                                de::Error::custom(format!(
-                                    "Failed to parse first_step '{}': {}",
-                                    first_step_str, e
-                                ))
-                            })
-                    })?);
-                }
-                "last_step" => {
-                    if last_step.is_some() {
-                        return Err(de::Error::duplicate_field("last_step"));
-                    }
-                    let last_step_opt: Option<String> = map.next_value()?; // Deserialize the value as an optional string
-                    last_step = Some(last_step_opt.map_or(Ok(None), |last_step_str| {
-                        StepTypeUUID::from_str_radix(&last_step_str, 10)
-                            .map(Some)
-                            .map_err(|e| {
-                                de::Error::custom(format!(
-                                    "Failed to parse last_step '{}': {}",
-                                    last_step_str, e
-                                ))
-                            })
-                    })?);
-                }
-                "num_steps" => {
-                    if num_steps.is_some() {
-                        return Err(de::Error::duplicate_field("num_steps"));
-                    }
-                    num_steps = Some(map.next_value::<usize>()?);
-                }
-                "q_enable" => {
-                    if q_enable.is_some() {
-                        return Err(de::Error::duplicate_field("q_enable"));
-                    }
-                    q_enable = Some(map.next_value::<bool>()?);
-                }
-                "id" => {
-                    if id.is_some() {
-                        return Err(de::Error::duplicate_field("id"));
-                    }
-                    let id_str: String = map.next_value()?;
-                    id = Some(id_str.parse::<u128>().map_err(|e| {
-                        de::Error::custom(format!("Failed to parse id '{}': {}", id_str, e))
-                    })?);
-                }
-                _ => {
-                    return Err(de::Error::unknown_field(
-                        &key,
-                        &[
-                            "step_types",
-                            "forward_signals",
-                            "shared_signals",
-                            "fixed_signals",
-                            "exposed",
-                            "annotations",
-                            "fixed_assignments",
-                            "first_step",
-                            "last_step",
-                            "num_steps",
-                            "q_enable",
-                            "id",
-                        ],
-                    ))
-                }
-            }
-        }
-        let step_types = step_types
-            .ok_or_else(|| de::Error::missing_field("step_types"))?
-            .into_iter()
-            .map(|(k, v)| (k, Rc::new(v)))
-            .collect();
-        let forward_signals =
-            forward_signals.ok_or_else(|| de::Error::missing_field("forward_signals"))?;
-        let shared_signals =
-            shared_signals.ok_or_else(|| de::Error::missing_field("shared_signals"))?;
-        let fixed_signals =
-            fixed_signals.ok_or_else(|| de::Error::missing_field("fixed_signals"))?;
-        let exposed = exposed.ok_or_else(|| de::Error::missing_field("exposed"))?;
-        let annotations = annotations.ok_or_else(|| de::Error::missing_field("annotations"))?;
-        let fixed_assignments = fixed_assignments
-            .ok_or_else(|| de::Error::missing_field("fixed_assignments"))?
-            .map(|inner| inner.into_values().collect());
-        let first_step = first_step.ok_or_else(|| de::Error::missing_field("first_step"))?;
-        let last_step = last_step.ok_or_else(|| de::Error::missing_field("last_step"))?;
-        let num_steps = num_steps.ok_or_else(|| de::Error::missing_field("num_steps"))?;
-        let q_enable = q_enable.ok_or_else(|| de::Error::missing_field("q_enable"))?;
-        let id = id.ok_or_else(|| de::Error::missing_field("id"))?;
-
-        Ok(SBPIR {
-            step_types,
-            forward_signals,
-            shared_signals,
-            fixed_signals,
-            halo2_advice: Default::default(),
-            halo2_fixed: Default::default(),
-            exposed,
-            num_steps,
-            annotations,
-            trace: Some(Rc::new(|_: &mut TraceContext<_>, _: _| {})),
-            fixed_assignments,
-            first_step,
-            last_step,
-            q_enable,
-            id,
-        })
-    }
+/// Begins a witness of the circuit of `rust_id` streamed in batches of step instances, so that
+/// neither Python nor Rust hold the whole trace at once. Returns the id of the witness, to add the
+/// batches with `chiquito_witness_add_steps` and then `chiquito_witness_finish` it. The finished
+/// witness is passed by its id to `chiquito_halo2_mock_prover` or `chiquito_halo2_prove`, which
+/// consume it.
+pub fn chiquito_witness_begin(rust_id: UUID) -> PyResult<UUID> {
+    with_field!(circuit_field(rust_id)?, F => begin_witness::<F>(rust_id))
 }
-struct StepTypeVisitor;
 
-impl<'de> Visitor<'de> for StepTypeVisitor {
-    type Value = StepType<Fr>;
+fn begin_witness<F: PyField>(rust_id: UUID) -> PyResult<UUID> {
+    let (_, assigner) = compiled_circuit::<F>(rust_id)?;
+    let assigner = assigner.ok_or_else(|| unknown_rust_id(rust_id))?;
 
-    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("struct StepType")
-    }
+    let witness_id = uuid();
+    let stream = WitnessStream::Open(rust_id, StreamingAssignments::new(assigner));
+    witness_streams().insert(witness_id, (F::FIELD, Box::new(stream)));
 
-    fn visit_map<A>(self, mut map: A) -> Result<StepType<Fr>, A::Error>
-    where
-        A: MapAccess<'de>,
-    {
-        let mut id = None;
-        let mut name = None;
-        let mut signals = None;
-        let mut constraints = None;
-        let mut transition_constraints = None;
-        let mut lookups = None;
-        let mut annotations = None;
-
-        while let Some(key) = map.next_key::<String>()? {
-            match key.as_str() {
-                "id" => {
-                    if id.is_some() {
-                        return Err(de::Error::duplicate_field("id"));
-                    }
-                    let id_str: String = map.next_value()?;
-                    id = Some(id_str.parse::<u128>().map_err(|e| {
-                        de::Error::custom(format!("Failed to parse id '{}': {}", id_str, e))
-                    })?);
-                }
-                "name" => {
-                    if name.is_some() {
-                        return Err(de::Error::duplicate_field("name"));
-                    }
-                    name = Some(map.next_value::<String>()?);
-                }
-                "signals" => {
-                    if signals.is_some() {
-                        return Err(de::Error::duplicate_field("signals"));
-                    }
-                    signals = Some(map.next_value::<Vec<InternalSignal>>()?);
-                }
-                "constraints" => {
-                    if constraints.is_some() {
-                        return Err(de::Error::duplicate_field("constraints"));
-                    }
-                    constraints = Some(map.next_value::<Vec<Constraint<Fr>>>()?);
-                }
-                "transition_constraints" => {
-                    if transition_constraints.is_some() {
-                        return Err(de::Error::duplicate_field("transition_constraints"));
-                    }
-                    transition_constraints =
-                        Some(map.next_value::<Vec<TransitionConstraint<Fr>>>()?);
-                }
-                "lookups" => {
-                    if lookups.is_some() {
-                        return Err(de::Error::duplicate_field("lookups"));
-                    }
-                    lookups = Some(map.next_value::<Vec<Lookup<Fr>>>()?);
-                }
-                "annotations" => {
-                    if annotations.is_some() {
-                        return Err(de::Error::duplicate_field("annotations"));
-                    }
-                    annotations = Some(map.next_value::<HashMap<UUID, String>>()?);
-                }
-                _ => {
-                    return Err(de::Error::unknown_field(
-                        &key,
-                        &[
-                            "id",
-                            "name",
-                            "signals",
-                            "constraints",
-                            "transition_constraints",
-                            "lookups",
-                            "annotations",
-                        ],
-                    ))
-                }
-            }
-        }
-        let id = id.ok_or_else(|| de::Error::missing_field("id"))?;
-        let name = name.ok_or_else(|| de::Error::missing_field("name"))?;
-        let signals = signals.ok_or_else(|| de::Error::missing_field("signals"))?;
-        let constraints = constraints.ok_or_else(|| de::Error::missing_field("constraints"))?;
-        let transition_constraints = transition_constraints
-            .ok_or_else(|| de::Error::missing_field("transition_constraints"))?;
-        let lookups = lookups.ok_or_else(|| de::Error::missing_field("lookups"))?;
-        let annotations = annotations.ok_or_else(|| de::Error::missing_field("annotations"))?;
-
-        let mut step_type = StepType::<Fr>::new(id, name);
-        step_type.signals = signals;
-        step_type.constraints = constraints;
-        step_type.transition_constraints = transition_constraints;
-        step_type.lookups = lookups;
-        step_type.annotations = annotations;
-
-        Ok(step_type)
-    }
+    Ok(witness_id)
 }
 
-macro_rules! impl_visitor_constraint_transition {
-    ($name:ident, $type:ty, $display:expr) => {
-        struct $name;
+/// Assigns the step instances of `witness`, the next batch of the streamed witness `witness_id`.
+/// Returns the step instances added so far.
+pub fn chiquito_witness_add_steps(witness_id: UUID, witness: WitnessData) -> PyResult<usize> {
+    with_field!(witness_field(witness_id)?, F => add_witness_steps::<F>(witness_id, witness))
+}
 
-        impl<'de> Visitor<'de> for $name {
-            type Value = $type;
+fn add_witness_steps<F: PyField>(witness_id: UUID, witness: WitnessData) -> PyResult<usize> {
+    let step_instances = witness.parse::<F>()?.step_instances;
 
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str($display)
-            }
+    let steps = with_open_witness(witness_id, |stream: &mut StreamingAssignments<F>| {
+        catch_compilation(|| {
+            stream.add_steps(step_instances);
+            stream.steps()
+        })
+    })??;
 
-            fn visit_map<A>(self, mut map: A) -> Result<$type, A::Error>
-            where
-                A: MapAccess<'de>,
-            {
-                let mut annotation = None;
-                let mut expr = None;
-                while let Some(key) = map.next_key::<String>()? {
-                    match key.as_str() {
-                        "annotation" => {
-                            if annotation.is_some() {
-                                return Err(de::Error::duplicate_field("annotation"));
-                            }
-                            annotation = Some(map.next_value::<String>()?);
-                        }
-                        "expr" => {
-                            if expr.is_some() {
-                                return Err(de::Error::duplicate_field("expr"));
-                            }
-                            expr = Some(map.next_value::<Expr<Fr, Queriable<Fr>>>()?);
-                        }
-                        _ => return Err(de::Error::unknown_field(&key, &["annotation", "expr"])),
-                    }
-                }
-                let annotation =
-                    annotation.ok_or_else(|| de::Error::missing_field("annotation"))?;
-                let expr = expr.ok_or_else(|| de::Error::missing_field("expr"))?;
-                Ok(Self::Value { annotation, expr })
-            }
-        }
-    };
+    Ok(steps)
 }
 
-impl_visitor_constraint_transition!(ConstraintVisitor, Constraint<Fr>, "struct Constraint");
-impl_visitor_constraint_transition!(
-    TransitionConstraintVisitor,
-    TransitionConstraint<Fr>,
-    "struct TransitionConstraint"
-);
+/// Runs `f` on the assignments of the streamed witness `witness_id`, that fails if it is finished.
+/// The streams are locked while `f` runs, so it does not call Python.
+fn with_open_witness<F: PyField, T>(
+    witness_id: UUID,
+    f: impl FnOnce(&mut StreamingAssignments<F>) -> T,
+) -> PyResult<T> {
+    let mut streams = witness_streams();
+    let stream = streams
+        .get_mut(&witness_id)
+        .and_then(|(_, stream)| stream.downcast_mut::<WitnessStream<F>>());
+    match stream {
+        Some(WitnessStream::Open(_, stream)) => Ok(f(stream)),
+        _ => Err(unknown_witness_id(witness_id)),
+    }
+}
 
-struct LookupVisitor;
+/// Generates the witness of the circuit of `rust_id` step by step, with a Python callable `step`
+/// called for each step instance with its index and the assignments of the previous one, as a dict
+/// from the annotations of its signals to their values, or `None` for the first. `step` returns
+/// the witness of the next step instances, in any format of `WitnessData`, which are assigned
+/// before it is called again, or `None` once the trace is complete. Returns the id of the finished
+/// streamed witness, see `chiquito_witness_begin`.
+pub fn chiquito_witness_from_steps(py: Python, rust_id: UUID, step: &PyAny) -> PyResult<UUID> {
+    with_field!(circuit_field(rust_id)?, F => witness_from_steps::<F>(py, rust_id, step))
+}
 
-impl<'de> Visitor<'de> for LookupVisitor {
-    type Value = Lookup<Fr>;
+fn witness_from_steps<F: PyField>(py: Python, rust_id: UUID, step: &PyAny) -> PyResult<UUID> {
+    let witness_id = begin_witness::<F>(rust_id)?;
 
-    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("struct Lookup")
+    match generate_steps::<F>(py, witness_id, step) {
+        Ok(()) => Ok(witness_id),
+        Err(err) => {
+            witness_streams().remove(&witness_id);
+            Err(err)
+        }
     }
+}
 
-    fn visit_map<A>(self, mut map: A) -> Result<Lookup<Fr>, A::Error>
-    where
-        A: MapAccess<'de>,
-    {
-        let mut annotation = None;
-        let mut exprs = None;
-        let mut enable = None;
-        while let Some(key) = map.next_key::<String>()? {
-            match key.as_str() {
-                "annotation" => {
-                    if annotation.is_some() {
-                        return Err(de::Error::duplicate_field("annotation"));
-                    }
-                    annotation = Some(map.next_value::<String>()?);
-                }
-                "exprs" => {
-                    if exprs.is_some() {
-                        return Err(de::Error::duplicate_field("exprs"));
-                    }
-                    exprs =
-                        Some(map.next_value::<Vec<(Constraint<Fr>, Expr<Fr, Queriable<Fr>>)>>()?);
-                }
-                "enable" => {
-                    if enable.is_some() {
-                        return Err(de::Error::duplicate_field("enable"));
-                    }
-                    enable = Some(map.next_value::<Option<Constraint<Fr>>>()?);
-                }
-                _ => {
-                    return Err(de::Error::unknown_field(
-                        &key,
-                        &["annotation", "exprs", "enable"],
-                    ))
-                }
-            }
+fn generate_steps<F: PyField>(py: Python, witness_id: UUID, step: &PyAny) -> PyResult<()> {
+    loop {
+        // the streams are not locked while Python runs, as `step` may use other witnesses
+        let (index, previous) =
+            with_open_witness(witness_id, |stream: &mut StreamingAssignments<F>| {
+                (stream.steps(), stream.previous().cloned())
+            })?;
+        let previous = previous
+            .map(|step_instance| step_assignments_to_py(py, &step_instance))
+            .transpose()?;
+
+        let witness = step.call1((index, previous))?;
+        if witness.is_none() {
+            return finish_witness::<F>(witness_id);
         }
-        let annotation = annotation.ok_or_else(|| de::Error::missing_field("annotation"))?;
-        let exprs = exprs.ok_or_else(|| de::Error::missing_field("exprs"))?;
-        let enable = enable.ok_or_else(|| de::Error::missing_field("enable"))?;
-        Ok(Self::Value {
-            annotation,
-            exprs,
-            enable,
-        })
+        add_witness_steps::<F>(witness_id, witness.extract()?)?;
     }
 }
 
-struct ExprVisitor;
+fn step_assignments_to_py<'py, F: PyField>(
+    py: Python<'py>,
+    step_instance: &StepInstance<F>,
+) -> PyResult<&'py PyDict> {
+    let assignments = PyDict::new(py);
+    for (queriable, value) in step_instance.assignments.iter() {
+        assignments.set_item(queriable.annotation(), field_to_py(py, value)?)?;
+    }
+
+    Ok(assignments)
+}
 
-impl<'de> Visitor<'de> for ExprVisitor {
-    type Value = Expr<Fr, Queriable<Fr>>;
+/// Pads the streamed witness `witness_id` and completes its assignments.
+pub fn chiquito_witness_finish(witness_id: UUID) -> PyResult<()> {
+    with_field!(witness_field(witness_id)?, F => finish_witness::<F>(witness_id))
+}
 
-    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("enum Expr")
+fn finish_witness<F: PyField>(witness_id: UUID) -> PyResult<()> {
+    let mut streams = witness_streams();
+    let (field, stream) = streams
+        .remove(&witness_id)
+        .ok_or_else(|| unknown_witness_id(witness_id))?;
+    match stream.downcast::<WitnessStream<F>>().map(|stream| *stream) {
+        Ok(WitnessStream::Open(rust_id, stream)) => {
+            let assignments = catch_compilation(|| stream.finish())?;
+            let stream = WitnessStream::Finished(rust_id, assignments);
+            streams.insert(witness_id, (field, Box::new(stream)));
+
+            Ok(())
+        }
+        Ok(stream) => {
+            streams.insert(witness_id, (field, Box::new(stream)));
+            Err(unknown_witness_id(witness_id))
+        }
+        Err(stream) => {
+            streams.insert(witness_id, (field, stream));
+            Err(unknown_witness_id(witness_id))
+        }
     }
+}
 
-    fn visit_map<A>(self, mut map: A) -> Result<Expr<Fr, Queriable<Fr>>, A::Error>
-    where
-        A: MapAccess<'de>,
-    {
-        let key: String = map
-            .next_key()?
-            .ok_or_else(|| de::Error::custom("map is empty"))?;
-        match key.as_str() {
-            "Const" => map.next_value().map(Expr::Const),
-            "Sum" => map.next_value().map(Expr::Sum),
-            "Mul" => map.next_value().map(Expr::Mul),
-            "Neg" => map.next_value().map(Expr::Neg),
-            "Pow" => map.next_value().map(|(expr, pow)| Expr::Pow(expr, pow)),
-            "Internal" => map
-                .next_value()
-                .map(|signal| Expr::Query(Queriable::Internal(signal))),
-            "Forward" => map
-                .next_value()
-                .map(|(signal, rotation)| Expr::Query(Queriable::Forward(signal, rotation))),
-            "Shared" => map
-                .next_value()
-                .map(|(signal, rotation)| Expr::Query(Queriable::Shared(signal, rotation))),
-            "Fixed" => map
-                .next_value()
-                .map(|(signal, rotation)| Expr::Query(Queriable::Fixed(signal, rotation))),
-            "StepTypeNext" => map
-                .next_value()
-                .map(|step_type| Expr::Query(Queriable::StepTypeNext(step_type))),
-            _ => Err(de::Error::unknown_variant(
-                &key,
-                &[
-                    "Const",
-                    "Sum",
-                    "Mul",
-                    "Neg",
-                    "Pow",
-                    "Internal",
-                    "Forward",
-                    "Shared",
-                    "Fixed",
-                    "StepTypeNext",
-                ],
-            )),
+/// Removes the finished streamed witness `witness_id` of the circuit of `rust_id`, and returns its
+/// assignments.
+fn finished_witness<F: PyField>(witness_id: UUID, rust_id: UUID) -> PyResult<Assignments<F>> {
+    let mut streams = witness_streams();
+    let (field, stream) = streams
+        .remove(&witness_id)
+        .ok_or_else(|| unknown_witness_id(witness_id))?;
+    match stream.downcast::<WitnessStream<F>>().map(|stream| *stream) {
+        Ok(WitnessStream::Finished(id, assignments)) if id == rust_id => Ok(assignments),
+        stream => {
+            let stream: Box<dyn Any + Send> = match stream {
+                Ok(stream) => Box::new(stream),
+                Err(stream) => stream,
+            };
+            streams.insert(witness_id, (field, stream));
+            Err(UnknownWitnessId::new_err(format!(
+                "witness {} is not a finished witness of rust_id {}",
+                witness_id, rust_id
+            )))
         }
     }
 }
 
-struct QueriableVisitor;
+fn witness_field(witness_id: UUID) -> PyResult<FieldOption> {
+    witness_streams()
+        .get(&witness_id)
+        .map(|(field, _)| *field)
+        .ok_or_else(|| unknown_witness_id(witness_id))
+}
 
-impl<'de> Visitor<'de> for QueriableVisitor {
-    type Value = Queriable<Fr>;
+fn unknown_witness_id(witness_id: UUID) -> PyErr {
+    UnknownWitnessId::new_err(format!("no streamed witness with id {}", witness_id))
+}
 
-    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("enum Queriable")
-    }
+/// Runs `MockProver` for a single circuit given a `TraceWitness` and `rust_id` of the circuit, with
+/// the k of `chiquito_estimate_k` if `k` is `None`.
+pub fn chiquito_halo2_mock_prover(
+    witness: WitnessData,
+    rust_id: UUID,
+    k: Option<usize>,
+) -> PyResult<()> {
+    with_field!(circuit_field(rust_id)?, F => mock_prove::<F>(witness, rust_id, k))
+}
 
-    fn visit_map<A>(self, mut map: A) -> Result<Queriable<Fr>, A::Error>
-    where
-        A: MapAccess<'de>,
-    {
-        let key: String = map
-            .next_key()?
-            .ok_or_else(|| de::Error::custom("map is empty"))?;
-
-        match key.as_str() {
-            "Internal" => map.next_value().map(Queriable::Internal),
-            "Forward" => map
-                .next_value()
-                .map(|(signal, rotation)| Queriable::Forward(signal, rotation)),
-            "Shared" => map
-                .next_value()
-                .map(|(signal, rotation)| Queriable::Shared(signal, rotation)),
-            "Fixed" => {
-                println!("Processing Fixed");
-                map.next_value()
-                    .map(|(signal, rotation)| Queriable::Fixed(signal, rotation))
-            }
-            "StepTypeNext" => map.next_value().map(Queriable::StepTypeNext),
-            _ => Err(de::Error::unknown_variant(
-                &key,
-                &["Internal", "Forward", "Shared", "Fixed", "StepTypeNext"],
-            )),
+fn mock_prove<F: PyField>(witness: WitnessData, rust_id: UUID, k: Option<usize>) -> PyResult<()> {
+    let (compiled, assigner) = compiled_circuit::<F>(rust_id)?;
+    let min_k = compiled.stats().min_k;
+    let assignments = witness.assignments(rust_id, assigner)?;
+    let circuit: ChiquitoHalo2Circuit<_> = ChiquitoHalo2Circuit::new(compiled, assignments);
+
+    let prover = mock_prover(k, min_k, &circuit, circuit.instance())?;
+
+    let result = prover.verify();
+
+    println!("{:#?}", result);
+
+    if let Err(failures) = &result {
+        for failure in failures.iter() {
+            println!("{}", failure);
         }
     }
+
+    Ok(())
+}
+
+/// Checks a witness against the AST of the circuit of `rust_id` with the native `Checker`, without
+/// compiling it to halo2 or running `MockProver`, which is much faster. Returns the violations in
+/// step order, none if the witness satisfies the circuit. Lookups into the columns of other
+/// circuits are not checked, and streamed witnesses are not accepted, as they are assigned while
+/// they are streamed.
+pub fn chiquito_check_witness(rust_id: UUID, witness: WitnessData) -> PyResult<Vec<Violation>> {
+    with_field!(circuit_field(rust_id)?, F => {
+        let ast = with_circuit(rust_id, |circuit| circuit.ast::<F>())??;
+        let witness = witness.parse::<F>()?;
+
+        Ok(check_witness(&ast, &witness)?)
+    })
 }
 
-struct ExposeOffsetVisitor;
+impl IntoPy<PyObject> for Violation {
+    fn into_py(self, py: Python) -> PyObject {
+        let dict = PyDict::new(py);
+        dict.set_item("message", self.to_string()).unwrap();
+        dict.set_item("kind", violation_kind_name(self.kind))
+            .unwrap();
+        dict.set_item("step", self.step).unwrap();
+        dict.set_item("step_type", self.step_type).unwrap();
+        dict.set_item("annotation", self.annotation).unwrap();
+        dict.set_item(
+            "location",
+            self.location.map(|location| location.to_string()),
+        )
+        .unwrap();
+        dict.set_item("explanation", self.explanation).unwrap();
+
+        dict.into_py(py)
+    }
+}
 
-impl<'de> Visitor<'de> for ExposeOffsetVisitor {
-    type Value = ExposeOffset;
+/// Result of mock proving a witness: the failures of `MockProver::verify`, as displayed by halo2,
+/// none if the witness satisfies the circuit.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MockProofResult {
+    pub failures: Vec<String>,
+}
 
-    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("enum ExposeOffset")
+impl MockProofResult {
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
     }
+}
 
-    fn visit_map<A>(self, mut map: A) -> Result<ExposeOffset, A::Error>
-    where
-        A: MapAccess<'de>,
-    {
-        let key: String = map
-            .next_key()?
-            .ok_or_else(|| de::Error::custom("map is empty"))?;
-        match key.as_str() {
-            "First" => {
-                let _ = map.next_value::<IgnoredAny>()?;
-                Ok(ExposeOffset::First)
-            }
-            "Last" => {
-                let _ = map.next_value::<IgnoredAny>()?;
-                Ok(ExposeOffset::Last)
-            }
-            "Step" => map.next_value().map(ExposeOffset::Step),
-            _ => Err(de::Error::unknown_variant(&key, &["First", "Last", "Step"])),
-        }
+impl IntoPy<PyObject> for MockProofResult {
+    fn into_py(self, py: Python) -> PyObject {
+        let dict = PyDict::new(py);
+        dict.set_item("ok", self.is_ok()).unwrap();
+        dict.set_item("failures", self.failures).unwrap();
+
+        dict.into_py(py)
     }
 }
 
-macro_rules! impl_visitor_internal_fixed_steptypehandler {
-    ($name:ident, $type:ty, $display:expr) => {
-        struct $name;
+/// Runs `MockProver` for the circuit of `rust_id` with each of `witnesses`, like
+/// `chiquito_halo2_mock_prover`, and returns their results in order. The circuit is compiled once,
+/// and the witnesses are assigned in turn, but mock proven in parallel on the available cores.
+/// Fails if a witness cannot be assigned, or for a `k` too small.
+pub fn chiquito_halo2_mock_prove_many(
+    witnesses: &[WitnessData],
+    rust_id: UUID,
+    k: Option<usize>,
+) -> PyResult<Vec<MockProofResult>> {
+    with_field!(circuit_field(rust_id)?, F => mock_prove_many::<F>(witnesses, rust_id, k))
+}
 
-        impl<'de> Visitor<'de> for $name {
-            type Value = $type;
+fn mock_prove_many<F: PyField>(
+    witnesses: &[WitnessData],
+    rust_id: UUID,
+    k: Option<usize>,
+) -> PyResult<Vec<MockProofResult>> {
+    let (compiled, assigner) = compiled_circuit::<F>(rust_id)?;
+    let min_k = compiled.stats().min_k;
+    let circuits = witnesses
+        .iter()
+        .map(|witness| {
+            let assignments = witness.assignments(rust_id, assigner.clone())?;
+            Ok(ChiquitoHalo2Circuit::new(compiled.clone(), assignments))
+        })
+        .collect::<PyResult<Vec<_>>>()?;
 
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str($display)
-            }
+    parallel_map(&circuits, |circuit| -> PyResult<MockProofResult> {
+        let prover = mock_prover(k, min_k, circuit, circuit.instance())?;
+        let failures = match prover.verify() {
+            Ok(()) => Vec::new(),
+            Err(failures) => failures.iter().map(ToString::to_string).collect(),
+        };
 
-            fn visit_map<A>(self, mut map: A) -> Result<$type, A::Error>
-            where
-                A: MapAccess<'de>,
-            {
-                let mut id = None;
-                let mut annotation = None;
-                while let Some(key) = map.next_key::<String>()? {
-                    match key.as_str() {
-                        "id" => {
-                            if id.is_some() {
-                                return Err(de::Error::duplicate_field("id"));
-                            }
-                            let id_str: String = map.next_value()?; // Get the UUID as a string
-                            id = Some(id_str.parse::<u128>().map_err(|e| {
-                                de::Error::custom(format!("Failed to parse id '{}': {}", id_str, e))
-                            })?);
-                        }
-                        "annotation" => {
-                            if annotation.is_some() {
-                                return Err(de::Error::duplicate_field("annotation"));
-                            }
-                            annotation = Some(map.next_value::<String>()?);
-                        }
-                        _ => return Err(de::Error::unknown_field(&key, &["id", "annotation"])),
-                    }
-                }
-                let id = id.ok_or_else(|| de::Error::missing_field("id"))?;
-                let annotation =
-                    annotation.ok_or_else(|| de::Error::missing_field("annotation"))?;
-                Ok(<$type>::new_with_id(id, annotation))
-            }
-        }
-    };
+        Ok(MockProofResult { failures })
+    })
+    .into_iter()
+    .collect()
 }
 
-impl_visitor_internal_fixed_steptypehandler!(
-    InternalSignalVisitor,
-    InternalSignal,
-    "struct InternalSignal"
-);
-impl_visitor_internal_fixed_steptypehandler!(FixedSignalVisitor, FixedSignal, "struct FixedSignal");
-impl_visitor_internal_fixed_steptypehandler!(
-    StepTypeHandlerVisitor,
-    StepTypeHandler,
-    "struct StepTypeHandler"
-);
+/// Runs `f` on each of `items`, split in a chunk per available core, and returns the results in
+/// order.
+fn parallel_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let threads = thread::available_parallelism().map_or(1, usize::from);
+    let chunk_size = items.len().div_ceil(threads).max(1);
 
-macro_rules! impl_visitor_forward_shared {
-    ($name:ident, $type:ty, $display:expr) => {
-        struct $name;
+    thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(|| chunk.iter().map(&f).collect::<Vec<_>>()))
+            .collect();
 
-        impl<'de> Visitor<'de> for $name {
-            type Value = $type;
+        handles
+            .into_iter()
+            .flat_map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|payload| panic::resume_unwind(payload))
+            })
+            .collect()
+    })
+}
 
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str($display)
-            }
+/// Generates a halo2 proof of the circuit of `rust_id` with a `TraceWitness`, with the
+/// KZG params (SRS) read from `params_path`, whose k is the one of the circuit. The params and the
+/// proving key of each circuit and params are cached, so only the first proof reads the params and
+/// generates the keys. Returns the proof and the public inputs, by instance column. Only bn256
+/// circuits are proven. With `evm`, the proof uses the Keccak256 transcript of the verifier of
+/// `chiquito_solidity_verifier`, instead of Blake2b.
+pub fn chiquito_halo2_prove(
+    rust_id: UUID,
+    witness: WitnessData,
+    params_path: &str,
+    evm: bool,
+) -> PyResult<(Vec<u8>, Vec<Vec<Fr>>)> {
+    ProvingJob::new(rust_id, witness, params_path, evm)?.run(|_| ())
+}
 
-            fn visit_map<A>(self, mut map: A) -> Result<$type, A::Error>
-            where
-                A: MapAccess<'de>,
-            {
-                let mut id = None;
-                let mut phase = None;
-                let mut annotation = None;
-                while let Some(key) = map.next_key::<String>()? {
-                    match key.as_str() {
-                        "id" => {
-                            if id.is_some() {
-                                return Err(de::Error::duplicate_field("id"));
-                            }
-                            let id_str: String = map.next_value()?; // Get the UUID as a string
-                            id = Some(id_str.parse::<u128>().map_err(|e| {
-                                de::Error::custom(format!("Failed to parse id '{}': {}", id_str, e))
-                            })?);
-                        }
-                        "phase" => {
-                            if phase.is_some() {
-                                return Err(de::Error::duplicate_field("phase"));
-                            }
-                            phase = Some(map.next_value()?);
-                        }
-                        "annotation" => {
-                            if annotation.is_some() {
-                                return Err(de::Error::duplicate_field("annotation"));
-                            }
-                            annotation = Some(map.next_value::<String>()?);
-                        }
-                        _ => {
-                            return Err(de::Error::unknown_field(
-                                &key,
-                                &["id", "phase", "annotation"],
-                            ))
-                        }
-                    }
-                }
-                let id = id.ok_or_else(|| de::Error::missing_field("id"))?;
-                let phase = phase.ok_or_else(|| de::Error::missing_field("phase"))?;
-                let annotation =
-                    annotation.ok_or_else(|| de::Error::missing_field("annotation"))?;
-                Ok(<$type>::new_with_id(id, phase, annotation))
-            }
-        }
-    };
+/// Phase of the generation of a proof, reported by `ProvingJob::run`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProvingPhase {
+    LoadingParams,
+    GeneratingKeys,
+    Proving,
+    Done,
 }
 
-impl_visitor_forward_shared!(ForwardSignalVisitor, ForwardSignal, "struct ForwardSignal");
-impl_visitor_forward_shared!(SharedSignalVisitor, SharedSignal, "struct SharedSignal");
+impl ProvingPhase {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProvingPhase::LoadingParams => "loading_params",
+            ProvingPhase::GeneratingKeys => "generating_keys",
+            ProvingPhase::Proving => "proving",
+            ProvingPhase::Done => "done",
+        }
+    }
 
-struct TraceWitnessVisitor;
+    /// Rough percent of the work of a proof done when the phase begins. halo2 does not report the
+    /// progress within a phase.
+    pub fn percent(&self) -> u8 {
+        match self {
+            ProvingPhase::LoadingParams => 0,
+            ProvingPhase::GeneratingKeys => 5,
+            ProvingPhase::Proving => 40,
+            ProvingPhase::Done => 100,
+        }
+    }
+}
 
-impl<'de> Visitor<'de> for TraceWitnessVisitor {
-    type Value = TraceWitness<Fr>;
+/// Proof to generate, with the witness already assigned, as the witness from Python is borrowed
+/// from the interpreter, and a streamed witness is consumed. It is `Send`, so it can be run
+/// without the GIL, or on another thread.
+pub struct ProvingJob {
+    keys: Arc<CircuitKeys>,
+    compiled: ChiquitoHalo2<Fr>,
+    assignments: Option<Assignments<Fr>>,
+    params_path: String,
+    evm: bool,
+}
+
+impl ProvingJob {
+    pub fn new(
+        rust_id: UUID,
+        witness: WitnessData,
+        params_path: &str,
+        evm: bool,
+    ) -> PyResult<Self> {
+        let (compiled, assigner) = compiled_circuit::<Fr>(rust_id)?;
+        let assignments = witness.assignments(rust_id, assigner)?;
+
+        Ok(Self {
+            keys: circuit_keys(rust_id)?,
+            compiled,
+            assignments,
+            params_path: params_path.to_string(),
+            evm,
+        })
+    }
 
-    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("struct TraceWitness")
+    /// Generates the proof, calling `progress` as each phase begins. Returns the proof and the
+    /// public inputs, by instance column.
+    pub fn run(self, progress: impl Fn(ProvingPhase)) -> PyResult<(Vec<u8>, Vec<Vec<Fr>>)> {
+        progress(ProvingPhase::LoadingParams);
+        let params = load_params(&self.params_path)?;
+        progress(ProvingPhase::GeneratingKeys);
+        let pk = self
+            .keys
+            .proving_key(&self.params_path, &params, &self.compiled)?;
+
+        progress(ProvingPhase::Proving);
+        let circuit = ChiquitoHalo2Circuit::new(self.compiled, self.assignments);
+        let proof = create_halo2_proof(&params, &pk, circuit, self.evm)?;
+        progress(ProvingPhase::Done);
+
+        Ok(proof)
     }
+}
+
+type ProofResult = PyResult<(Vec<u8>, Vec<Vec<Fr>>)>;
 
-    fn visit_map<A>(self, mut map: A) -> Result<TraceWitness<Fr>, A::Error>
-    where
-        A: MapAccess<'de>,
-    {
-        let mut step_instances = None;
-
-        while let Some(key) = map.next_key::<String>()? {
-            match key.as_str() {
-                "step_instances" => {
-                    if step_instances.is_some() {
-                        return Err(de::Error::duplicate_field("step_instances"));
+/// Proof generated on a thread of its own, returned to Python by `halo2_prove_async`. The thread
+/// runs without the GIL, so the interpreter is free while it proves.
+#[pyclass(module = "rust_chiquito")]
+pub struct ProvingTask {
+    phase: Arc<Mutex<ProvingPhase>>,
+    thread: Option<JoinHandle<ProofResult>>,
+    result: Option<ProofResult>,
+}
+
+impl ProvingTask {
+    /// Runs `job` on a new thread. `callback`, if any, is called with the name and percent of each
+    /// phase as it begins, from the thread of the task.
+    pub fn spawn(job: ProvingJob, callback: Option<PyObject>) -> Self {
+        let phase = Arc::new(Mutex::new(ProvingPhase::LoadingParams));
+        let reported = phase.clone();
+        let report = move |phase: ProvingPhase| {
+            *reported.lock().unwrap_or_else(PoisonError::into_inner) = phase;
+            if let Some(callback) = &callback {
+                Python::with_gil(|py| {
+                    if let Err(e) = callback.call1(py, (phase.name(), phase.percent())) {
+                        e.print(py);
                     }
-                    step_instances = Some(map.next_value()?);
-                }
-                _ => return Err(de::Error::unknown_field(&key, &["step_instances"])),
+                });
             }
+        };
+        let thread = thread::spawn(move || {
+            panic::catch_unwind(AssertUnwindSafe(|| job.run(report))).unwrap_or_else(|payload| {
+                Err(ProvingError::new_err(format!(
+                    "proof generation panicked: {}",
+                    panic_message(payload)
+                )))
+            })
+        });
+
+        Self {
+            phase,
+            thread: Some(thread),
+            result: None,
         }
-        let step_instances =
-            step_instances.ok_or_else(|| de::Error::missing_field("step_instances"))?;
-        Ok(Self::Value { step_instances })
     }
 }
 
-struct StepInstanceVisitor;
+#[pymethods]
+impl ProvingTask {
+    /// Name and rough percent of the phase the proof is in. A failed proof stays in the phase that
+    /// failed.
+    fn progress(&self) -> (&'static str, u8) {
+        let phase = *self.phase.lock().unwrap_or_else(PoisonError::into_inner);
 
-impl<'de> Visitor<'de> for StepInstanceVisitor {
-    type Value = StepInstance<Fr>;
+        (phase.name(), phase.percent())
+    }
 
-    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("struct StepInstance")
+    /// Returns if the proof is generated or failed, so `result` does not block.
+    fn done(&self) -> bool {
+        self.thread.as_ref().map_or(true, JoinHandle::is_finished)
     }
 
-    fn visit_map<A>(self, mut map: A) -> Result<StepInstance<Fr>, A::Error>
-    where
-        A: MapAccess<'de>,
-    {
-        let mut step_type_uuid = None;
-        let mut assignments = None;
-
-        while let Some(key) = map.next_key::<String>()? {
-            match key.as_str() {
-                "step_type_uuid" => {
-                    if step_type_uuid.is_some() {
-                        return Err(de::Error::duplicate_field("step_type_uuid"));
-                    }
-                    let uuid_str: String = map.next_value()?; // Get the UUID as a string
-                    step_type_uuid = Some(
-                        uuid_str
-                            .parse::<u128>() // Assuming the string is in decimal format
-                            .map_err(de::Error::custom)?,
-                    );
-                }
-                "assignments" => {
-                    if assignments.is_some() {
-                        return Err(de::Error::duplicate_field("assignments"));
-                    }
-                    assignments = Some(map.next_value::<HashMap<UUID, (Queriable<Fr>, Fr)>>()?);
-                }
-                _ => {
-                    return Err(de::Error::unknown_field(
-                        &key,
-                        &["step_type_uuid", "assignments"],
-                    ))
-                }
-            }
+    /// Waits for the proof, without the GIL, and returns it and the public inputs, by instance
+    /// column, or raises its error. Can be called again.
+    fn result(&mut self, py: Python) -> PyResult<(PyObject, Vec<Vec<PyObject>>)> {
+        if let Some(thread) = self.thread.take() {
+            let result = py
+                .allow_threads(|| thread.join())
+                .unwrap_or_else(|payload| Err(ProvingError::new_err(panic_message(payload))));
+            self.result = Some(result);
         }
-        let step_type_uuid =
-            step_type_uuid.ok_or_else(|| de::Error::missing_field("step_type_uuid"))?;
 
-        let assignments: HashMap<Queriable<Fr>, Fr> = assignments
-            .ok_or_else(|| de::Error::missing_field("assignments"))?
-            .into_values()
-            .collect();
-
-        Ok(Self::Value {
-            step_type_uuid,
-            assignments,
-        })
+        match self.result.as_ref().expect("result of a joined task") {
+            Ok((proof, instance)) => proof_to_py(py, proof, instance),
+            Err(e) => Err(e.clone_ref(py)),
+        }
     }
 }
 
-macro_rules! impl_deserialize {
-    ($name:ident, $type:ty) => {
-        impl<'de> Deserialize<'de> for $type {
-            fn deserialize<D>(deserializer: D) -> Result<$type, D::Error>
-            where
-                D: Deserializer<'de>,
-            {
-                deserializer.deserialize_map($name)
-            }
-        }
+/// Verifies a halo2 `proof` of the circuit of `rust_id` with its public inputs `instance`, by
+/// instance column, and the KZG params read from `params_path`. Without `params_path`, the params
+/// that the circuit was proven with in this process are used. Returns if the proof is valid. With
+/// `evm`, the proof is one of the Keccak256 transcript, see `chiquito_halo2_prove`.
+pub fn chiquito_halo2_verify(
+    rust_id: UUID,
+    proof: &[u8],
+    instance: Vec<Vec<Fr>>,
+    params_path: Option<&str>,
+    evm: bool,
+) -> PyResult<bool> {
+    let (params, vk) = params_and_verifying_key(rust_id, params_path)?;
+
+    Ok(verify_halo2_proof(&params, &vk, proof, &instance, evm)?)
+}
+
+/// Returns the params of `params_path`, or those the circuit of `rust_id` was proven with in this
+/// process if `None`, and the verifying key of the circuit with them.
+fn params_and_verifying_key(
+    rust_id: UUID,
+    params_path: Option<&str>,
+) -> PyResult<(Arc<ParamsKZG<Bn256>>, Arc<VerifyingKey<G1Affine>>)> {
+    let (compiled, _) = compiled_circuit::<Fr>(rust_id)?;
+
+    Ok(circuit_keys(rust_id)?.params_and_verifying_key(params_path, &compiled)?)
+}
+
+/// Returns the verifying key of the circuit of `rust_id` with the params of `params_path`, or
+/// those it was proven with if `None`, serialized in the raw bytes format of halo2.
+pub fn chiquito_halo2_verifying_key(rust_id: UUID, params_path: Option<&str>) -> PyResult<Vec<u8>> {
+    let (_, vk) = params_and_verifying_key(rust_id, params_path)?;
+
+    Ok(vk.to_bytes(SerdeFormat::RawBytes))
+}
+
+/// Returns the source of a Solidity contract that verifies the proofs of the circuit of `rust_id`
+/// generated with `evm`, see `chiquito_halo2_prove`, with the params of `params_path`, or those it
+/// was proven with if `None`. The verifying key is embedded in the contract.
+pub fn chiquito_solidity_verifier(rust_id: UUID, params_path: Option<&str>) -> PyResult<String> {
+    let (compiled, _) = compiled_circuit::<Fr>(rust_id)?;
+    let (params, vk) = params_and_verifying_key(rust_id, params_path)?;
+
+    Ok(solidity_verifier(&params, &vk, &compiled)?)
+}
+
+/// Converts `value`, a Python int or `F`, to a field element.
+fn py_to_fr(value: &PyAny) -> PyResult<Fr> {
+    let value = if value.hasattr("n")? {
+        value.getattr("n")?
+    } else {
+        value
     };
+    let digits = value.downcast::<PyLong>()?.str()?;
+
+    Ok(field_from_decimal(digits.to_str()?)?)
 }
 
-impl_deserialize!(ExprVisitor, Expr<Fr, Queriable<Fr>>);
-impl_deserialize!(QueriableVisitor, Queriable<Fr>);
-impl_deserialize!(ExposeOffsetVisitor, ExposeOffset);
-impl_deserialize!(InternalSignalVisitor, InternalSignal);
-impl_deserialize!(FixedSignalVisitor, FixedSignal);
-impl_deserialize!(ForwardSignalVisitor, ForwardSignal);
-impl_deserialize!(SharedSignalVisitor, SharedSignal);
-impl_deserialize!(StepTypeHandlerVisitor, StepTypeHandler);
-impl_deserialize!(ConstraintVisitor, Constraint<Fr>);
-impl_deserialize!(TransitionConstraintVisitor, TransitionConstraint<Fr>);
-impl_deserialize!(StepTypeVisitor, StepType<Fr>);
-impl_deserialize!(TraceWitnessVisitor, TraceWitness<Fr>);
-impl_deserialize!(StepInstanceVisitor, StepInstance<Fr>);
-impl_deserialize!(LookupVisitor, Lookup<Fr>);
-
-impl<'de> Deserialize<'de> for SBPIR<Fr, ()> {
-    fn deserialize<D>(deserializer: D) -> Result<SBPIR<Fr, ()>, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        deserializer.deserialize_map(CircuitVisitor)
-    }
+/// Converts `value` to a Python int.
+fn field_to_py<F: PyField>(py: Python, value: &F) -> PyResult<PyObject> {
+    Ok(py
+        .get_type::<PyLong>()
+        .call1((field_to_decimal(value),))?
+        .into_py(py))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        frontend::interchange::tests::{circuit_with_fixed_assignments, field_value_json},
+        sbpir::{checker::ViolationKind, query::Queriable, ForwardSignal, InternalSignal},
+        wit_gen::binary,
+    };
+    use serde_json::json;
+    use std::cell::RefCell;
 
     #[test]
-    #[ignore]
-    fn test_trace_witness() {
-        let json = r#"
-        {
-            "step_instances": [
-                {
-                    "step_type_uuid": "270606747459021742275781620564109167114",
-                    "assignments": {
-                        "270606737951642240564318377467548666378": [
-                            {
-                                "Forward": [
-                                    {
-                                        "id": "270606737951642240564318377467548666378",
-                                        "phase": 0,
-                                        "annotation": "a"
-                                    },
-                                    false
-                                ]
-                            },
-                            "0000000000000000000000000000000000000000000000000000000000000055"
-                        ],
-                        "270606743497613616562965561253747624458": [
-                            {
-                                "Forward": [
-                                    {
-                                        "id": "270606743497613616562965561253747624458",
-                                        "phase": 0,
-                                        "annotation": "b"
-                                    },
-                                    false
-                                ]
-                            },
-                            "0000000000000000000000000000000000000000000000000000000000000089"
-                        ],
-                        "270606753004993118272949371872716917258": [
-                            {
-                                "Internal": {
-                                    "id": "270606753004993118272949371872716917258",
-                                    "annotation": "c"
-                                }
-                            },
-                            "0000000000000000000000000000000000000000000000000000000000000144"
-                        ]
-                    }
-                },
-                {
-                    "step_type_uuid": "270606783111694873693576112554652600842",
-                    "assignments": {
-                        "270606737951642240564318377467548666378": [
-                            {
-                                "Forward": [
-                                    {
-                                        "id": "270606737951642240564318377467548666378",
-                                        "phase": 0,
-                                        "annotation": "a"
-                                    },
-                                    false
-                                ]
-                            },
-                            "0000000000000000000000000000000000000000000000000000000000000089"
-                        ],
-                        "270606743497613616562965561253747624458": [
-                            {
-                                "Forward": [
-                                    {
-                                        "id": "270606743497613616562965561253747624458",
-                                        "phase": 0,
-                                        "annotation": "b"
-                                    },
-                                    false
-                                ]
-                            },
-                            "0000000000000000000000000000000000000000000000000000000000000144"
-                        ],
-                        "270606786280821374261518951164072823306": [
-                            {
-                                "Internal": {
-                                    "id": "270606786280821374261518951164072823306",
-                                    "annotation": "c"
-                                }
-                            },
-                            "0000000000000000000000000000000000000000000000000000000000000233"
-                        ]
-                    }
-                }
-            ]
-        }
-        "#;
-        let trace_witness: TraceWitness<Fr> = serde_json::from_str(json).unwrap();
-        println!("{:?}", trace_witness);
-    }
-
-    #[test]
-    fn test_expose_offset() {
-        let mut json = r#"
-        {
-            "Step": 1
-        }
-        "#;
-        let _: ExposeOffset = serde_json::from_str(json).unwrap();
-        json = r#"
-        {
-            "Last": -1
-        }
-        "#;
-        let _: ExposeOffset = serde_json::from_str(json).unwrap();
-        json = r#"
-        {
-            "First": 1
-        }
-        "#;
-        let _: ExposeOffset = serde_json::from_str(json).unwrap();
+    fn test_errors() {
+        assert!(chiquito_ast_to_halo2("{", &CompilerOptions::default()).is_err());
+        assert!(chiquito_ast_map_store(r#"{ "step_types": {} }"#).is_err());
+        assert!(compiled_circuit::<Fr>(uuid()).is_err());
+        chiquito_free(uuid());
+        assert!(chiquito_circuit_stats(uuid()).is_err());
+        assert!(chiquito_witness_begin(uuid()).is_err());
+        assert!(chiquito_witness_add_steps(
+            uuid(),
+            WitnessData::Json(r#"{ "step_instances": [] }"#)
+        )
+        .is_err());
+        assert!(chiquito_witness_finish(uuid()).is_err());
+        assert!(WitnessData::Streamed(uuid()).parse::<Fr>().is_err());
+        assert!(load_params("/nonexistent/params").is_err());
+        assert!(params_and_verifying_key(uuid(), None).is_err());
+        assert!(chiquito_super_circuit_ast_halo2_mock_prover(
+            r#"{ "sub_circuits": [] }"#,
+            HashMap::from([(0, WitnessData::Json("{}"))]),
+            Some(4),
+            &CompilerOptions::default()
+        )
+        .is_err());
+        assert!(chiquito_super_circuit_to_pil(vec![uuid()], HashMap::new(), "Super").is_err());
+        assert!(chiquito_super_circuit_to_pil(
+            vec![],
+            HashMap::from([(1, WitnessData::Json("{}"))]),
+            "Super"
+        )
+        .is_err());
     }
 
     #[test]
-    fn test_circuit() {
+    fn test_free() {
         let json = r#"
         {
-            "step_types": {
-                "258869595755756204079859764249309612554": {
-                    "id": "258869595755756204079859764249309612554",
-                    "name": "fibo_first_step",
-                    "signals": [
-                        {
-                            "id": "258869599717164329791616633222308956682",
-                            "annotation": "c"
-                        }
-                    ],
-                    "constraints": [
-                        {
-                            "annotation": "(a == 1)",
-                            "expr": {
-                                "Sum": [
-                                    {
-                                        "Forward": [
-                                            {
-                                                "id": "258869580702405326369584955980151130634",
-                                                "phase": 0,
-                                                "annotation": "a"
-                                            },
-                                            false
-                                        ]
-                                    },
-                                    {
-                                        "Neg": {
-                                            "Const": "0000000000000000000000000000000000000000000000000000000000000001"
-                                        }
-                                    }
-                                ]
-                            }
-                        },
-                        {
-                            "annotation": "(b == 1)",
-                            "expr": {
-                                "Sum": [
-                                    {
-                                        "Forward": [
-                                            {
-                                                "id": "258869587040658327507391136965088381450",
-                                                "phase": 0,
-                                                "annotation": "b"
-                                            },
-                                            false
-                                        ]
-                                    },
-                                    {
-                                        "Neg": {
-                                            "Const": "0000000000000000000000000000000000000000000000000000000000000001"
-                                        }
-                                    }
-                                ]
-                            }
-                        },
-                        {
-                            "annotation": "((a + b) == c)",
-                            "expr": {
-                                "Sum": [
-                                    {
-                                        "Forward": [
-                                            {
-                                                "id": "258869580702405326369584955980151130634",
-                                                "phase": 0,
-                                                "annotation": "a"
-                                            },
-                                            false
-                                        ]
-                                    },
-                                    {
-                                        "Forward": [
-                                            {
-                                                "id": "258869587040658327507391136965088381450",
-                                                "phase": 0,
-                                                "annotation": "b"
-                                            },
-                                            false
-                                        ]
-                                    },
-                                    {
-                                        "Neg": {
-                                            "Internal": {
-                                                "id": "258869599717164329791616633222308956682",
-                                                "annotation": "c"
-                                            }
-                                        }
-                                    }
-                                ]
-                            }
-                        }
-                    ],
-                    "transition_constraints": [
-                        {
-                            "annotation": "(b == next(a))",
-                            "expr": {
-                                "Sum": [
-                                    {
-                                        "Forward": [
-                                            {
-                                                "id": "258869587040658327507391136965088381450",
-                                                "phase": 0,
-                                                "annotation": "b"
-                                            },
-                                            false
-                                        ]
-                                    },
-                                    {
-                                        "Neg": {
-                                            "Forward": [
-                                                {
-                                                    "id": "258869580702405326369584955980151130634",
-                                                    "phase": 0,
-                                                    "annotation": "a"
-                                                },
-                                                true
-                                            ]
-                                        }
-                                    }
-                                ]
-                            }
-                        },
-                        {
-                            "annotation": "(c == next(b))",
-                            "expr": {
-                                "Sum": [
-                                    {
-                                        "Internal": {
-                                            "id": "258869599717164329791616633222308956682",
-                                            "annotation": "c"
-                                        }
-                                    },
-                                    {
-                                        "Neg": {
-                                            "Forward": [
-                                                {
-                                                    "id": "258869587040658327507391136965088381450",
-                                                    "phase": 0,
-                                                    "annotation": "b"
-                                                },
-                                                true
-                                            ]
-                                        }
-                                    }
-                                ]
-                            }
-                        },
-                        {
-                            "annotation": "(n == next(n))",
-                            "expr": {
-                                "Sum": [
-                                    {
-                                        "Forward": [
-                                            {
-                                                "id": "258869589417503202934383108674030275082",
-                                                "phase": 0,
-                                                "annotation": "n"
-                                            },
-                                            false
-                                        ]
-                                    },
-                                    {
-                                        "Neg": {
-                                            "Forward": [
-                                                {
-                                                    "id": "258869589417503202934383108674030275082",
-                                                    "phase": 0,
-                                                    "annotation": "n"
-                                                },
-                                                true
-                                            ]
-                                        }
-                                    }
-                                ]
-                            }
-                        }
-                    ],
-                    "lookups": [],
-                    "annotations": {
-                        "258869599717164329791616633222308956682": "c"
-                    }
-                },
-                "258869628239302834927102989021255174666": {
-                    "id": "258869628239302834927102989021255174666",
-                    "name": "fibo_step",
-                    "signals": [
-                        {
-                            "id": "258869632200710960639812650790420089354",
-                            "annotation": "c"
-                        }
-                    ],
-                    "constraints": [
-                        {
-                            "annotation": "((a + b) == c)",
-                            "expr": {
-                                "Sum": [
-                                    {
-                                        "Forward": [
-                                            {
-                                                "id": "258869580702405326369584955980151130634",
-                                                "phase": 0,
-                                                "annotation": "a"
-                                            },
-                                            false
-                                        ]
-                                    },
-                                    {
-                                        "Forward": [
-                                            {
-                                                "id": "258869587040658327507391136965088381450",
-                                                "phase": 0,
-                                                "annotation": "b"
-                                            },
-                                            false
-                                        ]
-                                    },
-                                    {
-                                        "Neg": {
-                                            "Internal": {
-                                                "id": "258869632200710960639812650790420089354",
-                                                "annotation": "c"
-                                            }
-                                        }
-                                    }
-                                ]
-                            }
-                        }
-                    ],
-                    "transition_constraints": [
-                        {
-                            "annotation": "(b == next(a))",
-                            "expr": {
-                                "Sum": [
-                                    {
-                                        "Forward": [
-                                            {
-                                                "id": "258869587040658327507391136965088381450",
-                                                "phase": 0,
-                                                "annotation": "b"
-                                            },
-                                            false
-                                        ]
-                                    },
-                                    {
-                                        "Neg": {
-                                            "Forward": [
-                                                {
-                                                    "id": "258869580702405326369584955980151130634",
-                                                    "phase": 0,
-                                                    "annotation": "a"
-                                                },
-                                                true
-                                            ]
-                                        }
-                                    }
-                                ]
-                            }
-                        },
-                        {
-                            "annotation": "(c == next(b))",
-                            "expr": {
-                                "Sum": [
-                                    {
-                                        "Internal": {
-                                            "id": "258869632200710960639812650790420089354",
-                                            "annotation": "c"
-                                        }
-                                    },
-                                    {
-                                        "Neg": {
-                                            "Forward": [
-                                                {
-                                                    "id": "258869587040658327507391136965088381450",
-                                                    "phase": 0,
-                                                    "annotation": "b"
-                                                },
-                                                true
-                                            ]
-                                        }
-                                    }
-                                ]
-                            }
-                        },
-                        {
-                            "annotation": "(n == next(n))",
-                            "expr": {
-                                "Sum": [
-                                    {
-                                        "Forward": [
-                                            {
-                                                "id": "258869589417503202934383108674030275082",
-                                                "phase": 0,
-                                                "annotation": "n"
-                                            },
-                                            false
-                                        ]
-                                    },
-                                    {
-                                        "Neg": {
-                                            "Forward": [
-                                                {
-                                                    "id": "258869589417503202934383108674030275082",
-                                                    "phase": 0,
-                                                    "annotation": "n"
-                                                },
-                                                true
-                                            ]
-                                        }
-                                    }
-                                ]
-                            }
-                        }
-                    ],
-                    "lookups": [],
-                    "annotations": {
-                        "258869632200710960639812650790420089354": "c"
-                    }
-                },
-                "258869646461780213207493341245063432714": {
-                    "id": "258869646461780213207493341245063432714",
-                    "name": "padding",
-                    "signals": [],
-                    "constraints": [],
-                    "transition_constraints": [
-                        {
-                            "annotation": "(b == next(b))",
-                            "expr": {
-                                "Sum": [
-                                    {
-                                        "Forward": [
-                                            {
-                                                "id": "258869587040658327507391136965088381450",
-                                                "phase": 0,
-                                                "annotation": "b"
-                                            },
-                                            false
-                                        ]
-                                    },
-                                    {
-                                        "Neg": {
-                                            "Forward": [
-                                                {
-                                                    "id": "258869587040658327507391136965088381450",
-                                                    "phase": 0,
-                                                    "annotation": "b"
-                                                },
-                                                true
-                                            ]
-                                        }
-                                    }
-                                ]
-                            }
-                        },
-                        {
-                            "annotation": "(n == next(n))",
-                            "expr": {
-                                "Sum": [
-                                    {
-                                        "Forward": [
-                                            {
-                                                "id": "258869589417503202934383108674030275082",
-                                                "phase": 0,
-                                                "annotation": "n"
-                                            },
-                                            false
-                                        ]
-                                    },
-                                    {
-                                        "Neg": {
-                                            "Forward": [
-                                                {
-                                                    "id": "258869589417503202934383108674030275082",
-                                                    "phase": 0,
-                                                    "annotation": "n"
-                                                },
-                                                true
-                                            ]
-                                        }
-                                    }
-                                ]
-                            }
-                        }
-                    ],
-                    "lookups": [],
-                    "annotations": {}
-                }
-            },
-            "forward_signals": [
-                {
-                    "id": "258869580702405326369584955980151130634",
-                    "phase": 0,
-                    "annotation": "a"
-                },
-                {
-                    "id": "258869587040658327507391136965088381450",
-                    "phase": 0,
-                    "annotation": "b"
-                },
-                {
-                    "id": "258869589417503202934383108674030275082",
-                    "phase": 0,
-                    "annotation": "n"
-                }
-            ],
+            "step_types": {},
+            "forward_signals": [],
             "shared_signals": [],
             "fixed_signals": [],
-            "exposed": [
-                [
-                    {
-                        "Forward": [
-                            {
-                                "id": "258869587040658327507391136965088381450",
-                                "phase": 0,
-                                "annotation": "b"
-                            },
-                            false
-                        ]
-                    },
-                    {
-                        "Last": -1
-                    }
-                ],
-                [
-                    {
-                        "Forward": [
-                            {
-                                "id": "258869589417503202934383108674030275082",
-                                "phase": 0,
-                                "annotation": "n"
-                            },
-                            false
-                        ]
-                    },
-                    {
-                        "Last": -1
-                    }
-                ]
-            ],
-            "annotations": {
-                "258869580702405326369584955980151130634": "a",
-                "258869587040658327507391136965088381450": "b",
-                "258869589417503202934383108674030275082": "n",
-                "258869595755756204079859764249309612554": "fibo_first_step",
-                "258869628239302834927102989021255174666": "fibo_step",
-                "258869646461780213207493341245063432714": "padding"
-            },
+            "exposed": [],
+            "annotations": {},
             "fixed_assignments": null,
-            "first_step": "258869595755756204079859764249309612554",
-            "last_step": "258869646461780213207493341245063432714",
-            "num_steps": 10,
+            "first_step": null,
+            "last_step": null,
+            "num_steps": 0,
             "q_enable": true,
-            "id": "258867373405797678961444396351437277706"
-        }
-        "#;
-        let circuit: SBPIR<Fr, ()> = serde_json::from_str(json).unwrap();
-        println!("{:?}", circuit);
+            "id": "1"
+        }"#;
+        let rust_id = chiquito_ast_map_store(json).unwrap();
+        assert!(compiled_circuit::<Fr>(rust_id).is_ok());
+        assert_eq!(
+            with_circuit(rust_id, |circuit| circuit.ast::<Fr>().unwrap().id).unwrap(),
+            1
+        );
+
+        chiquito_free(rust_id);
+        assert!(compiled_circuit::<Fr>(rust_id).is_err());
+        // freeing twice, as a handle freed before it is dropped does
+        chiquito_free(rust_id);
     }
 
     #[test]
-    fn test_step_type() {
-        let json = r#"
-        {
-            "id":"1",
-            "name":"fibo",
-            "signals":[
-                {
-                    "id":"1",
-                    "annotation":"a"
-                },
-                {
-                    "id":"2",
-                    "annotation":"b"
-                }
-            ],
-            "constraints":[
-                {
-                    "annotation":"constraint",
-                    "expr":{
-                        "Sum":[
-                            {
-                                "Const": "0000000000000000000000000000000000000000000000000000000000000001"
-                            },
-                            {
-                                "Mul":[
-                                    {
-                                        "Internal":{
-                                            "id":"3",
-                                            "annotation":"c"
-                                        }
-                                    },
-                                    {
-                                        "Const": "0000000000000000000000000000000000000000000000000000000000000003"
-                                    }
-                                ]
-                            }
-                        ]
-                    }
-                },
-                {
-                    "annotation":"constraint",
-                    "expr":{
-                        "Sum":[
-                            {
-                                "Const": "0000000000000000000000000000000000000000000000000000000000000001"
-                            },
-                            {
-                                "Mul":[
-                                    {
-                                        "Shared":[
-                                            {
-                                                "id":"4",
-                                                "phase":2,
-                                                "annotation":"d"
-                                            },
-                                            1
-                                        ]
-                                    },
-                                    {
-                                        "Const": "0000000000000000000000000000000000000000000000000000000000000003"
-                                    }
-                                ]
-                            }
-                        ]
-                    }
-                }
-            ],
-            "transition_constraints":[
-                {
-                    "annotation":"trans",
-                    "expr":{
-                        "Sum":[
-                            {
-                                "Const": "0000000000000000000000000000000000000000000000000000000000000001"
-                            },
-                            {
-                                "Mul":[
-                                    {
-                                        "Forward":[
-                                            {
-                                                "id":"5",
-                                                "phase":1,
-                                                "annotation":"e"
-                                            },
-                                            true
-                                        ]
-                                    },
-                                    {
-                                        "Const": "0000000000000000000000000000000000000000000000000000000000000003"
-                                    }
-                                ]
-                            }
-                        ]
-                    }
-                },
-                {
-                    "annotation":"trans",
-                    "expr":{
-                        "Sum":[
-                            {
-                                "Const": "0000000000000000000000000000000000000000000000000000000000000001"
-                            },
-                            {
-                                "Mul":[
-                                    {
-                                        "Fixed":[
-                                            {
-                                                "id":"6",
-                                                "annotation":"e"
-                                            },
-                                            2
-                                        ]
-                                    },
-                                    {
-                                        "Const": "0000000000000000000000000000000000000000000000000000000000000003"
-                                    }
-                                ]
-                            }
-                        ]
-                    }
-                }
-            ],
-            "lookups":[],
-            "annotations":{
-                "5":"a",
-                "6":"b",
-                "7":"c"
-            }
-        }
-        "#;
-        let step_type: StepType<Fr> = serde_json::from_str(json).unwrap();
-        println!("{:?}", step_type);
+    fn test_binary_witness() {
+        let a = Queriable::Forward(ForwardSignal::new_with_id(10, 0, "a".to_string()), true);
+        let c = Queriable::Internal(InternalSignal::new_with_id(11, "c".to_string()));
+        let witness = TraceWitness {
+            step_instances: vec![StepInstance {
+                step_type_uuid: 20,
+                assignments: HashMap::from([(a, Fr::from(55)), (c, Fr::from(89))]),
+            }],
+        };
+        let mut bytes = Vec::new();
+        binary::write(&witness, &mut bytes).unwrap();
+
+        let parsed = WitnessData::Binary(&bytes).parse::<Fr>().unwrap();
+        assert_eq!(parsed.step_instances.len(), 1);
+        let step_instance = &parsed.step_instances[0];
+        assert_eq!(step_instance.step_type_uuid, 20);
+        assert_eq!(
+            step_instance.assignments,
+            witness.step_instances[0].assignments
+        );
+
+        assert!(WitnessData::Binary(&bytes[..bytes.len() - 1])
+            .parse::<Fr>()
+            .is_err());
+        assert!(WitnessData::Binary(b"{}").parse::<Fr>().is_err());
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(WitnessData::Binary(&trailing).parse::<Fr>().is_err());
     }
 
     #[test]
-    fn test_constraint() {
-        let json = r#"
-        {"annotation": "constraint",
-        "expr": 
-        {
-            "Sum": [
-                {
-                "Internal": {
-                    "id": "27",
-                    "annotation": "a"
-                }
-                },
-                {
-                "Fixed": [
-                    {
-                        "id": "28",
-                        "annotation": "b"
-                    },
-                    1
-                ]
-                },
-                {
-                "Shared": [
-                    {
-                        "id": "29",
-                        "phase": 1,
-                        "annotation": "c"
-                    },
-                    2
-                ]
-                },
-                {
-                "Forward": [
-                    {
-                        "id": "30",
-                        "phase": 2,
-                        "annotation": "d"
-                    },
-                    true
-                ]
-                },
-                {
-                "StepTypeNext": {
-                    "id": "31",
-                    "annotation": "e"
-                }
-                },
-                {
-                "Const": "0000000000000000000000000000000000000000000000000000000000000003"
-                },
-                {
-                "Mul": [
-                    {
-                    "Const": "0000000000000000000000000000000000000000000000000000000000000004"
-                    },
-                    {
-                    "Const": "0000000000000000000000000000000000000000000000000000000000000005"
-                    }
-                ]
-                },
-                {
-                "Neg": {
-                    "Const": "0000000000000000000000000000000000000000000000000000000000000002"
-                }
-                },
-                {
-                "Pow": [
-                    {
-                    "Const": "0000000000000000000000000000000000000000000000000000000000000003"
-                    },
-                    4
-                ]
-                }
-            ]
-            }
-        }"#;
-        let constraint: Constraint<Fr> = serde_json::from_str(json).unwrap();
-        println!("{:?}", constraint);
-        let transition_constraint: TransitionConstraint<Fr> = serde_json::from_str(json).unwrap();
-        println!("{:?}", transition_constraint);
+    fn test_plonkish_to_json() {
+        let json = circuit_with_fixed_assignments("null");
+        let rust_id = chiquito_ast_to_halo2(&json, &CompilerOptions::default()).unwrap();
+        let ast: Value = serde_json::from_str(&chiquito_ast_to_json(rust_id).unwrap()).unwrap();
+        assert_eq!(ast["id"], "3");
+        assert_eq!(ast["fixed_signals"][0]["annotation"], "byte");
+
+        let plonkish: Value =
+            serde_json::from_str(&chiquito_plonkish_to_json(rust_id).unwrap()).unwrap();
+        assert_eq!(plonkish["ast_id"], "3");
+        assert!(plonkish["columns"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|column| column["annotation"] == "byte" && column["ctype"] == "fixed"));
+        let byte = &plonkish["placement"]["fixed"]["1"];
+        assert_eq!(byte["annotation"], "byte");
+        assert_eq!(byte["rotation"], 0);
+        assert!(plonkish["columns"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|column| column["id"] == byte["column"]));
+        assert_eq!(plonkish["placement"]["steps"], json!({}));
+        chiquito_free(rust_id);
+
+        let rust_id = chiquito_ast_map_store(&json).unwrap();
+        assert!(chiquito_ast_to_json(rust_id).is_ok());
+        assert!(chiquito_plonkish_to_json(rust_id).is_err());
+        chiquito_free(rust_id);
     }
 
     #[test]
-    fn test_expr() {
-        let json = r#"
-        {
-            "Sum": [
-                {
-                "Internal": {
-                    "id": "27",
-                    "annotation": "a"
-                }
-                },
-                {
-                "Fixed": [
-                    {
-                        "id": "28",
-                        "annotation": "b"
-                    },
-                    1
-                ]
-                },
-                {
-                "Shared": [
-                    {
-                        "id": "29",
-                        "phase": 1,
-                        "annotation": "c"
-                    },
-                    2
-                ]
-                },
-                {
-                "Forward": [
-                    {
-                        "id": "30",
-                        "phase": 2,
-                        "annotation": "d"
-                    },
-                    true
-                ]
-                },
-                {
-                "StepTypeNext": {
-                    "id": "31",
-                    "annotation": "e"
-                }
-                },
-                {
-                "Const": "0000000000000000000000000000000000000000000000000000000000000003"
-                },
-                {
-                "Mul": [
-                    {
-                    "Const": "0000000000000000000000000000000000000000000000000000000000000004"
-                    },
-                    {
-                    "Const": "0000000000000000000000000000000000000000000000000000000000000005"
-                    }
-                ]
-                },
-                {
-                "Neg": {
-                    "Const": "0000000000000000000000000000000000000000000000000000000000000002"
-                }
-                },
-                {
-                "Pow": [
-                    {
-                    "Const": "0000000000000000000000000000000000000000000000000000000000000003"
-                    },
-                    4
-                ]
-                }
-            ]
-            }"#;
-        let expr: Expr<Fr, Queriable<Fr>> = serde_json::from_str(json).unwrap();
-        println!("{:?}", expr);
+    fn test_estimate_k() {
+        let json = circuit_with_fixed_assignments("null");
+        let rust_id = chiquito_ast_to_halo2(&json, &CompilerOptions::default()).unwrap();
+        let k = chiquito_estimate_k(rust_id).unwrap();
+        assert_eq!(k, chiquito_circuit_stats(rust_id).unwrap().min_k);
+        // 2 rows and at least 5 blinding rows
+        assert!(k >= 3);
+
+        let witness = WitnessData::Json(r#"{ "step_instances": [] }"#);
+        assert!(chiquito_halo2_mock_prover(witness, rust_id, Some(k as usize - 1)).is_err());
+        assert!(chiquito_estimate_k(uuid()).is_err());
+        chiquito_free(rust_id);
+    }
+
+    #[test]
+    fn test_mock_prove_many() {
+        let json = circuit_with_fixed_assignments("null");
+        let rust_id = chiquito_ast_to_halo2(&json, &CompilerOptions::default()).unwrap();
+        let witness = WitnessData::Json(r#"{ "step_instances": [] }"#);
+
+        let results = chiquito_halo2_mock_prove_many(&[witness; 3], rust_id, None).unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|result| result == &results[0]));
+        assert!(chiquito_halo2_mock_prove_many(&[], rust_id, None)
+            .unwrap()
+            .is_empty());
+
+        let k = Some(chiquito_estimate_k(rust_id).unwrap() as usize - 1);
+        assert!(chiquito_halo2_mock_prove_many(&[witness], rust_id, k).is_err());
+        let invalid = WitnessData::Json("{");
+        assert!(chiquito_halo2_mock_prove_many(&[witness, invalid], rust_id, None).is_err());
+        chiquito_free(rust_id);
+    }
+
+    #[test]
+    fn test_check_witness() {
+        let one = field_value_json(Fr::from(1));
+        let json = format!(
+            r#"
+            {{
+                "step_types": {{
+                    "20": {{
+                        "id": "20",
+                        "name": "one",
+                        "signals": [{{ "id": "10", "annotation": "a" }}],
+                        "constraints": [
+                            {{
+                                "annotation": "a == 1",
+                                "expr": {{
+                                    "Sum": [
+                                        {{ "Internal": {{ "id": "10", "annotation": "a" }} }},
+                                        {{ "Neg": {{ "Const": {} }} }}
+                                    ]
+                                }}
+                            }}
+                        ],
+                        "transition_constraints": [],
+                        "lookups": [],
+                        "annotations": {{}}
+                    }}
+                }},
+                "forward_signals": [],
+                "shared_signals": [],
+                "fixed_signals": [],
+                "exposed": [],
+                "annotations": {{}},
+                "fixed_assignments": null,
+                "first_step": null,
+                "last_step": null,
+                "num_steps": 0,
+                "q_enable": false,
+                "id": "30"
+            }}"#,
+            one
+        );
+        let rust_id = chiquito_ast_map_store(&json).unwrap();
+        let witness = |value: Fr| {
+            format!(
+                r#"{{ "step_instances": [{{ "step_type_uuid": "20", "assignments": {{
+                    "10": [{{ "Internal": {{ "id": "10", "annotation": "a" }} }}, {}]
+                }} }}] }}"#,
+                field_value_json(value)
+            )
+        };
+
+        let satisfying = witness(Fr::from(1));
+        assert!(
+            chiquito_check_witness(rust_id, WitnessData::Json(&satisfying))
+                .unwrap()
+                .is_empty()
+        );
+        let violating = witness(Fr::from(2));
+        let violations = chiquito_check_witness(rust_id, WitnessData::Json(&violating)).unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].kind, ViolationKind::Constraint);
+        assert_eq!(violations[0].step, 0);
+        assert_eq!(violations[0].annotation, "a == 1");
+
+        let unknown_step_type = satisfying.replace(r#""20""#, r#""21""#);
+        assert!(chiquito_check_witness(rust_id, WitnessData::Json(&unknown_step_type)).is_err());
+        assert!(chiquito_check_witness(rust_id, WitnessData::Streamed(uuid())).is_err());
+        assert!(chiquito_check_witness(uuid(), WitnessData::Json(&satisfying)).is_err());
+        chiquito_free(rust_id);
+    }
+
+    #[test]
+    fn test_witness_streamed_across_threads() {
+        let json = circuit_with_fixed_assignments("null");
+        let rust_id = chiquito_ast_to_halo2(&json, &CompilerOptions::default()).unwrap();
+
+        // Python threads stream a witness and prove it on different threads
+        let witness_id = thread::spawn(move || chiquito_witness_begin(rust_id).unwrap())
+            .join()
+            .unwrap();
+        thread::spawn(move || {
+            let steps = WitnessData::Json(r#"{ "step_instances": [] }"#);
+            assert_eq!(chiquito_witness_add_steps(witness_id, steps).unwrap(), 0);
+            chiquito_witness_finish(witness_id).unwrap();
+        })
+        .join()
+        .unwrap();
+
+        let witness = WitnessData::Streamed(witness_id);
+        assert!(chiquito_halo2_mock_prover(witness, rust_id, None).is_ok());
+        // consumed by the proof
+        assert!(chiquito_witness_finish(witness_id).is_err());
+        chiquito_free(rust_id);
+    }
+
+    #[test]
+    fn test_parallel_map() {
+        let items: Vec<usize> = (0..100).collect();
+        let doubled = parallel_map(&items, |item| item * 2);
+        assert_eq!(
+            doubled,
+            items.iter().map(|item| item * 2).collect::<Vec<_>>()
+        );
+        assert!(parallel_map(&[] as &[usize], |item| *item).is_empty());
+    }
+
+    #[test]
+    fn test_proving_job_progress() {
+        let json = circuit_with_fixed_assignments("null");
+        let rust_id = chiquito_ast_to_halo2(&json, &CompilerOptions::default()).unwrap();
+        let witness = WitnessData::Json(r#"{ "step_instances": [] }"#);
+        let job = ProvingJob::new(rust_id, witness, "/nonexistent/params", false).unwrap();
+
+        let phases = RefCell::new(Vec::new());
+        assert!(job.run(|phase| phases.borrow_mut().push(phase)).is_err());
+        // fails reading the params, before generating the keys
+        assert_eq!(phases.into_inner(), vec![ProvingPhase::LoadingParams]);
+
+        assert!(ProvingJob::new(uuid(), witness, "/nonexistent/params", false).is_err());
+        chiquito_free(rust_id);
     }
 }
 
 #[pyfunction]
-fn convert_and_print_ast(json: &PyString) {
-    let circuit: SBPIR<Fr, ()> =
-        serde_json::from_str(json.to_str().expect("PyString conversion failed."))
-            .expect("Json deserialization to Circuit failed.");
+fn convert_and_print_ast(json: &PyString) -> PyResult<()> {
+    let circuit: SBPIR<Fr, ()> = from_json(json.to_str()?, "Circuit")?;
     println!("{:?}", circuit);
+
+    Ok(())
 }
 
 #[pyfunction]
-fn convert_and_print_trace_witness(json: &PyString) {
-    let trace_witness: TraceWitness<Fr> =
-        serde_json::from_str(json.to_str().expect("PyString conversion failed."))
-            .expect("Json deserialization to TraceWitness failed.");
+fn convert_and_print_trace_witness(json: &PyString) -> PyResult<()> {
+    let trace_witness: TraceWitness<Fr> = from_json(json.to_str()?, "TraceWitness")?;
     println!("{:?}", trace_witness);
+
+    Ok(())
+}
+
+#[pyfunction]
+#[pyo3(signature = (json, config_json = None))]
+fn ast_to_halo2(json: &PyString, config_json: Option<&PyString>) -> PyResult<CircuitHandle> {
+    let options = CompilerOptions::from_json(config_json.map(|c| c.to_str()).transpose()?)?;
+    let rust_id = chiquito_ast_to_halo2(json.to_str()?, &options)?;
+
+    Ok(CircuitHandle { rust_id })
+}
+
+#[pyfunction]
+fn to_pil(witness: WitnessData, rust_id: &PyAny, circuit_name: &PyString) -> PyResult<String> {
+    let pil = chiquito_ast_to_pil(witness, rust_id_from_py(rust_id)?, circuit_name.to_str()?)?;
+
+    println!("{}", pil);
+    Ok(pil)
+}
+
+#[pyfunction]
+fn ast_map_store(json: &PyString) -> PyResult<CircuitHandle> {
+    let rust_id = chiquito_ast_map_store(json.to_str()?)?;
+
+    Ok(CircuitHandle { rust_id })
+}
+
+#[pyfunction]
+#[pyo3(signature = (witness, rust_id, k = None))]
+fn halo2_mock_prover(witness: WitnessData, rust_id: &PyAny, k: Option<usize>) -> PyResult<()> {
+    chiquito_halo2_mock_prover(witness, rust_id_from_py(rust_id)?, k)
+}
+
+#[pyfunction]
+#[pyo3(signature = (rust_id, witnesses, k = None))]
+fn halo2_mock_prove_many(
+    py: Python,
+    rust_id: &PyAny,
+    witnesses: Vec<WitnessData>,
+    k: Option<usize>,
+) -> PyResult<Vec<MockProofResult>> {
+    let rust_id = rust_id_from_py(rust_id)?;
+
+    py.allow_threads(|| chiquito_halo2_mock_prove_many(&witnesses, rust_id, k))
+}
+
+#[pyfunction]
+fn check_witness(rust_id: &PyAny, witness: WitnessData) -> PyResult<Vec<Violation>> {
+    chiquito_check_witness(rust_id_from_py(rust_id)?, witness)
 }
 
 #[pyfunction]
-fn ast_to_halo2(json: &PyString) -> u128 {
-    let uuid = chiquito_ast_to_halo2(json.to_str().expect("PyString conversion failed."));
+#[pyo3(signature = (rust_ids, super_witness, k = None))]
+fn super_circuit_halo2_mock_prover(
+    rust_ids: &PyList,
+    super_witness: &PyDict,
+    k: Option<usize>,
+) -> PyResult<()> {
+    chiquito_super_circuit_halo2_mock_prover(
+        rust_ids_from_py(rust_ids)?,
+        super_witness_from_py(super_witness, rust_id_from_py)?,
+        k,
+    )
+}
 
-    uuid
+#[pyfunction]
+#[pyo3(signature = (ast_json, super_witness, k = None, config_json = None))]
+fn super_circuit_ast_halo2_mock_prover(
+    ast_json: &PyString,
+    super_witness: &PyDict,
+    k: Option<usize>,
+    config_json: Option<&PyString>,
+) -> PyResult<()> {
+    let super_witness = super_witness_from_py(super_witness, |index| index.extract())?;
+    let options = CompilerOptions::from_json(config_json.map(|c| c.to_str()).transpose()?)?;
+
+    chiquito_super_circuit_ast_halo2_mock_prover(ast_json.to_str()?, super_witness, k, &options)
 }
 
 #[pyfunction]
-fn to_pil(witness_json: &PyString, rust_id: &PyLong, circuit_name: &PyString) -> String {
-    let pil = chiquito_ast_to_pil(
-        witness_json.to_str().expect("PyString convertion failed."),
-        rust_id.extract().expect("PyLong convertion failed."),
-        circuit_name.to_str().expect("PyString convertion failed."),
-    );
+fn super_circuit_to_pil(
+    rust_ids: &PyList,
+    super_witness: &PyDict,
+    name: &PyString,
+) -> PyResult<String> {
+    let pil = chiquito_super_circuit_to_pil(
+        rust_ids_from_py(rust_ids)?,
+        super_witness_from_py(super_witness, rust_id_from_py)?,
+        name.to_str()?,
+    )?;
 
     println!("{}", pil);
-    pil
+    Ok(pil)
+}
+
+/// Extracts the rust_id of a `CircuitHandle`. A bare rust_id is a `TypeError`, so a circuit is only
+/// used through the handle that owns it.
+fn rust_id_from_py(handle: &PyAny) -> PyResult<UUID> {
+    Ok(handle.extract::<PyRef<CircuitHandle>>()?.rust_id)
+}
+
+fn rust_ids_from_py(rust_ids: &PyList) -> PyResult<Vec<UUID>> {
+    rust_ids.iter().map(rust_id_from_py).collect()
+}
+
+/// Extracts the `TraceWitness` of each sub-circuit, by its key extracted with `key`, a rust_id or
+/// an index.
+fn super_witness_from_py<'a, K: Eq + std::hash::Hash>(
+    super_witness: &'a PyDict,
+    key: impl Fn(&'a PyAny) -> PyResult<K>,
+) -> PyResult<HashMap<K, WitnessData<'a>>> {
+    super_witness
+        .iter()
+        .map(|(k, value)| Ok((key(k)?, value.extract()?)))
+        .collect()
 }
 
 #[pyfunction]
-fn ast_map_store(json: &PyString) -> u128 {
-    let uuid = chiquito_ast_map_store(json.to_str().expect("PyString conversion failed."));
+fn witness_begin(rust_id: &PyAny) -> PyResult<u128> {
+    chiquito_witness_begin(rust_id_from_py(rust_id)?)
+}
+
+#[pyfunction]
+fn witness_add_steps(witness_id: &PyLong, steps: WitnessData) -> PyResult<usize> {
+    chiquito_witness_add_steps(witness_id.extract()?, steps)
+}
+
+#[pyfunction]
+fn witness_finish(witness_id: &PyLong) -> PyResult<u128> {
+    let witness_id = witness_id.extract()?;
+    chiquito_witness_finish(witness_id)?;
+
+    Ok(witness_id)
+}
+
+#[pyfunction]
+fn witness_from_steps(py: Python, rust_id: &PyAny, step: &PyAny) -> PyResult<u128> {
+    chiquito_witness_from_steps(py, rust_id_from_py(rust_id)?, step)
+}
+
+#[pyfunction]
+fn circuit_stats<'py>(py: Python<'py>, rust_id: &PyAny) -> PyResult<&'py PyDict> {
+    let stats = chiquito_circuit_stats(rust_id_from_py(rust_id)?)?;
+
+    let dict = PyDict::new(py);
+    dict.set_item("advice_columns", stats.advice_columns)?;
+    dict.set_item("fixed_columns", stats.fixed_columns)?;
+    dict.set_item("rows", stats.rows)?;
+    dict.set_item("polys", stats.polys)?;
+    dict.set_item("lookups", stats.lookups)?;
+    dict.set_item("max_degree", stats.max_degree)?;
+    dict.set_item("max_advice_queries", stats.max_advice_queries)?;
+    dict.set_item("min_k", stats.min_k)?;
+
+    Ok(dict)
+}
+
+#[pyfunction]
+fn estimate_k(rust_id: &PyAny) -> PyResult<u32> {
+    chiquito_estimate_k(rust_id_from_py(rust_id)?)
+}
+
+#[pyfunction]
+fn ast_to_json(rust_id: &PyAny) -> PyResult<String> {
+    chiquito_ast_to_json(rust_id_from_py(rust_id)?)
+}
+
+#[pyfunction]
+fn plonkish_to_json(rust_id: &PyAny) -> PyResult<String> {
+    chiquito_plonkish_to_json(rust_id_from_py(rust_id)?)
+}
+
+#[pyfunction]
+fn json_schema() -> String {
+    schema::json_schema().to_string()
+}
 
-    uuid
+#[pyfunction]
+fn typescript_types() -> String {
+    schema::typescript()
+}
+
+#[pyfunction]
+fn step_diagram(rust_id: &PyAny) -> PyResult<(String, String)> {
+    chiquito_step_diagram(rust_id_from_py(rust_id)?)
+}
+
+#[pyfunction]
+fn column_layout(rust_id: &PyAny) -> PyResult<(String, String)> {
+    chiquito_column_layout(rust_id_from_py(rust_id)?)
 }
 
 #[pyfunction]
-fn halo2_mock_prover(witness_json: &PyString, rust_id: &PyLong, k: &PyLong) {
-    chiquito_halo2_mock_prover(
-        witness_json.to_str().expect("PyString conversion failed."),
-        rust_id.extract().expect("PyLong conversion failed."),
-        k.extract().expect("PyLong conversion failed."),
-    );
+fn constraint_list(rust_id: &PyAny) -> PyResult<(String, String)> {
+    chiquito_constraint_list(rust_id_from_py(rust_id)?)
 }
 
 #[pyfunction]
-fn super_circuit_halo2_mock_prover(rust_ids: &PyList, super_witness: &PyDict, k: &PyLong) {
-    let uuids = rust_ids
+#[pyo3(signature = (rust_id, witness, params_path, evm = false))]
+fn halo2_prove(
+    py: Python,
+    rust_id: &PyAny,
+    witness: WitnessData,
+    params_path: &PyString,
+    evm: bool,
+) -> PyResult<(PyObject, Vec<Vec<PyObject>>)> {
+    let job = ProvingJob::new(
+        rust_id_from_py(rust_id)?,
+        witness,
+        params_path.to_str()?,
+        evm,
+    )?;
+    let (proof, instance) = py.allow_threads(|| job.run(|_| ()))?;
+
+    proof_to_py(py, &proof, &instance)
+}
+
+#[pyfunction]
+#[pyo3(signature = (rust_id, witness, params_path, evm = false, progress = None))]
+fn halo2_prove_async(
+    rust_id: &PyAny,
+    witness: WitnessData,
+    params_path: &PyString,
+    evm: bool,
+    progress: Option<PyObject>,
+) -> PyResult<ProvingTask> {
+    let job = ProvingJob::new(
+        rust_id_from_py(rust_id)?,
+        witness,
+        params_path.to_str()?,
+        evm,
+    )?;
+
+    Ok(ProvingTask::spawn(job, progress))
+}
+
+fn proof_to_py(
+    py: Python,
+    proof: &[u8],
+    instance: &[Vec<Fr>],
+) -> PyResult<(PyObject, Vec<Vec<PyObject>>)> {
+    let instance = instance
         .iter()
-        .map(|rust_id| {
-            rust_id
-                .downcast::<PyLong>()
-                .expect("PyAny downcast failed.")
-                .extract()
-                .expect("PyLong conversion failed.")
+        .map(|column| {
+            column
+                .iter()
+                .map(|value| field_to_py(py, value))
+                .collect::<PyResult<Vec<_>>>()
         })
-        .collect::<Vec<UUID>>();
+        .collect::<PyResult<_>>()?;
+
+    Ok((PyBytes::new(py, proof).into_py(py), instance))
+}
 
-    let super_witness = super_witness
+#[pyfunction]
+#[pyo3(signature = (rust_id, proof, instances, params_path = None, evm = false))]
+fn halo2_verify(
+    rust_id: &PyAny,
+    proof: &PyBytes,
+    instances: &PyList,
+    params_path: Option<&PyString>,
+    evm: bool,
+) -> PyResult<bool> {
+    let instances = instances
         .iter()
-        .map(|(key, value)| {
-            (
-                key.downcast::<PyLong>()
-                    .expect("PyAny downcast failed.")
-                    .extract()
-                    .expect("PyLong conversion failed."),
-                value
-                    .downcast::<PyString>()
-                    .expect("PyAny downcast failed.")
-                    .to_str()
-                    .expect("PyString conversion failed."),
-            )
+        .map(|column| {
+            column
+                .downcast::<PyList>()?
+                .iter()
+                .map(py_to_fr)
+                .collect::<PyResult<Vec<_>>>()
         })
-        .collect::<HashMap<u128, &str>>();
+        .collect::<PyResult<_>>()?;
+
+    chiquito_halo2_verify(
+        rust_id_from_py(rust_id)?,
+        proof.as_bytes(),
+        instances,
+        params_path.map(|path| path.to_str()).transpose()?,
+        evm,
+    )
+}
 
-    chiquito_super_circuit_halo2_mock_prover(
-        uuids,
-        super_witness,
-        k.extract().expect("PyLong conversion failed."),
+#[pyfunction]
+#[pyo3(signature = (rust_id, params_path = None))]
+fn halo2_verifying_key<'py>(
+    py: Python<'py>,
+    rust_id: &PyAny,
+    params_path: Option<&PyString>,
+) -> PyResult<&'py PyBytes> {
+    let vk = chiquito_halo2_verifying_key(
+        rust_id_from_py(rust_id)?,
+        params_path.map(|path| path.to_str()).transpose()?,
+    )?;
+
+    Ok(PyBytes::new(py, &vk))
+}
+
+#[pyfunction]
+#[pyo3(signature = (rust_id, params_path = None))]
+fn solidity_verifier(rust_id: &PyAny, params_path: Option<&PyString>) -> PyResult<String> {
+    chiquito_solidity_verifier(
+        rust_id_from_py(rust_id)?,
+        params_path.map(|path| path.to_str()).transpose()?,
     )
 }
 
+/// Writes KZG params of `k` to `params_path`, unsafe outside of tests, see `write_unsafe_params`.
+#[pyfunction]
+fn unsafe_kzg_params(k: u32, params_path: &PyString) -> PyResult<()> {
+    Ok(write_unsafe_params(k, params_path.to_str()?)?)
+}
+
 #[pymodule]
-fn rust_chiquito(_py: Python, m: &PyModule) -> PyResult<()> {
+fn rust_chiquito(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add(
+        "DeserializationError",
+        py.get_type::<DeserializationError>(),
+    )?;
+    m.add("UnknownCircuitId", py.get_type::<UnknownCircuitId>())?;
+    m.add("UnknownWitnessId", py.get_type::<UnknownWitnessId>())?;
+    m.add("CompilationError", py.get_type::<CompilationError>())?;
+    m.add("ProvingError", py.get_type::<ProvingError>())?;
+    m.add_class::<CircuitHandle>()?;
+    m.add_class::<ProvingTask>()?;
     m.add_function(wrap_pyfunction!(convert_and_print_ast, m)?)?;
     m.add_function(wrap_pyfunction!(convert_and_print_trace_witness, m)?)?;
     m.add_function(wrap_pyfunction!(ast_to_halo2, m)?)?;
     m.add_function(wrap_pyfunction!(to_pil, m)?)?;
     m.add_function(wrap_pyfunction!(ast_map_store, m)?)?;
     m.add_function(wrap_pyfunction!(halo2_mock_prover, m)?)?;
+    m.add_function(wrap_pyfunction!(halo2_mock_prove_many, m)?)?;
+    m.add_function(wrap_pyfunction!(check_witness, m)?)?;
     m.add_function(wrap_pyfunction!(super_circuit_halo2_mock_prover, m)?)?;
+    m.add_function(wrap_pyfunction!(super_circuit_ast_halo2_mock_prover, m)?)?;
+    m.add_function(wrap_pyfunction!(super_circuit_to_pil, m)?)?;
+    m.add_function(wrap_pyfunction!(witness_begin, m)?)?;
+    m.add_function(wrap_pyfunction!(witness_add_steps, m)?)?;
+    m.add_function(wrap_pyfunction!(witness_finish, m)?)?;
+    m.add_function(wrap_pyfunction!(witness_from_steps, m)?)?;
+    m.add_function(wrap_pyfunction!(circuit_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(estimate_k, m)?)?;
+    m.add_function(wrap_pyfunction!(ast_to_json, m)?)?;
+    m.add_function(wrap_pyfunction!(plonkish_to_json, m)?)?;
+    m.add_function(wrap_pyfunction!(json_schema, m)?)?;
+    m.add_function(wrap_pyfunction!(typescript_types, m)?)?;
+    m.add_function(wrap_pyfunction!(step_diagram, m)?)?;
+    m.add_function(wrap_pyfunction!(column_layout, m)?)?;
+    m.add_function(wrap_pyfunction!(constraint_list, m)?)?;
+    m.add_function(wrap_pyfunction!(halo2_prove, m)?)?;
+    m.add_function(wrap_pyfunction!(halo2_prove_async, m)?)?;
+    m.add_function(wrap_pyfunction!(halo2_verify, m)?)?;
+    m.add_function(wrap_pyfunction!(halo2_verifying_key, m)?)?;
+    m.add_function(wrap_pyfunction!(solidity_verifier, m)?)?;
+    m.add_function(wrap_pyfunction!(unsafe_kzg_params, m)?)?;
     Ok(())
 }
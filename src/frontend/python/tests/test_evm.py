@@ -0,0 +1,67 @@
+import os
+import tempfile
+import unittest
+
+from chiquito import rust_chiquito
+from chiquito.cb import eq
+from chiquito.chiquito_ast import Last
+from chiquito.dsl import Circuit, StepType
+from chiquito.util import F
+
+
+class CountStep(StepType):
+    def setup(self):
+        self.transition(eq(self.circuit.count + 1, self.circuit.count.next()))
+
+    def wg(self, count):
+        self.assign(self.circuit.count, F(count))
+
+
+class DoneStep(StepType):
+    def setup(self):
+        pass
+
+    def wg(self, count):
+        self.assign(self.circuit.count, F(count))
+
+
+class Counter(Circuit):
+    def setup(self):
+        self.count = self.forward("count")
+
+        self.count_step = self.step_type(CountStep(self, "count_step"))
+        self.done_step = self.step_type(DoneStep(self, "done_step"))
+
+        self.pragma_num_steps(4)
+        self.pragma_first_step(self.count_step)
+        self.pragma_last_step(self.done_step)
+
+        self.expose(self.count, Last())
+
+    def trace(self, n):
+        for i in range(n - 1):
+            self.add(self.count_step, i)
+        self.add(self.done_step, n - 1)
+
+
+class TestEvm(unittest.TestCase):
+    def test_solidity_verifier(self):
+        counter = Counter()
+        witness = counter.gen_witness(4)
+
+        with tempfile.TemporaryDirectory() as params_dir:
+            params_path = os.path.join(params_dir, "params.srs")
+            rust_chiquito.unsafe_kzg_params(counter.estimate_k(), params_path)
+
+            proof, instances = counter.halo2_prove(witness, params_path, evm=True)
+            self.assertEqual(instances, [[3]])
+            self.assertTrue(counter.halo2_verify(proof, instances, evm=True))
+
+            verifier = counter.solidity_verifier()
+
+        self.assertIn("contract Halo2Verifier", verifier)
+        self.assertIn("function verifyProof", verifier)
+
+
+if __name__ == "__main__":
+    unittest.main()
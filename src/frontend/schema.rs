@@ -0,0 +1,670 @@
+//! Schema of the JSON interchange format of circuits and witnesses, the one that the bindings
+//! parse in `frontend::interchange`, so other frontends validate their output before sending it to
+//! Rust. The format is described once, by the `Definition`s of its types, and emitted as a JSON
+//! Schema (draft 2020-12) and as TypeScript declarations.
+
+use serde_json::{json, Map, Value};
+
+/// Type of a JSON value of the interchange format.
+enum Type {
+    String,
+    /// String matching a regular expression.
+    Pattern(&'static str),
+    Boolean,
+    Integer,
+    Unsigned,
+    /// Any value, that the parser ignores.
+    Any,
+    /// Type of another definition, by name.
+    Ref(&'static str),
+    Array(Box<Type>),
+    /// Array with an item of each type, in order.
+    Tuple(Vec<Type>),
+    /// Object keyed by ids, with values of a type.
+    IdMap(Box<Type>),
+    Nullable(Box<Type>),
+    Object(Vec<Property>),
+    /// Object with a single key, the name of the variant, and its value, as serde serializes
+    /// enums.
+    Variants(Vec<(&'static str, Type)>),
+}
+
+struct Property {
+    name: &'static str,
+    ty: Type,
+    required: bool,
+}
+
+struct Definition {
+    name: &'static str,
+    description: &'static str,
+    ty: Type,
+}
+
+/// Definitions of the JSON documents that the bindings take, as in `ROOTS`.
+const ROOTS: [&str; 3] = ["Circuit", "SuperCircuitAst", "TraceWitness"];
+
+fn required(name: &'static str, ty: Type) -> Property {
+    Property {
+        name,
+        ty,
+        required: true,
+    }
+}
+
+fn optional(name: &'static str, ty: Type) -> Property {
+    Property {
+        name,
+        ty,
+        required: false,
+    }
+}
+
+fn array(ty: Type) -> Type {
+    Type::Array(Box::new(ty))
+}
+
+fn id_map(ty: Type) -> Type {
+    Type::IdMap(Box::new(ty))
+}
+
+fn nullable(ty: Type) -> Type {
+    Type::Nullable(Box::new(ty))
+}
+
+/// Variants of the queries, that are both a `Queriable` and an `Expr`.
+fn queriable_variants() -> Vec<(&'static str, Type)> {
+    vec![
+        ("Internal", Type::Ref("InternalSignal")),
+        (
+            "Forward",
+            Type::Tuple(vec![Type::Ref("ForwardSignal"), Type::Boolean]),
+        ),
+        (
+            "Shared",
+            Type::Tuple(vec![Type::Ref("SharedSignal"), Type::Integer]),
+        ),
+        (
+            "Fixed",
+            Type::Tuple(vec![Type::Ref("FixedSignal"), Type::Integer]),
+        ),
+        ("StepTypeNext", Type::Ref("StepTypeHandler")),
+    ]
+}
+
+/// Signal, or step type handler, with only an id and an annotation.
+fn signal() -> Type {
+    Type::Object(vec![
+        required("id", Type::Ref("Id")),
+        required("annotation", Type::String),
+    ])
+}
+
+/// Signal with the phase of the column it is placed in.
+fn phased_signal() -> Type {
+    Type::Object(vec![
+        required("id", Type::Ref("Id")),
+        required("phase", Type::Unsigned),
+        required("annotation", Type::String),
+    ])
+}
+
+fn definitions() -> Vec<Definition> {
+    let expr_variants = [
+        ("Const", Type::Ref("FieldValue")),
+        ("Sum", array(Type::Ref("Expr"))),
+        ("Mul", array(Type::Ref("Expr"))),
+        ("Neg", Type::Ref("Expr")),
+        ("Pow", Type::Tuple(vec![Type::Ref("Expr"), Type::Unsigned])),
+        (
+            "Challenge",
+            Type::Tuple(vec![Type::Unsigned, Type::Unsigned]),
+        ),
+    ];
+
+    vec![
+        Definition {
+            name: "Id",
+            description: "UUID of a circuit, step type or signal, as a decimal string.",
+            ty: Type::Pattern("^[0-9]+$"),
+        },
+        Definition {
+            name: "FieldValue",
+            description: "Field element: the hex of the 32 little endian bytes of its Montgomery \
+                          form, with a Montgomery factor of 2^256.",
+            ty: Type::Pattern("^[0-9a-fA-F]{64}$"),
+        },
+        Definition {
+            name: "Circuit",
+            description: "AST of a circuit. Fixed assignments are keyed by the id of their fixed \
+                          signal, with a value per step.",
+            ty: Type::Object(vec![
+                required("step_types", id_map(Type::Ref("StepType"))),
+                required("forward_signals", array(Type::Ref("ForwardSignal"))),
+                required("shared_signals", array(Type::Ref("SharedSignal"))),
+                required("fixed_signals", array(Type::Ref("FixedSignal"))),
+                required(
+                    "exposed",
+                    array(Type::Tuple(vec![
+                        Type::Ref("Queriable"),
+                        Type::Ref("ExposeOffset"),
+                    ])),
+                ),
+                required("annotations", id_map(Type::String)),
+                optional("aliases", id_map(array(Type::String))),
+                required(
+                    "fixed_assignments",
+                    nullable(id_map(Type::Tuple(vec![
+                        Type::Ref("Queriable"),
+                        array(Type::Ref("FieldValue")),
+                    ]))),
+                ),
+                required("first_step", nullable(Type::Ref("Id"))),
+                required("last_step", nullable(Type::Ref("Id"))),
+                required("num_steps", Type::Unsigned),
+                required("q_enable", Type::Boolean),
+                required("id", Type::Ref("Id")),
+            ]),
+        },
+        Definition {
+            name: "SuperCircuitAst",
+            description: "Super circuit: its sub-circuits, and the tables they look up into, \
+                          which are circuits without step types.",
+            ty: Type::Object(vec![
+                required("sub_circuits", array(Type::Ref("Circuit"))),
+                optional("tables", array(Type::Ref("Circuit"))),
+            ]),
+        },
+        Definition {
+            name: "StepType",
+            description: "Step type, with its internal signals, constraints and lookups.",
+            ty: Type::Object(vec![
+                required("id", Type::Ref("Id")),
+                required("name", Type::String),
+                required("signals", array(Type::Ref("InternalSignal"))),
+                required("constraints", array(Type::Ref("Constraint"))),
+                required("transition_constraints", array(Type::Ref("Constraint"))),
+                required("lookups", array(Type::Ref("Lookup"))),
+                required("annotations", id_map(Type::String)),
+            ]),
+        },
+        Definition {
+            name: "Constraint",
+            description: "Constraint, or transition constraint, that `expr` is zero.",
+            ty: Type::Object(vec![
+                required("annotation", Type::String),
+                required("expr", Type::Ref("Expr")),
+            ]),
+        },
+        Definition {
+            name: "Lookup",
+            description: "Lookup of source expressions, as constraints, into destination \
+                          expressions, enabled by `enable` if it is not null.",
+            ty: Type::Object(vec![
+                required("annotation", Type::String),
+                required(
+                    "exprs",
+                    array(Type::Tuple(vec![
+                        Type::Ref("Constraint"),
+                        Type::Ref("Expr"),
+                    ])),
+                ),
+                required("enable", nullable(Type::Ref("Constraint"))),
+            ]),
+        },
+        Definition {
+            name: "Expr",
+            description: "Expression over the field. Challenges are their id, at most 64 bits, \
+                          and the phase they are drawn after.",
+            ty: Type::Variants(
+                expr_variants
+                    .into_iter()
+                    .chain(queriable_variants())
+                    .collect(),
+            ),
+        },
+        Definition {
+            name: "Queriable",
+            description: "Query of a signal: forward signals at the next step if the boolean is \
+                          true, and shared and fixed signals at a rotation of steps.",
+            ty: Type::Variants(queriable_variants()),
+        },
+        Definition {
+            name: "ExposeOffset",
+            description: "Step of an exposed signal. The values of `First` and `Last` are ignored.",
+            ty: Type::Variants(vec![
+                ("First", Type::Any),
+                ("Last", Type::Any),
+                ("Step", Type::Unsigned),
+            ]),
+        },
+        Definition {
+            name: "InternalSignal",
+            description: "Signal of a single step type.",
+            ty: signal(),
+        },
+        Definition {
+            name: "ForwardSignal",
+            description: "Signal that the next step can query.",
+            ty: phased_signal(),
+        },
+        Definition {
+            name: "SharedSignal",
+            description: "Signal shared by all the steps, queried at a rotation of steps.",
+            ty: phased_signal(),
+        },
+        Definition {
+            name: "FixedSignal",
+            description: "Signal with values fixed by the circuit.",
+            ty: signal(),
+        },
+        Definition {
+            name: "StepTypeHandler",
+            description: "Reference to a step type, by its id.",
+            ty: signal(),
+        },
+        Definition {
+            name: "TraceWitness",
+            description: "Witness of a circuit: its step instances, in order.",
+            ty: Type::Object(vec![required(
+                "step_instances",
+                array(Type::Ref("StepInstance")),
+            )]),
+        },
+        Definition {
+            name: "StepInstance",
+            description: "Step of a witness: its step type and the values of its signals, keyed \
+                          by the id of the signal.",
+            ty: Type::Object(vec![
+                required("step_type_uuid", Type::Ref("Id")),
+                required(
+                    "assignments",
+                    id_map(Type::Tuple(vec![
+                        Type::Ref("Queriable"),
+                        Type::Ref("FieldValue"),
+                    ])),
+                ),
+            ]),
+        },
+    ]
+}
+
+/// Returns the JSON Schema of the interchange format, with a definition in `$defs` for each of
+/// its types. A document is a `Circuit`, a `SuperCircuitAst` or a `TraceWitness`, and frontends
+/// validate against one of them with `{ "$ref": "#/$defs/Circuit" }`.
+pub fn json_schema() -> Value {
+    let defs: Map<String, Value> = definitions()
+        .into_iter()
+        .map(|definition| {
+            let mut schema = type_schema(&definition.ty);
+            schema["description"] = json!(definition.description);
+            (definition.name.to_string(), schema)
+        })
+        .collect();
+    let roots: Vec<Value> = ROOTS
+        .iter()
+        .map(|root| type_schema(&Type::Ref(root)))
+        .collect();
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "chiquito interchange format",
+        "oneOf": roots,
+        "$defs": defs,
+    })
+}
+
+fn type_schema(ty: &Type) -> Value {
+    match ty {
+        Type::String => json!({ "type": "string" }),
+        Type::Pattern(pattern) => json!({ "type": "string", "pattern": pattern }),
+        Type::Boolean => json!({ "type": "boolean" }),
+        Type::Integer => json!({ "type": "integer" }),
+        Type::Unsigned => json!({ "type": "integer", "minimum": 0 }),
+        Type::Any => json!({}),
+        Type::Ref(name) => json!({ "$ref": format!("#/$defs/{}", name) }),
+        Type::Array(item) => json!({ "type": "array", "items": type_schema(item) }),
+        Type::Tuple(items) => json!({
+            "type": "array",
+            "prefixItems": items.iter().map(type_schema).collect::<Vec<_>>(),
+            "minItems": items.len(),
+            "items": false,
+        }),
+        Type::IdMap(value) => json!({
+            "type": "object",
+            "propertyNames": type_schema(&Type::Ref("Id")),
+            "additionalProperties": type_schema(value),
+        }),
+        Type::Nullable(ty) => json!({ "anyOf": [type_schema(ty), { "type": "null" }] }),
+        Type::Object(properties) => {
+            let schemas: Map<String, Value> = properties
+                .iter()
+                .map(|property| (property.name.to_string(), type_schema(&property.ty)))
+                .collect();
+            let required: Vec<&str> = properties
+                .iter()
+                .filter(|property| property.required)
+                .map(|property| property.name)
+                .collect();
+
+            json!({
+                "type": "object",
+                "properties": schemas,
+                "required": required,
+                "additionalProperties": false,
+            })
+        }
+        Type::Variants(variants) => {
+            let variants: Vec<Value> = variants
+                .iter()
+                .map(|(name, ty)| {
+                    json!({
+                        "type": "object",
+                        "properties": { *name: type_schema(ty) },
+                        "required": [name],
+                        "additionalProperties": false,
+                    })
+                })
+                .collect();
+
+            json!({ "oneOf": variants })
+        }
+    }
+}
+
+/// Returns the TypeScript declarations of the types of the interchange format, with an interface
+/// for each object and a type alias for the others.
+pub fn typescript() -> String {
+    let mut declarations = String::from("// Types of the JSON interchange format of chiquito.\n");
+    for definition in definitions() {
+        declarations += &format!("\n/** {} */\n", definition.description);
+        declarations += &match &definition.ty {
+            Type::Object(properties) => format!(
+                "export interface {} {}\n",
+                definition.name,
+                typescript_object(properties, "")
+            ),
+            Type::Variants(variants) => {
+                let variants: String = variants
+                    .iter()
+                    .map(|(name, ty)| format!("\n  | {{ {}: {} }}", name, typescript_type(ty)))
+                    .collect();
+                format!("export type {} ={};\n", definition.name, variants)
+            }
+            ty => format!(
+                "export type {} = {};\n",
+                definition.name,
+                typescript_type(ty)
+            ),
+        };
+    }
+
+    declarations
+}
+
+fn typescript_type(ty: &Type) -> String {
+    match ty {
+        Type::String | Type::Pattern(_) => "string".to_string(),
+        Type::Boolean => "boolean".to_string(),
+        Type::Integer | Type::Unsigned => "number".to_string(),
+        Type::Any => "unknown".to_string(),
+        Type::Ref(name) => name.to_string(),
+        Type::Array(item) => match item.as_ref() {
+            Type::Nullable(_) | Type::Variants(_) => format!("({})[]", typescript_type(item)),
+            item => format!("{}[]", typescript_type(item)),
+        },
+        Type::Tuple(items) => format!(
+            "[{}]",
+            items
+                .iter()
+                .map(typescript_type)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Type::IdMap(value) => format!("Record<Id, {}>", typescript_type(value)),
+        Type::Nullable(ty) => format!("{} | null", typescript_type(ty)),
+        Type::Object(properties) => typescript_object(properties, "  "),
+        Type::Variants(variants) => variants
+            .iter()
+            .map(|(name, ty)| format!("{{ {}: {} }}", name, typescript_type(ty)))
+            .collect::<Vec<_>>()
+            .join(" | "),
+    }
+}
+
+/// Body of an object type, with its properties indented by `indent`.
+fn typescript_object(properties: &[Property], indent: &str) -> String {
+    let properties: String = properties
+        .iter()
+        .map(|property| {
+            format!(
+                "{}  {}{}: {};\n",
+                indent,
+                property.name,
+                if property.required { "" } else { "?" },
+                typescript_type(&property.ty)
+            )
+        })
+        .collect();
+
+    format!("{{\n{}{}}}", properties, indent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Validates `value` against `schema`, with the keywords that `json_schema` emits except
+    /// `pattern` and `propertyNames`, as the ids and field values are parsed by the bindings
+    /// anyway. Returns the JSON pointer of the first invalid value.
+    fn validate(root: &Value, schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+        let invalid = || Err(path.to_string());
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            let name = reference.trim_start_matches("#/$defs/");
+            return validate(root, &root["$defs"][name], value, path);
+        }
+        if let Some(variants) = schema.get("oneOf").and_then(Value::as_array) {
+            let valid = variants
+                .iter()
+                .filter(|variant| validate(root, variant, value, path).is_ok())
+                .count();
+            return if valid == 1 { Ok(()) } else { invalid() };
+        }
+        if let Some(variants) = schema.get("anyOf").and_then(Value::as_array) {
+            return match variants
+                .iter()
+                .any(|variant| validate(root, variant, value, path).is_ok())
+            {
+                true => Ok(()),
+                false => invalid(),
+            };
+        }
+
+        match (schema.get("type").and_then(Value::as_str), value) {
+            (None, _) => Ok(()),
+            (Some("null"), Value::Null) => Ok(()),
+            (Some("boolean"), Value::Bool(_)) => Ok(()),
+            (Some("string"), Value::String(_)) => Ok(()),
+            (Some("integer"), Value::Number(number)) => {
+                let minimum = schema.get("minimum").and_then(Value::as_i64);
+                match (number.as_i64(), minimum) {
+                    (Some(number), Some(minimum)) if number < minimum => invalid(),
+                    (None, _) if !number.is_u64() => invalid(),
+                    _ => Ok(()),
+                }
+            }
+            (Some("array"), Value::Array(items)) => {
+                let prefix = schema
+                    .get("prefixItems")
+                    .and_then(Value::as_array)
+                    .cloned()
+                    .unwrap_or_default();
+                if let Some(min_items) = schema.get("minItems").and_then(Value::as_u64) {
+                    if (items.len() as u64) < min_items {
+                        return invalid();
+                    }
+                }
+                for (index, item) in items.iter().enumerate() {
+                    let path = format!("{}/{}", path, index);
+                    match (prefix.get(index), &schema["items"]) {
+                        (Some(item_schema), _) => validate(root, item_schema, item, &path)?,
+                        (None, Value::Bool(false)) => return Err(path),
+                        (None, item_schema) => validate(root, item_schema, item, &path)?,
+                    }
+                }
+                Ok(())
+            }
+            (Some("object"), Value::Object(object)) => {
+                let required = schema["required"].as_array().cloned().unwrap_or_default();
+                if let Some(missing) = required
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .find(|name| !object.contains_key(*name))
+                {
+                    return Err(format!("{}/{}", path, missing));
+                }
+                for (name, item) in object {
+                    let path = format!("{}/{}", path, name);
+                    match (
+                        schema["properties"].get(name),
+                        &schema["additionalProperties"],
+                    ) {
+                        (Some(item_schema), _) => validate(root, item_schema, item, &path)?,
+                        (None, Value::Bool(false)) => return Err(path),
+                        (None, Value::Null) => {}
+                        (None, item_schema) => validate(root, item_schema, item, &path)?,
+                    }
+                }
+                Ok(())
+            }
+            _ => invalid(),
+        }
+    }
+
+    fn circuit_json() -> Value {
+        let one = "01".to_string() + &"0".repeat(62);
+        let forward = json!({ "id": "1", "phase": 0, "annotation": "a" });
+        let fixed = json!({ "id": "3", "annotation": "byte" });
+
+        json!({
+            "step_types": {
+                "10": {
+                    "id": "10",
+                    "name": "step",
+                    "signals": [{ "id": "11", "annotation": "c" }],
+                    "constraints": [{
+                        "annotation": "c == a + 1",
+                        "expr": { "Sum": [
+                            { "Internal": { "id": "11", "annotation": "c" } },
+                            { "Neg": { "Forward": [forward, false] } },
+                            { "Const": one },
+                        ] },
+                    }],
+                    "transition_constraints": [{
+                        "annotation": "next",
+                        "expr": { "Mul": [
+                            { "Forward": [forward, true] },
+                            { "Pow": [{ "Challenge": [5, 1] }, 2] },
+                            { "StepTypeNext": { "id": "10", "annotation": "step" } },
+                        ] },
+                    }],
+                    "lookups": [{
+                        "annotation": "byte",
+                        "exprs": [[
+                            {
+                                "annotation": "c",
+                                "expr": { "Internal": { "id": "11", "annotation": "c" } },
+                            },
+                            { "Fixed": [fixed, 0] },
+                        ]],
+                        "enable": null,
+                    }],
+                    "annotations": { "11": "c" },
+                },
+            },
+            "forward_signals": [forward],
+            "shared_signals": [{ "id": "2", "phase": 1, "annotation": "b" }],
+            "fixed_signals": [fixed],
+            "exposed": [[{ "Forward": [forward, false] }, { "Last": -1 }]],
+            "annotations": { "1": "a", "2": "b", "3": "byte", "10": "step" },
+            "fixed_assignments": { "3": [{ "Fixed": [fixed, 0] }, [one, one]] },
+            "first_step": "10",
+            "last_step": null,
+            "num_steps": 2,
+            "q_enable": true,
+            "id": "20",
+        })
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let schema = json_schema();
+        let circuit = circuit_json();
+        assert_eq!(validate(&schema, &schema, &circuit, ""), Ok(()));
+
+        let super_circuit = json!({ "sub_circuits": [circuit.clone()] });
+        assert_eq!(validate(&schema, &schema, &super_circuit, ""), Ok(()));
+
+        let witness = json!({
+            "step_instances": [{
+                "step_type_uuid": "10",
+                "assignments": {
+                    "11": [{ "Internal": { "id": "11", "annotation": "c" } }, "0".repeat(64)],
+                },
+            }],
+        });
+        assert_eq!(validate(&schema, &schema, &witness, ""), Ok(()));
+
+        let mut invalid = circuit.clone();
+        invalid.as_object_mut().unwrap().remove("num_steps");
+        assert!(validate(&schema, &schema, &invalid, "").is_err());
+
+        let mut invalid = circuit.clone();
+        invalid["step_types"]["10"]["lookups"][0]["exprs"][0][1] = json!({ "Fixed": [{}, 0] });
+        assert_eq!(
+            validate(&schema, &schema["$defs"]["Circuit"], &invalid, ""),
+            Err("/step_types/10/lookups/0/exprs/0/1".to_string())
+        );
+
+        let mut invalid = circuit;
+        invalid["exposed"][0][1] = json!({ "Step": -1 });
+        assert!(validate(&schema, &schema, &invalid, "").is_err());
+    }
+
+    #[cfg(feature = "halo2")]
+    #[test]
+    fn test_json_schema_of_interchange() {
+        use halo2_proofs::halo2curves::bn256::Fr;
+
+        use crate::{
+            frontend::interchange::{from_json, sbpir_to_json},
+            sbpir::SBPIR,
+        };
+
+        let schema = json_schema();
+        let circuit: SBPIR<Fr, ()> = from_json(&circuit_json().to_string(), "Circuit").unwrap();
+        let json = sbpir_to_json(&circuit).unwrap();
+
+        assert_eq!(
+            validate(&schema, &schema["$defs"]["Circuit"], &json, ""),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_typescript() {
+        let typescript = typescript();
+
+        assert!(typescript.contains(
+            "/** Witness of a circuit: its step instances, in order. */\n\
+             export interface TraceWitness {\n  step_instances: StepInstance[];\n}\n"
+        ));
+        assert!(typescript.contains("  aliases?: Record<Id, string[]>;\n"));
+        assert!(typescript.contains("  first_step: Id | null;\n"));
+        assert!(typescript.contains(
+            "export type ExposeOffset =\n  | { First: unknown }\n  | { Last: unknown }\n  \
+             | { Step: number };\n"
+        ));
+        assert!(typescript.contains("export type Id = string;\n"));
+    }
+}
@@ -0,0 +1,744 @@
+//! HTTP server of the compiler and the prover, for frontends that run where the native bindings
+//! cannot be linked. Circuits are posted as the JSON of their AST, with the JSON of
+//! `CompilerOptions`, and cached by their id: the SHA-256 of the canonical JSON of the SBPIR, as
+//! Rust parsed it, and of the options, so posting the same circuit again is not compiled again.
+//! Witnesses are posted as the JSON of a `TraceWitness`, or in the binary format of
+//! `wit_gen::binary`, and field elements are decimal strings.
+//!
+//! - `POST /circuits`, with `{ "ast": .., "options": .. }`, compiles a circuit, or finds it in the
+//!   cache, and returns its `circuit_id`, its field and its stats.
+//! - `GET /circuits/{id}` returns the AST and the stats of a circuit, and `DELETE` drops it.
+//! - `GET /circuits/{id}/plonkish` returns the plonkish IR of a circuit.
+//! - `POST /circuits/{id}/check` checks a witness with the native `Checker`, and returns the
+//!   violations.
+//! - `POST /circuits/{id}/mock_prove?k=..` runs `MockProver` for a witness, with a k of at most the
+//!   `max_k` of the `ServerConfig`.
+//! - `POST /circuits/{id}/prove?evm=..` proves a witness, and returns the proof in hex and its
+//!   public inputs.
+//! - `POST /circuits/{id}/verify?evm=..`, with `{ "proof": .., "instance": .. }`, verifies a proof.
+//! - `GET /circuits/{id}/verifying_key` returns the verifying key, in the raw bytes format of
+//!   halo2, and `GET /circuits/{id}/solidity_verifier` a Solidity verifier.
+//! - `GET /schema` returns the JSON Schema of the ASTs and the JSON witnesses.
+//!
+//! Only bn256 circuits are proven, with the KZG params of the smallest k that fits them, read from
+//! `kzg_bn254_{k}.srs` in the params directory. Errors are `{ "error": .. }`, with the status 400
+//! for invalid requests, 404 for unknown circuits, 413 for bodies over the `max_body_bytes` of the
+//! `ServerConfig`, 422 for circuits that do not compile and 500 for failures of the server.
+//!
+//! Run with `cargo run --release --no-default-features --features server --bin chiquito-server`.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Write,
+    io::Read,
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    thread,
+};
+
+use halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::VerifyingKey,
+    poly::kzg::commitment::ParamsKZG,
+    SerdeFormat,
+};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{
+    frontend::{
+        interchange::{
+            self, check_witness, circuit_stats_to_json, field_from_decimal, field_to_decimal,
+            from_json, mock_prover, panic_message, plonkish_to_json, sbpir_to_json,
+            violation_to_json, with_field, witness_from_bytes, CompilerOptions, InterchangeError,
+            StoredCircuit,
+        },
+        proving::{create_halo2_proof, load_params, solidity_verifier, verify_halo2_proof},
+        schema,
+    },
+    plonkish::backend::halo2::{ChiquitoHalo2, ChiquitoHalo2Circuit},
+    sbpir::SBPIR,
+};
+
+/// Configuration of `serve`.
+#[derive(Clone, Debug)]
+pub struct ServerConfig {
+    /// Address to listen on, as `host:port`.
+    pub addr: String,
+    /// Directory of the KZG params, without which circuits are not proven.
+    pub params_dir: Option<PathBuf>,
+    /// Threads that handle the requests.
+    pub threads: usize,
+    /// Compiled circuits kept in the cache, beyond which the first compiled are dropped.
+    pub max_circuits: usize,
+    /// Largest k of `mock_prove`, as `MockProver` allocates the `2^k` rows of every column.
+    pub max_k: u32,
+    /// Largest body of a request, beyond which it is rejected without reading the rest.
+    pub max_body_bytes: usize,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            addr: "127.0.0.1:8080".to_string(),
+            params_dir: None,
+            threads: 4,
+            max_circuits: 64,
+            max_k: 20,
+            max_body_bytes: 64 << 20,
+        }
+    }
+}
+
+/// Serves the compiler and the prover over HTTP on `config.addr`, until the process ends.
+pub fn serve(config: ServerConfig) -> Result<(), InterchangeError> {
+    let server = Server::http(&config.addr)
+        .map_err(|e| InterchangeError::Io(format!("cannot listen on {}: {}", config.addr, e)))?;
+    let state = ServerState::new(config);
+
+    thread::scope(|scope| {
+        for _ in 0..state.config.threads.max(1) {
+            scope.spawn(|| {
+                for request in server.incoming_requests() {
+                    state.respond(request);
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+struct ServerState {
+    config: ServerConfig,
+    cache: Mutex<CircuitCache>,
+}
+
+/// Compiled circuits by id, with the order they were compiled in, to drop the first.
+#[derive(Default)]
+struct CircuitCache {
+    /// Circuits with their AST as its canonical JSON.
+    circuits: HashMap<String, Arc<Mutex<StoredCircuit>>>,
+    order: VecDeque<String>,
+}
+
+/// Body of `POST /circuits`.
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CompileRequest {
+    ast: Value,
+    options: Option<Value>,
+}
+
+/// Body of `POST /circuits/{id}/verify`.
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VerifyRequest {
+    proof: String,
+    instance: Vec<Vec<String>>,
+}
+
+/// Response to a request, with its status and content type.
+#[derive(Debug)]
+struct Reply {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Reply {
+    fn json(json: Value) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/json",
+            body: json.to_string().into_bytes(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            ..Self::json(json!({ "error": message }))
+        }
+    }
+}
+
+/// Failure of a request, replied with its status.
+#[derive(Debug)]
+struct HttpError {
+    status: u16,
+    message: String,
+}
+
+impl HttpError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<InterchangeError> for HttpError {
+    fn from(error: InterchangeError) -> Self {
+        let status = match error {
+            InterchangeError::Deserialization(_) | InterchangeError::Value(_) => 400,
+            InterchangeError::Compilation(_) => 422,
+            InterchangeError::Io(_) | InterchangeError::Proving(_) => 500,
+        };
+
+        Self::new(status, error.to_string())
+    }
+}
+
+type HttpResult<T> = Result<T, HttpError>;
+
+impl ServerState {
+    fn new(config: ServerConfig) -> Self {
+        Self {
+            config,
+            cache: Default::default(),
+        }
+    }
+
+    fn respond(&self, mut request: Request) {
+        let reply = match self.read_body(request.as_reader()) {
+            Ok(body) => self.handle(request.method(), request.url(), &body),
+            Err(error) => Reply::error(error.status, &error.message),
+        };
+
+        let content_type = Header::from_bytes("Content-Type", reply.content_type)
+            .expect("valid content type header");
+        let response = Response::from_data(reply.body)
+            .with_status_code(reply.status)
+            .with_header(content_type);
+        // The client may be gone, and there is nobody else to tell.
+        let _ = request.respond(response);
+    }
+
+    /// Reads the body of a request, of at most `max_body_bytes`.
+    fn read_body(&self, reader: &mut dyn Read) -> HttpResult<Vec<u8>> {
+        let max_body_bytes = self.config.max_body_bytes;
+        let mut body = Vec::new();
+        reader
+            .take(max_body_bytes as u64 + 1)
+            .read_to_end(&mut body)
+            .map_err(|e| HttpError::new(400, format!("cannot read the body: {}", e)))?;
+
+        if body.len() > max_body_bytes {
+            return Err(HttpError::new(
+                413,
+                format!("body is over the maximum of {} bytes", max_body_bytes),
+            ));
+        }
+        Ok(body)
+    }
+
+    /// Replies to a request, with the error of a panic of the handlers as a failure of the server.
+    fn handle(&self, method: &Method, url: &str, body: &[u8]) -> Reply {
+        match panic::catch_unwind(AssertUnwindSafe(|| self.route(method, url, body))) {
+            Ok(Ok(reply)) => reply,
+            Ok(Err(error)) => Reply::error(error.status, &error.message),
+            Err(payload) => Reply::error(500, &panic_message(payload)),
+        }
+    }
+
+    fn route(&self, method: &Method, url: &str, body: &[u8]) -> HttpResult<Reply> {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let segments: Vec<_> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+        match (method, segments.as_slice()) {
+            (Method::Post, ["circuits"]) => self.compile(body),
+            (Method::Get, ["schema"]) => Ok(Reply::json(schema::json_schema())),
+            (Method::Get, ["circuits", id]) => self.circuit_info(id),
+            (Method::Delete, ["circuits", id]) => self.remove(id),
+            (Method::Get, ["circuits", id, "plonkish"]) => self.plonkish(id),
+            (Method::Post, ["circuits", id, "check"]) => self.check(id, body),
+            (Method::Post, ["circuits", id, "mock_prove"]) => {
+                self.mock_prove(id, body, k_param(query, self.config.max_k)?)
+            }
+            (Method::Post, ["circuits", id, "prove"]) => self.prove(id, body, evm_param(query)?),
+            (Method::Post, ["circuits", id, "verify"]) => self.verify(id, body, evm_param(query)?),
+            (Method::Get, ["circuits", id, "verifying_key"]) => self.verifying_key(id),
+            (Method::Get, ["circuits", id, "solidity_verifier"]) => self.solidity_verifier(id),
+            _ => Err(HttpError::new(404, format!("no route {} {}", method, path))),
+        }
+    }
+
+    /// Compiles a circuit, unless one with the same id is in the cache.
+    fn compile(&self, body: &[u8]) -> HttpResult<Reply> {
+        let request: CompileRequest = from_json(body_str(body)?, "CompileRequest")?;
+        let options_json = request.options.map(|options| options.to_string());
+        let options = CompilerOptions::from_json(options_json.as_deref())?;
+        let ast_json = request.ast.to_string();
+
+        with_field!(options.field, F => {
+            let ast: SBPIR<F, ()> = from_json(&ast_json, "Circuit")?;
+            let canonical = sbpir_to_json(&ast).map_err(|e| HttpError::new(500, e))?;
+            let canonical = canonical_json(&canonical);
+            let options_json = serde_json::to_value(&options).expect("options serialize to JSON");
+            let id = circuit_id(&canonical, &options_json);
+
+            let cached = match self.find(&id) {
+                Some(circuit) => circuit,
+                None => {
+                    let (halo2, assigner) = interchange::compile_ast::<F>(&ast_json, &options)?;
+                    self.insert(&id, StoredCircuit::new(&canonical, halo2, assigner))
+                }
+            };
+            let circuit = lock(&cached);
+
+            Ok(Reply::json(json!({
+                "circuit_id": id,
+                "field": F::FIELD.name(),
+                "stats": circuit_stats_to_json(&circuit.compiled::<F>()?.halo2.stats()),
+            })))
+        })
+    }
+
+    fn circuit_info(&self, id: &str) -> HttpResult<Reply> {
+        let circuit = self.circuit(id)?;
+        let circuit = lock(&circuit);
+        let ast: Value = serde_json::from_str(circuit.ast_json()).expect("stored AST is JSON");
+
+        with_field!(circuit.field(), F => Ok(Reply::json(json!({
+            "circuit_id": id,
+            "field": F::FIELD.name(),
+            "ast": ast,
+            "stats": circuit_stats_to_json(&circuit.compiled::<F>()?.halo2.stats()),
+        }))))
+    }
+
+    fn remove(&self, id: &str) -> HttpResult<Reply> {
+        let mut cache = self.cache();
+        if cache.circuits.remove(id).is_none() {
+            return Err(unknown_circuit(id));
+        }
+        cache.order.retain(|cached| cached != id);
+
+        Ok(Reply::json(json!({ "circuit_id": id })))
+    }
+
+    fn plonkish(&self, id: &str) -> HttpResult<Reply> {
+        let circuit = self.circuit(id)?;
+        let circuit = lock(&circuit);
+
+        with_field!(circuit.field(), F => {
+            let compiled = circuit.compiled::<F>()?;
+            let placement = compiled.assigner.as_ref().map(|assigner| assigner.placement());
+            let plonkish = plonkish_to_json(compiled.halo2.circuit(), placement)
+                .map_err(|e| HttpError::new(500, e))?;
+
+            Ok(Reply::json(plonkish))
+        })
+    }
+
+    fn check(&self, id: &str, body: &[u8]) -> HttpResult<Reply> {
+        let (field, ast_json) = {
+            let circuit = self.circuit(id)?;
+            let circuit = lock(&circuit);
+            (circuit.field(), circuit.ast_json().to_string())
+        };
+
+        with_field!(field, F => {
+            let ast: SBPIR<F, ()> = from_json(&ast_json, "Circuit")?;
+            let violations = check_witness(&ast, &witness_from_bytes::<F>(body)?)?;
+            let violations: Vec<_> = violations.iter().map(violation_to_json).collect();
+
+            Ok(Reply::json(Value::from(violations)))
+        })
+    }
+
+    fn mock_prove(&self, id: &str, body: &[u8], k: Option<usize>) -> HttpResult<Reply> {
+        let circuit = self.circuit(id)?;
+        let field = lock(&circuit).field();
+
+        with_field!(field, F => {
+            let compiled = lock(&circuit).compiled::<F>()?.clone();
+            let assignments = compiled.assignments(witness_from_bytes::<F>(body)?);
+            let circuit = ChiquitoHalo2Circuit::new(compiled.halo2.clone(), assignments);
+
+            let min_k = compiled.halo2.stats().min_k;
+            if k.is_none() && min_k > self.config.max_k {
+                return Err(HttpError::new(
+                    422,
+                    format!(
+                        "the circuit needs k = {}, over the maximum of {}",
+                        min_k, self.config.max_k
+                    ),
+                ));
+            }
+            let prover = mock_prover(k, min_k, &circuit, circuit.instance())?;
+            let failures: Vec<_> = match prover.verify() {
+                Ok(()) => Vec::new(),
+                Err(failures) => failures.iter().map(ToString::to_string).collect(),
+            };
+
+            Ok(Reply::json(json!({ "ok": failures.is_empty(), "failures": failures })))
+        })
+    }
+
+    fn prove(&self, id: &str, body: &[u8], evm: bool) -> HttpResult<Reply> {
+        let circuit = self.circuit(id)?;
+        let (compiled, keys) = {
+            let circuit = lock(&circuit);
+            (circuit.compiled::<Fr>()?.clone(), circuit.keys())
+        };
+        let (params_path, params) = self.params(&compiled.halo2)?;
+        let pk = keys.proving_key(&params_path, &params, &compiled.halo2)?;
+
+        let assignments = compiled.assignments(witness_from_bytes::<Fr>(body)?);
+        let circuit = ChiquitoHalo2Circuit::new(compiled.halo2, assignments);
+        let (proof, instance) = create_halo2_proof(&params, &pk, circuit, evm)?;
+        let instance: Vec<Vec<_>> = instance
+            .iter()
+            .map(|column| column.iter().map(field_to_decimal).collect())
+            .collect();
+
+        Ok(Reply::json(json!({
+            "proof": to_hex(&proof),
+            "instance": instance,
+        })))
+    }
+
+    fn verify(&self, id: &str, body: &[u8], evm: bool) -> HttpResult<Reply> {
+        let request: VerifyRequest = from_json(body_str(body)?, "VerifyRequest")?;
+        let proof = from_hex(&request.proof)?;
+        let instance = request
+            .instance
+            .iter()
+            .map(|column| {
+                column
+                    .iter()
+                    .map(|value| field_from_decimal(value))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let circuit = self.circuit(id)?;
+        let (params, vk) = self.params_and_verifying_key(&circuit)?;
+        let valid = verify_halo2_proof(&params, &vk, &proof, &instance, evm)?;
+
+        Ok(Reply::json(json!({ "valid": valid })))
+    }
+
+    fn verifying_key(&self, id: &str) -> HttpResult<Reply> {
+        let circuit = self.circuit(id)?;
+        let (_, vk) = self.params_and_verifying_key(&circuit)?;
+
+        Ok(Reply {
+            status: 200,
+            content_type: "application/octet-stream",
+            body: vk.to_bytes(SerdeFormat::RawBytes),
+        })
+    }
+
+    fn solidity_verifier(&self, id: &str) -> HttpResult<Reply> {
+        let circuit = self.circuit(id)?;
+        let (params, vk) = self.params_and_verifying_key(&circuit)?;
+        let compiled = lock(&circuit).compiled::<Fr>()?.halo2.clone();
+
+        Ok(Reply {
+            status: 200,
+            content_type: "text/plain; charset=utf-8",
+            body: solidity_verifier(&params, &vk, &compiled)?.into_bytes(),
+        })
+    }
+
+    fn cache(&self) -> MutexGuard<'_, CircuitCache> {
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn find(&self, id: &str) -> Option<Arc<Mutex<StoredCircuit>>> {
+        self.cache().circuits.get(id).cloned()
+    }
+
+    fn circuit(&self, id: &str) -> HttpResult<Arc<Mutex<StoredCircuit>>> {
+        self.find(id).ok_or_else(|| unknown_circuit(id))
+    }
+
+    /// Caches a circuit, dropping the first compiled beyond `max_circuits`. Returns the circuit
+    /// cached with its id, which is the one of another request if it compiled it first.
+    fn insert(&self, id: &str, circuit: StoredCircuit) -> Arc<Mutex<StoredCircuit>> {
+        let mut cache = self.cache();
+        if let Some(cached) = cache.circuits.get(id) {
+            return cached.clone();
+        }
+
+        let circuit = Arc::new(Mutex::new(circuit));
+        cache.circuits.insert(id.to_string(), circuit.clone());
+        cache.order.push_back(id.to_string());
+        while cache.order.len() > self.config.max_circuits.max(1) {
+            if let Some(first) = cache.order.pop_front() {
+                cache.circuits.remove(&first);
+            }
+        }
+
+        circuit
+    }
+
+    /// Returns the path of the KZG params of the smallest k that fits `compiled`, and the params.
+    fn params(&self, compiled: &ChiquitoHalo2<Fr>) -> HttpResult<(String, Arc<ParamsKZG<Bn256>>)> {
+        let params_dir = self.config.params_dir.as_ref().ok_or_else(|| {
+            HttpError::new(
+                501,
+                "server started without a params directory, it does not prove",
+            )
+        })?;
+        let path = params_dir.join(format!("kzg_bn254_{}.srs", compiled.stats().min_k));
+        let path = path.to_string_lossy().to_string();
+        let params = load_params(&path)?;
+
+        Ok((path, params))
+    }
+
+    /// Returns the params of a bn256 circuit and its verifying key, the one of its proving key if
+    /// it was proven, or else generating it the first time.
+    fn params_and_verifying_key(
+        &self,
+        circuit: &Mutex<StoredCircuit>,
+    ) -> HttpResult<(Arc<ParamsKZG<Bn256>>, Arc<VerifyingKey<G1Affine>>)> {
+        let (compiled, keys) = {
+            let circuit = lock(circuit);
+            (circuit.compiled::<Fr>()?.halo2.clone(), circuit.keys())
+        };
+        let (params_path, params) = self.params(&compiled)?;
+        let vk = keys.verifying_key(&params_path, &params, &compiled)?;
+
+        Ok((params, vk))
+    }
+}
+
+fn lock(circuit: &Mutex<StoredCircuit>) -> MutexGuard<'_, StoredCircuit> {
+    circuit.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn unknown_circuit(id: &str) -> HttpError {
+    HttpError::new(404, format!("no circuit {}, it may have been dropped", id))
+}
+
+fn body_str(body: &[u8]) -> HttpResult<&str> {
+    std::str::from_utf8(body).map_err(|e| HttpError::new(400, format!("body is not UTF-8: {}", e)))
+}
+
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|param| param.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// Parses the `k` of `mock_prove`, which must not be over `max_k`.
+fn k_param(query: &str, max_k: u32) -> HttpResult<Option<usize>> {
+    match query_param(query, "k").map(|k| (k, k.parse::<u64>())) {
+        None => Ok(None),
+        Some((_, Ok(k))) if k <= max_k as u64 => Ok(Some(k as usize)),
+        Some((_, Ok(k))) => Err(HttpError::new(
+            400,
+            format!("k {} is over the maximum of {}", k, max_k),
+        )),
+        Some((k, Err(_))) => Err(HttpError::new(400, format!("k {} is not a number", k))),
+    }
+}
+
+fn evm_param(query: &str) -> HttpResult<bool> {
+    match query_param(query, "evm") {
+        None | Some("false") => Ok(false),
+        Some("true") => Ok(true),
+        Some(evm) => Err(HttpError::new(400, format!("evm {} is not a bool", evm))),
+    }
+}
+
+/// Id of a circuit in the cache: the SHA-256, in hex, of the canonical JSON of its AST and of its
+/// compiler options.
+fn circuit_id(ast_json: &str, options: &Value) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(ast_json.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(canonical_json(options).as_bytes());
+
+    to_hex(&hasher.finalize())
+}
+
+/// Serializes JSON with the keys of its objects sorted and without whitespace, so equal values
+/// serialize to the same string whatever the order of their keys.
+fn canonical_json(json: &Value) -> String {
+    match json {
+        Value::Object(object) => {
+            let mut entries: Vec<_> = object.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            let entries: Vec<_> = entries
+                .into_iter()
+                .map(|(key, value)| {
+                    format!("{}:{}", Value::from(key.as_str()), canonical_json(value))
+                })
+                .collect();
+            format!("{{{}}}", entries.join(","))
+        }
+        Value::Array(array) => {
+            let items: Vec<_> = array.iter().map(canonical_json).collect();
+            format!("[{}]", items.join(","))
+        }
+        _ => json.to_string(),
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        write!(hex, "{:02x}", byte).expect("writing to a string does not fail");
+        hex
+    })
+}
+
+fn from_hex(hex: &str) -> HttpResult<Vec<u8>> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    let invalid = || HttpError::new(400, "proof is not hex");
+    if hex.len() % 2 != 0 {
+        return Err(invalid());
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(invalid)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::interchange::tests::circuit_with_fixed_assignments;
+
+    fn state() -> ServerState {
+        ServerState::new(ServerConfig::default())
+    }
+
+    fn json_reply(reply: Reply) -> (u16, Value) {
+        (reply.status, serde_json::from_slice(&reply.body).unwrap())
+    }
+
+    fn compile(state: &ServerState) -> Value {
+        let body = format!(r#"{{ "ast": {} }}"#, circuit_with_fixed_assignments("null"));
+        let (status, reply) = json_reply(state.handle(&Method::Post, "/circuits", body.as_bytes()));
+        assert_eq!(status, 200, "{}", reply);
+
+        reply
+    }
+
+    #[test]
+    fn test_compile_cached() {
+        let state = state();
+        let compiled = compile(&state);
+        let id = compiled["circuit_id"].as_str().unwrap();
+        assert_eq!(id.len(), 64);
+        assert_eq!(compiled["field"], "bn256");
+        assert_eq!(compile(&state)["circuit_id"], id);
+        assert_eq!(state.cache().circuits.len(), 1);
+
+        let url = format!("/circuits/{}", id);
+        let (status, info) = json_reply(state.handle(&Method::Get, &url, b""));
+        assert_eq!(status, 200);
+        assert_eq!(info["ast"]["fixed_signals"][0]["annotation"], "byte");
+        assert_eq!(info["stats"], compiled["stats"]);
+
+        assert_eq!(state.handle(&Method::Delete, &url, b"").status, 200);
+        assert_eq!(state.handle(&Method::Get, &url, b"").status, 404);
+    }
+
+    #[test]
+    fn test_check_and_mock_prove() {
+        let state = state();
+        let compiled = compile(&state);
+        let id = compiled["circuit_id"].as_str().unwrap();
+        let min_k = compiled["stats"]["min_k"].as_u64().unwrap();
+        let witness = br#"{ "step_instances": [] }"#;
+
+        let url = format!("/circuits/{}/check", id);
+        let (status, violations) = json_reply(state.handle(&Method::Post, &url, witness));
+        assert_eq!(status, 200);
+        assert_eq!(violations, json!([]));
+
+        let url = format!("/circuits/{}/mock_prove", id);
+        let (status, result) = json_reply(state.handle(&Method::Post, &url, witness));
+        assert_eq!(status, 200);
+        assert_eq!(result["ok"], true);
+
+        let (status, result) =
+            json_reply(state.handle(&Method::Post, &format!("{}?k={}", url, min_k - 1), witness));
+        assert_eq!(status, 422);
+        assert!(result["error"].as_str().unwrap().contains("too small"));
+
+        for k in ["21", "4294967316"] {
+            let (status, result) =
+                json_reply(state.handle(&Method::Post, &format!("{}?k={}", url, k), witness));
+            assert_eq!(status, 400);
+            assert_eq!(
+                result["error"],
+                format!("k {} is over the maximum of 20", k)
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_body() {
+        let state = ServerState::new(ServerConfig {
+            max_body_bytes: 4,
+            ..Default::default()
+        });
+
+        assert_eq!(state.read_body(&mut &b"abcd"[..]).unwrap(), b"abcd");
+        let error = state.read_body(&mut &b"abcde"[..]).unwrap_err();
+        assert_eq!(error.status, 413);
+        assert_eq!(error.message, "body is over the maximum of 4 bytes");
+    }
+
+    #[test]
+    fn test_errors() {
+        let state = state();
+        let (status, error) = json_reply(state.handle(&Method::Post, "/circuits", b"{"));
+        assert_eq!(status, 400);
+        assert!(error["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Json deserialization to CompileRequest failed"));
+
+        assert_eq!(state.handle(&Method::Get, "/circuits/0", b"").status, 404);
+        assert_eq!(state.handle(&Method::Get, "/witnesses", b"").status, 404);
+
+        let (status, schema) = json_reply(state.handle(&Method::Get, "/schema", b""));
+        assert_eq!(status, 200);
+        assert!(schema["$defs"]["TraceWitness"].is_object());
+
+        let id = compile(&state)["circuit_id"].as_str().unwrap().to_string();
+        let url = format!("/circuits/{}/prove", id);
+        let witness = br#"{ "step_instances": [] }"#;
+        assert_eq!(state.handle(&Method::Post, &url, witness).status, 501);
+        let url = format!("{}?evm=yes", url);
+        assert_eq!(state.handle(&Method::Post, &url, witness).status, 400);
+    }
+
+    #[test]
+    fn test_canonical_json() {
+        let a: Value =
+            serde_json::from_str(r#"{ "b": [1, { "d": 2, "c": 3 }], "a": "x" }"#).unwrap();
+        let b: Value =
+            serde_json::from_str(r#"{ "a": "x", "b": [1, { "c": 3, "d": 2 }] }"#).unwrap();
+        assert_eq!(canonical_json(&a), r#"{"a":"x","b":[1,{"c":3,"d":2}]}"#);
+        assert_eq!(canonical_json(&a), canonical_json(&b));
+        assert_ne!(circuit_id("{}", &a), circuit_id("{}", &json!({})));
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(to_hex(&[0, 15, 255]), "000fff");
+        assert_eq!(from_hex("0x000fff").unwrap(), vec![0, 15, 255]);
+        assert!(from_hex("0f0").is_err());
+        assert!(from_hex("zz").is_err());
+    }
+}
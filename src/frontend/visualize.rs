@@ -0,0 +1,715 @@
+//! Visualizations of circuits for notebooks: the transitions between step types, the layout of the
+//! signals in the columns, and the constraints with their degrees. Each is serialized to JSON, and
+//! rendered to a self-contained HTML fragment, without scripts, that Jupyter displays inline.
+
+use std::{
+    collections::HashMap,
+    f64::consts::PI,
+    fmt::{Debug, Write},
+};
+
+use serde::Serialize;
+
+use crate::{
+    field::Field,
+    plonkish::{
+        compiler::cell_manager::{Placement, SignalPlacement},
+        ir::{Circuit, ColumnType},
+    },
+    sbpir::{query::Queriable, SourceLocation, StepType, StepTypeUUID, PIR, SBPIR},
+    util::UUID,
+};
+
+/// Step types of a circuit, and the step types that can follow each one.
+#[derive(Clone, Debug, Serialize)]
+pub struct StepDiagram {
+    pub steps: Vec<StepNode>,
+    pub transitions: Vec<StepTransition>,
+    pub num_steps: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct StepNode {
+    pub id: String,
+    pub name: String,
+    pub first: bool,
+    pub last: bool,
+    pub padding: bool,
+    pub signals: usize,
+    pub constraints: usize,
+    pub transitions: usize,
+    pub lookups: usize,
+}
+
+/// Step type `to` can be the next step of a step type `from`.
+#[derive(Clone, Debug, Serialize)]
+pub struct StepTransition {
+    pub from: String,
+    pub to: String,
+}
+
+/// Cells of the signals in the columns they are placed in: a row for each step type, with the
+/// internal signals it places in each column, and a row for the forward, shared and fixed signals.
+#[derive(Clone, Debug, Serialize)]
+pub struct ColumnLayout {
+    pub columns: Vec<LayoutColumn>,
+    pub rows: Vec<LayoutRow>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LayoutColumn {
+    pub id: String,
+    pub annotation: String,
+    pub ctype: &'static str,
+    pub phase: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LayoutRow {
+    pub name: String,
+    /// `step`, `forward`, `shared` or `fixed`.
+    pub kind: &'static str,
+    /// Rows of the table the cells of the row are placed in.
+    pub height: u32,
+    /// Number of cells in each column, in the order of `ColumnLayout::columns`.
+    pub cells: Vec<usize>,
+}
+
+/// Constraints, transition constraints and lookups of the step types, with their degrees.
+#[derive(Clone, Debug, Serialize)]
+pub struct ConstraintList {
+    pub constraints: Vec<ConstraintEntry>,
+    pub max_degree: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ConstraintEntry {
+    pub step: String,
+    pub step_id: String,
+    /// `constraint`, `transition` or `lookup`.
+    pub kind: &'static str,
+    pub annotation: String,
+    pub expr: String,
+    /// Degree of the expression, or of the largest source expression of a lookup, before the
+    /// compiler multiplies it by the step selector.
+    pub degree: usize,
+    pub location: Option<String>,
+    pub feature: Option<String>,
+}
+
+/// Returns the step types of `circuit` and their transitions. A step type can follow another one
+/// unless a transition constraint of the latter that only queries the next step type fails for
+/// it. Transition constraints that query signals do not restrict the diagram.
+pub fn step_diagram<F: Field, TraceArgs>(circuit: &SBPIR<F, TraceArgs>) -> StepDiagram {
+    let step_types = sorted_step_types(circuit);
+
+    let steps = step_types
+        .iter()
+        .map(|step_type| StepNode {
+            id: step_type.uuid().to_string(),
+            name: step_type.name(),
+            first: circuit.first_step == Some(step_type.uuid()),
+            last: circuit.last_step == Some(step_type.uuid()),
+            padding: circuit.padding_step == Some(step_type.uuid()),
+            signals: step_type.signals.len(),
+            constraints: step_type.constraints.len(),
+            transitions: step_type.transition_constraints.len(),
+            lookups: step_type.lookups.len(),
+        })
+        .collect();
+
+    let mut transitions = Vec::new();
+    for from in step_types.iter() {
+        for to in step_types.iter() {
+            if can_follow(from, to.uuid()) {
+                transitions.push(StepTransition {
+                    from: from.uuid().to_string(),
+                    to: to.uuid().to_string(),
+                });
+            }
+        }
+    }
+
+    StepDiagram {
+        steps,
+        transitions,
+        num_steps: circuit.num_steps,
+    }
+}
+
+/// Returns if no transition constraint of `step_type` fails when the next step is of `next`.
+fn can_follow<F: Field>(step_type: &StepType<F>, next: StepTypeUUID) -> bool {
+    step_type.transition_constraints.iter().all(|constraint| {
+        let value = constraint.expr.eval_with(&|query| match query {
+            Queriable::StepTypeNext(handler) if handler.uuid() == next => Some(F::ONE),
+            Queriable::StepTypeNext(_) => Some(F::ZERO),
+            _ => None,
+        });
+
+        value.map_or(true, |value| value == F::ZERO)
+    })
+}
+
+/// Returns the layout of the signals of `circuit` in its columns, as placed by the cell manager.
+/// Only the columns with signals are in the layout, not those of the step selector.
+pub fn column_layout<F, TraceArgs>(
+    ast: &SBPIR<F, TraceArgs>,
+    circuit: &Circuit<F>,
+    placement: &Placement,
+) -> ColumnLayout {
+    let mut rows = Vec::new();
+    for step_type in sorted_step_types(ast) {
+        if !placement.steps.contains_key(&step_type.uuid()) {
+            continue;
+        }
+        let cells = placement
+            .internal_signals(step_type.uuid())
+            .into_iter()
+            .map(|signal| placement.find_internal_signal_placement(step_type.uuid(), &signal))
+            .collect::<Vec<_>>();
+        rows.push((
+            step_type.name(),
+            "step",
+            placement.step_height(step_type.uuid()),
+            cells,
+        ));
+    }
+    rows.push((
+        "forward signals".to_string(),
+        "forward",
+        placement.base_height,
+        placement.forward.values().cloned().collect(),
+    ));
+    rows.push((
+        "shared signals".to_string(),
+        "shared",
+        placement.base_height,
+        placement.shared.values().cloned().collect(),
+    ));
+    rows.push((
+        "fixed signals".to_string(),
+        "fixed",
+        placement.base_height,
+        placement.fixed.values().cloned().collect(),
+    ));
+    rows.retain(|(_, kind, _, cells)| *kind == "step" || !cells.is_empty());
+
+    let placed: Vec<UUID> = rows
+        .iter()
+        .flat_map(|(_, _, _, cells)| cells.iter().map(|cell| cell.column.uuid()))
+        .collect();
+    let columns: Vec<_> = circuit
+        .columns
+        .iter()
+        .filter(|column| placed.contains(&column.uuid()))
+        .collect();
+
+    let rows = rows
+        .into_iter()
+        .map(|(name, kind, height, cells)| LayoutRow {
+            name,
+            kind,
+            height,
+            cells: count_cells(&cells, columns.iter().map(|column| column.uuid())),
+        })
+        .collect();
+    let columns = columns
+        .into_iter()
+        .map(|column| LayoutColumn {
+            id: column.uuid().to_string(),
+            annotation: column.annotation.clone(),
+            ctype: column_type_name(&column.ctype),
+            phase: column.phase,
+        })
+        .collect();
+
+    ColumnLayout { columns, rows }
+}
+
+fn count_cells(cells: &[SignalPlacement], columns: impl Iterator<Item = UUID>) -> Vec<usize> {
+    let mut counts: HashMap<UUID, usize> = HashMap::new();
+    for cell in cells {
+        *counts.entry(cell.column.uuid()).or_default() += 1;
+    }
+
+    columns
+        .map(|column| counts.get(&column).copied().unwrap_or(0))
+        .collect()
+}
+
+fn column_type_name(ctype: &ColumnType) -> &'static str {
+    match ctype {
+        ColumnType::Advice => "advice",
+        ColumnType::Fixed => "fixed",
+        #[cfg(feature = "halo2")]
+        ColumnType::Halo2Advice => "halo2_advice",
+        #[cfg(feature = "halo2")]
+        ColumnType::Halo2Fixed => "halo2_fixed",
+    }
+}
+
+/// Returns the constraints, transition constraints and lookups of the step types of `circuit`.
+pub fn constraint_list<F: Debug, TraceArgs>(circuit: &SBPIR<F, TraceArgs>) -> ConstraintList {
+    let mut constraints = Vec::new();
+    for step_type in sorted_step_types(circuit) {
+        let entry = |kind, annotation: &String, exprs: &[&PIR<F>], location: Option<_>, feature| {
+            ConstraintEntry {
+                step: step_type.name(),
+                step_id: step_type.uuid().to_string(),
+                kind,
+                annotation: annotation.clone(),
+                expr: exprs
+                    .iter()
+                    .map(|expr| format!("{:?}", expr))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                degree: exprs.iter().map(|expr| expr.degree()).max().unwrap_or(0),
+                location: location.map(|location: SourceLocation| location.to_string()),
+                feature,
+            }
+        };
+
+        for constr in step_type.constraints.iter() {
+            constraints.push(entry(
+                "constraint",
+                &constr.annotation,
+                &[&constr.expr],
+                constr.location,
+                constr.feature.clone(),
+            ));
+        }
+        for constr in step_type.transition_constraints.iter() {
+            constraints.push(entry(
+                "transition",
+                &constr.annotation,
+                &[&constr.expr],
+                constr.location,
+                constr.feature.clone(),
+            ));
+        }
+        for lookup in step_type.lookups.iter() {
+            let exprs: Vec<_> = lookup.exprs.iter().map(|(src, _)| &src.expr).collect();
+            constraints.push(entry(
+                "lookup",
+                &lookup.annotation,
+                &exprs,
+                None,
+                lookup.feature.clone(),
+            ));
+        }
+    }
+    let max_degree = constraints
+        .iter()
+        .map(|constraint| constraint.degree)
+        .max()
+        .unwrap_or(0);
+
+    ConstraintList {
+        constraints,
+        max_degree,
+    }
+}
+
+/// Step types of `circuit` in order of name, and of UUID for those of the same name.
+fn sorted_step_types<F, TraceArgs>(circuit: &SBPIR<F, TraceArgs>) -> Vec<&StepType<F>> {
+    let mut step_types: Vec<_> = circuit.step_types.values().map(|rc| rc.as_ref()).collect();
+    step_types.sort_by(|a, b| (&a.name, a.uuid()).cmp(&(&b.name, b.uuid())));
+
+    step_types
+}
+
+const STYLE: &str = "font-family: sans-serif; font-size: 12px;";
+const NODE_RADIUS: f64 = 18.0;
+
+impl StepDiagram {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("step diagram serializes to JSON")
+    }
+
+    /// Renders the diagram to an SVG, with the step types on a circle. The first step type is
+    /// green, the last one red and the padding one grey.
+    pub fn to_html(&self) -> String {
+        let n = self.steps.len().max(1) as f64;
+        let radius = (30.0 * n).max(80.0);
+        let center = radius + 90.0;
+        let size = 2.0 * center;
+        let position = |index: usize| {
+            let angle = 2.0 * PI * index as f64 / n - PI / 2.0;
+            (center + radius * angle.cos(), center + radius * angle.sin())
+        };
+        let index: HashMap<&str, usize> = self
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| (step.id.as_str(), index))
+            .collect();
+
+        let mut svg = String::new();
+        write!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" \
+             style=\"{}\"><defs><marker id=\"chiquito-arrow\" viewBox=\"0 0 10 10\" \
+             refX=\"10\" refY=\"5\" markerWidth=\"6\" markerHeight=\"6\" orient=\"auto\">\
+             <path d=\"M 0 0 L 10 5 L 0 10 z\" fill=\"#555\"/></marker></defs>",
+            size, size, STYLE
+        )
+        .unwrap();
+
+        for transition in self.transitions.iter() {
+            let (from, to) = (
+                index[transition.from.as_str()],
+                index[transition.to.as_str()],
+            );
+            let (x1, y1) = position(from);
+            if from == to {
+                // loop outside of the circle of the step types
+                let (dx, dy) = ((x1 - center) / radius, (y1 - center) / radius);
+                let (cx, cy) = (x1 + dx * NODE_RADIUS * 1.6, y1 + dy * NODE_RADIUS * 1.6);
+                write!(
+                    svg,
+                    "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" fill=\"none\" \
+                     stroke=\"#555\"/>",
+                    cx,
+                    cy,
+                    NODE_RADIUS * 0.8
+                )
+                .unwrap();
+                continue;
+            }
+            let (x2, y2) = position(to);
+            let (dx, dy) = (x2 - x1, y2 - y1);
+            let length = (dx * dx + dy * dy).sqrt();
+            let (ux, uy) = (dx / length, dy / length);
+            // curved to the right, so that the transitions in both directions do not overlap
+            let (mx, my) = ((x1 + x2) / 2.0 - uy * 20.0, (y1 + y2) / 2.0 + ux * 20.0);
+            let (sx, sy) = (x1 + ux * NODE_RADIUS, y1 + uy * NODE_RADIUS);
+            let (ex, ey) = (x2 - ux * NODE_RADIUS, y2 - uy * NODE_RADIUS);
+            write!(
+                svg,
+                "<path d=\"M {:.1} {:.1} Q {:.1} {:.1} {:.1} {:.1}\" fill=\"none\" \
+                 stroke=\"#555\" marker-end=\"url(#chiquito-arrow)\"/>",
+                sx, sy, mx, my, ex, ey
+            )
+            .unwrap();
+        }
+
+        for (index, step) in self.steps.iter().enumerate() {
+            let (x, y) = position(index);
+            let fill = if step.first {
+                "#b7e4c7"
+            } else if step.last {
+                "#f4b6b6"
+            } else if step.padding {
+                "#dddddd"
+            } else {
+                "#cfe2f3"
+            };
+            let (dx, dy) = ((x - center) / radius, (y - center) / radius);
+            let (lx, ly) = (x + dx * NODE_RADIUS * 3.2, y + dy * NODE_RADIUS * 3.2 + 4.0);
+            write!(
+                svg,
+                "<g><title>{} signals, {} constraints, {} transitions, {} lookups</title>\
+                 <circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{}\" fill=\"{}\" stroke=\"#333\"/>\
+                 <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>\
+                 <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text></g>",
+                step.signals,
+                step.constraints,
+                step.transitions,
+                step.lookups,
+                x,
+                y,
+                NODE_RADIUS,
+                fill,
+                x,
+                y + 4.0,
+                index,
+                lx,
+                ly,
+                escape_html(&step.name)
+            )
+            .unwrap();
+        }
+        svg.push_str("</svg>");
+
+        svg
+    }
+}
+
+impl ColumnLayout {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("column layout serializes to JSON")
+    }
+
+    /// Renders the layout to a table, with the cells of each column shaded by how many rows of
+    /// their step they take.
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        write!(
+            html,
+            "<table style=\"{} border-collapse: collapse;\"><tr><th></th><th>height</th>",
+            STYLE
+        )
+        .unwrap();
+        for column in self.columns.iter() {
+            write!(
+                html,
+                "<th title=\"{} column, phase {}\" style=\"writing-mode: vertical-rl; \
+                 padding: 2px;\">{}</th>",
+                column.ctype,
+                column.phase,
+                escape_html(&column.annotation)
+            )
+            .unwrap();
+        }
+        html.push_str("</tr>");
+
+        for row in self.rows.iter() {
+            write!(
+                html,
+                "<tr><th style=\"text-align: left; padding: 2px 6px;\">{}</th>\
+                 <td style=\"text-align: right; padding: 2px 6px;\">{}</td>",
+                escape_html(&row.name),
+                row.height
+            )
+            .unwrap();
+            for cells in row.cells.iter() {
+                let usage = if row.height == 0 {
+                    0.0
+                } else {
+                    (*cells as f64 / row.height as f64).min(1.0)
+                };
+                let lightness = 100.0 - 55.0 * usage;
+                write!(
+                    html,
+                    "<td style=\"background: hsl(210, 70%, {:.0}%); text-align: center; \
+                     min-width: 20px; border: 1px solid #eee;\">{}</td>",
+                    lightness,
+                    if *cells == 0 {
+                        String::new()
+                    } else {
+                        cells.to_string()
+                    }
+                )
+                .unwrap();
+            }
+            html.push_str("</tr>");
+        }
+        html.push_str("</table>");
+
+        html
+    }
+}
+
+impl ConstraintList {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("constraint list serializes to JSON")
+    }
+
+    /// Renders the list to a table, with the constraints of the maximum degree in bold.
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        write!(
+            html,
+            "<table style=\"{} text-align: left;\"><tr><th>step</th><th>kind</th>\
+             <th>annotation</th><th>expression</th><th>degree</th></tr>",
+            STYLE
+        )
+        .unwrap();
+        for constraint in self.constraints.iter() {
+            let weight = if constraint.degree == self.max_degree {
+                "bold"
+            } else {
+                "normal"
+            };
+            write!(
+                html,
+                "<tr title=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td><code>{}</code></td>\
+                 <td style=\"font-weight: {}; text-align: right;\">{}</td></tr>",
+                escape_html(constraint.location.as_deref().unwrap_or("")),
+                escape_html(&constraint.step),
+                constraint.kind,
+                escape_html(&constraint.annotation),
+                escape_html(&constraint.expr),
+                weight,
+                constraint.degree
+            )
+            .unwrap();
+        }
+        html.push_str("</table>");
+
+        html
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        frontend::dsl::{
+            cb::{eq, next_step_must_be},
+            circuit,
+        },
+        plonkish::compiler::{
+            cell_manager::SingleRowCellManager, compile, config,
+            step_selector::SimpleStepSelectorBuilder,
+        },
+    };
+
+    fn two_steps_circuit() -> SBPIR<Fr, ()> {
+        circuit("two steps", |ctx| {
+            let a = ctx.forward("a");
+            let last = ctx.step_type("last");
+
+            let first = ctx.step_type_def("first", |ctx| {
+                let b = ctx.internal("b");
+                ctx.setup(move |ctx| {
+                    ctx.constr(eq(b * b, a));
+                    ctx.transition(eq(a, a.next()));
+                    ctx.transition(next_step_must_be(last));
+                });
+                ctx.wg(move |ctx, value: u64| {
+                    ctx.assign(a, Fr::from(value * value));
+                    ctx.assign(b, Fr::from(value));
+                })
+            });
+            let last = ctx.step_type_def(last, |ctx| {
+                let c = ctx.internal("c");
+                let d = ctx.internal("d");
+                ctx.setup(move |ctx| {
+                    ctx.constr(eq(c, a));
+                    ctx.constr(eq(d, c + 1));
+                });
+                ctx.wg(move |ctx, value: u64| {
+                    ctx.assign(a, Fr::from(value));
+                    ctx.assign(c, Fr::from(value));
+                    ctx.assign(d, Fr::from(value + 1));
+                })
+            });
+
+            ctx.pragma_first_step(&first);
+            ctx.pragma_last_step(&last);
+            ctx.pragma_num_steps(2);
+            ctx.trace(move |ctx, _| {
+                ctx.add(&first, 3);
+                ctx.add(&last, 9);
+            });
+        })
+    }
+
+    fn step_name(diagram: &StepDiagram, id: &str) -> String {
+        diagram
+            .steps
+            .iter()
+            .find(|step| step.id == id)
+            .map(|step| step.name.clone())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_step_diagram() {
+        let diagram = step_diagram(&two_steps_circuit());
+
+        let names: Vec<_> = diagram
+            .steps
+            .iter()
+            .map(|step| step.name.as_str())
+            .collect();
+        assert_eq!(names, ["first", "last"]);
+        assert!(diagram.steps[0].first && diagram.steps[1].last);
+        assert_eq!(diagram.steps[0].transitions, 2);
+
+        // the transition of a signal does not restrict the next step type of "first"
+        let transitions: Vec<_> = diagram
+            .transitions
+            .iter()
+            .map(|transition| {
+                (
+                    step_name(&diagram, &transition.from),
+                    step_name(&diagram, &transition.to),
+                )
+            })
+            .collect();
+        assert_eq!(
+            transitions,
+            [
+                ("first".to_string(), "last".to_string()),
+                ("last".to_string(), "first".to_string()),
+                ("last".to_string(), "last".to_string()),
+            ]
+        );
+
+        let html = diagram.to_html();
+        assert!(html.starts_with("<svg") && html.ends_with("</svg>"));
+        assert_eq!(html.matches("marker-end").count(), 2);
+        assert_eq!(diagram.to_json()["num_steps"], 2);
+    }
+
+    #[test]
+    fn test_column_layout() {
+        let ast = two_steps_circuit();
+        let (circuit, generator) = compile(
+            config(SingleRowCellManager {}, SimpleStepSelectorBuilder {}),
+            &ast,
+        );
+        let assigner = generator.unwrap().assigner();
+
+        let layout = column_layout(&ast, &circuit, assigner.placement());
+
+        // "a", then the columns of the internal signals
+        assert_eq!(layout.columns.len(), 3);
+        let rows: Vec<_> = layout
+            .rows
+            .iter()
+            .map(|row| (row.name.as_str(), row.kind, row.height, row.cells.clone()))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("first", "step", 1, vec![0, 1, 0]),
+                ("last", "step", 1, vec![0, 1, 1]),
+                ("forward signals", "forward", 1, vec![1, 0, 0]),
+            ]
+        );
+        assert!(layout.to_html().contains("forward signals"));
+    }
+
+    #[test]
+    fn test_constraint_list() {
+        let list = constraint_list(&two_steps_circuit());
+
+        let constraints: Vec<_> = list
+            .constraints
+            .iter()
+            .map(|constraint| (constraint.step.as_str(), constraint.kind, constraint.degree))
+            .collect();
+        assert_eq!(
+            constraints,
+            [
+                ("first", "constraint", 2),
+                ("first", "transition", 1),
+                ("first", "transition", 1),
+                ("last", "constraint", 1),
+                ("last", "constraint", 1),
+            ]
+        );
+        assert_eq!(list.max_degree, 2);
+        assert!(list.constraints[0].location.is_some());
+
+        let html = list.to_html();
+        assert_eq!(html.matches("font-weight: bold").count(), 1);
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(escape_html("<a & \"b\">"), "&lt;a &amp; &quot;b&quot;&gt;");
+    }
+}
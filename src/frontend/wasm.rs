@@ -0,0 +1,176 @@
+//! WASM bindings of the compiler, the witness checker and the mock prover, with the same entry
+//! points as the Python bridge, for browser circuit playgrounds and JS tooling without a server.
+//! Circuits and witnesses are exchanged in the JSON of the Python frontend, or witnesses in the
+//! binary format of `wit_gen::binary` as a `Uint8Array`. Proving is left to the native bindings, as
+//! the KZG params do not fit a browser.
+//!
+//! Build with `wasm-pack build -- --no-default-features --features wasm`. Panics of the compiler
+//! abort the module instead of failing, as `wasm32-unknown-unknown` does not unwind.
+
+use js_sys::Uint8Array;
+use serde_json::{json, Value};
+use wasm_bindgen::{prelude::*, JsCast};
+
+use crate::{
+    frontend::{
+        interchange::{
+            self, check_witness, circuit_stats_to_json, from_json, mock_prover, plonkish_to_json,
+            sbpir_to_json, violation_to_json, with_field, CompiledCircuit, CompilerOptions,
+            FieldOption, InterchangeField, StoredCircuit,
+        },
+        schema,
+    },
+    plonkish::backend::halo2::ChiquitoHalo2Circuit,
+    sbpir::SBPIR,
+    wit_gen::TraceWitness,
+};
+
+/// Circuit compiled from the JSON of its AST.
+#[wasm_bindgen]
+pub struct Circuit {
+    circuit: StoredCircuit,
+}
+
+#[wasm_bindgen]
+impl Circuit {
+    /// Parses the JSON of a circuit and compiles it with the JSON of `CompilerOptions`, or with
+    /// the defaults without it.
+    #[wasm_bindgen(constructor)]
+    pub fn new(ast_json: &str, options_json: Option<String>) -> Result<Circuit, JsError> {
+        let options = CompilerOptions::from_json(options_json.as_deref())?;
+
+        Ok(Circuit {
+            circuit: StoredCircuit::compile(ast_json, &options)?,
+        })
+    }
+
+    /// Name of the field of the circuit, as in the JSON of the options.
+    #[wasm_bindgen(getter)]
+    pub fn field(&self) -> String {
+        self.circuit.field().name().to_string()
+    }
+
+    /// Returns the AST of the circuit as Rust parsed it, serialized back to JSON.
+    #[wasm_bindgen(js_name = astJson)]
+    pub fn ast_json(&self) -> Result<String, JsError> {
+        with_field!(self.circuit.field(), F => {
+            to_json_string(sbpir_to_json(&self.circuit.ast::<F>()?))
+        })
+    }
+
+    /// Returns the plonkish IR the circuit was compiled to, as JSON, with the placement of its
+    /// signals in the columns.
+    #[wasm_bindgen(js_name = plonkishJson)]
+    pub fn plonkish_json(&self) -> Result<String, JsError> {
+        with_field!(self.circuit.field(), F => {
+            let compiled = self.compiled::<F>();
+            let placement = compiled.assigner.as_ref().map(|assigner| assigner.placement());
+            to_json_string(plonkish_to_json(compiled.halo2.circuit(), placement))
+        })
+    }
+
+    /// Returns the size of the circuit in the halo2 table, as JSON.
+    pub fn stats(&self) -> String {
+        with_field!(self.circuit.field(), F => {
+            circuit_stats_to_json(&self.compiled::<F>().halo2.stats()).to_string()
+        })
+    }
+
+    /// Returns the smallest k of a halo2 table that fits the circuit.
+    #[wasm_bindgen(js_name = estimateK)]
+    pub fn estimate_k(&self) -> u32 {
+        with_field!(self.circuit.field(), F => self.compiled::<F>().halo2.stats().min_k)
+    }
+
+    /// Checks a witness against the AST of the circuit with the native `Checker`, without running
+    /// `MockProver`. Returns the JSON array of the violations in step order, with the keys of the
+    /// Python frontend, empty if the witness satisfies the circuit.
+    #[wasm_bindgen(js_name = checkWitness)]
+    pub fn check_witness(&self, witness: &JsValue) -> Result<String, JsError> {
+        with_field!(self.circuit.field(), F => {
+            let ast = self.circuit.ast::<F>()?;
+            let violations = check_witness(&ast, &parse_witness::<F>(witness)?)?;
+            let violations: Vec<_> = violations.iter().map(violation_to_json).collect();
+
+            Ok(Value::from(violations).to_string())
+        })
+    }
+
+    /// Runs `MockProver` for a witness, with the k of `estimateK` if `k` is not given. Returns the
+    /// JSON of the result, `{ "ok": bool, "failures": [string] }`, with the failures of
+    /// `MockProver::verify` as displayed by halo2. Fails if the witness cannot be assigned, or for
+    /// a `k` too small.
+    #[wasm_bindgen(js_name = mockProve)]
+    pub fn mock_prove(&self, witness: &JsValue, k: Option<u32>) -> Result<String, JsError> {
+        with_field!(self.circuit.field(), F => {
+            let compiled = self.compiled::<F>();
+            let assignments = compiled.assignments(parse_witness::<F>(witness)?);
+            let circuit = ChiquitoHalo2Circuit::new(compiled.halo2.clone(), assignments);
+
+            let min_k = compiled.halo2.stats().min_k;
+            let prover = mock_prover(k.map(|k| k as usize), min_k, &circuit, circuit.instance())?;
+            let failures: Vec<_> = match prover.verify() {
+                Ok(()) => Vec::new(),
+                Err(failures) => failures.iter().map(ToString::to_string).collect(),
+            };
+
+            Ok(json!({ "ok": failures.is_empty(), "failures": failures }).to_string())
+        })
+    }
+}
+
+impl Circuit {
+    /// Returns the compiled circuit, of the field `F` of the circuit.
+    fn compiled<F: InterchangeField>(&self) -> &CompiledCircuit<F> {
+        self.circuit
+            .compiled()
+            .expect("circuit compiled to its field")
+    }
+}
+
+/// Parses the JSON of a circuit in `field`, `bn256` if it is not given, and returns it serialized
+/// back to JSON, as Rust parsed it, without compiling it.
+#[wasm_bindgen(js_name = parseAst)]
+pub fn parse_ast(ast_json: &str, field: Option<String>) -> Result<String, JsError> {
+    let field = match field {
+        Some(field) => FieldOption::from_name(&field)?,
+        None => FieldOption::default(),
+    };
+
+    with_field!(field, F => {
+        let ast: SBPIR<F, ()> = from_json(ast_json, "Circuit")?;
+        to_json_string(sbpir_to_json(&ast))
+    })
+}
+
+/// Returns the JSON Schema of the JSON of circuits and witnesses, to validate them before
+/// compiling them.
+#[wasm_bindgen(js_name = jsonSchema)]
+pub fn json_schema() -> String {
+    schema::json_schema().to_string()
+}
+
+/// Returns the TypeScript declarations of the JSON of circuits and witnesses.
+#[wasm_bindgen(js_name = typescriptTypes)]
+pub fn typescript_types() -> String {
+    schema::typescript()
+}
+
+/// Parses a witness from JS: the JSON of a `TraceWitness` as a string, or its binary format as a
+/// `Uint8Array`.
+fn parse_witness<F: InterchangeField>(witness: &JsValue) -> Result<TraceWitness<F>, JsError> {
+    if let Some(json) = witness.as_string() {
+        Ok(from_json(&json, "TraceWitness")?)
+    } else if let Some(bytes) = witness.dyn_ref::<Uint8Array>() {
+        Ok(interchange::witness_from_binary(&bytes.to_vec())?)
+    } else {
+        Err(JsError::new(
+            "witness is neither the JSON of a TraceWitness nor a Uint8Array",
+        ))
+    }
+}
+
+fn to_json_string(json: Result<Value, String>) -> Result<String, JsError> {
+    json.and_then(|json| serde_json::to_string_pretty(&json).map_err(|e| e.to_string()))
+        .map_err(|message| JsError::new(&message))
+}
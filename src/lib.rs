@@ -1,5 +1,8 @@
+extern crate self as chiquito;
+
 pub mod field;
 pub mod frontend;
+#[cfg(feature = "pil")]
 pub mod pil;
 pub mod plonkish;
 pub mod poly;
@@ -4,13 +4,16 @@ use crate::{
         compiler::{compile, compile_super_circuits, PILColumn, PILExpr, PILQuery},
         ir::powdr_pil::PILCircuit,
     },
-    sbpir::SBPIR,
+    sbpir::{query::Queriable, SBPIR},
     util::UUID,
     wit_gen::TraceWitness,
 };
+use ff::PrimeField;
 use std::{
     collections::HashMap,
     fmt::{Debug, Write},
+    hash::Hash,
+    io,
 };
 extern crate regex;
 
@@ -161,11 +164,7 @@ fn generate_pil_fixed_columns<F: Debug>(pil: &mut String, pil_ir: &PILCircuit<F>
             "// === Fixed Columns for Signals and Step Type Selectors ==="
         )
         .unwrap();
-        for (col, assignments) in pil_ir.col_fixed.iter() {
-            let fixed_name = match col {
-                PILColumn::Fixed(_, annotation) => annotation.clone(),
-                _ => panic!("Fixed column should be an advice or fixed column."),
-            };
+        for (fixed_name, assignments) in fixed_columns(pil_ir) {
             let mut assignments_string = String::new();
             let assignments_vec = assignments
                 .iter()
@@ -182,6 +181,128 @@ fn generate_pil_fixed_columns<F: Debug>(pil: &mut String, pil_ir: &PILCircuit<F>
     }
 }
 
+/// Returns the fixed columns of a PIL circuit by name, in the order they are declared.
+fn fixed_columns<F>(pil_ir: &PILCircuit<F>) -> Vec<(String, &Vec<F>)> {
+    let mut columns: Vec<(String, &Vec<F>)> = pil_ir
+        .col_fixed
+        .iter()
+        .map(|(col, assignments)| match col {
+            PILColumn::Fixed(_, annotation) => (annotation.clone(), assignments),
+            _ => panic!("Fixed column should be an advice or fixed column."),
+        })
+        .collect();
+    columns.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    columns
+}
+
+/// Values of the columns of a PIL circuit, named `namespace.column` and in the order they are
+/// declared in its Powdr PIL code, to prove it with powdr.
+pub struct PowdrColumns<F> {
+    pub witness: Vec<(String, Vec<F>)>,
+    pub fixed: Vec<(String, Vec<F>)>,
+}
+
+impl<F: PrimeField> PowdrColumns<F> {
+    /// Writes the witness columns in powdr's polynomial file format, like its `commits.bin`.
+    pub fn write_witness<W: io::Write>(&self, writer: W) -> io::Result<()> {
+        write_powdr_polys(&self.witness, writer)
+    }
+
+    /// Writes the fixed columns in powdr's polynomial file format, like its `constants.bin`.
+    pub fn write_fixed<W: io::Write>(&self, writer: W) -> io::Result<()> {
+        write_powdr_polys(&self.fixed, writer)
+    }
+}
+
+/// Writes columns in powdr's polynomial file format: row by row, the value of each column as a
+/// little-endian field element, without names.
+fn write_powdr_polys<F: PrimeField, W: io::Write>(
+    columns: &[(String, Vec<F>)],
+    mut writer: W,
+) -> io::Result<()> {
+    let num_rows = columns.iter().map(|(_, values)| values.len()).max();
+    for row in 0..num_rows.unwrap_or(0) {
+        for (_, values) in columns {
+            let value = values.get(row).copied().unwrap_or(F::ZERO);
+            writer.write_all(value.to_repr().as_ref())?;
+        }
+    }
+
+    writer.flush()
+}
+
+#[allow(non_snake_case)]
+/// User generate the columns of the PIL code of `chiquito2Pil` with this function, to prove it
+/// with powdr. The witness needs to have `num_steps` step instances.
+pub fn chiquito2PowdrColumns<F: PrimeField + Field + Hash, TraceArgs>(
+    ast: &SBPIR<F, TraceArgs>,
+    witness: TraceWitness<F>,
+    circuit_name: String,
+) -> PowdrColumns<F> {
+    let pil_ir = compile::<F, TraceArgs>(ast, Some(witness.clone()), circuit_name, &None);
+
+    pil_ir_to_powdr_columns(&pil_ir, &witness)
+}
+
+/// Returns the values of the columns of a PIL circuit for the witness it was compiled with. There
+/// is a row per step instance, where each signal takes the value its step instance assigns, or
+/// the next value of forward signals assigned by the previous one, or zero.
+pub fn pil_ir_to_powdr_columns<F: Field + Hash>(
+    pil_ir: &PILCircuit<F>,
+    witness: &TraceWitness<F>,
+) -> PowdrColumns<F> {
+    let names: HashMap<UUID, String> = pil_ir
+        .col_witness
+        .iter()
+        .map(|col| (col.uuid(), col.annotation()))
+        .collect();
+    let mut witness_columns: HashMap<String, Vec<F>> = names
+        .values()
+        .map(|name| (name.clone(), vec![F::ZERO; pil_ir.num_steps]))
+        .collect();
+
+    for (row, step_instance) in witness.step_instances.iter().enumerate() {
+        for (signal, value) in step_instance.assignments.iter() {
+            let row = match signal {
+                Queriable::Internal(_) | Queriable::Forward(_, false) | Queriable::Shared(_, 0) => {
+                    row
+                }
+                // the step instance of the next row has precedence
+                Queriable::Forward(forward, true) => {
+                    let next = witness.step_instances.get(row + 1);
+                    let current = Queriable::Forward(*forward, false);
+                    if next.is_some_and(|next| next.assignments.contains_key(&current)) {
+                        continue;
+                    }
+                    row + 1
+                }
+                _ => continue,
+            };
+
+            let column = names
+                .get(&signal.uuid())
+                .and_then(|name| witness_columns.get_mut(name));
+            if let Some(value_at) = column.and_then(|values| values.get_mut(row)) {
+                *value_at = *value;
+            }
+        }
+    }
+
+    let named = |name: &str| format!("{}.{}", pil_ir.circuit_name, name);
+    let mut witness: Vec<(String, Vec<F>)> = witness_columns
+        .into_iter()
+        .map(|(name, values)| (named(&name), values))
+        .collect();
+    witness.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let fixed = fixed_columns(pil_ir)
+        .into_iter()
+        .map(|(name, values)| (named(&name), values.clone()))
+        .collect();
+
+    PowdrColumns { witness, fixed }
+}
+
 // Convert PIL expression to Powdr PIL string recursively.
 fn convert_to_pil_expr_string<F: Debug + Clone>(expr: PILExpr<F, PILQuery>) -> String {
     match expr {
@@ -15,6 +15,18 @@ pub fn compile<F: Clone + Debug + Field, TraceArgs>(
     circuit_name: String,
     super_circuit_annotations_map: &Option<&HashMap<UUID, String>>,
 ) -> PILCircuit<F> {
+    // Outside of a super circuit, signals are referred to by their names, which can differ from
+    // the ones they were created with when they are renamed.
+    let circuit_annotations_map: HashMap<UUID, String>;
+    let annotations_map = match super_circuit_annotations_map {
+        Some(map) => Some(*map),
+        None => {
+            circuit_annotations_map = collect_annotations(ast);
+            Some(&circuit_annotations_map)
+        }
+    };
+    let super_circuit_annotations_map = &annotations_map;
+
     let col_witness = collect_witness_columns(ast);
 
     // HashMap of fixed column to fixed assignments
@@ -221,7 +233,25 @@ pub fn compile_super_circuits<F: Clone + Debug + Field, TraceArgs>(
     pil_irs
 }
 
+/// Returns the cleaned names of the signals and step types of a circuit.
+fn collect_annotations<F, TraceArgs>(ast: &SBPIR<F, TraceArgs>) -> HashMap<UUID, String> {
+    let mut annotations_map = ast.annotations.clone();
+    for step_type in ast.step_types.values() {
+        annotations_map.extend(step_type.annotations.clone());
+    }
+
+    annotations_map
+        .into_iter()
+        .map(|(uuid, annotation)| (uuid, clean_annotation(annotation)))
+        .collect()
+}
+
 fn collect_witness_columns<F, TraceArgs>(ast: &SBPIR<F, TraceArgs>) -> Vec<PILColumn> {
+    // columns are declared with their current names, which are the ones in the annotations
+    let name = |uuid: UUID, annotation: String| {
+        clean_annotation(ast.annotation(uuid).cloned().unwrap_or(annotation))
+    };
+
     let mut col_witness = Vec::new();
 
     // Collect internal signals to witness columns.
@@ -233,7 +263,7 @@ fn collect_witness_columns<F, TraceArgs>(ast: &SBPIR<F, TraceArgs>) -> Vec<PILCo
                     .signals
                     .iter()
                     .map(|signal| {
-                        PILColumn::Advice(signal.uuid(), clean_annotation(signal.annotation()))
+                        PILColumn::Advice(signal.uuid(), name(signal.uuid(), signal.annotation()))
                     })
                     .collect::<Vec<PILColumn>>()
             })
@@ -247,7 +277,7 @@ fn collect_witness_columns<F, TraceArgs>(ast: &SBPIR<F, TraceArgs>) -> Vec<PILCo
             .map(|forward_signal| {
                 PILColumn::Advice(
                     forward_signal.uuid(),
-                    clean_annotation(forward_signal.annotation()),
+                    name(forward_signal.uuid(), forward_signal.annotation()),
                 )
             })
             .collect::<Vec<PILColumn>>(),
@@ -260,7 +290,7 @@ fn collect_witness_columns<F, TraceArgs>(ast: &SBPIR<F, TraceArgs>) -> Vec<PILCo
             .map(|shared_signal| {
                 PILColumn::Advice(
                     shared_signal.uuid(),
-                    clean_annotation(shared_signal.annotation()),
+                    name(shared_signal.uuid(), shared_signal.annotation()),
                 )
             })
             .collect::<Vec<PILColumn>>(),
@@ -281,6 +311,14 @@ fn compile_steps<F: Clone + Field, TraceArgs>(
 
     if !ast.step_types.is_empty() {
         ast.step_types.values().for_each(|step_type| {
+            if let Some(call) = step_type.gadget_calls.first() {
+                panic!(
+                    "Halo2 gadget {} called by step {} not supported by PIL backend.",
+                    call.gadget,
+                    step_type.name()
+                );
+            }
+
             // Create constraint statements.
             constraints.extend(
                 step_type
@@ -442,9 +480,13 @@ fn chiquito_expr_to_pil_expr<F: Clone>(
             queriable,
             super_circuit_annotations_map,
         )),
+        #[cfg(feature = "halo2")]
         Expr::Halo2Expr(_) => {
             panic!("Halo2 native expression not supported by PIL backend.")
         }
+        Expr::Challenge(..) => {
+            panic!("Challenges not supported by PIL backend.")
+        }
         Expr::MI(_) => {
             panic!("MI not supported by PIL backend.")
         }
@@ -567,9 +609,11 @@ fn chiquito_queriable_to_pil_query<F>(
             PILColumn::Fixed(s.uuid(), clean_annotation(s.annotation())),
             true,
         ),
+        #[cfg(feature = "halo2")]
         Queriable::Halo2AdviceQuery(_, _) => {
             panic!("Halo2 native advice query not supported by PIL backend.")
         }
+        #[cfg(feature = "halo2")]
         Queriable::Halo2FixedQuery(_, _) => {
             panic!("Halo2 native fixed query not supported by PIL backend.")
         }
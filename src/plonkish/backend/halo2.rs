@@ -1,41 +1,36 @@
-use std::{collections::HashMap, hash::Hash};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fmt,
+    hash::Hash,
+    sync::Arc,
+};
 
 use halo2_proofs::{
     arithmetic::Field,
-    circuit::{Cell, Layouter, Region, RegionIndex, SimpleFloorPlanner, Value},
+    circuit::{AssignedCell, Cell, Layouter, Region, RegionIndex, SimpleFloorPlanner, Value},
     plonk::{
-        Advice, Any, Circuit as h2Circuit, Column, ConstraintSystem, Error, Expression, FirstPhase,
-        Fixed, Instance, SecondPhase, ThirdPhase, VirtualCells,
+        Advice, Any, Challenge, Circuit as h2Circuit, Column, ConstraintSystem, Error, Expression,
+        FirstPhase, Fixed, Instance, SecondPhase, ThirdPhase, VirtualCells,
     },
     poly::Rotation,
 };
 
 use crate::{
-    field::Field as ChiquitoField,
     plonkish::ir::{
-        assignments::Assignments,
+        assignments::{Assignments, ChunkError, WitnessAssigner},
         sc::{SuperAssignments, SuperCircuit},
         Circuit, Column as cColumn,
         ColumnType::{Advice as cAdvice, Fixed as cFixed, Halo2Advice, Halo2Fixed},
-        PolyExpr,
+        GadgetCall, PolyExpr,
+    },
+    poly::{
+        lowering::{op_count, optimize_evaluation, OpCount},
+        ToField,
     },
-    poly::ToField,
     util::UUID,
+    wit_gen::StepInstance,
 };
 
-impl<T: Field + From<u64>> ChiquitoField for T {
-    const ZERO: Self = <Self as Field>::ZERO;
-    const ONE: Self = <Self as Field>::ONE;
-
-    fn mi(&self) -> Self {
-        self.invert().unwrap_or(Self::ZERO)
-    }
-
-    fn pow<S: AsRef<[u64]>>(&self, exp: S) -> Self {
-        Field::pow(self, exp)
-    }
-}
-
 #[allow(non_snake_case)]
 pub fn chiquito2Halo2<F: Field + From<u64> + Hash>(circuit: Circuit<F>) -> ChiquitoHalo2<F> {
     ChiquitoHalo2::new(circuit)
@@ -52,15 +47,129 @@ pub fn chiquitoSuperCircuit2Halo2<F: Field + From<u64> + Hash, MappingArgs>(
         .collect()
 }
 
+/// Estimated field operations of evaluating a poly, as compiled and with the
+/// evaluation-optimized lowering, see `poly::lowering`.
+#[derive(Clone, Debug)]
+pub struct GateCost {
+    pub annotation: String,
+    pub compiled: OpCount,
+    pub optimized: OpCount,
+}
+
+/// Size of a circuit in the halo2 table, see `ChiquitoHalo2::stats`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CircuitStats {
+    pub advice_columns: usize,
+    pub fixed_columns: usize,
+    pub rows: usize,
+    pub polys: usize,
+    pub lookups: usize,
+    /// Highest degree of the polys and of the expressions of the lookups.
+    pub max_degree: usize,
+    /// Most rotations that an advice column is queried with.
+    pub max_advice_queries: usize,
+    /// Smallest k of a table with the rows of the circuit and the blinding rows of its queries.
+    pub min_k: u32,
+}
+
+/// Size of a super circuit in the halo2 table, for each sub-circuit and in total, see
+/// `ChiquitoHalo2SuperCircuit::stats`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SuperCircuitStats {
+    /// Stats of each sub-circuit, by IR id, in order.
+    pub sub_circuits: Vec<(UUID, CircuitStats)>,
+    /// Columns, polys and lookups of all the sub-circuits, in the rows of the largest one or of
+    /// the largest stack of identical ones.
+    pub total: CircuitStats,
+    /// Columns that sub-circuits query in other sub-circuits, like shared tables, counted once per
+    /// sub-circuit querying them: the columns that sharing saves.
+    pub shared_columns: usize,
+}
+
+impl fmt::Display for SuperCircuitStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<12} {:>8} {:>8} {:>10} {:>8} {:>8} {:>8} {:>6}",
+            "sub-circuit", "advice", "fixed", "rows", "polys", "lookups", "degree", "k"
+        )?;
+        let rows = self
+            .sub_circuits
+            .iter()
+            .enumerate()
+            .map(|(index, (_, stats))| (index.to_string(), stats))
+            .chain(std::iter::once(("total".to_string(), &self.total)));
+        for (name, stats) in rows {
+            writeln!(
+                f,
+                "{:<12} {:>8} {:>8} {:>10} {:>8} {:>8} {:>8} {:>6}",
+                name,
+                stats.advice_columns,
+                stats.fixed_columns,
+                stats.rows,
+                stats.polys,
+                stats.lookups,
+                stats.max_degree,
+                stats.min_k
+            )?;
+        }
+        writeln!(f, "shared columns: {}", self.shared_columns)
+    }
+}
+
+/// Halo2 gadget that steps delegate the constraints between their signals to, see
+/// `StepTypeSetupContext::gadget`, to reuse the chips of the halo2 ecosystem instead of
+/// reimplementing them. A chip of halo2-lib is wrapped by configuring its config in `configure`,
+/// and by loading the inputs as witnesses and running it in `assign`. It is registered by name
+/// with `ChiquitoHalo2::register_gadget`.
+pub trait Halo2Gadget<F: Field>: fmt::Debug + Send + Sync {
+    /// Rows that a call of the gadget uses in its columns.
+    fn rows(&self) -> usize;
+
+    /// Configures the columns and gates of the gadget, and returns the chip that assigns its
+    /// calls. The columns of its input and output cells must have equality enabled.
+    fn configure(&self, meta: &mut ConstraintSystem<F>) -> Box<dyn Halo2GadgetChip<F>>;
+}
+
+/// Configured `Halo2Gadget`.
+pub trait Halo2GadgetChip<F: Field>: fmt::Debug + Send + Sync {
+    /// Assigns a call of the gadget with the values of its inputs, in its columns from the row
+    /// `offset` of `region`, and returns its input and output cells. The calls reserved beyond the
+    /// steps of the step type in a witness are made with zero inputs, so it must accept them.
+    fn assign(
+        &self,
+        region: &mut Region<F>,
+        offset: usize,
+        inputs: &[Value<F>],
+    ) -> Result<GadgetCells<F>, Error>;
+}
+
+/// Input and output cells of a call of a `Halo2Gadget`, in the order of the signals of the call.
+#[derive(Clone, Debug)]
+pub struct GadgetCells<F: Field> {
+    pub inputs: Vec<AssignedCell<F, F>>,
+    pub outputs: Vec<AssignedCell<F, F>>,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct ChiquitoHalo2<F: Field + From<u64>> {
     pub debug: bool,
+    /// Restructures the polys and lookups to lower the field operations the prover performs per
+    /// row, see `poly::lowering`.
+    pub optimize_evaluation: bool,
 
     circuit: Circuit<F>,
 
     advice_columns: HashMap<UUID, Column<Advice>>,
     fixed_columns: HashMap<UUID, Column<Fixed>>,
     instance_column: Option<Column<Instance>>,
+    challenges: HashMap<UUID, Challenge>,
+    /// Gadgets that the steps call, by name, and their configured chips.
+    gadgets: HashMap<String, Arc<dyn Halo2Gadget<F>>>,
+    gadget_chips: HashMap<String, Arc<dyn Halo2GadgetChip<F>>>,
+    /// First row of the circuit, after the rows of the structurally identical sub-circuits of a
+    /// super circuit stacked before it in the same columns.
+    row_offset: usize,
 
     ir_id: UUID,
 }
@@ -70,14 +179,25 @@ impl<F: Field + From<u64> + Hash> ChiquitoHalo2<F> {
         let ir_id = circuit.id;
         ChiquitoHalo2 {
             debug: true,
+            optimize_evaluation: false,
             circuit,
             advice_columns: Default::default(),
             fixed_columns: Default::default(),
             instance_column: Default::default(),
+            challenges: Default::default(),
+            gadgets: Default::default(),
+            gadget_chips: Default::default(),
+            row_offset: 0,
             ir_id,
         }
     }
 
+    /// Registers `gadget` with the name that steps call it with, before the circuit is
+    /// configured.
+    pub fn register_gadget<G: Halo2Gadget<F> + 'static>(&mut self, name: &str, gadget: G) {
+        self.gadgets.insert(name.to_string(), Arc::new(gadget));
+    }
+
     pub fn configure(&mut self, meta: &mut ConstraintSystem<F>) {
         self.configure_columns_sub_circuit(meta);
 
@@ -125,19 +245,38 @@ impl<F: Field + From<u64> + Hash> ChiquitoHalo2<F> {
 
         self.advice_columns = advice_columns;
         self.fixed_columns = fixed_columns;
+
+        let mut challenges = Vec::new();
+        for poly in self.circuit.polys.iter() {
+            collect_challenges(&poly.expr, &mut challenges);
+        }
+        for (src, dest) in self.circuit.lookups.iter().flat_map(|lookup| &lookup.exprs) {
+            collect_challenges(src, &mut challenges);
+            collect_challenges(dest, &mut challenges);
+        }
+        for (id, phase) in challenges {
+            self.challenges
+                .entry(id)
+                .or_insert_with(|| to_halo2_challenge(meta, phase));
+        }
     }
 
     pub fn configure_sub_circuit(&mut self, meta: &mut ConstraintSystem<F>) {
-        if !self.circuit.exposed.is_empty() {
-            self.instance_column = Some(meta.instance_column());
+        self.configure_instance(meta);
+
+        for ((from, _), (to, _)) in self.circuit.copies.iter() {
+            meta.enable_equality(self.convert_advice_column(from));
+            meta.enable_equality(self.convert_advice_column(to));
         }
 
+        self.configure_gadgets(meta);
+
         if !self.circuit.polys.is_empty() {
             meta.create_gate("main", |meta| {
                 let mut constraints: Vec<(&'static str, Expression<F>)> = Vec::new();
 
                 for poly in self.circuit.polys.iter() {
-                    let converted = self.convert_poly(meta, &poly.expr);
+                    let converted = self.convert_poly(meta, &self.lower(&poly.expr));
                     let annotation = Box::leak(
                         format!("{} => {:?}", poly.annotation, converted).into_boxed_str(),
                     );
@@ -153,7 +292,10 @@ impl<F: Field + From<u64> + Hash> ChiquitoHalo2<F> {
             meta.lookup_any(annotation, |meta| {
                 let mut exprs = Vec::new();
                 for (src, dest) in lookup.exprs.iter() {
-                    exprs.push((self.convert_poly(meta, src), self.convert_poly(meta, dest)))
+                    exprs.push((
+                        self.convert_poly(meta, &self.lower(src)),
+                        self.convert_poly(meta, &self.lower(dest)),
+                    ))
                 }
 
                 exprs
@@ -161,22 +303,232 @@ impl<F: Field + From<u64> + Hash> ChiquitoHalo2<F> {
         }
     }
 
+    fn configure_gadgets(&mut self, meta: &mut ConstraintSystem<F>) {
+        for call in self.circuit.gadget_calls.iter() {
+            if !self.gadget_chips.contains_key(&call.gadget) {
+                let gadget = self
+                    .gadgets
+                    .get(&call.gadget)
+                    .unwrap_or_else(|| panic!("gadget not registered {}", call.gadget));
+                let chip = Arc::from(gadget.configure(meta));
+                self.gadget_chips.insert(call.gadget.clone(), chip);
+            }
+
+            for column in call.input_columns.iter().chain(call.output_columns.iter()) {
+                meta.enable_equality(self.convert_advice_column(column));
+            }
+        }
+    }
+
+    fn configure_instance(&mut self, meta: &mut ConstraintSystem<F>) {
+        if !self.circuit.exposed.is_empty() {
+            self.instance_column = Some(meta.instance_column());
+        }
+    }
+
+    /// Returns the estimated field operations of evaluating each poly of the circuit.
+    pub fn gate_costs(&self) -> Vec<GateCost> {
+        self.circuit
+            .polys
+            .iter()
+            .map(|poly| GateCost {
+                annotation: poly.annotation.clone(),
+                compiled: op_count(&poly.expr),
+                optimized: op_count(&optimize_evaluation(poly.expr.clone())),
+            })
+            .collect()
+    }
+
+    /// Returns the size of the circuit in the halo2 table, with the rows of the calls of the
+    /// registered gadgets.
+    pub fn stats(&self) -> CircuitStats {
+        let mut stats = circuit_stats(&self.circuit, &advice_queries([&self.circuit]));
+
+        let mut gadget_rows: HashMap<&str, usize> = HashMap::new();
+        for call in self.circuit.gadget_calls.iter() {
+            if let Some(gadget) = self.gadgets.get(&call.gadget) {
+                *gadget_rows.entry(&call.gadget).or_default() += call.calls * gadget.rows();
+            }
+        }
+        // the call tables need a disabled row for the steps of other step types to look up
+        let call_rows = self.circuit.gadget_calls.iter().map(|call| call.calls + 1);
+        if let Some(rows) = gadget_rows
+            .into_values()
+            .chain(call_rows)
+            .max()
+            .filter(|rows| *rows > stats.rows)
+        {
+            stats.rows = rows;
+            stats.min_k = min_k(rows, stats.max_advice_queries);
+        }
+
+        stats
+    }
+
+    /// Returns the plonkish IR the circuit was compiled to.
+    pub fn circuit(&self) -> &Circuit<F> {
+        &self.circuit
+    }
+
+    fn lower(&self, expr: &PolyExpr<F>) -> PolyExpr<F> {
+        if self.optimize_evaluation {
+            optimize_evaluation(expr.clone())
+        } else {
+            expr.clone()
+        }
+    }
+
     pub fn synthesize(&self, layouter: &mut impl Layouter<F>, witness: Option<&Assignments<F>>) {
+        let _ =
+            layouter.assign_region(|| "circuit", |mut region| self.assign(&mut region, witness));
+
+        self.constrain(layouter);
+    }
+
+    /// Synthesizes the circuit as `synthesize`, with the advice assigned chunk by chunk as
+    /// `WitnessAssigner::generate_chunks` generates it from the step instances of `source`, so the
+    /// assignments of the whole trace are never held. `source` is called for every pass, as the
+    /// floor planner assigns the region more than once. Fails if the chunks cannot be generated.
+    pub fn synthesize_chunks<S>(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        assigner: &WitnessAssigner<F>,
+        source: impl Fn() -> S,
+        chunk_steps: usize,
+    ) -> Result<(), ChunkError>
+    where
+        S: Iterator<Item = StepInstance<F>>,
+    {
+        let mut chunk_error = None;
         let _ = layouter.assign_region(
             || "circuit",
             |mut region| {
                 self.annotate_circuit(&mut region);
-
                 self.assign_fixed(&mut region, &self.circuit.fixed_assignments)?;
 
-                if let Some(witness) = &witness {
-                    self.assign_advice(&mut region, witness)?;
+                let mut result = Ok(());
+                let generated = assigner.generate_chunks(source(), chunk_steps, |start, chunk| {
+                    if result.is_ok() {
+                        result = self.assign_advice(&mut region, start, &chunk);
+                    }
+                });
+                if let Err(error) = generated {
+                    chunk_error = Some(error);
+                    return Err(Error::Synthesis);
                 }
 
-                Ok(())
+                result
             },
         );
 
+        self.constrain(layouter);
+
+        chunk_error.map_or(Ok(()), Err)
+    }
+
+    fn assign(
+        &self,
+        region: &mut Region<F>,
+        witness: Option<&Assignments<F>>,
+    ) -> Result<(), Error> {
+        // a stacked sub-circuit keeps the column names of the one it is stacked on
+        if self.row_offset == 0 {
+            self.annotate_circuit(region);
+        }
+
+        self.assign_fixed(region, &self.circuit.fixed_assignments)?;
+
+        if let Some(witness) = witness {
+            self.assign_advice(region, 0, witness)?;
+        }
+
+        self.assign_gadget_calls(region, witness)?;
+
+        Ok(())
+    }
+
+    /// Calls the gadgets with the inputs of the steps of their step types, in their columns one
+    /// call after the other, and copies their input and output cells to the call tables, see
+    /// `GadgetCall`.
+    fn assign_gadget_calls(
+        &self,
+        region: &mut Region<F>,
+        witness: Option<&Assignments<F>>,
+    ) -> Result<(), Error> {
+        let mut offsets: HashMap<&str, usize> = HashMap::new();
+        for call in self.circuit.gadget_calls.iter() {
+            let chip = self
+                .gadget_chips
+                .get(&call.gadget)
+                .unwrap_or_else(|| panic!("gadget not configured {}", call.gadget));
+
+            let mut step_inputs = witness
+                .map(|witness| {
+                    call.rows
+                        .iter()
+                        .filter_map(|row| gadget_inputs(call, witness, *row))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            if step_inputs.len() > call.calls {
+                panic!(
+                    "{} is called by {} steps, over its {} calls",
+                    call.annotation,
+                    step_inputs.len(),
+                    call.calls
+                );
+            }
+            step_inputs.resize(call.calls, vec![F::ZERO; call.inputs.len()]);
+
+            for (row, inputs) in step_inputs.into_iter().enumerate() {
+                let inputs: Vec<Value<F>> = match witness {
+                    Some(_) => inputs.into_iter().map(Value::known).collect(),
+                    None => vec![Value::unknown(); call.inputs.len()],
+                };
+
+                let offset = offsets.entry(&call.gadget).or_default();
+                let cells = chip.assign(region, *offset, &inputs)?;
+                *offset += self.gadgets[&call.gadget].rows();
+
+                if cells.inputs.len() != call.input_columns.len()
+                    || cells.outputs.len() != call.output_columns.len()
+                {
+                    panic!(
+                        "gadget {} returned {} inputs and {} outputs, called with {} and {}",
+                        call.gadget,
+                        cells.inputs.len(),
+                        cells.outputs.len(),
+                        call.input_columns.len(),
+                        call.output_columns.len()
+                    );
+                }
+
+                let values = inputs
+                    .into_iter()
+                    .chain(cells.outputs.iter().map(|cell| cell.value().copied()));
+                let copies = cells
+                    .inputs
+                    .iter()
+                    .chain(cells.outputs.iter())
+                    .zip(call.input_columns.iter().chain(call.output_columns.iter()))
+                    .zip(values);
+                for ((cell, column), value) in copies {
+                    let copy = region.assign_advice(
+                        || column.annotation.clone(),
+                        self.convert_advice_column(column),
+                        self.row_offset + row,
+                        || value,
+                    )?;
+                    region.constrain_equal(cell.cell(), copy.cell())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Constrains the exposed cells to the instance column and the copied cells to be equal.
+    fn constrain(&self, layouter: &mut impl Layouter<F>) {
         for (index, (column, rotation)) in self.circuit.exposed.iter().enumerate() {
             let halo2_column =
                 Column::<Any>::from(*self.advice_columns.get(&column.uuid()).unwrap());
@@ -185,17 +537,40 @@ impl<F: Field + From<u64> + Hash> ChiquitoHalo2<F> {
                 // For single row cell manager, forward signal rotation is always zero.
                 // For max width cell manager, rotation can be non-zero.
                 // Offset is absolute row index calculated in `compile_exposed`.
-                *rotation as usize,
+                self.row_offset + *rotation as usize,
             );
             let _ = layouter.constrain_instance(cell, self.instance_column.unwrap(), index);
         }
+
+        if !self.circuit.copies.is_empty() {
+            let _ = layouter.assign_region(
+                || "copies",
+                |mut region| {
+                    for ((from, from_row), (to, to_row)) in self.circuit.copies.iter() {
+                        region.constrain_equal(
+                            new_cell(self.convert_advice_column(from).into(), *from_row as usize),
+                            new_cell(self.convert_advice_column(to).into(), *to_row as usize),
+                        )?;
+                    }
+
+                    Ok(())
+                },
+            );
+        }
     }
 
-    fn assign_advice(&self, region: &mut Region<F>, witness: &Assignments<F>) -> Result<(), Error> {
+    /// Assigns the advice of `witness` from the row `start` of the circuit.
+    fn assign_advice(
+        &self,
+        region: &mut Region<F>,
+        start: usize,
+        witness: &Assignments<F>,
+    ) -> Result<(), Error> {
         for (column, assignments) in witness.iter() {
             let column = self.convert_advice_column(column);
 
             for (offset, value) in assignments.iter().enumerate() {
+                let offset = self.row_offset + start + offset;
                 region.assign_advice(|| "", column, offset, || Value::known(*value))?;
             }
         }
@@ -208,6 +583,7 @@ impl<F: Field + From<u64> + Hash> ChiquitoHalo2<F> {
             let column = self.convert_fixed_column(column);
 
             for (offset, value) in values.iter().enumerate() {
+                let offset = self.row_offset + offset;
                 region.assign_fixed(|| "", column, offset, || Value::known(*value))?;
             }
         }
@@ -261,6 +637,12 @@ impl<F: Field + From<u64> + Hash> ChiquitoHalo2<F> {
                 }
             }
             PolyExpr::Halo2Expr(e) => e.clone(),
+            PolyExpr::Challenge(id, _) => meta.query_challenge(
+                *self
+                    .challenges
+                    .get(id)
+                    .unwrap_or_else(|| panic!("challenge not found {}", id)),
+            ),
             PolyExpr::Query((column, rotation, _)) => self.convert_query(meta, column, *rotation),
             PolyExpr::MI(_) => panic!("mi elimination not done"),
         }
@@ -346,6 +728,58 @@ pub fn to_halo2_advice<F: Field>(
     }
 }
 
+/// Returns a challenge usable after the advice columns of `phase`.
+fn to_halo2_challenge<F: Field>(meta: &mut ConstraintSystem<F>, phase: usize) -> Challenge {
+    match phase {
+        0 => meta.challenge_usable_after(FirstPhase),
+        1 => meta.challenge_usable_after(SecondPhase),
+        2 => meta.challenge_usable_after(ThirdPhase),
+        _ => panic!("wrong challenge phase {}", phase),
+    }
+}
+
+fn collect_challenges<F>(expr: &PolyExpr<F>, challenges: &mut Vec<(UUID, usize)>) {
+    match expr {
+        PolyExpr::Challenge(id, phase) => challenges.push((*id, *phase)),
+        PolyExpr::Sum(ses) | PolyExpr::Mul(ses) => {
+            for se in ses {
+                collect_challenges(se, challenges);
+            }
+        }
+        PolyExpr::Neg(se) | PolyExpr::Pow(se, _) | PolyExpr::MI(se) => {
+            collect_challenges(se, challenges)
+        }
+        _ => {}
+    }
+}
+
+/// Values of the input cells of a gadget call at the step of `row`, or `None` if the step is of
+/// another step type.
+fn gadget_inputs<F: Field + From<u64>>(
+    call: &GadgetCall<F>,
+    witness: &Assignments<F>,
+    row: usize,
+) -> Option<Vec<F>> {
+    let value = |column: &cColumn, rotation: i32| -> F {
+        usize::try_from(row as i64 + rotation as i64)
+            .ok()
+            .and_then(|row| witness.get(column).and_then(|values| values.get(row)))
+            .copied()
+            .unwrap_or(F::ZERO)
+    };
+    let selected = call
+        .selector
+        .eval_with(&|(column, rotation, _)| Some(value(column, *rotation)))
+        .is_some_and(|selected| selected == F::ONE);
+
+    selected.then(|| {
+        call.inputs
+            .iter()
+            .map(|(column, rotation)| value(column, *rotation))
+            .collect()
+    })
+}
+
 #[derive(Clone, Default)]
 pub struct ChiquitoHalo2Circuit<F: Field + From<u64>> {
     compiled: ChiquitoHalo2<F>,
@@ -406,6 +840,113 @@ impl<F: Field + From<u64> + Hash> h2Circuit<F> for ChiquitoHalo2Circuit<F> {
     }
 }
 
+/// Step instances of the witness of a `ChiquitoHalo2StreamingCircuit`, pulled again for every
+/// pass over the witness.
+pub type StepSource<F> = dyn Fn() -> Box<dyn Iterator<Item = StepInstance<F>>>;
+
+/// Circuit that assigns its witness in chunks of `chunk_steps` step instances, generated while it
+/// is synthesized, for traces whose assignments do not fit in memory, see
+/// `WitnessAssigner::generate_chunks`. Only the step instances of a chunk are assigned at a time,
+/// but the prover still holds the columns of the whole table, so the table must fit in memory.
+/// Nothing is spilled to disk.
+///
+/// Gadget calls read the inputs of every step at once, so circuits with gadget calls are not
+/// supported.
+#[derive(Clone, Default)]
+pub struct ChiquitoHalo2StreamingCircuit<F: Field + From<u64>> {
+    compiled: ChiquitoHalo2<F>,
+    witness: Option<(Arc<WitnessAssigner<F>>, Arc<StepSource<F>>, usize)>,
+}
+
+impl<F: Field + From<u64> + Hash> ChiquitoHalo2StreamingCircuit<F> {
+    pub fn new(
+        compiled: ChiquitoHalo2<F>,
+        witness: Option<(Arc<WitnessAssigner<F>>, Arc<StepSource<F>>)>,
+        chunk_steps: usize,
+    ) -> Self {
+        if let Some(call) = compiled.circuit.gadget_calls.first() {
+            panic!(
+                "cannot stream the witness of {}, a gadget call",
+                call.annotation
+            );
+        }
+
+        Self {
+            compiled,
+            witness: witness.map(|(assigner, source)| (assigner, source, chunk_steps)),
+        }
+    }
+
+    /// Returns the exposed cells, from a pass over the witness. Fails if the chunks cannot be
+    /// generated, before the circuit is proven.
+    pub fn instance(&self) -> Result<Vec<Vec<F>>, ChunkError> {
+        let exposed = &self.compiled.circuit.exposed;
+        if !exposed.is_empty() {
+            if let Some((assigner, source, chunk_steps)) = &self.witness {
+                let mut instance = vec![F::ZERO; exposed.len()];
+                assigner.generate_chunks(source(), *chunk_steps, |start, chunk| {
+                    for ((column, rotation), value) in exposed.iter().zip(instance.iter_mut()) {
+                        let row = *rotation as usize;
+                        if let Some(cell) = row
+                            .checked_sub(start)
+                            .and_then(|row| chunk.get(column).and_then(|values| values.get(row)))
+                        {
+                            *value = *cell;
+                        }
+                    }
+                })?;
+
+                return Ok(vec![instance]);
+            }
+        }
+        Ok(Vec::new())
+    }
+}
+
+impl<F: Field + From<u64> + Hash> h2Circuit<F> for ChiquitoHalo2StreamingCircuit<F> {
+    type Config = ChiquitoHalo2<F>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    type Params = ChiquitoHalo2<F>;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn params(&self) -> Self::Params {
+        self.compiled.clone()
+    }
+
+    fn configure_with_params(
+        meta: &mut ConstraintSystem<F>,
+        mut compiled: Self::Params,
+    ) -> Self::Config {
+        compiled.configure(meta);
+
+        compiled
+    }
+
+    fn synthesize(
+        &self,
+        compiled: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        match &self.witness {
+            Some((assigner, source, chunk_steps)) => compiled
+                .synthesize_chunks(&mut layouter, assigner, || source(), *chunk_steps)
+                .map_err(|_| Error::Synthesis)?,
+            None => compiled.synthesize(&mut layouter, None),
+        }
+
+        Ok(())
+    }
+
+    fn configure(_: &mut ConstraintSystem<F>) -> Self::Config {
+        unreachable!()
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct ChiquitoHalo2SuperCircuit<F: Field + From<u64>> {
     sub_circuits: Vec<ChiquitoHalo2<F>>,
@@ -436,6 +977,320 @@ impl<F: Field + From<u64> + Hash> ChiquitoHalo2SuperCircuit<F> {
 
         result
     }
+
+    /// Returns the sizes of the sub-circuits and of the whole super circuit, where the rows are the
+    /// ones of the largest sub-circuit, or stack of identical sub-circuits, so the one that
+    /// dominates the cost stands out. The columns, polys and lookups of stacked sub-circuits are
+    /// counted once, see `stack_sub_circuits`.
+    pub fn stats(&self) -> SuperCircuitStats {
+        let sub_circuits: Vec<(UUID, CircuitStats)> = self
+            .sub_circuits
+            .iter()
+            .map(|sub_circuit| (sub_circuit.ir_id, sub_circuit.stats()))
+            .collect();
+        let stacks = stack_sub_circuits(&self.sub_circuits);
+
+        let queries = advice_queries(self.sub_circuits.iter().map(|s| &s.circuit));
+        let max_advice_queries = queries.values().map(HashSet::len).max().unwrap_or(0);
+        let rows = sub_circuits
+            .iter()
+            .zip(stacks.iter())
+            .map(|((_, stats), stack)| stack.map_or(0, |(_, row_offset)| row_offset) + stats.rows)
+            .max()
+            .unwrap_or(0);
+        let sum = |field: fn(&CircuitStats) -> usize| -> usize {
+            sub_circuits
+                .iter()
+                .zip(stacks.iter())
+                .filter(|(_, stack)| stack.is_none())
+                .map(|((_, stats), _)| field(stats))
+                .sum()
+        };
+        let total = CircuitStats {
+            advice_columns: sum(|stats| stats.advice_columns),
+            fixed_columns: sum(|stats| stats.fixed_columns),
+            rows,
+            polys: sum(|stats| stats.polys),
+            lookups: sum(|stats| stats.lookups),
+            max_degree: sub_circuits
+                .iter()
+                .map(|(_, stats)| stats.max_degree)
+                .max()
+                .unwrap_or(0),
+            max_advice_queries,
+            min_k: min_k(rows, max_advice_queries),
+        };
+
+        let shared_columns = self
+            .sub_circuits
+            .iter()
+            .map(|sub_circuit| {
+                let circuit = &sub_circuit.circuit;
+                let own: HashSet<UUID> = circuit.columns.iter().map(|c| c.uuid()).collect();
+                let mut foreign = HashSet::new();
+                for expr in circuit_exprs(circuit) {
+                    for_each_query(expr, &mut |column, _| {
+                        if !own.contains(&column.uuid()) {
+                            foreign.insert(column.uuid());
+                        }
+                    });
+                }
+
+                foreign.len()
+            })
+            .sum();
+
+        SuperCircuitStats {
+            sub_circuits,
+            total,
+            shared_columns,
+        }
+    }
+}
+
+fn circuit_stats<F: Field>(
+    circuit: &Circuit<F>,
+    queries: &HashMap<UUID, HashSet<i32>>,
+) -> CircuitStats {
+    let advice_columns = circuit
+        .columns
+        .iter()
+        .filter(|column| matches!(column.ctype, cAdvice | Halo2Advice))
+        .count();
+    let max_advice_queries = queries.values().map(HashSet::len).max().unwrap_or(0);
+
+    CircuitStats {
+        advice_columns,
+        fixed_columns: circuit.columns.len() - advice_columns,
+        rows: circuit.num_rows,
+        polys: circuit.polys.len(),
+        lookups: circuit.lookups.len(),
+        max_degree: circuit_exprs(circuit).map(degree).max().unwrap_or(0),
+        max_advice_queries,
+        min_k: min_k(circuit.num_rows, max_advice_queries),
+    }
+}
+
+/// Smallest k of a halo2 table with `rows` usable rows, after which halo2 places the blinding
+/// rows, one more than the most queries of an advice column, at least 3, and two more rows.
+fn min_k(rows: usize, max_advice_queries: usize) -> u32 {
+    let blinding_factors = max_advice_queries.max(3) + 2;
+
+    (rows + blinding_factors + 1)
+        .next_power_of_two()
+        .trailing_zeros()
+}
+
+/// Returns the rotations that each advice column is queried with in the polys and lookups of
+/// `circuits`.
+fn advice_queries<'a, F: 'a, I: IntoIterator<Item = &'a Circuit<F>>>(
+    circuits: I,
+) -> HashMap<UUID, HashSet<i32>> {
+    let mut queries: HashMap<UUID, HashSet<i32>> = HashMap::new();
+    for circuit in circuits {
+        for expr in circuit_exprs(circuit) {
+            for_each_query(expr, &mut |column, rotation| {
+                if matches!(column.ctype, cAdvice | Halo2Advice) {
+                    queries.entry(column.uuid()).or_default().insert(rotation);
+                }
+            });
+        }
+    }
+
+    queries
+}
+
+/// Returns the expressions of the polys and lookups of `circuit`.
+fn circuit_exprs<F>(circuit: &Circuit<F>) -> impl Iterator<Item = &PolyExpr<F>> {
+    circuit.polys.iter().map(|poly| &poly.expr).chain(
+        circuit
+            .lookups
+            .iter()
+            .flat_map(|lookup| lookup.exprs.iter().flat_map(|(src, dest)| [src, dest])),
+    )
+}
+
+fn for_each_query<F>(expr: &PolyExpr<F>, f: &mut dyn FnMut(&cColumn, i32)) {
+    match expr {
+        PolyExpr::Query((column, rotation, _)) => f(column, *rotation),
+        PolyExpr::Sum(ses) | PolyExpr::Mul(ses) => ses.iter().for_each(|se| for_each_query(se, f)),
+        PolyExpr::Neg(se) | PolyExpr::Pow(se, _) | PolyExpr::MI(se) => for_each_query(se, f),
+        _ => {}
+    }
+}
+
+/// Degree of `expr`, with the degree of the halo2 expressions in it.
+fn degree<F: Field>(expr: &PolyExpr<F>) -> usize {
+    match expr {
+        PolyExpr::Sum(ses) => ses.iter().map(degree).max().unwrap_or(0),
+        PolyExpr::Mul(ses) => ses.iter().map(degree).sum(),
+        PolyExpr::Neg(se) => degree(se),
+        PolyExpr::Pow(se, exp) => degree(se) * (*exp as usize),
+        PolyExpr::Halo2Expr(e) => e.degree(),
+        PolyExpr::MI(se) => usize::from(degree(se) > 0),
+        expr => expr.degree(),
+    }
+}
+
+/// Finds the structurally identical sub-circuits, by their canonical form, and stacks each after
+/// the first of them in its columns, so they share its gates and lookups instead of configuring
+/// their own. Returns for each sub-circuit the index of the sub-circuit it is stacked on and its
+/// first row, or `None` if it is configured in its own columns.
+///
+/// Only the sub-circuits that are independent of the others stack: they query only their own
+/// columns, no other sub-circuit queries or copies them, they have no imported halo2 columns nor
+/// gadget calls, and they look up only into fixed columns, as the lookups of stacked sub-circuits
+/// see the rows of each other. Their polys and lookup sources must not query the rows before their
+/// first one or after their last one, which would be the rows of the sub-circuits stacked next to
+/// them, see `reaches_outside`.
+fn stack_sub_circuits<F: Field + From<u64> + Hash>(
+    sub_circuits: &[ChiquitoHalo2<F>],
+) -> Vec<Option<(usize, usize)>> {
+    let mut shared: HashSet<UUID> = HashSet::new();
+    for sub_circuit in sub_circuits {
+        let circuit = &sub_circuit.circuit;
+        let own: HashSet<UUID> = circuit.columns.iter().map(|c| c.uuid()).collect();
+        for expr in circuit_exprs(circuit) {
+            for_each_query(expr, &mut |column, _| {
+                if !own.contains(&column.uuid()) {
+                    shared.insert(column.uuid());
+                }
+            });
+        }
+        for ((from, _), (to, _)) in circuit.copies.iter() {
+            shared.insert(from.uuid());
+            shared.insert(to.uuid());
+        }
+    }
+
+    let stackable = |circuit: &Circuit<F>| {
+        let own: HashSet<UUID> = circuit.columns.iter().map(|c| c.uuid()).collect();
+
+        circuit.columns.iter().all(|column| {
+            matches!(column.ctype, cAdvice | cFixed) && !shared.contains(&column.uuid())
+        }) && circuit.gadget_calls.is_empty()
+            && circuit_exprs(circuit).all(|expr| foreign_column(expr, &own).is_none())
+            && circuit.lookups.iter().all(|lookup| {
+                lookup.exprs.iter().all(|(_, dest)| {
+                    let mut fixed = true;
+                    for_each_query(dest, &mut |column, _| fixed &= column.ctype == cFixed);
+                    fixed
+                })
+            })
+            && circuit
+                .polys
+                .iter()
+                .all(|poly| !reaches_outside(circuit, &poly.expr))
+            && circuit.lookups.iter().all(|lookup| {
+                lookup
+                    .exprs
+                    .iter()
+                    .all(|(src, _)| !reaches_outside(circuit, src))
+            })
+    };
+
+    // the sub-circuit each canonical form is first seen in, and the rows stacked in its columns.
+    // The forms are compared, not only their hashes, so circuits are never stacked on another
+    // circuit of the same hash.
+    let mut stacks: HashMap<Vec<u8>, (usize, usize)> = HashMap::new();
+    sub_circuits
+        .iter()
+        .enumerate()
+        .map(|(index, sub_circuit)| {
+            let circuit = &sub_circuit.circuit;
+            if !stackable(circuit) {
+                return None;
+            }
+
+            match stacks.entry(circuit.canonical_form()) {
+                Entry::Occupied(mut stack) => {
+                    let (base, rows) = stack.get_mut();
+                    let row_offset = *rows;
+                    *rows += circuit.num_rows;
+                    Some((*base, row_offset))
+                }
+                Entry::Vacant(stack) => {
+                    stack.insert((index, circuit.num_rows));
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Returns if `expr` can query a row outside of the rows of `circuit`, from a row where it is
+/// enabled. The rows where it is enabled are the ones where its fixed factors, like q_enable and
+/// the `1 - q_last` of the transition constraints, are not zero, so the factors of advice columns,
+/// like the step selectors, are not taken into account.
+fn reaches_outside<F: Field + From<u64>>(circuit: &Circuit<F>, expr: &PolyExpr<F>) -> bool {
+    let (mut min, mut max) = (0, 0);
+    for_each_query(expr, &mut |_, rotation| {
+        min = min.min(rotation);
+        max = max.max(rotation);
+    });
+    if min == 0 && max == 0 {
+        return false;
+    }
+
+    let mut factors = Vec::new();
+    fixed_factors(circuit, expr, &mut factors);
+    let enabled = |row: &usize| {
+        factors.iter().all(|factor| {
+            let value = factor.eval_with(&|(column, _, _)| {
+                circuit
+                    .fixed_assignments
+                    .get(column)
+                    .and_then(|values| values.get(*row))
+                    .copied()
+            });
+            value != Some(F::ZERO)
+        })
+    };
+
+    let first = (0..circuit.num_rows).find(enabled);
+    let last = (0..circuit.num_rows).rev().find(enabled);
+    match (first, last) {
+        (Some(first), Some(last)) => {
+            (first as i64) + (min as i64) < 0
+                || (last as i64) + (max as i64) >= circuit.num_rows as i64
+        }
+        _ => false,
+    }
+}
+
+/// Collects the factors of `expr` that only query fixed columns of `circuit` at the current row.
+fn fixed_factors<'a, F>(
+    circuit: &Circuit<F>,
+    expr: &'a PolyExpr<F>,
+    factors: &mut Vec<&'a PolyExpr<F>>,
+) {
+    match expr {
+        PolyExpr::Mul(ses) => ses
+            .iter()
+            .for_each(|se| fixed_factors(circuit, se, factors)),
+        expr => {
+            let (mut fixed, mut queries) = (true, false);
+            for_each_query(expr, &mut |column, rotation| {
+                queries = true;
+                fixed &= rotation == 0 && circuit.fixed_assignments.contains_key(column);
+            });
+            if fixed && queries {
+                factors.push(expr);
+            }
+        }
+    }
+}
+
+/// Returns the first column that `expr` queries that is not in `own`.
+fn foreign_column<'a, F>(expr: &'a PolyExpr<F>, own: &HashSet<UUID>) -> Option<&'a cColumn> {
+    match expr {
+        PolyExpr::Query((column, _, _)) if !own.contains(&column.uuid()) => Some(column),
+        PolyExpr::Sum(ses) | PolyExpr::Mul(ses) => {
+            ses.iter().find_map(|se| foreign_column(se, own))
+        }
+        PolyExpr::Neg(se) | PolyExpr::Pow(se, _) | PolyExpr::MI(se) => foreign_column(se, own),
+        _ => None,
+    }
 }
 
 impl<F: Field + From<u64> + Hash> h2Circuit<F> for ChiquitoHalo2SuperCircuit<F> {
@@ -457,9 +1312,49 @@ impl<F: Field + From<u64> + Hash> h2Circuit<F> for ChiquitoHalo2SuperCircuit<F>
         meta: &mut ConstraintSystem<F>,
         mut sub_circuits: Self::Params,
     ) -> Self::Config {
-        sub_circuits
-            .iter_mut()
-            .for_each(|c| c.configure_columns_sub_circuit(meta));
+        let stacks = stack_sub_circuits(&sub_circuits);
+
+        // the sub-circuits share the challenges with the same UUID
+        let mut challenges: HashMap<UUID, Challenge> = HashMap::default();
+        for (index, stack) in stacks.iter().enumerate() {
+            match *stack {
+                // the columns of a stacked sub-circuit correspond in order to the ones of the
+                // sub-circuit it is stacked on
+                Some((base, row_offset)) => {
+                    let (advice_columns, fixed_columns) = {
+                        let base = &sub_circuits[base];
+                        let mut advice_columns = HashMap::new();
+                        let mut fixed_columns = HashMap::new();
+                        for (column, base_column) in sub_circuits[index]
+                            .circuit
+                            .columns
+                            .iter()
+                            .zip(base.circuit.columns.iter())
+                        {
+                            match column.ctype {
+                                cAdvice => advice_columns.insert(
+                                    column.uuid(),
+                                    base.advice_columns[&base_column.uuid()],
+                                ),
+                                _ => fixed_columns
+                                    .insert(column.uuid(), base.fixed_columns[&base_column.uuid()]),
+                            };
+                        }
+                        (advice_columns, fixed_columns)
+                    };
+                    let sub_circuit = &mut sub_circuits[index];
+                    sub_circuit.advice_columns = advice_columns;
+                    sub_circuit.fixed_columns = fixed_columns;
+                    sub_circuit.row_offset = row_offset;
+                }
+                None => {
+                    let c = &mut sub_circuits[index];
+                    c.challenges = challenges.clone();
+                    c.configure_columns_sub_circuit(meta);
+                    challenges.extend(c.challenges.clone());
+                }
+            }
+        }
 
         let advice_columns: HashMap<UUID, Column<Advice>> =
             sub_circuits.iter().fold(HashMap::default(), |mut acc, s| {
@@ -472,11 +1367,20 @@ impl<F: Field + From<u64> + Hash> h2Circuit<F> for ChiquitoHalo2SuperCircuit<F>
                 acc
             });
 
-        sub_circuits.iter_mut().for_each(|sub_circuit| {
-            sub_circuit.advice_columns = advice_columns.clone();
-            sub_circuit.fixed_columns = fixed_columns.clone();
-            sub_circuit.configure_sub_circuit(meta)
-        });
+        sub_circuits
+            .iter_mut()
+            .zip(stacks)
+            .for_each(|(sub_circuit, stack)| {
+                sub_circuit.advice_columns = advice_columns.clone();
+                sub_circuit.fixed_columns = fixed_columns.clone();
+                sub_circuit.challenges = challenges.clone();
+                // a stacked sub-circuit is constrained by the gates and lookups of the one it is
+                // stacked on
+                match stack {
+                    Some(_) => sub_circuit.configure_instance(meta),
+                    None => sub_circuit.configure_sub_circuit(meta),
+                }
+            });
 
         sub_circuits
     }
@@ -486,8 +1390,20 @@ impl<F: Field + From<u64> + Hash> h2Circuit<F> for ChiquitoHalo2SuperCircuit<F>
         sub_circuits: Self::Config,
         mut layouter: impl Layouter<F>,
     ) -> Result<(), Error> {
-        for sub_circuit in sub_circuits {
-            sub_circuit.synthesize(&mut layouter, self.witness.get(&sub_circuit.ir_id))
+        // one region, as the stacked sub-circuits are assigned in the same columns
+        let _ = layouter.assign_region(
+            || "super circuit",
+            |mut region| {
+                for sub_circuit in sub_circuits.iter() {
+                    sub_circuit.assign(&mut region, self.witness.get(&sub_circuit.ir_id))?;
+                }
+
+                Ok(())
+            },
+        );
+
+        for sub_circuit in sub_circuits.iter() {
+            sub_circuit.constrain(&mut layouter);
         }
 
         Ok(())
@@ -497,3 +1413,110 @@ impl<F: Field + From<u64> + Hash> h2Circuit<F> for ChiquitoHalo2SuperCircuit<F>
         unreachable!()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+
+    use crate::{
+        frontend::dsl::{cb::eq, circuit, sc::SuperCircuitContext, CircuitContext},
+        plonkish::compiler::{
+            cell_manager::SingleRowCellManager, compile, config,
+            step_selector::SimpleStepSelectorBuilder,
+        },
+        poly::ToField,
+        sbpir::ExposeOffset,
+    };
+
+    use super::*;
+
+    /// Sub-circuit with a transition, or a constraint, between a shared signal and its next row.
+    fn next_row_circuit(ctx: &mut CircuitContext<Fr, ()>, transition: bool) {
+        let s = ctx.shared("s");
+        ctx.step_type_def("step", |ctx| {
+            ctx.setup(move |ctx| {
+                if transition {
+                    ctx.transition(eq(s.rot(1), s));
+                } else {
+                    ctx.constr(eq(s.rot(1), s));
+                }
+            });
+            ctx.wg(move |ctx, value: u32| ctx.assign(s, value.field()))
+        });
+        ctx.pragma_num_steps(2);
+    }
+
+    #[test]
+    fn test_stack_sub_circuits_next_row() {
+        // the constraint of the last row queries the first row of the sub-circuit stacked after it
+        for (transition, stacked) in [(true, true), (false, false)] {
+            let mut ctx = SuperCircuitContext::<Fr, ()>::default();
+            let config = config(SingleRowCellManager {}, SimpleStepSelectorBuilder {});
+            ctx.sub_circuit(config.clone(), next_row_circuit, transition);
+            ctx.sub_circuit(config, next_row_circuit, transition);
+
+            let sub_circuits = chiquitoSuperCircuit2Halo2(&ctx.compile());
+            assert_eq!(stack_sub_circuits(&sub_circuits)[0], None);
+            assert_eq!(stack_sub_circuits(&sub_circuits)[1].is_some(), stacked);
+        }
+    }
+
+    #[test]
+    fn test_streaming_circuit() {
+        let counter = circuit("counter", |ctx| {
+            let count = ctx.forward("count");
+
+            let start = ctx.step_type_def("start", |ctx| {
+                ctx.setup(move |ctx| {
+                    ctx.constr(eq(count, 0));
+                    ctx.transition(eq(count + 1, count.next()));
+                });
+                ctx.wg(move |ctx, _: ()| ctx.assign(count, Fr::ZERO))
+            });
+            let inc = ctx.step_type_def("inc", |ctx| {
+                ctx.setup(move |ctx| ctx.transition(eq(count + 1, count.next())));
+                ctx.wg(move |ctx, value: u64| ctx.assign(count, Fr::from(value)))
+            });
+            let done = ctx.step_type_def("done", |ctx| {
+                ctx.wg(move |ctx, value: u64| ctx.assign(count, Fr::from(value)))
+            });
+            let pad = ctx.step_type_def("pad", |ctx| ctx.wg(|_, _: ()| {}));
+
+            ctx.pragma_first_step(&start);
+            ctx.pragma_last_step(&done);
+            ctx.pragma_padding_step(&pad);
+            ctx.pragma_num_steps(8);
+            ctx.expose(count, ExposeOffset::Step(3));
+
+            ctx.trace(move |ctx, n: u64| {
+                ctx.add(&start, ());
+                for i in 1..n {
+                    ctx.add(&inc, i);
+                }
+                ctx.add(&done, n);
+            });
+        });
+        let config = config(SingleRowCellManager {}, SimpleStepSelectorBuilder {});
+        let (compiled, generator) = compile(config, &counter);
+        let generator = generator.unwrap();
+
+        let witness = generator.generate_trace_witness(5);
+        let whole = ChiquitoHalo2Circuit::new(
+            chiquito2Halo2(compiled.clone()),
+            Some(generator.generate_with_witness(witness.clone())),
+        );
+        let steps = witness.step_instances;
+        let source: Arc<StepSource<Fr>> = Arc::new(move || Box::new(steps.clone().into_iter()));
+        let streaming = ChiquitoHalo2StreamingCircuit::new(
+            chiquito2Halo2(compiled),
+            Some((Arc::new((*generator).clone()), source)),
+            3,
+        );
+
+        let instance = streaming.instance().unwrap();
+        assert_eq!(instance, vec![vec![Fr::from(3)]]);
+        assert_eq!(instance, whole.instance());
+        let prover = MockProver::run(6, &streaming, instance).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
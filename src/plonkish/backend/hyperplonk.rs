@@ -373,6 +373,7 @@ impl<F: Field> ChiquitoHyperPlonk<F> {
                 }
             }
             PolyExpr::Halo2Expr(_) => panic!("halo2 expressions not supported"),
+            PolyExpr::Challenge(..) => panic!("challenges not supported"),
             PolyExpr::MI(_) => panic!("MI expressions not supported"),
         }
     }
@@ -1,3 +1,6 @@
+#[cfg(feature = "halo2")]
 pub mod halo2;
+#[cfg(feature = "halo2")]
 pub mod hyperplonk;
+#[cfg(feature = "halo2")]
 pub mod plaf;
@@ -72,6 +72,21 @@ impl Placement {
             .clone()
     }
 
+    /// Returns the internal signals placed for a step type, in order of UUID.
+    pub fn internal_signals(&self, step_uuid: StepTypeUUID) -> Vec<InternalSignal> {
+        let mut signals: Vec<InternalSignal> = self
+            .steps
+            .get(&step_uuid)
+            .expect("step not found")
+            .signals
+            .keys()
+            .cloned()
+            .collect();
+        signals.sort_by_key(|signal| signal.uuid());
+
+        signals
+    }
+
     pub fn step_height(&self, step_uuid: StepTypeUUID) -> u32 {
         self.steps.get(&step_uuid).expect("step not found").height
     }
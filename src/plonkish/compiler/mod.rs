@@ -2,13 +2,21 @@ use crate::{
     field::Field,
     plonkish::ir::{
         assignments::{AssignmentGenerator, Assignments},
-        Circuit, Column, Poly, PolyExpr, PolyLookup,
+        query::Queriable as PolyQueriable,
+        Circuit, Column, GadgetCall, Poly, PolyExpr, PolyLookup,
+    },
+    poly::{
+        intern::{ExprArena, ExprId},
+        rewrite::RewriteRule,
+        Expr,
+    },
+    sbpir::{
+        analysis::trivial_constraints, query::Queriable, ExposeOffset, GadgetCall as astGadgetCall,
+        SourceLocation, StepType, StepTypeUUID, PIR, SBPIR as astCircuit,
     },
-    poly::Expr,
-    sbpir::{query::Queriable, ExposeOffset, StepType, StepTypeUUID, PIR, SBPIR as astCircuit},
     wit_gen::{AutoTraceGenerator, FixedAssignment, TraceGenerator},
 };
-use std::{hash::Hash, rc::Rc};
+use std::{collections::HashSet, fmt::Debug, hash::Hash, rc::Rc};
 
 use cell_manager::{CellManager, SignalPlacement};
 use step_selector::StepSelectorBuilder;
@@ -20,23 +28,72 @@ pub mod step_selector;
 pub(crate) mod unit;
 
 #[derive(Clone)]
-pub struct CompilerConfig<CM: CellManager, SSB: StepSelectorBuilder> {
+pub struct CompilerConfig<F, CM: CellManager, SSB: StepSelectorBuilder> {
     cell_manager: CM,
     step_selector_builder: SSB,
+    disabled_features: HashSet<String>,
+    simplify: bool,
+    warn_trivial_constraints: bool,
+    rewrite_rules: Vec<RewriteRule<F>>,
+}
+
+impl<F, CM: CellManager, SSB: StepSelectorBuilder> CompilerConfig<F, CM, SSB> {
+    /// Leaves out the constraints and lookups of a feature, see `StepTypeSetupContext::feature`.
+    /// All the features are enabled by default.
+    pub fn disable_feature<N: Into<String>>(mut self, feature: N) -> Self {
+        self.disabled_features.insert(feature.into());
+
+        self
+    }
+
+    /// Enables a feature disabled before.
+    pub fn enable_feature(mut self, feature: &str) -> Self {
+        self.disabled_features.remove(feature);
+
+        self
+    }
+
+    /// Brings the constraints and lookups to normal form before they are compiled, see
+    /// `poly::simplify::simplify`.
+    pub fn simplify(mut self) -> Self {
+        self.simplify = true;
+
+        self
+    }
+
+    /// Prints a warning for each constraint that no witness satisfies or that constrains nothing,
+    /// which are likely bugs, see `sbpir::analysis::trivial_constraints`.
+    pub fn warn_trivial_constraints(mut self) -> Self {
+        self.warn_trivial_constraints = true;
+
+        self
+    }
+
+    /// Rewrites the constraints and lookups with `rules` before they are compiled, see
+    /// `poly::rewrite::rewrite`.
+    pub fn rewrite_rules(mut self, rules: Vec<RewriteRule<F>>) -> Self {
+        self.rewrite_rules = rules;
+
+        self
+    }
 }
 
-pub fn config<CM: CellManager, SSB: StepSelectorBuilder>(
+pub fn config<F, CM: CellManager, SSB: StepSelectorBuilder>(
     cell_manager: CM,
     step_selector_builder: SSB,
-) -> CompilerConfig<CM, SSB> {
+) -> CompilerConfig<F, CM, SSB> {
     CompilerConfig {
         cell_manager,
         step_selector_builder,
+        disabled_features: Default::default(),
+        simplify: false,
+        warn_trivial_constraints: false,
+        rewrite_rules: Vec::new(),
     }
 }
 
 pub fn compile<F: Field + Hash + Clone, CM: CellManager, SSB: StepSelectorBuilder, TraceArgs>(
-    config: CompilerConfig<CM, SSB>,
+    config: CompilerConfig<F, CM, SSB>,
     ast: &astCircuit<F, TraceArgs>,
 ) -> (Circuit<F>, Option<AssignmentGenerator<F, TraceArgs>>) {
     let (mut unit, assignment) = compile_phase1(config, ast);
@@ -46,20 +103,68 @@ pub fn compile<F: Field + Hash + Clone, CM: CellManager, SSB: StepSelectorBuilde
     (unit.into(), assignment)
 }
 
+/// Expressions of the polys and lookups of a compiled circuit, interned so the identical
+/// sub-expressions are stored once, for analysis passes over large circuits.
+pub struct InternedExprs<F> {
+    pub arena: ExprArena<F, PolyQueriable>,
+    /// Id of the expression of each poly, in the order of `Circuit::polys`.
+    pub polys: Vec<ExprId>,
+    /// Ids of the source and destination expressions of each lookup, in the order of
+    /// `Circuit::lookups`.
+    pub lookups: Vec<Vec<(ExprId, ExprId)>>,
+}
+
+/// Interns the expressions of a compiled circuit, see `InternedExprs`.
+pub fn intern<F: Field + Hash>(circuit: &Circuit<F>) -> InternedExprs<F> {
+    let mut arena = ExprArena::new();
+    let polys = circuit
+        .polys
+        .iter()
+        .map(|poly| arena.intern(&poly.expr))
+        .collect();
+    let lookups = circuit
+        .lookups
+        .iter()
+        .map(|lookup| {
+            lookup
+                .exprs
+                .iter()
+                .map(|(src, dest)| (arena.intern(src), arena.intern(dest)))
+                .collect()
+        })
+        .collect();
+
+    InternedExprs {
+        arena,
+        polys,
+        lookups,
+    }
+}
+
 pub fn compile_phase1<
     F: Field + Hash + Clone,
     CM: CellManager,
     SSB: StepSelectorBuilder,
     TraceArgs,
 >(
-    config: CompilerConfig<CM, SSB>,
+    config: CompilerConfig<F, CM, SSB>,
     ast: &astCircuit<F, TraceArgs>,
 ) -> (
     CompilationUnit<F>,
     Option<AssignmentGenerator<F, TraceArgs>>,
 ) {
+    if config.warn_trivial_constraints {
+        for constraint in trivial_constraints(ast) {
+            eprintln!("warning: {}", constraint);
+        }
+    }
+
     let mut unit = CompilationUnit::from(ast);
+    unit.disabled_features = config.disabled_features;
+    unit.simplify = config.simplify;
+    unit.rewrite_rules = Rc::new(config.rewrite_rules);
 
+    #[cfg(feature = "halo2")]
     add_halo2_columns(&mut unit, ast);
 
     config.cell_manager.place(&mut unit);
@@ -109,6 +214,10 @@ pub fn compile_phase2<F: Field + Clone>(unit: &mut CompilationUnit<F>) {
         compile_step(unit, step);
     }
 
+    if let Some(padding_step) = unit.padding_step {
+        add_padding(unit, padding_step);
+    }
+
     if let Some(q_enable) = &unit.q_enable {
         add_q_enable(unit, q_enable.clone());
     }
@@ -124,6 +233,23 @@ pub fn compile_phase2<F: Field + Clone>(unit: &mut CompilationUnit<F>) {
     unit.compilation_phase = 2;
 }
 
+/// Annotation of the polynomial of a constraint, with its source location when known so that
+/// prover failures point at the code that created it.
+fn constraint_annotation<F: Debug>(
+    step_annotation: &str,
+    annotation: &str,
+    location: Option<SourceLocation>,
+    expr: &PIR<F>,
+) -> String {
+    match location {
+        Some(location) => format!(
+            "{}::{} at {} => {:?}",
+            step_annotation, annotation, location, expr
+        ),
+        None => format!("{}::{} => {:?}", step_annotation, annotation, expr),
+    }
+}
+
 fn compile_step<F: Field>(unit: &mut CompilationUnit<F>, step: &StepType<F>) {
     let step_annotation = unit
         .annotations
@@ -132,46 +258,59 @@ fn compile_step<F: Field>(unit: &mut CompilationUnit<F>, step: &StepType<F>) {
         .to_owned();
 
     for constr in step.constraints.iter() {
-        let constraint = transform_expr(unit, step, &constr.expr.clone());
+        if !unit.is_enabled(&constr.feature) {
+            continue;
+        }
+
+        let constraint = compile_expr(unit, step, &constr.expr.clone());
         let poly = unit.selector.select(step.uuid(), &constraint);
 
         unit.polys.push(Poly {
             expr: poly,
-            annotation: format!(
-                "{}::{} => {:?}",
-                step_annotation.clone(),
-                constr.annotation.clone(),
-                constr.expr
+            annotation: constraint_annotation(
+                &step_annotation,
+                &constr.annotation,
+                constr.location,
+                &constr.expr,
             ),
         })
     }
 
     // TODO only transition_constraints should have rotations
     for constr in step.transition_constraints.iter() {
-        let constraint = transform_expr(unit, step, &constr.expr.clone());
+        if !unit.is_enabled(&constr.feature) {
+            continue;
+        }
+
+        let constraint = compile_expr(unit, step, &constr.expr.clone());
         let poly = unit.selector.select(step.uuid(), &constraint);
         let poly = add_q_last_to_constraint(unit, poly);
+        let poly = add_not_next_padding_to_constraint(unit, step.uuid(), poly);
 
         unit.polys.push(Poly {
             expr: poly,
-            annotation: format!(
-                "{}::{} => {:?}",
-                step_annotation.clone(),
-                constr.annotation.clone(),
-                constr.expr
+            annotation: constraint_annotation(
+                &step_annotation,
+                &constr.annotation,
+                constr.location,
+                &constr.expr,
             ),
         })
     }
 
     for lookup in step.lookups.iter() {
+        if !unit.is_enabled(&lookup.feature) {
+            continue;
+        }
+
         let poly_lookup = PolyLookup {
             annotation: lookup.annotation.clone(),
             exprs: lookup
                 .exprs
                 .iter()
                 .map(|(src, dest)| {
-                    let src_poly = transform_expr(unit, step, &src.expr);
-                    let dest_poly = transform_expr(unit, step, dest);
+                    let src_poly = compile_expr(unit, step, &src.expr);
+                    let dest_poly = compile_expr(unit, step, dest);
                     let src_selected = unit.selector.select(step.uuid(), &src_poly);
 
                     (src_selected, dest_poly)
@@ -181,50 +320,102 @@ fn compile_step<F: Field>(unit: &mut CompilationUnit<F>, step: &StepType<F>) {
 
         unit.lookups.push(poly_lookup);
     }
+
+    for call in step.gadget_calls.iter() {
+        if !unit.is_enabled(&call.feature) {
+            continue;
+        }
+
+        compile_gadget_call(unit, step, &step_annotation, call);
+    }
+}
+
+/// Adds the call table that the halo2 backend copies the input and output cells of the calls of a
+/// gadget to, and the lookup of the input and output signals of the steps of the step type in it,
+/// see `ir::GadgetCall`.
+fn compile_gadget_call<F: Field>(
+    unit: &mut CompilationUnit<F>,
+    step: &StepType<F>,
+    step_annotation: &str,
+    call: &astGadgetCall<F>,
+) {
+    let annotation = format!("{}::{}", step_annotation, call.annotation);
+    let place = |signals: &[Queriable<F>]| -> Vec<(PolyExpr<F>, (Column, i32))> {
+        signals
+            .iter()
+            .map(|signal| match place_queriable(unit, step, signal.clone()) {
+                PolyExpr::Query((column, rotation, query)) => (
+                    PolyExpr::Query((column.clone(), rotation, query)),
+                    (column, rotation),
+                ),
+                _ => panic!("{} is called with {:?}, not a signal", annotation, signal),
+            })
+            .collect()
+    };
+    let inputs = place(&call.inputs);
+    let outputs = place(&call.outputs);
+    let phase = inputs
+        .iter()
+        .map(|(_, (column, _))| column.phase)
+        .max()
+        .unwrap_or(0);
+
+    let calls = call
+        .max_calls
+        .map_or(unit.num_steps, |max_calls| max_calls.min(unit.num_steps));
+    let enable = Column::fixed(format!("{} enable", annotation));
+    let mut enable_values = vec![F::ZERO; unit.num_rows];
+    enable_values[..calls].fill(F::ONE);
+    unit.fixed_assignments.insert(enable.clone(), enable_values);
+    unit.columns.push(enable.clone());
+
+    let enabled = enable.query(0, enable.annotation.clone());
+    let mut exprs = vec![(
+        unit.selector.select(step.uuid(), &PolyExpr::Const(F::ONE)),
+        enabled.clone(),
+    )];
+    let mut table_columns = |kind: &str, signals: &[(PolyExpr<F>, (Column, i32))]| {
+        let mut columns = Vec::new();
+        for (index, (signal, _)) in signals.iter().enumerate() {
+            let column = Column::advice(format!("{} {} {}", annotation, kind, index), phase);
+
+            exprs.push((
+                unit.selector.select(step.uuid(), signal),
+                enabled.clone() * column.query(0, column.annotation.clone()),
+            ));
+            unit.columns.push(column.clone());
+            columns.push(column);
+        }
+
+        columns
+    };
+    let input_columns = table_columns("input", &inputs);
+    let output_columns = table_columns("output", &outputs);
+
+    unit.lookups.push(PolyLookup {
+        annotation: format!("{} calls", annotation),
+        exprs,
+    });
+
+    let step_height = unit.placement.first_step_height() as usize;
+    unit.gadget_calls.push(GadgetCall {
+        gadget: call.gadget.clone(),
+        annotation,
+        selector: unit.selector.selector_expr[&step.uuid()].clone(),
+        inputs: inputs.into_iter().map(|(_, cell)| cell).collect(),
+        input_columns,
+        output_columns,
+        enable,
+        calls,
+        rows: (0..unit.num_steps).map(|step| step * step_height).collect(),
+    });
 }
 
 fn compile_exposed<F, TraceArgs>(ast: &astCircuit<F, TraceArgs>, unit: &mut CompilationUnit<F>) {
     for (queriable, offset) in &ast.exposed {
         let exposed = match queriable {
-            Queriable::Forward(forward_signal, _) => {
-                let placement = unit
-                    .placement
-                    .get_forward_placement(forward_signal)
-                    .expect("forward placement not found");
-                match offset {
-                    ExposeOffset::First => (placement.column, placement.rotation),
-                    ExposeOffset::Last => {
-                        let rot = placement.rotation
-                            + ((unit.num_steps - 1) as i32)
-                                * (unit.placement.first_step_height() as i32);
-                        (placement.column, rot)
-                    }
-                    ExposeOffset::Step(step) => {
-                        let rot = placement.rotation
-                            + (*step as i32) * (unit.placement.first_step_height() as i32);
-                        (placement.column, rot)
-                    }
-                }
-            }
-            Queriable::Shared(shared_signal, _) => {
-                let placement = unit
-                    .placement
-                    .get_shared_placement(shared_signal)
-                    .expect("shared placement not found");
-                match offset {
-                    ExposeOffset::First => (placement.column, placement.rotation),
-                    ExposeOffset::Last => {
-                        let rot = placement.rotation
-                            + ((unit.num_steps - 1) as i32)
-                                * (unit.placement.first_step_height() as i32);
-                        (placement.column, rot)
-                    }
-                    ExposeOffset::Step(step) => {
-                        let rot = placement.rotation
-                            + (*step as i32) * (unit.placement.first_step_height() as i32);
-                        (placement.column, rot)
-                    }
-                }
+            Queriable::Forward(..) | Queriable::Shared(..) => {
+                signal_cell(unit, queriable, offset).expect("forward or shared placement not found")
             }
             _ => panic!("Queriable was not Forward or Shared"),
         };
@@ -233,6 +424,36 @@ fn compile_exposed<F, TraceArgs>(ast: &astCircuit<F, TraceArgs>, unit: &mut Comp
     }
 }
 
+/// Returns the column and the row of the value of a forward or shared signal at `offset`, or
+/// `None` if the signal is not placed in `unit`.
+pub(crate) fn signal_cell<F>(
+    unit: &CompilationUnit<F>,
+    queriable: &Queriable<F>,
+    offset: &ExposeOffset,
+) -> Option<(Column, i32)> {
+    let placement = match queriable {
+        Queriable::Forward(forward_signal, _) => {
+            unit.placement.get_forward_placement(forward_signal)?
+        }
+        Queriable::Shared(shared_signal, _) => {
+            unit.placement.get_shared_placement(shared_signal)?
+        }
+        _ => return None,
+    };
+    let rotation = match offset {
+        ExposeOffset::First => placement.rotation,
+        ExposeOffset::Last => {
+            placement.rotation
+                + ((unit.num_steps - 1) as i32) * (unit.placement.first_step_height() as i32)
+        }
+        ExposeOffset::Step(step) => {
+            placement.rotation + (*step as i32) * (unit.placement.first_step_height() as i32)
+        }
+    };
+
+    Some((placement.column, rotation))
+}
+
 fn compile_fixed<F: Field + Hash, TraceArgs>(
     ast: &astCircuit<F, TraceArgs>,
     unit: &mut CompilationUnit<F>,
@@ -346,6 +567,7 @@ fn place_queriable<F: Clone>(
 
             unit.selector.next_expr(dest_step.uuid(), super_rotation)
         }
+        #[cfg(feature = "halo2")]
         Queriable::Halo2AdviceQuery(signal, rot) => {
             let annotation = if let Some(annotation) = unit.annotations.get(&signal.uuid()) {
                 format!("[{}, {}]", annotation, rot)
@@ -359,6 +581,7 @@ fn place_queriable<F: Clone>(
                 annotation,
             ))
         }
+        #[cfg(feature = "halo2")]
         Queriable::Halo2FixedQuery(signal, rot) => {
             let annotation = if let Some(annotation) = unit.annotations.get(&signal.uuid()) {
                 format!("[{}, {}]", annotation, rot)
@@ -376,6 +599,21 @@ fn place_queriable<F: Clone>(
     }
 }
 
+/// Transforms an expression of a step, rewritten with the rewrite rules and simplified if enabled
+/// in the compiler config.
+fn compile_expr<F: Field>(
+    unit: &CompilationUnit<F>,
+    step: &StepType<F>,
+    source: &PIR<F>,
+) -> PolyExpr<F> {
+    let expr = transform_expr(unit, step, &source.rewrite(&unit.rewrite_rules));
+    if unit.simplify {
+        expr.simplify()
+    } else {
+        expr
+    }
+}
+
 fn transform_expr<F: Clone>(
     unit: &CompilationUnit<F>,
     step: &StepType<F>,
@@ -396,7 +634,9 @@ fn transform_expr<F: Clone>(
         Expr::Neg(v) => PolyExpr::Neg(Box::new(transform_expr(unit, step, &v))),
         Expr::Pow(v, exp) => PolyExpr::Pow(Box::new(transform_expr(unit, step, &v)), exp),
         Expr::Query(q) => place_queriable(unit, step, q),
+        #[cfg(feature = "halo2")]
         Expr::Halo2Expr(expr) => PolyExpr::Halo2Expr(expr),
+        Expr::Challenge(id, phase) => PolyExpr::Challenge(id, phase),
         Expr::MI(_) => panic!("mi elimination not done"),
     }
 }
@@ -419,6 +659,7 @@ fn place_fixed_assignments<F: Field>(
                     .get_fixed_placement(&fixed)
                     .expect("fixed placement not found")
             }
+            #[cfg(feature = "halo2")]
             Queriable::Halo2FixedQuery(signal, rot) => SignalPlacement::new(
                 unit.find_halo2_fixed(signal).expect("column not found"),
                 rot,
@@ -499,7 +740,11 @@ fn add_q_last<F: Field>(
     if let Some(step_uuid) = step_uuid {
         let step = unit.step_types.get(&step_uuid).expect("step not found");
 
-        let poly = q_last.query(0, "q_last".to_owned()) * unit.selector.unselect(step.uuid());
+        let mut poly = q_last.query(0, "q_last".to_owned()) * unit.selector.unselect(step.uuid());
+        // with padding, the last row is a padding step unless the trace has all the steps
+        if let Some(padding_step) = unit.padding_step.filter(|padding| *padding != step_uuid) {
+            poly = poly * unit.selector.unselect(padding_step);
+        }
 
         unit.polys.push(Poly {
             annotation: "q_last".to_string(),
@@ -523,6 +768,95 @@ fn add_q_last_to_constraint<F: Field>(
     not_q_last_expr * constraint
 }
 
+/// Disables a transition constraint of `step_uuid` when the next step is a padding one, so the last
+/// step instance of a padded trace behaves as the last row of the circuit.
+fn add_not_next_padding_to_constraint<F: Field>(
+    unit: &CompilationUnit<F>,
+    step_uuid: StepTypeUUID,
+    constraint: PolyExpr<F>,
+) -> PolyExpr<F> {
+    match unit.padding_step {
+        Some(padding_step) if padding_step != step_uuid => {
+            let next_padding = unit
+                .selector
+                .next_expr(padding_step, unit.placement.step_height(step_uuid));
+
+            (PolyExpr::Const(F::ONE) + (-next_padding)) * constraint
+        }
+        _ => constraint,
+    }
+}
+
+/// Constrains the padding steps to be at the end of the trace, right after the last step type if
+/// any, and to keep the forward and shared signals of the previous step.
+fn add_padding<F: Field>(unit: &mut CompilationUnit<F>, padding_step: StepTypeUUID) {
+    let last_step = unit
+        .last_step
+        .clone()
+        .expect("last column not found")
+        .0
+        .filter(|last_step| *last_step != padding_step);
+
+    for step in unit.step_types.clone().values() {
+        let next_padding = unit
+            .selector
+            .next_expr(padding_step, unit.placement.step_height(step.uuid()));
+        let not_next_padding = PolyExpr::Const(F::ONE) + (-next_padding.clone());
+
+        let (annotation, constraint) = if step.uuid() == padding_step {
+            ("padding is followed by padding", not_next_padding)
+        } else if Some(step.uuid()) == last_step {
+            ("last step is followed by padding", not_next_padding)
+        } else if last_step.is_some() {
+            ("only the last step is followed by padding", next_padding)
+        } else {
+            continue;
+        };
+
+        let poly = unit.selector.select(step.uuid(), &constraint);
+        let poly = add_q_last_to_constraint(unit, poly);
+        unit.polys.push(Poly {
+            annotation: annotation.to_string(),
+            expr: poly,
+        });
+    }
+
+    let height = unit.placement.first_step_height() as i32;
+    let placements: Vec<(String, SignalPlacement)> = unit
+        .forward_signals
+        .iter()
+        .map(|signal| {
+            let placement = unit
+                .placement
+                .get_forward_placement(signal)
+                .expect("forward placement not found");
+            (signal.annotation(), placement)
+        })
+        .chain(unit.shared_signals.iter().map(|signal| {
+            let placement = unit
+                .placement
+                .get_shared_placement(signal)
+                .expect("shared placement not found");
+            (signal.annotation(), placement)
+        }))
+        .collect();
+
+    for (annotation, placement) in placements {
+        let current = placement
+            .column
+            .query(placement.rotation, annotation.clone());
+        let previous = placement
+            .column
+            .query(placement.rotation - height, format!("prev({})", annotation));
+        let poly = unit.selector.select(padding_step, &(current + (-previous)));
+
+        unit.polys.push(Poly {
+            annotation: format!("padding keeps {}", annotation),
+            expr: poly,
+        });
+    }
+}
+
 fn add_default_columns<F>(unit: &mut CompilationUnit<F>) {
     if let Some(q_enable) = &unit.q_enable {
         unit.columns.push(q_enable.clone())
@@ -537,6 +871,7 @@ fn add_default_columns<F>(unit: &mut CompilationUnit<F>) {
     }
 }
 
+#[cfg(feature = "halo2")]
 fn add_halo2_columns<F, TraceArgs>(unit: &mut CompilationUnit<F>, ast: &astCircuit<F, TraceArgs>) {
     let halo2_advice_columns: Vec<Column> = ast
         .halo2_advice
@@ -571,6 +906,7 @@ mod test {
     use halo2_proofs::{halo2curves::bn256::Fr, plonk::Any};
 
     use super::{cell_manager::SingleRowCellManager, step_selector::SimpleStepSelectorBuilder, *};
+    use std::collections::HashMap;
 
     #[test]
     fn test_compiler_config_initialization() {
@@ -637,6 +973,616 @@ mod test {
         compile_phase2(&mut unit);
     }
 
+    type Counter = (u64, bool);
+
+    fn counter_circuit() -> astCircuit<Fr, Counter> {
+        use crate::frontend::dsl::{cb::eq, circuit};
+
+        circuit("counter", |ctx| {
+            let count = ctx.forward("count");
+
+            let start = ctx.step_type_def("start", |ctx| {
+                ctx.setup(move |ctx| {
+                    ctx.constr(eq(count, 0));
+                    ctx.transition(eq(count + 1, count.next()));
+                });
+                ctx.wg(move |ctx, _: ()| ctx.assign(count, Fr::ZERO))
+            });
+            let inc = ctx.step_type_def("inc", |ctx| {
+                ctx.setup(move |ctx| ctx.transition(eq(count + 1, count.next())));
+                ctx.wg(move |ctx, value: u64| ctx.assign(count, Fr::from(value)))
+            });
+            let done = ctx.step_type_def("done", |ctx| {
+                ctx.wg(move |ctx, value: u64| ctx.assign(count, Fr::from(value)))
+            });
+            let pad = ctx.step_type_def("pad", |ctx| ctx.wg(|_, _: ()| {}));
+
+            ctx.pragma_first_step(&start);
+            ctx.pragma_last_step(&done);
+            ctx.pragma_padding_step(&pad);
+            ctx.pragma_num_steps(8);
+            ctx.expose(count, ExposeOffset::Last);
+
+            ctx.trace(move |ctx, (n, skip_done): Counter| {
+                ctx.add(&start, ());
+                for i in 1..n {
+                    ctx.add(&inc, i);
+                }
+                if !skip_done {
+                    ctx.add(&done, n);
+                }
+            });
+        })
+    }
+
+    fn eval_at(
+        circuit: &Circuit<Fr>,
+        assignments: &Assignments<Fr>,
+        expr: &PolyExpr<Fr>,
+        row: usize,
+        num_rows: usize,
+    ) -> Fr {
+        let eval = |expr| eval_at(circuit, assignments, expr, row, num_rows);
+        match expr {
+            Expr::Const(v) => *v,
+            Expr::Sum(ses) => ses.iter().fold(Fr::ZERO, |acc, se| acc + eval(se)),
+            Expr::Mul(ses) => ses.iter().fold(Fr::ONE, |acc, se| acc * eval(se)),
+            Expr::Neg(se) => -eval(se),
+            Expr::Query((column, rot, _)) => {
+                let row = (row as i32 + rot).rem_euclid(num_rows as i32) as usize;
+                assignments
+                    .get(column)
+                    .or(circuit.fixed_assignments.get(column))
+                    .map(|values| values[row])
+                    .unwrap_or(Fr::ZERO)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn failing_polys(circuit: &Circuit<Fr>, assignments: &Assignments<Fr>) -> Vec<String> {
+        circuit
+            .polys
+            .iter()
+            .filter(|poly| {
+                (0..8).any(|row| eval_at(circuit, assignments, &poly.expr, row, 8) != Fr::ZERO)
+            })
+            .map(|poly| poly.annotation.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_padding_step() {
+        let config = config(SingleRowCellManager {}, SimpleStepSelectorBuilder {});
+        let ast = counter_circuit();
+        let (circuit, assignment_generator) = compile(config, &ast);
+        let assignment_generator = assignment_generator.unwrap();
+
+        for n in [1, 3, 7] {
+            let assignments = assignment_generator.generate((n, false));
+            assert!(failing_polys(&circuit, &assignments).is_empty(), "{}", n);
+
+            let (column, rot) = &circuit.exposed[0];
+            assert_eq!(assignments[column][*rot as usize], Fr::from(n));
+        }
+
+        let assignments = assignment_generator.generate((3, true));
+        assert!(failing_polys(&circuit, &assignments)
+            .contains(&"only the last step is followed by padding".to_string()));
+
+        let mut witness = assignment_generator.generate_trace_witness((3, false));
+        witness.pad(ast.padding_step.unwrap(), 8);
+        let count = *witness.step_instances[0].assignments.keys().next().unwrap();
+        witness.step_instances[6].assign(count, Fr::from(4));
+        let assignments = assignment_generator.generate_with_witness(witness);
+        assert!(failing_polys(&circuit, &assignments)
+            .iter()
+            .any(|annotation| annotation.starts_with("padding keeps")));
+    }
+
+    #[test]
+    fn test_gadget_call() {
+        use crate::frontend::dsl::circuit;
+
+        let ast: astCircuit<Fr, ()> = circuit("square", |ctx| {
+            let square = ctx.step_type_def("square", |ctx| {
+                let x = ctx.internal("x");
+                let y = ctx.internal("y");
+                ctx.setup(move |ctx| ctx.gadget_with_max_calls("square", [x], [y], 4));
+                ctx.wg(move |ctx, value: u64| {
+                    ctx.assign(x, Fr::from(value));
+                    ctx.assign(y, Fr::from(value * value));
+                })
+            });
+            let skip = ctx.step_type_def("skip", |ctx| {
+                let x = ctx.internal("x");
+                ctx.wg(move |ctx, value: u64| ctx.assign(x, Fr::from(value)))
+            });
+
+            ctx.pragma_num_steps(8);
+            ctx.trace(move |ctx, _| {
+                for value in 0..4 {
+                    ctx.add(&square, value + 2);
+                    ctx.add(&skip, 7);
+                }
+            });
+        });
+        let config = config(SingleRowCellManager {}, SimpleStepSelectorBuilder {});
+        let (circuit, assignment_generator) = compile(config, &ast);
+
+        assert_eq!(circuit.gadget_calls.len(), 1);
+        let call = &circuit.gadget_calls[0];
+        assert_eq!(call.gadget, "square");
+        assert_eq!(call.inputs.len(), 1);
+        assert_eq!(call.input_columns.len(), 1);
+        assert_eq!(call.output_columns.len(), 1);
+        assert_eq!(call.calls, 4);
+        assert_eq!(call.rows, (0..8).collect::<Vec<usize>>());
+        assert_eq!(
+            circuit.fixed_assignments[&call.enable],
+            [[Fr::ONE; 4], [Fr::ZERO; 4]].concat()
+        );
+
+        // the halo2 backend assigns the call table with the cells of the calls, one per step of
+        // the step type
+        let mut assignments = assignment_generator.unwrap().generate(());
+        let values: Vec<Fr> = (2..6u64).map(Fr::from).collect();
+        let squares = values.iter().map(|value| value * value).collect();
+        assignments.insert(call.input_columns[0].clone(), values);
+        assignments.insert(call.output_columns[0].clone(), squares);
+        assert!(failing_polys(&circuit, &assignments).is_empty());
+        assert!(failing_lookups(&circuit, &assignments).is_empty());
+
+        assignments.get_mut(&call.output_columns[0]).unwrap()[3] = Fr::from(10);
+        assert_eq!(
+            failing_lookups(&circuit, &assignments),
+            vec!["square::gadget square calls".to_string()]
+        );
+    }
+
+    fn failing_lookups(circuit: &Circuit<Fr>, assignments: &Assignments<Fr>) -> Vec<String> {
+        let eval = |exprs: Vec<&PolyExpr<Fr>>, row| -> Vec<Fr> {
+            exprs
+                .into_iter()
+                .map(|expr| eval_at(circuit, assignments, expr, row, 8))
+                .collect()
+        };
+
+        circuit
+            .lookups
+            .iter()
+            .filter(|lookup| {
+                let (src, dest): (Vec<_>, Vec<_>) =
+                    lookup.exprs.iter().map(|(src, dest)| (src, dest)).unzip();
+                let table: Vec<Vec<Fr>> = (0..8).map(|row| eval(dest.clone(), row)).collect();
+                (0..8).any(|row| !table.contains(&eval(src.clone(), row)))
+            })
+            .map(|lookup| lookup.annotation.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_generate_chunks() {
+        let config = config(SingleRowCellManager {}, SimpleStepSelectorBuilder {});
+        let (_, assignment_generator) = compile(config, &counter_circuit());
+        let assignment_generator = assignment_generator.unwrap();
+
+        let witness = assignment_generator.generate_trace_witness((3, false));
+        let expected = assignment_generator.generate_with_witness(witness.clone());
+
+        let mut starts = Vec::new();
+        let mut stitched: HashMap<Column, Vec<Fr>> = HashMap::new();
+        assignment_generator
+            .generate_chunks(witness.step_instances.into_iter(), 3, |start, chunk| {
+                starts.push(start);
+                for (column, values) in chunk.0 {
+                    let column_values = stitched.entry(column).or_insert(vec![Fr::ZERO; 8]);
+                    column_values[start..start + values.len()].copy_from_slice(&values);
+                }
+            })
+            .unwrap();
+
+        assert_eq!(starts, vec![0, 3, 6]);
+        assert_eq!(stitched, expected.0);
+    }
+
+    #[test]
+    fn test_generate_chunks_errors() {
+        use crate::{
+            frontend::dsl::{cb::eq, circuit},
+            plonkish::ir::assignments::ChunkError,
+            wit_gen::StepInstance,
+        };
+
+        let ast: astCircuit<Fr, ()> = circuit("shared", |ctx| {
+            let value = ctx.shared("value");
+            let step = ctx.step_type_def("step", |ctx| {
+                ctx.setup(move |ctx| ctx.constr(eq(value, 1)));
+                ctx.wg(move |ctx, _: ()| ctx.assign(value, Fr::ONE))
+            });
+            ctx.pragma_num_steps(4);
+            ctx.trace(move |ctx, _| {
+                for _ in 0..4 {
+                    ctx.add(&step, ());
+                }
+            });
+        });
+        let config = config(SingleRowCellManager {}, SimpleStepSelectorBuilder {});
+        let (_, assignment_generator) = compile(config, &ast);
+        let assignment_generator = assignment_generator.unwrap();
+        let witness = assignment_generator.generate_trace_witness(());
+        let chunks = |step_instances: Vec<StepInstance<Fr>>, chunk_steps| {
+            assignment_generator.generate_chunks(step_instances.into_iter(), chunk_steps, |_, _| {})
+        };
+
+        assert_eq!(
+            chunks(witness.step_instances.clone(), 0),
+            Err(ChunkError::EmptyChunks)
+        );
+
+        let mut too_many = witness.step_instances.clone();
+        too_many.push(too_many[0].clone());
+        assert_eq!(
+            chunks(too_many, 2),
+            Err(ChunkError::TooManySteps { step: 4 })
+        );
+
+        // the previous value of the shared signal in the third step is in the first chunk
+        let mut previous = witness.step_instances;
+        previous[2].assign(Queriable::Shared(ast.shared_signals[0], -1), Fr::ONE);
+        assert_eq!(chunks(previous.clone(), 3), Ok(()));
+        assert_eq!(
+            chunks(previous, 2),
+            Err(ChunkError::PreviousChunk {
+                step: 2,
+                row: 1,
+                chunk_start: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_generate_profiled() {
+        let config = config(SingleRowCellManager {}, SimpleStepSelectorBuilder {});
+        let ast = counter_circuit();
+        let (_, assignment_generator) = compile(config, &ast);
+        let assignment_generator = assignment_generator.unwrap();
+
+        let (assignments, profile) = assignment_generator.generate_profiled((3, false));
+        assert_eq!(assignments.0, assignment_generator.generate((3, false)).0);
+
+        let step = |name: &str| {
+            let uuid = ast
+                .step_types
+                .values()
+                .find(|step_type| step_type.name() == name)
+                .unwrap()
+                .uuid();
+            profile.steps[&uuid].clone()
+        };
+        assert_eq!(step("start").instances, 1);
+        assert_eq!(step("inc").instances, 2);
+        assert_eq!(step("inc").annotation, "inc");
+        assert_eq!(step("done").rows, 1);
+        assert_eq!(step("pad").instances, 4);
+        assert_eq!(step("pad").rows, 4);
+        assert_eq!(step("pad").trace_time, std::time::Duration::ZERO);
+        assert_eq!(
+            profile.steps.values().map(|step| step.rows).sum::<usize>(),
+            8
+        );
+        assert!(format!("{}", profile).starts_with("step type"));
+    }
+
+    #[test]
+    fn test_generate_sparse() {
+        use crate::plonkish::ir::assignments::SparseAssignments;
+
+        let config = config(SingleRowCellManager {}, SimpleStepSelectorBuilder {});
+        let (_, assignment_generator) = compile(config, &counter_circuit());
+        let assignment_generator = assignment_generator.unwrap();
+
+        let witness = assignment_generator.generate_trace_witness((3, false));
+        let dense = assignment_generator.generate_with_witness(witness.clone());
+        let sparse = assignment_generator.generate_sparse_with_witness(witness);
+
+        assert_eq!(sparse.num_rows(), 8);
+        assert_eq!(
+            sparse.len(),
+            dense
+                .values()
+                .flatten()
+                .filter(|value| **value != Fr::ZERO)
+                .count()
+        );
+        for (column, values) in dense.iter() {
+            assert_eq!(
+                sparse.dense(column).unwrap_or(vec![Fr::ZERO; 8]),
+                *values,
+                "column {}",
+                column.annotation
+            );
+        }
+        assert_eq!(
+            SparseAssignments::from_dense(&dense, 8).to_dense().0,
+            sparse.to_dense().0
+        );
+    }
+
+    #[test]
+    fn test_streaming_assignments() {
+        use crate::plonkish::ir::assignments::StreamingAssignments;
+
+        let config = config(SingleRowCellManager {}, SimpleStepSelectorBuilder {});
+        let (_, assignment_generator) = compile(config, &counter_circuit());
+        let assignment_generator = assignment_generator.unwrap();
+
+        let witness = assignment_generator.generate_trace_witness((3, false));
+        let expected = assignment_generator.generate_with_witness(witness.clone());
+
+        let mut streaming = StreamingAssignments::new(assignment_generator.assigner());
+        let mut step_instances = witness.step_instances.into_iter();
+        streaming.add_steps(step_instances.by_ref().take(2));
+        streaming.add_steps(step_instances);
+        assert_eq!(streaming.steps(), 3);
+        assert_eq!(streaming.finish().0, expected.0);
+    }
+
+    #[test]
+    fn test_incremental_assignments() {
+        use crate::plonkish::ir::assignments::IncrementalAssignments;
+
+        let config = config(SingleRowCellManager {}, SimpleStepSelectorBuilder {});
+        let ast = counter_circuit();
+        let (_, assignment_generator) = compile(config, &ast);
+        let assignment_generator = assignment_generator.unwrap();
+        let count = Queriable::Forward(ast.forward_signals[0], false);
+
+        let mut witness = assignment_generator.generate_trace_witness((3, false));
+        let mut incremental = IncrementalAssignments::new(&assignment_generator, witness.clone());
+        assert_eq!(
+            incremental.assignments().0,
+            assignment_generator
+                .generate_with_witness(witness.clone())
+                .0
+        );
+
+        let mut changes = Vec::new();
+        for (index, value) in [(2, 5), (3, 9)] {
+            let mut step_instance = witness.step_instances[index].clone();
+            step_instance.assign(count, Fr::from(value));
+            witness.step_instances[index] = step_instance.clone();
+            changes.push((index, step_instance));
+        }
+        incremental.update(changes);
+
+        assert_eq!(incremental.witness().step_instances.len(), 8);
+        assert_eq!(
+            incremental.witness().step_instances[7].assignments[&count],
+            Fr::from(9)
+        );
+        assert_eq!(
+            incremental.into_assignments().0,
+            assignment_generator.generate_with_witness(witness).0
+        );
+    }
+
+    #[test]
+    fn test_reconstruct_witness() {
+        let config = config(SingleRowCellManager {}, SimpleStepSelectorBuilder {});
+        let ast = counter_circuit();
+        let (_, assignment_generator) = compile(config, &ast);
+        let assignment_generator = assignment_generator.unwrap();
+        let count = Queriable::Forward(ast.forward_signals[0], false);
+
+        let assignments = assignment_generator.generate((3, false));
+        let witness = assignment_generator.reconstruct_witness(&assignments, 8);
+
+        let step_types: Vec<_> = witness
+            .step_instances
+            .iter()
+            .map(|step_instance| {
+                ast.annotation(step_instance.step_type_uuid)
+                    .unwrap()
+                    .as_str()
+            })
+            .collect();
+        assert_eq!(
+            step_types,
+            vec!["start", "inc", "inc", "done", "pad", "pad", "pad", "pad"]
+        );
+        assert_eq!(witness.step_instances[2].assignments[&count], Fr::from(2));
+        assert_eq!(
+            assignment_generator.generate_with_witness(witness).0,
+            assignments.0
+        );
+        assert_eq!(
+            assignment_generator
+                .reconstruct_witness(&assignments, 2)
+                .step_instances
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_unassigned_signals() {
+        use crate::frontend::dsl::circuit;
+
+        let ast: astCircuit<Fr, ()> = circuit("unassigned", |ctx| {
+            let step = ctx.step_type_def("step", |ctx| {
+                let a = ctx.internal("a");
+                ctx.internal("b");
+                ctx.wg(move |ctx, _: ()| ctx.assign(a, Fr::ONE))
+            });
+            ctx.pragma_num_steps(2);
+            ctx.trace(move |ctx, _| {
+                ctx.add(&step, ());
+                ctx.add(&step, ());
+            });
+        });
+        let config = config(SingleRowCellManager {}, SimpleStepSelectorBuilder {});
+        let (_, assignment_generator) = compile(config, &ast);
+        let assignment_generator = assignment_generator
+            .unwrap()
+            .with_unassigned_default(Fr::from(7));
+
+        let witness = assignment_generator.generate_trace_witness(());
+        let unassigned = assignment_generator.unassigned_signals(witness.clone());
+        assert_eq!(
+            unassigned.iter().map(|u| u.step).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+        assert_eq!(
+            unassigned[0].to_string(),
+            format!(
+                "step 0 (step type {}) does not assign b",
+                unassigned[0].step_type_uuid
+            )
+        );
+
+        let assignments = assignment_generator.generate_with_witness(witness);
+        assert!(assignments
+            .values()
+            .any(|values| values == &vec![Fr::from(7), Fr::from(7)]));
+    }
+
+    #[test]
+    fn test_disable_feature() {
+        use crate::frontend::dsl::{cb::eq, circuit};
+
+        let ast: astCircuit<Fr, ()> = circuit("features", |ctx| {
+            let a = ctx.forward("a");
+            ctx.step_type_def("step", |ctx| {
+                ctx.setup(move |ctx| {
+                    ctx.constr(eq(a, 1));
+                    ctx.feature("debug", |ctx| ctx.constr(eq(a * a, 1)));
+                });
+                ctx.wg(move |ctx, _: ()| ctx.assign(a, Fr::ONE))
+            });
+        });
+        let annotations = |config| -> Vec<String> {
+            let (circuit, _) = compile(config, &ast);
+            circuit
+                .polys
+                .iter()
+                .map(|poly| poly.annotation.split(" at ").next().unwrap().to_string())
+                .collect()
+        };
+        let base = config(SingleRowCellManager {}, SimpleStepSelectorBuilder {});
+
+        assert_eq!(annotations(base.clone()).len(), 2);
+        assert_eq!(
+            annotations(base.clone().disable_feature("debug")),
+            vec!["step::a == 0x1"]
+        );
+        assert_eq!(
+            annotations(base.disable_feature("debug").enable_feature("debug")).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_challenge() {
+        use crate::frontend::dsl::{cb::eq, circuit};
+
+        let ast: astCircuit<Fr, ()> = circuit("challenge", |ctx| {
+            let a = ctx.forward("a");
+            let b = ctx.forward_with_phase("b", 1);
+            let r = ctx.challenge("r", 0);
+            ctx.step_type_def("step", |ctx| {
+                ctx.setup(move |ctx| ctx.constr(eq(b, r.clone() * a)));
+                ctx.wg(move |ctx, _: ()| ctx.assign(a, Fr::ONE))
+            });
+        });
+        let (circuit, _) = compile(
+            config(SingleRowCellManager {}, SimpleStepSelectorBuilder {}),
+            &ast,
+        );
+
+        assert!(format!("{:?}", circuit.polys[0].expr).contains("challenge0("));
+    }
+
+    #[test]
+    fn test_intern() {
+        use crate::frontend::dsl::{cb::eq, circuit};
+
+        let ast: astCircuit<Fr, ()> = circuit("intern", |ctx| {
+            let a = ctx.forward("a");
+            ctx.step_type_def("step", |ctx| {
+                ctx.setup(move |ctx| {
+                    ctx.constr(eq(a * a, 1));
+                    ctx.constr(eq(a * a, 2));
+                });
+                ctx.wg(move |ctx, _: ()| ctx.assign(a, Fr::ONE))
+            });
+        });
+        let (circuit, _) = compile(
+            config(SingleRowCellManager {}, SimpleStepSelectorBuilder {}),
+            &ast,
+        );
+        let interned = intern(&circuit);
+
+        assert_eq!(interned.polys.len(), circuit.polys.len());
+        for (poly, id) in circuit.polys.iter().zip(interned.polys.iter()) {
+            assert_eq!(
+                format!("{:?}", interned.arena.expr(*id)),
+                format!("{:?}", poly.expr)
+            );
+        }
+        assert!(!interned.arena.common_subexprs().is_empty());
+    }
+
+    #[test]
+    fn test_simplify() {
+        use crate::frontend::dsl::{cb::eq, circuit};
+
+        let ast: astCircuit<Fr, ()> = circuit("simplify", |ctx| {
+            let a = ctx.forward("a");
+            ctx.step_type_def("step", |ctx| {
+                ctx.setup(move |ctx| ctx.constr(eq(a * 2 * 3, 1)));
+                ctx.wg(move |ctx, _: ()| ctx.assign(a, Fr::ONE))
+            });
+        });
+        let poly = |config| -> String {
+            let (circuit, _) = compile(config, &ast);
+            format!("{:?}", circuit.polys[0].expr)
+        };
+        let base = config(SingleRowCellManager {}, SimpleStepSelectorBuilder {});
+
+        assert!(poly(base.clone()).contains("0x2"));
+        assert!(!poly(base.clone().simplify()).contains("0x2"));
+        assert!(poly(base.simplify()).contains("0x6"));
+    }
+
+    #[test]
+    fn test_rewrite_rules() {
+        use crate::{
+            frontend::dsl::{cb::eq, circuit},
+            poly::rewrite::var,
+        };
+
+        let ast: astCircuit<Fr, ()> = circuit("rewrite", |ctx| {
+            let a = ctx.forward("a");
+            ctx.step_type_def("step", |ctx| {
+                ctx.setup(move |ctx| ctx.constr(eq(a * a, 1)));
+                ctx.wg(move |ctx, _: ()| ctx.assign(a, Fr::ONE))
+            });
+        });
+        let square = RewriteRule::new(
+            "square",
+            var::<Fr>("x") * var("x"),
+            Expr::Pow(Box::new(var("x")), 2),
+        );
+        let (circuit, _) = compile(
+            config(SingleRowCellManager {}, SimpleStepSelectorBuilder {})
+                .rewrite_rules(vec![square]),
+            &ast,
+        );
+
+        assert!(format!("{:?}", circuit.polys[0].expr).contains(")^2"));
+    }
+
     #[test]
     fn test_add_default_columns() {
         let mock_ast_circuit = astCircuit::<Fr, Any>::default();
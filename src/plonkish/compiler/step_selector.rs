@@ -1,5 +1,6 @@
 use std::{collections::HashMap, rc::Rc};
 
+#[cfg(feature = "halo2")]
 use halo2_proofs::plonk::{Advice, Column as Halo2Column};
 
 use crate::{
@@ -37,6 +38,16 @@ impl<F: Clone> StepSelector<F> {
         PolyExpr::Mul(vec![selector.clone(), constraint.clone()])
     }
 
+    /// Returns the maximum degree of the step selectors, to compute the degree of the compiled
+    /// constraints with `SBPIR::max_degree`.
+    pub fn degree(&self) -> usize {
+        self.selector_expr
+            .values()
+            .map(|selector| selector.degree())
+            .max()
+            .unwrap_or(0)
+    }
+
     pub fn next_expr(&self, step_uuid: StepTypeUUID, step_height: u32) -> PolyExpr<F> {
         let selector = self.selector_expr.get(&step_uuid).expect("step not found");
 
@@ -107,6 +118,8 @@ impl StepSelectorBuilder for SimpleStepSelectorBuilder {
 
 #[derive(Debug, Default, Clone)]
 pub struct TwoStepsSelectorBuilder {
+    /// Imported halo2 advice column to use as the selector, instead of a new one.
+    #[cfg(feature = "halo2")]
     pub halo2_column: Option<Halo2Column<Advice>>,
     pub hint_one: Option<String>,
 }
@@ -144,10 +157,16 @@ impl StepSelectorBuilder for TwoStepsSelectorBuilder {
             )
         };
 
-        let column = match self.halo2_column {
-            Some(advice) => unit
-                .find_halo2_advice_native(advice)
-                .expect("column not found"),
+        #[cfg(feature = "halo2")]
+        let imported = self.halo2_column.map(|advice| {
+            unit.find_halo2_advice_native(advice)
+                .expect("column not found")
+        });
+        #[cfg(not(feature = "halo2"))]
+        let imported = None;
+
+        let column = match imported {
+            Some(column) => column,
             None => {
                 let column = Column::advice("step selector for two steps", 0);
                 unit.selector.columns.push(column.clone());
@@ -1,13 +1,21 @@
 use core::fmt::Debug;
-use std::{collections::HashMap, rc::Rc};
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
 
+#[cfg(feature = "halo2")]
 use halo2_proofs::plonk::{Advice, Column as Halo2Column};
 
+#[cfg(feature = "halo2")]
+use crate::sbpir::{ImportedHalo2Advice, ImportedHalo2Fixed};
 use crate::{
-    plonkish::ir::{assignments::Assignments, Circuit, Column, ColumnType, Poly, PolyLookup},
+    plonkish::ir::{
+        assignments::Assignments, Circuit, Column, ColumnType, GadgetCall, Poly, PolyLookup,
+    },
+    poly::rewrite::RewriteRule,
     sbpir::{
-        FixedSignal, ForwardSignal, ImportedHalo2Advice, ImportedHalo2Fixed, SharedSignal,
-        StepType, StepTypeUUID, SBPIR as astCircuit,
+        FixedSignal, ForwardSignal, SharedSignal, StepType, StepTypeUUID, SBPIR as astCircuit,
     },
     util::{uuid, UUID},
 };
@@ -35,6 +43,10 @@ pub struct CompilationUnit<F> {
     pub q_enable: Option<Column>,
     pub first_step: Option<(StepTypeUUID, Column)>,
     pub last_step: Option<(Option<StepTypeUUID>, Column)>,
+    pub padding_step: Option<StepTypeUUID>,
+    pub disabled_features: HashSet<String>,
+    pub simplify: bool,
+    pub rewrite_rules: Rc<Vec<RewriteRule<F>>>,
 
     pub num_rows: usize,
 
@@ -43,6 +55,11 @@ pub struct CompilationUnit<F> {
 
     pub fixed_assignments: Assignments<F>,
 
+    /// Copy constraints to cells of other sub-circuits, see `Circuit::copies`.
+    pub copies: Vec<((Column, i32), (Column, i32))>,
+
+    pub gadget_calls: Vec<GadgetCall<F>>,
+
     pub ast_id: UUID,
     pub uuid: UUID,
 
@@ -71,6 +88,10 @@ impl<F> Default for CompilationUnit<F> {
             q_enable: Default::default(),
             first_step: Default::default(),
             last_step: Default::default(),
+            padding_step: Default::default(),
+            disabled_features: Default::default(),
+            simplify: false,
+            rewrite_rules: Default::default(),
 
             num_rows: Default::default(),
 
@@ -79,6 +100,10 @@ impl<F> Default for CompilationUnit<F> {
 
             fixed_assignments: Default::default(),
 
+            copies: Default::default(),
+
+            gadget_calls: Default::default(),
+
             ast_id: Default::default(),
             uuid: uuid(),
 
@@ -91,6 +116,15 @@ impl<F> Default for CompilationUnit<F> {
 }
 
 impl<F> CompilationUnit<F> {
+    /// Returns if the constraints of a feature, or without one, are compiled.
+    pub(super) fn is_enabled(&self, feature: &Option<String>) -> bool {
+        match feature {
+            Some(feature) => !self.disabled_features.contains(feature),
+            None => true,
+        }
+    }
+
+    #[cfg(feature = "halo2")]
     pub(super) fn find_halo2_advice(&self, to_find: ImportedHalo2Advice) -> Option<Column> {
         for column in self.columns.iter() {
             if let Some(advice) = column.halo2_advice {
@@ -110,6 +144,7 @@ impl<F> CompilationUnit<F> {
         None
     }
 
+    #[cfg(feature = "halo2")]
     pub(super) fn find_halo2_advice_native(&self, to_find: Halo2Column<Advice>) -> Option<Column> {
         for column in self.columns.iter() {
             if let Some(advice) = column.halo2_advice {
@@ -129,6 +164,7 @@ impl<F> CompilationUnit<F> {
         None
     }
 
+    #[cfg(feature = "halo2")]
     pub(super) fn find_halo2_fixed(&self, to_find: ImportedHalo2Fixed) -> Option<Column> {
         for column in self.columns.iter() {
             if let Some(fixed) = column.halo2_fixed {
@@ -203,14 +239,7 @@ impl<F> CompilationUnit<F> {
 impl<F, TraceArgs> From<&astCircuit<F, TraceArgs>> for CompilationUnit<F> {
     fn from(ast: &astCircuit<F, TraceArgs>) -> Self {
         CompilationUnit::<F> {
-            annotations: {
-                let mut acc = ast.annotations.clone();
-                for step in ast.step_types.values() {
-                    acc.extend(step.annotations.clone());
-                }
-
-                acc
-            },
+            annotations: ast.aliased_annotations(),
             step_types: ast.step_types.clone(),
             forward_signals: ast.forward_signals.clone(),
             shared_signals: ast.shared_signals.clone(),
@@ -220,7 +249,9 @@ impl<F, TraceArgs> From<&astCircuit<F, TraceArgs>> for CompilationUnit<F> {
                 Some(Column {
                     annotation: "q_enable".to_owned(),
                     ctype: ColumnType::Fixed,
+                    #[cfg(feature = "halo2")]
                     halo2_advice: None,
+                    #[cfg(feature = "halo2")]
                     halo2_fixed: None,
                     phase: 0,
                     id: uuid(),
@@ -234,20 +265,27 @@ impl<F, TraceArgs> From<&astCircuit<F, TraceArgs>> for CompilationUnit<F> {
                     Column {
                         annotation: "q_first".to_owned(),
                         ctype: ColumnType::Fixed,
+                        #[cfg(feature = "halo2")]
                         halo2_advice: None,
+                        #[cfg(feature = "halo2")]
                         halo2_fixed: None,
                         phase: 0,
                         id: uuid(),
                     },
                 )
             }),
-            last_step: if ast.last_step.is_some() || Self::has_transition_constraints(ast) {
+            last_step: if ast.last_step.is_some()
+                || ast.padding_step.is_some()
+                || Self::has_transition_constraints(ast)
+            {
                 Some((
                     ast.last_step,
                     Column {
                         annotation: "q_last".to_owned(),
                         ctype: ColumnType::Fixed,
+                        #[cfg(feature = "halo2")]
                         halo2_advice: None,
+                        #[cfg(feature = "halo2")]
                         halo2_fixed: None,
                         phase: 0,
                         id: uuid(),
@@ -256,6 +294,7 @@ impl<F, TraceArgs> From<&astCircuit<F, TraceArgs>> for CompilationUnit<F> {
             } else {
                 None
             },
+            padding_step: ast.padding_step,
             ast_id: ast.id,
             ..Default::default()
         }
@@ -270,6 +309,9 @@ impl<F> From<CompilationUnit<F>> for Circuit<F> {
             polys: unit.polys,
             lookups: unit.lookups,
             fixed_assignments: unit.fixed_assignments,
+            num_rows: unit.num_rows,
+            copies: unit.copies,
+            gadget_calls: unit.gadget_calls,
             id: unit.uuid,
             ast_id: unit.ast_id,
         }
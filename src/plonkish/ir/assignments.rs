@@ -1,19 +1,19 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fmt,
     hash::Hash,
     ops::{Deref, DerefMut},
+    sync::Arc,
+    time::Instant,
 };
 
 use crate::{field::Field, wit_gen::AutoTraceGenerator};
 
-use halo2_proofs::plonk::{Advice, Column as Halo2Column};
-
 use crate::{
     plonkish::compiler::{cell_manager::Placement, step_selector::StepSelector},
     sbpir::{query::Queriable, ForwardSignal, SharedSignal, StepTypeUUID},
     util::UUID,
-    wit_gen::{StepInstance, TraceGenerator, TraceWitness},
+    wit_gen::{StepInstance, TraceGenerator, TraceWitness, WitnessProfile, WitnessSource},
 };
 
 use super::{Column, PolyExpr};
@@ -64,46 +64,144 @@ impl<F> DerefMut for Assignments<F> {
     }
 }
 
-pub struct AssignmentGenerator<F, TraceArgs> {
-    columns: Vec<Column>,
-    placement: Placement,
-    selector: StepSelector<F>,
-    trace_gen: TraceGenerator<F, TraceArgs>,
-    auto_trace_gen: AutoTraceGenerator<F>,
-
+/// Assignments that only store the cells with non zero values, to cut memory for traces that are
+/// mostly zeros, for instance with many padding steps. Columns are materialized dense on demand,
+/// one at a time, with `dense_columns`, or all at once with `to_dense`.
+#[derive(Debug, Clone)]
+pub struct SparseAssignments<F> {
     num_rows: usize,
+    columns: HashMap<Column, BTreeMap<usize, F>>,
+}
 
-    ir_id: UUID,
+impl<F: Field> SparseAssignments<F> {
+    pub fn new(num_rows: usize) -> Self {
+        Self {
+            num_rows,
+            columns: HashMap::new(),
+        }
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+
+    /// Returns the number of stored cells, all with non zero values.
+    pub fn len(&self) -> usize {
+        self.columns.values().map(|cells| cells.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the columns with some non zero value.
+    pub fn columns(&self) -> impl Iterator<Item = &Column> {
+        self.columns.keys()
+    }
+
+    pub fn get(&self, column: &Column, row: usize) -> F {
+        self.columns
+            .get(column)
+            .and_then(|cells| cells.get(&row))
+            .copied()
+            .unwrap_or(F::ZERO)
+    }
+
+    pub fn set(&mut self, column: &Column, row: usize, value: F) {
+        assert!(
+            row < self.num_rows,
+            "row {} out of {} rows",
+            row,
+            self.num_rows
+        );
+
+        if value == F::ZERO {
+            if let Some(cells) = self.columns.get_mut(column) {
+                cells.remove(&row);
+                if cells.is_empty() {
+                    self.columns.remove(column);
+                }
+            }
+        } else {
+            self.columns
+                .entry(column.clone())
+                .or_default()
+                .insert(row, value);
+        }
+    }
+
+    /// Returns the dense values of a column, or `None` if all of them are zero.
+    pub fn dense(&self, column: &Column) -> Option<Vec<F>> {
+        self.columns.get(column).map(|cells| {
+            let mut values = vec![F::ZERO; self.num_rows];
+            for (row, value) in cells.iter() {
+                values[*row] = *value;
+            }
+
+            values
+        })
+    }
+
+    /// Materializes the dense values of the columns lazily, one column at a time.
+    pub fn dense_columns(&self) -> impl Iterator<Item = (&Column, Vec<F>)> {
+        self.columns
+            .keys()
+            .map(|column| (column, self.dense(column).unwrap()))
+    }
+
+    /// Returns the dense assignments, with the columns with some non zero value.
+    pub fn to_dense(&self) -> Assignments<F> {
+        Assignments(
+            self.dense_columns()
+                .map(|(column, values)| (column.clone(), values))
+                .collect(),
+        )
+    }
+
+    /// Returns the sparse assignments of dense ones with `num_rows` rows.
+    pub fn from_dense(assignments: &Assignments<F>, num_rows: usize) -> Self {
+        let mut sparse = Self::new(num_rows);
+        for (column, values) in assignments.iter() {
+            for (row, value) in values.iter().enumerate() {
+                sparse.set(column, row, *value);
+            }
+        }
+
+        sparse
+    }
 }
 
-impl<F: Clone, TraceArgs> Clone for AssignmentGenerator<F, TraceArgs> {
+pub struct AssignmentGenerator<F, TraceArgs> {
+    trace_gen: TraceGenerator<F, TraceArgs>,
+    assigner: Arc<WitnessAssigner<F>>,
+}
+
+impl<F, TraceArgs> Clone for AssignmentGenerator<F, TraceArgs> {
     fn clone(&self) -> Self {
         Self {
-            columns: self.columns.clone(),
-            placement: self.placement.clone(),
-            selector: self.selector.clone(),
             trace_gen: self.trace_gen.clone(),
-            auto_trace_gen: self.auto_trace_gen.clone(),
-            num_rows: self.num_rows,
-            ir_id: self.ir_id,
+            assigner: self.assigner.clone(),
         }
     }
 }
 
-impl<F: Clone, TraceArgs> Default for AssignmentGenerator<F, TraceArgs> {
+impl<F: Field, TraceArgs> Default for AssignmentGenerator<F, TraceArgs> {
     fn default() -> Self {
         Self {
-            columns: Default::default(),
-            placement: Default::default(),
-            selector: Default::default(),
             trace_gen: Default::default(),
-            auto_trace_gen: Default::default(),
-            num_rows: Default::default(),
-            ir_id: Default::default(),
+            assigner: Default::default(),
         }
     }
 }
 
+impl<F, TraceArgs> Deref for AssignmentGenerator<F, TraceArgs> {
+    type Target = WitnessAssigner<F>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.assigner
+    }
+}
+
 impl<F: Field + Hash, TraceArgs> AssignmentGenerator<F, TraceArgs> {
     pub fn new(
         columns: Vec<Column>,
@@ -114,24 +212,56 @@ impl<F: Field + Hash, TraceArgs> AssignmentGenerator<F, TraceArgs> {
         num_rows: usize,
         ir_id: UUID,
     ) -> Self {
+        let layout = Layout::new(&columns, &placement, &selector);
+
         Self {
-            columns,
-            placement,
-            selector,
             trace_gen,
-            auto_trace_gen,
-            num_rows,
-            ir_id,
+            assigner: Arc::new(WitnessAssigner {
+                columns,
+                placement,
+                selector,
+                auto_trace_gen,
+                unassigned_default: F::ZERO,
+                warn_unassigned: false,
+                layout,
+                num_rows,
+                ir_id,
+            }),
         }
     }
 
+    /// Sets the value of the internal signals that a step instance does not assign, zero by
+    /// default.
+    pub fn with_unassigned_default(mut self, default: F) -> Self {
+        Arc::make_mut(&mut self.assigner).unassigned_default = default;
+
+        self
+    }
+
+    /// Prints a warning listing the internal signals that each step instance does not assign,
+    /// when they are assigned the default. See `unassigned_signals` to collect them instead.
+    pub fn with_unassigned_warnings(mut self) -> Self {
+        Arc::make_mut(&mut self.assigner).warn_unassigned = true;
+
+        self
+    }
+
     pub fn empty(ir_id: UUID) -> Self {
         Self {
-            ir_id,
-            ..Default::default()
+            trace_gen: Default::default(),
+            assigner: Arc::new(WitnessAssigner {
+                ir_id,
+                ..Default::default()
+            }),
         }
     }
 
+    /// Returns the part of the generator that assigns trace witnesses, which unlike the trace
+    /// generator can be shared with other threads.
+    pub fn assigner(&self) -> Arc<WitnessAssigner<F>> {
+        self.assigner.clone()
+    }
+
     pub fn generate_trace_witness(&self, args: TraceArgs) -> TraceWitness<F> {
         self.trace_gen.generate(args)
     }
@@ -142,80 +272,828 @@ impl<F: Field + Hash, TraceArgs> AssignmentGenerator<F, TraceArgs> {
         self.generate_with_witness(witness)
     }
 
+    /// Generates the assignments as `generate`, and reports the time spent and the rows assigned
+    /// by each step type, in the trace and in the assignments.
+    pub fn generate_profiled(&self, args: TraceArgs) -> (Assignments<F>, WitnessProfile) {
+        let (witness, mut profile) = self.trace_gen.generate_profiled(args);
+        let assignments = self.assign_witness(witness, Some(&mut profile));
+
+        (assignments, profile)
+    }
+
+    pub fn generate_sparse(&self, args: TraceArgs) -> SparseAssignments<F> {
+        let witness = self.generate_trace_witness(args);
+
+        self.generate_sparse_with_witness(witness)
+    }
+}
+
+/// Assigns trace witnesses to the columns of a circuit: the part of an `AssignmentGenerator` that
+/// does not run the trace, see `AssignmentGenerator::assigner`.
+pub struct WitnessAssigner<F> {
+    columns: Vec<Column>,
+    placement: Placement,
+    selector: StepSelector<F>,
+    auto_trace_gen: AutoTraceGenerator<F>,
+    unassigned_default: F,
+    warn_unassigned: bool,
+    layout: Layout<F>,
+
+    num_rows: usize,
+
+    ir_id: UUID,
+}
+
+impl<F: Clone> Clone for WitnessAssigner<F> {
+    fn clone(&self) -> Self {
+        Self {
+            columns: self.columns.clone(),
+            placement: self.placement.clone(),
+            selector: self.selector.clone(),
+            auto_trace_gen: self.auto_trace_gen.clone(),
+            unassigned_default: self.unassigned_default.clone(),
+            warn_unassigned: self.warn_unassigned,
+            layout: self.layout.clone(),
+            num_rows: self.num_rows,
+            ir_id: self.ir_id,
+        }
+    }
+}
+
+impl<F: Field> Default for WitnessAssigner<F> {
+    fn default() -> Self {
+        Self {
+            columns: Default::default(),
+            placement: Default::default(),
+            selector: Default::default(),
+            auto_trace_gen: Default::default(),
+            unassigned_default: F::ZERO,
+            warn_unassigned: false,
+            layout: Default::default(),
+            num_rows: Default::default(),
+            ir_id: Default::default(),
+        }
+    }
+}
+
+impl<F: Field + Hash> WitnessAssigner<F> {
     pub fn generate_with_witness(&self, witness: TraceWitness<F>) -> Assignments<F> {
+        self.assign_witness(witness, None)
+    }
+
+    /// Pads the witness and assigns its step instances, with their auto signals, recording them in
+    /// `profile` if given.
+    fn assign_witness(
+        &self,
+        mut witness: TraceWitness<F>,
+        mut profile: Option<&mut WitnessProfile>,
+    ) -> Assignments<F> {
         let mut offset: usize = 0;
-        let mut assignments: Assignments<F> = Default::default();
+        let mut values: Vec<Option<Vec<F>>> = vec![None; self.layout.columns.len()];
+
+        if let Some((padding_step, num_steps)) = self.auto_trace_gen.padding() {
+            let steps = witness.step_instances.len();
+            witness.pad(padding_step, num_steps);
+            if let Some(profile) = profile.as_deref_mut() {
+                profile.step_mut(padding_step).instances += witness.step_instances.len() - steps;
+            }
+        }
+
+        for (index, mut step_instance) in witness.step_instances.into_iter().enumerate() {
+            let start = profile.is_some().then(Instant::now);
+            let step_offset = offset;
+
+            self.auto_trace_gen.generate_step(&mut step_instance);
+            self.fill_unassigned(index, &mut step_instance);
+            self.assign_step(&mut offset, &step_instance, &mut |column, offset, value| {
+                values[column].get_or_insert_with(|| vec![F::ZERO; self.num_rows])[offset] = *value
+            });
+
+            if let (Some(profile), Some(start)) = (profile.as_deref_mut(), start) {
+                let step_profile = profile.step_mut(step_instance.step_type_uuid);
+                step_profile.assignment_time += start.elapsed();
+                step_profile.rows += offset - step_offset;
+            }
+        }
+
+        self.layout.assignments(values)
+    }
+
+    /// Generates the assignments as `generate_with_witness`, but only stores the non zero cells.
+    pub fn generate_sparse_with_witness(&self, witness: TraceWitness<F>) -> SparseAssignments<F> {
+        let mut offset: usize = 0;
+        let mut assignments = SparseAssignments::new(self.num_rows);
 
         let witness = self.auto_trace_gen.generate(witness);
 
-        for step_instance in witness.step_instances.into_iter() {
-            self.assign_step(&mut offset, &mut assignments, &step_instance);
+        for (index, mut step_instance) in witness.step_instances.into_iter().enumerate() {
+            self.fill_unassigned(index, &mut step_instance);
+            self.assign_step(&mut offset, &step_instance, &mut |column, offset, value| {
+                assignments.set(&self.layout.columns[column], offset, *value)
+            });
         }
 
         assignments
     }
 
+    /// Generates the assignments in chunks of `chunk_steps` step instances pulled from `source`,
+    /// so only a chunk is in memory at a time. `consume` receives the first row of each chunk and
+    /// its assignments, with `chunk_steps` times the step height rows, until all the rows of the
+    /// circuit are covered. The witness is padded and its auto signals are generated as in
+    /// `generate_with_witness`. Fails if a step assigns a row of a chunk already consumed, which
+    /// only happens with negative rotations of shared signals, and the chunks before it are
+    /// consumed by then. The halo2 backend proves a witness this way with
+    /// `ChiquitoHalo2StreamingCircuit`, which assigns each chunk as it is generated.
+    ///
+    /// Only the assignments are bounded by the chunk: the consumer decides where the rows go, and
+    /// the halo2 prover keeps every column of the table in memory.
+    pub fn generate_chunks<S, C>(
+        &self,
+        mut source: S,
+        chunk_steps: usize,
+        mut consume: C,
+    ) -> Result<(), ChunkError>
+    where
+        S: WitnessSource<Item = StepInstance<F>>,
+        C: FnMut(usize, Assignments<F>),
+    {
+        if chunk_steps == 0 {
+            return Err(ChunkError::EmptyChunks);
+        }
+
+        let chunk_rows = chunk_steps * self.placement.first_step_height() as usize;
+        let columns = self.layout.columns.len();
+        let mut chunk = AssignmentsChunk::new(0, chunk_rows.min(self.num_rows), columns);
+        let mut offset: usize = 0;
+        let mut steps: usize = 0;
+        let mut previous: Option<StepInstance<F>> = None;
+
+        loop {
+            let mut step_instance = match (source.next_step(), self.auto_trace_gen.padding()) {
+                (Some(step_instance), _) => step_instance,
+                (None, Some((padding_step, num_steps))) if steps < num_steps => {
+                    StepInstance::padding_after(padding_step, previous.as_ref())
+                }
+                _ => break,
+            };
+            if offset >= self.num_rows {
+                return Err(ChunkError::TooManySteps { step: steps });
+            }
+            self.auto_trace_gen.generate_step(&mut step_instance);
+            self.fill_unassigned(steps, &mut step_instance);
+
+            while offset >= chunk.start + chunk.rows {
+                let next = chunk.next(chunk_rows, self.num_rows);
+                let start = chunk.start;
+                consume(
+                    start,
+                    self.layout
+                        .assignments(std::mem::replace(&mut chunk, next).values),
+                );
+            }
+            self.assign_step(&mut offset, &step_instance, &mut |column, offset, value| {
+                chunk.set(column, offset, value)
+            });
+            if let Some(row) = chunk.previous_row.take() {
+                return Err(ChunkError::PreviousChunk {
+                    step: steps,
+                    row,
+                    chunk_start: chunk.start,
+                });
+            }
+
+            steps += 1;
+            previous = Some(step_instance);
+        }
+
+        while chunk.start < self.num_rows {
+            let next = chunk.next(chunk_rows, self.num_rows);
+            let start = chunk.start;
+            consume(
+                start,
+                self.layout
+                    .assignments(std::mem::replace(&mut chunk, next).values),
+            );
+        }
+
+        Ok(())
+    }
+
     pub fn uuid(&self) -> UUID {
         self.ir_id
     }
 
+    /// Cells of the signals of the circuit, relative to the first row of each step.
+    pub fn placement(&self) -> &Placement {
+        &self.placement
+    }
+
+    /// Reconstructs the witness of existing assignments, for instance captured from a circuit, with
+    /// up to `num_steps` step instances. The step type of each step instance is the one its step
+    /// selector selects, and it assigns the internal signals of its step type and the forward and
+    /// shared signals, with the values of their cells. Stops at the first rows where no step type
+    /// is selected.
+    pub fn reconstruct_witness(
+        &self,
+        assignments: &Assignments<F>,
+        num_steps: usize,
+    ) -> TraceWitness<F> {
+        let value = |column: &Column, row: i64| -> F {
+            usize::try_from(row)
+                .ok()
+                .and_then(|row| assignments.get(column).and_then(|values| values.get(row)))
+                .copied()
+                .unwrap_or(F::ZERO)
+        };
+
+        let mut step_types: Vec<StepTypeUUID> =
+            self.selector.selector_expr.keys().copied().collect();
+        step_types.sort();
+
+        let mut witness = TraceWitness {
+            step_instances: Vec::new(),
+        };
+        let mut offset: usize = 0;
+        while witness.step_instances.len() < num_steps && offset < self.num_rows {
+            let selected = step_types.iter().copied().find(|step_type| {
+                self.selector.selector_expr[step_type]
+                    .eval_with(&|(column, rotation, _)| {
+                        Some(value(column, offset as i64 + *rotation as i64))
+                    })
+                    .is_some_and(|selected| selected == F::ONE)
+            });
+            let step_type_uuid = match selected {
+                Some(step_type_uuid) => step_type_uuid,
+                None => break,
+            };
+
+            let step = self.layout.step(step_type_uuid);
+            let mut step_instance = StepInstance::new(step_type_uuid);
+            let signals = self
+                .placement
+                .internal_signals(step_type_uuid)
+                .into_iter()
+                .map(Queriable::Internal)
+                .chain(
+                    self.placement
+                        .forward
+                        .keys()
+                        .map(|forward| Queriable::Forward(*forward, false)),
+                )
+                .chain(
+                    self.placement
+                        .shared
+                        .keys()
+                        .map(|shared| Queriable::Shared(*shared, 0)),
+                );
+            for signal in signals {
+                let (column, rotation) = step.signals[&signal];
+                let column = &self.layout.columns[column];
+                step_instance.assign(signal, value(column, offset as i64 + rotation as i64));
+            }
+
+            offset += step.height;
+            witness.step_instances.push(step_instance);
+        }
+
+        witness
+    }
+
+    /// Returns the internal signals of their step type that the step instances of a witness do
+    /// not assign, after generating its auto signals, for the step instances missing some.
+    pub fn unassigned_signals(&self, witness: TraceWitness<F>) -> Vec<UnassignedSignals<F>> {
+        self.auto_trace_gen
+            .generate(witness)
+            .step_instances
+            .iter()
+            .enumerate()
+            .filter_map(|(index, step_instance)| self.find_unassigned(index, step_instance))
+            .collect()
+    }
+
+    fn find_unassigned(
+        &self,
+        index: usize,
+        step_instance: &StepInstance<F>,
+    ) -> Option<UnassignedSignals<F>> {
+        let signals: Vec<Queriable<F>> = self
+            .layout
+            .step(step_instance.step_type_uuid)
+            .internal
+            .iter()
+            .filter(|signal| !step_instance.assignments.contains_key(signal))
+            .copied()
+            .collect();
+
+        if signals.is_empty() {
+            None
+        } else {
+            Some(UnassignedSignals {
+                step: index,
+                step_type_uuid: step_instance.step_type_uuid,
+                signals,
+            })
+        }
+    }
+
+    /// Assigns the default value to the internal signals a step instance does not assign, with a
+    /// warning listing them if enabled.
+    fn fill_unassigned(&self, index: usize, step_instance: &mut StepInstance<F>) {
+        if let Some(unassigned) = self.find_unassigned(index, step_instance) {
+            if self.warn_unassigned {
+                eprintln!(
+                    "warning: {}, assigned {:?}",
+                    unassigned, self.unassigned_default
+                );
+            }
+            for signal in unassigned.signals {
+                step_instance.assign(signal, self.unassigned_default);
+            }
+        }
+    }
+
+    /// Assigns the signals and the selectors of a step instance at `offset` through `set`, which
+    /// takes the index of the column in the layout, the row and the value.
     fn assign_step(
         &self,
         offset: &mut usize,
-        assignments: &mut Assignments<F>,
         step_instance: &StepInstance<F>,
+        set: &mut dyn FnMut(usize, usize, &F),
     ) {
+        let step = self.layout.step(step_instance.step_type_uuid);
+
         for (lhs, rhs) in step_instance.assignments.iter() {
-            self.assign(offset, assignments, step_instance.step_type_uuid, lhs, rhs);
+            let (column, rotation) = match step.signals.get(lhs) {
+                Some(cell) => *cell,
+                None => self.layout.find_cell(lhs),
+            };
+
+            set(column, (*offset as i32 + rotation) as usize, rhs);
         }
 
-        let selector_assignment = self
-            .selector
-            .get_selector_assignment(step_instance.step_type_uuid);
+        for (column, rotation, value) in step.selectors.iter() {
+            set(*column, *offset + *rotation, value);
+        }
+
+        *offset += step.height;
+    }
+
+    fn set_value(&self, assignments: &mut Assignments<F>, column: usize, offset: usize, value: &F) {
+        let column = &self.layout.columns[column];
+        if let Some(column_assignments) = assignments.get_mut(column) {
+            column_assignments[offset] = *value;
+        } else {
+            let mut column_assignments = vec![F::ZERO; self.num_rows];
+            column_assignments[offset] = *value;
+
+            assignments.insert(column.clone(), column_assignments);
+        }
+    }
+}
+
+/// Assignments of a witness that can be updated when some step instances change, recomputing only
+/// the rows around them instead of the whole table. The witness is kept padded and with its auto
+/// signals. Assignments of shared signals rotated more than one step away are not recomputed.
+pub struct IncrementalAssignments<'a, F, TraceArgs> {
+    generator: &'a AssignmentGenerator<F, TraceArgs>,
+    witness: TraceWitness<F>,
+    offsets: Vec<usize>,
+    assignments: Assignments<F>,
+}
+
+impl<'a, F: Field + Hash, TraceArgs> IncrementalAssignments<'a, F, TraceArgs> {
+    pub fn new(generator: &'a AssignmentGenerator<F, TraceArgs>, witness: TraceWitness<F>) -> Self {
+        let mut incremental = Self {
+            generator,
+            witness: generator.auto_trace_gen.generate(witness),
+            offsets: Vec::new(),
+            assignments: Default::default(),
+        };
+        for index in 0..incremental.witness.step_instances.len() {
+            let step_instance = &mut incremental.witness.step_instances[index];
+            generator.fill_unassigned(index, step_instance);
+        }
+        incremental.regenerate();
 
-        for (expr, value) in selector_assignment.iter() {
-            match expr {
-                PolyExpr::Query((column, rot, _)) => {
-                    self.set_value(assignments, column.clone(), *offset + *rot as usize, value)
+        incremental
+    }
+
+    pub fn witness(&self) -> &TraceWitness<F> {
+        &self.witness
+    }
+
+    pub fn assignments(&self) -> &Assignments<F> {
+        &self.assignments
+    }
+
+    pub fn into_assignments(self) -> Assignments<F> {
+        self.assignments
+    }
+
+    /// Replaces step instances of the witness, by index, and updates the assignments. The padding
+    /// steps after a replaced step instance take its forward and shared signals, unless they are
+    /// replaced too. When a step instance changes height the whole table is regenerated.
+    pub fn update<I: IntoIterator<Item = (usize, StepInstance<F>)>>(&mut self, diff: I) {
+        let generator = self.generator;
+        let steps = &mut self.witness.step_instances;
+        let mut changed: Vec<usize> = Vec::new();
+        let mut height_changed = false;
+
+        for (index, mut step_instance) in diff {
+            assert!(
+                index < steps.len(),
+                "step instance {} not in the witness",
+                index
+            );
+
+            generator.auto_trace_gen.generate_step(&mut step_instance);
+            generator.fill_unassigned(index, &mut step_instance);
+            height_changed |= generator
+                .placement
+                .step_height(step_instance.step_type_uuid)
+                != generator.placement.step_height(steps[index].step_type_uuid);
+
+            steps[index] = step_instance;
+            changed.push(index);
+        }
+
+        if let Some((padding_step, _)) = generator.auto_trace_gen.padding() {
+            changed.sort_unstable();
+            changed.dedup();
+            let replaced = changed.clone();
+            for index in replaced.iter().copied() {
+                for next in index + 1..steps.len() {
+                    if steps[next].step_type_uuid != padding_step || replaced.contains(&next) {
+                        break;
+                    }
+                    steps[next] = StepInstance::padding_after(padding_step, Some(&steps[next - 1]));
+                    changed.push(next);
                 }
-                _ => panic!("wrong type of expresion is selector assignment"),
             }
         }
 
-        *offset += self.placement.step_height(step_instance.step_type_uuid) as usize;
+        if height_changed {
+            self.regenerate();
+        } else {
+            self.reassign(changed);
+        }
     }
 
-    fn assign(
-        &self,
-        offset: &mut usize,
-        assignments: &mut Assignments<F>,
-        step_uuid: StepTypeUUID,
-        lhs: &Queriable<F>,
-        value: &F,
-    ) {
-        let (column, rotation) = self.find_placement(step_uuid, lhs);
+    fn regenerate(&mut self) {
+        self.offsets.clear();
+        self.assignments = Default::default();
 
-        let offset = (*offset as i32 + rotation) as usize;
+        let mut offset = 0;
+        for step_instance in self.witness.step_instances.iter() {
+            self.offsets.push(offset);
+            let assignments = &mut self.assignments;
+            self.generator
+                .assign_step(&mut offset, step_instance, &mut |column, offset, value| {
+                    self.generator.set_value(assignments, column, offset, value)
+                });
+        }
+    }
 
-        self.set_value(assignments, column, offset, value);
+    /// Clears the rows of the changed step instances and of the following ones, that they can
+    /// write through the next value of forward signals, and assigns again the step instances that
+    /// can write in them, in order.
+    fn reassign(&mut self, mut changed: Vec<usize>) {
+        let steps = self.witness.step_instances.len();
+        changed.sort_unstable();
+        changed.dedup();
+
+        let mut cleared: Vec<usize> = changed
+            .iter()
+            .flat_map(|index| [*index, index + 1])
+            .filter(|index| *index < steps)
+            .collect();
+        cleared.dedup();
+        let mut reassigned: Vec<usize> = changed
+            .iter()
+            .flat_map(|index| index.saturating_sub(1)..(index + 3).min(steps))
+            .collect();
+        reassigned.sort_unstable();
+        reassigned.dedup();
+
+        for index in cleared {
+            let start = self.offsets[index];
+            let end = start
+                + self
+                    .generator
+                    .placement
+                    .step_height(self.witness.step_instances[index].step_type_uuid)
+                    as usize;
+            for values in self.assignments.values_mut() {
+                let end = end.min(values.len());
+                values[start..end]
+                    .iter_mut()
+                    .for_each(|value| *value = F::ZERO);
+            }
+        }
+
+        for index in reassigned {
+            let mut offset = self.offsets[index];
+            let assignments = &mut self.assignments;
+            self.generator.assign_step(
+                &mut offset,
+                &self.witness.step_instances[index],
+                &mut |column, offset, value| {
+                    self.generator.set_value(assignments, column, offset, value)
+                },
+            );
+        }
     }
+}
 
-    fn find_placement(&self, step_uuid: StepTypeUUID, query: &Queriable<F>) -> (Column, i32) {
-        match query {
-            Queriable::Internal(signal) => self
-                .placement
-                .find_internal_signal_placement(step_uuid, signal)
-                .into(),
+/// Assignments of a witness received in batches of step instances, each assigned as it arrives, so
+/// the whole witness does not need to be in memory at once. The witness is padded when it is
+/// finished, and its auto signals are generated as in `generate_with_witness`.
+pub struct StreamingAssignments<F> {
+    assigner: Arc<WitnessAssigner<F>>,
+    values: Vec<Option<Vec<F>>>,
+    offset: usize,
+    steps: usize,
+    previous: Option<StepInstance<F>>,
+}
 
-            Queriable::Forward(forward, next) => {
-                self.get_forward_placement(step_uuid, forward, *next)
+impl<F: Field + Hash> StreamingAssignments<F> {
+    pub fn new(assigner: Arc<WitnessAssigner<F>>) -> Self {
+        let columns = assigner.layout.columns.len();
+
+        Self {
+            assigner,
+            values: vec![None; columns],
+            offset: 0,
+            steps: 0,
+            previous: None,
+        }
+    }
+
+    /// Step instances added so far.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Last step instance added, with the signals generated or left unassigned filled in.
+    pub fn previous(&self) -> Option<&StepInstance<F>> {
+        self.previous.as_ref()
+    }
+
+    /// Assigns the next step instances of the witness. Panics if they do not fit in the circuit.
+    pub fn add_steps<I: IntoIterator<Item = StepInstance<F>>>(&mut self, step_instances: I) {
+        for step_instance in step_instances {
+            self.add_step(step_instance);
+        }
+    }
+
+    fn add_step(&mut self, mut step_instance: StepInstance<F>) {
+        let assigner = &self.assigner;
+        assert!(
+            self.offset < assigner.num_rows,
+            "witness has more step instances than the circuit"
+        );
+
+        assigner.auto_trace_gen.generate_step(&mut step_instance);
+        assigner.fill_unassigned(self.steps, &mut step_instance);
+        let (values, num_rows) = (&mut self.values, assigner.num_rows);
+        assigner.assign_step(
+            &mut self.offset,
+            &step_instance,
+            &mut |column, offset, value| {
+                values[column].get_or_insert_with(|| vec![F::ZERO; num_rows])[offset] = *value
+            },
+        );
+
+        self.steps += 1;
+        self.previous = Some(step_instance);
+    }
+
+    /// Pads the witness, if the circuit has a padding step, and returns its assignments.
+    pub fn finish(mut self) -> Assignments<F> {
+        if let Some((padding_step, num_steps)) = self.assigner.auto_trace_gen.padding() {
+            while self.steps < num_steps {
+                let padding = StepInstance::padding_after(padding_step, self.previous.as_ref());
+                self.add_step(padding);
+            }
+        }
+
+        self.assigner.layout.assignments(self.values)
+    }
+}
+
+/// Internal signals that a step instance does not assign, see
+/// `AssignmentGenerator::unassigned_signals`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnassignedSignals<F> {
+    /// Index of the step instance in the witness.
+    pub step: usize,
+    pub step_type_uuid: StepTypeUUID,
+    pub signals: Vec<Queriable<F>>,
+}
+
+impl<F> fmt::Display for UnassignedSignals<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let signals: Vec<String> = self
+            .signals
+            .iter()
+            .map(|signal| signal.annotation())
+            .collect();
+
+        write!(
+            f,
+            "step {} (step type {}) does not assign {}",
+            self.step,
+            self.step_type_uuid,
+            signals.join(", ")
+        )
+    }
+}
+
+/// Failure of `WitnessAssigner::generate_chunks`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChunkError {
+    /// The chunks have no step instances.
+    EmptyChunks,
+    /// The witness has more step instances than fit in the rows of the circuit.
+    TooManySteps { step: usize },
+    /// The step instance assigns a row of a chunk that was already consumed.
+    PreviousChunk {
+        step: usize,
+        row: usize,
+        chunk_start: usize,
+    },
+}
+
+impl fmt::Display for ChunkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkError::EmptyChunks => write!(f, "chunks need at least one step"),
+            ChunkError::TooManySteps { step } => write!(
+                f,
+                "step {}: witness has more step instances than the circuit",
+                step
+            ),
+            ChunkError::PreviousChunk {
+                step,
+                row,
+                chunk_start,
+            } => write!(
+                f,
+                "step {}: cannot assign row {} of a previous chunk, starting at {}",
+                step, row, chunk_start
+            ),
+        }
+    }
+}
+
+/// Placement of the signals and step selectors of each step type, with their columns as indexes
+/// into `columns`, computed once so that assigning a step instance does not look up nor clone
+/// columns for each cell.
+#[derive(Debug, Clone)]
+struct Layout<F> {
+    columns: Vec<Column>,
+    steps: HashMap<StepTypeUUID, StepLayout<F>>,
+    shared: HashMap<SharedSignal, (usize, i32)>,
+    shared_height: i32,
+}
+
+#[derive(Debug, Clone)]
+struct StepLayout<F> {
+    height: usize,
+    /// Internal signals of the step type, in order of UUID.
+    internal: Vec<Queriable<F>>,
+    /// Cells of the internal signals, of the forward signals and their next value and of the
+    /// shared signals without rotation.
+    signals: HashMap<Queriable<F>, (usize, i32)>,
+    /// Cells and values of the step selector.
+    selectors: Vec<(usize, usize, F)>,
+}
+
+impl<F> Default for Layout<F> {
+    fn default() -> Self {
+        Self {
+            columns: Vec::new(),
+            steps: HashMap::new(),
+            shared: HashMap::new(),
+            shared_height: 0,
+        }
+    }
+}
+
+impl<F: Field + Hash> Layout<F> {
+    fn new(columns: &[Column], placement: &Placement, selector: &StepSelector<F>) -> Self {
+        let mut layout = Self {
+            columns: columns.to_vec(),
+            shared_height: placement.first_step_height() as i32,
+            ..Default::default()
+        };
+        let mut indexes: HashMap<UUID, usize> = columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| (column.uuid(), index))
+            .collect();
+        let mut index = |column: &Column| {
+            *indexes.entry(column.uuid()).or_insert_with(|| {
+                layout.columns.push(column.clone());
+                layout.columns.len() - 1
+            })
+        };
+
+        let forward: Vec<(ForwardSignal, usize, i32)> = placement
+            .forward
+            .iter()
+            .map(|(signal, placement)| (*signal, index(&placement.column), placement.rotation))
+            .collect();
+        let shared: HashMap<SharedSignal, (usize, i32)> = placement
+            .shared
+            .iter()
+            .map(|(signal, placement)| (*signal, (index(&placement.column), placement.rotation)))
+            .collect();
+
+        let mut steps = HashMap::new();
+        for step_uuid in placement.steps.keys() {
+            let height = placement.step_height(*step_uuid);
+            let mut internal: Vec<Queriable<F>> = Vec::new();
+            let mut signals: HashMap<Queriable<F>, (usize, i32)> = HashMap::new();
+            for signal in placement.internal_signals(*step_uuid) {
+                let placement = placement.find_internal_signal_placement(*step_uuid, &signal);
+                internal.push(Queriable::Internal(signal));
+                signals.insert(
+                    Queriable::Internal(signal),
+                    (index(&placement.column), placement.rotation),
+                );
+            }
+            for (signal, column, rotation) in forward.iter() {
+                signals.insert(Queriable::Forward(*signal, false), (*column, *rotation));
+                signals.insert(
+                    Queriable::Forward(*signal, true),
+                    (*column, rotation + height as i32),
+                );
             }
+            for (signal, cell) in shared.iter() {
+                signals.insert(Queriable::Shared(*signal, 0), *cell);
+            }
+
+            let selectors = selector
+                .selector_assignment
+                .get(step_uuid)
+                .map(|assignment| {
+                    assignment
+                        .iter()
+                        .map(|(expr, value)| match expr {
+                            PolyExpr::Query((column, rot, _)) => {
+                                (index(column), *rot as usize, *value)
+                            }
+                            _ => panic!("wrong type of expresion is selector assignment"),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
 
-            Queriable::Shared(shared, rot) => self.get_shared_placement(shared, *rot),
+            steps.insert(
+                *step_uuid,
+                StepLayout {
+                    height: height as usize,
+                    internal,
+                    signals,
+                    selectors,
+                },
+            );
+        }
 
+        layout.steps = steps;
+        layout.shared = shared;
+
+        layout
+    }
+
+    fn step(&self, step_uuid: StepTypeUUID) -> &StepLayout<F> {
+        self.steps.get(&step_uuid).expect("step not found")
+    }
+
+    /// Returns the cell of a signal that is not in the layout of the step type assigning it.
+    fn find_cell(&self, query: &Queriable<F>) -> (usize, i32) {
+        match query {
+            Queriable::Internal(_) => panic!("signal not found"),
+
+            Queriable::Forward(_, _) => panic!("forward signal placement not found"),
+
+            Queriable::Shared(shared, rot) => {
+                let (column, rotation) = self.shared.get(shared).expect("shared signal not found");
+
+                (*column, rotation + rot * self.shared_height)
+            }
+
+            #[cfg(feature = "halo2")]
             Queriable::Halo2AdviceQuery(signal, rotation) => {
                 let column = self
-                    .find_halo2_advice_native(signal.column)
+                    .columns
+                    .iter()
+                    .position(|column| {
+                        column
+                            .halo2_advice
+                            .is_some_and(|advice| advice.column == signal.column)
+                    })
                     .expect("column not found");
 
                 (column, *rotation)
@@ -225,66 +1103,65 @@ impl<F: Field + Hash, TraceArgs> AssignmentGenerator<F, TraceArgs> {
         }
     }
 
-    fn set_value(
-        &self,
-        assignments: &mut Assignments<F>,
-        column: Column,
-        offset: usize,
-        value: &F,
-    ) {
-        if let Some(column_assignments) = assignments.get_mut(&column) {
-            column_assignments[offset] = *value;
-        } else {
-            let mut column_assignments = vec![F::ZERO; self.num_rows];
-            column_assignments[offset] = *value;
-
-            assignments.insert(column, column_assignments);
-        }
+    /// Returns the assignments of the columns with values, given by index.
+    fn assignments(&self, values: Vec<Option<Vec<F>>>) -> Assignments<F> {
+        Assignments(
+            self.columns
+                .iter()
+                .zip(values)
+                .filter_map(|(column, values)| values.map(|values| (column.clone(), values)))
+                .collect(),
+        )
     }
+}
 
-    fn get_forward_placement(
-        &self,
-        step_uuid: StepTypeUUID,
-        forward: &ForwardSignal,
-        next: bool,
-    ) -> (Column, i32) {
-        let placement = self
-            .placement
-            .get_forward_placement(forward)
-            .expect("forward signal placement not found");
-
-        let super_rotation = placement.rotation
-            + if next {
-                self.placement.step_height(step_uuid) as i32
-            } else {
-                0
-            };
+/// Assignments of the rows `[start, start + rows)` being generated by
+/// `AssignmentGenerator::generate_chunks`, by column index, with the values of later rows kept for
+/// the next chunks, and the first row assigned before `start`, if any.
+struct AssignmentsChunk<F> {
+    start: usize,
+    rows: usize,
+    values: Vec<Option<Vec<F>>>,
+    carry: Vec<(usize, usize, F)>,
+    previous_row: Option<usize>,
+}
 
-        (placement.column, super_rotation)
+impl<F: Field> AssignmentsChunk<F> {
+    fn new(start: usize, rows: usize, columns: usize) -> Self {
+        Self {
+            start,
+            rows,
+            values: vec![None; columns],
+            carry: Vec::new(),
+            previous_row: None,
+        }
     }
 
-    fn get_shared_placement(&self, shared: &SharedSignal, rotation: i32) -> (Column, i32) {
-        let placement = self
-            .placement
-            .get_shared_placement(shared)
-            .expect("shared signal not found");
-
-        let super_rotation =
-            placement.rotation + rotation * (self.placement.first_step_height() as i32);
-
-        (placement.column, super_rotation)
+    fn set(&mut self, column: usize, offset: usize, value: &F) {
+        if offset < self.start {
+            self.previous_row.get_or_insert(offset);
+        } else if offset >= self.start + self.rows {
+            self.carry.push((column, offset, *value));
+        } else {
+            let rows = self.rows;
+            self.values[column].get_or_insert_with(|| vec![F::ZERO; rows])[offset - self.start] =
+                *value;
+        }
     }
 
-    fn find_halo2_advice_native(&self, halo2_advice: Halo2Column<Advice>) -> Option<Column> {
-        for column in self.columns.iter() {
-            if let Some(advice) = column.halo2_advice {
-                if advice.column == halo2_advice {
-                    return Some(column.clone());
-                }
-            }
+    /// Returns the following chunk, with the values carried over, of at most `chunk_rows` rows.
+    fn next(&mut self, chunk_rows: usize, num_rows: usize) -> Self {
+        let start = self.start + self.rows;
+        let mut next = Self::new(
+            start,
+            chunk_rows.min(num_rows.saturating_sub(start)),
+            self.values.len(),
+        );
+        for (column, offset, value) in std::mem::take(&mut self.carry) {
+            next.set(column, offset, &value);
         }
 
-        None
+        next
     }
 }
 
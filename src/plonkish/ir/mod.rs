@@ -1,8 +1,13 @@
-use std::{fmt::Debug, hash::Hash};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    hash::{Hash, Hasher},
+};
 
+#[cfg(feature = "halo2")]
+use crate::sbpir::{ImportedHalo2Advice, ImportedHalo2Fixed};
 use crate::{
     poly::Expr,
-    sbpir::{ImportedHalo2Advice, ImportedHalo2Fixed},
     util::{uuid, UUID},
 };
 
@@ -22,6 +27,15 @@ pub struct Circuit<F> {
 
     pub fixed_assignments: Assignments<F>,
 
+    pub num_rows: usize,
+
+    /// Copy constraints between a cell of the circuit and a cell of another sub-circuit of the
+    /// same super circuit, as their columns and rows.
+    pub copies: Vec<((Column, i32), (Column, i32))>,
+
+    /// Calls of halo2 gadgets by the steps of the circuit, assigned by the halo2 backend.
+    pub gadget_calls: Vec<GadgetCall<F>>,
+
     pub id: UUID,
     pub ast_id: UUID,
 }
@@ -57,11 +71,206 @@ impl<F: Clone> Circuit<F> {
     }
 }
 
+impl<F: Hash + Debug> Circuit<F> {
+    /// Encoding of the structure of the circuit: its columns by their position and type, polys,
+    /// lookups, fixed assignments, exposed cells and rows, ignoring the annotations and the UUIDs
+    /// of its own columns. Structurally identical circuits, like two instances of the same
+    /// sub-circuit in a super circuit, have the same canonical form, and their columns correspond
+    /// in order.
+    pub fn canonical_form(&self) -> Vec<u8> {
+        let mut encoder = CanonicalEncoder::default();
+        self.encode(&mut encoder);
+
+        encoder.bytes
+    }
+
+    /// Hash of the canonical form of the circuit, see `canonical_form`. It is stable across
+    /// processes and builds, but two circuits with the same hash are only structurally identical
+    /// if their canonical forms are equal.
+    pub fn canonical_hash(&self) -> u64 {
+        let mut encoder = CanonicalEncoder::default();
+        self.encode(&mut encoder);
+
+        encoder.finish()
+    }
+
+    fn encode(&self, state: &mut CanonicalEncoder) {
+        let index: HashMap<UUID, usize> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| (column.uuid(), index))
+            .collect();
+
+        self.columns.len().hash(state);
+        for column in self.columns.iter() {
+            column.ctype.hash(state);
+            column.phase.hash(state);
+        }
+        self.polys.len().hash(state);
+        for poly in self.polys.iter() {
+            hash_expr(&poly.expr, &index, state);
+        }
+        self.lookups.len().hash(state);
+        for lookup in self.lookups.iter() {
+            lookup.exprs.len().hash(state);
+            for (src, dest) in lookup.exprs.iter() {
+                hash_expr(src, &index, state);
+                hash_expr(dest, &index, state);
+            }
+        }
+        let mut fixed: Vec<_> = self
+            .fixed_assignments
+            .iter()
+            .map(|(column, values)| (canonical_column(column, &index), values))
+            .collect();
+        fixed.sort_by_key(|(column, _)| *column);
+        fixed.hash(state);
+        self.exposed.len().hash(state);
+        for (column, rotation) in self.exposed.iter() {
+            canonical_column(column, &index).hash(state);
+            rotation.hash(state);
+        }
+        self.copies.len().hash(state);
+        for ((from, from_row), (to, to_row)) in self.copies.iter() {
+            canonical_column(from, &index).hash(state);
+            from_row.hash(state);
+            canonical_column(to, &index).hash(state);
+            to_row.hash(state);
+        }
+        self.gadget_calls.len().hash(state);
+        for call in self.gadget_calls.iter() {
+            call.gadget.hash(state);
+            hash_expr(&call.selector, &index, state);
+            call.inputs.len().hash(state);
+            for (column, rotation) in call.inputs.iter() {
+                canonical_column(column, &index).hash(state);
+                rotation.hash(state);
+            }
+            call.input_columns.len().hash(state);
+            call.output_columns.len().hash(state);
+            for column in call.input_columns.iter().chain(call.output_columns.iter()) {
+                canonical_column(column, &index).hash(state);
+            }
+            canonical_column(&call.enable, &index).hash(state);
+            call.calls.hash(state);
+            call.rows.hash(state);
+        }
+        self.num_rows.hash(state);
+    }
+}
+
+/// `Hasher` that keeps the bytes written to it, with the integers in little endian whatever the
+/// platform, and hashes them with 64-bit FNV-1a, which does not depend on the process or the
+/// build, unlike `DefaultHasher`.
+#[derive(Default)]
+struct CanonicalEncoder {
+    bytes: Vec<u8>,
+}
+
+impl Hasher for CanonicalEncoder {
+    fn finish(&self) -> u64 {
+        self.bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_isize(&mut self, n: isize) {
+        self.write_u64(n as i64 as u64);
+    }
+}
+
+/// Position of `column` in the circuit, or its UUID if it is a column of another circuit.
+fn canonical_column(column: &Column, index: &HashMap<UUID, usize>) -> (bool, UUID) {
+    match index.get(&column.uuid()) {
+        Some(position) => (true, *position as UUID),
+        None => (false, column.uuid()),
+    }
+}
+
+fn hash_expr<F: Hash + Debug, H: Hasher>(
+    expr: &PolyExpr<F>,
+    index: &HashMap<UUID, usize>,
+    state: &mut H,
+) {
+    // tags instead of `mem::discriminant`, whose hash may change between builds
+    match expr {
+        PolyExpr::Const(v) => {
+            state.write_u8(0);
+            v.hash(state);
+        }
+        PolyExpr::Sum(ses) => {
+            state.write_u8(1);
+            ses.len().hash(state);
+            ses.iter().for_each(|se| hash_expr(se, index, state));
+        }
+        PolyExpr::Mul(ses) => {
+            state.write_u8(2);
+            ses.len().hash(state);
+            ses.iter().for_each(|se| hash_expr(se, index, state));
+        }
+        PolyExpr::Neg(se) => {
+            state.write_u8(3);
+            hash_expr(se, index, state);
+        }
+        PolyExpr::Pow(se, exp) => {
+            state.write_u8(4);
+            exp.hash(state);
+            hash_expr(se, index, state);
+        }
+        PolyExpr::Query((column, rotation, _)) => {
+            state.write_u8(5);
+            canonical_column(column, index).hash(state);
+            rotation.hash(state);
+        }
+        #[cfg(feature = "halo2")]
+        PolyExpr::Halo2Expr(e) => {
+            state.write_u8(6);
+            format!("{:?}", e).hash(state);
+        }
+        PolyExpr::Challenge(id, phase) => {
+            state.write_u8(7);
+            id.hash(state);
+            phase.hash(state);
+        }
+        PolyExpr::MI(se) => {
+            state.write_u8(8);
+            hash_expr(se, index, state);
+        }
+    }
+}
+
 #[derive(Clone, Debug, Hash, PartialEq)]
 pub enum ColumnType {
     Advice,
     Fixed,
+    #[cfg(feature = "halo2")]
     Halo2Advice,
+    #[cfg(feature = "halo2")]
     Halo2Fixed,
 }
 
@@ -70,7 +279,9 @@ pub struct Column {
     pub annotation: String,
 
     pub ctype: ColumnType,
+    #[cfg(feature = "halo2")]
     pub halo2_advice: Option<ImportedHalo2Advice>,
+    #[cfg(feature = "halo2")]
     pub halo2_fixed: Option<ImportedHalo2Fixed>,
 
     pub phase: usize,
@@ -85,7 +296,9 @@ impl Column {
             id: uuid(),
             ctype: ColumnType::Advice,
             phase,
+            #[cfg(feature = "halo2")]
             halo2_advice: None,
+            #[cfg(feature = "halo2")]
             halo2_fixed: None,
         }
     }
@@ -96,11 +309,14 @@ impl Column {
             id: uuid(),
             ctype: ColumnType::Fixed,
             phase: 0,
+            #[cfg(feature = "halo2")]
             halo2_advice: None,
+            #[cfg(feature = "halo2")]
             halo2_fixed: None,
         }
     }
 
+    #[cfg(feature = "halo2")]
     pub fn new_halo2_advice<A: Into<String>>(
         annotation: A,
         halo2_advice: ImportedHalo2Advice,
@@ -115,6 +331,7 @@ impl Column {
         }
     }
 
+    #[cfg(feature = "halo2")]
     pub fn new_halo2_fixed<A: Into<String>>(
         annotation: A,
         halo2_fixed: ImportedHalo2Fixed,
@@ -169,7 +386,7 @@ pub type PolyExpr<F> = Expr<F, Queriable>;
 impl<F: Clone> PolyExpr<F> {
     pub fn rotate(&self, rot: i32) -> PolyExpr<F> {
         match self {
-            PolyExpr::Const(_) => (*self).clone(),
+            PolyExpr::Const(_) | PolyExpr::Challenge(..) => (*self).clone(),
             PolyExpr::Query((c, orig_rot, annotation)) => PolyExpr::Query((
                 c.clone(),
                 orig_rot + rot,
@@ -179,6 +396,7 @@ impl<F: Clone> PolyExpr<F> {
             PolyExpr::Mul(v) => PolyExpr::Mul(v.iter().map(|e| e.rotate(rot)).collect()),
             PolyExpr::Neg(v) => PolyExpr::Neg(Box::new(v.rotate(rot))),
             PolyExpr::Pow(v, exp) => PolyExpr::Pow(Box::new(v.rotate(rot)), *exp),
+            #[cfg(feature = "halo2")]
             PolyExpr::Halo2Expr(_) => panic!("jarrl: cannot rotate polyexpr that contains halo2"),
             PolyExpr::MI(v) => PolyExpr::MI(Box::new(v.rotate(rot))),
         }
@@ -191,9 +409,35 @@ pub struct PolyLookup<F> {
     pub exprs: Vec<(PolyExpr<F>, PolyExpr<F>)>,
 }
 
+/// Call of a halo2 gadget by the steps of a step type. As the step types of the rows depend on the
+/// witness and the layout cannot, the halo2 backend calls the gadget `calls` times, with the
+/// values of the input cells in the steps of the step type one after the other, and zero in the
+/// calls left. The input and output cells of the k-th call are copied to the row k of
+/// `input_columns` and `output_columns`, the call table, whose rows `enable` is 1 at. A lookup
+/// constrains the input and output signals of every step of the step type to be an enabled row of
+/// the call table.
+#[derive(Clone, Debug)]
+pub struct GadgetCall<F> {
+    /// Name the gadget is registered with in the halo2 backend.
+    pub gadget: String,
+    pub annotation: String,
+    /// Step selector of the step type, at the first row of a step.
+    pub selector: PolyExpr<F>,
+    /// Cells of the input signals, with their rotation from the first row of a step.
+    pub inputs: Vec<(Column, i32)>,
+    pub input_columns: Vec<Column>,
+    pub output_columns: Vec<Column>,
+    /// Fixed column of the call table, 1 in the rows of the calls.
+    pub enable: Column,
+    /// Calls of the gadget, the most steps of the step type in a witness.
+    pub calls: usize,
+    /// First row of each step.
+    pub rows: Vec<usize>,
+}
+
 #[cfg(test)]
 mod tests {
-    use super::PolyExpr;
+    use super::{assignments::Assignments, Circuit, Column, Poly, PolyExpr};
     use halo2_proofs::halo2curves::bn256::Fr;
 
     #[test]
@@ -216,4 +460,41 @@ mod tests {
         let expr5 = PolyExpr::Pow(Box::new(PolyExpr::Const(&a)), 2);
         assert_eq!(format!("{:?}", expr5), "(0xa)^2");
     }
+
+    fn sub_circuit(annotation: &str, constant: u64) -> Circuit<Fr> {
+        let a = Column::advice(format!("{} a", annotation), 0);
+        let q = Column::fixed(format!("{} q", annotation));
+
+        Circuit {
+            columns: vec![a.clone(), q.clone()],
+            polys: vec![Poly {
+                annotation: annotation.to_string(),
+                expr: q.query(0, "q")
+                    * (a.query(1, "a next") - PolyExpr::Const(Fr::from(constant))),
+            }],
+            fixed_assignments: Assignments([(q, vec![Fr::from(1), Fr::from(0)])].into()),
+            num_rows: 2,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_canonical_hash() {
+        let hash = sub_circuit("first", 1).canonical_hash();
+
+        assert_eq!(sub_circuit("second", 1).canonical_hash(), hash);
+        assert_ne!(sub_circuit("first", 2).canonical_hash(), hash);
+    }
+
+    #[test]
+    fn test_canonical_form() {
+        let form = sub_circuit("first", 1).canonical_form();
+
+        assert_eq!(sub_circuit("second", 1).canonical_form(), form);
+        assert_ne!(sub_circuit("first", 2).canonical_form(), form);
+
+        let mut shorter = sub_circuit("first", 1);
+        shorter.num_rows = 1;
+        assert_ne!(shorter.canonical_form(), form);
+    }
 }
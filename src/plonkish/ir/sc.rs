@@ -1,9 +1,9 @@
-use std::{collections::HashMap, fmt::Debug, hash::Hash, rc::Rc};
+use std::{collections::HashMap, fmt::Debug, hash::Hash, panic, rc::Rc, sync::Arc, thread};
 
 use crate::{field::Field, sbpir::SBPIR, util::UUID, wit_gen::TraceWitness};
 
 use super::{
-    assignments::{AssignmentGenerator, Assignments},
+    assignments::{AssignmentGenerator, Assignments, WitnessAssigner},
     Circuit,
 };
 
@@ -76,16 +76,19 @@ impl<F: Clone, MappingArgs> SuperCircuit<F, MappingArgs> {
 pub type SuperAssignments<F> = HashMap<UUID, Assignments<F>>;
 pub type SuperTraceWitness<F> = HashMap<UUID, TraceWitness<F>>;
 
+/// Context of a super circuit mapping. The trace of each sub-circuit is generated when it is
+/// mapped, and its assignments later, in parallel for all the sub-circuits, as they are in
+/// disjoint columns.
 #[derive(Clone)]
 pub struct MappingContext<F> {
-    assignments: SuperAssignments<F>,
+    witnesses: Vec<(Arc<WitnessAssigner<F>>, TraceWitness<F>)>,
     trace_witnesses: SuperTraceWitness<F>,
 }
 
 impl<F: Default> Default for MappingContext<F> {
     fn default() -> Self {
         Self {
-            assignments: Default::default(),
+            witnesses: Default::default(),
             trace_witnesses: Default::default(),
         }
     }
@@ -96,8 +99,7 @@ impl<F: Field + Hash> MappingContext<F> {
         let trace_witness = gen.generate_trace_witness(args);
         self.trace_witnesses
             .insert(gen.uuid(), trace_witness.clone());
-        self.assignments
-            .insert(gen.uuid(), gen.generate_with_witness(trace_witness));
+        self.witnesses.push((gen.assigner(), trace_witness));
     }
 
     pub fn map_with_witness<TraceArgs>(
@@ -105,12 +107,41 @@ impl<F: Field + Hash> MappingContext<F> {
         gen: &AssignmentGenerator<F, TraceArgs>,
         witness: TraceWitness<F>,
     ) {
-        self.assignments
-            .insert(gen.uuid(), gen.generate_with_witness(witness));
+        self.map_with_assigner(gen.assigner(), witness);
     }
 
+    /// Maps a sub-circuit to `witness` with only its assigner, which unlike its assignment
+    /// generator can be kept across threads.
+    pub fn map_with_assigner(
+        &mut self,
+        assigner: Arc<WitnessAssigner<F>>,
+        witness: TraceWitness<F>,
+    ) {
+        self.witnesses.push((assigner, witness));
+    }
+
+    /// Assigns the witnesses of the mapped sub-circuits, each in its own thread. If a sub-circuit
+    /// is mapped more than once, its last witness is assigned.
     pub fn get_super_assignments(self) -> SuperAssignments<F> {
-        self.assignments
+        let mut witnesses: HashMap<UUID, (Arc<WitnessAssigner<F>>, TraceWitness<F>)> =
+            HashMap::new();
+        for (assigner, witness) in self.witnesses {
+            witnesses.insert(assigner.uuid(), (assigner, witness));
+        }
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = witnesses
+                .into_iter()
+                .map(|(uuid, (assigner, witness))| {
+                    scope.spawn(move || (uuid, assigner.generate_with_witness(witness)))
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or_else(panic::resume_unwind))
+                .collect()
+        })
     }
 
     pub fn get_trace_witnesses(self) -> SuperTraceWitness<F> {
@@ -215,6 +246,9 @@ mod test {
                 polys,
                 lookups,
                 fixed_assignments,
+                num_rows: 1,
+                copies: vec![],
+                gadget_calls: vec![],
                 id: uuid(),
                 ast_id: uuid(),
             }
@@ -260,6 +294,9 @@ mod test {
                 polys,
                 lookups,
                 fixed_assignments,
+                num_rows: 1,
+                copies: vec![],
+                gadget_calls: vec![],
                 id: uuid(),
                 ast_id: uuid(),
             }
@@ -282,7 +319,7 @@ mod test {
         let ctx = MappingContext::<Fr>::default();
 
         assert_eq!(
-            format!("{:#?}", ctx.assignments),
+            format!("{:#?}", ctx.get_super_assignments()),
             format!("{:#?}", SuperAssignments::<Fr>::default())
         );
     }
@@ -310,13 +347,13 @@ mod test {
     fn test_mapping_context_map() {
         let mut ctx = MappingContext::<Fr>::default();
 
-        assert_eq!(ctx.assignments.len(), 0);
+        assert_eq!(ctx.witnesses.len(), 0);
 
         let gen = simple_assignment_generator();
 
         ctx.map(&gen, ());
 
-        assert_eq!(ctx.assignments.len(), 1);
+        assert_eq!(ctx.get_super_assignments().len(), 1);
     }
 
     #[test]
@@ -331,6 +368,24 @@ mod test {
 
         ctx.map_with_witness(&gen, witness);
 
-        assert_eq!(ctx.assignments.len(), 1);
+        assert_eq!(ctx.get_super_assignments().len(), 1);
+    }
+
+    #[test]
+    fn test_mapping_context_parallel_assignments() {
+        let mut ctx = MappingContext::<Fr>::default();
+
+        let gens: Vec<_> = (0..4).map(|_| simple_assignment_generator()).collect();
+        for gen in gens.iter() {
+            ctx.map(gen, ());
+        }
+        ctx.map(&gens[0], ());
+
+        let assignments = ctx.get_super_assignments();
+
+        assert_eq!(assignments.len(), gens.len());
+        for gen in gens.iter() {
+            assert!(assignments.contains_key(&gen.uuid()));
+        }
     }
 }
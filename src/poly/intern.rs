@@ -0,0 +1,216 @@
+//! Hash-consing of expressions. An `ExprArena` stores each distinct sub-expression once, as a
+//! node referring to its operands by `ExprId`, so the identical sub-expressions of large
+//! machine-generated circuits share memory, and passes over them, like common sub-expression
+//! elimination or degree analysis, visit each one once.
+
+use std::{collections::HashMap, hash::Hash};
+
+#[cfg(feature = "halo2")]
+use halo2_proofs::plonk::Expression;
+
+use crate::util::UUID;
+
+use super::Expr;
+
+/// Index of an interned expression in its `ExprArena`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ExprId(u32);
+
+impl ExprId {
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Interned expression, with its operands by id.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Node<F, V> {
+    Const(F),
+    Sum(Vec<ExprId>),
+    Mul(Vec<ExprId>),
+    Neg(ExprId),
+    Pow(ExprId, u32),
+    Query(V),
+    /// Index of the halo2 expression in the arena. They are not hashed, so they are never shared.
+    #[cfg(feature = "halo2")]
+    Halo2Expr(usize),
+    Challenge(UUID, usize),
+    MI(ExprId),
+}
+
+impl<F, V> Node<F, V> {
+    /// Returns the ids of the operands of the node.
+    pub fn operands(&self) -> Vec<ExprId> {
+        match self {
+            Node::Sum(ses) | Node::Mul(ses) => ses.clone(),
+            Node::Neg(se) | Node::Pow(se, _) | Node::MI(se) => vec![*se],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Arena of structurally shared expressions. Interning an expression returns the id of an equal
+/// expression interned before, if any. The operands of a node always have lower ids than the
+/// node.
+pub struct ExprArena<F, V> {
+    nodes: Vec<Node<F, V>>,
+    ids: HashMap<Node<F, V>, ExprId>,
+    #[cfg(feature = "halo2")]
+    halo2_exprs: Vec<Expression<F>>,
+}
+
+impl<F, V> Default for ExprArena<F, V> {
+    fn default() -> Self {
+        Self {
+            nodes: Default::default(),
+            ids: Default::default(),
+            #[cfg(feature = "halo2")]
+            halo2_exprs: Default::default(),
+        }
+    }
+}
+
+impl<F, V> ExprArena<F, V> {
+    /// Number of distinct expressions in the arena.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn node(&self, id: ExprId) -> &Node<F, V> {
+        &self.nodes[id.index()]
+    }
+
+    /// Returns the degree of every expression in the arena, indexed by id, as `Expr::degree`.
+    pub fn degrees(&self) -> Vec<usize> {
+        let mut degrees: Vec<usize> = Vec::with_capacity(self.nodes.len());
+        for node in self.nodes.iter() {
+            let degree = match node {
+                Node::Const(_) | Node::Challenge(..) => 0,
+                Node::Sum(ses) => ses.iter().map(|se| degrees[se.index()]).max().unwrap_or(0),
+                Node::Mul(ses) => ses.iter().map(|se| degrees[se.index()]).sum(),
+                Node::Neg(se) => degrees[se.index()],
+                Node::Pow(se, exp) => degrees[se.index()] * (*exp as usize),
+                Node::Query(_) => 1,
+                #[cfg(feature = "halo2")]
+                Node::Halo2Expr(_) => panic!("not implemented"),
+                Node::MI(se) => usize::from(degrees[se.index()] > 0),
+            };
+            degrees.push(degree);
+        }
+
+        degrees
+    }
+
+    /// Returns how many times each expression is an operand of the others, indexed by id.
+    pub fn use_counts(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.nodes.len()];
+        for node in self.nodes.iter() {
+            for operand in node.operands() {
+                counts[operand.index()] += 1;
+            }
+        }
+
+        counts
+    }
+
+    /// Returns the sums, products, negations, powers and inverses that are operands of more than
+    /// one expression, candidates for common sub-expression elimination.
+    pub fn common_subexprs(&self) -> Vec<ExprId> {
+        self.use_counts()
+            .into_iter()
+            .enumerate()
+            .filter(|(index, count)| *count > 1 && !self.nodes[*index].operands().is_empty())
+            .map(|(index, _)| ExprId(index as u32))
+            .collect()
+    }
+}
+
+impl<F: Clone + Eq + Hash, V: Clone + Eq + Hash> ExprArena<F, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Interns `expr` and its sub-expressions, and returns its id.
+    pub fn intern(&mut self, expr: &Expr<F, V>) -> ExprId {
+        let node = match expr {
+            Expr::Const(v) => Node::Const(v.clone()),
+            Expr::Sum(ses) => Node::Sum(ses.iter().map(|se| self.intern(se)).collect()),
+            Expr::Mul(ses) => Node::Mul(ses.iter().map(|se| self.intern(se)).collect()),
+            Expr::Neg(se) => Node::Neg(self.intern(se)),
+            Expr::Pow(se, exp) => Node::Pow(self.intern(se), *exp),
+            Expr::Query(q) => Node::Query(q.clone()),
+            #[cfg(feature = "halo2")]
+            Expr::Halo2Expr(e) => {
+                self.halo2_exprs.push(e.clone());
+                Node::Halo2Expr(self.halo2_exprs.len() - 1)
+            }
+            Expr::Challenge(id, phase) => Node::Challenge(*id, *phase),
+            Expr::MI(se) => Node::MI(self.intern(se)),
+        };
+
+        if let Some(id) = self.ids.get(&node) {
+            return *id;
+        }
+
+        let id = ExprId(u32::try_from(self.nodes.len()).expect("too many expressions"));
+        self.nodes.push(node.clone());
+        self.ids.insert(node, id);
+
+        id
+    }
+
+    /// Rebuilds the expression with `id`.
+    pub fn expr(&self, id: ExprId) -> Expr<F, V> {
+        match self.node(id) {
+            Node::Const(v) => Expr::Const(v.clone()),
+            Node::Sum(ses) => Expr::Sum(ses.iter().map(|se| self.expr(*se)).collect()),
+            Node::Mul(ses) => Expr::Mul(ses.iter().map(|se| self.expr(*se)).collect()),
+            Node::Neg(se) => Expr::Neg(Box::new(self.expr(*se))),
+            Node::Pow(se, exp) => Expr::Pow(Box::new(self.expr(*se)), *exp),
+            Node::Query(q) => Expr::Query(q.clone()),
+            #[cfg(feature = "halo2")]
+            Node::Halo2Expr(index) => Expr::Halo2Expr(self.halo2_exprs[*index].clone()),
+            Node::Challenge(id, phase) => Expr::Challenge(*id, *phase),
+            Node::MI(se) => Expr::MI(Box::new(self.expr(*se))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        poly::ToExpr,
+        sbpir::{query::Queriable, InternalSignal},
+    };
+
+    #[test]
+    fn test_intern() {
+        let a: Queriable<Fr> = Queriable::Internal(InternalSignal::new("a"));
+        let b: Queriable<Fr> = Queriable::Internal(InternalSignal::new("b"));
+
+        let mut arena = ExprArena::new();
+        let first = arena.intern(&((a * b) + (a * b) * 2));
+        let second = arena.intern(&((a * b) + 3));
+
+        // a, b, a * b, 2, a * b * 2, the first sum, 3 and the second sum
+        assert_eq!(arena.len(), 8);
+        assert_eq!(arena.intern(&((a * b) + 3)), second);
+        assert_eq!(
+            format!("{:?}", arena.expr(first)),
+            format!("{:?}", (a * b) + (a * b) * 2)
+        );
+
+        let a_times_b = arena.intern(&(a * b));
+        assert_eq!(arena.common_subexprs(), vec![a_times_b]);
+        let a = arena.intern(&a.expr());
+        assert_eq!(arena.degrees()[first.index()], 2);
+        assert_eq!(arena.degrees()[a.index()], 1);
+    }
+}
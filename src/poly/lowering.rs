@@ -0,0 +1,268 @@
+//! Restructuring of expressions to lower the field operations of evaluating them, for the
+//! polynomial gates a prover evaluates on every row.
+//!
+//! Sums are brought to Horner form, factoring out the factor common to the most terms, products
+//! become balanced trees of binary products, and powers are computed by repeated squaring. The
+//! balanced trees make identical sub-products more likely, which the halo2 evaluator computes
+//! once.
+
+use std::{collections::HashSet, fmt::Debug};
+
+use crate::field::Field;
+
+use super::Expr;
+
+/// Estimated field operations of evaluating an expression once, see `op_count`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OpCount {
+    pub muls: usize,
+    /// Additions, subtractions and negations.
+    pub adds: usize,
+}
+
+/// Returns the expression restructured to lower the field operations of evaluating it,
+/// equivalent to it.
+pub fn optimize_evaluation<F: Field, V: Clone + Debug>(expr: Expr<F, V>) -> Expr<F, V> {
+    lower(expr.simplify())
+}
+
+/// Estimates the field operations of evaluating `expr`, counting identical sub-expressions once
+/// and powers by repeated squaring. Multiplicative inverses and halo2 expressions are not counted.
+pub fn op_count<F: Debug, V: Debug>(expr: &Expr<F, V>) -> OpCount {
+    let mut count = OpCount::default();
+    let mut visited = HashSet::new();
+    count_ops(expr, &mut count, &mut visited);
+
+    count
+}
+
+fn count_ops<F: Debug, V: Debug>(
+    expr: &Expr<F, V>,
+    count: &mut OpCount,
+    visited: &mut HashSet<String>,
+) {
+    if !matches!(
+        expr,
+        Expr::Sum(_) | Expr::Mul(_) | Expr::Neg(_) | Expr::Pow(..) | Expr::MI(_)
+    ) || !visited.insert(format!("{:?}", expr))
+    {
+        return;
+    }
+
+    match expr {
+        Expr::Sum(ses) => {
+            count.adds += ses.len().saturating_sub(1);
+            ses.iter().for_each(|se| count_ops(se, count, visited));
+        }
+        Expr::Mul(ses) => {
+            count.muls += ses.len().saturating_sub(1);
+            ses.iter().for_each(|se| count_ops(se, count, visited));
+        }
+        Expr::Neg(se) => {
+            count.adds += 1;
+            count_ops(se, count, visited);
+        }
+        Expr::Pow(se, exp) => {
+            if *exp > 0 {
+                // a squaring per bit after the highest, and a multiplication per other set bit
+                count.muls += (31 - exp.leading_zeros() + exp.count_ones() - 1) as usize;
+            }
+            count_ops(se, count, visited);
+        }
+        Expr::MI(se) => count_ops(se, count, visited),
+        _ => {}
+    }
+}
+
+fn lower<F: Field, V: Clone + Debug>(expr: Expr<F, V>) -> Expr<F, V> {
+    match expr {
+        Expr::Sum(ses) => horner(ses.into_iter().map(Term::from).collect()),
+        Expr::Mul(ses) => product(ses.into_iter().map(lower).collect()),
+        Expr::Neg(se) => Expr::Neg(Box::new(lower(*se))),
+        Expr::Pow(se, exp) => power(lower(*se), exp),
+        Expr::MI(se) => Expr::MI(Box::new(lower(*se))),
+        expr => expr,
+    }
+}
+
+/// Term of a sum, as its factors, with their keys, and if it is negated.
+struct Term<F, V> {
+    negated: bool,
+    factors: Vec<(String, Expr<F, V>)>,
+}
+
+impl<F: Debug, V: Debug> From<Expr<F, V>> for Term<F, V> {
+    fn from(expr: Expr<F, V>) -> Self {
+        let (negated, expr) = match expr {
+            Expr::Neg(se) => (true, *se),
+            expr => (false, expr),
+        };
+        let factors = match expr {
+            Expr::Mul(ses) => ses,
+            expr => vec![expr],
+        };
+
+        Self {
+            negated,
+            factors: factors
+                .into_iter()
+                .map(|factor| (format!("{:?}", factor), factor))
+                .collect(),
+        }
+    }
+}
+
+impl<F: Field, V: Clone + Debug> Term<F, V> {
+    fn lower(self) -> Expr<F, V> {
+        let factors: Vec<_> = self
+            .factors
+            .into_iter()
+            .map(|(_, factor)| lower(factor))
+            .collect();
+        let term = if factors.is_empty() {
+            Expr::Const(F::ONE)
+        } else {
+            product(factors)
+        };
+
+        if self.negated {
+            Expr::Neg(Box::new(term))
+        } else {
+            term
+        }
+    }
+}
+
+/// Sum of `terms` in Horner form: the non-constant factor in the most terms, if in more than one,
+/// is factored out of them, recursively.
+fn horner<F: Field, V: Clone + Debug>(terms: Vec<Term<F, V>>) -> Expr<F, V> {
+    let mut candidates: Vec<(&String, usize)> = Vec::new();
+    for term in terms.iter() {
+        let mut seen = HashSet::new();
+        for (key, factor) in term.factors.iter() {
+            if matches!(factor, Expr::Const(_)) || !seen.insert(key) {
+                continue;
+            }
+            match candidates
+                .iter_mut()
+                .find(|(candidate, _)| *candidate == key)
+            {
+                Some((_, count)) => *count += 1,
+                None => candidates.push((key, 1)),
+            }
+        }
+    }
+    let common = candidates
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .max_by(|(a_key, a_count), (b_key, b_count)| a_count.cmp(b_count).then(b_key.cmp(a_key)))
+        .map(|(key, _)| key.clone());
+
+    let common = match common {
+        Some(common) => common,
+        None => {
+            let mut ses: Vec<_> = terms.into_iter().map(Term::lower).collect();
+            return if ses.len() == 1 {
+                ses.pop().unwrap()
+            } else {
+                Expr::Sum(ses)
+            };
+        }
+    };
+
+    let mut factor = None;
+    let mut with = Vec::new();
+    let mut without = Vec::new();
+    for mut term in terms {
+        match term.factors.iter().position(|(key, _)| *key == common) {
+            Some(index) => {
+                factor = Some(term.factors.remove(index).1);
+                with.push(term);
+            }
+            None => without.push(term),
+        }
+    }
+
+    let factored = product(vec![lower(factor.unwrap()), horner(with)]);
+    if without.is_empty() {
+        factored
+    } else {
+        match horner(without) {
+            Expr::Sum(mut ses) => {
+                ses.insert(0, factored);
+                Expr::Sum(ses)
+            }
+            rest => Expr::Sum(vec![factored, rest]),
+        }
+    }
+}
+
+/// Balanced tree of binary products of `factors`.
+fn product<F: Clone, V: Clone>(mut factors: Vec<Expr<F, V>>) -> Expr<F, V> {
+    if factors.len() == 1 {
+        return factors.pop().unwrap();
+    }
+
+    let right = factors.split_off(factors.len() / 2);
+    Expr::Mul(vec![product(factors), product(right)])
+}
+
+/// `base` to the power of `exp`, by repeated squaring.
+fn power<F: Field, V: Clone>(base: Expr<F, V>, exp: u32) -> Expr<F, V> {
+    match exp {
+        0 => Expr::Const(F::ONE),
+        1 => base,
+        exp if exp % 2 == 0 => {
+            let half = power(base, exp / 2);
+            Expr::Mul(vec![half.clone(), half])
+        }
+        exp => Expr::Mul(vec![power(base.clone(), exp - 1), base]),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        poly::ToExpr,
+        sbpir::{query::Queriable, InternalSignal},
+    };
+
+    #[test]
+    fn test_optimize_evaluation() {
+        let a: Queriable<Fr> = Queriable::Internal(InternalSignal::new("a"));
+        let b: Queriable<Fr> = Queriable::Internal(InternalSignal::new("b"));
+        let c: Queriable<Fr> = Queriable::Internal(InternalSignal::new("c"));
+        let x: Queriable<Fr> = Queriable::Internal(InternalSignal::new("x"));
+
+        let expr = a * x * x + b * x + c;
+        let optimized = optimize_evaluation(expr.clone());
+        assert_eq!(format!("{:?}", optimized), "((x * ((a * x) + b)) + c)");
+        assert_eq!(op_count(&expr), OpCount { muls: 3, adds: 2 });
+        assert_eq!(op_count(&optimized), OpCount { muls: 2, adds: 2 });
+
+        let assignments = HashMap::from([
+            (a, Fr::from(2)),
+            (b, Fr::from(3)),
+            (c, Fr::from(5)),
+            (x, Fr::from(7)),
+        ]);
+        assert_eq!(expr.eval(&assignments), optimized.eval(&assignments));
+
+        let expr = Expr::Pow(Box::new(a.expr()), 8);
+        let optimized = optimize_evaluation(expr.clone());
+        assert_eq!(op_count(&expr), OpCount { muls: 3, adds: 0 });
+        assert_eq!(op_count(&optimized), OpCount { muls: 3, adds: 0 });
+        assert_eq!(expr.eval(&assignments), optimized.eval(&assignments));
+
+        let expr = a * b * c * x;
+        assert_eq!(
+            format!("{:?}", optimize_evaluation(expr)),
+            "((a * b) * (c * x))"
+        );
+    }
+}
@@ -48,7 +48,9 @@ fn mi_elimination_recursive<
             exp,
         ),
         Expr::Query(_) => constr,
+        #[cfg(feature = "halo2")]
         Expr::Halo2Expr(_) => constr,
+        Expr::Challenge(..) => constr,
         Expr::MI(se) => {
             let se_elim = mi_elimination_recursive(decomp, *se.clone(), signal_factory);
 
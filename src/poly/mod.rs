@@ -2,15 +2,21 @@ use std::{
     collections::HashMap,
     fmt::Debug,
     hash::Hash,
+    iter::{Product, Sum},
     ops::{Add, Mul, Neg, Sub},
 };
 
+#[cfg(feature = "halo2")]
 use halo2_proofs::plonk::Expression;
+use num_bigint::BigUint;
 
-use crate::field::Field;
+use crate::{field::Field, util::UUID};
 
+pub mod intern;
+pub mod lowering;
 pub mod mielim;
 pub mod reduce;
+pub mod rewrite;
 pub mod simplify;
 
 pub trait ToExpr<F, V> {
@@ -29,22 +35,30 @@ pub enum Expr<F, V> {
     Neg(Box<Expr<F, V>>),
     Pow(Box<Expr<F, V>>, u32),
     Query(V),
+    #[cfg(feature = "halo2")]
     Halo2Expr(Expression<F>),
+    /// Verifier challenge, with its UUID and the phase of the advice columns it is drawn after.
+    Challenge(UUID, usize),
 
     MI(Box<Expr<F, V>>),
 }
 
 impl<F, V> Expr<F, V> {
+    /// Returns the degree of the expression in its queries. Challenges count as constants, and
+    /// multiplicative inverses as the query of the signal that replaces them in MI elimination.
+    /// Panics for halo2 expressions.
     pub fn degree(&self) -> usize {
         match self {
             Expr::Const(_) => 0,
-            Expr::Sum(ses) => ses.iter().map(|se| se.degree()).max().unwrap(),
+            Expr::Sum(ses) => ses.iter().map(|se| se.degree()).max().unwrap_or(0),
             Expr::Mul(ses) => ses.iter().fold(0, |acc, se| acc + se.degree()),
             Expr::Neg(se) => se.degree(),
             Expr::Pow(se, exp) => se.degree() * (*exp as usize),
             Expr::Query(_) => 1,
+            #[cfg(feature = "halo2")]
             Expr::Halo2Expr(_) => panic!("not implemented"),
-            Expr::MI(_) => panic!("not implemented"),
+            Expr::Challenge(..) => 0,
+            Expr::MI(se) => usize::from(se.degree() > 0),
         }
     }
 }
@@ -52,18 +66,7 @@ impl<F, V> Expr<F, V> {
 impl<F: Debug, V: Debug> Debug for Expr<F, V> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Const(arg0) => {
-                let formatted = format!("{:?}", arg0);
-                if formatted.starts_with("0x") {
-                    let s = format!(
-                        "0x{}",
-                        formatted.trim_start_matches("0x").trim_start_matches('0')
-                    );
-                    write!(f, "{}", s)
-                } else {
-                    write!(f, "{}", formatted)
-                }
-            }
+            Self::Const(arg0) => write!(f, "{}", fmt_value(arg0)),
             Self::Sum(arg0) => write!(
                 f,
                 "({})",
@@ -83,31 +86,98 @@ impl<F: Debug, V: Debug> Debug for Expr<F, V> {
             Self::Neg(arg0) => write!(f, "(-{:?})", arg0),
             Self::Pow(arg0, arg1) => write!(f, "({:?})^{}", arg0, arg1),
             Self::Query(arg0) => write!(f, "{:?}", arg0),
+            #[cfg(feature = "halo2")]
             Self::Halo2Expr(arg0) => write!(f, "halo2({:?})", arg0),
+            Self::Challenge(arg0, arg1) => write!(f, "challenge{}({})", arg1, arg0),
             Self::MI(arg0) => write!(f, "mi({:?})", arg0),
         }
     }
 }
 
+/// Formats a field value without the leading zeros of its hex representation.
+pub fn fmt_value<F: Debug>(value: &F) -> String {
+    let formatted = format!("{:?}", value);
+    if formatted.starts_with("0x") {
+        format!(
+            "0x{}",
+            formatted.trim_start_matches("0x").trim_start_matches('0')
+        )
+    } else {
+        formatted
+    }
+}
+
 pub type VarAssignments<F, V> = HashMap<V, F>;
 
 impl<F: Field + Hash, V: Eq + PartialEq + Hash> Expr<F, V> {
     pub fn eval(&self, assignments: &VarAssignments<F, V>) -> Option<F> {
+        self.eval_with(&|q| assignments.get(q).copied())
+    }
+}
+
+impl<F: Field, V> Expr<F, V> {
+    /// Evaluates the expression with the values of the queries given by `value`, `None` if some
+    /// query has no value.
+    pub fn eval_with<R: Fn(&V) -> Option<F>>(&self, value: &R) -> Option<F> {
         match self {
             Expr::Const(v) => Some(*v),
             Expr::Sum(ses) => ses
                 .iter()
-                .try_fold(F::ZERO, |acc, se| Some(acc + se.eval(assignments)?)),
+                .try_fold(F::ZERO, |acc, se| Some(acc + se.eval_with(value)?)),
             Expr::Mul(ses) => ses
                 .iter()
-                .try_fold(F::ONE, |acc, se| Some(acc * se.eval(assignments)?)),
-            Expr::Neg(se) => Some(F::ZERO - se.eval(assignments)?),
-            Expr::Pow(se, exp) => Some(se.eval(assignments)?.pow([*exp as u64])),
-            Expr::Query(q) => assignments.get(q).copied(),
-            Expr::MI(se) => Some(se.eval(assignments)?.mi()),
+                .try_fold(F::ONE, |acc, se| Some(acc * se.eval_with(value)?)),
+            Expr::Neg(se) => Some(F::ZERO - se.eval_with(value)?),
+            Expr::Pow(se, exp) => Some(se.eval_with(value)?.pow([*exp as u64])),
+            Expr::Query(q) => value(q),
+            Expr::MI(se) => Some(se.eval_with(value)?.mi()),
 
             // Not implemented, and not necessary for aexpr
+            #[cfg(feature = "halo2")]
             Expr::Halo2Expr(_) => None,
+            // Drawn by the verifier, unknown in witness generation
+            Expr::Challenge(..) => None,
+        }
+    }
+}
+
+impl<F: Field + Debug, V: Debug> Expr<F, V> {
+    /// Returns a line for the expression and each of its sub-expressions and queries, indented by
+    /// depth, with its value given the values of the queries given by `value`, or `?` if it cannot
+    /// be evaluated. Constants are left out.
+    pub fn explain_with<R: Fn(&V) -> Option<F>>(&self, value: &R) -> Vec<String> {
+        let mut lines = Vec::new();
+        self.explain_into(value, 0, &mut lines);
+
+        lines
+    }
+
+    fn explain_into<R: Fn(&V) -> Option<F>>(
+        &self,
+        value: &R,
+        depth: usize,
+        lines: &mut Vec<String>,
+    ) {
+        if let Expr::Const(_) = self {
+            return;
+        }
+
+        let evaluated = self
+            .eval_with(value)
+            .map(|v| fmt_value(&v))
+            .unwrap_or_else(|| "?".to_string());
+        lines.push(format!("{}{:?} = {}", "  ".repeat(depth), self, evaluated));
+
+        match self {
+            Expr::Sum(ses) | Expr::Mul(ses) => {
+                for se in ses {
+                    se.explain_into(value, depth + 1, lines);
+                }
+            }
+            Expr::Neg(se) | Expr::Pow(se, _) | Expr::MI(se) => {
+                se.explain_into(value, depth + 1, lines)
+            }
+            _ => {}
         }
     }
 }
@@ -170,6 +240,65 @@ impl<F, V> Neg for Expr<F, V> {
     }
 }
 
+impl<F: Clone, V: Clone, RHS: Into<Expr<F, V>>> Add<RHS> for &Expr<F, V> {
+    type Output = Expr<F, V>;
+    fn add(self, rhs: RHS) -> Expr<F, V> {
+        self.clone() + rhs
+    }
+}
+
+impl<F: Clone, V: Clone, RHS: Into<Expr<F, V>>> Sub<RHS> for &Expr<F, V> {
+    type Output = Expr<F, V>;
+    fn sub(self, rhs: RHS) -> Expr<F, V> {
+        self.clone() - rhs
+    }
+}
+
+impl<F: Clone, V: Clone, RHS: Into<Expr<F, V>>> Mul<RHS> for &Expr<F, V> {
+    type Output = Expr<F, V>;
+    fn mul(self, rhs: RHS) -> Expr<F, V> {
+        self.clone() * rhs
+    }
+}
+
+impl<F: Clone, V: Clone> Neg for &Expr<F, V> {
+    type Output = Expr<F, V>;
+    fn neg(self) -> Expr<F, V> {
+        self.clone().neg()
+    }
+}
+
+impl<F: Clone, V: Clone> From<&Expr<F, V>> for Expr<F, V> {
+    #[inline]
+    fn from(value: &Expr<F, V>) -> Self {
+        value.clone()
+    }
+}
+
+/// Adds the expressions, the empty sum being zero.
+impl<F: From<u64>, V, T: Into<Expr<F, V>>> Sum<T> for Expr<F, V> {
+    fn sum<I: Iterator<Item = T>>(iter: I) -> Self {
+        let mut terms: Vec<Expr<F, V>> = iter.map(Into::into).collect();
+        match terms.len() {
+            0 => Expr::Const(F::from(0)),
+            1 => terms.pop().unwrap(),
+            _ => Expr::Sum(terms),
+        }
+    }
+}
+
+/// Multiplies the expressions, the empty product being one.
+impl<F: From<u64>, V, T: Into<Expr<F, V>>> Product<T> for Expr<F, V> {
+    fn product<I: Iterator<Item = T>>(iter: I) -> Self {
+        let mut factors: Vec<Expr<F, V>> = iter.map(Into::into).collect();
+        match factors.len() {
+            0 => Expr::Const(F::from(1)),
+            1 => factors.pop().unwrap(),
+            _ => Expr::Mul(factors),
+        }
+    }
+}
+
 macro_rules! impl_expr_like {
     ($type:ty) => {
         impl<F: From<u64>, V> From<$type> for Expr<F, V> {
@@ -201,32 +330,135 @@ impl_expr_like!(u32);
 impl_expr_like!(u64);
 impl_expr_like!(usize);
 
-impl<F: Field + From<u64>, V> From<i32> for Expr<F, V> {
-    #[inline]
-    fn from(value: i32) -> Self {
-        Expr::Const(
-            F::from(value.unsigned_abs() as u64)
-                * if value.is_negative() { -F::ONE } else { F::ONE },
-        )
+macro_rules! impl_signed_expr_like {
+    ($type:ty) => {
+        impl<F: Field + From<u64>, V> From<$type> for Expr<F, V> {
+            #[inline]
+            fn from(value: $type) -> Self {
+                Expr::Const(value.field())
+            }
+        }
+
+        impl<F: Field + From<u64>, V> ToExpr<F, V> for $type {
+            #[inline]
+            fn expr(&self) -> Expr<F, V> {
+                Expr::Const(self.field())
+            }
+        }
+
+        impl<F: Field + From<u64>> ToField<F> for $type {
+            #[inline]
+            fn field(&self) -> F {
+                F::from(self.unsigned_abs() as u64)
+                    * if self.is_negative() { -F::ONE } else { F::ONE }
+            }
+        }
+    };
+}
+
+impl_signed_expr_like!(i32);
+impl_signed_expr_like!(i64);
+
+/// Implements the arithmetic operators with an integer on the left-hand side, converting it with
+/// its `From` implementation.
+macro_rules! impl_int_lhs_ops {
+    ($type:ty: $($bound:tt)+) => {
+        impl<F: $($bound)+, V> Add<Expr<F, V>> for $type {
+            type Output = Expr<F, V>;
+            fn add(self, rhs: Expr<F, V>) -> Expr<F, V> {
+                Expr::from(self) + rhs
+            }
+        }
+
+        impl<F: $($bound)+, V> Sub<Expr<F, V>> for $type {
+            type Output = Expr<F, V>;
+            fn sub(self, rhs: Expr<F, V>) -> Expr<F, V> {
+                Expr::from(self) - rhs
+            }
+        }
+
+        impl<F: $($bound)+, V> Mul<Expr<F, V>> for $type {
+            type Output = Expr<F, V>;
+            fn mul(self, rhs: Expr<F, V>) -> Expr<F, V> {
+                Expr::from(self) * rhs
+            }
+        }
+
+        impl<F: Clone + $($bound)+, V: Clone> Add<&Expr<F, V>> for $type {
+            type Output = Expr<F, V>;
+            fn add(self, rhs: &Expr<F, V>) -> Expr<F, V> {
+                Expr::from(self) + rhs
+            }
+        }
+
+        impl<F: Clone + $($bound)+, V: Clone> Sub<&Expr<F, V>> for $type {
+            type Output = Expr<F, V>;
+            fn sub(self, rhs: &Expr<F, V>) -> Expr<F, V> {
+                Expr::from(self) - rhs
+            }
+        }
+
+        impl<F: Clone + $($bound)+, V: Clone> Mul<&Expr<F, V>> for $type {
+            type Output = Expr<F, V>;
+            fn mul(self, rhs: &Expr<F, V>) -> Expr<F, V> {
+                Expr::from(self) * rhs
+            }
+        }
+    };
+}
+
+impl_int_lhs_ops!(u8: From<u64>);
+impl_int_lhs_ops!(u32: From<u64>);
+impl_int_lhs_ops!(u64: From<u64>);
+impl_int_lhs_ops!(usize: From<u64>);
+impl_int_lhs_ops!(i32: Field + From<u64>);
+impl_int_lhs_ops!(i64: Field + From<u64>);
+
+/// Reduces the integer modulo the field.
+impl<F: Field + From<u64>> ToField<F> for BigUint {
+    fn field(&self) -> F {
+        let base = F::from(2).pow([64]);
+
+        self.iter_u64_digits()
+            .rev()
+            .fold(F::ZERO, |acc, digit| acc * base + F::from(digit))
     }
 }
 
-impl<F: Field + From<u64>, V> ToExpr<F, V> for i32 {
+impl<F: Field + From<u64>, V> From<BigUint> for Expr<F, V> {
     #[inline]
-    fn expr(&self) -> Expr<F, V> {
-        Expr::Const(
-            F::from(self.unsigned_abs() as u64) * if self.is_negative() { -F::ONE } else { F::ONE },
-        )
+    fn from(value: BigUint) -> Self {
+        Expr::Const(value.field())
     }
 }
 
-impl<F: Field + From<u64>> ToField<F> for i32 {
+impl<F: Field + From<u64>, V> From<&BigUint> for Expr<F, V> {
     #[inline]
-    fn field(&self) -> F {
-        F::from(self.unsigned_abs() as u64) * if self.is_negative() { -F::ONE } else { F::ONE }
+    fn from(value: &BigUint) -> Self {
+        Expr::Const(value.field())
+    }
+}
+
+impl<F: Field + From<u64>, V> Expr<F, V> {
+    /// Parses a hexadecimal constant, with or without the `0x` prefix, reduced modulo the field.
+    /// Returns `None` if it is not a valid number.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let digits = hex
+            .strip_prefix("0x")
+            .or_else(|| hex.strip_prefix("0X"))
+            .unwrap_or(hex);
+
+        BigUint::parse_bytes(digits.as_bytes(), 16).map(Expr::from)
+    }
+
+    /// Parses a decimal constant, reduced modulo the field. Returns `None` if it is not a valid
+    /// number.
+    pub fn from_dec_str(dec: &str) -> Option<Self> {
+        BigUint::parse_bytes(dec.as_bytes(), 10).map(Expr::from)
     }
 }
 
+#[cfg(feature = "halo2")]
 impl<F, V> From<Expression<F>> for Expr<F, V> {
     #[inline]
     fn from(value: Expression<F>) -> Self {
@@ -271,6 +503,7 @@ impl<F: Clone, V: Clone + Eq + PartialEq + Hash> ConstrDecomp<F, V> {
 #[cfg(test)]
 mod test {
     use halo2_proofs::halo2curves::bn256::Fr;
+    use num_bigint::BigUint;
 
     use crate::{field::Field, poly::VarAssignments};
 
@@ -323,6 +556,30 @@ mod test {
         assert_eq!(experiment.eval(&assignments), None)
     }
 
+    #[test]
+    fn explain_expr() {
+        use super::Expr::*;
+
+        let experiment: Expr<Fr, &str> = (Query("a") * Query("b")) + Query("c") - Const(Fr::ONE);
+        let value = |q: &&str| match *q {
+            "a" => Some(Fr::from(2)),
+            "b" => Some(Fr::from(3)),
+            _ => None,
+        };
+
+        assert_eq!(
+            experiment.explain_with(&value),
+            vec![
+                "((\"a\" * \"b\") + \"c\" + (-0x1)) = ?",
+                "  (\"a\" * \"b\") = 0x6",
+                "    \"a\" = 0x2",
+                "    \"b\" = 0x3",
+                "  \"c\" = ?",
+                "  (-0x1) = 0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000000",
+            ]
+        );
+    }
+
     #[test]
     fn test_degree_expr() {
         use super::Expr::*;
@@ -387,4 +644,80 @@ mod test {
             format!("{:?}", expr)
         );
     }
+
+    #[test]
+    fn test_expr_ref_and_int_ops() {
+        use super::Expr::*;
+
+        let a: Expr<Fr, &str> = Query("a");
+        let b: Expr<Fr, &str> = Query("b");
+        let mut assignments: VarAssignments<Fr, &str> = VarAssignments::default();
+        assignments.insert("a", Fr::from(2));
+        assignments.insert("b", Fr::from(3));
+
+        let expr = &a * &b + 4u64 - &a;
+        assert_eq!(expr.eval(&assignments), Some(Fr::from(8)));
+        assert_eq!(a.eval(&assignments), Some(Fr::from(2)));
+
+        let expr = 5u64 * &a - 2usize * b.clone() + (-1i64 - &b);
+        assert_eq!(expr.eval(&assignments), Some(Fr::from(0)));
+
+        let expr = 10i32 - a + -&b;
+        assert_eq!(expr.eval(&assignments), Some(Fr::from(5)));
+    }
+
+    #[test]
+    fn test_expr_sum_product() {
+        use super::Expr::*;
+
+        let terms: Vec<Expr<Fr, &str>> = vec![Query("a"), Query("b"), Const(Fr::from(4))];
+        let mut assignments: VarAssignments<Fr, &str> = VarAssignments::default();
+        assignments.insert("a", Fr::from(2));
+        assignments.insert("b", Fr::from(3));
+
+        let sum: Expr<Fr, &str> = terms.iter().sum();
+        assert_eq!(sum.eval(&assignments), Some(Fr::from(9)));
+        let product: Expr<Fr, &str> = terms.into_iter().product();
+        assert_eq!(product.eval(&assignments), Some(Fr::from(24)));
+
+        let sum: Expr<Fr, &str> = (1u64..=4).sum();
+        assert_eq!(sum.eval(&assignments), Some(Fr::from(10)));
+        let empty: Vec<Expr<Fr, &str>> = vec![];
+        let product: Expr<Fr, &str> = empty.into_iter().product();
+        assert_eq!(product.eval(&assignments), Some(Fr::ONE));
+    }
+
+    #[test]
+    fn test_expr_from_big_constants() {
+        let assignments: VarAssignments<Fr, &str> = VarAssignments::default();
+        let eval = |expr: Expr<Fr, &str>| expr.eval(&assignments).unwrap();
+
+        assert_eq!(eval(Expr::from_hex("0x1f").unwrap()), Fr::from(31));
+        assert_eq!(eval(Expr::from_hex("FF").unwrap()), Fr::from(255));
+        assert_eq!(
+            eval(
+                Expr::from_hex(
+                    "0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001"
+                )
+                .unwrap()
+            ),
+            Fr::ZERO
+        );
+        assert_eq!(
+            eval(
+                Expr::from_dec_str(
+                    "21888242871839275222246405745257275088548364400416034343698204186575808495618"
+                )
+                .unwrap()
+            ),
+            Fr::ONE
+        );
+        assert_eq!(
+            eval(Expr::from(BigUint::from(u128::MAX))),
+            Fr::from(u64::MAX) * Fr::from(2).pow([64]) + Fr::from(u64::MAX)
+        );
+
+        assert!(Expr::<Fr, &str>::from_hex("0xfg").is_none());
+        assert!(Expr::<Fr, &str>::from_dec_str("12a").is_none());
+    }
 }
@@ -87,6 +87,8 @@ fn reduce_degree_recursive<
             signal_factory,
         ),
         Expr::Query(_) => constr,
+        Expr::Challenge(..) => constr,
+        #[cfg(feature = "halo2")]
         Expr::Halo2Expr(_) => unimplemented!(),
         Expr::MI(_) => unimplemented!(),
     }
@@ -0,0 +1,235 @@
+//! User-defined rewrite rules over expressions, for domain-specific algebraic optimizations.
+//!
+//! A rule is a pattern and its replacement, both expressions whose queries are metavariables,
+//! named by strings. A metavariable matches any sub-expression, and the same one every time it
+//! appears in a pattern. Sums and products match operand by operand, in order, so patterns are
+//! best written for expressions in normal form, see `simplify`.
+
+use std::collections::HashMap;
+
+use crate::field::Field;
+
+use super::Expr;
+
+/// Expression whose queries are metavariables.
+pub type Pattern<F> = Expr<F, &'static str>;
+
+/// Rewrites of a single expression after which the rules are considered not to terminate.
+const MAX_REWRITES: usize = 1000;
+
+/// Metavariable named `name`, matching any sub-expression.
+pub fn var<F>(name: &'static str) -> Pattern<F> {
+    Expr::Query(name)
+}
+
+#[derive(Clone, Debug)]
+pub struct RewriteRule<F> {
+    pub name: String,
+    pub pattern: Pattern<F>,
+    pub replacement: Pattern<F>,
+}
+
+impl<F> RewriteRule<F> {
+    /// Creates a rule replacing the expressions matching `pattern` by `replacement`. Panics if
+    /// `replacement` has a metavariable that is not in `pattern`.
+    pub fn new<N: Into<String>>(name: N, pattern: Pattern<F>, replacement: Pattern<F>) -> Self {
+        let name = name.into();
+        let mut bound = Vec::new();
+        vars(&pattern, &mut bound);
+        let mut used = Vec::new();
+        vars(&replacement, &mut used);
+        if let Some(unbound) = used.iter().find(|var| !bound.contains(var)) {
+            panic!(
+                "rewrite rule {}: metavariable {} is not in the pattern",
+                name, unbound
+            );
+        }
+
+        Self {
+            name,
+            pattern,
+            replacement,
+        }
+    }
+}
+
+impl<F: Field, V: Clone + PartialEq> Expr<F, V> {
+    /// Returns the expression rewritten with `rules`, see `rewrite`.
+    pub fn rewrite(&self, rules: &[RewriteRule<F>]) -> Self {
+        rewrite(self, rules)
+    }
+}
+
+/// Rewrites the sub-expressions of `expr`, from the leaves up, with the first of `rules` whose
+/// pattern matches them, until none matches. Panics if the rules keep rewriting an expression.
+pub fn rewrite<F: Field, V: Clone + PartialEq>(
+    expr: &Expr<F, V>,
+    rules: &[RewriteRule<F>],
+) -> Expr<F, V> {
+    if rules.is_empty() {
+        return expr.clone();
+    }
+
+    let mut fuel = MAX_REWRITES;
+    rewrite_with_fuel(expr, rules, &mut fuel)
+}
+
+fn rewrite_with_fuel<F: Field, V: Clone + PartialEq>(
+    expr: &Expr<F, V>,
+    rules: &[RewriteRule<F>],
+    fuel: &mut usize,
+) -> Expr<F, V> {
+    let expr = match expr {
+        Expr::Sum(ses) => Expr::Sum(
+            ses.iter()
+                .map(|se| rewrite_with_fuel(se, rules, fuel))
+                .collect(),
+        ),
+        Expr::Mul(ses) => Expr::Mul(
+            ses.iter()
+                .map(|se| rewrite_with_fuel(se, rules, fuel))
+                .collect(),
+        ),
+        Expr::Neg(se) => Expr::Neg(Box::new(rewrite_with_fuel(se, rules, fuel))),
+        Expr::Pow(se, exp) => Expr::Pow(Box::new(rewrite_with_fuel(se, rules, fuel)), *exp),
+        Expr::MI(se) => Expr::MI(Box::new(rewrite_with_fuel(se, rules, fuel))),
+        _ => expr.clone(),
+    };
+
+    for rule in rules {
+        let mut bindings = HashMap::new();
+        if match_pattern(&rule.pattern, &expr, &mut bindings) {
+            if *fuel == 0 {
+                panic!("rewrite rules do not terminate, last applied {}", rule.name);
+            }
+            *fuel -= 1;
+
+            let rewritten = substitute(&rule.replacement, &bindings);
+            return rewrite_with_fuel(&rewritten, rules, fuel);
+        }
+    }
+
+    expr
+}
+
+/// Returns if `expr` matches `pattern`, binding its metavariables in `bindings`.
+fn matches<F: Field, V: Clone + PartialEq>(
+    pattern: &Pattern<F>,
+    expr: &Expr<F, V>,
+    bindings: &mut HashMap<&'static str, Expr<F, V>>,
+) -> bool {
+    match (pattern, expr) {
+        (Expr::Query(var), _) => match bindings.get(var) {
+            Some(bound) => equal(bound, expr),
+            None => {
+                bindings.insert(*var, expr.clone());
+                true
+            }
+        },
+        (Expr::Const(p), Expr::Const(e)) => p == e,
+        (Expr::Sum(ps), Expr::Sum(es)) | (Expr::Mul(ps), Expr::Mul(es)) => {
+            ps.len() == es.len()
+                && ps
+                    .iter()
+                    .zip(es.iter())
+                    .all(|(p, e)| match_pattern(p, e, bindings))
+        }
+        (Expr::Neg(p), Expr::Neg(e)) | (Expr::MI(p), Expr::MI(e)) => match_pattern(p, e, bindings),
+        (Expr::Pow(p, p_exp), Expr::Pow(e, e_exp)) => {
+            p_exp == e_exp && match_pattern(p, e, bindings)
+        }
+        (Expr::Challenge(p_id, _), Expr::Challenge(e_id, _)) => p_id == e_id,
+        _ => false,
+    }
+}
+
+/// Structural equality of expressions. Halo2 expressions are never equal.
+fn equal<F: Field, V: PartialEq>(a: &Expr<F, V>, b: &Expr<F, V>) -> bool {
+    match (a, b) {
+        (Expr::Const(a), Expr::Const(b)) => a == b,
+        (Expr::Sum(a), Expr::Sum(b)) | (Expr::Mul(a), Expr::Mul(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| equal(a, b))
+        }
+        (Expr::Neg(a), Expr::Neg(b)) | (Expr::MI(a), Expr::MI(b)) => equal(a, b),
+        (Expr::Pow(a, a_exp), Expr::Pow(b, b_exp)) => a_exp == b_exp && equal(a, b),
+        (Expr::Query(a), Expr::Query(b)) => a == b,
+        (Expr::Challenge(a, _), Expr::Challenge(b, _)) => a == b,
+        _ => false,
+    }
+}
+
+fn substitute<F: Clone, V: Clone>(
+    pattern: &Pattern<F>,
+    bindings: &HashMap<&'static str, Expr<F, V>>,
+) -> Expr<F, V> {
+    match pattern {
+        Expr::Const(v) => Expr::Const(v.clone()),
+        Expr::Sum(ps) => Expr::Sum(ps.iter().map(|p| substitute(p, bindings)).collect()),
+        Expr::Mul(ps) => Expr::Mul(ps.iter().map(|p| substitute(p, bindings)).collect()),
+        Expr::Neg(p) => Expr::Neg(Box::new(substitute(p, bindings))),
+        Expr::Pow(p, exp) => Expr::Pow(Box::new(substitute(p, bindings)), *exp),
+        Expr::Query(var) => bindings[var].clone(),
+        #[cfg(feature = "halo2")]
+        Expr::Halo2Expr(e) => Expr::Halo2Expr(e.clone()),
+        Expr::Challenge(id, phase) => Expr::Challenge(*id, *phase),
+        Expr::MI(p) => Expr::MI(Box::new(substitute(p, bindings))),
+    }
+}
+
+fn vars<F>(pattern: &Pattern<F>, result: &mut Vec<&'static str>) {
+    match pattern {
+        Expr::Sum(ps) | Expr::Mul(ps) => ps.iter().for_each(|p| vars(p, result)),
+        Expr::Neg(p) | Expr::Pow(p, _) | Expr::MI(p) => vars(p, result),
+        Expr::Query(var) => result.push(*var),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        poly::ToExpr,
+        sbpir::{query::Queriable, InternalSignal},
+    };
+
+    #[test]
+    fn test_rewrite() {
+        let a: Queriable<Fr> = Queriable::Internal(InternalSignal::new("a"));
+        let b: Queriable<Fr> = Queriable::Internal(InternalSignal::new("b"));
+        let square = RewriteRule::new(
+            "square",
+            var::<Fr>("x") * var("x"),
+            Expr::Pow(Box::new(var("x")), 2),
+        );
+        let double_square = RewriteRule::new(
+            "double square",
+            Expr::Pow(Box::new(Expr::Pow(Box::new(var::<Fr>("x")), 2)), 2),
+            Expr::Pow(Box::new(var("x")), 4),
+        );
+        let rules = [square, double_square];
+
+        assert_eq!(
+            format!("{:?}", (a * a + a * b).rewrite(&rules)),
+            "((a)^2 + (a * b))"
+        );
+        // the squares of a * a are rewritten into a power of a power, and then into one power
+        assert_eq!(
+            format!("{:?}", Expr::Mul(vec![a * a, a * a]).rewrite(&rules)),
+            "(a)^4"
+        );
+        assert_eq!(
+            format!("{:?}", ((a + b) * (a + b)).rewrite(&rules)),
+            "((a + b))^2"
+        );
+        assert_eq!(format!("{:?}", a.expr().rewrite(&rules)), "a");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_rewrite_unbound() {
+        RewriteRule::<Fr>::new("unbound", var("x"), var("y"));
+    }
+}
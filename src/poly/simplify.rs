@@ -1,4 +1,4 @@
-use std::hash::Hash;
+use std::{fmt::Debug, hash::Hash};
 
 use crate::field::Field;
 
@@ -46,13 +46,152 @@ pub fn simplify_mul<F: Field, V: Clone + Eq + PartialEq + Hash>(
     ses
 }
 
+impl<F: Field, V: Clone + Debug> Expr<F, V> {
+    /// Returns the expression in normal form, see `simplify`.
+    pub fn simplify(self) -> Self {
+        simplify(self)
+    }
+}
+
+/// Brings an expression to a normal form, equivalent to it: nested sums and products are
+/// flattened, their constants folded into one, double negations and terms cancelling their
+/// negation removed, and the operands sorted in a canonical order, with the constant first in
+/// products and last in sums. Products with a zero constant become zero, and powers and
+/// multiplicative inverses of constants are evaluated.
+pub fn simplify<F: Field, V: Clone + Debug>(expr: Expr<F, V>) -> Expr<F, V> {
+    match expr {
+        Expr::Sum(ses) => simplify_sum(ses.into_iter().map(simplify).collect()),
+        Expr::Mul(ses) => simplify_product(ses.into_iter().map(simplify).collect()),
+        Expr::Neg(se) => match simplify(*se) {
+            Expr::Const(v) => Expr::Const(-v),
+            Expr::Neg(se) => *se,
+            se => Expr::Neg(Box::new(se)),
+        },
+        Expr::Pow(se, exp) => match (simplify(*se), exp) {
+            (_, 0) => Expr::Const(F::ONE),
+            (se, 1) => se,
+            (Expr::Const(v), exp) => Expr::Const(v.pow([exp as u64])),
+            (se, exp) => Expr::Pow(Box::new(se), exp),
+        },
+        Expr::MI(se) => match simplify(*se) {
+            Expr::Const(v) => Expr::Const(v.mi()),
+            se => Expr::MI(Box::new(se)),
+        },
+        expr => expr,
+    }
+}
+
+fn simplify_sum<F: Field, V: Clone + Debug>(ses: Vec<Expr<F, V>>) -> Expr<F, V> {
+    let mut constant = F::ZERO;
+    let mut terms: Vec<(String, Expr<F, V>)> = Vec::new();
+
+    for se in flatten_sum(ses) {
+        match se {
+            Expr::Const(v) => constant += v,
+            se => terms.push((canonical_key(&se), se)),
+        }
+    }
+
+    // a term and its negation cancel out
+    let mut index = 0;
+    while index < terms.len() {
+        let cancelled = match &terms[index].1 {
+            Expr::Neg(se) => {
+                let key = canonical_key(se);
+                terms.iter().position(|(other, _)| *other == key)
+            }
+            _ => None,
+        };
+        match cancelled {
+            Some(other) => {
+                let (first, second) = (index.max(other), index.min(other));
+                terms.remove(first);
+                terms.remove(second);
+                index = index.min(other);
+            }
+            None => index += 1,
+        }
+    }
+
+    terms.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let mut ses: Vec<Expr<F, V>> = terms.into_iter().map(|(_, se)| se).collect();
+    if constant != F::ZERO || ses.is_empty() {
+        ses.push(Expr::Const(constant));
+    }
+
+    if ses.len() == 1 {
+        ses.pop().unwrap()
+    } else {
+        Expr::Sum(ses)
+    }
+}
+
+fn flatten_sum<F, V>(ses: Vec<Expr<F, V>>) -> Vec<Expr<F, V>> {
+    let mut result = Vec::new();
+    for se in ses {
+        match se {
+            Expr::Sum(ses) => result.extend(flatten_sum(ses)),
+            se => result.push(se),
+        }
+    }
+
+    result
+}
+
+fn simplify_product<F: Field, V: Clone + Debug>(ses: Vec<Expr<F, V>>) -> Expr<F, V> {
+    let mut constant = F::ONE;
+    let mut negated = false;
+    let mut factors: Vec<(String, Expr<F, V>)> = Vec::new();
+
+    let mut pending = ses;
+    while let Some(se) = pending.pop() {
+        match se {
+            Expr::Const(v) => constant *= v,
+            Expr::Mul(ses) => pending.extend(ses),
+            // negations are pulled out of the product
+            Expr::Neg(se) => {
+                negated = !negated;
+                pending.push(*se);
+            }
+            se => factors.push((canonical_key(&se), se)),
+        }
+    }
+
+    if constant == F::ZERO {
+        return Expr::Const(F::ZERO);
+    }
+
+    factors.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let mut ses: Vec<Expr<F, V>> = factors.into_iter().map(|(_, se)| se).collect();
+    if constant != F::ONE || ses.is_empty() {
+        ses.insert(0, Expr::Const(constant));
+    }
+
+    let product = if ses.len() == 1 {
+        ses.pop().unwrap()
+    } else {
+        Expr::Mul(ses)
+    };
+
+    if negated {
+        Expr::Neg(Box::new(product))
+    } else {
+        product
+    }
+}
+
+/// Key of the canonical order of the operands of sums and products.
+fn canonical_key<F: Debug, V: Debug>(expr: &Expr<F, V>) -> String {
+    format!("{:?}", expr)
+}
+
 #[cfg(test)]
 mod test {
     use halo2_proofs::halo2curves::bn256::Fr;
 
     use crate::{
         poly::{
-            simplify::{assoc_mul_simplify, const_mul_simplify, simplify_mul},
+            simplify::{assoc_mul_simplify, const_mul_simplify, simplify, simplify_mul},
             ToExpr,
         },
         sbpir::{query::Queriable, InternalSignal},
@@ -160,4 +299,29 @@ mod test {
             "(0x6 * a * b * (c + (a * 0x4)))"
         );
     }
+
+    #[test]
+    fn test_simplify() {
+        let a: Queriable<Fr> = Queriable::Internal(InternalSignal::new("a"));
+        let b: Queriable<Fr> = Queriable::Internal(InternalSignal::new("b"));
+        let c: Queriable<Fr> = Queriable::Internal(InternalSignal::new("c"));
+
+        assert_eq!(
+            format!("{:#?}", simplify((b + 1) + (a + 2) + c * (a * 3))),
+            "((0x3 * a * c) + a + b + 0x3)"
+        );
+        assert_eq!(format!("{:#?}", simplify(a + b - a)), "b");
+        assert!(matches!(simplify(-(-(a * 2)) * 0), Const(v) if v == Fr::from(0)));
+        assert_eq!(format!("{:#?}", simplify((-a) * b * c)), "(-(a * b * c))");
+        assert_eq!(format!("{:#?}", (-a * 2).simplify()), "(-(0x2 * a))");
+        assert_eq!(format!("{:#?}", simplify(Pow(Box::new(a + 0), 1))), "a");
+        assert_eq!(
+            format!("{:#?}", simplify(Pow(Box::new(a * 0 + 6), 2))),
+            "0x24"
+        );
+        assert_eq!(
+            format!("{:#?}", simplify(c * b + 1 - 1)),
+            format!("{:#?}", simplify(b * c))
+        );
+    }
 }
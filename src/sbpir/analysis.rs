@@ -0,0 +1,384 @@
+//! Static analyses of circuits, that find soundness bugs from the circuit alone, without a
+//! witness.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+    hash::Hash,
+};
+
+use crate::{
+    field::Field,
+    poly::Expr,
+    sbpir::{query::Queriable, SourceLocation, StepType, PIR, SBPIR},
+    wit_gen::FixedAssignment,
+};
+
+/// Signals that a prover can assign any value to, as no constraint, lookup, gadget call or
+/// exposure queries them, found by `unconstrained_signals`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UnconstrainedSignals {
+    /// Names of the step types that leave each signal unconstrained, sorted, by the annotation of
+    /// the signal.
+    pub signals: BTreeMap<String, Vec<String>>,
+}
+
+impl UnconstrainedSignals {
+    pub fn is_empty(&self) -> bool {
+        self.signals.is_empty()
+    }
+}
+
+impl fmt::Display for UnconstrainedSignals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (signal, step_types) in self.signals.iter() {
+            writeln!(f, "{}: unconstrained in {}", signal, step_types.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Finds the signals that each step type assigns in the witness but does not constrain, the
+/// most common soundness bug of hand-written circuits. Values computed by `auto` are not
+/// constrained, unless a constraint also queries them.
+///
+/// An internal signal is constrained by the constraints, transition constraints, lookups and
+/// gadget calls of its step type. The value of a forward or shared signal in a step is
+/// constrained by the step type of the step querying it, by a transition from the previous step,
+/// as any step type querying the next value of a forward signal, or a rotated shared signal, may
+/// precede it, by its exposure, and by the padding step, which keeps the previous values.
+pub fn unconstrained_signals<F: Clone, TraceArgs>(
+    circuit: &SBPIR<F, TraceArgs>,
+) -> UnconstrainedSignals {
+    let queries: Vec<_> = circuit
+        .step_types
+        .values()
+        .map(|step_type| (step_type, step_type_queries(step_type)))
+        .collect();
+    // values constrained in any step, from the step before or through the instance column
+    let mut always_constrained: HashSet<_> = circuit
+        .exposed
+        .iter()
+        .map(|(query, _)| query.uuid())
+        .collect();
+    for (_, step_queries) in queries.iter() {
+        always_constrained.extend(step_queries.iter().filter_map(|query| match query {
+            Queriable::Forward(signal, true) => Some(signal.uuid()),
+            Queriable::Shared(signal, rotation) if *rotation != 0 => Some(signal.uuid()),
+            _ => None,
+        }));
+    }
+
+    let mut report = UnconstrainedSignals::default();
+    for (step_type, step_queries) in queries {
+        let queried: HashSet<_> = step_queries
+            .iter()
+            .filter_map(|query| match query {
+                Queriable::Internal(..)
+                | Queriable::Forward(_, false)
+                | Queriable::Shared(_, 0) => Some(query.uuid()),
+                _ => None,
+            })
+            .collect();
+        let is_padding = circuit.padding_step == Some(step_type.uuid());
+
+        let internal = step_type
+            .signals
+            .iter()
+            .filter(|signal| !queried.contains(&signal.uuid()))
+            .map(|signal| signal.annotation());
+        let forward = circuit
+            .forward_signals
+            .iter()
+            .filter(|signal| !is_padding && !queried.contains(&signal.uuid()))
+            .filter(|signal| !always_constrained.contains(&signal.uuid()))
+            .map(|signal| signal.annotation());
+        let shared = circuit
+            .shared_signals
+            .iter()
+            .filter(|signal| !is_padding && !queried.contains(&signal.uuid()))
+            .filter(|signal| !always_constrained.contains(&signal.uuid()))
+            .map(|signal| signal.annotation());
+
+        for signal in internal.chain(forward).chain(shared) {
+            report
+                .signals
+                .entry(signal)
+                .or_default()
+                .push(step_type.name.clone());
+        }
+    }
+    for step_types in report.signals.values_mut() {
+        step_types.sort();
+    }
+
+    report
+}
+
+/// Returns the queries of the constraints, transition constraints, lookups and gadget calls of
+/// `step_type`.
+fn step_type_queries<F: Clone>(step_type: &StepType<F>) -> Vec<Queriable<F>> {
+    let mut queries = Vec::new();
+    let constraints = step_type.constraints.iter().map(|constr| &constr.expr);
+    let transitions = step_type
+        .transition_constraints
+        .iter()
+        .map(|constr| &constr.expr);
+    let lookups = step_type.lookups.iter().flat_map(|lookup| {
+        lookup
+            .exprs
+            .iter()
+            .flat_map(|(constr, expr)| [&constr.expr, expr])
+            .chain(lookup.enable.iter().map(|enable| &enable.expr))
+    });
+    for expr in constraints.chain(transitions).chain(lookups) {
+        collect_queries(expr, &mut queries);
+    }
+    for call in step_type.gadget_calls.iter() {
+        queries.extend(call.inputs.iter().chain(call.outputs.iter()).cloned());
+    }
+
+    queries
+}
+
+fn collect_queries<F: Clone>(expr: &PIR<F>, queries: &mut Vec<Queriable<F>>) {
+    match expr {
+        Expr::Sum(ses) | Expr::Mul(ses) => {
+            for se in ses {
+                collect_queries(se, queries);
+            }
+        }
+        Expr::Neg(se) | Expr::Pow(se, _) | Expr::MI(se) => collect_queries(se, queries),
+        Expr::Query(query) => queries.push(query.clone()),
+        Expr::Const(_) | Expr::Challenge(..) => {}
+        #[cfg(feature = "halo2")]
+        Expr::Halo2Expr(_) => {}
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrivialKind {
+    /// Nonzero in every step, so no witness using the step type satisfies it.
+    Unsatisfiable,
+    /// Zero whatever the values of the signals, so it constrains nothing.
+    Useless,
+}
+
+/// Constraint or transition constraint found by `trivial_constraints`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrivialConstraint {
+    pub kind: TrivialKind,
+    pub step_type: String,
+    pub annotation: String,
+    pub location: Option<SourceLocation>,
+}
+
+impl fmt::Display for TrivialConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            TrivialKind::Unsatisfiable => "unsatisfiable",
+            TrivialKind::Useless => "useless",
+        };
+        write!(
+            f,
+            "{} constraint {}::{}",
+            kind, self.step_type, self.annotation
+        )?;
+        if let Some(location) = self.location {
+            write!(f, " at {}", location)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Finds the constraints and transition constraints that can never be satisfied, and the ones
+/// that are identically zero, with the step types sorted by name. A constraint is unsatisfiable
+/// when its normal form, see `poly::simplify::simplify`, is a nonzero constant, like `1 == 0`, or
+/// when it only queries fixed signals and is nonzero with their assigned values in every step. A
+/// constraint is useless when its normal form is zero, like `a == a`.
+pub fn trivial_constraints<F: Field + Hash, TraceArgs>(
+    circuit: &SBPIR<F, TraceArgs>,
+) -> Vec<TrivialConstraint> {
+    let mut step_types: Vec<_> = circuit.step_types.values().collect();
+    step_types.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut found = Vec::new();
+    for step_type in step_types {
+        let constraints = step_type
+            .constraints
+            .iter()
+            .map(|constr| (&constr.annotation, &constr.expr, constr.location));
+        let transitions = step_type
+            .transition_constraints
+            .iter()
+            .map(|constr| (&constr.annotation, &constr.expr, constr.location));
+
+        for (annotation, expr, location) in constraints.chain(transitions) {
+            if let Some(kind) = trivial_kind(circuit, expr) {
+                found.push(TrivialConstraint {
+                    kind,
+                    step_type: step_type.name.clone(),
+                    annotation: annotation.clone(),
+                    location,
+                });
+            }
+        }
+    }
+
+    found
+}
+
+fn trivial_kind<F: Field + Hash, TraceArgs>(
+    circuit: &SBPIR<F, TraceArgs>,
+    expr: &PIR<F>,
+) -> Option<TrivialKind> {
+    match expr.clone().simplify() {
+        Expr::Const(value) if value == F::ZERO => return Some(TrivialKind::Useless),
+        Expr::Const(_) => return Some(TrivialKind::Unsatisfiable),
+        _ => {}
+    }
+
+    let fixed = circuit.fixed_assignments.as_ref()?;
+    let mut queries = Vec::new();
+    collect_queries(expr, &mut queries);
+    if circuit.num_steps == 0
+        || !queries
+            .iter()
+            .all(|query| matches!(query, Queriable::Fixed(..)))
+    {
+        return None;
+    }
+
+    (0..circuit.num_steps)
+        .all(|step| {
+            let value = expr.eval_with(&|query| Some(fixed_value(fixed, query, step)));
+            matches!(value, Some(value) if value != F::ZERO)
+        })
+        .then_some(TrivialKind::Unsatisfiable)
+}
+
+/// Returns the value of a fixed query in a step, zero outside of its assigned values, as in the
+/// compiled circuit.
+fn fixed_value<F: Field + Hash>(
+    fixed: &FixedAssignment<F>,
+    query: &Queriable<F>,
+    step: usize,
+) -> F {
+    let (signal, rotation) = match query {
+        Queriable::Fixed(signal, rotation) => (signal, rotation),
+        _ => return F::ZERO,
+    };
+
+    usize::try_from(step as i64 + *rotation as i64)
+        .ok()
+        .and_then(|step| fixed.get(&Queriable::Fixed(*signal, 0))?.get(step).copied())
+        .unwrap_or(F::ZERO)
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        frontend::dsl::{
+            cb::{eq, lookup},
+            circuit,
+        },
+        sbpir::ExposeOffset,
+    };
+
+    #[test]
+    fn test_unconstrained_signals() {
+        let circuit: SBPIR<Fr, ()> = circuit("unconstrained", |ctx| {
+            let a = ctx.forward("a");
+            let b = ctx.forward("b");
+            let total = ctx.shared("total");
+            let range = ctx.fixed("range");
+
+            ctx.step_type_def("first", |ctx| {
+                let c = ctx.internal("c");
+                let d = ctx.internal("d");
+                let e = ctx.internal("e");
+                ctx.setup(move |ctx| {
+                    ctx.constr(eq(a, 1));
+                    ctx.transition(eq(a + d, b.next()));
+                    ctx.add_lookup(lookup().add(d, range));
+                    ctx.auto(c, a * 2);
+                    ctx.gadget("hash", [a], [e]);
+                });
+                ctx.wg(|_, _: ()| {})
+            });
+            ctx.step_type_def("last", |ctx| {
+                let f = ctx.internal("f");
+                ctx.setup(move |ctx| ctx.constr(eq(f, 0)));
+                ctx.wg(|_, _: ()| {})
+            });
+            let pad = ctx.step_type_def("pad", |ctx| ctx.wg(|_, _: ()| {}));
+
+            ctx.pragma_padding_step(&pad);
+            ctx.expose(total, ExposeOffset::Last);
+        });
+
+        let report = unconstrained_signals(&circuit);
+
+        assert_eq!(
+            report.signals,
+            BTreeMap::from([
+                ("a".to_string(), vec!["last".to_string()]),
+                ("c".to_string(), vec!["first".to_string()]),
+            ])
+        );
+        assert_eq!(
+            report.to_string(),
+            "a: unconstrained in last\nc: unconstrained in first\n"
+        );
+        assert!(unconstrained_signals(&SBPIR::<Fr, ()>::default()).is_empty());
+    }
+
+    #[test]
+    fn test_trivial_constraints() {
+        let circuit: SBPIR<Fr, ()> = circuit("trivial", |ctx| {
+            let a = ctx.forward("a");
+            let one = ctx.fixed("one");
+            let index = ctx.fixed("index");
+
+            ctx.step_type_def("step", |ctx| {
+                ctx.setup(move |ctx| {
+                    ctx.constr(eq(a, 1));
+                    ctx.constr(eq(a, a));
+                    ctx.constr(eq(one, 0));
+                    ctx.constr(eq(index, 0));
+                    ctx.constr(eq(index.next() * 0, 0));
+                    ctx.transition(eq(1, 0));
+                });
+                ctx.wg(|_, _: ()| {})
+            });
+
+            ctx.pragma_num_steps(4);
+            ctx.fixed_gen(move |ctx| {
+                ctx.assign_with(one, |_| Fr::ONE);
+                ctx.assign_with(index, |i| Fr::from(i as u64));
+            });
+        });
+
+        let found = trivial_constraints(&circuit);
+
+        assert_eq!(
+            found.iter().map(|constr| constr.kind).collect::<Vec<_>>(),
+            vec![
+                TrivialKind::Useless,
+                TrivialKind::Unsatisfiable,
+                TrivialKind::Useless,
+                TrivialKind::Unsatisfiable,
+            ]
+        );
+        assert_eq!(found[0].annotation, "a == a");
+        assert!(found[1].annotation.starts_with("one == "));
+        assert!(found[0]
+            .to_string()
+            .starts_with("useless constraint step::a == a at src/sbpir/analysis.rs:"));
+    }
+}
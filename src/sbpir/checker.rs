@@ -0,0 +1,829 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    hash::Hash,
+};
+
+use crate::{
+    field::Field,
+    poly::Expr,
+    sbpir::{query::Queriable, SourceLocation, StepTypeUUID, PIR, SBPIR},
+    wit_gen::{AutoTraceGenerator, FixedAssignment, StepInstance, TraceWitness},
+};
+
+/// Kind of requirement of the circuit that a witness does not meet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ViolationKind {
+    Constraint,
+    Transition,
+    Lookup,
+    /// A padding step that changes a forward or shared signal, or that is followed by a step
+    /// that is not padding.
+    Padding,
+    FirstStep,
+    LastStep,
+    /// More step instances than the number of steps of the circuit.
+    NumSteps,
+    /// A step instance of a step type that is not in the circuit.
+    StepType,
+}
+
+/// Requirement of the circuit that a witness does not meet, found by `Checker`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    pub kind: ViolationKind,
+    /// Index of the step instance.
+    pub step: usize,
+    pub step_type: String,
+    pub annotation: String,
+    pub location: Option<SourceLocation>,
+    /// Values of the failing expression, its sub-expressions and the signals it queries, a line
+    /// each, see `Expr::explain_with`. Empty for the violations not about an expression.
+    pub explanation: Vec<String>,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "step {} ({}): {:?} {}",
+            self.step, self.step_type, self.kind, self.annotation
+        )?;
+        if let Some(location) = self.location {
+            write!(f, " at {}", location)?;
+        }
+        for line in self.explanation.iter() {
+            write!(f, "\n    {}", line)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How a witness exercises a constraint or transition constraint, see `Checker::coverage`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Coverage {
+    /// No step instance of its step type, or, for transition constraints, none followed by a step
+    /// where they apply.
+    Unexercised,
+    /// All the signals it queries are zero in every step instance.
+    ZeroOperands,
+    /// In every step instance a factor of its expression is zero, like a selector never set, or
+    /// all its signals are, so it holds regardless of the other factors.
+    ZeroFactor,
+    Exercised,
+}
+
+impl Coverage {
+    /// Returns the coverage of the evaluations of two coverages together.
+    fn combine(self, other: Coverage) -> Coverage {
+        match (self, other) {
+            (Coverage::Exercised, _) | (_, Coverage::Exercised) => Coverage::Exercised,
+            (Coverage::Unexercised, coverage) | (coverage, Coverage::Unexercised) => coverage,
+            (Coverage::ZeroOperands, Coverage::ZeroOperands) => Coverage::ZeroOperands,
+            _ => Coverage::ZeroFactor,
+        }
+    }
+}
+
+/// Coverage of a constraint or transition constraint of a step type.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConstraintCoverage {
+    /// `ViolationKind::Constraint` or `ViolationKind::Transition`.
+    pub kind: ViolationKind,
+    pub step_type: String,
+    pub annotation: String,
+    pub location: Option<SourceLocation>,
+    /// Number of step instances where it applies.
+    pub evaluations: usize,
+    pub coverage: Coverage,
+}
+
+impl fmt::Display for ConstraintCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {:?} {}", self.step_type, self.kind, self.annotation)?;
+        if let Some(location) = self.location {
+            write!(f, " at {}", location)?;
+        }
+
+        write!(f, ": {:?} in {} steps", self.coverage, self.evaluations)
+    }
+}
+
+/// Coverage of the constraints of a circuit by a witness, by step type name and in order of
+/// definition, to find the parts of the circuit that a test suite never exercises.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CoverageReport {
+    pub constraints: Vec<ConstraintCoverage>,
+}
+
+impl CoverageReport {
+    /// Returns the constraints that are only trivially satisfied, or not exercised at all.
+    pub fn trivial(&self) -> impl Iterator<Item = &ConstraintCoverage> {
+        self.constraints
+            .iter()
+            .filter(|constraint| constraint.coverage != Coverage::Exercised)
+    }
+
+    /// Returns if all the constraints are exercised.
+    pub fn is_covered(&self) -> bool {
+        self.trivial().next().is_none()
+    }
+
+    /// Merges the coverage of another witness of the same circuit, so the report covers a whole
+    /// test suite.
+    pub fn merge(&mut self, other: &CoverageReport) {
+        for (constraint, other) in self.constraints.iter_mut().zip(other.constraints.iter()) {
+            constraint.evaluations += other.evaluations;
+            constraint.coverage = constraint.coverage.combine(other.coverage);
+        }
+    }
+}
+
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for constraint in self.trivial() {
+            writeln!(f, "{}", constraint)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks a `TraceWitness` against the constraints, transition constraints and lookups of a
+/// circuit, evaluating them natively without compiling the circuit or running a prover. The
+/// witness is padded and its auto signals are generated as in the plonkish assignment generator,
+/// and unassigned signals are zero. Lookups are checked against the fixed assignments of the
+/// circuit and the ones added with `with_fixed`, so lookups into the advice columns of other
+/// circuits are not checked.
+pub struct Checker<'a, F, TraceArgs> {
+    circuit: &'a SBPIR<F, TraceArgs>,
+    fixed: Vec<&'a FixedAssignment<F>>,
+}
+
+impl<'a, F: Field + Hash, TraceArgs> Checker<'a, F, TraceArgs> {
+    pub fn new(circuit: &'a SBPIR<F, TraceArgs>) -> Self {
+        Self {
+            circuit,
+            fixed: circuit.fixed_assignments.iter().collect(),
+        }
+    }
+
+    /// Adds the fixed assignments of another circuit, usually a table of a super circuit.
+    pub fn with_fixed(mut self, fixed: &'a FixedAssignment<F>) -> Self {
+        self.fixed.push(fixed);
+
+        self
+    }
+
+    /// Returns if the witness meets all the requirements of the circuit.
+    pub fn is_satisfied(&self, witness: &TraceWitness<F>) -> bool {
+        self.check(witness).is_empty()
+    }
+
+    /// Returns the requirements of the circuit that the witness does not meet, in step order.
+    pub fn check(&self, witness: &TraceWitness<F>) -> Vec<Violation> {
+        let mut witness = witness.clone();
+        let mut violations = Vec::new();
+
+        let num_steps = self.circuit.num_steps;
+        if num_steps > 0 && witness.step_instances.len() > num_steps {
+            violations.push(self.violation(
+                ViolationKind::NumSteps,
+                num_steps,
+                &witness.step_instances[num_steps],
+                format!(
+                    "{} step instances, more than {}",
+                    witness.step_instances.len(),
+                    num_steps
+                ),
+                None,
+            ));
+            // the steps past the end of the circuit are not checked
+            witness.step_instances.truncate(num_steps);
+        }
+        let witness = AutoTraceGenerator::from(self.circuit).generate(witness);
+        let steps = &witness.step_instances;
+
+        let tables = self.tables();
+        for index in 0..steps.len() {
+            self.check_step(steps, index, &tables, &mut violations);
+        }
+        self.check_order(steps, &mut violations);
+
+        violations.sort_by_key(|violation| violation.step);
+        violations
+    }
+
+    /// Returns how the witness exercises each constraint and transition constraint of the circuit,
+    /// pointing out the ones only satisfied trivially. The witness is prepared as in `check`.
+    pub fn coverage(&self, witness: &TraceWitness<F>) -> CoverageReport {
+        let mut witness = witness.clone();
+        if self.circuit.num_steps > 0 {
+            witness.step_instances.truncate(self.circuit.num_steps);
+        }
+        let witness = AutoTraceGenerator::from(self.circuit).generate(witness);
+        let steps = &witness.step_instances;
+
+        let mut step_types: Vec<_> = self.circuit.step_types.values().collect();
+        step_types.sort_by_key(|step_type| (step_type.name(), step_type.uuid()));
+
+        let mut report = CoverageReport::default();
+        for step_type in step_types {
+            let instances: Vec<usize> = (0..steps.len())
+                .filter(|index| steps[*index].step_type_uuid == step_type.uuid())
+                .collect();
+            let transitions: Vec<usize> = instances
+                .iter()
+                .copied()
+                .filter(|index| self.has_transitions(steps, *index))
+                .collect();
+
+            let constraints = step_type
+                .constraints
+                .iter()
+                .map(|c| {
+                    (
+                        &c.annotation,
+                        &c.expr,
+                        c.location,
+                        ViolationKind::Constraint,
+                    )
+                })
+                .chain(step_type.transition_constraints.iter().map(|c| {
+                    (
+                        &c.annotation,
+                        &c.expr,
+                        c.location,
+                        ViolationKind::Transition,
+                    )
+                }));
+            for (annotation, expr, location, kind) in constraints {
+                let indexes = if kind == ViolationKind::Transition {
+                    &transitions
+                } else {
+                    &instances
+                };
+                let mut coverage = Coverage::Unexercised;
+                for index in indexes.iter() {
+                    let value = |query: &Queriable<F>| self.value(steps, *index, query);
+                    let evaluation = if Self::zero_operands(expr, &value) {
+                        Coverage::ZeroOperands
+                    } else if Self::zero_factor(expr, &value) {
+                        Coverage::ZeroFactor
+                    } else {
+                        Coverage::Exercised
+                    };
+                    coverage = coverage.combine(evaluation);
+                }
+
+                report.constraints.push(ConstraintCoverage {
+                    kind,
+                    step_type: step_type.name(),
+                    annotation: annotation.clone(),
+                    location,
+                    evaluations: indexes.len(),
+                    coverage,
+                });
+            }
+        }
+
+        report
+    }
+
+    /// Returns if all the signals an expression queries are zero.
+    fn zero_operands<R: Fn(&Queriable<F>) -> Option<F>>(expr: &PIR<F>, value: &R) -> bool {
+        match expr {
+            Expr::Const(_) | Expr::Challenge(..) => true,
+            Expr::Sum(ses) | Expr::Mul(ses) => ses.iter().all(|se| Self::zero_operands(se, value)),
+            Expr::Neg(se) | Expr::Pow(se, _) | Expr::MI(se) => Self::zero_operands(se, value),
+            Expr::Query(query) => value(query).unwrap_or(F::ZERO) == F::ZERO,
+            #[cfg(feature = "halo2")]
+            Expr::Halo2Expr(_) => false,
+        }
+    }
+
+    /// Returns if an expression is zero because of a zero factor, in all its non constant terms.
+    fn zero_factor<R: Fn(&Queriable<F>) -> Option<F>>(expr: &PIR<F>, value: &R) -> bool {
+        match expr {
+            Expr::Mul(ses) => ses.iter().any(|se| se.eval_with(value) == Some(F::ZERO)),
+            Expr::Neg(se) => Self::zero_factor(se, value),
+            Expr::Sum(ses) => {
+                ses.iter().any(|se| Self::zero_factor(se, value))
+                    && ses.iter().all(|se| {
+                        se.eval_with(&|_| None) == Some(F::ZERO) || Self::zero_factor(se, value)
+                    })
+            }
+            _ => false,
+        }
+    }
+
+    fn check_step(
+        &self,
+        steps: &[StepInstance<F>],
+        index: usize,
+        tables: &HashMap<(StepTypeUUID, usize), HashSet<Vec<F>>>,
+        violations: &mut Vec<Violation>,
+    ) {
+        let step = &steps[index];
+        let step_type = match self.circuit.step_types.get(&step.step_type_uuid) {
+            Some(step_type) => step_type,
+            None => {
+                violations.push(self.violation(
+                    ViolationKind::StepType,
+                    index,
+                    step,
+                    format!("step type {} not found", step.step_type_uuid),
+                    None,
+                ));
+                return;
+            }
+        };
+        let value = |query: &Queriable<F>| self.value(steps, index, query);
+        let is_zero = |expr: &PIR<F>| !matches!(expr.eval_with(&value), Some(v) if v != F::ZERO);
+
+        for constraint in step_type.constraints.iter() {
+            if !is_zero(&constraint.expr) {
+                violations.push(Violation {
+                    explanation: constraint.expr.explain_with(&value),
+                    ..self.violation(
+                        ViolationKind::Constraint,
+                        index,
+                        step,
+                        constraint.annotation.clone(),
+                        constraint.location,
+                    )
+                });
+            }
+        }
+
+        if self.has_transitions(steps, index) {
+            for constraint in step_type.transition_constraints.iter() {
+                if !is_zero(&constraint.expr) {
+                    violations.push(Violation {
+                        explanation: constraint.expr.explain_with(&value),
+                        ..self.violation(
+                            ViolationKind::Transition,
+                            index,
+                            step,
+                            constraint.annotation.clone(),
+                            constraint.location,
+                        )
+                    });
+                }
+            }
+        }
+
+        for (lookup_index, lookup) in step_type.lookups.iter().enumerate() {
+            let table = match tables.get(&(step_type.uuid(), lookup_index)) {
+                Some(table) => table,
+                None => continue,
+            };
+            let src: Option<Vec<F>> = lookup
+                .exprs
+                .iter()
+                .map(|(src, _)| src.expr.eval_with(&value))
+                .collect();
+            if matches!(src, Some(src) if !table.contains(&src)) {
+                violations.push(Violation {
+                    explanation: lookup
+                        .exprs
+                        .iter()
+                        .flat_map(|(src, _)| src.expr.explain_with(&value))
+                        .collect(),
+                    ..self.violation(
+                        ViolationKind::Lookup,
+                        index,
+                        step,
+                        lookup.annotation.clone(),
+                        None,
+                    )
+                });
+            }
+        }
+
+        if Some(step.step_type_uuid) == self.circuit.padding_step && index > 0 {
+            let previous = &steps[index - 1];
+            let signals = self
+                .circuit
+                .forward_signals
+                .iter()
+                .map(|forward| Queriable::Forward(*forward, false))
+                .chain(
+                    self.circuit
+                        .shared_signals
+                        .iter()
+                        .map(|shared| Queriable::Shared(*shared, 0)),
+                );
+            for signal in signals {
+                let value =
+                    |step: &StepInstance<F>| *step.assignments.get(&signal).unwrap_or(&F::ZERO);
+                if value(step) != value(previous) {
+                    violations.push(self.violation(
+                        ViolationKind::Padding,
+                        index,
+                        step,
+                        format!("padding keeps {}", signal.annotation()),
+                        None,
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Checks the first and last step types, and that the padding steps are at the end.
+    fn check_order(&self, steps: &[StepInstance<F>], violations: &mut Vec<Violation>) {
+        let padding_step = self.circuit.padding_step;
+        let is_padding = |step: &StepInstance<F>| Some(step.step_type_uuid) == padding_step;
+
+        if let (Some(first_step), Some(step)) = (self.circuit.first_step, steps.first()) {
+            if step.step_type_uuid != first_step {
+                violations.push(self.violation(
+                    ViolationKind::FirstStep,
+                    0,
+                    step,
+                    "first step".to_string(),
+                    None,
+                ));
+            }
+        }
+
+        let real_steps = steps.iter().take_while(|step| !is_padding(step)).count();
+        if let Some((index, step)) = steps
+            .iter()
+            .enumerate()
+            .skip(real_steps)
+            .find(|(_, step)| !is_padding(step))
+        {
+            violations.push(self.violation(
+                ViolationKind::Padding,
+                index,
+                step,
+                "padding is followed by padding".to_string(),
+                None,
+            ));
+        }
+
+        if let Some(last_step) = self
+            .circuit
+            .last_step
+            .filter(|last| Some(*last) != padding_step)
+        {
+            let last = if real_steps > 0 { real_steps - 1 } else { 0 };
+            if let Some(step) = steps.get(last) {
+                if step.step_type_uuid != last_step {
+                    violations.push(self.violation(
+                        ViolationKind::LastStep,
+                        last,
+                        step,
+                        "last step".to_string(),
+                        None,
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Returns if the transition constraints of a step apply, which is not the case for the last
+    /// step, nor for the last one before the padding.
+    fn has_transitions(&self, steps: &[StepInstance<F>], index: usize) -> bool {
+        match steps.get(index + 1) {
+            None => false,
+            Some(next) => match self.circuit.padding_step {
+                Some(padding_step) => {
+                    next.step_type_uuid != padding_step
+                        || steps[index].step_type_uuid == padding_step
+                }
+                None => true,
+            },
+        }
+    }
+
+    /// Returns the value of a query in a step, as assigned by the plonkish assignment generator,
+    /// or `None` if it cannot be evaluated natively.
+    fn value(&self, steps: &[StepInstance<F>], index: usize, query: &Queriable<F>) -> Option<F> {
+        let assigned = |step: Option<&StepInstance<F>>, query: &Queriable<F>| {
+            step.and_then(|step| step.assignments.get(query).copied())
+        };
+        let rotated = |rotation: i32| {
+            let index = index as i64 + rotation as i64;
+            usize::try_from(index)
+                .ok()
+                .and_then(|index| steps.get(index))
+        };
+
+        match query {
+            Queriable::Internal(_) | Queriable::Forward(_, false) => {
+                Some(assigned(steps.get(index), query).unwrap_or(F::ZERO))
+            }
+            Queriable::Forward(forward, true) => Some(
+                assigned(steps.get(index + 1), &Queriable::Forward(*forward, false))
+                    .or_else(|| assigned(steps.get(index), query))
+                    .unwrap_or(F::ZERO),
+            ),
+            Queriable::Shared(shared, rotation) => Some(
+                assigned(rotated(*rotation), &Queriable::Shared(*shared, 0)).unwrap_or(F::ZERO),
+            ),
+            Queriable::Fixed(..) => Some(self.fixed_value(query, index).unwrap_or(F::ZERO)),
+            Queriable::StepTypeNext(step_type) => Some(match steps.get(index + 1) {
+                Some(next) if next.step_type_uuid == step_type.uuid() => F::ONE,
+                _ => F::ZERO,
+            }),
+            #[cfg(feature = "halo2")]
+            Queriable::Halo2AdviceQuery(..) | Queriable::Halo2FixedQuery(..) => None,
+            Queriable::_unaccessible(_) => None,
+        }
+    }
+
+    /// Returns the value of a fixed signal, with its rotation, at a row, `None` past the end of
+    /// its column.
+    fn fixed_value(&self, query: &Queriable<F>, row: usize) -> Option<F> {
+        let (signal, rotation) = match query {
+            Queriable::Fixed(signal, rotation) => (signal, rotation),
+            _ => return None,
+        };
+        let row = usize::try_from(row as i64 + *rotation as i64).ok()?;
+        let key = Queriable::Fixed(*signal, 0);
+
+        self.fixed
+            .iter()
+            .find_map(|fixed| fixed.get(&key))
+            .and_then(|column| column.get(row).copied())
+    }
+
+    /// Returns the rows of the tables of the lookups into fixed columns, by step type and lookup.
+    fn tables(&self) -> HashMap<(StepTypeUUID, usize), HashSet<Vec<F>>> {
+        let mut tables = HashMap::new();
+        for step_type in self.circuit.step_types.values() {
+            for (index, lookup) in step_type.lookups.iter().enumerate() {
+                let dests: Vec<&PIR<F>> = lookup.exprs.iter().map(|(_, dest)| dest).collect();
+                if !dests.iter().all(|dest| Self::is_fixed(dest)) {
+                    continue;
+                }
+
+                let rows: HashSet<Vec<F>> = (0..self.fixed_rows())
+                    .filter_map(|row| {
+                        dests
+                            .iter()
+                            .map(|dest| dest.eval_with(&|query| self.fixed_value(query, row)))
+                            .collect::<Option<Vec<F>>>()
+                    })
+                    .collect();
+                tables.insert((step_type.uuid(), index), rows);
+            }
+        }
+
+        tables
+    }
+
+    fn fixed_rows(&self) -> usize {
+        self.fixed
+            .iter()
+            .flat_map(|fixed| fixed.values())
+            .map(|column| column.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn is_fixed(expr: &PIR<F>) -> bool {
+        match expr {
+            Expr::Const(_) | Expr::Challenge(..) => true,
+            Expr::Sum(ses) | Expr::Mul(ses) => ses.iter().all(Self::is_fixed),
+            Expr::Neg(se) | Expr::Pow(se, _) | Expr::MI(se) => Self::is_fixed(se),
+            Expr::Query(query) => matches!(query, Queriable::Fixed(..)),
+            #[cfg(feature = "halo2")]
+            Expr::Halo2Expr(_) => false,
+        }
+    }
+
+    fn violation(
+        &self,
+        kind: ViolationKind,
+        step: usize,
+        instance: &StepInstance<F>,
+        annotation: String,
+        location: Option<SourceLocation>,
+    ) -> Violation {
+        let step_type = self
+            .circuit
+            .annotation(instance.step_type_uuid)
+            .cloned()
+            .unwrap_or_else(|| instance.step_type_uuid.to_string());
+
+        Violation {
+            kind,
+            step,
+            step_type,
+            annotation,
+            location,
+            explanation: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        frontend::dsl::{
+            cb::{eq, lookup},
+            circuit,
+        },
+        wit_gen::TraceGenerator,
+    };
+
+    fn counter_circuit() -> SBPIR<Fr, (u64, bool)> {
+        circuit("counter", |ctx| {
+            let count = ctx.forward("count");
+            let range = ctx.fixed("range");
+
+            let start = ctx.step_type_def("start", |ctx| {
+                ctx.setup(move |ctx| {
+                    ctx.constr(eq(count, 0));
+                    ctx.transition(eq(count + 1, count.next()));
+                });
+                ctx.wg(move |ctx, _: ()| ctx.assign(count, Fr::ZERO))
+            });
+            let inc = ctx.step_type_def("inc", |ctx| {
+                ctx.setup(move |ctx| {
+                    ctx.transition(eq(count + 1, count.next()));
+                    ctx.add_lookup(lookup().add(count, range));
+                });
+                ctx.wg(move |ctx, value: u64| ctx.assign(count, Fr::from(value)))
+            });
+            let done = ctx.step_type_def("done", |ctx| {
+                ctx.wg(move |ctx, value: u64| ctx.assign(count, Fr::from(value)))
+            });
+            let pad = ctx.step_type_def("pad", |ctx| ctx.wg(|_, _: ()| {}));
+
+            ctx.pragma_first_step(&start);
+            ctx.pragma_last_step(&done);
+            ctx.pragma_padding_step(&pad);
+            ctx.pragma_num_steps(8);
+            ctx.fixed_gen(move |ctx| ctx.assign_with(range, |i| Fr::from(i as u64)));
+
+            ctx.trace(move |ctx, (n, skip_done): (u64, bool)| {
+                ctx.add(&start, ());
+                for i in 1..n {
+                    ctx.add(&inc, i);
+                }
+                if !skip_done {
+                    ctx.add(&done, n);
+                }
+            });
+        })
+    }
+
+    fn witness(circuit: &SBPIR<Fr, (u64, bool)>, args: (u64, bool)) -> TraceWitness<Fr> {
+        TraceGenerator::new(circuit.trace.clone().unwrap(), circuit.num_steps).generate(args)
+    }
+
+    fn found(violations: &[Violation]) -> Vec<(usize, ViolationKind)> {
+        violations
+            .iter()
+            .map(|violation| (violation.step, violation.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_checker_satisfied() {
+        let circuit = counter_circuit();
+        let checker = Checker::new(&circuit);
+
+        assert!(checker.is_satisfied(&witness(&circuit, (3, false))));
+        assert!(checker.is_satisfied(&witness(&circuit, (7, false))));
+    }
+
+    #[test]
+    fn test_checker_violations() {
+        let circuit = counter_circuit();
+        let checker = Checker::new(&circuit);
+        let count = Queriable::Forward(circuit.forward_signals[0], false);
+
+        let mut tampered = witness(&circuit, (3, false));
+        tampered.step_instances[2]
+            .assignments
+            .insert(count, Fr::from(10));
+        let violations = checker.check(&tampered);
+        assert_eq!(
+            found(&violations),
+            vec![
+                (1, ViolationKind::Transition),
+                (2, ViolationKind::Transition),
+                (2, ViolationKind::Lookup),
+            ]
+        );
+        assert!(violations[0]
+            .to_string()
+            .starts_with("step 1 (inc): Transition"));
+        assert!(violations[0].location.is_some());
+        assert!(violations[0]
+            .explanation
+            .iter()
+            .any(|line| line.trim_start() == "next(count) = 0xa"));
+
+        let violations = checker.check(&witness(&circuit, (3, true)));
+        assert_eq!(found(&violations), vec![(2, ViolationKind::LastStep)]);
+
+        let mut tampered = witness(&circuit, (3, false));
+        tampered.pad(circuit.padding_step.unwrap(), circuit.num_steps);
+        tampered.step_instances[5]
+            .assignments
+            .insert(count, Fr::from(7));
+        let violations = checker.check(&tampered);
+        assert_eq!(
+            found(&violations),
+            vec![(5, ViolationKind::Padding), (6, ViolationKind::Padding)]
+        );
+
+        let violations = checker.check(&witness(&circuit, (8, false)));
+        assert_eq!(
+            found(&violations),
+            vec![(7, ViolationKind::LastStep), (8, ViolationKind::NumSteps)]
+        );
+
+        let mut tampered = witness(&circuit, (3, false));
+        tampered.step_instances[1].step_type_uuid = 0;
+        let violations = checker.check(&tampered);
+        assert_eq!(found(&violations), vec![(1, ViolationKind::StepType)]);
+        assert_eq!(violations[0].step_type, "0");
+    }
+
+    #[test]
+    fn test_coverage() {
+        let circuit = counter_circuit();
+        let checker = Checker::new(&circuit);
+
+        let report = checker.coverage(&witness(&circuit, (1, false)));
+        let coverage = |report: &CoverageReport| -> Vec<(String, ViolationKind, Coverage)> {
+            report
+                .constraints
+                .iter()
+                .map(|c| (c.step_type.clone(), c.kind, c.coverage))
+                .collect()
+        };
+        assert_eq!(
+            coverage(&report),
+            vec![
+                (
+                    "inc".to_string(),
+                    ViolationKind::Transition,
+                    Coverage::Unexercised
+                ),
+                (
+                    "start".to_string(),
+                    ViolationKind::Constraint,
+                    Coverage::ZeroOperands
+                ),
+                (
+                    "start".to_string(),
+                    ViolationKind::Transition,
+                    Coverage::Exercised
+                ),
+            ]
+        );
+        assert!(!report.is_covered());
+        let display = report.to_string();
+        assert!(display.starts_with("inc: Transition"));
+        assert!(display.contains(": Unexercised in 0 steps\nstart: Constraint"));
+
+        let mut report = report;
+        report.merge(&checker.coverage(&witness(&circuit, (3, false))));
+        assert_eq!(report.trivial().count(), 1);
+        assert_eq!(report.constraints[0].evaluations, 2);
+        assert_eq!(report.constraints[0].coverage, Coverage::Exercised);
+    }
+
+    #[test]
+    fn test_coverage_zero_factor() {
+        let circuit: SBPIR<Fr, ()> = circuit("gated", |ctx| {
+            let a = ctx.forward("a");
+
+            let gated = ctx.step_type_def("gated", |ctx| {
+                let enabled = ctx.internal("enabled");
+                let b = ctx.internal("b");
+                ctx.setup(move |ctx| ctx.constr(eq(enabled * (a - b), 0)));
+                ctx.wg(move |ctx, value: u64| {
+                    ctx.assign(enabled, Fr::ZERO);
+                    ctx.assign(a, Fr::from(value));
+                    ctx.assign(b, Fr::from(value + 1));
+                })
+            });
+
+            ctx.pragma_num_steps(2);
+            ctx.trace(move |ctx, _| {
+                ctx.add(&gated, 1);
+                ctx.add(&gated, 0);
+            });
+        });
+        let witness =
+            TraceGenerator::new(circuit.trace.clone().unwrap(), circuit.num_steps).generate(());
+
+        let report = Checker::new(&circuit).coverage(&witness);
+        assert_eq!(report.constraints.len(), 1);
+        assert_eq!(report.constraints[0].coverage, Coverage::ZeroFactor);
+        assert_eq!(report.constraints[0].evaluations, 2);
+    }
+}
@@ -0,0 +1,388 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use crate::{
+    poly::fmt_value,
+    sbpir::{query::Queriable, StepType, StepTypeUUID, SBPIR},
+    wit_gen::TraceWitness,
+};
+
+/// Difference found by `diff_witnesses` or `diff_circuits`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    Added(String),
+    Removed(String),
+    /// What changed, with its old and new values.
+    Changed(String, String, String),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added(what) => write!(f, "+ {}", what),
+            Change::Removed(what) => write!(f, "- {}", what),
+            Change::Changed(what, old, new) => write!(f, "~ {}: {} -> {}", what, old, new),
+        }
+    }
+}
+
+/// Differences between two witnesses or two circuits, a line each when displayed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Diff {
+    pub changes: Vec<Change>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    fn added(&mut self, what: String) {
+        self.changes.push(Change::Added(what));
+    }
+
+    fn removed(&mut self, what: String) {
+        self.changes.push(Change::Removed(what));
+    }
+
+    fn changed<T: PartialEq + fmt::Display>(&mut self, what: String, old: T, new: T) {
+        if old != new {
+            self.changes
+                .push(Change::Changed(what, old.to_string(), new.to_string()));
+        }
+    }
+
+    /// Compares two maps by key, reporting the entries only in one of them and the ones with
+    /// different values.
+    fn compare(
+        &mut self,
+        what: &str,
+        old: &BTreeMap<String, String>,
+        new: &BTreeMap<String, String>,
+    ) {
+        for (key, old_value) in old.iter() {
+            match new.get(key) {
+                Some(new_value) => self.changed(format!("{} {}", what, key), old_value, new_value),
+                None => self.removed(format!("{} {}", what, key)),
+            }
+        }
+        for key in new.keys().filter(|key| !old.contains_key(*key)) {
+            self.added(format!("{} {}", what, key));
+        }
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in self.changes.iter() {
+            writeln!(f, "{}", change)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the differences between two witnesses, step by step: step instances added or removed
+/// at the end, step instances of another step type and the signals they assign differently.
+/// Step types and signals are compared by annotation, so the witnesses can come from two builds
+/// of a circuit, with different UUIDs.
+pub fn diff_witnesses<F: fmt::Debug, A, B>(
+    old_circuit: &SBPIR<F, A>,
+    old: &TraceWitness<F>,
+    new_circuit: &SBPIR<F, B>,
+    new: &TraceWitness<F>,
+) -> Diff {
+    let mut diff = Diff::default();
+
+    let steps = old.step_instances.len().max(new.step_instances.len());
+    for index in 0..steps {
+        match (old.step_instances.get(index), new.step_instances.get(index)) {
+            (Some(old_step), Some(new_step)) => {
+                let old_name = step_type_name(old_circuit, old_step.step_type_uuid);
+                let new_name = step_type_name(new_circuit, new_step.step_type_uuid);
+                diff.changed(format!("step {} step type", index), &old_name, &new_name);
+
+                let values = |assignments: &HashMap<Queriable<F>, F>| {
+                    assignments
+                        .iter()
+                        .map(|(signal, value)| (signal.annotation(), fmt_value(value)))
+                        .collect()
+                };
+                diff.compare(
+                    &format!("step {} ({})", index, new_name),
+                    &values(&old_step.assignments),
+                    &values(&new_step.assignments),
+                );
+            }
+            (Some(old_step), None) => diff.removed(format!(
+                "step {} ({})",
+                index,
+                step_type_name(old_circuit, old_step.step_type_uuid)
+            )),
+            (None, Some(new_step)) => diff.added(format!(
+                "step {} ({})",
+                index,
+                step_type_name(new_circuit, new_step.step_type_uuid)
+            )),
+            (None, None) => unreachable!(),
+        }
+    }
+
+    diff
+}
+
+/// Returns the differences between two circuits: step types, signals, constraints, transition
+/// constraints and lookups added or removed, by annotation, constraints with a changed
+/// expression, and changed pragmas.
+pub fn diff_circuits<F: fmt::Debug, A, B>(old: &SBPIR<F, A>, new: &SBPIR<F, B>) -> Diff {
+    let mut diff = Diff::default();
+
+    diff.compare(
+        "forward signal",
+        &forward_signals(old),
+        &forward_signals(new),
+    );
+    diff.compare("shared signal", &shared_signals(old), &shared_signals(new));
+    diff.compare("fixed signal", &fixed_signals(old), &fixed_signals(new));
+
+    let old_steps = step_types(old);
+    let new_steps = step_types(new);
+    diff.compare(
+        "step type",
+        &old_steps
+            .keys()
+            .map(|name| (name.clone(), String::new()))
+            .collect(),
+        &new_steps
+            .keys()
+            .map(|name| (name.clone(), String::new()))
+            .collect(),
+    );
+    for (name, old_step) in old_steps.iter() {
+        if let Some(new_step) = new_steps.get(name) {
+            diff_step_types(&mut diff, name, old_step, new_step);
+        }
+    }
+
+    diff.changed(
+        "first step".to_string(),
+        pragma_name(old, old.first_step),
+        pragma_name(new, new.first_step),
+    );
+    diff.changed(
+        "last step".to_string(),
+        pragma_name(old, old.last_step),
+        pragma_name(new, new.last_step),
+    );
+    diff.changed(
+        "padding step".to_string(),
+        pragma_name(old, old.padding_step),
+        pragma_name(new, new.padding_step),
+    );
+    diff.changed("num steps".to_string(), old.num_steps, new.num_steps);
+
+    diff
+}
+
+fn diff_step_types<F: fmt::Debug>(
+    diff: &mut Diff,
+    name: &str,
+    old: &StepType<F>,
+    new: &StepType<F>,
+) {
+    let signals = |step_type: &StepType<F>| {
+        step_type
+            .signals
+            .iter()
+            .map(|signal| (signal.annotation(), String::new()))
+            .collect()
+    };
+    diff.compare(
+        &format!("internal signal of {}:", name),
+        &signals(old),
+        &signals(new),
+    );
+
+    let constraints = |step_type: &StepType<F>| {
+        step_type
+            .constraints
+            .iter()
+            .map(|c| (c.annotation.clone(), format!("{:?}", c.expr)))
+            .collect()
+    };
+    diff.compare(
+        &format!("constraint of {}:", name),
+        &constraints(old),
+        &constraints(new),
+    );
+
+    let transitions = |step_type: &StepType<F>| {
+        step_type
+            .transition_constraints
+            .iter()
+            .map(|c| (c.annotation.clone(), format!("{:?}", c.expr)))
+            .collect()
+    };
+    diff.compare(
+        &format!("transition of {}:", name),
+        &transitions(old),
+        &transitions(new),
+    );
+
+    let lookups = |step_type: &StepType<F>| {
+        step_type
+            .lookups
+            .iter()
+            .map(|lookup| {
+                let exprs: Vec<String> = lookup
+                    .exprs
+                    .iter()
+                    .map(|(src, dest)| format!("{:?} => {:?}", src.expr, dest))
+                    .collect();
+                (lookup.annotation.clone(), exprs.join(", "))
+            })
+            .collect()
+    };
+    diff.compare(
+        &format!("lookup of {}:", name),
+        &lookups(old),
+        &lookups(new),
+    );
+}
+
+fn step_type_name<F, A>(circuit: &SBPIR<F, A>, uuid: StepTypeUUID) -> String {
+    circuit
+        .step_types
+        .get(&uuid)
+        .map(|step_type| step_type.name())
+        .unwrap_or_else(|| uuid.to_string())
+}
+
+fn pragma_name<F, A>(circuit: &SBPIR<F, A>, step_type: Option<StepTypeUUID>) -> String {
+    step_type
+        .map(|uuid| step_type_name(circuit, uuid))
+        .unwrap_or_else(|| "none".to_string())
+}
+
+fn step_types<F, A>(circuit: &SBPIR<F, A>) -> BTreeMap<String, &StepType<F>> {
+    circuit
+        .step_types
+        .values()
+        .map(|step_type| (step_type.name(), step_type.as_ref()))
+        .collect()
+}
+
+fn forward_signals<F, A>(circuit: &SBPIR<F, A>) -> BTreeMap<String, String> {
+    circuit
+        .forward_signals
+        .iter()
+        .map(|signal| (signal.annotation(), format!("phase {}", signal.phase())))
+        .collect()
+}
+
+fn shared_signals<F, A>(circuit: &SBPIR<F, A>) -> BTreeMap<String, String> {
+    circuit
+        .shared_signals
+        .iter()
+        .map(|signal| (signal.annotation(), format!("phase {}", signal.phase())))
+        .collect()
+}
+
+fn fixed_signals<F, A>(circuit: &SBPIR<F, A>) -> BTreeMap<String, String> {
+    circuit
+        .fixed_signals
+        .iter()
+        .map(|signal| (signal.annotation(), String::new()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        frontend::dsl::{cb::eq, circuit},
+        wit_gen::TraceGenerator,
+    };
+
+    fn sum_circuit(version: u64) -> SBPIR<Fr, u64> {
+        circuit("sum", move |ctx| {
+            let a = ctx.forward("a");
+            if version > 1 {
+                ctx.forward("b");
+            }
+
+            let sum = ctx.step_type_def("sum", move |ctx| {
+                let c = ctx.internal("c");
+                ctx.setup(move |ctx| {
+                    ctx.constr(eq(a + version, c));
+                    ctx.transition(eq(c, a.next()));
+                });
+                ctx.wg(move |ctx, value: u64| {
+                    ctx.assign(a, Fr::from(value));
+                    ctx.assign(c, Fr::from(value + version));
+                })
+            });
+
+            ctx.pragma_num_steps(2 * version as usize);
+            ctx.trace(move |ctx, start: u64| {
+                let mut value = start;
+                for _ in 0..2 {
+                    ctx.add(&sum, value);
+                    value += version;
+                }
+            });
+        })
+    }
+
+    fn witness(circuit: &SBPIR<Fr, u64>) -> TraceWitness<Fr> {
+        TraceGenerator::new(circuit.trace.clone().unwrap(), circuit.num_steps).generate(1)
+    }
+
+    #[test]
+    fn test_diff_circuits() {
+        assert!(diff_circuits(&sum_circuit(1), &sum_circuit(1)).is_empty());
+
+        let diff = diff_circuits(&sum_circuit(1), &sum_circuit(2));
+        assert_eq!(
+            diff.to_string(),
+            "+ forward signal b\n\
+             - constraint of sum: (a + 0x1) == c\n\
+             + constraint of sum: (a + 0x2) == c\n\
+             ~ num steps: 2 -> 4\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_witnesses() {
+        let old = sum_circuit(1);
+        let new = sum_circuit(2);
+        let rebuilt = sum_circuit(1);
+        assert!(diff_witnesses(&old, &witness(&old), &rebuilt, &witness(&rebuilt)).is_empty());
+
+        let diff = diff_witnesses(&old, &witness(&old), &new, &witness(&new));
+        assert_eq!(
+            diff.changes,
+            vec![
+                Change::Changed(
+                    "step 0 (sum) c".to_string(),
+                    fmt_value(&Fr::from(2)),
+                    fmt_value(&Fr::from(3))
+                ),
+                Change::Changed(
+                    "step 1 (sum) a".to_string(),
+                    fmt_value(&Fr::from(2)),
+                    fmt_value(&Fr::from(3))
+                ),
+                Change::Changed(
+                    "step 1 (sum) c".to_string(),
+                    fmt_value(&Fr::from(3)),
+                    fmt_value(&Fr::from(5))
+                ),
+            ]
+        );
+    }
+}
@@ -1,6 +1,12 @@
+pub mod analysis;
+pub mod checker;
+pub mod diff;
+pub mod mutation;
 pub mod query;
+pub mod smt;
+pub mod spec;
 
-use std::{collections::HashMap, fmt::Debug, hash::Hash, rc::Rc};
+use std::{collections::HashMap, fmt::Debug, hash::Hash, panic::Location, rc::Rc};
 
 use crate::{
     frontend::dsl::StepTypeHandler,
@@ -9,6 +15,7 @@ use crate::{
     wit_gen::{FixedAssignment, FixedGenContext, Trace, TraceContext},
 };
 
+#[cfg(feature = "halo2")]
 use halo2_proofs::plonk::{Advice, Column as Halo2Column, ColumnType, Fixed};
 
 use self::query::Queriable;
@@ -21,17 +28,24 @@ pub struct SBPIR<F, TraceArgs> {
     pub forward_signals: Vec<ForwardSignal>,
     pub shared_signals: Vec<SharedSignal>,
     pub fixed_signals: Vec<FixedSignal>,
+    #[cfg(feature = "halo2")]
     pub halo2_advice: Vec<ImportedHalo2Advice>,
+    #[cfg(feature = "halo2")]
     pub halo2_fixed: Vec<ImportedHalo2Fixed>,
     pub exposed: Vec<(Queriable<F>, ExposeOffset)>,
 
     pub annotations: HashMap<UUID, String>,
+    /// Additional names of signals and step types, by UUID.
+    pub aliases: HashMap<UUID, Vec<String>>,
 
     pub trace: Option<Rc<Trace<F, TraceArgs>>>,
     pub fixed_assignments: Option<FixedAssignment<F>>,
 
     pub first_step: Option<StepTypeUUID>,
     pub last_step: Option<StepTypeUUID>,
+    /// Step type filling the rows after the last step instance of a witness shorter than
+    /// `num_steps`.
+    pub padding_step: Option<StepTypeUUID>,
     pub num_steps: usize,
     pub q_enable: bool,
 
@@ -40,18 +54,24 @@ pub struct SBPIR<F, TraceArgs> {
 
 impl<F: Debug, TraceArgs: Debug> Debug for SBPIR<F, TraceArgs> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Circuit")
+        let mut debug = f.debug_struct("Circuit");
+        debug
             .field("step_types", &self.step_types)
             .field("forward_signals", &self.forward_signals)
             .field("shared_signals", &self.shared_signals)
-            .field("fixed_signals", &self.fixed_signals)
+            .field("fixed_signals", &self.fixed_signals);
+        #[cfg(feature = "halo2")]
+        debug
             .field("halo2_advice", &self.halo2_advice)
-            .field("halo2_fixed", &self.halo2_fixed)
+            .field("halo2_fixed", &self.halo2_fixed);
+        debug
             .field("exposed", &self.exposed)
             .field("annotations", &self.annotations)
+            .field("aliases", &self.aliases)
             .field("fixed_assignments", &self.fixed_assignments)
             .field("first_step", &self.first_step)
             .field("last_step", &self.last_step)
+            .field("padding_step", &self.padding_step)
             .field("num_steps", &self.num_steps)
             .field("q_enable", &self.q_enable)
             .finish()
@@ -65,19 +85,23 @@ impl<F, TraceArgs> Default for SBPIR<F, TraceArgs> {
             forward_signals: Default::default(),
             shared_signals: Default::default(),
             fixed_signals: Default::default(),
+            #[cfg(feature = "halo2")]
             halo2_advice: Default::default(),
+            #[cfg(feature = "halo2")]
             halo2_fixed: Default::default(),
             exposed: Default::default(),
 
             num_steps: Default::default(),
 
             annotations: Default::default(),
+            aliases: Default::default(),
 
             trace: None,
             fixed_assignments: None,
 
             first_step: None,
             last_step: None,
+            padding_step: None,
 
             id: uuid(),
             q_enable: true,
@@ -138,6 +162,7 @@ impl<F, TraceArgs> SBPIR<F, TraceArgs> {
         }
     }
 
+    #[cfg(feature = "halo2")]
     pub fn add_halo2_advice(
         &mut self,
         name: &str,
@@ -151,6 +176,7 @@ impl<F, TraceArgs> SBPIR<F, TraceArgs> {
         advice
     }
 
+    #[cfg(feature = "halo2")]
     pub fn add_halo2_fixed(
         &mut self,
         name: &str,
@@ -194,6 +220,68 @@ impl<F, TraceArgs> SBPIR<F, TraceArgs> {
         Rc::clone(step_rc)
     }
 
+    /// Replaces the name of a signal. Internal signals are renamed in their step type, which must
+    /// not be shared yet. Step types cannot be renamed, as their name is copied in the queries of
+    /// the next step type, but they can have aliases.
+    pub fn rename<N: Into<String>>(&mut self, uuid: UUID, name: N) {
+        assert!(
+            !self.step_types.contains_key(&uuid),
+            "step types cannot be renamed, add an alias instead"
+        );
+
+        if let Some(annotation) = self.annotations.get_mut(&uuid) {
+            *annotation = name.into();
+            return;
+        }
+
+        let step_type = self
+            .step_types
+            .values_mut()
+            .find(|step_type| step_type.annotations.contains_key(&uuid))
+            .unwrap_or_else(|| panic!("signal or step type not found: {}", uuid));
+        let step_type =
+            Rc::get_mut(step_type).expect("cannot rename a signal of a shared step type");
+        step_type.annotations.insert(uuid, name.into());
+    }
+
+    /// Adds a name to a signal or step type, which is shown after its name in annotations.
+    pub fn add_alias<N: Into<String>>(&mut self, uuid: UUID, alias: N) {
+        assert!(
+            self.annotation(uuid).is_some(),
+            "signal or step type not found: {}",
+            uuid
+        );
+
+        self.aliases.entry(uuid).or_default().push(alias.into());
+    }
+
+    /// Returns the name of a signal or step type, including the ones of its step type for the
+    /// internal signals.
+    pub fn annotation(&self, uuid: UUID) -> Option<&String> {
+        self.annotations.get(&uuid).or_else(|| {
+            self.step_types
+                .values()
+                .find_map(|step_type| step_type.annotations.get(&uuid))
+        })
+    }
+
+    /// Returns all the names, including the ones of the internal signals, as `name (alias, ...)`
+    /// for the signals and step types with aliases.
+    pub fn aliased_annotations(&self) -> HashMap<UUID, String> {
+        let mut annotations = self.annotations.clone();
+        for step_type in self.step_types.values() {
+            annotations.extend(step_type.annotations.clone());
+        }
+
+        for (uuid, aliases) in self.aliases.iter() {
+            if let Some(annotation) = annotations.get_mut(uuid) {
+                *annotation = format!("{} ({})", annotation, aliases.join(", "));
+            }
+        }
+
+        annotations
+    }
+
     pub fn set_fixed_assignments(&mut self, assignments: FixedAssignment<F>) {
         match self.fixed_assignments {
             None => {
@@ -204,6 +292,31 @@ impl<F, TraceArgs> SBPIR<F, TraceArgs> {
     }
 }
 
+impl<F, TraceArgs> SBPIR<F, TraceArgs> {
+    /// Returns the maximum degree of the compiled constraints and lookup sources of the circuit,
+    /// with step selectors of degree `selector_degree`, see `StepType::max_degree`. It includes
+    /// the `q_enable` factor, and the factor disabling the transition constraints of a step before
+    /// a padding step, which is the step selector of the padding step rotated to the next step.
+    pub fn max_degree(&self, selector_degree: usize) -> usize {
+        self.step_types
+            .values()
+            .map(|step_type| {
+                let mut degree = step_type.max_degree(selector_degree);
+                if matches!(self.padding_step, Some(padding) if padding != step_type.uuid()) {
+                    let transitions = step_type
+                        .transition_constraints
+                        .iter()
+                        .map(|constr| constr.expr.degree() + 2 * selector_degree + 1);
+                    degree = transitions.fold(degree, usize::max);
+                }
+
+                degree + usize::from(self.q_enable && degree > 0)
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
 impl<F: Clone, TraceArgs> SBPIR<F, TraceArgs> {
     pub fn clone_without_trace(&self) -> SBPIR<F, ()> {
         SBPIR {
@@ -211,14 +324,18 @@ impl<F: Clone, TraceArgs> SBPIR<F, TraceArgs> {
             forward_signals: self.forward_signals.clone(),
             shared_signals: self.shared_signals.clone(),
             fixed_signals: self.fixed_signals.clone(),
+            #[cfg(feature = "halo2")]
             halo2_advice: self.halo2_advice.clone(),
+            #[cfg(feature = "halo2")]
             halo2_fixed: self.halo2_fixed.clone(),
             exposed: self.exposed.clone(),
             annotations: self.annotations.clone(),
+            aliases: self.aliases.clone(),
             trace: None, // Remove the trace.
             fixed_assignments: self.fixed_assignments.clone(),
             first_step: self.first_step,
             last_step: self.last_step,
+            padding_step: self.padding_step,
             num_steps: self.num_steps,
             q_enable: self.q_enable,
             id: self.id,
@@ -239,6 +356,7 @@ pub struct StepType<F> {
     pub constraints: Vec<Constraint<F>>,
     pub transition_constraints: Vec<TransitionConstraint<F>>,
     pub lookups: Vec<Lookup<F>>,
+    pub gadget_calls: Vec<GadgetCall<F>>,
 
     pub auto_signals: HashMap<Queriable<F>, PIR<F>>,
 
@@ -253,6 +371,7 @@ impl<F: Debug> Debug for StepType<F> {
             .field("constraints", &self.constraints)
             .field("transition_constraints", &self.transition_constraints)
             .field("lookups", &self.lookups)
+            .field("gadget_calls", &self.gadget_calls)
             .finish()
     }
 }
@@ -266,6 +385,7 @@ impl<F> StepType<F> {
             constraints: Default::default(),
             transition_constraints: Default::default(),
             lookups: Default::default(),
+            gadget_calls: Default::default(),
             auto_signals: Default::default(),
             annotations: Default::default(),
         }
@@ -289,17 +409,58 @@ impl<F> StepType<F> {
         signal
     }
 
+    /// Adds a constraint, located at the caller, which is tracked through `#[track_caller]`
+    /// functions up to the user code.
+    #[track_caller]
     pub fn add_constr(&mut self, annotation: String, expr: PIR<F>) {
-        let condition = Constraint { annotation, expr };
+        let condition = Constraint {
+            annotation,
+            expr,
+            location: Some(Location::caller().into()),
+            feature: None,
+        };
 
         self.constraints.push(condition)
     }
 
+    /// Adds a transition constraint, located as in `add_constr`.
+    #[track_caller]
     pub fn add_transition(&mut self, annotation: String, expr: PIR<F>) {
-        let condition = TransitionConstraint { annotation, expr };
+        let condition = TransitionConstraint {
+            annotation,
+            expr,
+            location: Some(Location::caller().into()),
+            feature: None,
+        };
 
         self.transition_constraints.push(condition)
     }
+
+    /// Returns the maximum degree of the constraints, transition constraints and lookup sources of
+    /// the step type once compiled: they are multiplied by the step selector, of degree
+    /// `selector_degree`, and the transition constraints by `1 - q_last` too. The signals of the
+    /// gadget calls are constrained to equal a cell, with degree one. The factors that depend on
+    /// the circuit are added by `SBPIR::max_degree`.
+    pub fn max_degree(&self, selector_degree: usize) -> usize {
+        let constraints = self.constraints.iter().map(|constr| constr.expr.degree());
+        let transitions = self
+            .transition_constraints
+            .iter()
+            .map(|constr| constr.expr.degree() + 1);
+        let lookups = self
+            .lookups
+            .iter()
+            .flat_map(|lookup| lookup.exprs.iter())
+            .map(|(src, _)| src.expr.degree());
+        let gadget_calls = self.gadget_calls.iter().map(|_| 1);
+
+        constraints
+            .chain(transitions)
+            .chain(lookups)
+            .chain(gadget_calls)
+            .max()
+            .map_or(0, |degree| degree + selector_degree)
+    }
 }
 
 impl<F> PartialEq for StepType<F> {
@@ -318,11 +479,39 @@ impl<F> core::hash::Hash for StepType<F> {
 
 pub type PIR<F> = Expr<F, Queriable<F>>;
 
+/// Place in the Rust source where a constraint was created.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SourceLocation {
+    pub file: &'static str,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl From<&'static Location<'static>> for SourceLocation {
+    fn from(location: &'static Location<'static>) -> Self {
+        SourceLocation {
+            file: location.file(),
+            line: location.line(),
+            column: location.column(),
+        }
+    }
+}
+
+impl std::fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
 #[derive(Clone, Debug)]
 /// Condition
 pub struct Constraint<F> {
     pub annotation: String,
     pub expr: PIR<F>,
+    /// Where the constraint was created, if it comes from the Rust DSL.
+    pub location: Option<SourceLocation>,
+    /// Feature the constraint belongs to, compiled only when the feature is enabled.
+    pub feature: Option<String>,
 }
 
 #[derive(Clone, Debug)]
@@ -330,6 +519,10 @@ pub struct Constraint<F> {
 pub struct TransitionConstraint<F> {
     pub annotation: String,
     pub expr: PIR<F>,
+    /// Where the constraint was created, if it comes from the Rust DSL.
+    pub location: Option<SourceLocation>,
+    /// Feature the constraint belongs to, compiled only when the feature is enabled.
+    pub feature: Option<String>,
 }
 
 #[derive(Clone, Debug)]
@@ -337,6 +530,8 @@ pub struct Lookup<F> {
     pub annotation: String,
     pub exprs: Vec<(Constraint<F>, PIR<F>)>,
     pub enable: Option<Constraint<F>>,
+    /// Feature the lookup belongs to, compiled only when the feature is enabled.
+    pub feature: Option<String>,
 }
 
 impl<F> Default for Lookup<F> {
@@ -345,10 +540,28 @@ impl<F> Default for Lookup<F> {
             annotation: String::new(),
             exprs: Vec::<(Constraint<F>, PIR<F>)>::new(),
             enable: None,
+            feature: None,
         }
     }
 }
 
+/// Call of a halo2 gadget by the steps of a step type, see `StepTypeSetupContext::gadget`. The
+/// gadget is registered with its name in the halo2 backend, see `Halo2Gadget`.
+#[derive(Clone, Debug)]
+pub struct GadgetCall<F> {
+    pub gadget: String,
+    pub annotation: String,
+    /// Signals whose values the gadget is called with.
+    pub inputs: Vec<Queriable<F>>,
+    /// Signals constrained to equal the outputs of the gadget.
+    pub outputs: Vec<Queriable<F>>,
+    /// Most steps of the step type in a witness, that the gadget is called for. The number of
+    /// steps of the circuit if `None`.
+    pub max_calls: Option<usize>,
+    /// Feature the call belongs to, compiled only when the feature is enabled.
+    pub feature: Option<String>,
+}
+
 impl<F: Debug + Clone> Lookup<F> {
     // Function: adds (constraint, expression) to exprs if there's no enabler, OR add (enabler *
     // constraint, expression) to exprs if there's enabler Note that constraint_annotation and
@@ -363,6 +576,8 @@ impl<F: Debug + Clone> Lookup<F> {
         let constraint = Constraint {
             annotation: constraint_annotation,
             expr: constraint_expr,
+            location: None,
+            feature: None,
         };
         self.annotation += &format!("match({} => {:?}) ", &constraint.annotation, &expression); // expression: Expr<F> is formatted using the fmt method defined in the Debug trait
         match self.enable {
@@ -384,6 +599,8 @@ impl<F: Debug + Clone> Lookup<F> {
         let enable = Constraint {
             annotation: enable_annotation.clone(),
             expr: enable_expr,
+            location: None,
+            feature: None,
         };
         match self.enable {
             None => {
@@ -407,6 +624,8 @@ impl<F: Debug + Clone> Lookup<F> {
                                                         * enable function above in the format of
                                                         * "if {enable}" */
             expr: enable.expr * constraint.expr,
+            location: constraint.location,
+            feature: constraint.feature,
         }
     }
 }
@@ -552,6 +771,8 @@ impl InternalSignal {
     }
 }
 
+/// Column of a halo2 circuit queried from chiquito.
+#[cfg(feature = "halo2")]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct ImportedHalo2Column<CT: ColumnType> {
     id: UUID,
@@ -559,6 +780,7 @@ pub struct ImportedHalo2Column<CT: ColumnType> {
     annotation: &'static str,
 }
 
+#[cfg(feature = "halo2")]
 impl<CT: ColumnType> ImportedHalo2Column<CT> {
     pub fn new(column: Halo2Column<CT>, annotation: String) -> ImportedHalo2Column<CT> {
         ImportedHalo2Column {
@@ -573,7 +795,9 @@ impl<CT: ColumnType> ImportedHalo2Column<CT> {
     }
 }
 
+#[cfg(feature = "halo2")]
 pub type ImportedHalo2Advice = ImportedHalo2Column<Advice>;
+#[cfg(feature = "halo2")]
 pub type ImportedHalo2Fixed = ImportedHalo2Column<Fixed>;
 
 #[cfg(test)]
@@ -609,6 +833,27 @@ mod tests {
         assert_eq!(circuit.exposed.len(), 1);
     }
 
+    #[test]
+    fn test_max_degree() {
+        let mut step_type: StepType<i32> = StepType::new(uuid(), "step".to_string());
+        let a = Queriable::Internal(step_type.add_signal("a"));
+        let b = Queriable::Internal(step_type.add_signal("b"));
+        step_type.add_constr("a * b".to_string(), a * b);
+        step_type.add_transition("a".to_string(), a.into());
+        assert_eq!(step_type.max_degree(1), 3);
+        assert_eq!(step_type.max_degree(2), 4);
+
+        let mut circuit: SBPIR<i32, i32> = SBPIR::default();
+        let padding = StepType::new(uuid(), "padding".to_string());
+        circuit.padding_step = Some(padding.uuid());
+        circuit.add_step_type_def(step_type);
+        circuit.add_step_type_def(padding);
+        // a * (1 - q_last) * (1 - next padding) * selector * q_enable
+        assert_eq!(circuit.max_degree(2), 7);
+        circuit.q_enable = false;
+        assert_eq!(circuit.max_degree(1), 4);
+    }
+
     #[test]
     fn test_expose_shared_signal() {
         let mut circuit: SBPIR<i32, i32> = SBPIR::default();
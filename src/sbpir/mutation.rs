@@ -0,0 +1,221 @@
+use std::{fmt, hash::Hash};
+
+use crate::{
+    field::Field,
+    sbpir::{checker::Checker, query::Queriable},
+    util::SplitMix64,
+    wit_gen::TraceWitness,
+};
+
+/// Change of the value of a single assignment of a witness.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mutation<F> {
+    /// Index of the step instance.
+    pub step: usize,
+    pub signal: Queriable<F>,
+    pub value: F,
+}
+
+impl<F: fmt::Debug> fmt::Display for Mutation<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "step {}: {} = {:?}",
+            self.step,
+            self.signal.annotation(),
+            self.value
+        )
+    }
+}
+
+/// Soundness smoke test: mutates the assignments of a valid witness one at a time and collects
+/// the mutations that the circuit still accepts, which usually point to unconstrained cells. The
+/// mutated witnesses are checked natively with a `Checker`.
+pub struct MutationTester<'a, F, TraceArgs> {
+    checker: Checker<'a, F, TraceArgs>,
+}
+
+impl<'a, F: Field + Hash, TraceArgs> MutationTester<'a, F, TraceArgs> {
+    pub fn new(checker: Checker<'a, F, TraceArgs>) -> Self {
+        Self { checker }
+    }
+
+    /// Returns the mutations accepted by the circuit out of adding one to each assignment of the
+    /// witness. Panics if the witness itself is not accepted.
+    pub fn systematic(&self, witness: &TraceWitness<F>) -> Vec<Mutation<F>> {
+        self.accepted(witness, |value| value + F::ONE)
+    }
+
+    /// Returns the mutations accepted by the circuit out of `count` mutations of random
+    /// assignments of the witness to random values, drawn deterministically from `seed`. Panics if
+    /// the witness itself is not accepted.
+    pub fn random(&self, witness: &TraceWitness<F>, seed: u64, count: usize) -> Vec<Mutation<F>> {
+        let mut rng = SplitMix64(seed);
+        let targets = Self::targets(witness);
+        if targets.is_empty() {
+            return Vec::new();
+        }
+
+        let mutations = (0..count).map(|_| {
+            let (step, signal) = targets[rng.below(targets.len())];
+            let value = F::from(rng.next());
+            let original = witness.step_instances[step].assignments[&signal];
+
+            Mutation {
+                step,
+                signal,
+                value: if value == original {
+                    value + F::ONE
+                } else {
+                    value
+                },
+            }
+        });
+
+        self.assert_accepted(witness);
+        mutations
+            .filter(|mutation| self.accepts(witness, mutation))
+            .collect()
+    }
+
+    /// Panics, listing them, if the circuit accepts some of the systematic mutations.
+    pub fn assert_rejected(&self, witness: &TraceWitness<F>) {
+        let accepted = self.systematic(witness);
+        if !accepted.is_empty() {
+            let mutations: Vec<String> = accepted.iter().map(|m| m.to_string()).collect();
+            panic!(
+                "circuit accepts {} mutated witnesses:\n{}",
+                accepted.len(),
+                mutations.join("\n")
+            );
+        }
+    }
+
+    fn accepted<M: Fn(F) -> F>(&self, witness: &TraceWitness<F>, mutate: M) -> Vec<Mutation<F>> {
+        self.assert_accepted(witness);
+
+        Self::targets(witness)
+            .into_iter()
+            .map(|(step, signal)| Mutation {
+                step,
+                signal,
+                value: mutate(witness.step_instances[step].assignments[&signal]),
+            })
+            .filter(|mutation| self.accepts(witness, mutation))
+            .collect()
+    }
+
+    fn accepts(&self, witness: &TraceWitness<F>, mutation: &Mutation<F>) -> bool {
+        let mut mutated = witness.clone();
+        mutated.step_instances[mutation.step]
+            .assignments
+            .insert(mutation.signal, mutation.value);
+
+        self.checker.is_satisfied(&mutated)
+    }
+
+    fn assert_accepted(&self, witness: &TraceWitness<F>) {
+        let violations = self.checker.check(witness);
+        if let Some(violation) = violations.first() {
+            panic!("the witness to mutate is not accepted: {}", violation);
+        }
+    }
+
+    /// Returns the assignments of the witness, by step and in order of annotation.
+    fn targets(witness: &TraceWitness<F>) -> Vec<(usize, Queriable<F>)> {
+        witness
+            .step_instances
+            .iter()
+            .enumerate()
+            .flat_map(|(step, step_instance)| {
+                let mut signals: Vec<Queriable<F>> =
+                    step_instance.assignments.keys().copied().collect();
+                signals.sort_by_key(|signal| signal.annotation());
+
+                signals.into_iter().map(move |signal| (step, signal))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        frontend::dsl::{cb::eq, circuit},
+        sbpir::SBPIR,
+        wit_gen::TraceGenerator,
+    };
+
+    fn sum_circuit(constrain_c: bool) -> SBPIR<Fr, ()> {
+        circuit("sum", move |ctx| {
+            let a = ctx.forward("a");
+
+            let sum = ctx.step_type_def("sum", |ctx| {
+                let b = ctx.internal("b");
+                let c = ctx.internal("c");
+                ctx.setup(move |ctx| {
+                    ctx.constr(eq(a + 1, b));
+                    if constrain_c {
+                        ctx.constr(eq(b * 2, c));
+                    }
+                });
+                ctx.wg(move |ctx, value: u64| {
+                    ctx.assign(a, Fr::from(value));
+                    ctx.assign(b, Fr::from(value + 1));
+                    ctx.assign(c, Fr::from(2 * value + 2));
+                })
+            });
+
+            ctx.pragma_num_steps(2);
+            ctx.trace(move |ctx, _| {
+                ctx.add(&sum, 1);
+                ctx.add(&sum, 5);
+            });
+        })
+    }
+
+    fn witness(circuit: &SBPIR<Fr, ()>) -> TraceWitness<Fr> {
+        TraceGenerator::new(circuit.trace.clone().unwrap(), circuit.num_steps).generate(())
+    }
+
+    #[test]
+    fn test_mutations_rejected() {
+        let circuit = sum_circuit(true);
+        let tester = MutationTester::new(Checker::new(&circuit));
+        let witness = witness(&circuit);
+
+        tester.assert_rejected(&witness);
+        assert!(tester.random(&witness, 7, 20).is_empty());
+    }
+
+    #[test]
+    fn test_mutations_accepted() {
+        let circuit = sum_circuit(false);
+        let tester = MutationTester::new(Checker::new(&circuit));
+        let witness = witness(&circuit);
+
+        let accepted = tester.systematic(&witness);
+        assert_eq!(
+            accepted.iter().map(|m| m.to_string()).collect::<Vec<_>>(),
+            vec![
+                format!("step 0: c = {:?}", Fr::from(5)),
+                format!("step 1: c = {:?}", Fr::from(13)),
+            ]
+        );
+        assert!(tester
+            .random(&witness, 7, 20)
+            .iter()
+            .all(|m| m.signal.annotation() == "c"));
+    }
+
+    #[test]
+    #[should_panic(expected = "circuit accepts 2 mutated witnesses")]
+    fn test_assert_rejected() {
+        let circuit = sum_circuit(false);
+
+        MutationTester::new(Checker::new(&circuit)).assert_rejected(&witness(&circuit));
+    }
+}
@@ -4,16 +4,18 @@ use std::{
     ops::{Add, Mul, Neg, Sub},
 };
 
+#[cfg(feature = "halo2")]
+use crate::sbpir::{ImportedHalo2Advice, ImportedHalo2Fixed};
 use crate::{
     frontend::dsl::StepTypeHandler,
-    sbpir::{
-        FixedSignal, ForwardSignal, ImportedHalo2Advice, ImportedHalo2Fixed, InternalSignal,
-        SharedSignal,
-    },
+    sbpir::{FixedSignal, ForwardSignal, InternalSignal, SharedSignal},
     util::UUID,
 };
 
-use crate::poly::{Expr, ToExpr};
+use crate::{
+    field::Field,
+    poly::{Expr, ToExpr},
+};
 
 use super::PIR;
 
@@ -25,7 +27,9 @@ pub enum Queriable<F> {
     Shared(SharedSignal, i32),
     Fixed(FixedSignal, i32),
     StepTypeNext(StepTypeHandler),
+    #[cfg(feature = "halo2")]
     Halo2AdviceQuery(ImportedHalo2Advice, i32),
+    #[cfg(feature = "halo2")]
     Halo2FixedQuery(ImportedHalo2Fixed, i32),
     #[allow(non_camel_case_types)]
     _unaccessible(PhantomData<F>),
@@ -53,7 +57,9 @@ impl<F> Queriable<F> {
             }
             Shared(s, rot) => Shared(*s, rot + 1),
             Fixed(s, rot) => Fixed(*s, rot + 1),
+            #[cfg(feature = "halo2")]
             Halo2AdviceQuery(s, rot) => Halo2AdviceQuery(*s, rot + 1),
+            #[cfg(feature = "halo2")]
             Halo2FixedQuery(s, r) => Halo2FixedQuery(*s, r + 1),
             _ => panic!("can only next a forward, shared, fixed, or halo2 column"),
         }
@@ -92,7 +98,9 @@ impl<F> Queriable<F> {
             Queriable::Shared(s, _) => s.uuid(),
             Queriable::Fixed(s, _) => s.uuid(),
             Queriable::StepTypeNext(s) => s.uuid(),
+            #[cfg(feature = "halo2")]
             Queriable::Halo2AdviceQuery(s, _) => s.uuid(),
+            #[cfg(feature = "halo2")]
             Queriable::Halo2FixedQuery(s, _) => s.uuid(),
             Queriable::_unaccessible(_) => panic!("jarrl wrong queriable type"),
         }
@@ -123,6 +131,7 @@ impl<F> Queriable<F> {
                 }
             }
             Queriable::StepTypeNext(s) => s.annotation.to_string(),
+            #[cfg(feature = "halo2")]
             Queriable::Halo2AdviceQuery(s, rot) => {
                 if *rot != 0 {
                     format!("{}(rot {})", s.annotation, rot)
@@ -130,6 +139,7 @@ impl<F> Queriable<F> {
                     s.annotation.to_string()
                 }
             }
+            #[cfg(feature = "halo2")]
             Queriable::Halo2FixedQuery(s, rot) => {
                 if *rot != 0 {
                     format!("{}(rot {})", s.annotation, rot)
@@ -186,6 +196,104 @@ impl<F> From<Queriable<F>> for PIR<F> {
     }
 }
 
+impl<F: Clone, RHS: Into<PIR<F>>> Add<RHS> for &Queriable<F> {
+    type Output = PIR<F>;
+
+    fn add(self, rhs: RHS) -> Self::Output {
+        self.expr() + rhs
+    }
+}
+
+impl<F: Clone, RHS: Into<PIR<F>>> Sub<RHS> for &Queriable<F> {
+    type Output = PIR<F>;
+
+    fn sub(self, rhs: RHS) -> Self::Output {
+        self.expr() - rhs
+    }
+}
+
+impl<F: Clone, RHS: Into<PIR<F>>> Mul<RHS> for &Queriable<F> {
+    type Output = PIR<F>;
+
+    fn mul(self, rhs: RHS) -> Self::Output {
+        self.expr() * rhs
+    }
+}
+
+impl<F: Clone> Neg for &Queriable<F> {
+    type Output = PIR<F>;
+
+    fn neg(self) -> Self::Output {
+        self.expr().neg()
+    }
+}
+
+impl<F: Clone> From<&Queriable<F>> for PIR<F> {
+    fn from(value: &Queriable<F>) -> Self {
+        value.expr()
+    }
+}
+
+/// Implements the arithmetic operators with an integer on the left-hand side of a signal.
+macro_rules! impl_int_lhs_ops {
+    ($type:ty: $($bound:tt)+) => {
+        impl<F: Clone + $($bound)+> Add<Queriable<F>> for $type {
+            type Output = PIR<F>;
+
+            fn add(self, rhs: Queriable<F>) -> Self::Output {
+                PIR::from(self) + rhs
+            }
+        }
+
+        impl<F: Clone + $($bound)+> Sub<Queriable<F>> for $type {
+            type Output = PIR<F>;
+
+            fn sub(self, rhs: Queriable<F>) -> Self::Output {
+                PIR::from(self) - rhs
+            }
+        }
+
+        impl<F: Clone + $($bound)+> Mul<Queriable<F>> for $type {
+            type Output = PIR<F>;
+
+            fn mul(self, rhs: Queriable<F>) -> Self::Output {
+                PIR::from(self) * rhs
+            }
+        }
+
+        impl<F: Clone + $($bound)+> Add<&Queriable<F>> for $type {
+            type Output = PIR<F>;
+
+            fn add(self, rhs: &Queriable<F>) -> Self::Output {
+                PIR::from(self) + rhs
+            }
+        }
+
+        impl<F: Clone + $($bound)+> Sub<&Queriable<F>> for $type {
+            type Output = PIR<F>;
+
+            fn sub(self, rhs: &Queriable<F>) -> Self::Output {
+                PIR::from(self) - rhs
+            }
+        }
+
+        impl<F: Clone + $($bound)+> Mul<&Queriable<F>> for $type {
+            type Output = PIR<F>;
+
+            fn mul(self, rhs: &Queriable<F>) -> Self::Output {
+                PIR::from(self) * rhs
+            }
+        }
+    };
+}
+
+impl_int_lhs_ops!(u8: From<u64>);
+impl_int_lhs_ops!(u32: From<u64>);
+impl_int_lhs_ops!(u64: From<u64>);
+impl_int_lhs_ops!(usize: From<u64>);
+impl_int_lhs_ops!(i32: Field + From<u64>);
+impl_int_lhs_ops!(i64: Field + From<u64>);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -212,6 +320,24 @@ mod tests {
         assert_eq!(format!("{:?}", expr5), "(0xa)^2");
     }
 
+    #[test]
+    fn test_queriable_ref_and_int_ops() {
+        let a: Queriable<Fr> = Queriable::Internal(InternalSignal::new("a"));
+        let b: Queriable<Fr> = Queriable::Internal(InternalSignal::new("b"));
+        let assignments = std::collections::HashMap::from([(a, Fr::from(2)), (b, Fr::from(3))]);
+
+        let expr = 3u64 * a + &b * 2 - 1i64;
+        assert_eq!(expr.eval(&assignments), Some(Fr::from(11)));
+
+        let expr = 1usize - &a + -&b;
+        assert_eq!(expr.eval(&assignments), Some(-Fr::from(4)));
+
+        let expr: PIR<Fr> = [a, b].iter().sum();
+        assert_eq!(expr.eval(&assignments), Some(Fr::from(5)));
+        let expr: PIR<Fr> = [a, b, a].into_iter().product();
+        assert_eq!(expr.eval(&assignments), Some(Fr::from(12)));
+    }
+
     #[test]
     fn test_next_for_forward_signal() {
         let forward_signal = ForwardSignal {
@@ -0,0 +1,375 @@
+//! SMT-LIB export of circuits for formal analysis: the constraints of a step type, or of a bounded
+//! sequence of steps, as assertions over the field of the circuit, in the theory of finite fields
+//! of cvc5 (`QF_FF`). Append the negation of a property the steps should have, and
+//! `(check-sat)`, and a model of the solver is a witness of the steps violating it.
+
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+use ff::PrimeField;
+use num_bigint::BigUint;
+
+use crate::{
+    poly::Expr,
+    sbpir::{query::Queriable, FixedSignal, StepTypeUUID, PIR, SBPIR},
+    util::UUID,
+};
+
+/// Encodes the constraints of a single step of `step_type`, see `steps_smt_lib`.
+pub fn step_type_smt_lib<F: PrimeField + Hash, TraceArgs>(
+    circuit: &SBPIR<F, TraceArgs>,
+    step_type: StepTypeUUID,
+) -> String {
+    steps_smt_lib(circuit, &[step_type])
+}
+
+/// Encodes the constraints, transition constraints and lookups of a sequence of steps, of the step
+/// types `steps`, in SMT-LIB. The value of each signal in each step is a constant named
+/// `|{step}:{annotation}|`, so the next value of a forward signal in a step is its value in the
+/// following one. The values past the sequence, like the next values of the last step, are free,
+/// and so are the fixed signals without fixed assignments, the challenges and the step types
+/// following the last step. Multiplicative inverses are constants constrained to be the inverse,
+/// or zero for zero.
+///
+/// Lookups into tables of fixed signals with fixed assignments are encoded as the rows the
+/// sources must match, and the others are left out with a comment. Panics for halo2 queries and
+/// expressions, and for unknown step types.
+pub fn steps_smt_lib<F: PrimeField + Hash, TraceArgs>(
+    circuit: &SBPIR<F, TraceArgs>,
+    steps: &[StepTypeUUID],
+) -> String {
+    let mut encoder = Encoder::new(circuit, steps);
+    for (step, uuid) in steps.iter().enumerate() {
+        encoder.encode_step(step, *uuid);
+    }
+
+    encoder.finish()
+}
+
+struct Encoder<'a, F, TraceArgs> {
+    circuit: &'a SBPIR<F, TraceArgs>,
+    steps: &'a [StepTypeUUID],
+    /// Constants, in the order they are declared.
+    constants: Vec<String>,
+    declared: HashSet<String>,
+    /// Name of each signal, step type or challenge, its annotation unless another one took it.
+    names: HashMap<UUID, String>,
+    taken: HashSet<String>,
+    /// Assertions and comments, a line each.
+    lines: Vec<String>,
+    inverses: usize,
+}
+
+impl<'a, F: PrimeField + Hash, TraceArgs> Encoder<'a, F, TraceArgs> {
+    fn new(circuit: &'a SBPIR<F, TraceArgs>, steps: &'a [StepTypeUUID]) -> Self {
+        Self {
+            circuit,
+            steps,
+            constants: Vec::new(),
+            declared: HashSet::new(),
+            names: HashMap::new(),
+            taken: HashSet::new(),
+            lines: Vec::new(),
+            inverses: 0,
+        }
+    }
+
+    fn encode_step(&mut self, step: usize, uuid: StepTypeUUID) {
+        let step_type = self.circuit.get_step_type(uuid);
+        let comment = |kind: &str, annotation: &str| {
+            format!(
+                "; step {} {}, {} {}",
+                step,
+                step_type.name,
+                kind,
+                annotation.replace('\n', " ")
+            )
+        };
+
+        for constr in step_type.constraints.iter() {
+            self.lines.push(comment("constraint", &constr.annotation));
+            self.assert_zero(step, &constr.expr);
+        }
+        for constr in step_type.transition_constraints.iter() {
+            self.lines.push(comment("transition", &constr.annotation));
+            self.assert_zero(step, &constr.expr);
+        }
+        for lookup in step_type.lookups.iter() {
+            self.lines.push(comment("lookup", &lookup.annotation));
+            let dests: Vec<_> = lookup.exprs.iter().map(|(_, dest)| dest).collect();
+            let rows = match self.table_rows(&dests) {
+                Some(rows) => rows,
+                None => {
+                    self.lines
+                        .push("; left out, as its table is not of fixed assignments".to_string());
+                    continue;
+                }
+            };
+
+            let sources: Vec<_> = lookup
+                .exprs
+                .iter()
+                .map(|(src, _)| self.expr(step, &src.expr))
+                .collect();
+            let rows: Vec<_> = rows
+                .iter()
+                .map(|row| {
+                    let equalities: Vec<_> = sources
+                        .iter()
+                        .zip(row.iter())
+                        .map(|(src, value)| format!("(= {} {})", src, self.value(value)))
+                        .collect();
+                    format!("(and {})", equalities.join(" "))
+                })
+                .collect();
+            let mut matched = match rows.len() {
+                0 => "false".to_string(),
+                _ => format!("(or {})", rows.join(" ")),
+            };
+            if let Some(enable) = &lookup.enable {
+                let enable = self.expr(step, &enable.expr);
+                matched = format!("(or (= {} {}) {})", enable, self.value(&F::ZERO), matched);
+            }
+            self.lines.push(format!("(assert {})", matched));
+        }
+    }
+
+    /// Returns the rows of a table with destinations `dests`, without repetitions, when they only
+    /// query fixed signals with fixed assignments.
+    fn table_rows(&self, dests: &[&PIR<F>]) -> Option<Vec<Vec<F>>> {
+        self.circuit.fixed_assignments.as_ref()?;
+
+        let mut rows = Vec::new();
+        let mut seen = HashSet::new();
+        for row in 0..self.circuit.num_steps {
+            let value = |query: &Queriable<F>| match query {
+                Queriable::Fixed(signal, rotation) => self.fixed_value(signal, *rotation, row),
+                _ => None,
+            };
+            let values = dests
+                .iter()
+                .map(|dest| dest.eval_with(&value))
+                .collect::<Option<Vec<_>>>()?;
+            if seen.insert(values.clone()) {
+                rows.push(values);
+            }
+        }
+
+        Some(rows)
+    }
+
+    /// Returns the value of a fixed signal, with a rotation, in a step, zero outside of its
+    /// assigned values, or `None` if the circuit has no fixed assignments.
+    fn fixed_value(&self, signal: &FixedSignal, rotation: i32, step: usize) -> Option<F> {
+        let fixed = self.circuit.fixed_assignments.as_ref()?;
+        let step = usize::try_from(step as i64 + rotation as i64).ok();
+
+        Some(
+            step.and_then(|step| fixed.get(&Queriable::Fixed(*signal, 0))?.get(step))
+                .copied()
+                .unwrap_or(F::ZERO),
+        )
+    }
+
+    fn assert_zero(&mut self, step: usize, expr: &PIR<F>) {
+        let expr = self.expr(step, expr);
+        let zero = self.value(&F::ZERO);
+        self.lines.push(format!("(assert (= {} {}))", expr, zero));
+    }
+
+    fn expr(&mut self, step: usize, expr: &PIR<F>) -> String {
+        match expr {
+            Expr::Const(value) => self.value(value),
+            Expr::Sum(ses) => self.operation("ff.add", step, ses, F::ZERO),
+            Expr::Mul(ses) => self.operation("ff.mul", step, ses, F::ONE),
+            Expr::Neg(se) => format!("(ff.neg {})", self.expr(step, se)),
+            Expr::Pow(se, exp) => {
+                let factors = vec![se.as_ref().clone(); *exp as usize];
+                self.operation("ff.mul", step, &factors, F::ONE)
+            }
+            Expr::Query(query) => self.query(step, query),
+            Expr::Challenge(id, _) => self.constant("challenge", *id),
+            Expr::MI(se) => {
+                let se = self.expr(step, se);
+                let inverse = format!("|mi{}|", self.inverses);
+                self.inverses += 1;
+                self.declare(inverse.clone());
+
+                let (zero, one) = (self.value(&F::ZERO), self.value(&F::ONE));
+                self.lines.push(format!(
+                    "(assert (or (and (= {} {}) (= {} {})) (= (ff.mul {} {}) {})))",
+                    se, zero, inverse, zero, se, inverse, one
+                ));
+
+                inverse
+            }
+            #[cfg(feature = "halo2")]
+            Expr::Halo2Expr(expr) => panic!("cannot encode halo2 expression {:?}", expr),
+        }
+    }
+
+    /// Encodes the `operator` of the SMT-LIB theory applied to `ses`, `empty` without operands.
+    fn operation(&mut self, operator: &str, step: usize, ses: &[PIR<F>], empty: F) -> String {
+        match ses {
+            [] => self.value(&empty),
+            [se] => self.expr(step, se),
+            ses => {
+                let operands: Vec<_> = ses.iter().map(|se| self.expr(step, se)).collect();
+                format!("({} {})", operator, operands.join(" "))
+            }
+        }
+    }
+
+    fn query(&mut self, step: usize, query: &Queriable<F>) -> String {
+        let rotated = |rotation: i32| (step as i64 + rotation as i64).to_string();
+
+        match query {
+            Queriable::Internal(_) | Queriable::Forward(_, false) => {
+                self.constant(&step.to_string(), query.uuid())
+            }
+            Queriable::Forward(_, true) => self.constant(&rotated(1), query.uuid()),
+            Queriable::Shared(_, rotation) => self.constant(&rotated(*rotation), query.uuid()),
+            Queriable::Fixed(signal, rotation) => match self.fixed_value(signal, *rotation, step) {
+                Some(value) => self.value(&value),
+                None => self.constant(&rotated(*rotation), query.uuid()),
+            },
+            Queriable::StepTypeNext(step_type) => match self.steps.get(step + 1) {
+                Some(next) if *next == step_type.uuid() => self.value(&F::ONE),
+                Some(_) => self.value(&F::ZERO),
+                None => self.constant(&rotated(1), step_type.uuid()),
+            },
+            _ => panic!("cannot encode halo2 query {}", query.annotation()),
+        }
+    }
+
+    /// Returns the name of the constant of a signal, step type or challenge, with a prefix, like
+    /// the step of the value of a signal, declaring it the first time.
+    fn constant(&mut self, prefix: &str, uuid: UUID) -> String {
+        let name = match self.names.get(&uuid) {
+            Some(name) => name.clone(),
+            None => {
+                let annotation = self
+                    .circuit
+                    .annotation(uuid)
+                    .cloned()
+                    .unwrap_or_else(|| uuid.to_string())
+                    .replace(['|', '\\'], "_");
+                let name = if self.taken.contains(&annotation) {
+                    format!("{}#{}", annotation, uuid)
+                } else {
+                    annotation
+                };
+                self.taken.insert(name.clone());
+                self.names.insert(uuid, name.clone());
+                name
+            }
+        };
+
+        let constant = format!("|{}:{}|", prefix, name);
+        self.declare(constant.clone());
+
+        constant
+    }
+
+    fn declare(&mut self, constant: String) {
+        if self.declared.insert(constant.clone()) {
+            self.constants.push(constant);
+        }
+    }
+
+    fn value(&self, value: &F) -> String {
+        format!("(as ff{} F)", decimal(value))
+    }
+
+    fn finish(self) -> String {
+        let modulus = decimal(&-F::ONE) + 1u32;
+        let names: Vec<_> = self
+            .steps
+            .iter()
+            .map(|uuid| self.circuit.get_step_type(*uuid).name.clone())
+            .collect();
+
+        let mut smt = format!("; steps: {}\n", names.join(", "));
+        smt += "(set-logic QF_FF)\n";
+        smt += &format!("(define-sort F () (_ FiniteField {}))\n", modulus);
+        for constant in self.constants {
+            smt += &format!("(declare-const {} F)\n", constant);
+        }
+        for line in self.lines {
+            smt += &line;
+            smt.push('\n');
+        }
+
+        smt
+    }
+}
+
+fn decimal<F: PrimeField>(value: &F) -> BigUint {
+    BigUint::from_bytes_le(value.to_repr().as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::frontend::dsl::{
+        cb::{eq, lookup},
+        circuit,
+    };
+
+    #[test]
+    fn test_steps_smt_lib() {
+        let circuit: SBPIR<Fr, ()> = circuit("fibonacci", |ctx| {
+            let a = ctx.forward("a");
+            let b = ctx.forward("b");
+            let bit = ctx.fixed("bit");
+
+            ctx.step_type_def("fibo", |ctx| {
+                let c = ctx.internal("c");
+                ctx.setup(move |ctx| {
+                    ctx.constr(eq(a + b, c));
+                    ctx.transition(eq(b, a.next()));
+                    ctx.transition(eq(c, b.next()));
+                    ctx.add_lookup(lookup().add(a, bit));
+                });
+                ctx.wg(|_, _: ()| {})
+            });
+
+            ctx.pragma_num_steps(4);
+            ctx.fixed_gen(move |ctx| ctx.assign_with(bit, |i| Fr::from(i as u64 % 2)));
+        });
+        let step = circuit.step_types.values().next().unwrap().uuid();
+
+        let smt = steps_smt_lib(&circuit, &[step, step]);
+        let lines: Vec<_> = smt.lines().collect();
+
+        assert_eq!(
+            lines[..3],
+            [
+                "; steps: fibo, fibo",
+                "(set-logic QF_FF)",
+                "(define-sort F () (_ FiniteField \
+                 21888242871839275222246405745257275088548364400416034343698204186575808495617))",
+            ]
+        );
+        for constant in [
+            "|0:a|", "|0:b|", "|0:c|", "|1:a|", "|1:b|", "|1:c|", "|2:a|",
+        ] {
+            assert!(lines.contains(&format!("(declare-const {} F)", constant).as_str()));
+        }
+        assert!(!smt.contains("|2:c|"));
+        assert!(lines.contains(&"; step 1 fibo, transition b == next(a)"));
+        assert!(lines.contains(&"(assert (= (ff.add |1:b| (ff.neg |2:a|)) (as ff0 F)))"));
+        assert!(
+            lines.contains(&"(assert (or (and (= |0:a| (as ff0 F))) (and (= |0:a| (as ff1 F)))))")
+        );
+
+        assert_eq!(
+            step_type_smt_lib(&circuit, step).lines().next(),
+            Some("; steps: fibo")
+        );
+    }
+}
@@ -0,0 +1,290 @@
+//! Circuit specifications for auditors, rendered from the circuit itself: a table of the
+//! constraints, transition constraints and lookups of each step type, in Markdown or LaTeX, with
+//! signals and challenges named by their annotations.
+
+use std::{collections::HashMap, fmt::Debug};
+
+use crate::{
+    poly::{fmt_value, Expr},
+    sbpir::{query::Queriable, Constraint, StepType, PIR, SBPIR},
+    util::UUID,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpecFormat {
+    Markdown,
+    Latex,
+}
+
+/// Renders the specification of `circuit` in `format`, with the step types sorted by name.
+pub fn spec<F: Debug, TraceArgs>(circuit: &SBPIR<F, TraceArgs>, format: SpecFormat) -> String {
+    let mut step_types: Vec<_> = circuit.step_types.values().collect();
+    step_types.sort_by(|a, b| a.name.cmp(&b.name));
+
+    step_types
+        .into_iter()
+        .map(|step_type| step_type_spec(step_type, &circuit.annotations, format))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Renders the specification of `step_type` in `format`. `annotations` names the challenges.
+pub fn step_type_spec<F: Debug>(
+    step_type: &StepType<F>,
+    annotations: &HashMap<UUID, String>,
+    format: SpecFormat,
+) -> String {
+    let mut rows = Vec::new();
+    for constraint in step_type.constraints.iter() {
+        rows.push((
+            "constraint",
+            constraint.annotation.clone(),
+            format!("{} = 0", render(&constraint.expr, annotations, format)),
+        ));
+    }
+    for constraint in step_type.transition_constraints.iter() {
+        rows.push((
+            "transition",
+            constraint.annotation.clone(),
+            format!("{} = 0", render(&constraint.expr, annotations, format)),
+        ));
+    }
+    for lookup in step_type.lookups.iter() {
+        let srcs: Vec<_> = lookup
+            .exprs
+            .iter()
+            .map(|(src, _)| src.expr.clone())
+            .collect();
+        let dests: Vec<_> = lookup.exprs.iter().map(|(_, dest)| dest.clone()).collect();
+        let mut expr = format!(
+            "{} {} {}",
+            render_tuple(&srcs, annotations, format),
+            match format {
+                SpecFormat::Markdown => "in",
+                SpecFormat::Latex => "\\in",
+            },
+            render_tuple(&dests, annotations, format)
+        );
+        if let Some(Constraint { expr: enable, .. }) = &lookup.enable {
+            let enable = render(enable, annotations, format);
+            expr = match format {
+                SpecFormat::Markdown => format!("{} if {} != 0", expr, enable),
+                SpecFormat::Latex => format!("{} \\text{{ if }} {} \\neq 0", expr, enable),
+            };
+        }
+        rows.push(("lookup", lookup.annotation.clone(), expr));
+    }
+
+    let signals = step_type
+        .signals
+        .iter()
+        .map(|signal| signal.annotation())
+        .collect::<Vec<_>>();
+
+    match format {
+        SpecFormat::Markdown => {
+            let mut spec = format!("## Step type `{}`\n\n", step_type.name);
+            if !signals.is_empty() {
+                spec += &format!("Internal signals: `{}`\n\n", signals.join("`, `"));
+            }
+            spec += "| Kind | Annotation | Expression |\n|---|---|---|\n";
+            for (kind, annotation, expr) in rows {
+                spec += &format!(
+                    "| {} | {} | `{}` |\n",
+                    kind,
+                    escape_markdown(&annotation),
+                    escape_markdown(&expr)
+                );
+            }
+            spec
+        }
+        SpecFormat::Latex => {
+            let mut spec = format!(
+                "\\subsection*{{Step type {}}}\n",
+                escape_latex(&step_type.name)
+            );
+            if !signals.is_empty() {
+                let signals = signals
+                    .iter()
+                    .map(|signal| escape_latex(signal))
+                    .collect::<Vec<_>>();
+                spec += &format!("Internal signals: {}\n\n", signals.join(", "));
+            }
+            spec +=
+                "\\begin{tabular}{lll}\n\\hline\nKind & Annotation & Expression \\\\\n\\hline\n";
+            for (kind, annotation, expr) in rows {
+                spec += &format!(
+                    "{} & {} & ${}$ \\\\\n",
+                    kind,
+                    escape_latex(&annotation),
+                    expr
+                );
+            }
+            spec += "\\hline\n\\end{tabular}\n";
+            spec
+        }
+    }
+}
+
+/// Renders `expr` in `format`, as plain text for Markdown and math mode for LaTeX.
+pub fn render<F: Debug>(
+    expr: &PIR<F>,
+    annotations: &HashMap<UUID, String>,
+    format: SpecFormat,
+) -> String {
+    match expr {
+        Expr::Const(v) => fmt_value(v),
+        Expr::Sum(ses) => {
+            let mut rendered = String::new();
+            for (i, se) in ses.iter().enumerate() {
+                match (i, se) {
+                    (0, _) => rendered += &render_operand(se, annotations, format),
+                    (_, Expr::Neg(se)) => {
+                        rendered += &format!(" - {}", render_operand(se, annotations, format))
+                    }
+                    _ => rendered += &format!(" + {}", render_operand(se, annotations, format)),
+                }
+            }
+            rendered
+        }
+        Expr::Mul(ses) => ses
+            .iter()
+            .map(|se| render_operand(se, annotations, format))
+            .collect::<Vec<_>>()
+            .join(match format {
+                SpecFormat::Markdown => " * ",
+                SpecFormat::Latex => " \\cdot ",
+            }),
+        Expr::Neg(se) => format!("-{}", render_operand(se, annotations, format)),
+        Expr::Pow(se, exp) => match format {
+            SpecFormat::Markdown => format!("{}^{}", render_operand(se, annotations, format), exp),
+            SpecFormat::Latex => format!("{}^{{{}}}", render_operand(se, annotations, format), exp),
+        },
+        Expr::Query(q) => render_query(q, format),
+        #[cfg(feature = "halo2")]
+        Expr::Halo2Expr(e) => match format {
+            SpecFormat::Markdown => format!("halo2({:?})", e),
+            SpecFormat::Latex => format!("\\text{{{}}}", escape_latex(&format!("halo2({:?})", e))),
+        },
+        Expr::Challenge(id, phase) => {
+            let name = annotations
+                .get(id)
+                .cloned()
+                .unwrap_or_else(|| format!("challenge{}({})", phase, id));
+            match format {
+                SpecFormat::Markdown => name,
+                SpecFormat::Latex => format!("\\gamma_{{\\text{{{}}}}}", escape_latex(&name)),
+            }
+        }
+        Expr::MI(se) => match format {
+            SpecFormat::Markdown => format!("{}^-1", render_operand(se, annotations, format)),
+            SpecFormat::Latex => format!("\\frac{{1}}{{{}}}", render(se, annotations, format)),
+        },
+    }
+}
+
+/// Renders an operand of a product, power, negation or inverse, in parentheses if it is a sum.
+fn render_operand<F: Debug>(
+    expr: &PIR<F>,
+    annotations: &HashMap<UUID, String>,
+    format: SpecFormat,
+) -> String {
+    match expr {
+        Expr::Sum(_) | Expr::Neg(_) => match format {
+            SpecFormat::Markdown => format!("({})", render(expr, annotations, format)),
+            SpecFormat::Latex => format!("\\left({}\\right)", render(expr, annotations, format)),
+        },
+        _ => render(expr, annotations, format),
+    }
+}
+
+fn render_tuple<F: Debug>(
+    exprs: &[PIR<F>],
+    annotations: &HashMap<UUID, String>,
+    format: SpecFormat,
+) -> String {
+    let rendered = exprs
+        .iter()
+        .map(|expr| render(expr, annotations, format))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    match format {
+        SpecFormat::Markdown => format!("({})", rendered),
+        SpecFormat::Latex => format!("\\left({}\\right)", rendered),
+    }
+}
+
+fn render_query<F>(query: &Queriable<F>, format: SpecFormat) -> String {
+    match format {
+        SpecFormat::Markdown => query.annotation(),
+        SpecFormat::Latex => format!("\\text{{{}}}", escape_latex(&query.annotation())),
+    }
+}
+
+/// Escapes the characters with a meaning in a Markdown table cell.
+fn escape_markdown(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+/// Escapes the characters with a meaning in LaTeX text.
+fn escape_latex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped += "\\textbackslash{}",
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '~' => escaped += "\\textasciitilde{}",
+            '^' => escaped += "\\textasciicircum{}",
+            '\n' => escaped.push(' '),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::frontend::dsl::{
+        cb::{eq, lookup},
+        circuit,
+    };
+
+    #[test]
+    fn test_spec() {
+        let circuit = circuit::<Fr, (), _>("spec", |ctx| {
+            let a = ctx.forward("a");
+            let r = ctx.challenge("r", 0);
+            ctx.step_type_def("step", |ctx| {
+                let b = ctx.internal("b_1");
+                ctx.setup(move |ctx| {
+                    ctx.constr(eq(a * b, r.clone() - 1));
+                    ctx.transition(eq(a.next(), a + 1));
+                    ctx.add_lookup(lookup().add(a, b));
+                });
+                ctx.wg(|_, _: ()| {})
+            });
+        });
+
+        let markdown = spec(&circuit, SpecFormat::Markdown);
+        assert!(markdown.contains("## Step type `step`"));
+        assert!(markdown.contains("Internal signals: `b_1`"));
+        assert!(markdown.contains("`a * b_1 - (r - 0x1) = 0`"));
+        assert!(markdown.contains("| transition |"));
+        assert!(markdown.contains("`(a) in (b_1)`"));
+
+        let latex = spec(&circuit, SpecFormat::Latex);
+        assert!(latex.contains("\\subsection*{Step type step}"));
+        assert!(latex.contains("Internal signals: b\\_1"));
+        assert!(latex.contains("\\text{a} \\cdot \\text{b\\_1}"));
+        assert!(latex.contains("\\gamma_{\\text{r}}"));
+        assert!(latex.contains("\\end{tabular}"));
+    }
+}
@@ -0,0 +1,227 @@
+use std::hash::Hash;
+
+use crate::{
+    field::Field,
+    frontend::dsl::{
+        cb::{eq, Constraint},
+        CircuitContext, StepTypeSetupContext,
+    },
+    poly::Expr,
+    sbpir::{query::Queriable, ExposeOffset, PIR},
+    wit_gen::StepInstance,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccumulatorKind {
+    Sum,
+    Product,
+}
+
+impl AccumulatorKind {
+    /// Returns the starting value, which absorbing leaves unchanged.
+    pub fn identity<F: Field>(&self) -> F {
+        match self {
+            AccumulatorKind::Sum => F::ZERO,
+            AccumulatorKind::Product => F::ONE,
+        }
+    }
+
+    pub fn absorb<F: Field>(&self, acc: F, value: F) -> F {
+        match self {
+            AccumulatorKind::Sum => acc + value,
+            AccumulatorKind::Product => acc * value,
+        }
+    }
+}
+
+/// Running sum or product of a value per step, accumulated across the steps in a forward signal.
+/// The signal holds the accumulation of the previous steps, so it starts at the identity and the
+/// total is in the step after the last absorbing one, which can be exposed with `expose`.
+#[derive(Clone)]
+pub struct RunningAccumulator<F> {
+    kind: AccumulatorKind,
+    acc: Queriable<F>,
+}
+
+impl<F: Field + From<u64>> RunningAccumulator<F> {
+    pub fn new<TraceArgs>(
+        ctx: &mut CircuitContext<F, TraceArgs>,
+        name: &str,
+        kind: AccumulatorKind,
+    ) -> RunningAccumulator<F> {
+        RunningAccumulator {
+            kind,
+            acc: ctx.forward(name),
+        }
+    }
+
+    pub fn kind(&self) -> AccumulatorKind {
+        self.kind
+    }
+
+    pub fn acc(&self) -> Queriable<F> {
+        self.acc
+    }
+
+    /// Exposes the accumulator of the step at `offset`, usually `ExposeOffset::Last` for the total.
+    pub fn expose<TraceArgs>(&self, ctx: &mut CircuitContext<F, TraceArgs>, offset: ExposeOffset) {
+        ctx.expose(self.acc, offset);
+    }
+
+    /// Constrains the accumulator to be the identity, in the first step.
+    #[track_caller]
+    pub fn setup_start(&self, ctx: &mut StepTypeSetupContext<F>) {
+        ctx.constr(eq(self.acc, Expr::Const(self.kind.identity::<F>())));
+    }
+
+    /// Constrains the accumulator of the next step to absorb `value`.
+    #[track_caller]
+    pub fn setup_absorb<V: Into<Constraint<F>>>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        value: V,
+    ) {
+        ctx.transition(eq(self.acc.next(), self.absorbed(value)));
+    }
+
+    /// Constrains the accumulator of the next step to be the same, in the step types that do not
+    /// absorb a value.
+    #[track_caller]
+    pub fn setup_keep(&self, ctx: &mut StepTypeSetupContext<F>) {
+        ctx.transition(eq(self.acc.next(), self.acc));
+    }
+
+    /// Returns the accumulation after absorbing `value` in this step.
+    pub fn absorbed<V: Into<Constraint<F>>>(&self, value: V) -> PIR<F> {
+        let value: PIR<F> = value.into().expr;
+
+        match self.kind {
+            AccumulatorKind::Sum => self.acc + value,
+            AccumulatorKind::Product => self.acc * value,
+        }
+    }
+}
+
+impl<F: Field + From<u64> + Hash> RunningAccumulator<F> {
+    /// Assigns the accumulator and returns its value for the next step after absorbing `value`,
+    /// which is the identity in the step types that keep it.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, acc: F, value: F) -> F {
+        ctx.assign(self.acc, acc);
+
+        self.kind.absorb(acc, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{frontend::dsl::circuit, sbpir::SBPIR, wit_gen::TraceGenerator};
+
+    type Args = (Vec<u64>, bool);
+
+    fn satisfied(circuit: &SBPIR<Fr, Args>, args: Args) -> bool {
+        let num_steps = args.0.len() + 1;
+        let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), num_steps).generate(args);
+
+        trace
+            .step_instances
+            .iter()
+            .enumerate()
+            .all(|(i, instance)| {
+                let step_type = &circuit.step_types[&instance.step_type_uuid];
+                let mut assignments = instance.assignments.clone();
+                if let Some(next) = trace.step_instances.get(i + 1) {
+                    for (signal, value) in next.assignments.iter() {
+                        if let Queriable::Forward(forward, false) = signal {
+                            assignments.insert(Queriable::Forward(*forward, true), *value);
+                        }
+                    }
+                }
+
+                let transitions = step_type
+                    .transition_constraints
+                    .iter()
+                    .filter(|_| i + 1 < num_steps);
+                step_type
+                    .constraints
+                    .iter()
+                    .map(|c| &c.expr)
+                    .chain(transitions.map(|c| &c.expr))
+                    .all(|expr| expr.eval(&assignments) == Some(Fr::ZERO))
+            })
+    }
+
+    fn accumulator_circuit(kind: AccumulatorKind) -> SBPIR<Fr, Args> {
+        circuit("accumulator", |ctx| {
+            let value = ctx.forward("value");
+            let accumulator = RunningAccumulator::new(ctx, "acc", kind);
+            accumulator.expose(ctx, ExposeOffset::Last);
+
+            let mut absorb_types = Vec::new();
+            for (name, first) in [("first", true), ("absorb", false)] {
+                let accumulator = accumulator.clone();
+                absorb_types.push(ctx.step_type_def(name, |ctx| {
+                    let setup_accumulator = accumulator.clone();
+                    ctx.setup(move |ctx| {
+                        if first {
+                            setup_accumulator.setup_start(ctx);
+                        }
+                        setup_accumulator.setup_absorb(ctx, value);
+                    });
+
+                    ctx.wg(move |ctx, (acc, v): (Fr, Fr)| {
+                        ctx.assign(value, v);
+                        accumulator.wg(ctx, acc, v);
+                    })
+                }));
+            }
+            let end_accumulator = accumulator.clone();
+            let end = ctx.step_type_def("end", |ctx| {
+                let setup_accumulator = end_accumulator.clone();
+                ctx.setup(move |ctx| setup_accumulator.setup_keep(ctx));
+
+                ctx.wg(move |ctx, (acc, v): (Fr, Fr)| {
+                    ctx.assign(value, v);
+                    end_accumulator.wg(ctx, acc, kind.identity());
+                })
+            });
+
+            ctx.trace(move |ctx, (values, tamper): Args| {
+                let mut acc = kind.identity();
+                for (i, v) in values.iter().enumerate() {
+                    ctx.add(&absorb_types[(i > 0) as usize], (acc, Fr::from(*v)));
+                    acc = kind.absorb(acc, Fr::from(*v));
+                }
+                if tamper {
+                    acc += Fr::ONE;
+                }
+                ctx.add(&end, (acc, Fr::ZERO));
+            });
+        })
+    }
+
+    #[test]
+    fn test_running_sum() {
+        let circuit = accumulator_circuit(AccumulatorKind::Sum);
+        assert_eq!(circuit.exposed.len(), 1);
+
+        let trace =
+            TraceGenerator::new(circuit.trace.clone().unwrap(), 4).generate((vec![3, 4, 5], false));
+        let (acc, _) = &circuit.exposed[0];
+        assert_eq!(trace.step_instances[3].assignments[acc], Fr::from(12));
+
+        assert!(satisfied(&circuit, (vec![3, 4, 5], false)));
+        assert!(!satisfied(&circuit, (vec![3, 4, 5], true)));
+    }
+
+    #[test]
+    fn test_running_product() {
+        let circuit = accumulator_circuit(AccumulatorKind::Product);
+
+        assert!(satisfied(&circuit, (vec![3, 4, 5], false)));
+        assert!(satisfied(&circuit, (vec![7], false)));
+        assert!(!satisfied(&circuit, (vec![3, 4, 5], true)));
+    }
+}
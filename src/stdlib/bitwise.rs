@@ -0,0 +1,246 @@
+use std::hash::Hash;
+
+use crate::{
+    field::Field,
+    frontend::dsl::{
+        cb::{table, Constraint},
+        lb::LookupTable,
+        CircuitContext, StepTypeContext, StepTypeSetupContext,
+    },
+    poly::Expr,
+    sbpir::query::Queriable,
+    wit_gen::StepInstance,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BitwiseOp {
+    Xor,
+    And,
+    Or,
+}
+
+impl BitwiseOp {
+    pub fn apply(&self, lhs: u8, rhs: u8) -> u8 {
+        match self {
+            BitwiseOp::Xor => lhs ^ rhs,
+            BitwiseOp::And => lhs & rhs,
+            BitwiseOp::Or => lhs | rhs,
+        }
+    }
+
+    /// Value of the tag column of the rows of the operation in a `BitwiseTable`.
+    fn tag(&self) -> u64 {
+        match self {
+            BitwiseOp::Xor => 0,
+            BitwiseOp::And => 1,
+            BitwiseOp::Or => 2,
+        }
+    }
+}
+
+/// Lookup table with the columns `tag`, `lhs`, `rhs` and `lhs op rhs` for all the pairs of bytes
+/// and the given operations, 65536 rows each. See `bitwise_table`.
+#[derive(Clone)]
+pub struct BitwiseTable {
+    table: LookupTable,
+    ops: Vec<BitwiseOp>,
+}
+
+/// Creates a `BitwiseTable` for `ops`. It sets the number of steps of the circuit, so it is meant
+/// to be used in a dedicated fixed sub-circuit.
+pub fn bitwise_table<F: Field + From<u64> + Hash, TraceArgs>(
+    ctx: &mut CircuitContext<F, TraceArgs>,
+    ops: &[BitwiseOp],
+) -> BitwiseTable {
+    assert!(
+        !ops.is_empty(),
+        "bitwise table needs at least one operation"
+    );
+
+    let tag = ctx.fixed("bitwise tag");
+    let lhs = ctx.fixed("bitwise lhs");
+    let rhs = ctx.fixed("bitwise rhs");
+    let result = ctx.fixed("bitwise result");
+
+    ctx.pragma_num_steps(ops.len() << 16);
+    let fixed_ops = ops.to_vec();
+    ctx.fixed_gen(move |ctx| {
+        for (i, op) in fixed_ops.iter().enumerate() {
+            for j in 0..(1usize << 16) {
+                let (a, b) = ((j >> 8) as u8, j as u8);
+                let row = (i << 16) + j;
+                ctx.assign(row, tag, F::from(op.tag()));
+                ctx.assign(row, lhs, F::from(a as u64));
+                ctx.assign(row, rhs, F::from(b as u64));
+                ctx.assign(row, result, F::from(op.apply(a, b) as u64));
+            }
+        }
+    });
+
+    BitwiseTable {
+        table: ctx.new_table(table().add(tag).add(lhs).add(rhs).add(result)),
+        ops: ops.to_vec(),
+    }
+}
+
+impl BitwiseTable {
+    pub fn ops(&self) -> &[BitwiseOp] {
+        &self.ops
+    }
+
+    pub fn table(&self) -> LookupTable {
+        self.table
+    }
+
+    /// Looks up `result` being `lhs op rhs`, which also range checks the three of them as bytes.
+    pub fn lookup<F, A, B, C>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        op: BitwiseOp,
+        lhs: A,
+        rhs: B,
+        result: C,
+    ) where
+        F: Field + From<u64>,
+        A: Into<Constraint<F>>,
+        B: Into<Constraint<F>>,
+        C: Into<Constraint<F>>,
+    {
+        assert!(
+            self.ops.contains(&op),
+            "{:?} is not in the bitwise table",
+            op
+        );
+
+        ctx.add_lookup(
+            self.table
+                .apply(Expr::Const(F::from(op.tag())))
+                .apply(lhs)
+                .apply(rhs)
+                .apply(result),
+        );
+    }
+}
+
+/// In-step bitwise operation of two bytes, with the result in a signal.
+#[derive(Clone)]
+pub struct ByteOp<F> {
+    op: BitwiseOp,
+    result: Queriable<F>,
+}
+
+impl<F: Field + From<u64>> ByteOp<F> {
+    pub fn new(ctx: &mut StepTypeContext<F>, name: &str, op: BitwiseOp) -> ByteOp<F> {
+        ByteOp {
+            op,
+            result: ctx.internal(format!("{}.result", name).as_str()),
+        }
+    }
+
+    /// Constrains the result to be `lhs op rhs`, both of them being bytes.
+    #[track_caller]
+    pub fn setup<A: Into<Constraint<F>>, B: Into<Constraint<F>>>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        lhs: A,
+        rhs: B,
+        table: &BitwiseTable,
+    ) {
+        table.lookup(ctx, self.op, lhs, rhs, self.result);
+    }
+
+    pub fn result(&self) -> Queriable<F> {
+        self.result
+    }
+}
+
+impl<F: Field + From<u64> + Hash> ByteOp<F> {
+    /// Assigns the result and returns it.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, lhs: u8, rhs: u8) -> u8 {
+        let result = self.op.apply(lhs, rhs);
+        ctx.assign(self.result, F::from(result as u64));
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{frontend::dsl::circuit, sbpir::SBPIR, wit_gen::TraceGenerator};
+
+    #[test]
+    fn test_bitwise_table() {
+        let ops = [BitwiseOp::Xor, BitwiseOp::And, BitwiseOp::Or];
+
+        let circuit: SBPIR<Fr, (u8, u8, bool)> = circuit("bitwise", |ctx| {
+            let table = bitwise_table(ctx, &ops);
+            let lhs = ctx.forward("lhs");
+            let rhs = ctx.forward("rhs");
+
+            let step = ctx.step_type_def("step", |ctx| {
+                let byte_ops: Vec<ByteOp<Fr>> = ops
+                    .iter()
+                    .map(|op| ByteOp::new(ctx, format!("{:?}", op).as_str(), *op))
+                    .collect();
+
+                let setup_byte_ops = byte_ops.clone();
+                ctx.setup(move |ctx| {
+                    for byte_op in setup_byte_ops.iter() {
+                        byte_op.setup(ctx, lhs, rhs, &table);
+                    }
+                });
+
+                ctx.wg(move |ctx, (a, b, tamper): (u8, u8, bool)| {
+                    ctx.assign(lhs, Fr::from(a as u64));
+                    ctx.assign(rhs, Fr::from(b as u64));
+                    for byte_op in byte_ops.iter() {
+                        byte_op.wg(ctx, a, b);
+                    }
+                    if tamper {
+                        ctx.assign(byte_ops[1].result(), Fr::from(256));
+                    }
+                })
+            });
+
+            ctx.trace(move |ctx, args| ctx.add(&step, args));
+        });
+
+        let fixed = circuit.fixed_assignments.as_ref().unwrap();
+        let step_type = circuit.step_types.values().next().unwrap();
+        let columns: Vec<Queriable<Fr>> = step_type.lookups[0]
+            .exprs
+            .iter()
+            .map(|(_, dest)| match dest {
+                Expr::Query(column) => *column,
+                _ => unreachable!(),
+            })
+            .collect();
+        let rows: HashSet<Vec<Fr>> = (0..3 << 16)
+            .map(|row| columns.iter().map(|column| fixed[column][row]).collect())
+            .collect();
+        assert_eq!(rows.len(), 3 << 16);
+
+        let in_table = |args| {
+            let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), 1).generate(args);
+            let assignments = &trace.step_instances[0].assignments;
+
+            step_type.lookups.iter().all(|lookup| {
+                let src: Vec<Fr> = lookup
+                    .exprs
+                    .iter()
+                    .map(|(src, _)| src.expr.eval(assignments).unwrap())
+                    .collect();
+                rows.contains(&src)
+            })
+        };
+
+        assert!(in_table((0xa5, 0x3c, false)));
+        assert!(in_table((0xff, 0x00, false)));
+        assert!(!in_table((0xa5, 0x3c, true)));
+    }
+}
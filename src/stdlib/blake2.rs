@@ -0,0 +1,693 @@
+use std::hash::Hash;
+
+use crate::{
+    field::Field,
+    frontend::dsl::{
+        cb::{eq, table, Constraint},
+        lb::LookupTable,
+        CircuitContext, StepTypeContext, StepTypeSetupContext,
+    },
+    poly::{Expr, ToExpr},
+    sbpir::{query::Queriable, PIR},
+    wit_gen::StepInstance,
+};
+
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+/// State indices `a`, `b`, `c` and `d` of every G function call in a round.
+const G_INDICES: [[usize; 4]; 8] = [
+    [0, 4, 8, 12],
+    [1, 5, 9, 13],
+    [2, 6, 10, 14],
+    [3, 7, 11, 15],
+    [0, 5, 10, 15],
+    [1, 6, 11, 12],
+    [2, 7, 8, 13],
+    [3, 4, 9, 14],
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Blake2Variant {
+    /// 64-bit words, 12 rounds.
+    Blake2b,
+    /// 32-bit words, 10 rounds.
+    Blake2s,
+}
+
+impl Blake2Variant {
+    pub fn word_bits(&self) -> usize {
+        match self {
+            Blake2Variant::Blake2b => 64,
+            Blake2Variant::Blake2s => 32,
+        }
+    }
+
+    pub fn rounds(&self) -> usize {
+        match self {
+            Blake2Variant::Blake2b => 12,
+            Blake2Variant::Blake2s => 10,
+        }
+    }
+
+    pub fn iv(&self) -> [u64; 8] {
+        match self {
+            Blake2Variant::Blake2b => [
+                0x6a09e667f3bcc908,
+                0xbb67ae8584caa73b,
+                0x3c6ef372fe94f82b,
+                0xa54ff53a5f1d36f1,
+                0x510e527fade682d1,
+                0x9b05688c2b3e6c1f,
+                0x1f83d9abfb41bd6b,
+                0x5be0cd19137e2179,
+            ],
+            Blake2Variant::Blake2s => [
+                0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+                0x5be0cd19,
+            ],
+        }
+    }
+
+    /// Right rotations of the G function. The first three are multiples of 4 bits and the last
+    /// one is 3 bits past a multiple of 4.
+    fn rotations(&self) -> [usize; 4] {
+        match self {
+            Blake2Variant::Blake2b => [32, 24, 16, 63],
+            Blake2Variant::Blake2s => [16, 12, 8, 7],
+        }
+    }
+
+    fn nibbles(&self) -> usize {
+        self.word_bits() / 4
+    }
+
+    fn mask(&self) -> u64 {
+        u64::MAX >> (64 - self.word_bits())
+    }
+
+    fn rotr(&self, x: u64, n: usize) -> u64 {
+        ((x >> n) | (x << (self.word_bits() - n))) & self.mask()
+    }
+}
+
+/// Values of a G function call, used for witness generation. The sums keep their carry and the
+/// XORs are stored before rotating.
+struct GValues {
+    sums: [(u64, u64); 4],
+    xors: [u64; 4],
+}
+
+struct CompressionValues {
+    t: [u64; 2],
+    gs: Vec<GValues>,
+    partial: [u64; 8],
+    output: [u64; 8],
+}
+
+fn compress(
+    variant: Blake2Variant,
+    rounds: usize,
+    h: &[u64; 8],
+    m: &[u64; 16],
+    t: u128,
+    f: bool,
+) -> CompressionValues {
+    let iv = variant.iv();
+    let mask = variant.mask();
+    let bits = variant.word_bits();
+    let rotations = variant.rotations();
+    let t = [(t as u64) & mask, ((t >> bits) as u64) & mask];
+
+    let mut v = [0u64; 16];
+    v[..8].copy_from_slice(h);
+    v[8..].copy_from_slice(&iv);
+    v[12] ^= t[0];
+    v[13] ^= t[1];
+    if f {
+        v[14] ^= mask;
+    }
+
+    let add = |terms: &[u64]| -> (u64, u64) {
+        let sum: u128 = terms.iter().map(|term| *term as u128).sum();
+        (sum as u64 & mask, (sum >> bits) as u64)
+    };
+
+    let mut gs = Vec::with_capacity(rounds * 8);
+    for round in 0..rounds {
+        let s = SIGMA[round % 10];
+        for (i, [a, b, c, d]) in G_INDICES.iter().enumerate() {
+            let (x, y) = (m[s[2 * i]], m[s[2 * i + 1]]);
+            let mut sums = [(0, 0); 4];
+            let mut xors = [0; 4];
+
+            sums[0] = add(&[v[*a], v[*b], x]);
+            v[*a] = sums[0].0;
+            xors[0] = v[*d] ^ v[*a];
+            v[*d] = variant.rotr(xors[0], rotations[0]);
+
+            sums[1] = add(&[v[*c], v[*d]]);
+            v[*c] = sums[1].0;
+            xors[1] = v[*b] ^ v[*c];
+            v[*b] = variant.rotr(xors[1], rotations[1]);
+
+            sums[2] = add(&[v[*a], v[*b], y]);
+            v[*a] = sums[2].0;
+            xors[2] = v[*d] ^ v[*a];
+            v[*d] = variant.rotr(xors[2], rotations[2]);
+
+            sums[3] = add(&[v[*c], v[*d]]);
+            v[*c] = sums[3].0;
+            xors[3] = v[*b] ^ v[*c];
+            v[*b] = variant.rotr(xors[3], rotations[3]);
+
+            gs.push(GValues { sums, xors });
+        }
+    }
+
+    let mut partial = [0u64; 8];
+    let mut output = [0u64; 8];
+    for i in 0..8 {
+        partial[i] = h[i] ^ v[i];
+        output[i] = partial[i] ^ v[i + 8];
+    }
+
+    CompressionValues {
+        t,
+        gs,
+        partial,
+        output,
+    }
+}
+
+/// Computes the Blake2 compression function `F` natively. `t` is the offset counter and `f` the
+/// final block flag. The words of `h` and `m` must fit in the word size of the variant.
+pub fn blake2_compress(
+    variant: Blake2Variant,
+    rounds: usize,
+    h: &[u64; 8],
+    m: &[u64; 16],
+    t: u128,
+    f: bool,
+) -> [u64; 8] {
+    compress(variant, rounds, h, m, t, f).output
+}
+
+/// Creates a lookup table with the columns `a`, `b` and `a ^ b` for all the 4-bit values. It sets
+/// the number of steps of the circuit, so it is meant to be used in a dedicated fixed sub-circuit.
+pub fn blake2_xor_table<F: Field + From<u64> + Hash, TraceArgs>(
+    ctx: &mut CircuitContext<F, TraceArgs>,
+) -> LookupTable {
+    let lhs = ctx.fixed("xor lhs");
+    let rhs = ctx.fixed("xor rhs");
+    let result = ctx.fixed("xor result");
+
+    ctx.pragma_num_steps(256);
+    ctx.fixed_gen(move |ctx| {
+        for i in 0..256u64 {
+            ctx.assign(i as usize, lhs, F::from(i >> 4));
+            ctx.assign(i as usize, rhs, F::from(i & 15));
+            ctx.assign(i as usize, result, F::from((i >> 4) ^ (i & 15)));
+        }
+    });
+
+    ctx.new_table(table().add(lhs).add(rhs).add(result))
+}
+
+/// A word given by its 4-bit nibbles, least significant first.
+type Nibbles<F> = Vec<PIR<F>>;
+
+#[derive(Clone)]
+struct SumSignals<F> {
+    nibbles: Vec<Queriable<F>>,
+    carry: Queriable<F>,
+}
+
+#[derive(Clone)]
+struct GSignals<F> {
+    sums: Vec<SumSignals<F>>,
+    xors: Vec<Vec<Queriable<F>>>,
+    /// Top bit and low 3 bits of every nibble of the last XOR, to rotate it by 3 bits.
+    high: Vec<Queriable<F>>,
+    low: Vec<Queriable<F>>,
+}
+
+/// In-step gadget for the Blake2b or Blake2s compression function `F`. Words are handled as 4-bit
+/// nibbles: XORs are looked up in a table created by `blake2_xor_table`, rotations by a multiple of
+/// 4 bits are free rewiring and the remaining 3-bit rotation splits the nibbles. Modular additions
+/// keep their carry in a signal. Range checks are lookups in the XOR table as well, `(n, 0, n)` for
+/// nibbles and `(n, 8, n + 8)` for carries and the 3-bit splits. A Blake2s compression adds around
+/// 7000 signals to the step type.
+#[derive(Clone)]
+pub struct Blake2Compression<F> {
+    variant: Blake2Variant,
+    rounds: usize,
+    h: Vec<Vec<Queriable<F>>>,
+    m: Vec<Vec<Queriable<F>>>,
+    t: Vec<Vec<Queriable<F>>>,
+    t_xor: Vec<Vec<Queriable<F>>>,
+    gs: Vec<GSignals<F>>,
+    partial: Vec<Vec<Queriable<F>>>,
+    output: Vec<Vec<Queriable<F>>>,
+}
+
+impl<F: Field + From<u64>> Blake2Compression<F> {
+    /// Adds the signals of the compression to the step type, annotated with the given name.
+    pub fn new(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        variant: Blake2Variant,
+        rounds: usize,
+    ) -> Blake2Compression<F> {
+        let nibbles = variant.nibbles();
+        let mut signals = |label: String, count: usize| -> Vec<Queriable<F>> {
+            (0..count)
+                .map(|i| ctx.internal(format!("{}.{}[{}]", name, label, i).as_str()))
+                .collect()
+        };
+
+        let h = (0..8)
+            .map(|i| signals(format!("h[{}]", i), nibbles))
+            .collect();
+        let m = (0..16)
+            .map(|i| signals(format!("m[{}]", i), nibbles))
+            .collect();
+        let t = (0..2)
+            .map(|i| signals(format!("t[{}]", i), nibbles))
+            .collect();
+        let t_xor = (0..2)
+            .map(|i| signals(format!("v[{}]", 12 + i), nibbles))
+            .collect();
+
+        let mut gs = Vec::with_capacity(rounds * 8);
+        for g in 0..rounds * 8 {
+            let carries = signals(format!("g[{}].carry", g), 4);
+            let sums = carries
+                .into_iter()
+                .enumerate()
+                .map(|(i, carry)| SumSignals {
+                    nibbles: signals(format!("g[{}].sum[{}]", g, i), nibbles),
+                    carry,
+                })
+                .collect();
+            let xors = (0..4)
+                .map(|i| signals(format!("g[{}].xor[{}]", g, i), nibbles))
+                .collect();
+            let high = signals(format!("g[{}].high", g), nibbles);
+            let low = signals(format!("g[{}].low", g), nibbles);
+
+            gs.push(GSignals {
+                sums,
+                xors,
+                high,
+                low,
+            });
+        }
+
+        let partial = (0..8)
+            .map(|i| signals(format!("partial[{}]", i), nibbles))
+            .collect();
+        let output = (0..8)
+            .map(|i| signals(format!("output[{}]", i), nibbles))
+            .collect();
+
+        Blake2Compression {
+            variant,
+            rounds,
+            h,
+            m,
+            t,
+            t_xor,
+            gs,
+            partial,
+            output,
+        }
+    }
+
+    fn word(&self, nibbles: &[PIR<F>]) -> PIR<F> {
+        nibbles
+            .iter()
+            .enumerate()
+            .fold(0u64.expr(), |acc, (i, nibble)| {
+                acc + Expr::Const(F::from(16).pow([i as u64])) * nibble.clone()
+            })
+    }
+
+    fn constant(&self, value: u64) -> Nibbles<F> {
+        (0..self.variant.nibbles())
+            .map(|i| ((value >> (4 * i)) & 15).expr())
+            .collect()
+    }
+
+    /// Rotates right by a multiple of 4 bits.
+    fn rotate(&self, nibbles: &[Queriable<F>], bits: usize) -> Nibbles<F> {
+        let n = self.variant.nibbles();
+
+        (0..n).map(|i| nibbles[(i + bits / 4) % n].expr()).collect()
+    }
+
+    fn exprs(signals: &[Queriable<F>]) -> Nibbles<F> {
+        signals.iter().map(|s| s.expr()).collect()
+    }
+
+    /// Constrains `result` to be the XOR of the nibbles `lhs` and `rhs`.
+    #[track_caller]
+    fn setup_xor(
+        ctx: &mut StepTypeSetupContext<F>,
+        xor_table: LookupTable,
+        lhs: &Nibbles<F>,
+        rhs: &Nibbles<F>,
+        result: &[Queriable<F>],
+    ) {
+        for ((lhs, rhs), result) in lhs.iter().zip(rhs.iter()).zip(result.iter()) {
+            ctx.add_lookup(
+                xor_table
+                    .apply(lhs.clone())
+                    .apply(rhs.clone())
+                    .apply(*result),
+            );
+        }
+    }
+
+    /// Constrains the nibbles of `sum` to be the sum of `terms` modulo the word size.
+    #[track_caller]
+    fn setup_sum(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        xor_table: LookupTable,
+        terms: &[&Nibbles<F>],
+        sum: &SumSignals<F>,
+    ) {
+        let total = terms
+            .iter()
+            .fold(0u64.expr(), |acc, term| acc + self.word(term));
+        let overflow = Expr::Const(F::from(2).pow([self.variant.word_bits() as u64]));
+
+        ctx.constr(eq(
+            self.word(&Self::exprs(&sum.nibbles)) + overflow * sum.carry,
+            total,
+        ));
+        Self::setup_range(ctx, xor_table, &sum.nibbles);
+        Self::setup_low_bits(ctx, xor_table, sum.carry);
+    }
+
+    #[track_caller]
+    fn setup_range(
+        ctx: &mut StepTypeSetupContext<F>,
+        xor_table: LookupTable,
+        nibbles: &[Queriable<F>],
+    ) {
+        for nibble in nibbles.iter() {
+            ctx.add_lookup(xor_table.apply(*nibble).apply(0u64).apply(*nibble));
+        }
+    }
+
+    /// Constrains `value` to be in `[0, 8)`, as the only 4-bit values whose XOR with 8 is the
+    /// value plus 8.
+    #[track_caller]
+    fn setup_low_bits(
+        ctx: &mut StepTypeSetupContext<F>,
+        xor_table: LookupTable,
+        value: Queriable<F>,
+    ) {
+        ctx.add_lookup(xor_table.apply(value).apply(8u64).apply(value + 8u64));
+    }
+
+    /// Constrains the compression of the 16 `m` words with the 8 `h` words, the offset counter `t`
+    /// given as two words and the boolean final block flag `f`.
+    #[track_caller]
+    pub fn setup<V: Into<Constraint<F>> + Clone>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        h: &[V],
+        m: &[V],
+        t: &[V],
+        f: V,
+        xor_table: LookupTable,
+    ) {
+        assert_eq!(h.len(), 8, "blake2 state must have 8 words");
+        assert_eq!(m.len(), 16, "blake2 block must have 16 words");
+        assert_eq!(t.len(), 2, "blake2 offset counter must have 2 words");
+
+        for (value, nibbles) in h
+            .iter()
+            .chain(m.iter())
+            .chain(t.iter())
+            .zip(self.h.iter().chain(self.m.iter()).chain(self.t.iter()))
+        {
+            ctx.constr(eq(value.clone(), self.word(&Self::exprs(nibbles))));
+            Self::setup_range(ctx, xor_table, nibbles);
+        }
+
+        let f: PIR<F> = f.into().expr;
+        ctx.constr(eq(f.clone() * (1u64.expr() - f.clone()), 0u64));
+
+        let iv = self.variant.iv();
+        let mut v: Vec<Nibbles<F>> = self.h.iter().map(|h| Self::exprs(h)).collect();
+        v.extend(iv.iter().map(|word| self.constant(*word)));
+        for i in 0..2 {
+            Self::setup_xor(
+                ctx,
+                xor_table,
+                &v[12 + i],
+                &Self::exprs(&self.t[i]),
+                &self.t_xor[i],
+            );
+            v[12 + i] = Self::exprs(&self.t_xor[i]);
+        }
+        v[14] = (0..self.variant.nibbles())
+            .map(|i| {
+                let nibble = (iv[6] >> (4 * i)) & 15;
+                nibble.expr() + f.clone() * Expr::Const(F::from(15) - F::from(2 * nibble))
+            })
+            .collect();
+
+        let m: Vec<Nibbles<F>> = self.m.iter().map(|m| Self::exprs(m)).collect();
+        let rotations = self.variant.rotations();
+        for round in 0..self.rounds {
+            let s = SIGMA[round % 10];
+            for (i, [a, b, c, d]) in G_INDICES.iter().enumerate() {
+                let g = &self.gs[round * 8 + i];
+                let (x, y) = (&m[s[2 * i]], &m[s[2 * i + 1]]);
+
+                self.setup_sum(ctx, xor_table, &[&v[*a], &v[*b], x], &g.sums[0]);
+                v[*a] = Self::exprs(&g.sums[0].nibbles);
+                Self::setup_xor(ctx, xor_table, &v[*d], &v[*a], &g.xors[0]);
+                v[*d] = self.rotate(&g.xors[0], rotations[0]);
+
+                self.setup_sum(ctx, xor_table, &[&v[*c], &v[*d]], &g.sums[1]);
+                v[*c] = Self::exprs(&g.sums[1].nibbles);
+                Self::setup_xor(ctx, xor_table, &v[*b], &v[*c], &g.xors[1]);
+                v[*b] = self.rotate(&g.xors[1], rotations[1]);
+
+                self.setup_sum(ctx, xor_table, &[&v[*a], &v[*b], y], &g.sums[2]);
+                v[*a] = Self::exprs(&g.sums[2].nibbles);
+                Self::setup_xor(ctx, xor_table, &v[*d], &v[*a], &g.xors[2]);
+                v[*d] = self.rotate(&g.xors[2], rotations[2]);
+
+                self.setup_sum(ctx, xor_table, &[&v[*c], &v[*d]], &g.sums[3]);
+                v[*c] = Self::exprs(&g.sums[3].nibbles);
+                Self::setup_xor(ctx, xor_table, &v[*b], &v[*c], &g.xors[3]);
+
+                // the last rotation is a multiple of 4 bits followed by 3 bits, so every output
+                // nibble takes the top bit of a nibble and the low 3 bits of the next one
+                for ((xor, high), low) in g.xors[3].iter().zip(g.high.iter()).zip(g.low.iter()) {
+                    ctx.constr(eq(*xor, *high * 8u64 + *low));
+                    ctx.constr(eq(*high * (1u64.expr() - *high), 0u64));
+                    Self::setup_low_bits(ctx, xor_table, *low);
+                }
+                let n = self.variant.nibbles();
+                let offset = rotations[3] / 4;
+                v[*b] = (0..n)
+                    .map(|i| g.high[(i + offset) % n].expr() + g.low[(i + offset + 1) % n] * 2u64)
+                    .collect();
+            }
+        }
+
+        for i in 0..8 {
+            Self::setup_xor(
+                ctx,
+                xor_table,
+                &Self::exprs(&self.h[i]),
+                &v[i],
+                &self.partial[i],
+            );
+            Self::setup_xor(
+                ctx,
+                xor_table,
+                &Self::exprs(&self.partial[i]),
+                &v[i + 8],
+                &self.output[i],
+            );
+        }
+    }
+
+    /// Returns the words of the output state.
+    pub fn output(&self) -> Vec<PIR<F>> {
+        self.output
+            .iter()
+            .map(|nibbles| self.word(&Self::exprs(nibbles)))
+            .collect()
+    }
+}
+
+impl<F: Field + From<u64> + Hash> Blake2Compression<F> {
+    /// Assigns the signals of the compression and returns the output state.
+    pub fn wg(
+        &self,
+        ctx: &mut StepInstance<F>,
+        h: &[u64; 8],
+        m: &[u64; 16],
+        t: u128,
+        f: bool,
+    ) -> [u64; 8] {
+        let values = compress(self.variant, self.rounds, h, m, t, f);
+        let iv = self.variant.iv();
+
+        let mut assign = |signals: &[Queriable<F>], value: u64| {
+            for (i, signal) in signals.iter().enumerate() {
+                ctx.assign(*signal, F::from((value >> (4 * i)) & 15));
+            }
+        };
+
+        for (signals, value) in self.h.iter().zip(h.iter()) {
+            assign(signals, *value);
+        }
+        for (signals, value) in self.m.iter().zip(m.iter()) {
+            assign(signals, *value);
+        }
+        for i in 0..2 {
+            assign(&self.t[i], values.t[i]);
+            assign(&self.t_xor[i], iv[4 + i] ^ values.t[i]);
+        }
+        for (signals, value) in self.gs.iter().zip(values.gs.iter()) {
+            for (sum, (result, _)) in signals.sums.iter().zip(value.sums.iter()) {
+                assign(&sum.nibbles, *result);
+            }
+            for (xor, result) in signals.xors.iter().zip(value.xors.iter()) {
+                assign(xor, *result);
+            }
+        }
+        for i in 0..8 {
+            assign(&self.partial[i], values.partial[i]);
+            assign(&self.output[i], values.output[i]);
+        }
+
+        for (signals, value) in self.gs.iter().zip(values.gs.iter()) {
+            for (sum, (_, carry)) in signals.sums.iter().zip(value.sums.iter()) {
+                ctx.assign(sum.carry, F::from(*carry));
+            }
+            for (i, (high, low)) in signals.high.iter().zip(signals.low.iter()).enumerate() {
+                let nibble = (value.xors[3] >> (4 * i)) & 15;
+                ctx.assign(*high, F::from(nibble >> 3));
+                ctx.assign(*low, F::from(nibble & 7));
+            }
+        }
+
+        values.output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{frontend::dsl::circuit, sbpir::SBPIR, wit_gen::TraceGenerator};
+
+    fn abc_block() -> [u64; 16] {
+        let mut m = [0u64; 16];
+        m[0] = 0x636261;
+
+        m
+    }
+
+    #[test]
+    fn test_blake2b_compress() {
+        let mut h = Blake2Variant::Blake2b.iv();
+        h[0] ^= 0x01010040;
+
+        let output = blake2_compress(Blake2Variant::Blake2b, 12, &h, &abc_block(), 3, true);
+
+        assert_eq!(output[0], 0x0d4d1c983fa580ba);
+        assert_eq!(output[7], 0x239900d4ed8623b9);
+    }
+
+    #[test]
+    fn test_blake2s_compress() {
+        let mut h = Blake2Variant::Blake2s.iv();
+        h[0] ^= 0x01010020;
+
+        let output = blake2_compress(Blake2Variant::Blake2s, 10, &h, &abc_block(), 3, true);
+
+        assert_eq!(output[0], 0x8c5e8c50);
+        assert_eq!(output[7], 0x82596786);
+    }
+
+    #[test]
+    fn test_blake2s_compression_gadget() {
+        let mut h = Blake2Variant::Blake2s.iv();
+        h[0] ^= 0x01010020;
+        let m = abc_block();
+
+        let circuit: SBPIR<Fr, ()> = circuit("blake2s", |ctx| {
+            let xor_table = blake2_xor_table(ctx);
+
+            let compress = ctx.step_type_def("compress", |ctx| {
+                let blake2 = Blake2Compression::new(ctx, "blake2s", Blake2Variant::Blake2s, 10);
+                let setup_blake2 = blake2.clone();
+
+                ctx.setup(move |ctx| {
+                    setup_blake2.setup(ctx, &h, &m, &[3, 0], 1, xor_table);
+                });
+
+                ctx.wg(move |ctx, _: ()| {
+                    blake2.wg(ctx, &h, &m, 3, true);
+                })
+            });
+
+            ctx.trace(move |ctx, _| ctx.add(&compress, ()));
+        });
+
+        let step_type = circuit.step_types.values().next().unwrap();
+        let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), 1).generate(());
+        let assignments = &trace.step_instances[0].assignments;
+
+        for constraint in step_type.constraints.iter() {
+            assert_eq!(
+                constraint.expr.eval(assignments),
+                Some(Fr::ZERO),
+                "{}",
+                constraint.annotation
+            );
+        }
+
+        let nibble = |expr: &PIR<Fr>| -> u64 {
+            let value = expr.eval(assignments).unwrap();
+            (0..16u64)
+                .find(|n| Fr::from(*n) == value)
+                .unwrap_or_else(|| panic!("{:?} is not a nibble", expr))
+        };
+        for lookup in step_type.lookups.iter() {
+            let src: Vec<u64> = lookup
+                .exprs
+                .iter()
+                .map(|(src, _)| nibble(&src.expr))
+                .collect();
+            match src.as_slice() {
+                [a, b, c] => assert_eq!(a ^ b, *c),
+                _ => unreachable!(),
+            }
+        }
+    }
+}
@@ -0,0 +1,1112 @@
+use std::{hash::Hash, rc::Rc};
+
+use num_bigint::BigUint;
+
+use crate::{
+    field::Field,
+    frontend::dsl::{
+        cb::{eq, Constraint},
+        lb::LookupTable,
+        StepTypeContext, StepTypeSetupContext,
+    },
+    poly::{Expr, ToExpr, ToField},
+    sbpir::{query::Queriable, PIR},
+    wit_gen::StepInstance,
+};
+
+use super::{
+    nonnative::{
+        add_columns, constant_columns, inverse_mod, mul_columns, product_bound, scale_columns,
+        sqrt_mod, sub_columns, sub_mod, value_columns, Column, NonNativeElement, Reduction,
+    },
+    sha256::sha256,
+};
+
+/// Affine point, the coordinates being integers in `[0, p)`.
+pub type Point = (BigUint, BigUint);
+
+fn hex(value: &str) -> BigUint {
+    BigUint::parse_bytes(value.as_bytes(), 16).expect("hex constant")
+}
+
+fn dec(value: &str) -> BigUint {
+    BigUint::parse_bytes(value.as_bytes(), 10).expect("decimal constant")
+}
+
+/// Short Weierstrass curve `y^2 = x^3 + a * x + b` over the prime field of the given modulus, with
+/// a prime order group.
+#[derive(Clone, Debug)]
+pub struct CurveParams {
+    pub modulus: BigUint,
+    pub order: BigUint,
+    pub a: BigUint,
+    pub b: BigUint,
+    pub generator: Point,
+    /// Point the scalar multiplications start from, so that the accumulator is never the point at
+    /// infinity and incomplete addition formulas can be used. Nobody must know its discrete
+    /// logarithm.
+    pub offset: Point,
+}
+
+impl CurveParams {
+    pub fn secp256k1() -> CurveParams {
+        CurveParams {
+            modulus: hex("fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f"),
+            order: hex("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141"),
+            a: BigUint::from(0u64),
+            b: BigUint::from(7u64),
+            generator: (
+                hex("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"),
+                hex("483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8"),
+            ),
+            // first valid x coordinate from the SHA-256 hash of "chiquito ecdsa offset"
+            offset: (
+                hex("beb930976af16d7c0ab3f2e1606afa0dde8f555f83538a1bc800f49f75face46"),
+                hex("6c215c4f68f508a11063f106797cd88a99285e1f3e5f9bde8b3db2f00becff12"),
+            ),
+        }
+    }
+
+    /// Grumpkin, whose base field is the scalar field of BN254, so its arithmetic is native in
+    /// circuits over `bn256::Fr`.
+    pub fn grumpkin() -> CurveParams {
+        let modulus =
+            dec("21888242871839275222246405745257275088548364400416034343698204186575808495617");
+        let b = &modulus - 17u64;
+
+        CurveParams {
+            order: dec(
+                "21888242871839275222246405745257275088696311157297823662689037894645226208583",
+            ),
+            a: BigUint::from(0u64),
+            b,
+            generator: (
+                BigUint::from(1u64),
+                dec("17631683881184975370165255887551781615748388533673675138860"),
+            ),
+            // first valid x coordinate from the SHA-256 hash of "chiquito ecc offset"
+            offset: (
+                hex("287af1ba1a27b92a1c48ae94f7c507a3efd8d72d7fe8e10993da4e4976fbe01e"),
+                hex("21a81960860150449b9cfc889ba439c7daf74582fdf7ef39a36b5f80d34cf02d"),
+            ),
+            modulus,
+        }
+    }
+
+    pub fn is_on_curve(&self, (x, y): &Point) -> bool {
+        let p = &self.modulus;
+
+        x < p && y < p && (y * y) % p == (x * x * x + &self.a * x + &self.b) % p
+    }
+
+    pub fn neg(&self, (x, y): &Point) -> Point {
+        (x.clone(), sub_mod(&BigUint::from(0u64), y, &self.modulus))
+    }
+
+    /// Adds two points with different x coordinates, returning the slope and the sum.
+    pub fn add_incomplete(&self, a: &Point, b: &Point) -> (BigUint, Point) {
+        let p = &self.modulus;
+        let lambda = sub_mod(&b.1, &a.1, p) * inverse_mod(&sub_mod(&b.0, &a.0, p), p) % p;
+
+        (lambda.clone(), self.lambda_point(lambda, a, &b.0))
+    }
+
+    /// Doubles a point with a non-zero y coordinate, returning the slope and the result.
+    pub fn double(&self, a: &Point) -> (BigUint, Point) {
+        let p = &self.modulus;
+        let lambda =
+            (BigUint::from(3u64) * &a.0 * &a.0 + &self.a) % p * inverse_mod(&(&a.1 * 2u64), p) % p;
+
+        (lambda.clone(), self.lambda_point(lambda, a, &a.0))
+    }
+
+    fn lambda_point(&self, lambda: BigUint, a: &Point, x2: &BigUint) -> Point {
+        let p = &self.modulus;
+        let x = sub_mod(&(&lambda * &lambda), &(&a.0 + x2), p);
+        let y = sub_mod(&(&lambda * sub_mod(&a.0, &x, p)), &a.1, p);
+
+        (x, y)
+    }
+
+    /// Adds two points with complete formulas, `None` being the point at infinity.
+    pub fn add(&self, a: Option<Point>, b: Option<Point>) -> Option<Point> {
+        match (a, b) {
+            (None, b) => b,
+            (a, None) => a,
+            (Some(a), Some(b)) if a.0 == b.0 => {
+                if a.1 == b.1 && a.1 != BigUint::from(0u64) {
+                    Some(self.double(&a).1)
+                } else {
+                    None
+                }
+            }
+            (Some(a), Some(b)) => Some(self.add_incomplete(&a, &b).1),
+        }
+    }
+
+    /// Multiplies a point by a scalar, `None` being the point at infinity.
+    pub fn mul(&self, scalar: &BigUint, point: &Point) -> Option<Point> {
+        (0..scalar.bits()).rev().fold(None, |acc, bit| {
+            let acc = self.add(acc.clone(), acc);
+            if scalar.bit(bit) {
+                self.add(acc, Some(point.clone()))
+            } else {
+                acc
+            }
+        })
+    }
+
+    /// Hashes a domain separator to a point whose discrete logarithm nobody knows, taking the first
+    /// valid x coordinate from the SHA-256 hash of the domain followed by a 32-bit big-endian
+    /// counter, and the even y coordinate.
+    pub fn hash_to_point(&self, domain: &[u8]) -> Point {
+        let p = &self.modulus;
+
+        for counter in 0u32.. {
+            let digest = sha256(&[domain, &counter.to_be_bytes()].concat());
+            let x = BigUint::from_bytes_be(&digest) % p;
+            if let Some(y) = sqrt_mod(&((&x * &x * &x + &self.a * &x + &self.b) % p), p) {
+                let y = if y.bit(0) { p - y } else { y };
+                return (x, y);
+            }
+        }
+
+        unreachable!("half of the x coordinates are on the curve")
+    }
+
+    /// Returns `-2^bits` times the offset point, which removes the offset from the result of a
+    /// scalar multiplication of `bits` bits.
+    pub fn offset_correction(&self, bits: usize) -> Point {
+        let scalar = (BigUint::from(1u64) << bits) % &self.order;
+        let point = self
+            .mul(&scalar, &self.offset)
+            .expect("offset point has prime order");
+
+        self.neg(&point)
+    }
+}
+
+/// Converts an integer smaller than the field modulus to a field element.
+pub(crate) fn field_value<F: Field + From<u64>>(value: &BigUint) -> F {
+    value.field()
+}
+
+pub(crate) fn field_point<F: Field + From<u64>>((x, y): &Point) -> (F, F) {
+    (field_value(x), field_value(y))
+}
+
+pub(crate) fn constant_point<F: Field + From<u64>>(point: &Point) -> (PIR<F>, PIR<F>) {
+    let (x, y) = field_point(point);
+
+    (Expr::Const(x), Expr::Const(y))
+}
+
+/// In-step addition of two points of a curve whose base field is the circuit field, with
+/// incomplete formulas. The x coordinates are constrained to be different, so neither the points
+/// can be equal or opposite nor the result can be the point at infinity.
+#[derive(Clone)]
+pub struct NativePointAdd<F> {
+    lambda: Queriable<F>,
+    inverse: Queriable<F>,
+    x: Queriable<F>,
+    y: Queriable<F>,
+}
+
+impl<F: Field + From<u64>> NativePointAdd<F> {
+    pub fn new(ctx: &mut StepTypeContext<F>, name: &str) -> NativePointAdd<F> {
+        NativePointAdd {
+            lambda: ctx.internal(format!("{}.lambda", name).as_str()),
+            inverse: ctx.internal(format!("{}.inverse", name).as_str()),
+            x: ctx.internal(format!("{}.x", name).as_str()),
+            y: ctx.internal(format!("{}.y", name).as_str()),
+        }
+    }
+
+    /// Constrains `output` to be the sum of `(x1, y1)` and `(x2, y2)`.
+    #[track_caller]
+    pub fn setup<V: Into<Constraint<F>>>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        (x1, y1): (V, V),
+        (x2, y2): (V, V),
+    ) {
+        let (x1, y1): (PIR<F>, PIR<F>) = (x1.into().expr, y1.into().expr);
+        let (x2, y2): (PIR<F>, PIR<F>) = (x2.into().expr, y2.into().expr);
+
+        ctx.constr(eq(self.inverse * (x2.clone() - x1.clone()), 1u64));
+        ctx.constr(eq(self.lambda * (x2.clone() - x1.clone()), y2 - y1.clone()));
+        constrain_lambda_point(ctx, self.lambda, (x1, y1), x2, (self.x, self.y));
+    }
+
+    pub fn output(&self) -> (Queriable<F>, Queriable<F>) {
+        (self.x, self.y)
+    }
+}
+
+impl<F: Field + From<u64> + Hash> NativePointAdd<F> {
+    pub fn wg(&self, ctx: &mut StepInstance<F>, a: (F, F), b: (F, F)) -> (F, F) {
+        let inverse = (b.0 - a.0).mi();
+        let lambda = (b.1 - a.1) * inverse;
+        ctx.assign(self.inverse, inverse);
+
+        assign_lambda_point(ctx, self.lambda, lambda, a, b.0, (self.x, self.y))
+    }
+}
+
+/// In-step doubling of a point of a curve whose base field is the circuit field. The curve must not
+/// have points of order two, so the slope is defined for every point on it.
+#[derive(Clone)]
+pub struct NativePointDouble<F> {
+    a: F,
+    lambda: Queriable<F>,
+    x: Queriable<F>,
+    y: Queriable<F>,
+}
+
+impl<F: Field + From<u64>> NativePointDouble<F> {
+    pub fn new(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        curve: &CurveParams,
+    ) -> NativePointDouble<F> {
+        NativePointDouble {
+            a: field_value(&curve.a),
+            lambda: ctx.internal(format!("{}.lambda", name).as_str()),
+            x: ctx.internal(format!("{}.x", name).as_str()),
+            y: ctx.internal(format!("{}.y", name).as_str()),
+        }
+    }
+
+    /// Constrains `output` to be two times `(x, y)`.
+    #[track_caller]
+    pub fn setup<V: Into<Constraint<F>>>(&self, ctx: &mut StepTypeSetupContext<F>, (x, y): (V, V)) {
+        let (x, y): (PIR<F>, PIR<F>) = (x.into().expr, y.into().expr);
+
+        ctx.constr(eq(
+            self.lambda * y.clone() * 2u64,
+            x.clone() * x.clone() * 3u64 + Expr::Const(self.a),
+        ));
+        constrain_lambda_point(ctx, self.lambda, (x.clone(), y), x, (self.x, self.y));
+    }
+
+    pub fn output(&self) -> (Queriable<F>, Queriable<F>) {
+        (self.x, self.y)
+    }
+}
+
+impl<F: Field + From<u64> + Hash> NativePointDouble<F> {
+    pub fn wg(&self, ctx: &mut StepInstance<F>, a: (F, F)) -> (F, F) {
+        let lambda = (a.0 * a.0 * F::from(3) + self.a) * (a.1 * F::from(2)).mi();
+
+        assign_lambda_point(ctx, self.lambda, lambda, a, a.0, (self.x, self.y))
+    }
+}
+
+#[track_caller]
+fn constrain_lambda_point<F: Field + From<u64>>(
+    ctx: &mut StepTypeSetupContext<F>,
+    lambda: Queriable<F>,
+    (x1, y1): (PIR<F>, PIR<F>),
+    x2: PIR<F>,
+    (x, y): (Queriable<F>, Queriable<F>),
+) {
+    ctx.constr(eq(x, lambda * lambda - x1.clone() - x2));
+    ctx.constr(eq(y, lambda * (x1 - x) - y1));
+}
+
+fn assign_lambda_point<F: Field + From<u64> + Hash>(
+    ctx: &mut StepInstance<F>,
+    lambda_signal: Queriable<F>,
+    lambda: F,
+    (x1, y1): (F, F),
+    x2: F,
+    (x_signal, y_signal): (Queriable<F>, Queriable<F>),
+) -> (F, F) {
+    let x = lambda * lambda - x1 - x2;
+    let y = lambda * (x1 - x) - y1;
+    ctx.assign(lambda_signal, lambda);
+    ctx.assign(x_signal, x);
+    ctx.assign(y_signal, y);
+
+    (x, y)
+}
+
+/// In-step multiplication of a point by a scalar of `num_bits` bits, for a curve whose base field
+/// is the circuit field. The bits are processed from the most significant one with a doubling and
+/// an addition each, starting from the offset point of the curve and removing it at the end. It
+/// adds ten signals per bit. The scalar must not be zero modulo the group order and `num_bits` must
+/// be smaller than the size of the field, so that the bits are unique.
+#[derive(Clone)]
+pub struct NativeScalarMul<F> {
+    offset: Point,
+    correction_point: Point,
+    bits: Vec<Queriable<F>>,
+    doubles: Vec<NativePointDouble<F>>,
+    adds: Vec<NativePointAdd<F>>,
+    accs: Vec<(Queriable<F>, Queriable<F>)>,
+    correction: NativePointAdd<F>,
+}
+
+impl<F: Field + From<u64>> NativeScalarMul<F> {
+    pub fn new(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        curve: &CurveParams,
+        num_bits: usize,
+    ) -> NativeScalarMul<F> {
+        assert!(num_bits > 0, "scalar multiplication needs at least one bit");
+
+        let bits = (0..num_bits)
+            .map(|i| ctx.internal(format!("{}.bits[{}]", name, i).as_str()))
+            .collect();
+        let doubles = (0..num_bits)
+            .map(|i| NativePointDouble::new(ctx, format!("{}.double[{}]", name, i).as_str(), curve))
+            .collect();
+        let adds = (0..num_bits)
+            .map(|i| NativePointAdd::new(ctx, format!("{}.add[{}]", name, i).as_str()))
+            .collect();
+        let accs = (0..num_bits)
+            .map(|i| {
+                (
+                    ctx.internal(format!("{}.acc[{}].x", name, i).as_str()),
+                    ctx.internal(format!("{}.acc[{}].y", name, i).as_str()),
+                )
+            })
+            .collect();
+
+        NativeScalarMul {
+            offset: curve.offset.clone(),
+            correction_point: curve.offset_correction(num_bits),
+            bits,
+            doubles,
+            adds,
+            accs,
+            correction: NativePointAdd::new(ctx, format!("{}.correction", name).as_str()),
+        }
+    }
+
+    /// Constrains `output` to be `scalar` times `(x, y)`.
+    #[track_caller]
+    pub fn setup<V: Into<Constraint<F>>>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        scalar: V,
+        (x, y): (V, V),
+    ) {
+        let point: (PIR<F>, PIR<F>) = (x.into().expr, y.into().expr);
+
+        let mut coeff = F::ONE;
+        let mut recomposed: PIR<F> = 0u64.expr();
+        for bit in self.bits.iter() {
+            ctx.constr(eq(*bit * (1u64.expr() - *bit), 0u64));
+            recomposed = recomposed + Expr::Const(coeff) * *bit;
+            coeff = coeff + coeff;
+        }
+        ctx.constr(eq(scalar, recomposed));
+
+        let mut acc = constant_point(&self.offset);
+        for (((bit, double), add), next) in self
+            .bits
+            .iter()
+            .rev()
+            .zip(self.doubles.iter())
+            .zip(self.adds.iter())
+            .zip(self.accs.iter())
+        {
+            double.setup(ctx, acc);
+            let doubled = double.output();
+            add.setup(ctx, (doubled.0.expr(), doubled.1.expr()), point.clone());
+            let added = add.output();
+
+            ctx.constr(eq(next.0, doubled.0 + *bit * (added.0 - doubled.0)));
+            ctx.constr(eq(next.1, doubled.1 + *bit * (added.1 - doubled.1)));
+            acc = (next.0.expr(), next.1.expr());
+        }
+
+        self.correction
+            .setup(ctx, acc, constant_point(&self.correction_point));
+    }
+
+    pub fn output(&self) -> (Queriable<F>, Queriable<F>) {
+        self.correction.output()
+    }
+}
+
+impl<F: Field + From<u64> + Hash> NativeScalarMul<F> {
+    /// Assigns the signals for the multiplication of `point` by `scalar` and returns the result.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, scalar: &BigUint, point: (F, F)) -> (F, F) {
+        assert!(
+            scalar.bits() <= self.bits.len() as u64,
+            "scalar does not fit in {} bits",
+            self.bits.len()
+        );
+
+        for (i, bit) in self.bits.iter().enumerate() {
+            ctx.assign(*bit, F::from(scalar.bit(i as u64) as u64));
+        }
+
+        let mut acc = field_point(&self.offset);
+        for (i, ((double, add), next)) in self
+            .doubles
+            .iter()
+            .zip(self.adds.iter())
+            .zip(self.accs.iter())
+            .enumerate()
+        {
+            let doubled = double.wg(ctx, acc);
+            let added = add.wg(ctx, doubled, point);
+            acc = if scalar.bit((self.bits.len() - 1 - i) as u64) {
+                added
+            } else {
+                doubled
+            };
+            ctx.assign(next.0, acc.0);
+            ctx.assign(next.1, acc.1);
+        }
+
+        self.correction
+            .wg(ctx, acc, field_point(&self.correction_point))
+    }
+}
+
+fn add_lambda_columns<T: Column>(lambda: &[T], x1: &[T], y1: &[T], x2: &[T], y2: &[T]) -> Vec<T> {
+    sub_columns(
+        &mul_columns(lambda, &sub_columns(x2, x1)),
+        &sub_columns(y2, y1),
+    )
+}
+
+fn double_lambda_columns<T: Column>(lambda: &[T], x: &[T], y: &[T], a: &[T]) -> Vec<T> {
+    sub_columns(
+        &mul_columns(lambda, &scale_columns(y, 2)),
+        &add_columns(&scale_columns(&mul_columns(x, x), 3), a),
+    )
+}
+
+fn lambda_x_columns<T: Column>(lambda: &[T], x1: &[T], x2: &[T]) -> Vec<T> {
+    sub_columns(&mul_columns(lambda, lambda), &add_columns(x1, x2))
+}
+
+fn lambda_y_columns<T: Column>(lambda: &[T], x1: &[T], y1: &[T], x3: &[T]) -> Vec<T> {
+    sub_columns(&mul_columns(lambda, &sub_columns(x1, x3)), y1)
+}
+
+/// Returns the columns of `value * inverse - 1`, which reduce to zero when `inverse` is the inverse
+/// of `value`.
+pub(crate) fn inverse_columns<T: Column>(value: &[T], inverse: &[T]) -> Vec<T> {
+    sub_columns(&mul_columns(value, inverse), &[T::from(1)])
+}
+
+/// Returns the columns of `y^2 - x^3 - a * x - b`, with `x_squared` the reduced `x^2`.
+fn on_curve_columns<T: Column>(x: &[T], y: &[T], x_squared: &[T], a: &[T], b: &[T]) -> Vec<T> {
+    sub_columns(
+        &mul_columns(y, y),
+        &add_columns(
+            &add_columns(&mul_columns(x_squared, x), &mul_columns(a, x)),
+            b,
+        ),
+    )
+}
+
+/// In-step addition of two points of a curve over a non-native field, with incomplete formulas.
+/// The coordinates are given as limbs of reduced elements, see `NonNativeElement`, and the x
+/// coordinates are constrained to be different.
+#[derive(Clone)]
+pub struct NonNativePointAdd<F> {
+    curve: Rc<CurveParams>,
+    lambda: NonNativeElement<F>,
+    lambda_check: Reduction<F>,
+    x: Reduction<F>,
+    y: Reduction<F>,
+    inverse: NonNativeElement<F>,
+    inverse_check: Reduction<F>,
+}
+
+impl<F: Field + From<u64>> NonNativePointAdd<F> {
+    pub fn new(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        curve: Rc<CurveParams>,
+    ) -> NonNativePointAdd<F> {
+        let p = &curve.modulus;
+        let bound = product_bound();
+
+        NonNativePointAdd {
+            lambda: NonNativeElement::new(ctx, format!("{}.lambda", name).as_str()),
+            lambda_check: Reduction::new_zero(
+                ctx,
+                format!("{}.lambda_check", name).as_str(),
+                p,
+                &bound,
+            ),
+            x: Reduction::new(ctx, format!("{}.x", name).as_str(), p, &bound),
+            y: Reduction::new(ctx, format!("{}.y", name).as_str(), p, &bound),
+            inverse: NonNativeElement::new(ctx, format!("{}.inverse", name).as_str()),
+            inverse_check: Reduction::new_zero(
+                ctx,
+                format!("{}.inverse_check", name).as_str(),
+                p,
+                &bound,
+            ),
+            curve,
+        }
+    }
+
+    /// Constrains `output` to be the sum of `(x1, y1)` and `(x2, y2)`. The elements are range
+    /// checked in `byte_table`, which must contain the values in `[0, 256)`.
+    #[track_caller]
+    pub fn setup(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        (x1, y1): (&[PIR<F>], &[PIR<F>]),
+        (x2, y2): (&[PIR<F>], &[PIR<F>]),
+        byte_table: LookupTable,
+    ) {
+        let lambda = self.lambda.limbs();
+        self.lambda.setup(ctx, byte_table);
+        self.inverse.setup(ctx, byte_table);
+
+        self.inverse_check.setup(
+            ctx,
+            inverse_columns(&sub_columns(x2, x1), &self.inverse.limbs()),
+            byte_table,
+        );
+        self.lambda_check
+            .setup(ctx, add_lambda_columns(&lambda, x1, y1, x2, y2), byte_table);
+        self.x
+            .setup(ctx, lambda_x_columns(&lambda, x1, x2), byte_table);
+        self.y.setup(
+            ctx,
+            lambda_y_columns(&lambda, x1, y1, &self.x.result().limbs()),
+            byte_table,
+        );
+    }
+
+    /// Returns the limbs of the coordinates of the sum.
+    pub fn output(&self) -> (Vec<PIR<F>>, Vec<PIR<F>>) {
+        (self.x.result().limbs(), self.y.result().limbs())
+    }
+}
+
+impl<F: Field + From<u64> + Hash> NonNativePointAdd<F> {
+    pub fn wg(&self, ctx: &mut StepInstance<F>, a: &Point, b: &Point) -> Point {
+        let p = &self.curve.modulus;
+        let (lambda, _) = self.curve.add_incomplete(a, b);
+        let inverse = inverse_mod(&sub_mod(&b.0, &a.0, p), p);
+        self.lambda.wg(ctx, &lambda);
+        self.inverse.wg(ctx, &inverse);
+
+        let (x1, y1) = (value_columns(&a.0), value_columns(&a.1));
+        let (x2, y2) = (value_columns(&b.0), value_columns(&b.1));
+        let lambda = value_columns(&lambda);
+
+        self.inverse_check.wg(
+            ctx,
+            &inverse_columns(&sub_columns(&x2, &x1), &value_columns(&inverse)),
+        );
+        self.lambda_check
+            .wg(ctx, &add_lambda_columns(&lambda, &x1, &y1, &x2, &y2));
+        let x = self.x.wg(ctx, &lambda_x_columns(&lambda, &x1, &x2));
+        let y = self.y.wg(
+            ctx,
+            &lambda_y_columns(&lambda, &x1, &y1, &value_columns(&x)),
+        );
+
+        (x, y)
+    }
+}
+
+/// In-step doubling of a point of a curve over a non-native field. The curve must not have points
+/// of order two, so the slope is defined for every point on it.
+#[derive(Clone)]
+pub struct NonNativePointDouble<F> {
+    curve: Rc<CurveParams>,
+    lambda: NonNativeElement<F>,
+    lambda_check: Reduction<F>,
+    x: Reduction<F>,
+    y: Reduction<F>,
+}
+
+impl<F: Field + From<u64>> NonNativePointDouble<F> {
+    pub fn new(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        curve: Rc<CurveParams>,
+    ) -> NonNativePointDouble<F> {
+        let p = &curve.modulus;
+        let bound = product_bound();
+
+        NonNativePointDouble {
+            lambda: NonNativeElement::new(ctx, format!("{}.lambda", name).as_str()),
+            lambda_check: Reduction::new_zero(
+                ctx,
+                format!("{}.lambda_check", name).as_str(),
+                p,
+                &bound,
+            ),
+            x: Reduction::new(ctx, format!("{}.x", name).as_str(), p, &bound),
+            y: Reduction::new(ctx, format!("{}.y", name).as_str(), p, &bound),
+            curve,
+        }
+    }
+
+    /// Constrains `output` to be two times `(x, y)`.
+    #[track_caller]
+    pub fn setup(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        (x, y): (&[PIR<F>], &[PIR<F>]),
+        byte_table: LookupTable,
+    ) {
+        let lambda = self.lambda.limbs();
+        self.lambda.setup(ctx, byte_table);
+
+        self.lambda_check.setup(
+            ctx,
+            double_lambda_columns(&lambda, x, y, &constant_columns(&self.curve.a)),
+            byte_table,
+        );
+        self.x
+            .setup(ctx, lambda_x_columns(&lambda, x, x), byte_table);
+        self.y.setup(
+            ctx,
+            lambda_y_columns(&lambda, x, y, &self.x.result().limbs()),
+            byte_table,
+        );
+    }
+
+    /// Returns the limbs of the coordinates of the result.
+    pub fn output(&self) -> (Vec<PIR<F>>, Vec<PIR<F>>) {
+        (self.x.result().limbs(), self.y.result().limbs())
+    }
+}
+
+impl<F: Field + From<u64> + Hash> NonNativePointDouble<F> {
+    pub fn wg(&self, ctx: &mut StepInstance<F>, a: &Point) -> Point {
+        let (lambda, _) = self.curve.double(a);
+        self.lambda.wg(ctx, &lambda);
+
+        let (x1, y1) = (value_columns(&a.0), value_columns(&a.1));
+        let lambda = value_columns(&lambda);
+
+        self.lambda_check.wg(
+            ctx,
+            &double_lambda_columns(&lambda, &x1, &y1, &value_columns(&self.curve.a)),
+        );
+        let x = self.x.wg(ctx, &lambda_x_columns(&lambda, &x1, &x1));
+        let y = self.y.wg(
+            ctx,
+            &lambda_y_columns(&lambda, &x1, &y1, &value_columns(&x)),
+        );
+
+        (x, y)
+    }
+}
+
+/// In-step check that a point of reduced non-native coordinates is on the curve.
+#[derive(Clone)]
+pub struct NonNativeOnCurve<F> {
+    curve: Rc<CurveParams>,
+    x_squared: Reduction<F>,
+    check: Reduction<F>,
+}
+
+impl<F: Field + From<u64>> NonNativeOnCurve<F> {
+    pub fn new(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        curve: Rc<CurveParams>,
+    ) -> NonNativeOnCurve<F> {
+        let p = &curve.modulus;
+        let bound = product_bound();
+
+        NonNativeOnCurve {
+            x_squared: Reduction::new(ctx, format!("{}.x_squared", name).as_str(), p, &bound),
+            check: Reduction::new_zero(ctx, format!("{}.check", name).as_str(), p, &bound),
+            curve,
+        }
+    }
+
+    #[track_caller]
+    pub fn setup(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        (x, y): (&[PIR<F>], &[PIR<F>]),
+        byte_table: LookupTable,
+    ) {
+        self.x_squared.setup(ctx, mul_columns(x, x), byte_table);
+        self.check.setup(
+            ctx,
+            on_curve_columns(
+                x,
+                y,
+                &self.x_squared.result().limbs(),
+                &constant_columns(&self.curve.a),
+                &constant_columns(&self.curve.b),
+            ),
+            byte_table,
+        );
+    }
+}
+
+impl<F: Field + From<u64> + Hash> NonNativeOnCurve<F> {
+    pub fn wg(&self, ctx: &mut StepInstance<F>, (x, y): &Point) {
+        let (x, y) = (value_columns(x), value_columns(y));
+        let x_squared = self.x_squared.wg(ctx, &mul_columns(&x, &x));
+
+        self.check.wg(
+            ctx,
+            &on_curve_columns(
+                &x,
+                &y,
+                &value_columns(&x_squared),
+                &value_columns(&self.curve.a),
+                &value_columns(&self.curve.b),
+            ),
+        );
+    }
+}
+
+/// One step of a non-native double-and-add: doubles the accumulator and adds a point to it, the
+/// sum being kept only when the `enable` expression given to `output` is one. The added point
+/// must never have the x coordinate of the doubled accumulator, even when the sum is discarded.
+#[derive(Clone)]
+pub struct NonNativeDoubleAndAdd<F> {
+    double: NonNativePointDouble<F>,
+    add: NonNativePointAdd<F>,
+}
+
+impl<F: Field + From<u64>> NonNativeDoubleAndAdd<F> {
+    pub fn new(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        curve: Rc<CurveParams>,
+    ) -> NonNativeDoubleAndAdd<F> {
+        NonNativeDoubleAndAdd {
+            double: NonNativePointDouble::new(
+                ctx,
+                format!("{}.double", name).as_str(),
+                curve.clone(),
+            ),
+            add: NonNativePointAdd::new(ctx, format!("{}.add", name).as_str(), curve),
+        }
+    }
+
+    #[track_caller]
+    pub fn setup(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        acc: (&[PIR<F>], &[PIR<F>]),
+        point: (&[PIR<F>], &[PIR<F>]),
+        byte_table: LookupTable,
+    ) {
+        self.double.setup(ctx, acc, byte_table);
+        let doubled = self.double.output();
+        self.add
+            .setup(ctx, (&doubled.0, &doubled.1), point, byte_table);
+    }
+
+    /// Returns the limbs of `2 * acc + enable * point`, `enable` being a boolean expression.
+    pub fn output(&self, enable: PIR<F>) -> (Vec<PIR<F>>, Vec<PIR<F>>) {
+        let doubled = self.double.output();
+        let added = self.add.output();
+        let select = |doubled: Vec<PIR<F>>, added: Vec<PIR<F>>| -> Vec<PIR<F>> {
+            doubled
+                .into_iter()
+                .zip(added)
+                .map(|(d, a)| d.clone() + enable.clone() * (a - d))
+                .collect()
+        };
+
+        (select(doubled.0, added.0), select(doubled.1, added.1))
+    }
+}
+
+impl<F: Field + From<u64> + Hash> NonNativeDoubleAndAdd<F> {
+    pub fn wg(&self, ctx: &mut StepInstance<F>, acc: &Point, point: &Point, enable: bool) -> Point {
+        let doubled = self.double.wg(ctx, acc);
+        let added = self.add.wg(ctx, &doubled, point);
+
+        if enable {
+            added
+        } else {
+            doubled
+        }
+    }
+}
+
+/// In-step multiplication of a point by a scalar of `num_bits` bits, for a curve over a non-native
+/// field, working like `NativeScalarMul`. Every bit adds more than a thousand signals, so full size
+/// scalars should be spread over several steps with `NonNativeDoubleAndAdd`, as
+/// `ecdsa_verify_circuit` does.
+#[derive(Clone)]
+pub struct NonNativeScalarMul<F> {
+    curve: Rc<CurveParams>,
+    bits: Vec<Queriable<F>>,
+    steps: Vec<NonNativeDoubleAndAdd<F>>,
+    correction: NonNativePointAdd<F>,
+}
+
+impl<F: Field + From<u64>> NonNativeScalarMul<F> {
+    pub fn new(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        curve: Rc<CurveParams>,
+        num_bits: usize,
+    ) -> NonNativeScalarMul<F> {
+        assert!(num_bits > 0, "scalar multiplication needs at least one bit");
+
+        let bits = (0..num_bits)
+            .map(|i| ctx.internal(format!("{}.bits[{}]", name, i).as_str()))
+            .collect();
+        let steps = (0..num_bits)
+            .map(|i| {
+                NonNativeDoubleAndAdd::new(
+                    ctx,
+                    format!("{}.step[{}]", name, i).as_str(),
+                    curve.clone(),
+                )
+            })
+            .collect();
+        let correction =
+            NonNativePointAdd::new(ctx, format!("{}.correction", name).as_str(), curve.clone());
+
+        NonNativeScalarMul {
+            curve,
+            bits,
+            steps,
+            correction,
+        }
+    }
+
+    /// Constrains `output` to be `scalar` times `(x, y)`, the scalar being a native expression.
+    #[track_caller]
+    pub fn setup<V: Into<Constraint<F>>>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        scalar: V,
+        point: (&[PIR<F>], &[PIR<F>]),
+        byte_table: LookupTable,
+    ) {
+        let mut coeff = F::ONE;
+        let mut recomposed: PIR<F> = 0u64.expr();
+        for bit in self.bits.iter() {
+            ctx.constr(eq(*bit * (1u64.expr() - *bit), 0u64));
+            recomposed = recomposed + Expr::Const(coeff) * *bit;
+            coeff = coeff + coeff;
+        }
+        ctx.constr(eq(scalar, recomposed));
+
+        let mut acc: (Vec<PIR<F>>, Vec<PIR<F>>) = (
+            constant_columns(&self.curve.offset.0),
+            constant_columns(&self.curve.offset.1),
+        );
+        for (bit, step) in self.bits.iter().rev().zip(self.steps.iter()) {
+            step.setup(ctx, (&acc.0, &acc.1), point, byte_table);
+            acc = step.output(bit.expr());
+        }
+
+        let correction = self.curve.offset_correction(self.bits.len());
+        self.correction.setup(
+            ctx,
+            (&acc.0, &acc.1),
+            (
+                &constant_columns(&correction.0),
+                &constant_columns(&correction.1),
+            ),
+            byte_table,
+        );
+    }
+
+    /// Returns the limbs of the coordinates of the result.
+    pub fn output(&self) -> (Vec<PIR<F>>, Vec<PIR<F>>) {
+        self.correction.output()
+    }
+}
+
+impl<F: Field + From<u64> + Hash> NonNativeScalarMul<F> {
+    pub fn wg(&self, ctx: &mut StepInstance<F>, scalar: &BigUint, point: &Point) -> Point {
+        assert!(
+            scalar.bits() <= self.bits.len() as u64,
+            "scalar does not fit in {} bits",
+            self.bits.len()
+        );
+
+        for (i, bit) in self.bits.iter().enumerate() {
+            ctx.assign(*bit, F::from(scalar.bit(i as u64) as u64));
+        }
+
+        let mut acc = self.curve.offset.clone();
+        for (i, step) in self.steps.iter().enumerate() {
+            let bit = scalar.bit((self.bits.len() - 1 - i) as u64);
+            acc = step.wg(ctx, &acc, point, bit);
+        }
+
+        self.correction
+            .wg(ctx, &acc, &self.curve.offset_correction(self.bits.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        frontend::dsl::circuit, sbpir::SBPIR, stdlib::range_table, wit_gen::TraceGenerator,
+    };
+
+    fn satisfied<TraceArgs>(circuit: &SBPIR<Fr, TraceArgs>, trace_args: TraceArgs) -> bool {
+        let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), 1).generate(trace_args);
+        let step_instance = &trace.step_instances[0];
+        let step_type = &circuit.step_types[&step_instance.step_type_uuid];
+
+        step_type
+            .constraints
+            .iter()
+            .all(|c| c.expr.eval(&step_instance.assignments) == Some(Fr::ZERO))
+    }
+
+    #[test]
+    fn test_curve_params() {
+        for curve in [CurveParams::secp256k1(), CurveParams::grumpkin()] {
+            assert!(curve.is_on_curve(&curve.generator));
+            assert!(curve.is_on_curve(&curve.offset));
+            assert_eq!(curve.mul(&curve.order, &curve.generator), None);
+
+            let three = curve.mul(&BigUint::from(3u64), &curve.generator);
+            let double = curve.double(&curve.generator).1;
+            assert_eq!(
+                three,
+                curve.add(Some(double), Some(curve.generator.clone()))
+            );
+            assert!(curve.is_on_curve(&three.unwrap()));
+
+            let hashed = curve.hash_to_point(b"chiquito test");
+            assert!(curve.is_on_curve(&hashed));
+            assert!(!hashed.1.bit(0));
+            assert_ne!(hashed, curve.hash_to_point(b"chiquito test 2"));
+        }
+    }
+
+    #[test]
+    fn test_native_scalar_mul() {
+        let curve = Rc::new(CurveParams::grumpkin());
+        let expected = field_point::<Fr>(
+            &curve
+                .mul(&BigUint::from(0xa5u64), &curve.generator)
+                .unwrap(),
+        );
+
+        let setup_curve = curve.clone();
+        let circuit: SBPIR<Fr, u64> = circuit("native scalar mul", |ctx| {
+            let scalar = ctx.forward("scalar");
+            let x = ctx.forward("x");
+            let y = ctx.forward("y");
+
+            let mul = ctx.step_type_def("mul", |ctx| {
+                let gadget = NativeScalarMul::new(ctx, "mul", &setup_curve, 8);
+                let result = (ctx.internal("result.x"), ctx.internal("result.y"));
+
+                let setup_gadget = gadget.clone();
+                ctx.setup(move |ctx| {
+                    setup_gadget.setup(ctx, scalar, (x, y));
+                    let (output_x, output_y) = setup_gadget.output();
+                    ctx.constr(eq(result.0, output_x));
+                    ctx.constr(eq(result.1, output_y));
+                });
+
+                let generator = field_point::<Fr>(&setup_curve.generator);
+                ctx.wg(move |ctx, value: u64| {
+                    ctx.assign(scalar, Fr::from(value));
+                    ctx.assign(x, generator.0);
+                    ctx.assign(y, generator.1);
+                    let output = gadget.wg(ctx, &BigUint::from(value), generator);
+                    ctx.assign(result.0, output.0);
+                    ctx.assign(result.1, output.1);
+                    assert_eq!(output, expected);
+                })
+            });
+
+            ctx.trace(move |ctx, value| ctx.add(&mul, value));
+        });
+
+        assert!(satisfied(&circuit, 0xa5));
+    }
+
+    #[test]
+    fn test_native_point_add() {
+        let curve = CurveParams::grumpkin();
+        let a = curve.mul(&BigUint::from(2u64), &curve.generator).unwrap();
+        let b = curve.mul(&BigUint::from(5u64), &curve.generator).unwrap();
+        let expected = curve.mul(&BigUint::from(7u64), &curve.generator).unwrap();
+
+        let circuit: SBPIR<Fr, Fr> = circuit("native point add", |ctx| {
+            let add = ctx.step_type_def("add", |ctx| {
+                let gadget = NativePointAdd::new(ctx, "add");
+                let (a_expr, b_expr) = (constant_point(&a), constant_point(&b));
+                let (a, b) = (field_point::<Fr>(&a), field_point::<Fr>(&b));
+
+                let setup_gadget = gadget.clone();
+                ctx.setup(move |ctx| setup_gadget.setup(ctx, a_expr.clone(), b_expr.clone()));
+
+                ctx.wg(move |ctx, shift: Fr| {
+                    let output = gadget.wg(ctx, a, b);
+                    ctx.assign(gadget.output().0, output.0 + shift);
+                })
+            });
+
+            ctx.trace(move |ctx, shift| ctx.add(&add, shift));
+        });
+
+        assert_eq!(
+            field_point::<Fr>(&curve.add_incomplete(&a, &b).1),
+            field_point::<Fr>(&expected)
+        );
+        assert!(satisfied(&circuit, Fr::ZERO));
+        assert!(!satisfied(&circuit, Fr::ONE));
+    }
+
+    #[test]
+    fn test_non_native_scalar_mul() {
+        let curve = Rc::new(CurveParams::secp256k1());
+        let expected = curve.mul(&BigUint::from(11u64), &curve.generator).unwrap();
+
+        let setup_curve = curve.clone();
+        let circuit: SBPIR<Fr, (u64, Point)> = circuit("non-native scalar mul", |ctx| {
+            let byte_table = range_table(ctx, 8);
+            let scalar = ctx.forward("scalar");
+
+            let mul = ctx.step_type_def("mul", |ctx| {
+                let x = NonNativeElement::new(ctx, "x");
+                let y = NonNativeElement::new(ctx, "y");
+                let gadget = NonNativeScalarMul::new(ctx, "mul", setup_curve.clone(), 4);
+
+                let (setup_x, setup_y, setup_gadget) = (x.clone(), y.clone(), gadget.clone());
+                ctx.setup(move |ctx| {
+                    setup_x.setup(ctx, byte_table);
+                    setup_y.setup(ctx, byte_table);
+                    setup_gadget.setup(
+                        ctx,
+                        scalar,
+                        (&setup_x.limbs(), &setup_y.limbs()),
+                        byte_table,
+                    );
+                });
+
+                let generator = setup_curve.generator.clone();
+                ctx.wg(move |ctx, (value, expected): (u64, Point)| {
+                    ctx.assign(scalar, Fr::from(value));
+                    x.wg(ctx, &generator.0);
+                    y.wg(ctx, &generator.1);
+                    let output = gadget.wg(ctx, &BigUint::from(value), &generator);
+                    assert_eq!(output, expected);
+                })
+            });
+
+            ctx.trace(move |ctx, args| ctx.add(&mul, args));
+        });
+
+        assert!(satisfied(&circuit, (11, expected)));
+    }
+}
@@ -0,0 +1,693 @@
+use std::{hash::Hash, rc::Rc};
+
+use num_bigint::BigUint;
+
+use crate::{
+    field::Field,
+    frontend::dsl::{
+        cb::{eq, Constraint},
+        lb::LookupTable,
+        CircuitContext, StepTypeSetupContext,
+    },
+    poly::{Expr, ToExpr},
+    sbpir::{query::Queriable, PIR},
+    wit_gen::StepInstance,
+};
+
+use super::{
+    ecc::{
+        inverse_columns, CurveParams, NonNativeDoubleAndAdd, NonNativeOnCurve, NonNativePointAdd,
+        Point,
+    },
+    nonnative::{
+        constant_columns, inverse_mod, mul_columns, product_bound, sub_columns, value_columns,
+        CanonicalCheck, NonNativeElement, Reduction, NUM_LIMBS,
+    },
+    IsZero,
+};
+
+/// Bits of the scalars of secp256k1.
+const SCALAR_BITS: usize = 256;
+
+/// Verifies an ECDSA signature `(r, s)` of the message hash over secp256k1 natively.
+pub fn ecdsa_verify(msg_hash: &BigUint, r: &BigUint, s: &BigUint, public_key: &Point) -> bool {
+    let curve = CurveParams::secp256k1();
+    let n = &curve.order;
+    let zero = BigUint::from(0u64);
+    if r == &zero || r >= n || s == &zero || s >= n {
+        return false;
+    }
+
+    let w = inverse_mod(s, n);
+    let u1 = msg_hash * &w % n;
+    let u2 = r * &w % n;
+
+    match curve.add(curve.mul(&u1, &curve.generator), curve.mul(&u2, public_key)) {
+        Some((x, _)) => &(x % n) == r,
+        None => false,
+    }
+}
+
+fn limb_exprs<F: Clone>(signals: &[Queriable<F>]) -> Vec<PIR<F>> {
+    signals.iter().map(|s| s.expr()).collect()
+}
+
+/// Inputs of an ECDSA signature verification over secp256k1.
+#[derive(Clone, Debug)]
+pub struct EcdsaVerifyArgs {
+    pub msg_hash: BigUint,
+    pub r: BigUint,
+    pub s: BigUint,
+    pub public_key: Point,
+}
+
+/// Forward signals holding the limbs of the inputs in the first step of the ECDSA sub-circuit.
+#[derive(Clone)]
+pub struct EcdsaVerifyInputs<F> {
+    pub msg_hash: Vec<Queriable<F>>,
+    pub r: Vec<Queriable<F>>,
+    pub s: Vec<Queriable<F>>,
+    pub public_key_x: Vec<Queriable<F>>,
+    pub public_key_y: Vec<Queriable<F>>,
+}
+
+/// Forward signals carried through the double-and-add steps.
+#[derive(Clone)]
+struct EcdsaState<F> {
+    r: Vec<Queriable<F>>,
+    public_key: (Vec<Queriable<F>>, Vec<Queriable<F>>),
+    /// Precomputed `G + Q`.
+    sum: (Vec<Queriable<F>>, Vec<Queriable<F>>),
+    acc: (Vec<Queriable<F>>, Vec<Queriable<F>>),
+    /// High and low 128 bits of `u1` and `u2`.
+    scalars: Vec<Queriable<F>>,
+    /// Bits of the scalars processed so far, accumulated like `scalars`.
+    scalar_accs: Vec<Queriable<F>>,
+    index: Queriable<F>,
+    /// One while the bits of the high halves are processed.
+    high: Queriable<F>,
+}
+
+#[derive(Clone)]
+struct EcdsaStateValues {
+    r: BigUint,
+    public_key: Point,
+    sum: Point,
+    acc: Point,
+    scalars: [u128; 4],
+    scalar_accs: [u128; 4],
+    index: usize,
+}
+
+fn u128_value<F: Field + From<u64>>(value: u128) -> F {
+    F::from(value as u64) + F::from((value >> 64) as u64) * F::from(2).pow([64])
+}
+
+fn halves(value: &BigUint) -> (u128, u128) {
+    let mask = (BigUint::from(1u64) << 128) - 1u64;
+
+    (
+        u128::try_from(value >> 128).expect("value fits in 256 bits"),
+        u128::try_from(value & mask).expect("128 bits"),
+    )
+}
+
+fn assign_limbs<F: Field + From<u64> + Hash>(
+    ctx: &mut StepInstance<F>,
+    signals: &[Queriable<F>],
+    value: &BigUint,
+) {
+    for (signal, limb) in signals.iter().zip(value_columns(value)) {
+        let limb = u64::try_from(limb).expect("value fits in the limbs");
+        ctx.assign(*signal, F::from(limb));
+    }
+}
+
+impl<F: Field + From<u64> + Hash> EcdsaState<F> {
+    fn wg(&self, ctx: &mut StepInstance<F>, values: &EcdsaStateValues) {
+        assign_limbs(ctx, &self.r, &values.r);
+        for (signals, point) in [
+            (&self.public_key, &values.public_key),
+            (&self.sum, &values.sum),
+            (&self.acc, &values.acc),
+        ] {
+            assign_limbs(ctx, &signals.0, &point.0);
+            assign_limbs(ctx, &signals.1, &point.1);
+        }
+        for (signal, value) in self.scalars.iter().zip(values.scalars.iter()) {
+            ctx.assign(*signal, u128_value(*value));
+        }
+        for (signal, value) in self.scalar_accs.iter().zip(values.scalar_accs.iter()) {
+            ctx.assign(*signal, u128_value(*value));
+        }
+        ctx.assign(self.index, F::from(values.index as u64));
+        ctx.assign(self.high, F::from((values.index < SCALAR_BITS / 2) as u64));
+    }
+}
+
+#[track_caller]
+fn constrain_equal<F: Field + From<u64>, L: Into<Constraint<F>> + Clone>(
+    ctx: &mut StepTypeSetupContext<F>,
+    lhs: &[L],
+    rhs: &[PIR<F>],
+) {
+    for (lhs, rhs) in lhs.iter().zip(rhs.iter()) {
+        ctx.constr(eq(lhs.clone(), rhs.clone()));
+    }
+}
+
+#[track_caller]
+fn carry<F: Field + From<u64>>(ctx: &mut StepTypeSetupContext<F>, signals: &[Queriable<F>]) {
+    for signal in signals.iter() {
+        ctx.transition(eq(signal.next(), *signal));
+    }
+}
+
+/// Defines a secp256k1 ECDSA verification as a sub-circuit with 258 steps: an initial step that
+/// checks the inputs and computes `u1 = z / s` and `u2 = r / s`, one double-and-add step per bit
+/// of the scalars computing `u1 * G + u2 * Q`, and a final step that checks the x coordinate of
+/// the result against `r`. Public keys equal to the generator or its negation are not supported.
+/// The non-native elements are range checked in `byte_table`, which must contain the values in
+/// `[0, 256)`, for example `range_table(ctx, 8)` in a fixed sub-circuit. Returns the input signals
+/// so that they can be connected to other sub-circuits.
+pub fn ecdsa_verify_circuit<F: Field + From<u64> + Hash>(
+    ctx: &mut CircuitContext<F, EcdsaVerifyArgs>,
+    byte_table: LookupTable,
+) -> EcdsaVerifyInputs<F> {
+    let mut limbs = |name: &str| -> Vec<Queriable<F>> {
+        (0..NUM_LIMBS)
+            .map(|i| ctx.forward(format!("{}[{}]", name, i).as_str()))
+            .collect()
+    };
+
+    let inputs = EcdsaVerifyInputs {
+        msg_hash: limbs("msg_hash"),
+        r: limbs("r"),
+        s: limbs("s"),
+        public_key_x: limbs("public_key.x"),
+        public_key_y: limbs("public_key.y"),
+    };
+    let sum = (limbs("sum.x"), limbs("sum.y"));
+    let acc = (limbs("acc.x"), limbs("acc.y"));
+    let state = EcdsaState {
+        r: inputs.r.clone(),
+        public_key: (inputs.public_key_x.clone(), inputs.public_key_y.clone()),
+        sum,
+        acc,
+        scalars: ["u1.high", "u1.low", "u2.high", "u2.low"]
+            .iter()
+            .map(|name| ctx.forward(name))
+            .collect(),
+        scalar_accs: ["u1_acc.high", "u1_acc.low", "u2_acc.high", "u2_acc.low"]
+            .iter()
+            .map(|name| ctx.forward(name))
+            .collect(),
+        index: ctx.forward("index"),
+        high: ctx.forward("high"),
+    };
+
+    let curve = Rc::new(CurveParams::secp256k1());
+    let p = curve.modulus.clone();
+    let n = curve.order.clone();
+    let generator = curve.generator.clone();
+    let correction = curve.offset_correction(SCALAR_BITS);
+    let generator_limbs: (Vec<PIR<F>>, Vec<PIR<F>>) = (
+        constant_columns(&generator.0),
+        constant_columns(&generator.1),
+    );
+
+    let offset_limbs: (Vec<PIR<F>>, Vec<PIR<F>>) = (
+        constant_columns(&curve.offset.0),
+        constant_columns(&curve.offset.1),
+    );
+    let init = ctx.step_type_def("ecdsa init", |ctx| {
+        let elements: Vec<NonNativeElement<F>> =
+            ["msg_hash", "r", "s", "public_key.x", "public_key.y"]
+                .iter()
+                .map(|name| NonNativeElement::new(ctx, name))
+                .collect();
+        let canonical = vec![
+            CanonicalCheck::new(ctx, "r_canonical", &n),
+            CanonicalCheck::new(ctx, "s_canonical", &n),
+            CanonicalCheck::new(ctx, "public_key.x_canonical", &p),
+            CanonicalCheck::new(ctx, "public_key.y_canonical", &p),
+        ];
+        let r_inverse = NonNativeElement::new(ctx, "r_inverse");
+        let r_inverse_check = Reduction::new_zero(ctx, "r_inverse_check", &n, &product_bound());
+        let s_inverse = NonNativeElement::new(ctx, "s_inverse");
+        let s_inverse_check = Reduction::new_zero(ctx, "s_inverse_check", &n, &product_bound());
+        let u1 = Reduction::new(ctx, "u1", &n, &product_bound());
+        let u2 = Reduction::new(ctx, "u2", &n, &product_bound());
+        let on_curve = NonNativeOnCurve::new(ctx, "public_key.on_curve", curve.clone());
+        let sum = NonNativePointAdd::new(ctx, "sum", curve.clone());
+
+        let setup_inputs = inputs.clone();
+        let setup_state = state.clone();
+        let setup_elements = elements.clone();
+        let setup_canonical = canonical.clone();
+        let setup_r_inverse = r_inverse.clone();
+        let setup_r_inverse_check = r_inverse_check.clone();
+        let setup_s_inverse = s_inverse.clone();
+        let setup_s_inverse_check = s_inverse_check.clone();
+        let setup_u1 = u1.clone();
+        let setup_u2 = u2.clone();
+        let setup_on_curve = on_curve.clone();
+        let setup_sum = sum.clone();
+        let generator_limbs = generator_limbs.clone();
+        ctx.setup(move |ctx| {
+            let inputs = [
+                &setup_inputs.msg_hash,
+                &setup_inputs.r,
+                &setup_inputs.s,
+                &setup_inputs.public_key_x,
+                &setup_inputs.public_key_y,
+            ];
+            let limbs: Vec<Vec<PIR<F>>> = setup_elements.iter().map(|e| e.limbs()).collect();
+            for ((input, element), limbs) in
+                inputs.iter().zip(setup_elements.iter()).zip(limbs.iter())
+            {
+                element.setup(ctx, byte_table);
+                constrain_equal(ctx, input, limbs);
+            }
+            let [z, r, s, x, y] = [&limbs[0], &limbs[1], &limbs[2], &limbs[3], &limbs[4]];
+            for (check, limbs) in setup_canonical.iter().zip([r, s, x, y]) {
+                check.setup(ctx, limbs, byte_table);
+            }
+
+            // r and s are not zero
+            setup_r_inverse.setup(ctx, byte_table);
+            setup_r_inverse_check.setup(
+                ctx,
+                inverse_columns(r, &setup_r_inverse.limbs()),
+                byte_table,
+            );
+            setup_s_inverse.setup(ctx, byte_table);
+            setup_s_inverse_check.setup(
+                ctx,
+                inverse_columns(s, &setup_s_inverse.limbs()),
+                byte_table,
+            );
+
+            setup_u1.setup(ctx, mul_columns(z, &setup_s_inverse.limbs()), byte_table);
+            setup_u2.setup(ctx, mul_columns(r, &setup_s_inverse.limbs()), byte_table);
+
+            setup_on_curve.setup(ctx, (x, y), byte_table);
+
+            setup_sum.setup(
+                ctx,
+                (&generator_limbs.0, &generator_limbs.1),
+                (x, y),
+                byte_table,
+            );
+
+            carry(ctx, &setup_state.r);
+            carry(ctx, &setup_state.public_key.0);
+            carry(ctx, &setup_state.public_key.1);
+            let (sum_x, sum_y) = setup_sum.output();
+            constrain_next(ctx, &setup_state.sum.0, &sum_x);
+            constrain_next(ctx, &setup_state.sum.1, &sum_y);
+            constrain_next(ctx, &setup_state.acc.0, &offset_limbs.0);
+            constrain_next(ctx, &setup_state.acc.1, &offset_limbs.1);
+
+            let base = Expr::Const(F::from(2).pow([64]));
+            let (u1, u2) = (setup_u1.result().limbs(), setup_u2.result().limbs());
+            let scalars = [
+                u1[2].clone() + base.clone() * u1[3].clone(),
+                u1[0].clone() + base.clone() * u1[1].clone(),
+                u2[2].clone() + base.clone() * u2[3].clone(),
+                u2[0].clone() + base * u2[1].clone(),
+            ];
+            constrain_next(ctx, &setup_state.scalars, &scalars);
+            for acc in setup_state.scalar_accs.iter() {
+                ctx.transition(eq(acc.next(), 0u64));
+            }
+            ctx.transition(eq(setup_state.index.next(), 0u64));
+            ctx.transition(eq(setup_state.high.next(), 1u64));
+        });
+
+        let wg_inputs = inputs.clone();
+        let wg_n = n.clone();
+        let wg_generator = generator.clone();
+        ctx.wg(move |ctx, args: EcdsaVerifyArgs| {
+            let n = &wg_n;
+            let values = [
+                &args.msg_hash,
+                &args.r,
+                &args.s,
+                &args.public_key.0,
+                &args.public_key.1,
+            ];
+            let inputs = [
+                &wg_inputs.msg_hash,
+                &wg_inputs.r,
+                &wg_inputs.s,
+                &wg_inputs.public_key_x,
+                &wg_inputs.public_key_y,
+            ];
+            for ((signals, element), value) in inputs.iter().zip(elements.iter()).zip(values) {
+                assign_limbs(ctx, signals, value);
+                element.wg(ctx, value);
+            }
+            for (check, value) in canonical.iter().zip(&values[1..]) {
+                check.wg(ctx, value);
+            }
+
+            let [z, r, s] = [values[0], values[1], values[2]].map(value_columns);
+            let r_inv = inverse_mod(&args.r, n);
+            r_inverse.wg(ctx, &r_inv);
+            r_inverse_check.wg(ctx, &inverse_columns(&r, &value_columns(&r_inv)));
+            let s_inv = inverse_mod(&args.s, n);
+            s_inverse.wg(ctx, &s_inv);
+            s_inverse_check.wg(ctx, &inverse_columns(&s, &value_columns(&s_inv)));
+
+            let s_inv = value_columns(&s_inv);
+            u1.wg(ctx, &mul_columns(&z, &s_inv));
+            u2.wg(ctx, &mul_columns(&r, &s_inv));
+
+            on_curve.wg(ctx, &args.public_key);
+            sum.wg(ctx, &wg_generator, &args.public_key);
+        })
+    });
+
+    let double_and_add = ctx.step_type_def("ecdsa double and add", |ctx| {
+        let u1_bit = ctx.internal("u1_bit");
+        let u2_bit = ctx.internal("u2_bit");
+        let double_and_add = NonNativeDoubleAndAdd::new(ctx, "double_and_add", curve.clone());
+        let index_inv = ctx.internal("index_inv");
+        let last_high = IsZero::setup(ctx, state.index - (SCALAR_BITS / 2 - 1) as u64, index_inv);
+        let last_high_expr = last_high.is_zero().expr;
+
+        let setup_state = state.clone();
+        let setup_double_and_add = double_and_add.clone();
+        let generator_limbs = generator_limbs.clone();
+        ctx.setup(move |ctx| {
+            let state = &setup_state;
+            ctx.constr(eq(u1_bit * (1u64.expr() - u1_bit), 0u64));
+            ctx.constr(eq(u2_bit * (1u64.expr() - u2_bit), 0u64));
+
+            let acc = (limb_exprs(&state.acc.0), limb_exprs(&state.acc.1));
+            // G, Q or G + Q depending on the bits, G when both are zero and the sum is discarded
+            let select = |g: &[PIR<F>], q: &[Queriable<F>], sum: &[Queriable<F>]| -> Vec<PIR<F>> {
+                g.iter()
+                    .zip(q.iter())
+                    .zip(sum.iter())
+                    .map(|((g, q), sum)| {
+                        g.clone() + u2_bit * (*q - g.clone()) + u1_bit * u2_bit * (*sum - *q)
+                    })
+                    .collect()
+            };
+            let selected = (
+                select(&generator_limbs.0, &state.public_key.0, &state.sum.0),
+                select(&generator_limbs.1, &state.public_key.1, &state.sum.1),
+            );
+            setup_double_and_add.setup(
+                ctx,
+                (&acc.0, &acc.1),
+                (&selected.0, &selected.1),
+                byte_table,
+            );
+            let next = setup_double_and_add.output(u1_bit + u2_bit - u1_bit * u2_bit);
+            constrain_next(ctx, &state.acc.0, &next.0);
+            constrain_next(ctx, &state.acc.1, &next.1);
+
+            for (i, (acc, bit)) in state
+                .scalar_accs
+                .iter()
+                .zip([u1_bit, u1_bit, u2_bit, u2_bit])
+                .enumerate()
+            {
+                let active = if i % 2 == 0 {
+                    state.high.expr()
+                } else {
+                    1u64.expr() - state.high
+                };
+                ctx.transition(eq(acc.next(), *acc + active * (*acc + bit)));
+            }
+
+            carry(ctx, &state.r);
+            carry(ctx, &state.public_key.0);
+            carry(ctx, &state.public_key.1);
+            carry(ctx, &state.sum.0);
+            carry(ctx, &state.sum.1);
+            carry(ctx, &state.scalars);
+            ctx.transition(eq(state.index.next(), state.index + 1u64));
+            ctx.transition(eq(
+                state.high.next(),
+                state.high * (1u64.expr() - last_high_expr.clone()),
+            ));
+        });
+
+        let wg_state = state.clone();
+        let wg_generator = generator.clone();
+        ctx.wg(
+            move |ctx, (values, bits): (EcdsaStateValues, (bool, bool))| {
+                wg_state.wg(ctx, &values);
+                ctx.assign(u1_bit, F::from(bits.0 as u64));
+                ctx.assign(u2_bit, F::from(bits.1 as u64));
+                last_high.wg::<()>(
+                    ctx,
+                    F::from(values.index as u64) - F::from((SCALAR_BITS / 2 - 1) as u64),
+                );
+
+                let selected = match bits {
+                    (_, false) => wg_generator.clone(),
+                    (false, true) => values.public_key.clone(),
+                    (true, true) => values.sum.clone(),
+                };
+                double_and_add.wg(ctx, &values.acc, &selected, bits.0 || bits.1);
+            },
+        )
+    });
+
+    let finalize = ctx.step_type_def("ecdsa finalize", |ctx| {
+        let add = NonNativePointAdd::new(ctx, "result", curve.clone());
+        let x_canonical = CanonicalCheck::new(ctx, "result.x_canonical", &p);
+        let r_check = Reduction::new_zero(ctx, "r_check", &n, &product_bound());
+
+        let setup_state = state.clone();
+        let setup_add = add.clone();
+        let setup_x_canonical = x_canonical.clone();
+        let setup_r_check = r_check.clone();
+        let setup_correction = correction.clone();
+        ctx.setup(move |ctx| {
+            let state = &setup_state;
+            ctx.constr(eq(state.index, SCALAR_BITS as u64));
+            for (scalar, acc) in state.scalars.iter().zip(state.scalar_accs.iter()) {
+                ctx.constr(eq(*scalar, *acc));
+            }
+
+            // subtract the offset, 2^256 times the offset point
+            let correction = &setup_correction;
+            let acc = (limb_exprs(&state.acc.0), limb_exprs(&state.acc.1));
+            setup_add.setup(
+                ctx,
+                (&acc.0, &acc.1),
+                (
+                    &constant_columns(&correction.0),
+                    &constant_columns(&correction.1),
+                ),
+                byte_table,
+            );
+            let (x, _) = setup_add.output();
+            setup_x_canonical.setup(ctx, &x, byte_table);
+            setup_r_check.setup(ctx, sub_columns(&x, &limb_exprs(&state.r)), byte_table);
+        });
+
+        let wg_state = state.clone();
+        ctx.wg(move |ctx, values: EcdsaStateValues| {
+            wg_state.wg(ctx, &values);
+
+            let (x, _) = add.wg(ctx, &values.acc, &correction);
+            x_canonical.wg(ctx, &x);
+            r_check.wg(
+                ctx,
+                &sub_columns(&value_columns(&x), &value_columns(&values.r)),
+            );
+        })
+    });
+
+    ctx.pragma_first_step(&init);
+    ctx.pragma_last_step(&finalize);
+    ctx.pragma_num_steps(SCALAR_BITS + 2);
+
+    ctx.trace(move |ctx, args: EcdsaVerifyArgs| {
+        let n = &curve.order;
+        let w = inverse_mod(&args.s, n);
+        let u1 = &args.msg_hash * &w % n;
+        let u2 = &args.r * &w % n;
+        let (u1_high, u1_low) = halves(&u1);
+        let (u2_high, u2_low) = halves(&u2);
+
+        let mut values = EcdsaStateValues {
+            r: args.r.clone(),
+            public_key: args.public_key.clone(),
+            sum: curve.add_incomplete(&generator, &args.public_key).1,
+            acc: curve.offset.clone(),
+            scalars: [u1_high, u1_low, u2_high, u2_low],
+            scalar_accs: [0; 4],
+            index: 0,
+        };
+
+        ctx.add(&init, args);
+        for index in 0..SCALAR_BITS {
+            let bit = (SCALAR_BITS - 1 - index) as u64;
+            let bits = (u1.bit(bit), u2.bit(bit));
+            values.index = index;
+            ctx.add(&double_and_add, (values.clone(), bits));
+
+            let doubled = curve.double(&values.acc).1;
+            values.acc = match bits {
+                (false, false) => doubled,
+                (true, false) => curve.add_incomplete(&doubled, &generator).1,
+                (false, true) => curve.add_incomplete(&doubled, &values.public_key).1,
+                (true, true) => curve.add_incomplete(&doubled, &values.sum).1,
+            };
+            let high = index < SCALAR_BITS / 2;
+            for (i, acc) in values.scalar_accs.iter_mut().enumerate() {
+                let bit = if i < 2 { bits.0 } else { bits.1 };
+                if high == (i % 2 == 0) {
+                    *acc = 2 * *acc + bit as u128;
+                }
+            }
+        }
+        values.index = SCALAR_BITS;
+        ctx.add(&finalize, values);
+    });
+
+    inputs
+}
+
+#[track_caller]
+fn constrain_next<F: Field + From<u64>>(
+    ctx: &mut StepTypeSetupContext<F>,
+    signals: &[Queriable<F>],
+    values: &[PIR<F>],
+) {
+    for (signal, value) in signals.iter().zip(values.iter()) {
+        ctx.transition(eq(signal.next(), value.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        frontend::dsl::circuit,
+        sbpir::SBPIR,
+        stdlib::range_table,
+        wit_gen::{TraceGenerator, TraceWitness},
+    };
+
+    fn hex(value: &str) -> BigUint {
+        BigUint::parse_bytes(value.as_bytes(), 16).unwrap()
+    }
+
+    fn sign(msg_hash: &BigUint, private_key: &BigUint, nonce: &BigUint) -> (BigUint, BigUint) {
+        let curve = CurveParams::secp256k1();
+        let n = &curve.order;
+        let (x, _) = curve.mul(nonce, &curve.generator).unwrap();
+        let r = x % n;
+        let s = inverse_mod(nonce, n) * (msg_hash + &r * private_key) % n;
+
+        (r, s)
+    }
+
+    fn signed_args() -> EcdsaVerifyArgs {
+        let private_key = hex("c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721");
+        let nonce = hex("a6e3c57dd01abe90086538398355dd4c3b17aa873382b0f24d6129493d8aad60");
+        let msg_hash = hex("af2bdbe1aa9b6ec1e2ade1d694f41fc71a831d0268e9891562113d8a62add1bf");
+        let (r, s) = sign(&msg_hash, &private_key, &nonce);
+
+        EcdsaVerifyArgs {
+            msg_hash,
+            r,
+            s,
+            public_key: CurveParams::secp256k1()
+                .mul(&private_key, &CurveParams::secp256k1().generator)
+                .unwrap(),
+        }
+    }
+
+    fn satisfied(circuit: &SBPIR<Fr, EcdsaVerifyArgs>, trace: &TraceWitness<Fr>) -> bool {
+        trace
+            .step_instances
+            .iter()
+            .enumerate()
+            .all(|(i, step_instance)| {
+                let step_type = &circuit.step_types[&step_instance.step_type_uuid];
+                let mut assignments: HashMap<Queriable<Fr>, Fr> = step_instance.assignments.clone();
+                if let Some(next) = trace.step_instances.get(i + 1) {
+                    for (queriable, value) in next.assignments.iter() {
+                        if let Queriable::Forward(signal, false) = queriable {
+                            assignments.insert(Queriable::Forward(*signal, true), *value);
+                        }
+                    }
+                }
+
+                let transitions = if i + 1 < trace.step_instances.len() {
+                    step_type
+                        .transition_constraints
+                        .iter()
+                        .map(|c| &c.expr)
+                        .collect()
+                } else {
+                    vec![]
+                };
+
+                step_type
+                    .constraints
+                    .iter()
+                    .map(|c| &c.expr)
+                    .chain(transitions)
+                    .all(|expr| expr.eval(&assignments) == Some(Fr::ZERO))
+            })
+    }
+
+    #[test]
+    fn test_ecdsa_verify() {
+        let args = signed_args();
+
+        assert!(ecdsa_verify(
+            &args.msg_hash,
+            &args.r,
+            &args.s,
+            &args.public_key
+        ));
+        assert!(!ecdsa_verify(
+            &(&args.msg_hash + 1u64),
+            &args.r,
+            &args.s,
+            &args.public_key
+        ));
+        assert!(!ecdsa_verify(
+            &args.msg_hash,
+            &args.r,
+            &(&args.s + CurveParams::secp256k1().order),
+            &args.public_key
+        ));
+    }
+
+    #[test]
+    fn test_ecdsa_verify_circuit() {
+        let circuit: SBPIR<Fr, EcdsaVerifyArgs> = circuit("ecdsa", |ctx| {
+            let byte_table = range_table(ctx, 8);
+            ecdsa_verify_circuit(ctx, byte_table);
+        });
+        let generator = TraceGenerator::new(circuit.trace.clone().unwrap(), SCALAR_BITS + 2);
+
+        let args = signed_args();
+        let trace = generator.generate(args.clone());
+        assert_eq!(trace.step_instances.len(), SCALAR_BITS + 2);
+        assert!(satisfied(&circuit, &trace));
+
+        let forged = EcdsaVerifyArgs {
+            msg_hash: &args.msg_hash + 1u64,
+            ..args
+        };
+        assert!(!satisfied(&circuit, &generator.generate(forged)));
+    }
+}
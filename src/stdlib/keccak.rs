@@ -0,0 +1,507 @@
+use std::hash::Hash;
+
+use crate::{
+    field::Field,
+    frontend::dsl::{
+        cb::{annotate, eq, table, Typing},
+        lb::LookupTable,
+        CircuitContext, StepTypeContext, StepTypeSetupContext,
+    },
+    poly::ToExpr,
+    sbpir::{query::Queriable, PIR},
+    wit_gen::StepInstance,
+};
+
+pub const KECCAK_ROUNDS: usize = 24;
+pub const KECCAK_STATE_BITS: usize = 1600;
+
+pub const ROUND_CONSTANTS: [u64; KECCAK_ROUNDS] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// Rho rotation offsets, indexed by `[x][y]`.
+const ROTATION_OFFSETS: [[u32; 5]; 5] = [
+    [0, 36, 3, 41, 18],
+    [1, 44, 10, 45, 2],
+    [62, 6, 43, 15, 61],
+    [28, 55, 25, 21, 56],
+    [27, 20, 39, 8, 14],
+];
+
+/// The only bits that are set in any of the round constants.
+const ROUND_CONSTANT_BITS: [usize; 7] = [0, 1, 3, 7, 15, 31, 63];
+
+/// Position of the bit `z` of the lane `(x, y)` in a bit-level state.
+pub fn state_bit(x: usize, y: usize, z: usize) -> usize {
+    (x + 5 * y) * 64 + z
+}
+
+/// Intermediate values of a round, used for witness generation.
+struct RoundValues {
+    c: [u64; 5],
+    theta: [u64; 25],
+    out: [u64; 25],
+}
+
+fn keccak_round(state: &[u64; 25], round: usize) -> RoundValues {
+    let mut c = [0u64; 5];
+    for (x, c) in c.iter_mut().enumerate() {
+        *c = (0..5).fold(0, |acc, y| acc ^ state[x + 5 * y]);
+    }
+
+    let mut theta = [0u64; 25];
+    for x in 0..5 {
+        let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+        for y in 0..5 {
+            theta[x + 5 * y] = state[x + 5 * y] ^ d;
+        }
+    }
+
+    let mut b = [0u64; 25];
+    for x in 0..5 {
+        for y in 0..5 {
+            b[y + 5 * ((2 * x + 3 * y) % 5)] = theta[x + 5 * y].rotate_left(ROTATION_OFFSETS[x][y]);
+        }
+    }
+
+    let mut out = [0u64; 25];
+    for x in 0..5 {
+        for y in 0..5 {
+            out[x + 5 * y] = b[x + 5 * y] ^ (!b[(x + 1) % 5 + 5 * y] & b[(x + 2) % 5 + 5 * y]);
+        }
+    }
+    out[0] ^= ROUND_CONSTANTS[round];
+
+    RoundValues { c, theta, out }
+}
+
+/// Computes the Keccak-f[1600] permutation natively. Lanes are indexed by `x + 5 * y`.
+pub fn keccak_f(state: &mut [u64; 25]) {
+    for round in 0..KECCAK_ROUNDS {
+        *state = keccak_round(state, round).out;
+    }
+}
+
+/// Returns the bits of the lanes, in the order given by `state_bit`.
+pub fn lanes_to_bits(state: &[u64; 25]) -> Vec<bool> {
+    state
+        .iter()
+        .flat_map(|lane| (0..64).map(move |z| (lane >> z) & 1 == 1))
+        .collect()
+}
+
+fn xor<F: From<u64> + Clone>(a: PIR<F>, b: PIR<F>) -> PIR<F> {
+    a.clone() + b.clone() - 2u64.expr() * a * b
+}
+
+/// Creates a lookup table with the round index and the bits of the round constant at the positions
+/// in `ROUND_CONSTANT_BITS`. It sets the number of steps of the circuit, so it is meant to be used
+/// in a dedicated fixed sub-circuit.
+pub fn keccak_round_constants_table<F: Field + From<u64> + Hash, TraceArgs>(
+    ctx: &mut CircuitContext<F, TraceArgs>,
+) -> LookupTable {
+    let round = ctx.fixed("keccak round");
+    let bits: Vec<Queriable<F>> = ROUND_CONSTANT_BITS
+        .iter()
+        .map(|z| ctx.fixed(format!("keccak round constant bit {}", z).as_str()))
+        .collect();
+
+    ctx.pragma_num_steps(KECCAK_ROUNDS);
+    let fixed_bits = bits.clone();
+    ctx.fixed_gen(move |ctx| {
+        for (i, round_constant) in ROUND_CONSTANTS.iter().enumerate() {
+            ctx.assign(i, round, F::from(i as u64));
+            for (bit, z) in fixed_bits.iter().zip(ROUND_CONSTANT_BITS.iter()) {
+                ctx.assign(i, *bit, F::from((round_constant >> z) & 1));
+            }
+        }
+    });
+
+    ctx.new_table(
+        bits.into_iter()
+            .fold(table().add(round), |table, bit| table.add(bit)),
+    )
+}
+
+/// In-step gadget for one round of Keccak-f[1600] over a bit-level state. The state bits are
+/// expected to be boolean, indexed as in `state_bit`. The theta column parities and the theta
+/// output are stored in internal signals, while rho and pi are free wiring. The output of chi and
+/// iota is returned as expressions of degree 4 by `output`, so the caller decides where the next
+/// state lives. The maximum degree of the constraints is 5.
+#[derive(Clone)]
+pub struct KeccakRound<F> {
+    c: Vec<Queriable<F>>,
+    theta: Vec<Queriable<F>>,
+    round_constant: Vec<Queriable<F>>,
+}
+
+impl<F: Field + From<u64>> KeccakRound<F> {
+    /// Adds the internal signals of the round to the step type, annotated with the given name.
+    pub fn new(ctx: &mut StepTypeContext<F>, name: &str) -> KeccakRound<F> {
+        let c = (0..5 * 64)
+            .map(|i| ctx.internal(format!("{}.c[{}][{}]", name, i / 64, i % 64).as_str()))
+            .collect();
+        let theta = (0..KECCAK_STATE_BITS)
+            .map(|i| ctx.internal(format!("{}.theta[{}][{}]", name, i / 64, i % 64).as_str()))
+            .collect();
+        let round_constant = ROUND_CONSTANT_BITS
+            .iter()
+            .map(|z| ctx.internal(format!("{}.rc[{}]", name, z).as_str()))
+            .collect();
+
+        KeccakRound {
+            c,
+            theta,
+            round_constant,
+        }
+    }
+
+    /// Constrains theta for the `input` state bits, and looks up the round constant bits for
+    /// `round` in a table created by `keccak_round_constants_table`.
+    #[track_caller]
+    pub fn setup<R: Into<PIR<F>>>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        input: &[Queriable<F>],
+        round: R,
+        round_constants_table: LookupTable,
+    ) {
+        assert_eq!(input.len(), KECCAK_STATE_BITS, "wrong keccak state size");
+
+        for x in 0..5 {
+            for z in 0..64 {
+                let parity = (1..5).fold(input[state_bit(x, 0, z)].expr(), |acc, y| {
+                    xor(acc, input[state_bit(x, y, z)].expr())
+                });
+                ctx.constr(eq(self.c[x * 64 + z], parity));
+            }
+        }
+
+        for x in 0..5 {
+            for z in 0..64 {
+                let d = xor(
+                    self.c[(x + 4) % 5 * 64 + z].expr(),
+                    self.c[(x + 1) % 5 * 64 + (z + 63) % 64].expr(),
+                );
+                for y in 0..5 {
+                    ctx.constr(eq(
+                        self.theta[state_bit(x, y, z)],
+                        xor(input[state_bit(x, y, z)].expr(), d.clone()),
+                    ));
+                }
+            }
+        }
+
+        let lookup = self
+            .round_constant
+            .iter()
+            .fold(round_constants_table.apply(round.into()), |lookup, bit| {
+                lookup.apply(*bit)
+            });
+        ctx.add_lookup(lookup);
+    }
+
+    /// Returns the bit `z` of the lane `(x, y)` after rho and pi.
+    fn pi(&self, x: usize, y: usize, z: usize) -> PIR<F> {
+        // pi moves (x', y') to (y', 2x' + 3y'), so the source lane is x' = x + 3y, y' = x
+        let (src_x, src_y) = ((x + 3 * y) % 5, x);
+        let offset = ROTATION_OFFSETS[src_x][src_y] as usize;
+
+        self.theta[state_bit(src_x, src_y, (z + 64 - offset) % 64)].expr()
+    }
+
+    /// Returns the expressions of the state bits after the round, indexed as in `state_bit`.
+    pub fn output(&self) -> Vec<PIR<F>> {
+        let mut output = Vec::with_capacity(KECCAK_STATE_BITS);
+
+        for y in 0..5 {
+            for x in 0..5 {
+                for z in 0..64 {
+                    let a = self.pi(x, y, z);
+                    let b = self.pi((x + 1) % 5, y, z);
+                    let c = self.pi((x + 2) % 5, y, z);
+                    let mut bit = xor(a, (1u64.expr() - b) * c);
+
+                    if x == 0 && y == 0 {
+                        if let Some(i) = ROUND_CONSTANT_BITS.iter().position(|rc| *rc == z) {
+                            bit = xor(bit, self.round_constant[i].expr());
+                        }
+                    }
+
+                    output.push(bit);
+                }
+            }
+        }
+
+        output
+    }
+}
+
+impl<F: Field + From<u64> + Hash> KeccakRound<F> {
+    /// Assigns the internal signals of the round and returns the state after the round.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, state: &[u64; 25], round: usize) -> [u64; 25] {
+        let values = keccak_round(state, round);
+
+        for x in 0..5 {
+            for z in 0..64 {
+                ctx.assign(self.c[x * 64 + z], F::from((values.c[x] >> z) & 1));
+            }
+        }
+        for (signal, bit) in self.theta.iter().zip(lanes_to_bits(&values.theta)) {
+            ctx.assign(*signal, F::from(bit as u64));
+        }
+        for (signal, z) in self.round_constant.iter().zip(ROUND_CONSTANT_BITS.iter()) {
+            ctx.assign(*signal, F::from((ROUND_CONSTANTS[round] >> z) & 1));
+        }
+
+        values.out
+    }
+}
+
+/// Defines a reusable sub-circuit that computes Keccak-f[1600] on the state given as trace
+/// argument. It has one step per round followed by a final step holding the permuted state, so the
+/// number of steps is fixed to 25. The first round is a step type of its own, which constrains the
+/// round to 0 and the input state bits to be boolean. The state is kept in forward signals, which
+/// are returned so the caller can expose them or look them up from other sub-circuits.
+pub fn keccak_f_circuit<F: Field + From<u64> + Hash>(
+    ctx: &mut CircuitContext<F, [u64; 25]>,
+    round_constants_table: LookupTable,
+) -> Vec<Queriable<F>> {
+    let state: Vec<Queriable<F>> = (0..KECCAK_STATE_BITS)
+        .map(|i| ctx.forward(format!("state[{}][{}]", i / 64, i % 64).as_str()))
+        .collect();
+    let round = ctx.forward("round");
+
+    let round_step_def = |ctx: &mut CircuitContext<F, [u64; 25]>, name: &str, first: bool| {
+        let state = state.clone();
+
+        ctx.step_type_def(name, move |ctx| {
+            let gadget = KeccakRound::new(ctx, "round");
+            let setup_gadget = gadget.clone();
+            let setup_state = state.clone();
+
+            ctx.setup(move |ctx| {
+                if first {
+                    ctx.constr(eq(round, 0u64));
+                    // the following states are xors of boolean signals, so they are boolean too
+                    for (i, bit) in setup_state.iter().enumerate() {
+                        ctx.constr(annotate(
+                            format!("state[{}][{}] is boolean", i / 64, i % 64),
+                            *bit * (1u64.expr() - *bit),
+                            Typing::AntiBooly,
+                        ));
+                    }
+                }
+
+                setup_gadget.setup(ctx, &setup_state, round.expr(), round_constants_table);
+
+                for (bit, output) in setup_state.iter().zip(setup_gadget.output()) {
+                    ctx.transition(eq(bit.next(), output));
+                }
+                ctx.transition(eq(round + 1, round.next()));
+            });
+
+            ctx.wg(move |ctx, (values, index): ([u64; 25], usize)| {
+                for (signal, bit) in state.iter().zip(lanes_to_bits(&values)) {
+                    ctx.assign(*signal, F::from(bit as u64));
+                }
+                ctx.assign(round, F::from(index as u64));
+                gadget.wg(ctx, &values, index);
+            })
+        })
+    };
+    let first_round_step = round_step_def(ctx, "keccak first round", true);
+    let round_step = round_step_def(ctx, "keccak round", false);
+
+    let output_step = ctx.step_type_def("keccak output", |ctx| {
+        ctx.setup(move |ctx| {
+            ctx.constr(eq(round, KECCAK_ROUNDS as u64));
+            // no step can follow the output, as the round after it is not in the round constants
+            // table nor the round of an output step
+            ctx.transition(eq(round + 1, round.next()));
+        });
+
+        let wg_state = state.clone();
+        ctx.wg(move |ctx, values: [u64; 25]| {
+            for (signal, bit) in wg_state.iter().zip(lanes_to_bits(&values)) {
+                ctx.assign(*signal, F::from(bit as u64));
+            }
+            ctx.assign(round, F::from(KECCAK_ROUNDS as u64));
+        })
+    });
+
+    ctx.pragma_first_step(&first_round_step);
+    ctx.pragma_last_step(&output_step);
+    ctx.pragma_num_steps(KECCAK_ROUNDS + 1);
+
+    ctx.trace(move |ctx, mut values| {
+        for index in 0..KECCAK_ROUNDS {
+            let step = if index == 0 {
+                &first_round_step
+            } else {
+                &round_step
+            };
+            ctx.add(step, (values, index));
+            values = keccak_round(&values, index).out;
+        }
+        ctx.add(&output_step, values);
+    });
+
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        frontend::dsl::circuit,
+        sbpir::{
+            checker::{Checker, ViolationKind},
+            SBPIR,
+        },
+        wit_gen::{TraceGenerator, TraceWitness},
+    };
+
+    #[test]
+    fn test_keccak_f() {
+        let mut state = [0u64; 25];
+
+        keccak_f(&mut state);
+
+        assert_eq!(state[0], 0xf1258f7940e1dde7);
+        assert_eq!(state[1], 0x84d5ccf933c0478a);
+        assert_eq!(state[24], 0xeaf1ff7b5ceca249);
+    }
+
+    #[test]
+    fn test_keccak_f_circuit() {
+        let mut input = [0u64; 25];
+        for (i, lane) in input.iter_mut().enumerate() {
+            *lane = (i as u64 + 1).wrapping_mul(0x9e3779b97f4a7c15);
+        }
+        let mut expected = input;
+        keccak_f(&mut expected);
+
+        let circuit: SBPIR<Fr, [u64; 25]> = circuit("keccak", |ctx| {
+            let table = keccak_round_constants_table(ctx);
+            keccak_f_circuit(ctx, table);
+        });
+
+        let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), 25).generate(input);
+        assert_eq!(trace.step_instances.len(), 25);
+
+        for (i, step_instance) in trace.step_instances.iter().enumerate() {
+            let step_type = &circuit.step_types[&step_instance.step_type_uuid];
+            let mut assignments: HashMap<Queriable<Fr>, Fr> = step_instance.assignments.clone();
+            if let Some(next) = trace.step_instances.get(i + 1) {
+                for (queriable, value) in next.assignments.iter() {
+                    if let Queriable::Forward(signal, false) = queriable {
+                        assignments.insert(Queriable::Forward(*signal, true), *value);
+                    }
+                }
+            }
+
+            for constraint in step_type.constraints.iter() {
+                assert_eq!(constraint.expr.eval(&assignments), Some(Fr::ZERO));
+            }
+            if i + 1 < trace.step_instances.len() {
+                for constraint in step_type.transition_constraints.iter() {
+                    assert_eq!(constraint.expr.eval(&assignments), Some(Fr::ZERO));
+                }
+            }
+        }
+
+        let last = &trace.step_instances[24].assignments;
+        for (signal, bit) in circuit.forward_signals[..64]
+            .iter()
+            .zip(lanes_to_bits(&expected))
+        {
+            assert_eq!(
+                last[&Queriable::Forward(*signal, false)],
+                Fr::from(bit as u64)
+            );
+        }
+    }
+
+    #[test]
+    fn test_keccak_f_circuit_forged() {
+        let circuit: SBPIR<Fr, [u64; 25]> = circuit("keccak", |ctx| {
+            let table = keccak_round_constants_table(ctx);
+            keccak_f_circuit(ctx, table);
+        });
+        let trace = |input| TraceGenerator::new(circuit.trace.clone().unwrap(), 25).generate(input);
+        let checker = Checker::new(&circuit);
+        let found = |witness: &TraceWitness<Fr>| -> Vec<(usize, ViolationKind, String)> {
+            checker
+                .check(witness)
+                .into_iter()
+                .map(|violation| (violation.step, violation.kind, violation.annotation))
+                .collect()
+        };
+
+        let honest = trace([0u64; 25]);
+        assert!(checker.is_satisfied(&honest));
+
+        // the last rounds of another state, an output step in the middle, and the last rounds of
+        // the permutation, which skip the first rounds
+        let other = trace([1u64; 25]);
+        let forged = TraceWitness {
+            step_instances: other.step_instances[14..]
+                .iter()
+                .chain(honest.step_instances[11..].iter())
+                .cloned()
+                .collect(),
+        };
+        assert_eq!(forged.step_instances.len(), 25);
+        let violations = found(&forged);
+        assert!(violations
+            .iter()
+            .any(|(step, kind, _)| (*step, *kind) == (0, ViolationKind::FirstStep)));
+        assert!(violations
+            .iter()
+            .any(|(step, kind, _)| (*step, *kind) == (10, ViolationKind::Transition)));
+
+        let mut forged = honest.clone();
+        let bit = Queriable::Forward(circuit.forward_signals[0], false);
+        forged.step_instances[0].assign(bit, Fr::from(2));
+        assert!(found(&forged).contains(&(
+            0,
+            ViolationKind::Constraint,
+            "state[0][0] is boolean".to_string()
+        )));
+
+        let mut forged = honest;
+        let round = Queriable::Forward(circuit.forward_signals[KECCAK_STATE_BITS], false);
+        forged.step_instances[0].assign(round, Fr::ONE);
+        assert!(found(&forged)
+            .iter()
+            .any(|(step, kind, _)| (*step, *kind) == (0, ViolationKind::Constraint)));
+    }
+}
@@ -0,0 +1,572 @@
+use std::{hash::Hash, rc::Rc};
+
+use crate::{
+    field::Field,
+    frontend::dsl::{
+        cb::{eq, Constraint},
+        lb::LookupTable,
+        StepTypeContext, StepTypeSetupContext,
+    },
+    poly::{Expr, ToExpr},
+    sbpir::{query::Queriable, PIR},
+    wit_gen::StepInstance,
+};
+
+use super::{
+    keccak::{keccak_f, lanes_to_bits, state_bit, KeccakRound, KECCAK_ROUNDS, KECCAK_STATE_BITS},
+    poseidon::{Poseidon, PoseidonParams},
+};
+
+/// Two-to-one hash used by `MerklePath`. A node is given in the circuit by `node_width` field
+/// elements, and the hash of two nodes is computed by an in-step gadget created once per level.
+pub trait MerkleHasher<F: Field + From<u64> + Hash>: Clone {
+    /// Native value of a node.
+    type Node: Clone;
+    type Gadget: Clone;
+
+    fn node_width(&self) -> usize;
+
+    /// Returns the field elements of a node.
+    fn node_values(&self, node: &Self::Node) -> Vec<F>;
+
+    /// Hashes two nodes natively.
+    fn hash(&self, left: &Self::Node, right: &Self::Node) -> Self::Node;
+
+    fn new_gadget(&self, ctx: &mut StepTypeContext<F>, name: &str) -> Self::Gadget;
+
+    /// Constrains the hash of the nodes held by the `left` and `right` signals.
+    #[track_caller]
+    fn setup_gadget(
+        &self,
+        gadget: &Self::Gadget,
+        ctx: &mut StepTypeSetupContext<F>,
+        left: &[Queriable<F>],
+        right: &[Queriable<F>],
+    );
+
+    /// Returns the expressions of the parent node computed by the gadget.
+    fn gadget_output(&self, gadget: &Self::Gadget) -> Vec<PIR<F>>;
+
+    fn wg_gadget(
+        &self,
+        gadget: &Self::Gadget,
+        ctx: &mut StepInstance<F>,
+        left: &Self::Node,
+        right: &Self::Node,
+    ) -> Self::Node;
+}
+
+/// Poseidon hash of the two nodes, see `PoseidonParams::hash`. Nodes are single field elements and
+/// the width of the permutation must be at least 3.
+#[derive(Clone)]
+pub struct PoseidonHasher<F> {
+    params: Rc<PoseidonParams<F>>,
+}
+
+impl<F: Field> PoseidonHasher<F> {
+    pub fn new(params: Rc<PoseidonParams<F>>) -> PoseidonHasher<F> {
+        assert!(
+            params.t >= 3,
+            "poseidon width must be at least 3 to hash two nodes"
+        );
+
+        PoseidonHasher { params }
+    }
+}
+
+impl<F: Field + From<u64> + Hash> MerkleHasher<F> for PoseidonHasher<F> {
+    type Node = F;
+    type Gadget = Poseidon<F>;
+
+    fn node_width(&self) -> usize {
+        1
+    }
+
+    fn node_values(&self, node: &F) -> Vec<F> {
+        vec![*node]
+    }
+
+    fn hash(&self, left: &F, right: &F) -> F {
+        self.params.hash(&[*left, *right])
+    }
+
+    fn new_gadget(&self, ctx: &mut StepTypeContext<F>, name: &str) -> Poseidon<F> {
+        Poseidon::new(ctx, name, self.params.clone())
+    }
+
+    #[track_caller]
+    fn setup_gadget(
+        &self,
+        gadget: &Poseidon<F>,
+        ctx: &mut StepTypeSetupContext<F>,
+        left: &[Queriable<F>],
+        right: &[Queriable<F>],
+    ) {
+        gadget.setup_hash(ctx, &[left[0], right[0]]);
+    }
+
+    fn gadget_output(&self, gadget: &Poseidon<F>) -> Vec<PIR<F>> {
+        vec![gadget.hash_output().expr()]
+    }
+
+    fn wg_gadget(&self, gadget: &Poseidon<F>, ctx: &mut StepInstance<F>, left: &F, right: &F) -> F {
+        gadget.wg_hash(ctx, &[*left, *right])
+    }
+}
+
+/// Keccak-256 of the 64 bytes of the two nodes, as in Ethereum Merkle trees. Nodes are the four
+/// little-endian 64-bit lanes of the 32-byte hashes.
+pub fn keccak_node_hash(left: &[u64; 4], right: &[u64; 4]) -> [u64; 4] {
+    let mut state = keccak_node_state(left, right);
+    keccak_f(&mut state);
+
+    [state[0], state[1], state[2], state[3]]
+}
+
+/// Absorbs the 64 bytes into the rate with the Keccak padding, which fits in a single block.
+fn keccak_node_state(left: &[u64; 4], right: &[u64; 4]) -> [u64; 25] {
+    let mut state = [0u64; 25];
+    state[..4].copy_from_slice(left);
+    state[4..8].copy_from_slice(right);
+    state[8] = 0x01;
+    state[16] = 0x80 << 56;
+
+    state
+}
+
+/// Keccak-256 of two nodes with all the rounds in the step, see `keccak_node_hash`. It is wide, as
+/// every round adds almost 3600 signals, so it is meant for shallow trees or dedicated step types.
+#[derive(Clone)]
+pub struct KeccakHasher {
+    round_constants_table: LookupTable,
+}
+
+impl KeccakHasher {
+    /// The round constants are looked up in a table created by `keccak_round_constants_table`.
+    pub fn new(round_constants_table: LookupTable) -> KeccakHasher {
+        KeccakHasher {
+            round_constants_table,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct KeccakNodeGadget<F> {
+    input: Vec<Queriable<F>>,
+    rounds: Vec<KeccakRound<F>>,
+    /// State bits between rounds.
+    states: Vec<Vec<Queriable<F>>>,
+}
+
+impl<F: Field + From<u64> + Hash> MerkleHasher<F> for KeccakHasher {
+    type Node = [u64; 4];
+    type Gadget = KeccakNodeGadget<F>;
+
+    fn node_width(&self) -> usize {
+        4
+    }
+
+    fn node_values(&self, node: &[u64; 4]) -> Vec<F> {
+        node.iter().map(|lane| F::from(*lane)).collect()
+    }
+
+    fn hash(&self, left: &[u64; 4], right: &[u64; 4]) -> [u64; 4] {
+        keccak_node_hash(left, right)
+    }
+
+    fn new_gadget(&self, ctx: &mut StepTypeContext<F>, name: &str) -> KeccakNodeGadget<F> {
+        let input = (0..KECCAK_STATE_BITS)
+            .map(|i| ctx.internal(format!("{}.input[{}][{}]", name, i / 64, i % 64).as_str()))
+            .collect();
+        let rounds = (0..KECCAK_ROUNDS)
+            .map(|round| KeccakRound::new(ctx, format!("{}.round[{}]", name, round).as_str()))
+            .collect();
+        let states = (0..KECCAK_ROUNDS - 1)
+            .map(|round| {
+                (0..KECCAK_STATE_BITS)
+                    .map(|i| {
+                        ctx.internal(
+                            format!("{}.state[{}][{}][{}]", name, round, i / 64, i % 64).as_str(),
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+
+        KeccakNodeGadget {
+            input,
+            rounds,
+            states,
+        }
+    }
+
+    #[track_caller]
+    fn setup_gadget(
+        &self,
+        gadget: &KeccakNodeGadget<F>,
+        ctx: &mut StepTypeSetupContext<F>,
+        left: &[Queriable<F>],
+        right: &[Queriable<F>],
+    ) {
+        let padding = lanes_to_bits(&keccak_node_state(&[0; 4], &[0; 4]));
+
+        for (lane, node) in left.iter().chain(right.iter()).enumerate() {
+            let bits = &gadget.input[lane * 64..(lane + 1) * 64];
+            for bit in bits.iter() {
+                ctx.constr(eq(*bit * (1u64.expr() - *bit), 0u64));
+            }
+            ctx.constr(eq(*node, lane_expr(bits.iter().map(|bit| bit.expr()))));
+        }
+        for (bit, value) in gadget.input.iter().zip(padding).skip(8 * 64) {
+            ctx.constr(eq(*bit, value as u64));
+        }
+
+        let mut input = &gadget.input;
+        for (round, gadget_round) in gadget.rounds.iter().enumerate() {
+            gadget_round.setup(
+                ctx,
+                input,
+                (round as u64).expr(),
+                self.round_constants_table,
+            );
+
+            if let Some(state) = gadget.states.get(round) {
+                for (bit, output) in state.iter().zip(gadget_round.output()) {
+                    ctx.constr(eq(*bit, output));
+                }
+                input = state;
+            }
+        }
+    }
+
+    fn gadget_output(&self, gadget: &KeccakNodeGadget<F>) -> Vec<PIR<F>> {
+        let output = gadget.rounds.last().expect("keccak rounds").output();
+
+        (0..4)
+            .map(|x| lane_expr((0..64).map(|z| output[state_bit(x, 0, z)].clone())))
+            .collect()
+    }
+
+    fn wg_gadget(
+        &self,
+        gadget: &KeccakNodeGadget<F>,
+        ctx: &mut StepInstance<F>,
+        left: &[u64; 4],
+        right: &[u64; 4],
+    ) -> [u64; 4] {
+        let mut state = keccak_node_state(left, right);
+        for (signal, bit) in gadget.input.iter().zip(lanes_to_bits(&state)) {
+            ctx.assign(*signal, F::from(bit as u64));
+        }
+
+        for (round, gadget_round) in gadget.rounds.iter().enumerate() {
+            state = gadget_round.wg(ctx, &state, round);
+
+            if let Some(signals) = gadget.states.get(round) {
+                for (signal, bit) in signals.iter().zip(lanes_to_bits(&state)) {
+                    ctx.assign(*signal, F::from(bit as u64));
+                }
+            }
+        }
+
+        [state[0], state[1], state[2], state[3]]
+    }
+}
+
+fn lane_expr<F: Field + From<u64>, I: Iterator<Item = PIR<F>>>(bits: I) -> PIR<F> {
+    let mut coeff = F::ONE;
+
+    bits.fold(0u64.expr(), |acc, bit| {
+        let term = Expr::Const(coeff) * bit;
+        coeff = coeff + coeff;
+        acc + term
+    })
+}
+
+/// In-step verification of a Merkle authentication path of `depth` levels. For every level a
+/// direction bit tells whether the current node is the right child, the children are ordered into
+/// internal signals and hashed with the gadget of the hasher. The recomputed root is returned as
+/// expressions by `root`, to be compared with the expected one by the caller.
+#[derive(Clone)]
+pub struct MerklePath<F: Field + From<u64> + Hash, H: MerkleHasher<F>> {
+    hasher: H,
+    directions: Vec<Queriable<F>>,
+    siblings: Vec<Vec<Queriable<F>>>,
+    lefts: Vec<Vec<Queriable<F>>>,
+    rights: Vec<Vec<Queriable<F>>>,
+    gadgets: Vec<H::Gadget>,
+}
+
+impl<F: Field + From<u64> + Hash, H: MerkleHasher<F>> MerklePath<F, H> {
+    pub fn new(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        hasher: H,
+        depth: usize,
+    ) -> MerklePath<F, H> {
+        assert!(depth > 0, "merkle path needs at least one level");
+
+        let width = hasher.node_width();
+        let mut nodes = |node: &str| -> Vec<Vec<Queriable<F>>> {
+            (0..depth)
+                .map(|level| {
+                    (0..width)
+                        .map(|i| {
+                            ctx.internal(format!("{}.{}[{}][{}]", name, node, level, i).as_str())
+                        })
+                        .collect()
+                })
+                .collect()
+        };
+        let siblings = nodes("sibling");
+        let lefts = nodes("left");
+        let rights = nodes("right");
+        let directions = (0..depth)
+            .map(|level| ctx.internal(format!("{}.direction[{}]", name, level).as_str()))
+            .collect();
+        let gadgets = (0..depth)
+            .map(|level| hasher.new_gadget(ctx, format!("{}.hash[{}]", name, level).as_str()))
+            .collect();
+
+        MerklePath {
+            hasher,
+            directions,
+            siblings,
+            lefts,
+            rights,
+            gadgets,
+        }
+    }
+
+    /// Constrains the path from `leaf`, given as `node_width` expressions.
+    #[track_caller]
+    pub fn setup<V: Into<Constraint<F>> + Clone>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        leaf: &[V],
+    ) {
+        assert_eq!(
+            leaf.len(),
+            self.hasher.node_width(),
+            "wrong merkle node width"
+        );
+
+        let mut node: Vec<PIR<F>> = leaf.iter().map(|v| v.clone().into().expr).collect();
+        for level in 0..self.depth() {
+            let direction = self.directions[level];
+            ctx.constr(eq(direction * (1u64.expr() - direction), 0u64));
+
+            for (((current, sibling), left), right) in node
+                .iter()
+                .zip(self.siblings[level].iter())
+                .zip(self.lefts[level].iter())
+                .zip(self.rights[level].iter())
+            {
+                ctx.constr(eq(
+                    *left,
+                    current.clone() + direction * (*sibling - current.clone()),
+                ));
+                ctx.constr(eq(*right, current.clone() + *sibling - *left));
+            }
+
+            let gadget = &self.gadgets[level];
+            self.hasher
+                .setup_gadget(gadget, ctx, &self.lefts[level], &self.rights[level]);
+            node = self.hasher.gadget_output(gadget);
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.directions.len()
+    }
+
+    /// Returns the expressions of the recomputed root.
+    pub fn root(&self) -> Vec<PIR<F>> {
+        self.hasher
+            .gadget_output(self.gadgets.last().expect("at least one level"))
+    }
+
+    /// Returns the expression of the leaf index given by the direction bits.
+    pub fn index(&self) -> PIR<F> {
+        lane_expr(self.directions.iter().map(|d| d.expr()))
+    }
+
+    /// Assigns the path for the leaf at `index` with the given siblings, from the leaf level up,
+    /// and returns the root.
+    pub fn wg(
+        &self,
+        ctx: &mut StepInstance<F>,
+        leaf: &H::Node,
+        index: usize,
+        siblings: &[H::Node],
+    ) -> H::Node {
+        assert_eq!(
+            siblings.len(),
+            self.depth(),
+            "wrong number of merkle siblings"
+        );
+
+        let mut node = leaf.clone();
+        for (level, sibling) in siblings.iter().enumerate() {
+            let is_right = (index >> level) & 1 == 1;
+            let (left, right) = if is_right {
+                (sibling.clone(), node)
+            } else {
+                (node, sibling.clone())
+            };
+
+            ctx.assign(self.directions[level], F::from(is_right as u64));
+            for (signals, value) in [
+                (&self.siblings[level], sibling),
+                (&self.lefts[level], &left),
+                (&self.rights[level], &right),
+            ] {
+                for (signal, value) in signals.iter().zip(self.hasher.node_values(value)) {
+                    ctx.assign(*signal, value);
+                }
+            }
+
+            node = self
+                .hasher
+                .wg_gadget(&self.gadgets[level], ctx, &left, &right);
+        }
+
+        node
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        frontend::dsl::circuit, sbpir::SBPIR, stdlib::keccak::keccak_round_constants_table,
+        wit_gen::TraceGenerator,
+    };
+
+    fn satisfied<TraceArgs>(circuit: &SBPIR<Fr, TraceArgs>, args: TraceArgs) -> bool {
+        let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), 1).generate(args);
+        let step_instance = &trace.step_instances[0];
+        let step_type = &circuit.step_types[&step_instance.step_type_uuid];
+
+        step_type
+            .constraints
+            .iter()
+            .all(|c| c.expr.eval(&step_instance.assignments) == Some(Fr::ZERO))
+    }
+
+    fn toy_params() -> PoseidonParams<Fr> {
+        let t = 3;
+        let round_constants = (0..t * (4 + 3)).map(|i| Fr::from(i as u64 + 1)).collect();
+        let mds = (0..t)
+            .map(|i| (0..t).map(|j| Fr::from((i + j) as u64 + 1).mi()).collect())
+            .collect();
+
+        PoseidonParams::new(t, 4, 3, round_constants, mds)
+    }
+
+    #[test]
+    fn test_poseidon_merkle_path() {
+        let hasher = PoseidonHasher::new(Rc::new(toy_params()));
+        let leaves: Vec<Fr> = (0..8).map(|i| Fr::from(i * 11 + 3)).collect();
+        let level_1: Vec<Fr> = leaves
+            .chunks(2)
+            .map(|c| hasher.hash(&c[0], &c[1]))
+            .collect();
+        let level_2: Vec<Fr> = level_1
+            .chunks(2)
+            .map(|c| hasher.hash(&c[0], &c[1]))
+            .collect();
+        let root = hasher.hash(&level_2[0], &level_2[1]);
+
+        // leaf 5 is the right child of level_1[2], which is the left child of level_2[1]
+        let siblings = vec![leaves[4], level_1[3], level_2[0]];
+
+        let setup_hasher = hasher.clone();
+        let circuit: SBPIR<Fr, (Fr, Vec<Fr>)> = circuit("merkle", |ctx| {
+            let leaf = ctx.forward("leaf");
+            let expected_root = ctx.forward("root");
+
+            let step = ctx.step_type_def("verify", |ctx| {
+                let path = MerklePath::new(ctx, "path", setup_hasher.clone(), 3);
+
+                let setup_path = path.clone();
+                ctx.setup(move |ctx| {
+                    setup_path.setup(ctx, &[leaf]);
+                    ctx.constr(eq(expected_root, setup_path.root()[0].clone()));
+                    ctx.constr(eq(setup_path.index(), 5u64));
+                });
+
+                ctx.wg(move |ctx, (leaf_value, siblings): (Fr, Vec<Fr>)| {
+                    ctx.assign(leaf, leaf_value);
+                    ctx.assign(expected_root, root);
+                    path.wg(ctx, &leaf_value, 5, &siblings);
+                })
+            });
+
+            ctx.pragma_num_steps(1);
+            ctx.trace(move |ctx, args| ctx.add(&step, args));
+        });
+
+        assert!(satisfied(&circuit, (leaves[5], siblings.clone())));
+        assert!(!satisfied(&circuit, (leaves[6], siblings.clone())));
+
+        let mut wrong_siblings = siblings;
+        wrong_siblings[1] += Fr::ONE;
+        assert!(!satisfied(&circuit, (leaves[5], wrong_siblings)));
+    }
+
+    #[test]
+    fn test_keccak_node_hash() {
+        // keccak256 of 64 zero bytes
+        assert_eq!(
+            keccak_node_hash(&[0; 4], &[0; 4]),
+            [
+                0xcdd3f776b62832ad,
+                0x96f1173f44a58442,
+                0xb2400ab391e4362b,
+                0xb55fba97e5495840
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keccak_merkle_path() {
+        let leaf = [1u64, 2, 3, 4];
+        let sibling = [5u64, 6, 7, 8];
+        let root = keccak_node_hash(&sibling, &leaf);
+
+        let circuit: SBPIR<Fr, [u64; 4]> = circuit("keccak merkle", |ctx| {
+            let table = keccak_round_constants_table(ctx);
+            let leaf_signals: Vec<Queriable<Fr>> = (0..4)
+                .map(|i| ctx.forward(format!("leaf[{}]", i).as_str()))
+                .collect();
+
+            let step = ctx.step_type_def("verify", |ctx| {
+                let path = MerklePath::new(ctx, "path", KeccakHasher::new(table), 1);
+
+                let setup_path = path.clone();
+                let setup_leaf = leaf_signals.clone();
+                ctx.setup(move |ctx| {
+                    setup_path.setup(ctx, &setup_leaf);
+                    for (lane, expected) in setup_path.root().into_iter().zip(root) {
+                        ctx.constr(eq(lane, expected));
+                    }
+                });
+
+                ctx.wg(move |ctx, leaf_value: [u64; 4]| {
+                    for (signal, lane) in leaf_signals.iter().zip(leaf_value) {
+                        ctx.assign(*signal, Fr::from(lane));
+                    }
+                    path.wg(ctx, &leaf_value, 1, &[sibling]);
+                })
+            });
+
+            ctx.trace(move |ctx, args| ctx.add(&step, args));
+        });
+
+        assert!(satisfied(&circuit, leaf));
+        assert!(!satisfied(&circuit, [1, 2, 3, 5]));
+    }
+}
@@ -0,0 +1,195 @@
+use std::{hash::Hash, rc::Rc};
+
+use crate::{
+    field::Field,
+    frontend::dsl::{
+        cb::{eq, Constraint},
+        StepTypeContext, StepTypeSetupContext,
+    },
+    poly::Expr,
+    sbpir::{query::Queriable, PIR},
+    wit_gen::StepInstance,
+};
+
+/// Parameters of a MiMC block cipher over the field `F`. The round constants are field specific and
+/// must be provided by the user. MiMC7, the usual instance over the BN254 scalar field, uses 91
+/// rounds with the exponent 7 and a zero first round constant.
+#[derive(Clone, Debug)]
+pub struct MimcParams<F> {
+    pub round_constants: Vec<F>,
+    /// Exponent of each round, it must be coprime with `p - 1` for the round to be a permutation.
+    pub exponent: u32,
+}
+
+impl<F: Field> MimcParams<F> {
+    /// Creates the parameters for MiMC7 with the given round constants.
+    pub fn new(round_constants: Vec<F>) -> MimcParams<F> {
+        Self::with_exponent(round_constants, 7)
+    }
+
+    pub fn with_exponent(round_constants: Vec<F>, exponent: u32) -> MimcParams<F> {
+        assert!(!round_constants.is_empty(), "mimc needs at least one round");
+
+        MimcParams {
+            round_constants,
+            exponent,
+        }
+    }
+
+    pub fn num_rounds(&self) -> usize {
+        self.round_constants.len()
+    }
+
+    /// Encrypts `x` with the key `k` natively, computing `x_{i+1} = (x_i + k + c_i)^e` for every
+    /// round and returning `x_n + k`.
+    pub fn encrypt(&self, x: F, k: F) -> F {
+        *self.rounds(x, k).last().expect("at least one round") + k
+    }
+
+    fn rounds(&self, x: F, k: F) -> Vec<F> {
+        let mut x = x;
+
+        self.round_constants
+            .iter()
+            .map(|c| {
+                x = (x + k + c).pow([self.exponent as u64]);
+                x
+            })
+            .collect()
+    }
+}
+
+/// In-step gadget that constrains a MiMC encryption. It adds one internal signal per round plus one
+/// for the output, and the constraints have the degree of the exponent.
+#[derive(Clone)]
+pub struct Mimc<F> {
+    params: Rc<MimcParams<F>>,
+    rounds: Vec<Queriable<F>>,
+    output: Queriable<F>,
+}
+
+impl<F: Field + From<u64>> Mimc<F> {
+    /// Adds the round signals to the step type, annotated with the given name.
+    pub fn new(ctx: &mut StepTypeContext<F>, name: &str, params: Rc<MimcParams<F>>) -> Mimc<F> {
+        let rounds = (0..params.num_rounds())
+            .map(|i| ctx.internal(format!("{}.x[{}]", name, i + 1).as_str()))
+            .collect();
+        let output = ctx.internal(format!("{}.out", name).as_str());
+
+        Mimc {
+            params,
+            rounds,
+            output,
+        }
+    }
+
+    /// Constrains the encryption of the message `x` with the key `k`. The result is `output`.
+    #[track_caller]
+    pub fn setup<X: Into<Constraint<F>>, K: Into<Constraint<F>>>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        x: X,
+        k: K,
+    ) {
+        let k: PIR<F> = k.into().expr;
+        let mut x: PIR<F> = x.into().expr;
+
+        for (round, c) in self.rounds.iter().zip(self.params.round_constants.iter()) {
+            ctx.constr(eq(
+                *round,
+                Expr::Pow(
+                    Box::new(x + k.clone() + Expr::Const(*c)),
+                    self.params.exponent,
+                ),
+            ));
+            x = (*round).into();
+        }
+
+        ctx.constr(eq(self.output, x + k));
+    }
+
+    /// Returns the signal holding the encryption result.
+    pub fn output(&self) -> Queriable<F> {
+        self.output
+    }
+}
+
+impl<F: Field + From<u64> + Hash> Mimc<F> {
+    /// Assigns the round signals for the encryption of `x` with the key `k` and returns the result.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, x: F, k: F) -> F {
+        let values = self.params.rounds(x, k);
+
+        for (signal, value) in self.rounds.iter().zip(values.iter()) {
+            ctx.assign(*signal, *value);
+        }
+
+        let output = *values.last().expect("at least one round") + k;
+        ctx.assign(self.output, output);
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{frontend::dsl::circuit, sbpir::SBPIR, wit_gen::TraceGenerator};
+
+    fn toy_params() -> MimcParams<Fr> {
+        MimcParams::new((0..10).map(|i| Fr::from(i * 7919)).collect())
+    }
+
+    #[test]
+    fn test_mimc_encrypt() {
+        let params = MimcParams::new(vec![Fr::ZERO, Fr::from(2)]);
+
+        // (((3 + 5)^7 + 5 + 2)^7) + 5
+        let expected = (Fr::from(8).pow([7]) + Fr::from(7)).pow([7]) + Fr::from(5);
+        assert_eq!(params.encrypt(Fr::from(3), Fr::from(5)), expected);
+        assert_ne!(
+            toy_params().encrypt(Fr::from(1), Fr::from(2)),
+            toy_params().encrypt(Fr::from(2), Fr::from(2))
+        );
+    }
+
+    #[test]
+    fn test_mimc_gadget() {
+        let params = Rc::new(toy_params());
+        let expected = params.encrypt(Fr::from(11), Fr::from(13));
+
+        let circuit: SBPIR<Fr, ()> = circuit("mimc", |ctx| {
+            let x = ctx.forward("x");
+            let k = ctx.forward("k");
+
+            let encrypt = ctx.step_type_def("encrypt", |ctx| {
+                let mimc = Mimc::new(ctx, "mimc", params.clone());
+                let setup_mimc = mimc.clone();
+
+                ctx.setup(move |ctx| setup_mimc.setup(ctx, x, k));
+
+                ctx.wg(move |ctx, _: ()| {
+                    ctx.assign(x, Fr::from(11));
+                    ctx.assign(k, Fr::from(13));
+                    mimc.wg(ctx, Fr::from(11), Fr::from(13));
+                })
+            });
+
+            ctx.trace(move |ctx, _| ctx.add(&encrypt, ()));
+        });
+
+        let step_type = circuit.step_types.values().next().unwrap();
+        assert_eq!(step_type.signals.len(), 11);
+
+        let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), 1).generate(());
+        let assignments = &trace.step_instances[0].assignments;
+
+        for constraint in step_type.constraints.iter() {
+            assert_eq!(constraint.expr.eval(assignments), Some(Fr::ZERO));
+        }
+
+        let output = Queriable::Internal(step_type.signals[10]);
+        assert_eq!(assignments[&output], expected);
+    }
+}
@@ -0,0 +1,457 @@
+use std::hash::Hash;
+
+use ff::PrimeField;
+use num_bigint::BigUint;
+
+use crate::field::Field;
+
+use crate::{
+    frontend::dsl::{
+        cb::{eq, table, Constraint, Typing},
+        lb::LookupTable,
+        CircuitContext, StepTypeContext, StepTypeSetupContext,
+    },
+    poly::{Expr, ToExpr},
+    sbpir::{query::Queriable, PIR},
+    wit_gen::StepInstance,
+};
+
+pub mod accumulator;
+pub mod bitwise;
+pub mod blake2;
+pub mod ecc;
+pub mod ecdsa;
+pub mod keccak;
+pub mod merkle;
+pub mod mimc;
+pub mod modexp;
+pub mod nonnative;
+pub mod padding;
+pub mod pedersen;
+pub mod poseidon;
+pub mod rlc;
+pub mod rom;
+pub mod sha256;
+pub mod signed;
+pub mod sort;
+pub mod stack;
+pub mod u256;
+pub mod word;
+
+#[derive(Clone)]
+pub struct IsZero<F> {
+    value_inv: Queriable<F>,
+    is_zero_constraint: Constraint<F>,
+}
+
+impl<F: Field + From<u64>> IsZero<F> {
+    #[track_caller]
+    pub fn setup<V: Into<Constraint<F>>>(
+        ctx: &mut StepTypeContext<F>,
+        value: V,
+        value_inv: Queriable<F>,
+    ) -> IsZero<F> {
+        let value: Constraint<F> = value.into();
+        let is_zero_expression = 1.expr() - (value.expr.clone() * value_inv);
+
+        ctx.constr(value.expr.clone() * is_zero_expression.clone());
+
+        let is_zero_constraint = Constraint {
+            expr: is_zero_expression,
+            annotation: format!("is_zero({:?})", value),
+            typing: Typing::Boolean,
+        };
+
+        IsZero {
+            value_inv,
+            is_zero_constraint,
+        }
+    }
+
+    pub fn is_zero(&self) -> Constraint<F> {
+        self.is_zero_constraint.clone()
+    }
+}
+
+impl<F: Field + Eq + Hash> IsZero<F> {
+    pub fn wg<WGC>(&self, ctx: &mut StepInstance<F>, value: F) {
+        ctx.assign(self.value_inv, value.mi());
+    }
+}
+
+/// One-hot encoding of an index in `[0, size)` with a boolean signal per value, for opcodes and
+/// states. The signals are annotated as `name[i]`.
+#[derive(Clone)]
+pub struct OneHot<F> {
+    bits: Vec<Queriable<F>>,
+}
+
+impl<F: Field + From<u64>> OneHot<F> {
+    pub fn new(ctx: &mut StepTypeContext<F>, name: &str, size: usize) -> OneHot<F> {
+        assert!(size > 0, "one-hot encoding needs at least one value");
+
+        let bits = (0..size)
+            .map(|i| ctx.internal(format!("{}[{}]", name, i).as_str()))
+            .collect();
+
+        OneHot { bits }
+    }
+
+    /// Constrains the signals to be boolean with exactly one of them set.
+    #[track_caller]
+    pub fn setup(&self, ctx: &mut StepTypeSetupContext<F>) {
+        let mut sum: PIR<F> = 0u64.expr();
+        for bit in self.bits.iter() {
+            ctx.constr(eq(*bit * (1u64.expr() - *bit), 0u64));
+            sum = sum + *bit;
+        }
+        ctx.constr(eq(sum, 1u64));
+    }
+
+    pub fn size(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub fn bits(&self) -> &[Queriable<F>] {
+        &self.bits
+    }
+
+    /// Returns the signal that is set when the index is `index`.
+    pub fn bit(&self, index: usize) -> Queriable<F> {
+        self.bits[index]
+    }
+
+    /// Returns the expression `sum(i * bit[i])`, which is the encoded index.
+    pub fn decoded(&self) -> PIR<F> {
+        self.bits
+            .iter()
+            .enumerate()
+            .fold(0u64.expr(), |acc, (i, bit)| {
+                acc + Expr::Const(F::from(i as u64)) * *bit
+            })
+    }
+
+    /// Returns the expression `sum(bit[i] * values[i])`, which is the value at the encoded index.
+    pub fn select<V: Into<Constraint<F>> + Clone>(&self, values: &[V]) -> PIR<F> {
+        assert_eq!(values.len(), self.size(), "wrong number of values");
+
+        self.bits
+            .iter()
+            .zip(values.iter())
+            .fold(0u64.expr(), |acc, (bit, value)| {
+                acc + *bit * value.clone().into().expr
+            })
+    }
+}
+
+impl<F: Field + From<u64> + Hash> OneHot<F> {
+    /// Sets the signal of `index`. Panics if it is out of range.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, index: usize) {
+        assert!(
+            index < self.size(),
+            "index {} out of the one-hot encoding of size {}",
+            index,
+            self.size()
+        );
+
+        for (i, bit) in self.bits.iter().enumerate() {
+            ctx.assign(*bit, F::from((i == index) as u64));
+        }
+    }
+}
+
+/// Splits a value into `num_limbs` limbs of `limb_bits` bits each, in little-endian order. Every
+/// limb is range checked with a lookup and the limbs are constrained to recompose to the value.
+#[derive(Clone)]
+pub struct LimbDecomposition<F> {
+    limbs: Vec<Queriable<F>>,
+    limb_bits: u32,
+}
+
+impl<F: PrimeField> LimbDecomposition<F> {
+    /// Adds the limb internal signals to the step type. The limbs are annotated as `name[i]`.
+    /// Panics if a limb is wider than `MAX_RANGE_TABLE_BITS`, as no `range_table` could then range
+    /// check it, or if the limbs do not fit below the field modulus, as a value of `num_limbs *
+    /// limb_bits` bits could then recompose to the same field element as another one.
+    pub fn new(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        num_limbs: usize,
+        limb_bits: u32,
+    ) -> LimbDecomposition<F> {
+        assert!(num_limbs > 0, "limb decomposition needs at least one limb");
+        assert!(
+            limb_bits > 0 && limb_bits <= MAX_RANGE_TABLE_BITS,
+            "limb size must be between 1 and {} bits, the widest range table",
+            MAX_RANGE_TABLE_BITS
+        );
+        assert!(
+            (num_limbs as u64) * (limb_bits as u64) < F::NUM_BITS as u64,
+            "{} limbs of {} bits do not fit in a field of {} bits",
+            num_limbs,
+            limb_bits,
+            F::NUM_BITS
+        );
+
+        let limbs = (0..num_limbs)
+            .map(|i| ctx.internal(format!("{}[{}]", name, i).as_str()))
+            .collect();
+
+        LimbDecomposition { limbs, limb_bits }
+    }
+}
+
+impl<F: Field + From<u64>> LimbDecomposition<F> {
+    /// Constrains the limbs to recompose to `value` and looks up every limb in `range_table`, which
+    /// must contain all the values in `[0, 2^limb_bits)`. See `range_table`.
+    #[track_caller]
+    pub fn setup<V: Into<Constraint<F>>>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        value: V,
+        range_table: LookupTable,
+    ) {
+        ctx.constr(eq(value, self.recompose()));
+
+        for limb in self.limbs.iter() {
+            ctx.add_lookup(range_table.apply(*limb));
+        }
+    }
+
+    /// Returns the limb signals, least significant first.
+    pub fn limbs(&self) -> &[Queriable<F>] {
+        &self.limbs
+    }
+
+    pub fn limb(&self, index: usize) -> Queriable<F> {
+        self.limbs[index]
+    }
+
+    pub fn limb_bits(&self) -> u32 {
+        self.limb_bits
+    }
+
+    /// Returns the expression `sum(limb[i] * 2^(i * limb_bits))`.
+    pub fn recompose(&self) -> PIR<F> {
+        let base = F::from(2).pow([self.limb_bits as u64]);
+        let mut coeff = F::ONE;
+        let mut expr: PIR<F> = 0u64.expr();
+
+        for limb in self.limbs.iter() {
+            expr = expr + Expr::Const(coeff) * *limb;
+            coeff *= base;
+        }
+
+        expr
+    }
+}
+
+impl<F: Field + From<u64> + Hash> LimbDecomposition<F> {
+    /// Assigns the limbs of `value`. Panics if `value` does not fit in the limbs.
+    pub fn wg<V: Into<BigUint>>(&self, ctx: &mut StepInstance<F>, value: V) {
+        let mut value: BigUint = value.into();
+        let mask = (BigUint::from(1u64) << self.limb_bits) - 1u64;
+
+        for limb in self.limbs.iter() {
+            let limb_value = (&value & &mask).iter_u64_digits().next().unwrap_or(0);
+            ctx.assign(*limb, F::from(limb_value));
+            value >>= self.limb_bits;
+        }
+
+        assert!(
+            value == BigUint::from(0u64),
+            "value does not fit in {} limbs of {} bits",
+            self.limbs.len(),
+            self.limb_bits
+        );
+    }
+}
+
+/// Largest number of bits of `range_table`, whose `2^28` rows are already the most the halo2
+/// backend can prove with the bn256 trusted setup.
+pub const MAX_RANGE_TABLE_BITS: u32 = 28;
+
+/// Creates a lookup table containing all the values in `[0, 2^bits)`. It sets the number of steps
+/// of the circuit, so it is meant to be used in a dedicated fixed sub-circuit. Panics if `bits` is
+/// over `MAX_RANGE_TABLE_BITS`.
+pub fn range_table<F: Field + From<u64> + Hash, TraceArgs>(
+    ctx: &mut CircuitContext<F, TraceArgs>,
+    bits: u32,
+) -> LookupTable {
+    assert!(
+        bits <= MAX_RANGE_TABLE_BITS,
+        "range table of {} bits is over the maximum of {} bits",
+        bits,
+        MAX_RANGE_TABLE_BITS
+    );
+    let value = ctx.fixed(format!("range {} bits", bits).as_str());
+    let size = 1usize << bits;
+
+    ctx.pragma_num_steps(size);
+    ctx.fixed_gen(move |ctx| ctx.assign_with(value, |i| F::from(i as u64)));
+
+    ctx.new_table(table().add(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        frontend::dsl::{circuit, lb::LookupTableRegistry},
+        sbpir::SBPIR,
+        wit_gen::TraceGenerator,
+    };
+
+    fn limb_decomposition(num_limbs: usize, limb_bits: u32) -> LimbDecomposition<Fr> {
+        let mut ctx = StepTypeContext::new(0, "step".to_string(), LookupTableRegistry::default());
+
+        LimbDecomposition::new(&mut ctx, "value", num_limbs, limb_bits)
+    }
+
+    #[test]
+    fn test_limb_decomposition_wg() {
+        let gadget = limb_decomposition(4, 8);
+        let mut instance = StepInstance::new(0);
+
+        gadget.wg(&mut instance, 0x12345678u64);
+
+        assert_eq!(instance.assignments[&gadget.limb(0)], Fr::from(0x78));
+        assert_eq!(instance.assignments[&gadget.limb(1)], Fr::from(0x56));
+        assert_eq!(instance.assignments[&gadget.limb(2)], Fr::from(0x34));
+        assert_eq!(instance.assignments[&gadget.limb(3)], Fr::from(0x12));
+
+        let recomposed = gadget.recompose().eval(&instance.assignments);
+        assert_eq!(recomposed, Some(Fr::from(0x12345678)));
+    }
+
+    #[test]
+    fn test_limb_decomposition_wide_limbs() {
+        let gadget = limb_decomposition(2, MAX_RANGE_TABLE_BITS);
+        let mut instance = StepInstance::new(0);
+        let value = (1u64 << MAX_RANGE_TABLE_BITS) + 5;
+
+        gadget.wg(&mut instance, value);
+
+        assert_eq!(instance.assignments[&gadget.limb(0)], Fr::from(5));
+        assert_eq!(instance.assignments[&gadget.limb(1)], Fr::from(1));
+        assert_eq!(
+            gadget.recompose().eval(&instance.assignments),
+            Some(Fr::from(2).pow([MAX_RANGE_TABLE_BITS as u64]) + Fr::from(5))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "10 limbs of 28 bits do not fit in a field of 254 bits")]
+    fn test_limb_decomposition_too_wide() {
+        limb_decomposition(10, 28);
+    }
+
+    #[test]
+    #[should_panic(expected = "limb size must be between 1 and 28 bits, the widest range table")]
+    fn test_limb_decomposition_limb_over_range_table() {
+        limb_decomposition(2, 29);
+    }
+
+    #[test]
+    #[should_panic(expected = "range table of 64 bits is over the maximum of 28 bits")]
+    fn test_range_table_too_wide() {
+        let _: SBPIR<Fr, ()> = circuit("range", |ctx| {
+            range_table(ctx, 64);
+        });
+    }
+
+    #[test]
+    fn test_one_hot() {
+        let mut ctx = StepTypeContext::new(0, "step".to_string(), LookupTableRegistry::default());
+        let one_hot: OneHot<Fr> = OneHot::new(&mut ctx, "opcode", 4);
+        let mut instance = StepInstance::new(0);
+
+        one_hot.wg(&mut instance, 2);
+
+        assert_eq!(instance.assignments[&one_hot.bit(2)], Fr::ONE);
+        assert_eq!(instance.assignments[&one_hot.bit(3)], Fr::ZERO);
+        assert_eq!(
+            one_hot.decoded().eval(&instance.assignments),
+            Some(Fr::from(2))
+        );
+        assert_eq!(
+            one_hot
+                .select(&[10u64, 20, 30, 40].map(|v| Expr::Const(Fr::from(v))))
+                .eval(&instance.assignments),
+            Some(Fr::from(30))
+        );
+    }
+
+    #[test]
+    fn test_one_hot_constraints() {
+        let circuit: SBPIR<Fr, (usize, bool)> = circuit("one-hot", |ctx| {
+            let step = ctx.step_type_def("step", |ctx| {
+                let one_hot = OneHot::new(ctx, "state", 3);
+
+                let setup_one_hot = one_hot.clone();
+                ctx.setup(move |ctx| setup_one_hot.setup(ctx));
+
+                ctx.wg(move |ctx, (index, tamper): (usize, bool)| {
+                    one_hot.wg(ctx, index);
+                    if tamper {
+                        ctx.assign(one_hot.bit((index + 1) % 3), Fr::ONE);
+                    }
+                })
+            });
+
+            ctx.pragma_num_steps(1);
+            ctx.trace(move |ctx, args| ctx.add(&step, args));
+        });
+
+        let satisfied = |args| {
+            let step_type = circuit.step_types.values().next().unwrap();
+            let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), 1).generate(args);
+            step_type
+                .constraints
+                .iter()
+                .all(|c| c.expr.eval(&trace.step_instances[0].assignments) == Some(Fr::ZERO))
+        };
+
+        assert!(satisfied((0, false)));
+        assert!(satisfied((2, false)));
+        assert!(!satisfied((1, true)));
+    }
+
+    #[test]
+    fn test_gadget_constraint_location() {
+        let line = line!() + 5;
+        let circuit: SBPIR<Fr, ()> = circuit("located", |ctx| {
+            ctx.step_type_def("step", |ctx| {
+                let value = ctx.internal("value");
+                let value_inv = ctx.internal("value_inv");
+                IsZero::setup(ctx, value, value_inv);
+                let one_hot = OneHot::new(ctx, "state", 3);
+                ctx.setup(move |ctx| one_hot.setup(ctx));
+                ctx.wg(|_, _: ()| {})
+            });
+        });
+
+        let step_type = circuit.step_types.values().next().unwrap();
+        let lines: Vec<_> = step_type
+            .constraints
+            .iter()
+            .map(|constraint| {
+                let location = constraint.location.unwrap();
+                (location.file, location.line)
+            })
+            .collect();
+        assert_eq!(lines[0], (file!(), line));
+        assert!(lines[1..].iter().all(|l| *l == (file!(), line + 2)));
+    }
+
+    #[test]
+    #[should_panic(expected = "value does not fit in 2 limbs of 8 bits")]
+    fn test_limb_decomposition_overflow() {
+        let gadget = limb_decomposition(2, 8);
+        let mut instance = StepInstance::new(0);
+
+        gadget.wg(&mut instance, 0x10000u64);
+    }
+}
@@ -0,0 +1,220 @@
+use std::hash::Hash;
+
+use num_bigint::BigUint;
+
+use crate::{
+    field::Field,
+    frontend::dsl::{
+        cb::{eq, Constraint},
+        lb::LookupTable,
+        StepTypeContext, StepTypeSetupContext,
+    },
+    poly::{Expr, ToExpr},
+    sbpir::{query::Queriable, PIR},
+    wit_gen::StepInstance,
+};
+
+use super::nonnative::{
+    constant_columns, value_columns, NonNativeOp, NonNativeReduce, LIMB_BITS, NUM_LIMBS,
+};
+
+/// In-step modular exponentiation `base^exponent mod modulus` with square-and-multiply, for
+/// RSA-style verifications. The exponent is a native expression of `exponent_bits` bits, processed
+/// from the most significant one with a squaring and a multiplication by the base each, and the
+/// modulus is a constant of up to `NUM_LIMBS` limbs, see `NonNativeOp`. Every bit adds two
+/// non-native multiplications, so for a constant exponent like `65537` it is cheaper to pass its
+/// bit length than the full width.
+#[derive(Clone)]
+pub struct ModExp<F> {
+    modulus: BigUint,
+    bits: Vec<Queriable<F>>,
+    squares: Vec<NonNativeOp<F>>,
+    products: Vec<NonNativeOp<F>>,
+    result: NonNativeReduce<F>,
+}
+
+impl<F: Field + From<u64>> ModExp<F> {
+    pub fn new(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        modulus: &BigUint,
+        exponent_bits: usize,
+    ) -> ModExp<F> {
+        assert!(exponent_bits > 0, "modexp needs at least one exponent bit");
+
+        let bits = (0..exponent_bits)
+            .map(|i| ctx.internal(format!("{}.bits[{}]", name, i).as_str()))
+            .collect();
+        let squares = (0..exponent_bits)
+            .map(|i| NonNativeOp::mul(ctx, format!("{}.square[{}]", name, i).as_str(), modulus))
+            .collect();
+        let products = (0..exponent_bits)
+            .map(|i| NonNativeOp::mul(ctx, format!("{}.product[{}]", name, i).as_str(), modulus))
+            .collect();
+        let bound = BigUint::from(1u64) << (NUM_LIMBS * LIMB_BITS);
+        let result =
+            NonNativeReduce::new(ctx, format!("{}.result", name).as_str(), modulus, &bound);
+
+        ModExp {
+            modulus: modulus.clone(),
+            bits,
+            squares,
+            products,
+            result,
+        }
+    }
+
+    /// Constrains the result to be `base^exponent mod modulus`, `base` being the limbs of a range
+    /// checked element. The intermediate elements are range checked in `byte_table`, which must
+    /// contain the values in `[0, 256)`.
+    #[track_caller]
+    pub fn setup<V: Into<Constraint<F>>>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        base: &[PIR<F>],
+        exponent: V,
+        byte_table: LookupTable,
+    ) {
+        let mut coeff = F::ONE;
+        let mut recomposed: PIR<F> = 0u64.expr();
+        for bit in self.bits.iter() {
+            ctx.constr(eq(*bit * (1u64.expr() - *bit), 0u64));
+            recomposed = recomposed + Expr::Const(coeff) * *bit;
+            coeff = coeff + coeff;
+        }
+        ctx.constr(eq(exponent, recomposed));
+
+        let mut acc: Vec<PIR<F>> = constant_columns(&BigUint::from(1u64));
+        for ((bit, square), product) in self
+            .bits
+            .iter()
+            .rev()
+            .zip(self.squares.iter())
+            .zip(self.products.iter())
+        {
+            square.setup(ctx, &acc, &acc, byte_table);
+            let squared = square.result().limbs();
+            product.setup(ctx, &squared, base, byte_table);
+            let multiplied = product.result().limbs();
+
+            acc = squared
+                .into_iter()
+                .zip(multiplied)
+                .map(|(s, m)| s.clone() + *bit * (m - s))
+                .collect();
+        }
+
+        self.result.setup(ctx, acc, byte_table);
+    }
+
+    /// Returns the limbs of the result, which is lower than the modulus.
+    pub fn output(&self) -> Vec<PIR<F>> {
+        self.result.result().limbs()
+    }
+}
+
+impl<F: Field + From<u64> + Hash> ModExp<F> {
+    /// Assigns the bits of the exponent and the intermediate squarings and products, and returns
+    /// the result.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, base: &BigUint, exponent: &BigUint) -> BigUint {
+        assert!(
+            exponent.bits() <= self.bits.len() as u64,
+            "exponent does not fit in {} bits",
+            self.bits.len()
+        );
+
+        for (i, bit) in self.bits.iter().enumerate() {
+            ctx.assign(*bit, F::from(exponent.bit(i as u64) as u64));
+        }
+
+        let mut acc = BigUint::from(1u64);
+        for (i, (square, product)) in self.squares.iter().zip(self.products.iter()).enumerate() {
+            let squared = square.wg(ctx, &acc, &acc);
+            let multiplied = product.wg(ctx, &squared, base);
+
+            acc = if exponent.bit((self.bits.len() - 1 - i) as u64) {
+                multiplied
+            } else {
+                squared
+            };
+        }
+
+        let result = self.result.wg(ctx, &value_columns(&acc));
+        debug_assert_eq!(result, base.modpow(exponent, &self.modulus));
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        frontend::dsl::circuit,
+        sbpir::SBPIR,
+        stdlib::{nonnative::NonNativeElement, range_table},
+        wit_gen::TraceGenerator,
+    };
+
+    fn satisfied(circuit: &SBPIR<Fr, (BigUint, u64, bool)>, args: (BigUint, u64, bool)) -> bool {
+        let step_type = circuit.step_types.values().next().unwrap();
+        let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), 1).generate(args);
+        let assignments = &trace.step_instances[0].assignments;
+
+        step_type
+            .constraints
+            .iter()
+            .all(|c| c.expr.eval(assignments) == Some(Fr::ZERO))
+    }
+
+    #[test]
+    fn test_modexp() {
+        // product of two 128-bit primes, like a tiny RSA modulus
+        let modulus = BigUint::parse_bytes(b"340282366920938463463374607431768211297", 10).unwrap()
+            * BigUint::parse_bytes(b"340282366920938463463374607431768211283", 10).unwrap();
+        let setup_modulus = modulus.clone();
+
+        let circuit: SBPIR<Fr, (BigUint, u64, bool)> = circuit("modexp", |ctx| {
+            let byte_table = range_table(ctx, 8);
+            let exponent = ctx.forward("exponent");
+
+            let step = ctx.step_type_def("step", |ctx| {
+                let base = NonNativeElement::new(ctx, "base");
+                let modexp = ModExp::new(ctx, "modexp", &setup_modulus, 17);
+
+                let (setup_base, setup_modexp) = (base.clone(), modexp.clone());
+                ctx.setup(move |ctx| {
+                    setup_base.setup(ctx, byte_table);
+                    setup_modexp.setup(ctx, &setup_base.limbs(), exponent, byte_table);
+                });
+
+                let modulus = setup_modulus.clone();
+                ctx.wg(
+                    move |ctx, (base_value, exponent_value, tamper): (BigUint, u64, bool)| {
+                        ctx.assign(exponent, Fr::from(exponent_value));
+                        let exponent_value = BigUint::from(exponent_value);
+                        base.wg(ctx, &base_value);
+
+                        let result = modexp.wg(ctx, &base_value, &exponent_value);
+                        assert_eq!(result, base_value.modpow(&exponent_value, &modulus));
+
+                        if tamper {
+                            ctx.assign(exponent, Fr::from(3));
+                        }
+                    },
+                )
+            });
+
+            ctx.pragma_num_steps(1);
+            ctx.trace(move |ctx, args| ctx.add(&step, args));
+        });
+
+        let base = &modulus - 12345u64;
+        assert!(satisfied(&circuit, (base.clone(), 65537, false)));
+        assert!(satisfied(&circuit, (base.clone(), 3, false)));
+        assert!(satisfied(&circuit, (BigUint::from(0u64), 1, false)));
+        assert!(!satisfied(&circuit, (base, 65537, true)));
+    }
+}
@@ -0,0 +1,868 @@
+use std::{
+    hash::Hash,
+    ops::{Add, Mul, Sub},
+};
+
+use num_bigint::{BigInt, BigUint};
+
+use crate::{
+    field::Field,
+    frontend::dsl::{cb::eq, lb::LookupTable, StepTypeContext, StepTypeSetupContext},
+    poly::{Expr, ToExpr},
+    sbpir::{query::Queriable, PIR},
+    wit_gen::StepInstance,
+};
+
+pub const LIMB_BITS: usize = 64;
+/// Number of limbs of a non-native element, enough for moduli of up to 256 bits.
+pub const NUM_LIMBS: usize = 4;
+const LIMB_BYTES: usize = LIMB_BITS / 8;
+/// Carries between columns can be negative, so they are kept with this offset added.
+const CARRY_OFFSET_BITS: usize = 71;
+const CARRY_BYTES: usize = 9;
+
+/// Limb column of an integer `sum(columns[k] * 2^(64 * k))`. Integer relations are written once
+/// over this trait and used with expressions in the setup and with native values in witness
+/// generation.
+pub trait Column:
+    Clone + From<u64> + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self>
+{
+}
+
+impl<T: Clone + From<u64> + Add<Output = T> + Sub<Output = T> + Mul<Output = T>> Column for T {}
+
+pub fn add_columns<T: Column>(lhs: &[T], rhs: &[T]) -> Vec<T> {
+    (0..lhs.len().max(rhs.len()))
+        .map(|k| match (lhs.get(k), rhs.get(k)) {
+            (Some(l), Some(r)) => l.clone() + r.clone(),
+            (Some(l), None) => l.clone(),
+            (None, Some(r)) => r.clone(),
+            (None, None) => unreachable!(),
+        })
+        .collect()
+}
+
+pub fn sub_columns<T: Column>(lhs: &[T], rhs: &[T]) -> Vec<T> {
+    (0..lhs.len().max(rhs.len()))
+        .map(|k| match (lhs.get(k), rhs.get(k)) {
+            (Some(l), Some(r)) => l.clone() - r.clone(),
+            (Some(l), None) => l.clone(),
+            (None, Some(r)) => T::from(0) - r.clone(),
+            (None, None) => unreachable!(),
+        })
+        .collect()
+}
+
+pub fn mul_columns<T: Column>(lhs: &[T], rhs: &[T]) -> Vec<T> {
+    let mut columns: Vec<Option<T>> = vec![None; lhs.len() + rhs.len() - 1];
+
+    for (i, l) in lhs.iter().enumerate() {
+        for (j, r) in rhs.iter().enumerate() {
+            let product = l.clone() * r.clone();
+            columns[i + j] = Some(match columns[i + j].take() {
+                Some(column) => column + product,
+                None => product,
+            });
+        }
+    }
+
+    columns.into_iter().map(|c| c.expect("column")).collect()
+}
+
+pub fn scale_columns<T: Column>(columns: &[T], factor: u64) -> Vec<T> {
+    columns
+        .iter()
+        .map(|c| c.clone() * T::from(factor))
+        .collect()
+}
+
+/// Returns the limbs of a constant.
+pub fn constant_columns<T: Column>(value: &BigUint) -> Vec<T> {
+    let mut limbs: Vec<T> = value.iter_u64_digits().map(T::from).collect();
+    if limbs.is_empty() {
+        limbs.push(T::from(0));
+    }
+
+    limbs
+}
+
+/// Returns the `NUM_LIMBS` native limbs of a value, to evaluate relations in witness generation.
+pub fn value_columns(value: &BigUint) -> Vec<BigInt> {
+    let mut limbs: Vec<BigInt> = value.iter_u64_digits().map(BigInt::from).collect();
+    limbs.resize(NUM_LIMBS.max(limbs.len()), BigInt::from(0u64));
+
+    limbs
+}
+
+/// Bound of the integers made of a few products of two elements plus lower terms, which is what
+/// the gadgets built on reductions use.
+pub fn product_bound() -> BigUint {
+    BigUint::from(1u64) << (2 * NUM_LIMBS * LIMB_BITS + 4)
+}
+
+pub(crate) fn sub_mod(lhs: &BigUint, rhs: &BigUint, modulus: &BigUint) -> BigUint {
+    (lhs + modulus - rhs % modulus) % modulus
+}
+
+/// Returns the modular inverse, or zero when there is none.
+pub(crate) fn inverse_mod(value: &BigUint, modulus: &BigUint) -> BigUint {
+    (value % modulus)
+        .modinv(modulus)
+        .unwrap_or_else(|| BigUint::from(0u64))
+}
+
+/// Returns a square root modulo a prime with Tonelli-Shanks, or `None` for non-residues.
+pub(crate) fn sqrt_mod(value: &BigUint, modulus: &BigUint) -> Option<BigUint> {
+    let one = BigUint::from(1u64);
+    let value = value % modulus;
+    if value == BigUint::from(0u64) {
+        return Some(value);
+    }
+    let minus_one = modulus - &one;
+    if value.modpow(&(&minus_one >> 1), modulus) != one {
+        return None;
+    }
+
+    // modulus - 1 = q * 2^s with q odd
+    let s = minus_one.trailing_zeros().expect("odd prime modulus");
+    let q = &minus_one >> s;
+    let mut z = BigUint::from(2u64);
+    while z.modpow(&(&minus_one >> 1), modulus) != minus_one {
+        z += 1u64;
+    }
+
+    let mut m = s;
+    let mut c = z.modpow(&q, modulus);
+    let mut t = value.modpow(&q, modulus);
+    let mut root = value.modpow(&((&q + 1u64) >> 1), modulus);
+    while t != one {
+        let mut i = 0;
+        let mut square = t.clone();
+        while square != one {
+            square = &square * &square % modulus;
+            i += 1;
+        }
+        let b = c.modpow(&(BigUint::from(1u64) << (m - i - 1)), modulus);
+        m = i;
+        c = &b * &b % modulus;
+        t = t * &c % modulus;
+        root = root * b % modulus;
+    }
+
+    Some(root)
+}
+
+fn columns_value(columns: &[BigInt]) -> BigInt {
+    columns
+        .iter()
+        .rev()
+        .fold(BigInt::from(0u64), |acc, c| (acc << LIMB_BITS) + c)
+}
+
+fn bytes_to_limbs<F: Field + From<u64>>(bytes: &[Queriable<F>]) -> Vec<PIR<F>> {
+    bytes
+        .chunks(LIMB_BYTES)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0u64.expr(), |acc, (i, byte)| {
+                    acc + Expr::Const(F::from(1u64 << (8 * i))) * *byte
+                })
+        })
+        .collect()
+}
+
+fn assign_bytes<F: Field + From<u64> + Hash>(
+    ctx: &mut StepInstance<F>,
+    bytes: &[Queriable<F>],
+    value: &BigUint,
+) {
+    let mut values = value.to_bytes_le();
+    assert!(
+        values.len() <= bytes.len(),
+        "value does not fit in {} bytes",
+        bytes.len()
+    );
+    values.resize(bytes.len(), 0);
+
+    for (signal, value) in bytes.iter().zip(values) {
+        ctx.assign(*signal, F::from(value as u64));
+    }
+}
+
+/// Non-native element given by `NUM_LIMBS * 8` little-endian byte signals, each one looked up in a
+/// table with the values in `[0, 256)`, for example `range_table(ctx, 8)`. The element is not
+/// necessarily reduced, its value is any integer lower than `2^256`.
+#[derive(Clone)]
+pub struct NonNativeElement<F> {
+    bytes: Vec<Queriable<F>>,
+}
+
+impl<F: Field + From<u64>> NonNativeElement<F> {
+    /// Adds the byte signals to the step type, annotated as `name[i]`.
+    pub fn new(ctx: &mut StepTypeContext<F>, name: &str) -> NonNativeElement<F> {
+        let bytes = (0..NUM_LIMBS * LIMB_BYTES)
+            .map(|i| ctx.internal(format!("{}[{}]", name, i).as_str()))
+            .collect();
+
+        NonNativeElement { bytes }
+    }
+
+    /// Range checks the bytes.
+    #[track_caller]
+    pub fn setup(&self, ctx: &mut StepTypeSetupContext<F>, byte_table: LookupTable) {
+        for byte in self.bytes.iter() {
+            ctx.add_lookup(byte_table.apply(*byte));
+        }
+    }
+
+    /// Returns the limbs, least significant first.
+    pub fn limbs(&self) -> Vec<PIR<F>> {
+        bytes_to_limbs(&self.bytes)
+    }
+}
+
+impl<F: Field + From<u64> + Hash> NonNativeElement<F> {
+    /// Assigns the bytes of `value`. Panics if it does not fit in `NUM_LIMBS` limbs.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, value: &BigUint) {
+        assign_bytes(ctx, &self.bytes, value);
+    }
+}
+
+/// Constrains an integer given by limb columns to be zero. Every column but the last one moves a
+/// signed carry to the next column, and the carries are range checked with byte lookups.
+#[derive(Clone)]
+struct ZeroColumns<F> {
+    carries: Vec<Vec<Queriable<F>>>,
+}
+
+impl<F: Field + From<u64>> ZeroColumns<F> {
+    fn new(ctx: &mut StepTypeContext<F>, name: &str, num_columns: usize) -> ZeroColumns<F> {
+        let carries = (0..num_columns - 1)
+            .map(|k| {
+                (0..CARRY_BYTES)
+                    .map(|i| ctx.internal(format!("{}.carry[{}][{}]", name, k, i).as_str()))
+                    .collect()
+            })
+            .collect();
+
+        ZeroColumns { carries }
+    }
+
+    fn num_columns(&self) -> usize {
+        self.carries.len() + 1
+    }
+
+    #[track_caller]
+    fn setup(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        mut columns: Vec<PIR<F>>,
+        byte_table: LookupTable,
+    ) {
+        assert!(
+            columns.len() <= self.num_columns(),
+            "{} columns do not fit in {}",
+            columns.len(),
+            self.num_columns()
+        );
+        columns.resize(self.num_columns(), 0u64.expr());
+
+        let offset = Expr::Const(F::from(2).pow([CARRY_OFFSET_BITS as u64]));
+        let base = Expr::Const(F::from(2).pow([LIMB_BITS as u64]));
+        let carries: Vec<PIR<F>> = self
+            .carries
+            .iter()
+            .map(|bytes| {
+                bytes
+                    .iter()
+                    .enumerate()
+                    .fold(0u64.expr(), |acc, (i, byte)| {
+                        acc + Expr::Const(F::from(2).pow([8 * i as u64])) * *byte
+                    })
+                    - offset.clone()
+            })
+            .collect();
+
+        for (k, column) in columns.into_iter().enumerate() {
+            let lhs = if k == 0 {
+                column
+            } else {
+                column + carries[k - 1].clone()
+            };
+            let rhs = if k < carries.len() {
+                base.clone() * carries[k].clone()
+            } else {
+                0u64.expr()
+            };
+
+            ctx.constr(eq(lhs, rhs));
+        }
+
+        for byte in self.carries.iter().flatten() {
+            ctx.add_lookup(byte_table.apply(*byte));
+        }
+    }
+}
+
+impl<F: Field + From<u64> + Hash> ZeroColumns<F> {
+    fn wg(&self, ctx: &mut StepInstance<F>, columns: &[BigInt]) {
+        let offset = BigInt::from(1u64) << CARRY_OFFSET_BITS;
+        let mut carry = BigInt::from(0u64);
+
+        for (k, bytes) in self.carries.iter().enumerate() {
+            carry = (columns.get(k).cloned().unwrap_or_default() + carry) >> LIMB_BITS;
+            let value = (&carry + &offset).to_biguint().expect("carry out of range");
+
+            assign_bytes(ctx, bytes, &value);
+        }
+    }
+}
+
+/// Constrains an integer given by limb columns to be congruent to `result` modulo `modulus`. The
+/// absolute value of the integer must be lower than `bound`, which determines the size of the
+/// quotient and how many columns it can have. The quotient and the result are witness hints
+/// computed natively, the result is not necessarily reduced.
+#[derive(Clone)]
+pub struct Reduction<F> {
+    modulus: BigUint,
+    /// Multiple of the modulus added to the integer so that it is never negative.
+    offset: BigUint,
+    quotient: Vec<Queriable<F>>,
+    result: Option<NonNativeElement<F>>,
+    carries: ZeroColumns<F>,
+}
+
+impl<F: Field + From<u64>> Reduction<F> {
+    /// Adds the signals of a reduction with a result element, annotated with the given name.
+    pub fn new(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        modulus: &BigUint,
+        bound: &BigUint,
+    ) -> Reduction<F> {
+        let result = NonNativeElement::new(ctx, format!("{}.result", name).as_str());
+
+        Self::with_result(ctx, name, modulus, bound, Some(result))
+    }
+
+    /// Adds the signals of a reduction that constrains the integer to be a multiple of the modulus.
+    pub fn new_zero(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        modulus: &BigUint,
+        bound: &BigUint,
+    ) -> Reduction<F> {
+        Self::with_result(ctx, name, modulus, bound, None)
+    }
+
+    fn with_result(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        modulus: &BigUint,
+        bound: &BigUint,
+        result: Option<NonNativeElement<F>>,
+    ) -> Reduction<F> {
+        assert!(
+            modulus.bits() <= (NUM_LIMBS * LIMB_BITS) as u64,
+            "modulus does not fit in {} limbs",
+            NUM_LIMBS
+        );
+
+        let offset = (bound + modulus - 1u64) / modulus * modulus;
+        let max_quotient = (bound + &offset) / modulus;
+        let quotient_bytes = max_quotient.bits().div_ceil(8).max(1) as usize;
+        let quotient = (0..quotient_bytes)
+            .map(|i| ctx.internal(format!("{}.quotient[{}]", name, i).as_str()))
+            .collect();
+
+        let quotient_limbs = quotient_bytes.div_ceil(LIMB_BYTES);
+        let modulus_limbs = (modulus.bits() as usize).div_ceil(LIMB_BITS);
+        let num_columns = (2 * NUM_LIMBS - 1)
+            .max(quotient_limbs + modulus_limbs - 1)
+            .max(((bound + &offset).bits() as usize).div_ceil(LIMB_BITS));
+        let carries = ZeroColumns::new(ctx, name, num_columns);
+
+        Reduction {
+            modulus: modulus.clone(),
+            offset,
+            quotient,
+            result,
+            carries,
+        }
+    }
+
+    /// Constrains `columns` to be congruent to the result. The result and the quotient are range
+    /// checked in `byte_table`.
+    #[track_caller]
+    pub fn setup(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        columns: Vec<PIR<F>>,
+        byte_table: LookupTable,
+    ) {
+        let mut columns = add_columns(&columns, &constant_columns(&self.offset));
+        columns = sub_columns(
+            &columns,
+            &mul_columns(
+                &bytes_to_limbs(&self.quotient),
+                &constant_columns(&self.modulus),
+            ),
+        );
+        if let Some(result) = &self.result {
+            columns = sub_columns(&columns, &result.limbs());
+            result.setup(ctx, byte_table);
+        }
+
+        for byte in self.quotient.iter() {
+            ctx.add_lookup(byte_table.apply(*byte));
+        }
+        self.carries.setup(ctx, columns, byte_table);
+    }
+
+    /// Returns the result element. Panics for reductions created with `new_zero`.
+    pub fn result(&self) -> &NonNativeElement<F> {
+        self.result.as_ref().expect("zero reduction has no result")
+    }
+
+    pub fn modulus(&self) -> &BigUint {
+        &self.modulus
+    }
+}
+
+impl<F: Field + From<u64> + Hash> Reduction<F> {
+    /// Assigns the quotient, result and carries for the native `columns`, and returns the result.
+    /// For zero reductions an integer that is not a multiple of the modulus gets an assignment
+    /// that does not satisfy the constraints.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, columns: &[BigInt]) -> BigUint {
+        let value = columns_value(columns) + BigInt::from(self.offset.clone());
+        let value = value
+            .to_biguint()
+            .expect("value out of the reduction bound");
+
+        let quotient = &value / &self.modulus;
+        let result = match &self.result {
+            Some(element) => {
+                let result = &value % &self.modulus;
+                element.wg(ctx, &result);
+                result
+            }
+            None => BigUint::from(0u64),
+        };
+        assign_bytes(ctx, &self.quotient, &quotient);
+
+        let mut columns = add_columns(columns, &constant_columns(&self.offset));
+        columns = sub_columns(
+            &columns,
+            &mul_columns(
+                &constant_columns(&quotient),
+                &constant_columns(&self.modulus),
+            ),
+        );
+        if self.result.is_some() {
+            columns = sub_columns(&columns, &value_columns(&result));
+        }
+        self.carries.wg(ctx, &columns);
+
+        result
+    }
+}
+
+/// Constrains a non-native value to be lower than a modulus, by range checking its difference
+/// with `modulus - 1`.
+#[derive(Clone)]
+pub struct CanonicalCheck<F> {
+    modulus: BigUint,
+    diff: NonNativeElement<F>,
+    carries: ZeroColumns<F>,
+}
+
+impl<F: Field + From<u64>> CanonicalCheck<F> {
+    pub fn new(ctx: &mut StepTypeContext<F>, name: &str, modulus: &BigUint) -> CanonicalCheck<F> {
+        CanonicalCheck {
+            modulus: modulus.clone(),
+            diff: NonNativeElement::new(ctx, format!("{}.diff", name).as_str()),
+            carries: ZeroColumns::new(ctx, name, NUM_LIMBS),
+        }
+    }
+
+    /// Constrains the value given by `limbs`, which must be range checked, to be lower than the
+    /// modulus.
+    #[track_caller]
+    pub fn setup(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        limbs: &[PIR<F>],
+        byte_table: LookupTable,
+    ) {
+        let columns = sub_columns(
+            &add_columns(limbs, &self.diff.limbs()),
+            &constant_columns(&(&self.modulus - 1u64)),
+        );
+
+        self.diff.setup(ctx, byte_table);
+        self.carries.setup(ctx, columns, byte_table);
+    }
+}
+
+impl<F: Field + From<u64> + Hash> CanonicalCheck<F> {
+    /// Assigns the difference for `value`. A value that is not lower than the modulus gets an
+    /// assignment that does not satisfy the constraints.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, value: &BigUint) {
+        let max = &self.modulus - 1u64;
+        let diff = if value <= &max {
+            &max - value
+        } else {
+            BigUint::from(0u64)
+        };
+
+        self.diff.wg(ctx, &diff);
+
+        let columns = sub_columns(
+            &add_columns(&value_columns(value), &value_columns(&diff)),
+            &value_columns(&max),
+        );
+        self.carries.wg(ctx, &columns);
+    }
+}
+
+/// Modular operation of a `NonNativeOp`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NonNativeOpKind {
+    Add,
+    Sub,
+    Mul,
+}
+
+impl NonNativeOpKind {
+    /// Bound of the absolute value of the operation on two elements of `NUM_LIMBS` limbs.
+    fn bound(&self) -> BigUint {
+        match self {
+            NonNativeOpKind::Add => BigUint::from(1u64) << (NUM_LIMBS * LIMB_BITS + 1),
+            NonNativeOpKind::Sub => BigUint::from(1u64) << (NUM_LIMBS * LIMB_BITS),
+            NonNativeOpKind::Mul => BigUint::from(1u64) << (2 * NUM_LIMBS * LIMB_BITS),
+        }
+    }
+
+    fn columns<T: Column>(&self, lhs: &[T], rhs: &[T]) -> Vec<T> {
+        match self {
+            NonNativeOpKind::Add => add_columns(lhs, rhs),
+            NonNativeOpKind::Sub => sub_columns(lhs, rhs),
+            NonNativeOpKind::Mul => mul_columns(lhs, rhs),
+        }
+    }
+}
+
+/// In-step modular addition, subtraction or multiplication of two non-native elements, for
+/// example secp256k1 or ed25519 field elements over `bn256::Fr`. The operands are given as limbs of
+/// range checked elements, see `NonNativeElement`, and the result is a new element congruent to
+/// the operation. The quotient and the result are witness hints computed natively. The result is
+/// lower than the modulus when it is assigned by `wg`, but it is only constrained to be so with a
+/// `CanonicalCheck`, or by using `NonNativeReduce`.
+#[derive(Clone)]
+pub struct NonNativeOp<F> {
+    kind: NonNativeOpKind,
+    reduction: Reduction<F>,
+}
+
+impl<F: Field + From<u64>> NonNativeOp<F> {
+    pub fn new(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        kind: NonNativeOpKind,
+        modulus: &BigUint,
+    ) -> NonNativeOp<F> {
+        NonNativeOp {
+            kind,
+            reduction: Reduction::new(ctx, name, modulus, &kind.bound()),
+        }
+    }
+
+    pub fn add(ctx: &mut StepTypeContext<F>, name: &str, modulus: &BigUint) -> NonNativeOp<F> {
+        Self::new(ctx, name, NonNativeOpKind::Add, modulus)
+    }
+
+    pub fn sub(ctx: &mut StepTypeContext<F>, name: &str, modulus: &BigUint) -> NonNativeOp<F> {
+        Self::new(ctx, name, NonNativeOpKind::Sub, modulus)
+    }
+
+    pub fn mul(ctx: &mut StepTypeContext<F>, name: &str, modulus: &BigUint) -> NonNativeOp<F> {
+        Self::new(ctx, name, NonNativeOpKind::Mul, modulus)
+    }
+
+    /// Constrains the result to be congruent to `lhs op rhs`. The result, the quotient and the
+    /// carries are range checked in `byte_table`, which must contain the values in `[0, 256)`.
+    #[track_caller]
+    pub fn setup(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        lhs: &[PIR<F>],
+        rhs: &[PIR<F>],
+        byte_table: LookupTable,
+    ) {
+        self.reduction
+            .setup(ctx, self.kind.columns(lhs, rhs), byte_table);
+    }
+
+    pub fn kind(&self) -> NonNativeOpKind {
+        self.kind
+    }
+
+    pub fn result(&self) -> &NonNativeElement<F> {
+        self.reduction.result()
+    }
+}
+
+impl<F: Field + From<u64> + Hash> NonNativeOp<F> {
+    /// Assigns the hints for the operation on `lhs` and `rhs`, and returns the result.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, lhs: &BigUint, rhs: &BigUint) -> BigUint {
+        self.reduction.wg(
+            ctx,
+            &self.kind.columns(&value_columns(lhs), &value_columns(rhs)),
+        )
+    }
+}
+
+/// In-step reduction of an integer given by limb columns to its canonical representative, the
+/// result being constrained to be lower than the modulus.
+#[derive(Clone)]
+pub struct NonNativeReduce<F> {
+    reduction: Reduction<F>,
+    canonical: CanonicalCheck<F>,
+}
+
+impl<F: Field + From<u64>> NonNativeReduce<F> {
+    /// The absolute value of the reduced integers must be lower than `bound`.
+    pub fn new(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        modulus: &BigUint,
+        bound: &BigUint,
+    ) -> NonNativeReduce<F> {
+        NonNativeReduce {
+            reduction: Reduction::new(ctx, name, modulus, bound),
+            canonical: CanonicalCheck::new(ctx, format!("{}.canonical", name).as_str(), modulus),
+        }
+    }
+
+    #[track_caller]
+    pub fn setup(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        columns: Vec<PIR<F>>,
+        byte_table: LookupTable,
+    ) {
+        self.reduction.setup(ctx, columns, byte_table);
+        self.canonical
+            .setup(ctx, &self.reduction.result().limbs(), byte_table);
+    }
+
+    pub fn result(&self) -> &NonNativeElement<F> {
+        self.reduction.result()
+    }
+}
+
+impl<F: Field + From<u64> + Hash> NonNativeReduce<F> {
+    pub fn wg(&self, ctx: &mut StepInstance<F>, columns: &[BigInt]) -> BigUint {
+        let result = self.reduction.wg(ctx, columns);
+        self.canonical.wg(ctx, &result);
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        frontend::dsl::circuit, sbpir::SBPIR, stdlib::range_table, wit_gen::TraceGenerator,
+    };
+
+    fn modulus() -> BigUint {
+        BigUint::parse_bytes(
+            b"fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
+            16,
+        )
+        .unwrap()
+    }
+
+    fn satisfied<TraceArgs: Clone>(circuit: &SBPIR<Fr, TraceArgs>, args: TraceArgs) -> bool {
+        let step_type = circuit.step_types.values().next().unwrap();
+        let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), 1).generate(args);
+        let assignments = &trace.step_instances[0].assignments;
+
+        step_type
+            .constraints
+            .iter()
+            .all(|c| c.expr.eval(assignments) == Some(Fr::ZERO))
+    }
+
+    #[test]
+    fn test_reduction() {
+        let circuit: SBPIR<Fr, (BigUint, BigUint)> = circuit("reduction", |ctx| {
+            let byte_table = range_table(ctx, 8);
+
+            let step = ctx.step_type_def("step", |ctx| {
+                let a = NonNativeElement::new(ctx, "a");
+                let b = NonNativeElement::new(ctx, "b");
+                let bound = BigUint::from(1u64) << 512;
+                let product = Reduction::new(ctx, "product", &modulus(), &bound);
+                let difference = Reduction::new_zero(ctx, "difference", &modulus(), &bound);
+
+                let (setup_a, setup_b) = (a.clone(), b.clone());
+                let (setup_product, setup_difference) = (product.clone(), difference.clone());
+                ctx.setup(move |ctx| {
+                    let (a, b) = (setup_a.limbs(), setup_b.limbs());
+                    setup_a.setup(ctx, byte_table);
+                    setup_b.setup(ctx, byte_table);
+                    setup_product.setup(ctx, mul_columns(&a, &b), byte_table);
+                    setup_difference.setup(
+                        ctx,
+                        sub_columns(&a, &add_columns(&b, &constant_columns(&modulus()))),
+                        byte_table,
+                    );
+                });
+
+                ctx.wg(move |ctx, (a_value, b_value): (BigUint, BigUint)| {
+                    a.wg(ctx, &a_value);
+                    b.wg(ctx, &b_value);
+                    let (a_columns, b_columns) = (value_columns(&a_value), value_columns(&b_value));
+
+                    let result = product.wg(ctx, &mul_columns(&a_columns, &b_columns));
+                    assert_eq!(result, a_value * b_value % modulus());
+                    difference.wg(
+                        ctx,
+                        &sub_columns(
+                            &a_columns,
+                            &add_columns(&b_columns, &value_columns(&modulus())),
+                        ),
+                    );
+                })
+            });
+
+            ctx.pragma_num_steps(1);
+            ctx.trace(move |ctx, args| ctx.add(&step, args));
+        });
+
+        // (p - 2)^2 = 4 mod p
+        let value = modulus() - 2u64;
+        assert!(satisfied(&circuit, (value.clone(), value.clone())));
+        // a - (b + p) is not a multiple of p when b is not a
+        assert!(!satisfied(&circuit, (value.clone(), value - 1u64)));
+    }
+
+    #[test]
+    fn test_canonical_check() {
+        let circuit: SBPIR<Fr, BigUint> = circuit("canonical", |ctx| {
+            let byte_table = range_table(ctx, 8);
+
+            let step = ctx.step_type_def("step", |ctx| {
+                let value = NonNativeElement::new(ctx, "value");
+                let check = CanonicalCheck::new(ctx, "canonical", &modulus());
+
+                let (setup_value, setup_check) = (value.clone(), check.clone());
+                ctx.setup(move |ctx| {
+                    setup_value.setup(ctx, byte_table);
+                    setup_check.setup(ctx, &setup_value.limbs(), byte_table);
+                });
+
+                ctx.wg(move |ctx, input: BigUint| {
+                    value.wg(ctx, &input);
+                    check.wg(ctx, &input);
+                })
+            });
+
+            ctx.pragma_num_steps(1);
+            ctx.trace(move |ctx, input| ctx.add(&step, input));
+        });
+
+        assert!(satisfied(&circuit, BigUint::from(0u64)));
+        assert!(satisfied(&circuit, modulus() - 1u64));
+        assert!(!satisfied(&circuit, modulus()));
+        assert!(!satisfied(&circuit, (BigUint::from(1u64) << 256) - 1u64));
+    }
+
+    #[test]
+    fn test_non_native_ops() {
+        // ed25519 base field
+        let ed25519: BigUint = (BigUint::from(1u64) << 255) - 19u64;
+        let setup_modulus = ed25519.clone();
+
+        let circuit: SBPIR<Fr, (BigUint, BigUint, bool)> = circuit("ops", |ctx| {
+            let byte_table = range_table(ctx, 8);
+
+            let step = ctx.step_type_def("step", |ctx| {
+                let a = NonNativeElement::new(ctx, "a");
+                let b = NonNativeElement::new(ctx, "b");
+                let ops: Vec<NonNativeOp<Fr>> = vec![
+                    NonNativeOp::add(ctx, "add", &setup_modulus),
+                    NonNativeOp::sub(ctx, "sub", &setup_modulus),
+                    NonNativeOp::mul(ctx, "mul", &setup_modulus),
+                ];
+                let reduce = NonNativeReduce::new(ctx, "reduce", &setup_modulus, &product_bound());
+
+                let (setup_a, setup_b) = (a.clone(), b.clone());
+                let (setup_ops, setup_reduce) = (ops.clone(), reduce.clone());
+                ctx.setup(move |ctx| {
+                    let (a, b) = (setup_a.limbs(), setup_b.limbs());
+                    setup_a.setup(ctx, byte_table);
+                    setup_b.setup(ctx, byte_table);
+                    for op in setup_ops.iter() {
+                        op.setup(ctx, &a, &b, byte_table);
+                    }
+                    // (a + b) * (a - b)
+                    setup_reduce.setup(
+                        ctx,
+                        mul_columns(
+                            &setup_ops[0].result().limbs(),
+                            &setup_ops[1].result().limbs(),
+                        ),
+                        byte_table,
+                    );
+                });
+
+                let modulus = setup_modulus.clone();
+                ctx.wg(
+                    move |ctx, (a_value, b_value, tamper): (BigUint, BigUint, bool)| {
+                        a.wg(ctx, &a_value);
+                        b.wg(ctx, &b_value);
+                        let results: Vec<BigUint> = ops
+                            .iter()
+                            .map(|op| op.wg(ctx, &a_value, &b_value))
+                            .collect();
+
+                        assert_eq!(results[0], (&a_value + &b_value) % &modulus);
+                        assert_eq!(results[1], (&a_value + &modulus - &b_value) % &modulus);
+                        assert_eq!(results[2], &a_value * &b_value % &modulus);
+
+                        let reduced = reduce.wg(
+                            ctx,
+                            &mul_columns(&value_columns(&results[0]), &value_columns(&results[1])),
+                        );
+                        assert_eq!(
+                            reduced,
+                            (&a_value * &a_value + &modulus * &modulus - &b_value * &b_value)
+                                % &modulus
+                        );
+
+                        if tamper {
+                            ops[2].result().wg(ctx, &(&results[2] + 1u64));
+                        }
+                    },
+                )
+            });
+
+            ctx.pragma_num_steps(1);
+            ctx.trace(move |ctx, args| ctx.add(&step, args));
+        });
+
+        let a = &ed25519 - 3u64;
+        let b = BigUint::from(5u64);
+        assert!(satisfied(&circuit, (a.clone(), b.clone(), false)));
+        assert!(satisfied(&circuit, (b.clone(), a.clone(), false)));
+        assert!(!satisfied(&circuit, (a, b, true)));
+    }
+}
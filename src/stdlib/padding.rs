@@ -0,0 +1,274 @@
+use std::hash::Hash;
+
+use crate::{
+    field::Field,
+    frontend::dsl::{
+        cb::{eq, Constraint},
+        StepTypeContext, StepTypeSetupContext,
+    },
+    poly::{Expr, ToExpr},
+    sbpir::{query::Queriable, PIR},
+    wit_gen::StepInstance,
+};
+
+/// Byte-level `10*1` padding: the first padding byte is `first`, the last byte of the block is
+/// `last`, and they are combined with an OR when the padding is a single byte, so their bits must
+/// not overlap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PaddingRule {
+    pub first: u8,
+    pub last: u8,
+}
+
+impl PaddingRule {
+    pub fn new(first: u8, last: u8) -> PaddingRule {
+        assert_eq!(first & last, 0, "padding bytes must not overlap");
+        assert!(first != 0 && last != 0, "padding bytes must not be zero");
+
+        PaddingRule { first, last }
+    }
+
+    /// Keccak-256 padding, as used by Ethereum.
+    pub fn keccak() -> PaddingRule {
+        PaddingRule::new(0x01, 0x80)
+    }
+
+    /// SHA3-256 padding, with the domain separation bits.
+    pub fn sha3() -> PaddingRule {
+        PaddingRule::new(0x06, 0x80)
+    }
+
+    /// Pads a message natively, returning its blocks of `block_bytes` bytes.
+    pub fn pad(&self, message: &[u8], block_bytes: usize) -> Vec<Vec<u8>> {
+        let mut padded = message.to_vec();
+        padded.push(self.first);
+        while padded.len() % block_bytes != 0 {
+            padded.push(0);
+        }
+        *padded.last_mut().unwrap() |= self.last;
+
+        padded
+            .chunks(block_bytes)
+            .map(|block| block.to_vec())
+            .collect()
+    }
+}
+
+/// In-step padding check of a block of a variable-length message absorbed over several steps. The
+/// number of message bytes not absorbed by the previous steps is kept in a forward signal, which
+/// the first step must constrain to the message length. A block is final when its last byte is
+/// padding, which needs an extra block when the message fills the previous one. It adds a signal
+/// per byte of the block.
+#[derive(Clone)]
+pub struct MessagePadding<F> {
+    rule: PaddingRule,
+    is_padding: Vec<Queriable<F>>,
+}
+
+impl<F: Field + From<u64>> MessagePadding<F> {
+    pub fn new(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        rule: PaddingRule,
+        block_bytes: usize,
+    ) -> MessagePadding<F> {
+        assert!(block_bytes > 0, "padding needs at least one byte per block");
+
+        MessagePadding {
+            rule,
+            is_padding: (0..block_bytes)
+                .map(|i| ctx.internal(format!("{}.is_padding[{}]", name, i).as_str()))
+                .collect(),
+        }
+    }
+
+    /// Constrains the padding of `block` and the number of message bytes left in the next step,
+    /// `remaining` being the forward signal with the ones left before this block. The message bytes
+    /// are not constrained.
+    #[track_caller]
+    pub fn setup<V: Into<Constraint<F>> + Clone>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        remaining: Queriable<F>,
+        block: &[V],
+    ) {
+        assert_eq!(block.len(), self.block_bytes(), "wrong block size");
+
+        let mut previous: PIR<F> = 0u64.expr();
+        for (i, (is_padding, byte)) in self.is_padding.iter().zip(block.iter()).enumerate() {
+            // the padding starts once and continues until the end of the block
+            let start = *is_padding - previous;
+            ctx.constr(eq(start.clone() * (1u64.expr() - start.clone()), 0u64));
+
+            let mut expected = start * Expr::Const(F::from(self.rule.first as u64));
+            if i + 1 == self.block_bytes() {
+                expected = expected + Expr::Const(F::from(self.rule.last as u64));
+            }
+            ctx.constr(eq(
+                *is_padding * (byte.clone().into().expr - expected),
+                0u64,
+            ));
+
+            previous = is_padding.expr();
+        }
+
+        let block_bytes = self.block_bytes() as u64;
+        ctx.constr(eq(self.is_final() * (remaining - self.data_len()), 0u64));
+        ctx.transition(eq(
+            (1u64.expr() - self.is_final()) * (remaining.next() - remaining + block_bytes),
+            0u64,
+        ));
+    }
+
+    pub fn block_bytes(&self) -> usize {
+        self.is_padding.len()
+    }
+
+    /// Returns the signal that is one for the padding bytes.
+    pub fn is_padding(&self, index: usize) -> Queriable<F> {
+        self.is_padding[index]
+    }
+
+    /// Returns the boolean signal of the block being the last one of the message.
+    pub fn is_final(&self) -> Queriable<F> {
+        self.is_padding[self.block_bytes() - 1]
+    }
+
+    /// Returns the number of message bytes in the block.
+    pub fn data_len(&self) -> PIR<F> {
+        self.is_padding
+            .iter()
+            .fold((self.block_bytes() as u64).expr(), |acc, is_padding| {
+                acc - *is_padding
+            })
+    }
+}
+
+impl<F: Field + From<u64> + Hash> MessagePadding<F> {
+    /// Assigns the padding flags for a block with `remaining` message bytes left before it, and
+    /// returns the ones left after it. The final block is the one with fewer message bytes than
+    /// its size.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, remaining: usize) -> usize {
+        for (i, is_padding) in self.is_padding.iter().enumerate() {
+            ctx.assign(*is_padding, F::from((i >= remaining) as u64));
+        }
+
+        remaining.saturating_sub(self.block_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{frontend::dsl::circuit, sbpir::SBPIR, wit_gen::TraceGenerator};
+
+    const BLOCK_BYTES: usize = 8;
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Tamper {
+        None,
+        PaddingByte,
+        EarlyFinal,
+    }
+
+    fn satisfied(circuit: &SBPIR<Fr, (Vec<u8>, Tamper)>, args: (Vec<u8>, Tamper)) -> bool {
+        let num_steps = args.0.len() / BLOCK_BYTES + 1;
+        let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), num_steps).generate(args);
+
+        trace
+            .step_instances
+            .iter()
+            .enumerate()
+            .all(|(i, instance)| {
+                let step_type = &circuit.step_types[&instance.step_type_uuid];
+                let mut assignments = instance.assignments.clone();
+                if let Some(next) = trace.step_instances.get(i + 1) {
+                    for (signal, value) in next.assignments.iter() {
+                        if let Queriable::Forward(forward, false) = signal {
+                            assignments.insert(Queriable::Forward(*forward, true), *value);
+                        }
+                    }
+                }
+
+                let transitions = step_type
+                    .transition_constraints
+                    .iter()
+                    .filter(|_| i + 1 < num_steps);
+                step_type
+                    .constraints
+                    .iter()
+                    .map(|c| &c.expr)
+                    .chain(transitions.map(|c| &c.expr))
+                    .all(|expr| expr.eval(&assignments) == Some(Fr::ZERO))
+            })
+    }
+
+    #[test]
+    fn test_padding_rule() {
+        let rule = PaddingRule::keccak();
+
+        let empty = rule.pad(&[], 136);
+        assert_eq!(empty.len(), 1);
+        assert_eq!((empty[0][0], empty[0][135]), (0x01, 0x80));
+
+        assert_eq!(rule.pad(&[7; 7], 8), vec![vec![7, 7, 7, 7, 7, 7, 7, 0x81]]);
+        assert_eq!(rule.pad(&[7; 8], 8)[1], vec![0x01, 0, 0, 0, 0, 0, 0, 0x80]);
+    }
+
+    #[test]
+    fn test_message_padding() {
+        let circuit: SBPIR<Fr, (Vec<u8>, Tamper)> = circuit("padding", |ctx| {
+            let remaining = ctx.forward("remaining");
+            let block: Vec<Queriable<Fr>> = (0..BLOCK_BYTES)
+                .map(|i| ctx.forward(format!("block[{}]", i).as_str()))
+                .collect();
+
+            let absorb = ctx.step_type_def("absorb", |ctx| {
+                let padding =
+                    MessagePadding::new(ctx, "padding", PaddingRule::keccak(), BLOCK_BYTES);
+
+                let (setup_padding, setup_block) = (padding.clone(), block.clone());
+                ctx.setup(move |ctx| setup_padding.setup(ctx, remaining, &setup_block));
+
+                ctx.wg(move |ctx, (left, bytes, early): (usize, Vec<u8>, bool)| {
+                    ctx.assign(remaining, Fr::from(left as u64));
+                    for (signal, byte) in block.iter().zip(bytes) {
+                        ctx.assign(*signal, Fr::from(byte as u64));
+                    }
+                    padding.wg(ctx, left);
+                    if early {
+                        ctx.assign(padding.is_final(), Fr::ONE);
+                    }
+                })
+            });
+
+            ctx.trace(move |ctx, (message, tamper): (Vec<u8>, Tamper)| {
+                let mut left = message.len();
+                for (i, mut bytes) in PaddingRule::keccak()
+                    .pad(&message, BLOCK_BYTES)
+                    .into_iter()
+                    .enumerate()
+                {
+                    if tamper == Tamper::PaddingByte {
+                        *bytes.last_mut().unwrap() ^= 1;
+                    }
+                    ctx.add(
+                        &absorb,
+                        (left, bytes, i == 0 && tamper == Tamper::EarlyFinal),
+                    );
+                    left = left.saturating_sub(BLOCK_BYTES);
+                }
+            });
+        });
+
+        for len in [0, 3, 7, 8, 13, 16] {
+            let message: Vec<u8> = (0..len).map(|i| i as u8 + 1).collect();
+            assert!(satisfied(&circuit, (message, Tamper::None)), "{}", len);
+        }
+        let message: Vec<u8> = (1..=13).collect();
+        assert!(!satisfied(&circuit, (message.clone(), Tamper::PaddingByte)));
+        assert!(!satisfied(&circuit, (message, Tamper::EarlyFinal)));
+    }
+}
@@ -0,0 +1,373 @@
+use std::hash::Hash;
+
+use num_bigint::BigUint;
+
+use crate::{
+    field::Field,
+    frontend::dsl::{
+        cb::{eq, Constraint},
+        StepTypeContext, StepTypeSetupContext,
+    },
+    poly::{Expr, ToExpr},
+    sbpir::{query::Queriable, PIR},
+    wit_gen::StepInstance,
+};
+
+use super::ecc::{
+    constant_point, field_point, CurveParams, NativePointAdd, NativePointDouble, Point,
+};
+
+/// Generators of a Pedersen commitment `C = m_0 * G_0 + ... + m_{n-1} * G_{n-1} + r * H` to the
+/// messages `m_i` with the randomness `r`. Nobody must know the discrete logarithms of the
+/// generators with respect to each other, or the commitment is not binding.
+#[derive(Clone, Debug)]
+pub struct PedersenParams {
+    pub curve: CurveParams,
+    pub generators: Vec<Point>,
+    pub blinding: Point,
+}
+
+impl PedersenParams {
+    pub fn new(curve: CurveParams, generators: Vec<Point>, blinding: Point) -> PedersenParams {
+        assert!(
+            !generators.is_empty(),
+            "pedersen needs at least one generator"
+        );
+        for (i, point) in generators.iter().chain([&blinding]).enumerate() {
+            assert!(
+                curve.is_on_curve(point),
+                "generator {} is not on the curve",
+                i
+            );
+        }
+
+        PedersenParams {
+            curve,
+            generators,
+            blinding,
+        }
+    }
+
+    /// Derives `num_generators` generators and the blinding one by hashing `domain` to the curve,
+    /// see `CurveParams::hash_to_point`.
+    pub fn derive(curve: CurveParams, num_generators: usize, domain: &str) -> PedersenParams {
+        let generators = (0..num_generators)
+            .map(|i| curve.hash_to_point(format!("{}/{}", domain, i).as_bytes()))
+            .collect();
+        let blinding = curve.hash_to_point(format!("{}/blinding", domain).as_bytes());
+
+        PedersenParams::new(curve, generators, blinding)
+    }
+
+    /// Computes the commitment natively, `None` being the point at infinity.
+    pub fn commit(&self, messages: &[BigUint], randomness: &BigUint) -> Option<Point> {
+        assert_eq!(
+            messages.len(),
+            self.generators.len(),
+            "wrong number of messages"
+        );
+
+        messages
+            .iter()
+            .zip(self.generators.iter())
+            .chain([(randomness, &self.blinding)])
+            .fold(None, |acc, (scalar, point)| {
+                self.curve.add(acc, self.curve.mul(scalar, point))
+            })
+    }
+}
+
+/// In-step Pedersen commitment, for a curve whose base field is the circuit field, like Grumpkin in
+/// circuits over `bn256::Fr`. The scalars are decomposed in `num_bits` bits and multiplied at once,
+/// processing the bits from the most significant one with a doubling and an addition per scalar,
+/// starting from the offset point of the curve and removing it at the end. It adds three signals
+/// per bit plus seven per bit and scalar.
+///
+/// `num_bits` must be smaller than the size of the field and the group order, so that the openings
+/// are unique. The commitment must not be the point at infinity, which is negligible when the
+/// randomness is uniform.
+#[derive(Clone)]
+pub struct PedersenCommitment<F> {
+    generators: Vec<Point>,
+    offset: Point,
+    correction_point: Point,
+    bits: Vec<Vec<Queriable<F>>>,
+    doubles: Vec<NativePointDouble<F>>,
+    adds: Vec<Vec<NativePointAdd<F>>>,
+    accs: Vec<Vec<(Queriable<F>, Queriable<F>)>>,
+    correction: NativePointAdd<F>,
+}
+
+impl<F: Field + From<u64>> PedersenCommitment<F> {
+    pub fn new(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        params: &PedersenParams,
+        num_bits: usize,
+    ) -> PedersenCommitment<F> {
+        assert!(num_bits > 0, "pedersen needs at least one bit");
+
+        let generators: Vec<Point> = params
+            .generators
+            .iter()
+            .chain([&params.blinding])
+            .cloned()
+            .collect();
+
+        let bits = (0..generators.len())
+            .map(|i| {
+                (0..num_bits)
+                    .map(|j| ctx.internal(format!("{}.bits[{}][{}]", name, i, j).as_str()))
+                    .collect()
+            })
+            .collect();
+        let doubles = (0..num_bits)
+            .map(|j| {
+                NativePointDouble::new(
+                    ctx,
+                    format!("{}.double[{}]", name, j).as_str(),
+                    &params.curve,
+                )
+            })
+            .collect();
+        let adds = (0..num_bits)
+            .map(|j| {
+                (0..generators.len())
+                    .map(|i| {
+                        NativePointAdd::new(ctx, format!("{}.add[{}][{}]", name, j, i).as_str())
+                    })
+                    .collect()
+            })
+            .collect();
+        let accs = (0..num_bits)
+            .map(|j| {
+                (0..generators.len())
+                    .map(|i| {
+                        (
+                            ctx.internal(format!("{}.acc[{}][{}].x", name, j, i).as_str()),
+                            ctx.internal(format!("{}.acc[{}][{}].y", name, j, i).as_str()),
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+
+        PedersenCommitment {
+            generators,
+            offset: params.curve.offset.clone(),
+            correction_point: params.curve.offset_correction(num_bits),
+            bits,
+            doubles,
+            adds,
+            accs,
+            correction: NativePointAdd::new(ctx, format!("{}.correction", name).as_str()),
+        }
+    }
+
+    /// Constrains `output` to be the commitment to `messages` with `randomness`.
+    #[track_caller]
+    pub fn setup<V: Into<Constraint<F>> + Clone>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        messages: &[V],
+        randomness: V,
+    ) {
+        assert_eq!(
+            messages.len() + 1,
+            self.generators.len(),
+            "wrong number of messages"
+        );
+
+        for (scalar, bits) in messages.iter().chain([&randomness]).zip(self.bits.iter()) {
+            let mut coeff = F::ONE;
+            let mut recomposed: PIR<F> = 0u64.expr();
+            for bit in bits.iter() {
+                ctx.constr(eq(*bit * (1u64.expr() - *bit), 0u64));
+                recomposed = recomposed + Expr::Const(coeff) * *bit;
+                coeff = coeff + coeff;
+            }
+            ctx.constr(eq(scalar.clone(), recomposed));
+        }
+
+        let num_bits = self.doubles.len();
+        let mut acc = constant_point(&self.offset);
+        for (j, ((double, adds), accs)) in self
+            .doubles
+            .iter()
+            .zip(self.adds.iter())
+            .zip(self.accs.iter())
+            .enumerate()
+        {
+            double.setup(ctx, acc);
+            let doubled = double.output();
+            acc = (doubled.0.expr(), doubled.1.expr());
+
+            for (i, (add, next)) in adds.iter().zip(accs.iter()).enumerate() {
+                let bit = self.bits[i][num_bits - 1 - j];
+                add.setup(ctx, acc.clone(), constant_point(&self.generators[i]));
+                let added = add.output();
+
+                ctx.constr(eq(next.0, acc.0.clone() + bit * (added.0 - acc.0.clone())));
+                ctx.constr(eq(next.1, acc.1.clone() + bit * (added.1 - acc.1.clone())));
+                acc = (next.0.expr(), next.1.expr());
+            }
+        }
+
+        self.correction
+            .setup(ctx, acc, constant_point(&self.correction_point));
+    }
+
+    pub fn output(&self) -> (Queriable<F>, Queriable<F>) {
+        self.correction.output()
+    }
+}
+
+impl<F: Field + From<u64> + Hash> PedersenCommitment<F> {
+    /// Assigns the signals for the commitment to `messages` with `randomness` and returns it.
+    pub fn wg(
+        &self,
+        ctx: &mut StepInstance<F>,
+        messages: &[BigUint],
+        randomness: &BigUint,
+    ) -> (F, F) {
+        assert_eq!(
+            messages.len() + 1,
+            self.generators.len(),
+            "wrong number of messages"
+        );
+        let num_bits = self.doubles.len();
+
+        let scalars: Vec<&BigUint> = messages.iter().chain([randomness]).collect();
+        for (scalar, bits) in scalars.iter().zip(self.bits.iter()) {
+            assert!(
+                scalar.bits() <= num_bits as u64,
+                "scalar does not fit in {} bits",
+                num_bits
+            );
+            for (j, bit) in bits.iter().enumerate() {
+                ctx.assign(*bit, F::from(scalar.bit(j as u64) as u64));
+            }
+        }
+
+        let generators: Vec<(F, F)> = self.generators.iter().map(field_point).collect();
+        let mut acc = field_point(&self.offset);
+        for (j, ((double, adds), accs)) in self
+            .doubles
+            .iter()
+            .zip(self.adds.iter())
+            .zip(self.accs.iter())
+            .enumerate()
+        {
+            acc = double.wg(ctx, acc);
+
+            for (i, (add, next)) in adds.iter().zip(accs.iter()).enumerate() {
+                let added = add.wg(ctx, acc, generators[i]);
+                if scalars[i].bit((num_bits - 1 - j) as u64) {
+                    acc = added;
+                }
+                ctx.assign(next.0, acc.0);
+                ctx.assign(next.1, acc.1);
+            }
+        }
+
+        self.correction
+            .wg(ctx, acc, field_point(&self.correction_point))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{frontend::dsl::circuit, sbpir::SBPIR, wit_gen::TraceGenerator};
+
+    type Args = ([u64; 2], u64, bool);
+
+    fn satisfied(circuit: &SBPIR<Fr, Args>, args: Args) -> bool {
+        let step_type = circuit.step_types.values().next().unwrap();
+        let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), 1).generate(args);
+        let assignments = &trace.step_instances[0].assignments;
+
+        step_type
+            .constraints
+            .iter()
+            .all(|c| c.expr.eval(assignments) == Some(Fr::ZERO))
+    }
+
+    #[test]
+    fn test_pedersen_params() {
+        let params = PedersenParams::derive(CurveParams::grumpkin(), 2, "chiquito pedersen test");
+        assert_ne!(params.generators[0], params.generators[1]);
+        assert_ne!(params.generators[1], params.blinding);
+
+        let one = BigUint::from(1u64);
+        let two = BigUint::from(2u64);
+        let curve = &params.curve;
+        assert_eq!(
+            params.commit(&[one.clone(), two.clone()], &one),
+            curve.add(
+                curve.add(
+                    Some(params.generators[0].clone()),
+                    curve.mul(&two, &params.generators[1])
+                ),
+                Some(params.blinding.clone())
+            )
+        );
+        // hiding: the same messages with different randomness
+        assert_ne!(
+            params.commit(&[one.clone(), two.clone()], &one),
+            params.commit(&[one, two.clone()], &two)
+        );
+    }
+
+    #[test]
+    fn test_pedersen_commitment() {
+        let params = PedersenParams::derive(CurveParams::grumpkin(), 2, "chiquito pedersen test");
+
+        let setup_params = params.clone();
+        let circuit: SBPIR<Fr, Args> = circuit("pedersen", |ctx| {
+            let messages = [ctx.forward("m0"), ctx.forward("m1")];
+            let randomness = ctx.forward("randomness");
+            let commitment = (ctx.forward("commitment.x"), ctx.forward("commitment.y"));
+
+            let open = ctx.step_type_def("open", |ctx| {
+                let pedersen = PedersenCommitment::new(ctx, "pedersen", &setup_params, 8);
+
+                let setup_pedersen = pedersen.clone();
+                ctx.setup(move |ctx| {
+                    setup_pedersen.setup(ctx, &messages, randomness);
+                    let output = setup_pedersen.output();
+                    ctx.constr(eq(commitment.0, output.0));
+                    ctx.constr(eq(commitment.1, output.1));
+                });
+
+                let params = setup_params.clone();
+                ctx.wg(move |ctx, (values, r, tamper): Args| {
+                    for (message, value) in messages.iter().zip(values) {
+                        ctx.assign(*message, Fr::from(value));
+                    }
+                    ctx.assign(randomness, Fr::from(r));
+
+                    let values: Vec<BigUint> = values.into_iter().map(BigUint::from).collect();
+                    let output = pedersen.wg(ctx, &values, &BigUint::from(r));
+                    let expected = params.commit(&values, &BigUint::from(r)).unwrap();
+                    assert_eq!(output, field_point(&expected));
+
+                    ctx.assign(commitment.0, output.0);
+                    ctx.assign(commitment.1, output.1);
+                    if tamper {
+                        ctx.assign(commitment.1, -output.1);
+                    }
+                })
+            });
+
+            ctx.pragma_num_steps(1);
+            ctx.trace(move |ctx, args| ctx.add(&open, args));
+        });
+
+        assert!(satisfied(&circuit, ([0x5a, 0xff], 0xc3, false)));
+        assert!(satisfied(&circuit, ([0, 1], 0x81, false)));
+        assert!(!satisfied(&circuit, ([0x5a, 0xff], 0xc3, true)));
+    }
+}
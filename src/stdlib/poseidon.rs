@@ -0,0 +1,777 @@
+use std::{collections::VecDeque, hash::Hash, rc::Rc};
+
+use num_bigint::BigUint;
+
+use crate::{
+    field::Field,
+    frontend::dsl::{
+        cb::{eq, Constraint},
+        StepTypeContext, StepTypeSetupContext,
+    },
+    poly::{Expr, ToExpr, ToField},
+    sbpir::{query::Queriable, PIR},
+    wit_gen::StepInstance,
+};
+
+/// Parameters of a Poseidon permutation over the field `F`. The round constants and the MDS matrix
+/// are field specific, `bn254_t3` returns the usual ones over the BN254 scalar field and `grain`
+/// generates them for other fields and widths as the reference implementation does.
+#[derive(Clone, Debug)]
+pub struct PoseidonParams<F> {
+    /// Width of the permutation state.
+    pub t: usize,
+    pub full_rounds: usize,
+    pub partial_rounds: usize,
+    /// Exponent of the S-box, usually 5.
+    pub alpha: u32,
+    /// Round constants, `t` per round.
+    pub round_constants: Vec<Vec<F>>,
+    /// `t` by `t` MDS matrix.
+    pub mds: Vec<Vec<F>>,
+}
+
+impl<F: Field> PoseidonParams<F> {
+    /// Creates the parameters from the round constants flattened in round order, which is the
+    /// layout used by the reference implementations.
+    pub fn new(
+        t: usize,
+        full_rounds: usize,
+        partial_rounds: usize,
+        round_constants: Vec<F>,
+        mds: Vec<Vec<F>>,
+    ) -> PoseidonParams<F> {
+        assert!(t > 1, "poseidon state width must be at least 2");
+        assert!(
+            full_rounds % 2 == 0,
+            "the number of full rounds must be even"
+        );
+        assert_eq!(
+            round_constants.len(),
+            t * (full_rounds + partial_rounds),
+            "wrong number of round constants"
+        );
+        assert!(
+            mds.len() == t && mds.iter().all(|row| row.len() == t),
+            "the MDS matrix must be {} by {}",
+            t,
+            t
+        );
+
+        PoseidonParams {
+            t,
+            full_rounds,
+            partial_rounds,
+            alpha: 5,
+            round_constants: round_constants.chunks(t).map(|c| c.to_vec()).collect(),
+            mds,
+        }
+    }
+
+    /// Generates the round constants and the MDS matrix of the permutation with the S-box `x^5`
+    /// over the prime field of the given modulus, with the Grain LFSR of the reference
+    /// implementation of Poseidon. The reference script also rejects the MDS matrices with
+    /// invariant subspace trails, which is not checked here, so the parameters of a new instance
+    /// must be compared against the reference ones.
+    pub fn grain(
+        modulus: &BigUint,
+        t: usize,
+        full_rounds: usize,
+        partial_rounds: usize,
+    ) -> PoseidonParams<F> {
+        let mut grain = Grain::new(modulus.bits(), t, full_rounds, partial_rounds);
+
+        let round_constants = (0..t * (full_rounds + partial_rounds))
+            .map(|_| grain.next_element(modulus).field())
+            .collect();
+
+        // Cauchy matrix of 2t distinct elements, 1 / (x_i + y_j)
+        let mds = loop {
+            let elements: Vec<F> = (0..2 * t)
+                .map(|_| (grain.next_int(modulus.bits()) % modulus).field())
+                .collect();
+            let (xs, ys) = elements.split_at(t);
+            let distinct = elements
+                .iter()
+                .enumerate()
+                .all(|(i, x)| !elements[..i].contains(x));
+            if distinct && xs.iter().all(|x| ys.iter().all(|y| *x + y != F::ZERO)) {
+                break xs
+                    .iter()
+                    .map(|x| ys.iter().map(|y| (*x + y).mi()).collect())
+                    .collect();
+            }
+        };
+
+        PoseidonParams::new(t, full_rounds, partial_rounds, round_constants, mds)
+    }
+
+    /// Parameters of the permutation of width 3 over the BN254 scalar field with 8 full rounds
+    /// and 57 partial rounds, `poseidonperm_x5_254_3` in the reference implementation, which
+    /// circomlib uses to hash two inputs. `F` must be the BN254 scalar field, like `bn256::Fr`.
+    pub fn bn254_t3() -> PoseidonParams<F> {
+        let modulus = BigUint::parse_bytes(
+            b"21888242871839275222246405745257275088548364400416034343698204186575808495617",
+            10,
+        )
+        .expect("decimal constant");
+
+        Self::grain(&modulus, 3, 8, 57)
+    }
+
+    pub fn num_rounds(&self) -> usize {
+        self.full_rounds + self.partial_rounds
+    }
+
+    /// Full rounds are placed half at the beginning and half at the end of the permutation.
+    pub fn is_full_round(&self, round: usize) -> bool {
+        round < self.full_rounds / 2 || round >= self.full_rounds / 2 + self.partial_rounds
+    }
+
+    fn num_sboxes(&self, round: usize) -> usize {
+        if self.is_full_round(round) {
+            self.t
+        } else {
+            1
+        }
+    }
+
+    /// Computes the permutation natively.
+    pub fn permute(&self, state: &[F]) -> Vec<F> {
+        self.rounds(state).pop().expect("at least one round").1
+    }
+
+    /// Hashes up to `t - 1` inputs. The state is initialized with a zero capacity element followed
+    /// by the inputs, and the first element of the permuted state is returned.
+    pub fn hash(&self, inputs: &[F]) -> F {
+        self.permute(&self.initial_state(inputs))[0]
+    }
+
+    /// Hashes any number of inputs with a sponge, see `sponge_blocks`. The state starts at zero,
+    /// every block is added to the `t - 1` elements after the capacity element before a
+    /// permutation, and the first element of the final state is returned. As the inputs are
+    /// always padded, it differs from `hash` for up to `t - 1` inputs.
+    pub fn hash_sponge(&self, inputs: &[F]) -> F {
+        let mut state = vec![F::ZERO; self.t];
+        for block in self.sponge_blocks(inputs).iter() {
+            state = self.permute(&absorb(&state, block));
+        }
+
+        state[0]
+    }
+
+    /// Pads the inputs of the sponge with a one and the zeros up to a multiple of `t - 1`, so
+    /// inputs of different lengths are not padded to the same blocks, and splits them in blocks
+    /// of `t - 1` elements.
+    pub fn sponge_blocks<V: Clone + From<u64>>(&self, inputs: &[V]) -> Vec<Vec<V>> {
+        let rate = self.t - 1;
+        let mut padded = inputs.to_vec();
+        padded.push(V::from(1));
+        padded.resize(padded.len().div_ceil(rate) * rate, V::from(0));
+
+        padded.chunks(rate).map(|block| block.to_vec()).collect()
+    }
+
+    fn initial_state(&self, inputs: &[F]) -> Vec<F> {
+        assert!(inputs.len() < self.t, "too many inputs for poseidon width");
+
+        let mut state = vec![F::ZERO; self.t];
+        state[1..=inputs.len()].copy_from_slice(inputs);
+
+        state
+    }
+
+    /// Returns the S-box outputs and the state after every round.
+    fn rounds(&self, state: &[F]) -> Vec<(Vec<F>, Vec<F>)> {
+        assert_eq!(state.len(), self.t, "wrong poseidon state width");
+
+        let mut state = state.to_vec();
+        let mut rounds = Vec::with_capacity(self.num_rounds());
+
+        for round in 0..self.num_rounds() {
+            let mut after_sbox: Vec<F> = state
+                .iter()
+                .zip(self.round_constants[round].iter())
+                .map(|(s, c)| *s + c)
+                .collect();
+
+            let sboxes: Vec<F> = (0..self.num_sboxes(round))
+                .map(|i| {
+                    after_sbox[i] = after_sbox[i].pow([self.alpha as u64]);
+                    after_sbox[i]
+                })
+                .collect();
+
+            state = self
+                .mds
+                .iter()
+                .map(|row| row.iter().zip(after_sbox.iter()).map(|(m, s)| *m * s).sum())
+                .collect();
+
+            rounds.push((sboxes, state.clone()));
+        }
+
+        rounds
+    }
+}
+
+/// Adds a block of the sponge to the elements after the capacity element of the state.
+fn absorb<V: Clone + std::ops::Add<Output = V>>(state: &[V], block: &[V]) -> Vec<V> {
+    let mut state = state.to_vec();
+    for (element, value) in state[1..].iter_mut().zip(block.iter()) {
+        *element = element.clone() + value.clone();
+    }
+
+    state
+}
+
+/// Grain LFSR of the reference implementation of Poseidon, seeded with the parameters of the
+/// instance, which generates its round constants and MDS matrix.
+struct Grain {
+    bits: VecDeque<bool>,
+}
+
+impl Grain {
+    fn new(field_bits: u64, t: usize, full_rounds: usize, partial_rounds: usize) -> Grain {
+        // prime field, S-box x^alpha, the field size and the number of cells and rounds
+        let seed = [
+            (1, 2),
+            (0, 4),
+            (field_bits, 12),
+            (t as u64, 12),
+            (full_rounds as u64, 10),
+            (partial_rounds as u64, 10),
+        ];
+        let mut bits: VecDeque<bool> = seed
+            .iter()
+            .flat_map(|(value, width)| (0..*width).rev().map(move |i| (value >> i) & 1 == 1))
+            .collect();
+        bits.extend([true; 30]);
+
+        let mut grain = Grain { bits };
+        for _ in 0..160 {
+            grain.step();
+        }
+
+        grain
+    }
+
+    fn step(&mut self) -> bool {
+        let bit = [62, 51, 38, 23, 13, 0]
+            .iter()
+            .fold(false, |bit, i| bit ^ self.bits[*i]);
+        self.bits.pop_front();
+        self.bits.push_back(bit);
+
+        bit
+    }
+
+    /// Returns the second bit of the next pair of bits whose first bit is set.
+    fn next_bit(&mut self) -> bool {
+        loop {
+            let keep = self.step();
+            let bit = self.step();
+            if keep {
+                return bit;
+            }
+        }
+    }
+
+    /// Returns the integer of the next `num_bits` bits, the most significant first.
+    fn next_int(&mut self, num_bits: u64) -> BigUint {
+        (0..num_bits).fold(BigUint::from(0u64), |value, _| {
+            (value << 1usize) + BigUint::from(self.next_bit() as u64)
+        })
+    }
+
+    /// Returns the next integer of the size of the modulus that is smaller than it.
+    fn next_element(&mut self, modulus: &BigUint) -> BigUint {
+        loop {
+            let value = self.next_int(modulus.bits());
+            if value < *modulus {
+                return value;
+            }
+        }
+    }
+}
+
+/// In-step gadget that constrains a full Poseidon permutation. Every round adds internal signals
+/// for the S-box outputs and for the resulting state, so all the constraints have degree `alpha`
+/// at most, but the step type gets wide. With `PoseidonParams::bn254_t3` it uses 276 signals.
+#[derive(Clone)]
+pub struct Poseidon<F> {
+    params: Rc<PoseidonParams<F>>,
+    sboxes: Vec<Vec<Queriable<F>>>,
+    states: Vec<Vec<Queriable<F>>>,
+}
+
+impl<F: Field + From<u64>> Poseidon<F> {
+    /// Adds the round signals to the step type, annotated with the given name.
+    pub fn new(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        params: Rc<PoseidonParams<F>>,
+    ) -> Poseidon<F> {
+        let sboxes = (0..params.num_rounds())
+            .map(|round| {
+                (0..params.num_sboxes(round))
+                    .map(|i| ctx.internal(format!("{}.sbox[{}][{}]", name, round, i).as_str()))
+                    .collect()
+            })
+            .collect();
+        let states = (0..params.num_rounds())
+            .map(|round| {
+                (0..params.t)
+                    .map(|i| ctx.internal(format!("{}.state[{}][{}]", name, round, i).as_str()))
+                    .collect()
+            })
+            .collect();
+
+        Poseidon {
+            params,
+            sboxes,
+            states,
+        }
+    }
+
+    /// Constrains the permutation of `initial_state`, which must have `t` elements.
+    #[track_caller]
+    pub fn setup<V: Into<Constraint<F>> + Clone>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        initial_state: &[V],
+    ) {
+        assert_eq!(
+            initial_state.len(),
+            self.params.t,
+            "wrong poseidon state width"
+        );
+
+        let mut state: Vec<PIR<F>> = initial_state
+            .iter()
+            .map(|v| v.clone().into().expr)
+            .collect();
+
+        for round in 0..self.params.num_rounds() {
+            let mut after_sbox: Vec<PIR<F>> = state
+                .into_iter()
+                .zip(self.params.round_constants[round].iter())
+                .map(|(s, c)| s + Expr::Const(*c))
+                .collect();
+
+            for (i, sbox) in self.sboxes[round].iter().enumerate() {
+                ctx.constr(eq(
+                    *sbox,
+                    Expr::Pow(Box::new(after_sbox[i].clone()), self.params.alpha),
+                ));
+                after_sbox[i] = sbox.expr();
+            }
+
+            for (row, out) in self.params.mds.iter().zip(self.states[round].iter()) {
+                let mix = row
+                    .iter()
+                    .zip(after_sbox.iter())
+                    .fold(0u64.expr(), |acc, (m, s)| acc + Expr::Const(*m) * s.clone());
+
+                ctx.constr(eq(*out, mix));
+            }
+
+            state = self.states[round].iter().map(|s| s.expr()).collect();
+        }
+    }
+
+    /// Constrains the hash of `inputs`, see `PoseidonParams::hash`. The result is `hash_output`.
+    #[track_caller]
+    pub fn setup_hash<V: Into<Constraint<F>> + Clone>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        inputs: &[V],
+    ) {
+        assert!(
+            inputs.len() < self.params.t,
+            "too many inputs for poseidon width"
+        );
+
+        let mut state: Vec<Constraint<F>> = vec![0u64.into()];
+        state.extend(inputs.iter().map(|v| v.clone().into()));
+        state.resize(self.params.t, 0u64.into());
+
+        self.setup(ctx, &state);
+    }
+
+    /// Returns the signals holding the permuted state.
+    pub fn output(&self) -> &[Queriable<F>] {
+        self.states.last().expect("at least one round")
+    }
+
+    /// Returns the signal holding the hash when using `setup_hash`.
+    pub fn hash_output(&self) -> Queriable<F> {
+        self.output()[0]
+    }
+}
+
+impl<F: Field + From<u64> + Hash> Poseidon<F> {
+    /// Assigns the round signals for the permutation of `initial_state` and returns the permuted
+    /// state.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, initial_state: &[F]) -> Vec<F> {
+        let rounds = self.params.rounds(initial_state);
+
+        for (round, (sboxes, state)) in rounds.iter().enumerate() {
+            for (signal, value) in self.sboxes[round].iter().zip(sboxes.iter()) {
+                ctx.assign(*signal, *value);
+            }
+            for (signal, value) in self.states[round].iter().zip(state.iter()) {
+                ctx.assign(*signal, *value);
+            }
+        }
+
+        rounds.last().expect("at least one round").1.clone()
+    }
+
+    /// Assigns the round signals for the hash of `inputs` and returns the hash.
+    pub fn wg_hash(&self, ctx: &mut StepInstance<F>, inputs: &[F]) -> F {
+        self.wg(ctx, &self.params.initial_state(inputs))[0]
+    }
+}
+
+/// In-step gadget that constrains the sponge hash of a fixed number of inputs, see
+/// `PoseidonParams::hash_sponge`, with a `Poseidon` permutation per block of `t - 1` inputs.
+#[derive(Clone)]
+pub struct PoseidonSponge<F> {
+    params: Rc<PoseidonParams<F>>,
+    num_inputs: usize,
+    permutations: Vec<Poseidon<F>>,
+}
+
+impl<F: Field + From<u64>> PoseidonSponge<F> {
+    /// Adds the signals of the permutations of the blocks of `num_inputs` inputs to the step
+    /// type, annotated with the given name.
+    pub fn new(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        params: Rc<PoseidonParams<F>>,
+        num_inputs: usize,
+    ) -> PoseidonSponge<F> {
+        // the inputs and the padding one
+        let num_blocks = (num_inputs + 1).div_ceil(params.t - 1);
+        let permutations = (0..num_blocks)
+            .map(|block| {
+                Poseidon::new(
+                    ctx,
+                    format!("{}.block[{}]", name, block).as_str(),
+                    params.clone(),
+                )
+            })
+            .collect();
+
+        PoseidonSponge {
+            params,
+            num_inputs,
+            permutations,
+        }
+    }
+
+    /// Constrains the sponge hash of `inputs`. The result is `hash_output`.
+    #[track_caller]
+    pub fn setup<V: Into<Constraint<F>> + Clone>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        inputs: &[V],
+    ) {
+        assert_eq!(
+            inputs.len(),
+            self.num_inputs,
+            "wrong number of poseidon sponge inputs"
+        );
+
+        let inputs: Vec<Constraint<F>> = inputs.iter().map(|v| v.clone().into()).collect();
+        let mut state: Vec<PIR<F>> = vec![0u64.expr(); self.params.t];
+        for (block, permutation) in self
+            .params
+            .sponge_blocks(&inputs)
+            .into_iter()
+            .zip(self.permutations.iter())
+        {
+            let block: Vec<PIR<F>> = block.into_iter().map(|v| v.expr).collect();
+            permutation.setup(ctx, &absorb(&state, &block));
+            state = permutation.output().iter().map(|s| s.expr()).collect();
+        }
+    }
+
+    /// Returns the signal holding the hash.
+    pub fn hash_output(&self) -> Queriable<F> {
+        self.permutations
+            .last()
+            .expect("at least one block")
+            .hash_output()
+    }
+}
+
+impl<F: Field + From<u64> + Hash> PoseidonSponge<F> {
+    /// Assigns the signals of the permutations for the sponge hash of `inputs` and returns the
+    /// hash.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, inputs: &[F]) -> F {
+        let mut state = vec![F::ZERO; self.params.t];
+        for (block, permutation) in self
+            .params
+            .sponge_blocks(inputs)
+            .iter()
+            .zip(self.permutations.iter())
+        {
+            state = permutation.wg(ctx, &absorb(&state, block));
+        }
+
+        state[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        frontend::dsl::circuit,
+        sbpir::{StepType, SBPIR},
+        wit_gen::TraceGenerator,
+    };
+
+    fn hex(value: &str) -> Fr {
+        BigUint::parse_bytes(value.as_bytes(), 16)
+            .expect("hex constant")
+            .field()
+    }
+
+    fn assert_satisfied(step_type: &StepType<Fr>, assignments: &HashMap<Queriable<Fr>, Fr>) {
+        for constraint in step_type.constraints.iter() {
+            assert_eq!(
+                constraint.expr.eval(assignments),
+                Some(Fr::ZERO),
+                "{}",
+                constraint.annotation
+            );
+        }
+    }
+
+    fn toy_params() -> PoseidonParams<Fr> {
+        let t = 3;
+        let round_constants = (0..t * (4 + 3)).map(|i| Fr::from(i as u64 + 1)).collect();
+        let mds = (0..t)
+            .map(|i| (0..t).map(|j| Fr::from((i + j) as u64 + 1).mi()).collect())
+            .collect();
+
+        PoseidonParams::new(t, 4, 3, round_constants, mds)
+    }
+
+    #[test]
+    fn test_poseidon_rounds() {
+        let params = toy_params();
+
+        assert_eq!(params.num_rounds(), 7);
+        assert!(params.is_full_round(0));
+        assert!(params.is_full_round(1));
+        assert!(!params.is_full_round(2));
+        assert!(!params.is_full_round(4));
+        assert!(params.is_full_round(5));
+        assert_eq!(params.rounds(&[Fr::ZERO; 3])[2].0.len(), 1);
+        assert_ne!(
+            params.hash(&[Fr::from(1), Fr::from(2)]),
+            params.hash(&[Fr::from(2), Fr::from(1)])
+        );
+    }
+
+    #[test]
+    fn test_poseidon_bn254_t3() {
+        let params = PoseidonParams::<Fr>::bn254_t3();
+
+        assert_eq!(
+            params.round_constants[0][0],
+            hex("0ee9a592ba9a9518d05986d656f40c2114c4993c11bb29938d21d47304cd8e6e")
+        );
+        assert_eq!(
+            params.mds[0][0],
+            hex("109b7f411ba0e4c9b2b70caf5c36a7b194be7c11ad24378bfedb68592ba8118b")
+        );
+
+        // test vector of poseidonperm_x5_254_3 in the reference implementation
+        assert_eq!(
+            params.permute(&[Fr::from(0), Fr::from(1), Fr::from(2)]),
+            vec![
+                hex("115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a"),
+                hex("0fca49b798923ab0239de1c9e7a4a9a2210312b6a2f616d18b5a87f9b628ae29"),
+                hex("0e7ae82e40091e63cbd4f16a6d16310b3729d4b6e138fcf54110e2867045a30c"),
+            ]
+        );
+        assert_eq!(
+            params.hash(&[Fr::from(1), Fr::from(2)]),
+            hex("115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a")
+        );
+    }
+
+    #[test]
+    fn test_poseidon_sponge() {
+        let params = toy_params();
+        let inputs: Vec<Fr> = (1..=5).map(Fr::from).collect();
+
+        let blocks = params.sponge_blocks(&inputs);
+        assert_eq!(
+            blocks,
+            vec![
+                vec![Fr::from(1), Fr::from(2)],
+                vec![Fr::from(3), Fr::from(4)],
+                vec![Fr::from(5), Fr::ONE],
+            ]
+        );
+        assert_eq!(params.sponge_blocks(&inputs[..4]).len(), 3);
+
+        let mut state = vec![Fr::ZERO; 3];
+        for block in blocks.iter() {
+            state[1] += block[0];
+            state[2] += block[1];
+            state = params.permute(&state);
+        }
+        assert_eq!(params.hash_sponge(&inputs), state[0]);
+        assert_ne!(
+            params.hash_sponge(&inputs[..4]),
+            params.hash_sponge(&[&inputs[..4], &[Fr::ONE]].concat())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "wrong number of round constants")]
+    fn test_poseidon_params_wrong_constants() {
+        PoseidonParams::new(3, 8, 57, vec![Fr::ONE; 3], vec![vec![Fr::ONE; 3]; 3]);
+    }
+
+    #[test]
+    fn test_poseidon_gadget() {
+        let params = Rc::new(toy_params());
+        let expected = params.hash(&[Fr::from(1), Fr::from(2)]);
+
+        let circuit: SBPIR<Fr, ()> = circuit("poseidon", |ctx| {
+            let a = ctx.forward("a");
+            let b = ctx.forward("b");
+
+            let hash = ctx.step_type_def("hash", |ctx| {
+                let poseidon = Poseidon::new(ctx, "poseidon", params.clone());
+                let setup_poseidon = poseidon.clone();
+
+                ctx.setup(move |ctx| setup_poseidon.setup_hash(ctx, &[a, b]));
+
+                ctx.wg(move |ctx, _: ()| {
+                    ctx.assign(a, Fr::from(1));
+                    ctx.assign(b, Fr::from(2));
+                    poseidon.wg_hash(ctx, &[Fr::from(1), Fr::from(2)]);
+                })
+            });
+
+            ctx.pragma_num_steps(1);
+            ctx.trace(move |ctx, _| ctx.add(&hash, ()));
+        });
+
+        let step_type = circuit.step_types.values().next().unwrap();
+        assert_eq!(step_type.signals.len(), 4 * 3 + 3 + 7 * 3);
+
+        let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), 1).generate(());
+        let assignments = &trace.step_instances[0].assignments;
+        assert_satisfied(step_type, assignments);
+
+        let output = step_type
+            .signals
+            .iter()
+            .find(|signal| signal.annotation() == "poseidon.state[6][0]")
+            .unwrap();
+        assert_eq!(assignments[&Queriable::Internal(*output)], expected);
+    }
+
+    #[test]
+    fn test_poseidon_gadget_bn254_t3() {
+        let params = Rc::new(PoseidonParams::<Fr>::bn254_t3());
+
+        let circuit: SBPIR<Fr, ()> = circuit("poseidon", |ctx| {
+            let hash = ctx.step_type_def("hash", |ctx| {
+                let poseidon = Poseidon::new(ctx, "poseidon", params.clone());
+                let setup_poseidon = poseidon.clone();
+
+                ctx.setup(move |ctx| setup_poseidon.setup_hash(ctx, &[1u64, 2u64]));
+
+                ctx.wg(move |ctx, _: ()| {
+                    poseidon.wg_hash(ctx, &[Fr::from(1), Fr::from(2)]);
+                })
+            });
+
+            ctx.pragma_num_steps(1);
+            ctx.trace(move |ctx, _| ctx.add(&hash, ()));
+        });
+
+        let step_type = circuit.step_types.values().next().unwrap();
+        assert_eq!(step_type.signals.len(), 276);
+
+        let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), 1).generate(());
+        let assignments = &trace.step_instances[0].assignments;
+        assert_satisfied(step_type, assignments);
+
+        let output = step_type
+            .signals
+            .iter()
+            .find(|signal| signal.annotation() == "poseidon.state[64][0]")
+            .unwrap();
+        assert_eq!(
+            assignments[&Queriable::Internal(*output)],
+            hex("115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a")
+        );
+    }
+
+    #[test]
+    fn test_poseidon_sponge_gadget() {
+        let params = Rc::new(toy_params());
+        let values: Vec<Fr> = (1..=5).map(Fr::from).collect();
+        let expected = params.hash_sponge(&values);
+
+        let circuit: SBPIR<Fr, Vec<Fr>> = circuit("sponge", |ctx| {
+            let inputs: Vec<Queriable<Fr>> = (0..5)
+                .map(|i| ctx.forward(format!("input[{}]", i).as_str()))
+                .collect();
+
+            let hash = ctx.step_type_def("hash", |ctx| {
+                let sponge = PoseidonSponge::new(ctx, "sponge", params.clone(), 5);
+                let setup_sponge = sponge.clone();
+                let setup_inputs = inputs.clone();
+
+                ctx.setup(move |ctx| setup_sponge.setup(ctx, &setup_inputs));
+
+                ctx.wg(move |ctx, values: Vec<Fr>| {
+                    for (input, value) in inputs.iter().zip(values.iter()) {
+                        ctx.assign(*input, *value);
+                    }
+                    sponge.wg(ctx, &values);
+                })
+            });
+
+            ctx.pragma_num_steps(1);
+            ctx.trace(move |ctx, values| ctx.add(&hash, values));
+        });
+
+        let step_type = circuit.step_types.values().next().unwrap();
+        assert_eq!(step_type.signals.len(), 3 * (4 * 3 + 3 + 7 * 3));
+
+        let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), 1).generate(values);
+        let mut assignments = trace.step_instances[0].assignments.clone();
+        assert_satisfied(step_type, &assignments);
+
+        let output = step_type
+            .signals
+            .iter()
+            .find(|signal| signal.annotation() == "sponge.block[2].state[6][0]")
+            .unwrap();
+        assert_eq!(assignments[&Queriable::Internal(*output)], expected);
+
+        let input = Queriable::Forward(circuit.forward_signals[4], false);
+        assignments.insert(input, Fr::from(6));
+        assert!(step_type
+            .constraints
+            .iter()
+            .any(|constraint| constraint.expr.eval(&assignments) != Some(Fr::ZERO)));
+    }
+}
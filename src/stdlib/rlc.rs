@@ -0,0 +1,235 @@
+use std::hash::Hash;
+
+use crate::{
+    field::Field,
+    frontend::dsl::{
+        cb::{eq, Constraint},
+        CircuitContext, StepTypeSetupContext,
+    },
+    poly::ToExpr,
+    sbpir::{query::Queriable, PIR},
+    wit_gen::StepInstance,
+};
+
+/// Returns the random linear combination `exprs[0] + exprs[1] * challenge + ... + exprs[n - 1] *
+/// challenge^(n - 1)`, evaluated with Horner's rule so that its degree grows by the degree of the
+/// challenge per expression.
+pub fn rlc<F: Field + From<u64>, V: Into<Constraint<F>>, C: Into<Constraint<F>>>(
+    exprs: Vec<V>,
+    challenge: C,
+) -> PIR<F> {
+    let challenge: PIR<F> = challenge.into().expr;
+
+    exprs.into_iter().rev().fold(0u64.expr(), |acc, expr| {
+        acc * challenge.clone() + expr.into().expr
+    })
+}
+
+/// Computes `rlc` natively.
+pub fn rlc_value<F: Field>(values: &[F], challenge: F) -> F {
+    values
+        .iter()
+        .rev()
+        .fold(F::ZERO, |acc, value| acc * challenge + *value)
+}
+
+/// Random linear combination of a value per step, accumulated across a step sequence in a forward
+/// signal, for table and bus arguments. The signal holds the combination of the values absorbed by
+/// the previous steps, so after absorbing `v_0, ..., v_{n-1}` it is `v_0 * challenge^(n - 1) + ...
+/// + v_{n-1}`.
+///
+/// The challenge is an expression and must come from the verifier for the combination to be
+/// binding. The accumulator is allocated in the given phase, which must be later than the phases
+/// of the absorbed values.
+#[derive(Clone)]
+pub struct RunningRlc<F> {
+    acc: Queriable<F>,
+    challenge: PIR<F>,
+}
+
+impl<F: Field + From<u64>> RunningRlc<F> {
+    pub fn new<TraceArgs, C: Into<Constraint<F>>>(
+        ctx: &mut CircuitContext<F, TraceArgs>,
+        name: &str,
+        phase: usize,
+        challenge: C,
+    ) -> RunningRlc<F> {
+        RunningRlc {
+            acc: ctx.forward_with_phase(name, phase),
+            challenge: challenge.into().expr,
+        }
+    }
+
+    /// Returns the accumulator signal, which holds the combination of the previous steps.
+    pub fn acc(&self) -> Queriable<F> {
+        self.acc
+    }
+
+    /// Constrains the accumulator to be empty, in the step that absorbs the first value.
+    #[track_caller]
+    pub fn setup_start(&self, ctx: &mut StepTypeSetupContext<F>) {
+        ctx.constr(eq(self.acc, 0u64));
+    }
+
+    /// Constrains the accumulator of the next step to absorb `value`.
+    #[track_caller]
+    pub fn setup_absorb<V: Into<Constraint<F>>>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        value: V,
+    ) {
+        ctx.transition(eq(self.acc.next(), self.absorbed(value)));
+    }
+
+    /// Returns the combination after absorbing `value` in this step, to be used in the last step
+    /// instead of `setup_absorb`.
+    pub fn absorbed<V: Into<Constraint<F>>>(&self, value: V) -> PIR<F> {
+        self.acc * self.challenge.clone() + value.into().expr
+    }
+}
+
+impl<F: Field + From<u64> + Hash> RunningRlc<F> {
+    /// Assigns the accumulator and returns its value for the next step.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, acc: F, value: F, challenge: F) -> F {
+        ctx.assign(self.acc, acc);
+
+        acc * challenge + value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        frontend::dsl::{circuit, lb::LookupTableRegistry, StepTypeContext},
+        poly::Expr,
+        sbpir::SBPIR,
+        wit_gen::TraceGenerator,
+    };
+
+    #[test]
+    fn test_rlc() {
+        let mut ctx =
+            StepTypeContext::<Fr>::new(0, "step".to_string(), LookupTableRegistry::default());
+        let signals: Vec<Queriable<Fr>> = (0..3)
+            .map(|i| ctx.internal(format!("s[{}]", i).as_str()))
+            .collect();
+        let challenge = ctx.internal("challenge");
+
+        let mut instance = StepInstance::new(0);
+        for (signal, value) in signals.iter().zip([3, 5, 7]) {
+            instance.assign(*signal, Fr::from(value));
+        }
+        instance.assign(challenge, Fr::from(10));
+
+        let expr = rlc(signals, challenge);
+        assert_eq!(expr.eval(&instance.assignments), Some(Fr::from(753)));
+        assert_eq!(
+            rlc_value(&[3, 5, 7].map(Fr::from), Fr::from(10)),
+            Fr::from(753)
+        );
+    }
+
+    fn satisfied(circuit: &SBPIR<Fr, Vec<u64>>, values: Vec<u64>) -> bool {
+        let num_steps = values.len();
+        let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), num_steps).generate(values);
+
+        trace
+            .step_instances
+            .iter()
+            .enumerate()
+            .all(|(i, instance)| {
+                let step_type = &circuit.step_types[&instance.step_type_uuid];
+                let mut assignments = instance.assignments.clone();
+                if let Some(next) = trace.step_instances.get(i + 1) {
+                    for (signal, value) in next.assignments.iter() {
+                        if let Queriable::Forward(forward, false) = signal {
+                            assignments.insert(Queriable::Forward(*forward, true), *value);
+                        }
+                    }
+                }
+
+                let transitions = step_type
+                    .transition_constraints
+                    .iter()
+                    .filter(|_| i + 1 < num_steps);
+                step_type
+                    .constraints
+                    .iter()
+                    .map(|c| &c.expr)
+                    .chain(transitions.map(|c| &c.expr))
+                    .all(|expr| expr.eval(&assignments) == Some(Fr::ZERO))
+            })
+    }
+
+    #[test]
+    fn test_running_rlc() {
+        let challenge = Fr::from(1000);
+
+        let circuit: SBPIR<Fr, Vec<u64>> = circuit("running rlc", |ctx| {
+            let value = ctx.forward("value");
+            let result = ctx.forward("result");
+            let running = RunningRlc::new(ctx, "acc", 1, Expr::Const(challenge));
+
+            let setup_running = running.clone();
+            let first = ctx.step_type_def("first", |ctx| {
+                let running = setup_running.clone();
+                ctx.setup(move |ctx| {
+                    running.setup_start(ctx);
+                    running.setup_absorb(ctx, value);
+                });
+
+                let running = setup_running.clone();
+                ctx.wg(move |ctx, (acc, v): (Fr, Fr)| {
+                    ctx.assign(value, v);
+                    running.wg(ctx, acc, v, challenge);
+                })
+            });
+            let middle = ctx.step_type_def("middle", |ctx| {
+                let running = setup_running.clone();
+                ctx.setup(move |ctx| running.setup_absorb(ctx, value));
+
+                let running = setup_running.clone();
+                ctx.wg(move |ctx, (acc, v): (Fr, Fr)| {
+                    ctx.assign(value, v);
+                    running.wg(ctx, acc, v, challenge);
+                })
+            });
+            let last = ctx.step_type_def("last", |ctx| {
+                let running = setup_running.clone();
+                ctx.setup(move |ctx| ctx.constr(eq(result, running.absorbed(value))));
+
+                let running = setup_running.clone();
+                ctx.wg(move |ctx, (acc, v, r): (Fr, Fr, Fr)| {
+                    ctx.assign(value, v);
+                    ctx.assign(result, r);
+                    running.wg(ctx, acc, v, challenge);
+                })
+            });
+
+            ctx.trace(move |ctx, values: Vec<u64>| {
+                let values: Vec<Fr> = values.into_iter().map(Fr::from).collect();
+                let n = values.len();
+                let mut acc = Fr::ZERO;
+                for (i, v) in values.iter().enumerate() {
+                    if i == 0 {
+                        ctx.add(&first, (acc, *v));
+                    } else if i + 1 < n {
+                        ctx.add(&middle, (acc, *v));
+                    } else {
+                        // a tampered result when the last value is zero
+                        let result = rlc_value(&[*v, acc], challenge)
+                            + if *v == Fr::ZERO { Fr::ONE } else { Fr::ZERO };
+                        ctx.add(&last, (acc, *v, result));
+                    }
+                    acc = acc * challenge + v;
+                }
+            });
+        });
+
+        assert!(satisfied(&circuit, vec![1, 2, 3, 4]));
+        assert!(!satisfied(&circuit, vec![1, 2, 3, 0]));
+    }
+}
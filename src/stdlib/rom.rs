@@ -0,0 +1,261 @@
+use std::{hash::Hash, rc::Rc};
+
+use crate::{
+    field::Field,
+    frontend::dsl::{
+        cb::{table, Constraint},
+        lb::LookupTable,
+        CircuitContext, StepTypeContext, StepTypeSetupContext,
+    },
+    sbpir::query::Queriable,
+    wit_gen::StepInstance,
+};
+
+/// Read-only program for zkVM-like machines, stored in a lookup table that maps every program
+/// counter in `[0, program.len())` to the fields of its instruction. The execution steps fetch the
+/// instruction at their program counter with an `InstructionFetch`.
+#[derive(Clone)]
+pub struct ProgramRom<F> {
+    table: LookupTable,
+    fields: Vec<String>,
+    program: Rc<Vec<Vec<F>>>,
+}
+
+impl<F: Field + From<u64> + Hash> ProgramRom<F> {
+    /// Creates the table, every instruction of `program` having a value per field. It sets the
+    /// number of steps of the circuit, so it is meant to be used in a dedicated fixed sub-circuit.
+    pub fn new<TraceArgs>(
+        ctx: &mut CircuitContext<F, TraceArgs>,
+        name: &str,
+        fields: &[&str],
+        program: Vec<Vec<F>>,
+    ) -> ProgramRom<F> {
+        assert!(
+            !program.is_empty(),
+            "program needs at least one instruction"
+        );
+        for (pc, instruction) in program.iter().enumerate() {
+            assert_eq!(
+                instruction.len(),
+                fields.len(),
+                "wrong number of fields in instruction {}",
+                pc
+            );
+        }
+
+        let pc = ctx.fixed(format!("{}.pc", name).as_str());
+        let columns: Vec<Queriable<F>> = fields
+            .iter()
+            .map(|field| ctx.fixed(format!("{}.{}", name, field).as_str()))
+            .collect();
+
+        let program = Rc::new(program);
+        ctx.pragma_num_steps(program.len());
+        let (fixed_program, fixed_columns) = (program.clone(), columns.clone());
+        ctx.fixed_gen(move |ctx| {
+            for (i, instruction) in fixed_program.iter().enumerate() {
+                ctx.assign(i, pc, F::from(i as u64));
+                for (column, value) in fixed_columns.iter().zip(instruction.iter()) {
+                    ctx.assign(i, *column, *value);
+                }
+            }
+        });
+
+        let table = ctx.new_table(
+            columns
+                .into_iter()
+                .fold(table().add(pc), |table, column| table.add(column)),
+        );
+
+        ProgramRom {
+            table,
+            fields: fields.iter().map(|field| field.to_string()).collect(),
+            program,
+        }
+    }
+
+    pub fn table(&self) -> LookupTable {
+        self.table
+    }
+
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    pub fn len(&self) -> usize {
+        self.program.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.program.is_empty()
+    }
+
+    /// Returns the instruction at `pc`.
+    pub fn instruction(&self, pc: usize) -> &[F] {
+        self.program
+            .get(pc)
+            .unwrap_or_else(|| panic!("program counter {} out of the program", pc))
+    }
+}
+
+/// Fetch of the instruction at the program counter of an execution step, with an internal signal
+/// per field annotated `name.field`.
+#[derive(Clone)]
+pub struct InstructionFetch<F> {
+    rom: ProgramRom<F>,
+    fields: Vec<Queriable<F>>,
+}
+
+impl<F: Field + From<u64> + Hash> InstructionFetch<F> {
+    pub fn new(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        rom: &ProgramRom<F>,
+    ) -> InstructionFetch<F> {
+        InstructionFetch {
+            rom: rom.clone(),
+            fields: rom
+                .fields
+                .iter()
+                .map(|field| ctx.internal(format!("{}.{}", name, field).as_str()))
+                .collect(),
+        }
+    }
+
+    /// Looks up the fields of the instruction at `pc` in the program table.
+    #[track_caller]
+    pub fn setup<V: Into<Constraint<F>>>(&self, ctx: &mut StepTypeSetupContext<F>, pc: V) {
+        ctx.add_lookup(
+            self.fields
+                .iter()
+                .fold(self.rom.table.apply(pc), |lookup, field| {
+                    lookup.apply(*field)
+                }),
+        );
+    }
+
+    /// Returns the signal of the field with the given name. Panics if there is none.
+    pub fn field(&self, name: &str) -> Queriable<F> {
+        let index = self
+            .rom
+            .fields
+            .iter()
+            .position(|field| field == name)
+            .unwrap_or_else(|| panic!("unknown instruction field {}", name));
+
+        self.fields[index]
+    }
+
+    pub fn fields(&self) -> &[Queriable<F>] {
+        &self.fields
+    }
+
+    /// Assigns the fields of the instruction at `pc` and returns them.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, pc: usize) -> Vec<F> {
+        let instruction = self.rom.instruction(pc).to_vec();
+        for (field, value) in self.fields.iter().zip(instruction.iter()) {
+            ctx.assign(*field, *value);
+        }
+
+        instruction
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        frontend::dsl::{cb::eq, circuit},
+        poly::Expr,
+        sbpir::SBPIR,
+        wit_gen::TraceGenerator,
+    };
+
+    #[test]
+    fn test_program_rom() {
+        // accumulates the immediates of the instructions with opcode 1
+        let program: Vec<Vec<Fr>> = [[1, 5], [0, 9], [1, 7]]
+            .iter()
+            .map(|instruction| instruction.iter().map(|v| Fr::from(*v)).collect())
+            .collect();
+
+        let circuit: SBPIR<Fr, ()> = circuit("rom", |ctx| {
+            let rom = ProgramRom::new(ctx, "rom", &["opcode", "immediate"], program.clone());
+            let pc = ctx.forward("pc");
+            let acc = ctx.forward("acc");
+
+            let exec = ctx.step_type_def("exec", |ctx| {
+                let fetch = InstructionFetch::new(ctx, "fetch", &rom);
+
+                let setup_fetch = fetch.clone();
+                ctx.setup(move |ctx| {
+                    setup_fetch.setup(ctx, pc);
+                    ctx.transition(eq(pc.next(), pc + 1u64));
+                    ctx.transition(eq(
+                        acc.next(),
+                        acc + setup_fetch.field("opcode") * setup_fetch.field("immediate"),
+                    ));
+                });
+
+                ctx.wg(move |ctx, (pc_value, acc_value): (usize, u64)| {
+                    ctx.assign(pc, Fr::from(pc_value as u64));
+                    ctx.assign(acc, Fr::from(acc_value));
+                    fetch.wg(ctx, pc_value);
+                })
+            });
+
+            ctx.trace(move |ctx, _| {
+                let mut acc = 0;
+                for pc in 0..3 {
+                    ctx.add(&exec, (pc, acc));
+                    if pc != 1 {
+                        acc += [5, 9, 7][pc];
+                    }
+                }
+            });
+        });
+
+        let step_type = circuit.step_types.values().next().unwrap();
+        let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), 3).generate(());
+        let fixed = circuit.fixed_assignments.as_ref().unwrap();
+
+        let lookup = &step_type.lookups[0];
+        let rows: Vec<Vec<Fr>> = (0..program.len())
+            .map(|row| {
+                lookup
+                    .exprs
+                    .iter()
+                    .map(|(_, dest)| match dest {
+                        Expr::Query(column) => fixed[column][row],
+                        _ => unreachable!(),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        for instance in trace.step_instances.iter() {
+            let src: Vec<Fr> = lookup
+                .exprs
+                .iter()
+                .map(|(src, _)| src.expr.eval(&instance.assignments).unwrap())
+                .collect();
+            assert!(rows.contains(&src));
+        }
+        // the opcode at pc 1 is not 1
+        assert!(!rows.contains(&[1, 1, 9].map(Fr::from).to_vec()));
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown instruction field jump")]
+    fn test_unknown_field() {
+        let _: SBPIR<Fr, ()> = circuit("rom", |ctx| {
+            let rom = ProgramRom::new(ctx, "rom", &["opcode"], vec![vec![Fr::from(1)]]);
+            ctx.step_type_def("exec", |ctx| {
+                InstructionFetch::new(ctx, "fetch", &rom).field("jump");
+                ctx.wg(|_, _: ()| {})
+            });
+        });
+    }
+}
@@ -0,0 +1,507 @@
+use std::hash::Hash;
+
+use crate::{
+    field::Field,
+    frontend::dsl::{
+        cb::{eq, Constraint},
+        lb::LookupTable,
+        StepTypeContext, StepTypeSetupContext,
+    },
+    poly::{Expr, ToExpr},
+    sbpir::{query::Queriable, PIR},
+    wit_gen::StepInstance,
+};
+
+pub const SHA256_ROUNDS: usize = 64;
+
+pub const SHA256_IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+pub const ROUND_CONSTANTS: [u32; SHA256_ROUNDS] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Values of a compression, used for witness generation.
+struct CompressionValues {
+    schedule: [u32; SHA256_ROUNDS],
+    schedule_carries: [u64; SHA256_ROUNDS],
+    a: [u32; SHA256_ROUNDS],
+    e: [u32; SHA256_ROUNDS],
+    a_carries: [u64; SHA256_ROUNDS],
+    e_carries: [u64; SHA256_ROUNDS],
+    output: [u32; 8],
+    output_carries: [u64; 8],
+}
+
+fn compress(state: &[u32; 8], block: &[u32; 16]) -> CompressionValues {
+    let mut schedule = [0u32; SHA256_ROUNDS];
+    let mut schedule_carries = [0u64; SHA256_ROUNDS];
+    schedule[..16].copy_from_slice(block);
+    for t in 16..SHA256_ROUNDS {
+        let (w2, w15) = (schedule[t - 2], schedule[t - 15]);
+        let sigma1 = w2.rotate_right(17) ^ w2.rotate_right(19) ^ (w2 >> 10);
+        let sigma0 = w15.rotate_right(7) ^ w15.rotate_right(18) ^ (w15 >> 3);
+        let sum = sigma1 as u64 + schedule[t - 7] as u64 + sigma0 as u64 + schedule[t - 16] as u64;
+        schedule[t] = sum as u32;
+        schedule_carries[t] = sum >> 32;
+    }
+
+    let mut a = [0u32; SHA256_ROUNDS];
+    let mut e = [0u32; SHA256_ROUNDS];
+    let mut a_carries = [0u64; SHA256_ROUNDS];
+    let mut e_carries = [0u64; SHA256_ROUNDS];
+    let mut working = *state;
+    for t in 0..SHA256_ROUNDS {
+        let [wa, wb, wc, wd, we, wf, wg, wh] = working;
+        let sigma1 = we.rotate_right(6) ^ we.rotate_right(11) ^ we.rotate_right(25);
+        let ch = (we & wf) ^ (!we & wg);
+        let sigma0 = wa.rotate_right(2) ^ wa.rotate_right(13) ^ wa.rotate_right(22);
+        let maj = (wa & wb) ^ (wa & wc) ^ (wb & wc);
+
+        let t1 =
+            wh as u64 + sigma1 as u64 + ch as u64 + ROUND_CONSTANTS[t] as u64 + schedule[t] as u64;
+        let new_e = wd as u64 + t1;
+        let new_a = t1 + sigma0 as u64 + maj as u64;
+
+        a[t] = new_a as u32;
+        e[t] = new_e as u32;
+        a_carries[t] = new_a >> 32;
+        e_carries[t] = new_e >> 32;
+        working = [a[t], wa, wb, wc, e[t], we, wf, wg];
+    }
+
+    let mut output = [0u32; 8];
+    let mut output_carries = [0u64; 8];
+    for i in 0..8 {
+        let sum = state[i] as u64 + working[i] as u64;
+        output[i] = sum as u32;
+        output_carries[i] = sum >> 32;
+    }
+
+    CompressionValues {
+        schedule,
+        schedule_carries,
+        a,
+        e,
+        a_carries,
+        e_carries,
+        output,
+        output_carries,
+    }
+}
+
+/// Computes the SHA-256 compression function natively.
+pub fn sha256_compress(state: &[u32; 8], block: &[u32; 16]) -> [u32; 8] {
+    compress(state, block).output
+}
+
+/// Computes the SHA-256 hash of a message natively.
+pub fn sha256(message: &[u8]) -> [u8; 32] {
+    let mut padded = message.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&((message.len() as u64) * 8).to_be_bytes());
+
+    let state = padded.chunks(64).fold(SHA256_IV, |state, chunk| {
+        let mut block = [0u32; 16];
+        for (word, bytes) in block.iter_mut().zip(chunk.chunks(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+        sha256_compress(&state, &block)
+    });
+
+    let mut digest = [0u8; 32];
+    for (bytes, word) in digest.chunks_mut(4).zip(state.iter()) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+
+    digest
+}
+
+fn xor<F: From<u64> + Clone>(a: PIR<F>, b: PIR<F>) -> PIR<F> {
+    a.clone() + b.clone() - 2u64.expr() * a * b
+}
+
+/// Returns the word with the given bits, least significant first.
+fn word<F: From<u64> + Clone>(bits: Vec<PIR<F>>) -> PIR<F> {
+    bits.into_iter()
+        .enumerate()
+        .fold(0u64.expr(), |acc, (i, bit)| {
+            acc + Expr::Const(F::from(1 << i)) * bit
+        })
+}
+
+/// In-step gadget for the SHA-256 compression function. The input state and message block words
+/// are decomposed into bits, the message schedule and the 64 rounds are computed over boolean
+/// signals, and every modular addition keeps its carry in a signal range checked with a lookup.
+/// It adds around 6600 signals to the step type, so it is usually placed in a dedicated step with a
+/// cell manager that limits the width.
+#[derive(Clone)]
+pub struct Sha256Compression<F> {
+    state: Vec<Vec<Queriable<F>>>,
+    schedule: Vec<Vec<Queriable<F>>>,
+    schedule_carries: Vec<Queriable<F>>,
+    a: Vec<Vec<Queriable<F>>>,
+    e: Vec<Vec<Queriable<F>>>,
+    a_carries: Vec<Queriable<F>>,
+    e_carries: Vec<Queriable<F>>,
+    output: Vec<Queriable<F>>,
+    output_carries: Vec<Queriable<F>>,
+}
+
+impl<F: Field + From<u64>> Sha256Compression<F> {
+    /// Adds the signals of the compression to the step type, annotated with the given name.
+    pub fn new(ctx: &mut StepTypeContext<F>, name: &str) -> Sha256Compression<F> {
+        let mut words = |label: &str, count: usize| -> Vec<Vec<Queriable<F>>> {
+            (0..count)
+                .map(|i| {
+                    (0..32)
+                        .map(|z| ctx.internal(format!("{}.{}[{}][{}]", name, label, i, z).as_str()))
+                        .collect()
+                })
+                .collect()
+        };
+
+        let state = words("state", 8);
+        let schedule = words("w", SHA256_ROUNDS);
+        let a = words("a", SHA256_ROUNDS);
+        let e = words("e", SHA256_ROUNDS);
+
+        let mut signals = |label: &str, count: usize| -> Vec<Queriable<F>> {
+            (0..count)
+                .map(|i| ctx.internal(format!("{}.{}[{}]", name, label, i).as_str()))
+                .collect()
+        };
+
+        Sha256Compression {
+            state,
+            schedule,
+            schedule_carries: signals("w_carry", SHA256_ROUNDS - 16),
+            a,
+            e,
+            a_carries: signals("a_carry", SHA256_ROUNDS),
+            e_carries: signals("e_carry", SHA256_ROUNDS),
+            output: signals("output", 8),
+            output_carries: signals("output_carry", 8),
+        }
+    }
+
+    /// Bits of the `a` working variable after round `t`, where the rounds before the first one
+    /// are the words of the input state.
+    fn a_bits(&self, t: isize) -> &[Queriable<F>] {
+        if t < 0 {
+            &self.state[(-t - 1) as usize]
+        } else {
+            &self.a[t as usize]
+        }
+    }
+
+    fn e_bits(&self, t: isize) -> &[Queriable<F>] {
+        if t < 0 {
+            &self.state[4 + (-t - 1) as usize]
+        } else {
+            &self.e[t as usize]
+        }
+    }
+
+    /// Constrains the compression of the 16 `block` words with the 8 `state` words. The carries
+    /// are looked up in `carry_table`, which must contain the values in `[0, 8)`, for example
+    /// `range_table(ctx, 3)`.
+    #[track_caller]
+    pub fn setup<V: Into<Constraint<F>> + Clone>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        state: &[V],
+        block: &[V],
+        carry_table: LookupTable,
+    ) {
+        assert_eq!(state.len(), 8, "sha256 state must have 8 words");
+        assert_eq!(block.len(), 16, "sha256 block must have 16 words");
+
+        let bits =
+            |bits: &[Queriable<F>]| -> Vec<PIR<F>> { bits.iter().map(|b| b.expr()).collect() };
+        let two_32 = Expr::Const(F::from(1 << 32));
+
+        for bit in self
+            .state
+            .iter()
+            .chain(self.schedule.iter())
+            .chain(self.a.iter())
+            .chain(self.e.iter())
+            .flatten()
+        {
+            ctx.constr(eq(*bit * (1u64.expr() - *bit), 0u64));
+        }
+
+        for (value, bits) in state
+            .iter()
+            .chain(block.iter())
+            .zip(self.state.iter().chain(self.schedule.iter()))
+        {
+            ctx.constr(eq(
+                value.clone(),
+                word(bits.iter().map(|b| b.expr()).collect()),
+            ));
+        }
+
+        for t in 16..SHA256_ROUNDS {
+            let (w2, w15) = (&self.schedule[t - 2], &self.schedule[t - 15]);
+            let sigma1 = word(
+                (0..32)
+                    .map(|i| {
+                        let shr = if i + 10 < 32 {
+                            w2[i + 10].expr()
+                        } else {
+                            0u64.expr()
+                        };
+                        xor(xor(w2[(i + 17) % 32].expr(), w2[(i + 19) % 32].expr()), shr)
+                    })
+                    .collect(),
+            );
+            let sigma0 = word(
+                (0..32)
+                    .map(|i| {
+                        let shr = if i + 3 < 32 {
+                            w15[i + 3].expr()
+                        } else {
+                            0u64.expr()
+                        };
+                        xor(
+                            xor(w15[(i + 7) % 32].expr(), w15[(i + 18) % 32].expr()),
+                            shr,
+                        )
+                    })
+                    .collect(),
+            );
+
+            let carry = self.schedule_carries[t - 16];
+            ctx.constr(eq(
+                word(bits(&self.schedule[t])) + two_32.clone() * carry,
+                sigma1
+                    + word(bits(&self.schedule[t - 7]))
+                    + sigma0
+                    + word(bits(&self.schedule[t - 16])),
+            ));
+            ctx.add_lookup(carry_table.apply(carry));
+        }
+
+        for (t, round_constant) in ROUND_CONSTANTS.iter().enumerate() {
+            let r = t as isize;
+            let (a, b, c, d) = (
+                self.a_bits(r - 1),
+                self.a_bits(r - 2),
+                self.a_bits(r - 3),
+                self.a_bits(r - 4),
+            );
+            let (e, f, g, h) = (
+                self.e_bits(r - 1),
+                self.e_bits(r - 2),
+                self.e_bits(r - 3),
+                self.e_bits(r - 4),
+            );
+
+            let sigma1 = word(
+                (0..32)
+                    .map(|i| {
+                        xor(
+                            xor(e[(i + 6) % 32].expr(), e[(i + 11) % 32].expr()),
+                            e[(i + 25) % 32].expr(),
+                        )
+                    })
+                    .collect(),
+            );
+            let ch = word(
+                (0..32)
+                    .map(|i| e[i] * f[i] + (1u64.expr() - e[i]) * g[i])
+                    .collect(),
+            );
+            let sigma0 = word(
+                (0..32)
+                    .map(|i| {
+                        xor(
+                            xor(a[(i + 2) % 32].expr(), a[(i + 13) % 32].expr()),
+                            a[(i + 22) % 32].expr(),
+                        )
+                    })
+                    .collect(),
+            );
+            let maj = word(
+                (0..32)
+                    .map(|i| {
+                        a[i] * b[i] + a[i] * c[i] + b[i] * c[i] - 2u64.expr() * a[i] * b[i] * c[i]
+                    })
+                    .collect(),
+            );
+
+            let t1 = word(bits(h))
+                + sigma1
+                + ch
+                + Expr::Const(F::from(*round_constant as u64))
+                + word(bits(&self.schedule[t]));
+
+            ctx.constr(eq(
+                word(bits(&self.e[t])) + two_32.clone() * self.e_carries[t],
+                word(bits(d)) + t1.clone(),
+            ));
+            ctx.constr(eq(
+                word(bits(&self.a[t])) + two_32.clone() * self.a_carries[t],
+                t1 + sigma0 + maj,
+            ));
+            ctx.add_lookup(carry_table.apply(self.e_carries[t]));
+            ctx.add_lookup(carry_table.apply(self.a_carries[t]));
+        }
+
+        for i in 0..8 {
+            let working = if i < 4 {
+                self.a_bits(SHA256_ROUNDS as isize - 1 - i as isize)
+            } else {
+                self.e_bits(SHA256_ROUNDS as isize - 1 - (i - 4) as isize)
+            };
+
+            ctx.constr(eq(
+                self.output[i] + two_32.clone() * self.output_carries[i],
+                word(bits(&self.state[i])) + word(bits(working)),
+            ));
+            ctx.add_lookup(carry_table.apply(self.output_carries[i]));
+        }
+    }
+
+    /// Returns the signals holding the words of the output state.
+    pub fn output(&self) -> &[Queriable<F>] {
+        &self.output
+    }
+}
+
+impl<F: Field + From<u64> + Hash> Sha256Compression<F> {
+    /// Assigns the signals of the compression and returns the output state.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, state: &[u32; 8], block: &[u32; 16]) -> [u32; 8] {
+        let values = compress(state, block);
+
+        let mut assign_word = |bits: &[Queriable<F>], value: u32| {
+            for (z, bit) in bits.iter().enumerate() {
+                ctx.assign(*bit, F::from(((value >> z) & 1) as u64));
+            }
+        };
+
+        for (bits, value) in self.state.iter().zip(state.iter()) {
+            assign_word(bits, *value);
+        }
+        for (bits, value) in self.schedule.iter().zip(values.schedule.iter()) {
+            assign_word(bits, *value);
+        }
+        for (bits, value) in self.a.iter().zip(values.a.iter()) {
+            assign_word(bits, *value);
+        }
+        for (bits, value) in self.e.iter().zip(values.e.iter()) {
+            assign_word(bits, *value);
+        }
+
+        let signals = self
+            .schedule_carries
+            .iter()
+            .chain(self.a_carries.iter())
+            .chain(self.e_carries.iter())
+            .chain(self.output.iter())
+            .chain(self.output_carries.iter());
+        let assigned = values.schedule_carries[16..]
+            .iter()
+            .chain(values.a_carries.iter())
+            .chain(values.e_carries.iter())
+            .copied()
+            .chain(values.output.iter().map(|v| *v as u64))
+            .chain(values.output_carries.iter().copied());
+        for (signal, value) in signals.zip(assigned) {
+            ctx.assign(*signal, F::from(value));
+        }
+
+        values.output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        frontend::dsl::circuit, sbpir::SBPIR, stdlib::range_table, wit_gen::TraceGenerator,
+    };
+
+    fn abc_block() -> [u32; 16] {
+        let mut block = [0u32; 16];
+        block[0] = 0x61626380;
+        block[15] = 24;
+
+        block
+    }
+
+    #[test]
+    fn test_sha256_compress() {
+        assert_eq!(
+            sha256_compress(&SHA256_IV, &abc_block()),
+            [
+                0xba7816bf, 0x8f01cfea, 0x414140de, 0x5dae2223, 0xb00361a3, 0x96177a9c, 0xb410ff61,
+                0xf20015ad
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(sha256(b"abc")[..4], [0xba, 0x78, 0x16, 0xbf]);
+        // two blocks, the length does not fit in the first one
+        assert_eq!(
+            sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")[28..],
+            [0x19, 0xdb, 0x06, 0xc1]
+        );
+    }
+
+    #[test]
+    fn test_sha256_compression_gadget() {
+        let circuit: SBPIR<Fr, ()> = circuit("sha256", |ctx| {
+            let carry_table = range_table(ctx, 3);
+
+            let compress = ctx.step_type_def("compress", |ctx| {
+                let sha256 = Sha256Compression::new(ctx, "sha256");
+                let setup_sha256 = sha256.clone();
+
+                ctx.setup(move |ctx| {
+                    let state: Vec<u64> = SHA256_IV.iter().map(|w| *w as u64).collect();
+                    let block: Vec<u64> = abc_block().iter().map(|w| *w as u64).collect();
+
+                    setup_sha256.setup(ctx, &state, &block, carry_table);
+                });
+
+                ctx.wg(move |ctx, _: ()| {
+                    sha256.wg(ctx, &SHA256_IV, &abc_block());
+                })
+            });
+
+            ctx.trace(move |ctx, _| ctx.add(&compress, ()));
+        });
+
+        let step_type = circuit.step_types.values().next().unwrap();
+        let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), 1).generate(());
+        let assignments = &trace.step_instances[0].assignments;
+
+        for constraint in step_type.constraints.iter() {
+            assert_eq!(
+                constraint.expr.eval(assignments),
+                Some(Fr::ZERO),
+                "{}",
+                constraint.annotation
+            );
+        }
+        assert_eq!(step_type.lookups.len(), 48 + 2 * 64 + 8);
+    }
+}
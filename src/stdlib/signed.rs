@@ -0,0 +1,278 @@
+use std::hash::Hash;
+
+use ff::PrimeField;
+
+use crate::{
+    field::Field,
+    frontend::dsl::{
+        cb::{eq, Constraint},
+        lb::LookupTable,
+        StepTypeContext, StepTypeSetupContext,
+    },
+    poly::{Expr, ToExpr},
+    sbpir::{query::Queriable, PIR},
+    wit_gen::StepInstance,
+};
+
+use super::LimbDecomposition;
+
+/// Two's complement interpretation of a signal of `bits` bits, a multiple of 8 up to 64. The
+/// signal is decomposed in bytes and the top one in the sign bit and seven low bits, range checked
+/// with two lookups in a byte table. It adds `bits / 8 + 1` signals.
+#[derive(Clone)]
+pub struct SignedValue<F> {
+    bytes: LimbDecomposition<F>,
+    sign: Queriable<F>,
+}
+
+impl<F: PrimeField> SignedValue<F> {
+    pub fn new(ctx: &mut StepTypeContext<F>, name: &str, bits: usize) -> SignedValue<F> {
+        assert!(
+            bits > 0 && bits <= 64 && bits % 8 == 0,
+            "signed values must have a multiple of 8 bits up to 64"
+        );
+
+        SignedValue {
+            bytes: LimbDecomposition::new(ctx, format!("{}.bytes", name).as_str(), bits / 8, 8),
+            sign: ctx.internal(format!("{}.sign", name).as_str()),
+        }
+    }
+}
+
+impl<F: Field + From<u64>> SignedValue<F> {
+    /// Constrains `raw` to be a value of `bits` bits with the sign bit in `sign`. `byte_table` must
+    /// contain the values in `[0, 256)`.
+    #[track_caller]
+    pub fn setup<V: Into<Constraint<F>>>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        raw: V,
+        byte_table: LookupTable,
+    ) {
+        self.bytes.setup(ctx, raw, byte_table);
+
+        ctx.constr(eq(self.sign * (1u64.expr() - self.sign), 0u64));
+        let top = self.bytes.limb(self.bytes.limbs().len() - 1);
+        let low = top - self.sign * 128u64;
+        ctx.add_lookup(byte_table.apply(low.clone()));
+        ctx.add_lookup(byte_table.apply(low + 128u64));
+    }
+
+    pub fn bits(&self) -> usize {
+        self.bytes.limbs().len() * 8
+    }
+
+    /// Returns the signal that is one for negative values.
+    pub fn sign(&self) -> Queriable<F> {
+        self.sign
+    }
+
+    /// Returns the unsigned value of `bits` bits.
+    pub fn raw(&self) -> PIR<F> {
+        self.bytes.recompose()
+    }
+
+    /// Returns the signed value as a field element, negative values being `p - |value|`.
+    pub fn value(&self) -> PIR<F> {
+        self.raw() - Expr::Const(F::from(2).pow([self.bits() as u64])) * self.sign
+    }
+
+    /// Returns the absolute value.
+    pub fn abs(&self) -> PIR<F> {
+        self.value() * (1u64.expr() - self.sign * 2u64)
+    }
+
+    /// Returns the value plus `2^(bits - 1)`, which is in `[0, 2^bits)` and keeps the order.
+    fn biased(&self) -> PIR<F> {
+        self.value() + Expr::Const(F::from(2).pow([self.bits() as u64 - 1]))
+    }
+}
+
+impl<F: Field + From<u64> + Hash> SignedValue<F> {
+    /// Assigns the bytes and the sign of `value`. Panics if it does not fit in the bits.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, value: i64) {
+        let bits = self.bits() as u32;
+        assert!(
+            bits == 64 || (value >= -(1i64 << (bits - 1)) && value < 1i64 << (bits - 1)),
+            "value does not fit in {} signed bits",
+            bits
+        );
+
+        let raw = if bits == 64 {
+            value as u64
+        } else {
+            (value as u64) & ((1u64 << bits) - 1)
+        };
+        self.bytes.wg(ctx, raw);
+        ctx.assign(self.sign, F::from((value < 0) as u64));
+    }
+}
+
+/// In-step signed comparison `lhs < rhs` of two `SignedValue` with the same number of bits. The
+/// values are biased by `2^(bits - 1)` and compared as unsigned, with the difference range checked
+/// in bytes. It adds `bits / 8 + 1` signals.
+#[derive(Clone)]
+pub struct SignedLessThan<F> {
+    lt: Queriable<F>,
+    diff: LimbDecomposition<F>,
+}
+
+impl<F: PrimeField> SignedLessThan<F> {
+    pub fn new(ctx: &mut StepTypeContext<F>, name: &str, bits: usize) -> SignedLessThan<F> {
+        assert!(
+            bits > 0 && bits <= 64 && bits % 8 == 0,
+            "signed values must have a multiple of 8 bits up to 64"
+        );
+
+        SignedLessThan {
+            lt: ctx.internal(format!("{}.lt", name).as_str()),
+            diff: LimbDecomposition::new(ctx, format!("{}.diff", name).as_str(), bits / 8, 8),
+        }
+    }
+}
+
+impl<F: Field + From<u64>> SignedLessThan<F> {
+    /// Constrains `lt` to be one when `lhs < rhs`. `byte_table` must contain the values in
+    /// `[0, 256)`.
+    #[track_caller]
+    pub fn setup(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        lhs: &SignedValue<F>,
+        rhs: &SignedValue<F>,
+        byte_table: LookupTable,
+    ) {
+        let bits = self.diff.limbs().len() * 8;
+        assert!(
+            lhs.bits() == bits && rhs.bits() == bits,
+            "compared values must have {} bits",
+            bits
+        );
+
+        ctx.constr(eq(self.lt * (1u64.expr() - self.lt), 0u64));
+        let modulus = Expr::Const(F::from(2).pow([bits as u64]));
+        self.diff.setup(
+            ctx,
+            lhs.biased() - rhs.biased() + modulus * self.lt,
+            byte_table,
+        );
+    }
+
+    pub fn lt(&self) -> Queriable<F> {
+        self.lt
+    }
+}
+
+impl<F: Field + From<u64> + Hash> SignedLessThan<F> {
+    /// Assigns the comparison of `lhs` and `rhs` and returns it.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, lhs: i64, rhs: i64) -> bool {
+        let lt = lhs < rhs;
+        let diff = (lhs as i128 - rhs as i128)
+            + if lt {
+                1i128 << (self.diff.limbs().len() * 8)
+            } else {
+                0
+            };
+
+        ctx.assign(self.lt, F::from(lt as u64));
+        self.diff.wg(ctx, diff as u128);
+
+        lt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        frontend::dsl::circuit, sbpir::SBPIR, stdlib::range_table, wit_gen::TraceGenerator,
+    };
+
+    type Args = (i64, i64, bool);
+
+    fn signed_circuit(bits: usize) -> SBPIR<Fr, Args> {
+        circuit("signed", |ctx| {
+            let byte_table = range_table(ctx, 8);
+            let lhs_raw = ctx.forward("lhs");
+            let rhs_raw = ctx.forward("rhs");
+            let lt = ctx.forward("lt");
+            let abs = ctx.forward("abs");
+
+            let step = ctx.step_type_def("step", |ctx| {
+                let lhs = SignedValue::new(ctx, "lhs", bits);
+                let rhs = SignedValue::new(ctx, "rhs", bits);
+                let cmp = SignedLessThan::new(ctx, "cmp", bits);
+
+                let (setup_lhs, setup_rhs, setup_cmp) = (lhs.clone(), rhs.clone(), cmp.clone());
+                ctx.setup(move |ctx| {
+                    setup_lhs.setup(ctx, lhs_raw, byte_table);
+                    setup_rhs.setup(ctx, rhs_raw, byte_table);
+                    setup_cmp.setup(ctx, &setup_lhs, &setup_rhs, byte_table);
+                    ctx.constr(eq(lt, setup_cmp.lt()));
+                    ctx.constr(eq(abs, setup_lhs.abs()));
+                });
+
+                ctx.wg(move |ctx, (a, b, tamper): Args| {
+                    let raw = |v: i64| Fr::from(v as u64 & (u64::MAX >> (64 - bits)));
+                    ctx.assign(lhs_raw, raw(a));
+                    ctx.assign(rhs_raw, raw(b));
+                    lhs.wg(ctx, a);
+                    rhs.wg(ctx, b);
+                    let less = cmp.wg(ctx, a, b);
+                    ctx.assign(lt, Fr::from(less as u64));
+                    ctx.assign(abs, Fr::from(a.unsigned_abs()));
+                    if tamper {
+                        ctx.assign(lhs.sign(), Fr::ONE - Fr::from((a < 0) as u64));
+                    }
+                })
+            });
+
+            ctx.pragma_num_steps(1);
+            ctx.trace(move |ctx, args| ctx.add(&step, args));
+        })
+    }
+
+    fn satisfied(circuit: &SBPIR<Fr, Args>, args: Args) -> bool {
+        let step_type = circuit.step_types.values().next().unwrap();
+        let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), 1).generate(args);
+        let assignments = &trace.step_instances[0].assignments;
+
+        let constraints = step_type
+            .constraints
+            .iter()
+            .all(|c| c.expr.eval(assignments) == Some(Fr::ZERO));
+        let lookups = step_type.lookups.iter().all(|lookup| {
+            let value = lookup.exprs[0].0.expr.eval(assignments).unwrap();
+            (0..256).any(|byte| Fr::from(byte) == value)
+        });
+
+        constraints && lookups
+    }
+
+    #[test]
+    fn test_signed() {
+        let circuit = signed_circuit(8);
+        for (a, b) in [
+            (-5, 3),
+            (3, -5),
+            (-128, 127),
+            (127, -128),
+            (-1, -1),
+            (0, -1),
+        ] {
+            assert!(satisfied(&circuit, (a, b, false)), "{} {}", a, b);
+        }
+        assert!(!satisfied(&circuit, (-5, 3, true)));
+        assert!(!satisfied(&circuit, (5, 3, true)));
+
+        let circuit = signed_circuit(32);
+        assert!(satisfied(&circuit, (-70000, 70000, false)));
+        assert!(satisfied(
+            &circuit,
+            (i32::MAX as i64, i32::MIN as i64, false)
+        ));
+        assert!(!satisfied(&circuit, (-70000, 70000, true)));
+    }
+}
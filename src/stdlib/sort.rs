@@ -0,0 +1,294 @@
+use std::hash::Hash;
+
+use ff::PrimeField;
+use num_bigint::BigUint;
+
+use crate::{
+    field::Field,
+    frontend::dsl::{
+        cb::{eq, Constraint},
+        lb::LookupTable,
+        CircuitContext, StepTypeContext, StepTypeSetupContext,
+    },
+    sbpir::{query::Queriable, PIR},
+    wit_gen::StepInstance,
+};
+
+use super::LimbDecomposition;
+
+/// Multiset equality of a value per step between an input sequence and a sorted one, with a grand
+/// product `prod (challenge - input) / (challenge - sorted)` accumulated across the steps in a
+/// forward signal. The signal holds the product of the previous steps, so it starts at one and the
+/// last step checks that absorbing its values gives one again. Together with a `SortedOrder` on
+/// the sorted values it asserts a sorted permutation.
+///
+/// The challenge is an expression and must come from the verifier for the argument to be sound.
+/// The product is allocated in the given phase, which must be later than the phases of the values.
+#[derive(Clone)]
+pub struct SortedPermutation<F> {
+    product: Queriable<F>,
+    challenge: PIR<F>,
+}
+
+impl<F: Field + From<u64>> SortedPermutation<F> {
+    pub fn new<TraceArgs, C: Into<Constraint<F>>>(
+        ctx: &mut CircuitContext<F, TraceArgs>,
+        name: &str,
+        phase: usize,
+        challenge: C,
+    ) -> SortedPermutation<F> {
+        SortedPermutation {
+            product: ctx.forward_with_phase(format!("{}.product", name).as_str(), phase),
+            challenge: challenge.into().expr,
+        }
+    }
+
+    pub fn product(&self) -> Queriable<F> {
+        self.product
+    }
+
+    /// Constrains the product to be one, in the first step.
+    #[track_caller]
+    pub fn setup_start(&self, ctx: &mut StepTypeSetupContext<F>) {
+        ctx.constr(eq(self.product, 1u64));
+    }
+
+    /// Constrains the product of the next step to absorb `input` and `sorted`.
+    #[track_caller]
+    pub fn setup_absorb<V: Into<Constraint<F>>, W: Into<Constraint<F>>>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        input: V,
+        sorted: W,
+    ) {
+        let (input, sorted) = (input.into().expr, sorted.into().expr);
+
+        ctx.transition(eq(
+            self.product.next() * (self.challenge.clone() - sorted),
+            self.product * (self.challenge.clone() - input),
+        ));
+    }
+
+    /// Constrains the product to be one after absorbing `input` and `sorted`, in the last step.
+    #[track_caller]
+    pub fn setup_end<V: Into<Constraint<F>>, W: Into<Constraint<F>>>(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        input: V,
+        sorted: W,
+    ) {
+        let (input, sorted) = (input.into().expr, sorted.into().expr);
+
+        ctx.constr(eq(
+            self.challenge.clone() - sorted,
+            self.product * (self.challenge.clone() - input),
+        ));
+    }
+}
+
+impl<F: Field + From<u64> + Hash> SortedPermutation<F> {
+    /// Assigns the product and returns its value for the next step.
+    pub fn wg(
+        &self,
+        ctx: &mut StepInstance<F>,
+        product: F,
+        input: F,
+        sorted: F,
+        challenge: F,
+    ) -> F {
+        ctx.assign(self.product, product);
+
+        product * (challenge - input) * (challenge - sorted).mi()
+    }
+}
+
+/// Order check between the value of a forward signal in this step and in the next one, decomposing
+/// their difference in `num_limbs` limbs of `limb_bits` bits. The limbs must be narrower than the
+/// field, so that the difference of a decreasing pair does not fit in them.
+#[derive(Clone)]
+pub struct SortedOrder<F> {
+    diff: LimbDecomposition<F>,
+}
+
+impl<F: PrimeField> SortedOrder<F> {
+    pub fn new(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        num_limbs: usize,
+        limb_bits: u32,
+    ) -> SortedOrder<F> {
+        SortedOrder {
+            diff: LimbDecomposition::new(
+                ctx,
+                format!("{}.diff", name).as_str(),
+                num_limbs,
+                limb_bits,
+            ),
+        }
+    }
+}
+
+impl<F: Field + From<u64>> SortedOrder<F> {
+    /// Constrains `sorted` not to decrease in the next step. `range_table` must contain the values
+    /// in `[0, 2^limb_bits)`, see `range_table`.
+    #[track_caller]
+    pub fn setup(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        sorted: Queriable<F>,
+        range_table: LookupTable,
+    ) {
+        ctx.transition(eq(sorted.next() - sorted, self.diff.recompose()));
+        for limb in self.diff.limbs().iter() {
+            ctx.add_lookup(range_table.apply(*limb));
+        }
+    }
+}
+
+impl<F: Field + From<u64> + Hash> SortedOrder<F> {
+    /// Assigns the difference between the value in the next step and the one in this step.
+    pub fn wg<V: Into<BigUint>>(&self, ctx: &mut StepInstance<F>, sorted: V, next: V) {
+        let (sorted, next) = (sorted.into(), next.into());
+        assert!(sorted <= next, "values are not sorted");
+
+        self.diff.wg(ctx, next - sorted);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        frontend::dsl::circuit, poly::Expr, sbpir::SBPIR, stdlib::range_table,
+        wit_gen::TraceGenerator,
+    };
+
+    type Args = (Vec<u64>, Vec<u64>);
+
+    /// Evaluates the constraints, the transitions and the lookups of the steps, where every lookup
+    /// is into the 8 bits range table.
+    fn satisfied(circuit: &SBPIR<Fr, Args>, args: Args) -> bool {
+        let num_steps = args.0.len();
+        let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), num_steps).generate(args);
+
+        trace
+            .step_instances
+            .iter()
+            .enumerate()
+            .all(|(i, instance)| {
+                let step_type = &circuit.step_types[&instance.step_type_uuid];
+                let mut assignments = instance.assignments.clone();
+                if let Some(next) = trace.step_instances.get(i + 1) {
+                    for (signal, value) in next.assignments.iter() {
+                        if let Queriable::Forward(forward, false) = signal {
+                            assignments.insert(Queriable::Forward(*forward, true), *value);
+                        }
+                    }
+                }
+
+                let transitions = step_type
+                    .transition_constraints
+                    .iter()
+                    .filter(|_| i + 1 < num_steps);
+                let in_range = step_type.lookups.iter().all(|lookup| {
+                    lookup.exprs.iter().all(|(src, _)| {
+                        (0..256u64).any(|v| src.expr.eval(&assignments) == Some(Fr::from(v)))
+                    })
+                });
+                in_range
+                    && step_type
+                        .constraints
+                        .iter()
+                        .map(|c| &c.expr)
+                        .chain(transitions.map(|c| &c.expr))
+                        .all(|expr| expr.eval(&assignments) == Some(Fr::ZERO))
+            })
+    }
+
+    #[test]
+    fn test_sorted_permutation() {
+        let challenge = Fr::from(0xc0ffee);
+
+        let circuit: SBPIR<Fr, Args> = circuit("sorted permutation", |ctx| {
+            let range_table = range_table(ctx, 8);
+            let input = ctx.forward("input");
+            let sorted = ctx.forward("sorted");
+            let permutation = SortedPermutation::new(ctx, "perm", 1, Expr::Const(challenge));
+
+            let mut step_types = Vec::new();
+            for (name, first) in [("first", true), ("middle", false)] {
+                let permutation = permutation.clone();
+                step_types.push(ctx.step_type_def(name, |ctx| {
+                    let order = SortedOrder::new(ctx, "order", 1, 8);
+
+                    let (setup_permutation, setup_order) = (permutation.clone(), order.clone());
+                    ctx.setup(move |ctx| {
+                        if first {
+                            setup_permutation.setup_start(ctx);
+                        }
+                        setup_permutation.setup_absorb(ctx, input, sorted);
+                        setup_order.setup(ctx, sorted, range_table);
+                    });
+
+                    ctx.wg(move |ctx, (product, values, next): (Fr, [u64; 2], u64)| {
+                        ctx.assign(input, Fr::from(values[0]));
+                        ctx.assign(sorted, Fr::from(values[1]));
+                        permutation.wg(
+                            ctx,
+                            product,
+                            Fr::from(values[0]),
+                            Fr::from(values[1]),
+                            challenge,
+                        );
+                        if values[1] <= next {
+                            order.wg(ctx, values[1], next);
+                        } else {
+                            // forges a decreasing pair, which the witness generator rejects
+                            ctx.assign(order.diff.limb(0), Fr::from(next) - Fr::from(values[1]));
+                        }
+                    })
+                }));
+            }
+            let last = ctx.step_type_def("last", |ctx| {
+                let setup_permutation = permutation.clone();
+                ctx.setup(move |ctx| setup_permutation.setup_end(ctx, input, sorted));
+
+                ctx.wg(move |ctx, (product, values): (Fr, [u64; 2])| {
+                    ctx.assign(input, Fr::from(values[0]));
+                    ctx.assign(sorted, Fr::from(values[1]));
+                    permutation.wg(
+                        ctx,
+                        product,
+                        Fr::from(values[0]),
+                        Fr::from(values[1]),
+                        challenge,
+                    );
+                })
+            });
+
+            ctx.pragma_num_steps(4);
+            ctx.trace(move |ctx, (inputs, sorted): Args| {
+                let mut product = Fr::ONE;
+                for i in 0..inputs.len() {
+                    let values = [inputs[i], sorted[i]];
+                    if i + 1 < inputs.len() {
+                        let step_type = &step_types[(i > 0) as usize];
+                        ctx.add(step_type, (product, values, sorted[i + 1]));
+                    } else {
+                        ctx.add(&last, (product, values));
+                    }
+                    product *=
+                        (challenge - Fr::from(values[0])) * (challenge - Fr::from(values[1])).mi();
+                }
+            });
+        });
+
+        assert!(satisfied(&circuit, (vec![5, 3, 9, 3], vec![3, 3, 5, 9])));
+        assert!(!satisfied(&circuit, (vec![5, 3, 9, 3], vec![3, 4, 5, 9])));
+        assert!(!satisfied(&circuit, (vec![5, 3, 9, 3], vec![3, 5, 5, 9])));
+        // a permutation of the input, but not sorted
+        assert!(!satisfied(&circuit, (vec![5, 3, 9, 3], vec![3, 5, 3, 9])));
+    }
+}
@@ -0,0 +1,635 @@
+use std::hash::Hash;
+
+use ff::PrimeField;
+
+use crate::{
+    field::Field,
+    frontend::dsl::{
+        cb::{eq, rlc, Constraint},
+        lb::LookupTable,
+        CircuitContext, StepTypeContext, StepTypeSetupContext,
+    },
+    poly::ToExpr,
+    sbpir::{query::Queriable, PIR},
+    wit_gen::StepInstance,
+};
+
+use super::sort::{SortedOrder, SortedPermutation};
+
+/// Parameters of the memory argument of a `Stack`.
+#[derive(Clone)]
+pub struct StackParams<F> {
+    /// Phase of the grand product, which must be later than the phases of the pushed values.
+    pub phase: usize,
+    /// Challenge that compresses an access in a value, from the verifier.
+    pub compression: PIR<F>,
+    /// Challenge of the grand product, from the verifier.
+    pub challenge: PIR<F>,
+    /// Bits of the timestamps, the circuit having at most `2^timestamp_bits` steps.
+    pub timestamp_bits: u32,
+    /// Limbs that the differences between the sorted keys are decomposed in, so the keys must fit
+    /// in `num_limbs * limb_bits` bits.
+    pub num_limbs: usize,
+    pub limb_bits: u32,
+    /// Table with the values in `[0, 2^limb_bits)`, see `range_table`.
+    pub range_table: LookupTable,
+}
+
+/// Stack for interpreter-style circuits, built on a memory argument. Every step makes one access:
+/// a push writes the slot above the top, a pop reads the top slot, and a keep reads zero at
+/// address 0. The slots are at the addresses from 1, the depth being the address of the top.
+///
+/// An access is `(address, key, is_write, value)`, with `key = address * 2^timestamp_bits +
+/// timestamp` and the timestamp being the index of the step. A `SortedPermutation` asserts that
+/// the sorted accesses, carried in forward signals, are a permutation of the accesses of the
+/// steps, and a `SortedOrder` that their keys increase, so they are sorted by address and then by
+/// time. Every read of the sorted accesses then has the address and value of the access before it,
+/// so a pop returns the last value pushed to its slot.
+///
+/// The first step type must call `setup_start`, the last one `StackOp::setup_end`, and the others
+/// a push, pop or keep of a `StackOp`. The witness is generated by recording the accesses in a
+/// `StackState`.
+#[derive(Clone)]
+pub struct Stack<F> {
+    params: StackParams<F>,
+    depth: Queriable<F>,
+    timestamp: Queriable<F>,
+    sorted_address: Queriable<F>,
+    sorted_key: Queriable<F>,
+    sorted_is_write: Queriable<F>,
+    sorted_value: Queriable<F>,
+    permutation: SortedPermutation<F>,
+}
+
+impl<F: Field + From<u64>> Stack<F> {
+    pub fn new<TraceArgs>(
+        ctx: &mut CircuitContext<F, TraceArgs>,
+        name: &str,
+        params: StackParams<F>,
+    ) -> Stack<F> {
+        let mut forward = |signal: &str| ctx.forward(format!("{}.{}", name, signal).as_str());
+        let depth = forward("depth");
+        let timestamp = forward("timestamp");
+        let sorted_address = forward("sorted.address");
+        let sorted_key = forward("sorted.key");
+        let sorted_is_write = forward("sorted.is_write");
+        let sorted_value = forward("sorted.value");
+        let permutation = SortedPermutation::new(
+            ctx,
+            format!("{}.permutation", name).as_str(),
+            params.phase,
+            params.challenge.clone(),
+        );
+
+        Stack {
+            params,
+            depth,
+            timestamp,
+            sorted_address,
+            sorted_key,
+            sorted_is_write,
+            sorted_value,
+            permutation,
+        }
+    }
+
+    pub fn depth(&self) -> Queriable<F> {
+        self.depth
+    }
+
+    /// Constrains the stack to be empty and the memory argument to start, in the first step. The
+    /// first sorted access is a write or the zero read at address 0.
+    #[track_caller]
+    pub fn setup_start(&self, ctx: &mut StepTypeSetupContext<F>) {
+        ctx.constr(eq(self.depth, 0u64));
+        ctx.constr(eq(self.timestamp, 0u64));
+        self.permutation.setup_start(ctx);
+
+        let is_read = 1u64.expr() - self.sorted_is_write;
+        ctx.constr(eq(is_read.clone() * self.sorted_address, 0u64));
+        ctx.constr(eq(is_read * self.sorted_value, 0u64));
+    }
+
+    fn compress(&self, address: PIR<F>, key: PIR<F>, is_write: PIR<F>, value: PIR<F>) -> PIR<F> {
+        rlc(
+            &[address, key, is_write, value],
+            self.params.compression.clone(),
+        )
+    }
+
+    fn sorted(&self) -> PIR<F> {
+        self.compress(
+            self.sorted_address.expr(),
+            self.sorted_key.expr(),
+            self.sorted_is_write.expr(),
+            self.sorted_value.expr(),
+        )
+    }
+
+    /// Returns the compressed access of this step.
+    fn access(&self, address: PIR<F>, is_write: bool, value: PIR<F>) -> PIR<F> {
+        let key = address.clone() * (1u64 << self.params.timestamp_bits).expr() + self.timestamp;
+
+        self.compress(address, key, (is_write as u64).expr(), value)
+    }
+}
+
+impl<F: Field + From<u64> + Hash> Stack<F> {
+    fn wg(&self, ctx: &mut StepInstance<F>, step: &StackStep<F>) {
+        ctx.assign(self.depth, F::from(step.depth));
+        ctx.assign(self.timestamp, F::from(step.access.timestamp));
+        ctx.assign(self.permutation.product(), step.product);
+        ctx.assign(self.sorted_address, F::from(step.sorted.address));
+        ctx.assign(self.sorted_key, F::from(step.sorted.key));
+        ctx.assign(self.sorted_is_write, F::from(step.sorted.is_write as u64));
+        ctx.assign(self.sorted_value, step.sorted.value);
+    }
+}
+
+/// Access of a `Stack` in a step type, a push, a pop or none. It adds an internal signal for the
+/// value, one for the inverse of the depth, and the limbs of the order check of the sorted keys.
+#[derive(Clone)]
+pub struct StackOp<F> {
+    stack: Stack<F>,
+    value: Queriable<F>,
+    depth_inv: Queriable<F>,
+    order: SortedOrder<F>,
+}
+
+impl<F: PrimeField> StackOp<F> {
+    pub fn new(ctx: &mut StepTypeContext<F>, name: &str, stack: &Stack<F>) -> StackOp<F> {
+        StackOp {
+            stack: stack.clone(),
+            value: ctx.internal(format!("{}.value", name).as_str()),
+            depth_inv: ctx.internal(format!("{}.depth_inv", name).as_str()),
+            order: SortedOrder::new(
+                ctx,
+                format!("{}.order", name).as_str(),
+                stack.params.num_limbs,
+                stack.params.limb_bits,
+            ),
+        }
+    }
+}
+
+impl<F: Field + From<u64>> StackOp<F> {
+    /// Returns the signal of the pushed or popped value.
+    pub fn value(&self) -> Queriable<F> {
+        self.value
+    }
+
+    /// Constrains the next step to have `value` pushed.
+    #[track_caller]
+    pub fn setup_push<V: Into<Constraint<F>>>(&self, ctx: &mut StepTypeSetupContext<F>, value: V) {
+        let stack = &self.stack;
+        ctx.constr(eq(self.value, value));
+
+        ctx.transition(eq(stack.depth.next(), stack.depth + 1u64));
+        self.setup_access(ctx, stack.depth + 1u64, true, self.value.expr());
+    }
+
+    /// Constrains the next step to have the top value popped, the stack not being empty. The
+    /// popped value is `value`.
+    #[track_caller]
+    pub fn setup_pop(&self, ctx: &mut StepTypeSetupContext<F>) {
+        let stack = &self.stack;
+        ctx.constr(eq(stack.depth * self.depth_inv, 1u64));
+
+        ctx.transition(eq(stack.depth.next(), stack.depth - 1u64));
+        self.setup_access(ctx, stack.depth.expr(), false, self.value.expr());
+    }
+
+    /// Constrains the stack of the next step to be the same.
+    #[track_caller]
+    pub fn setup_keep(&self, ctx: &mut StepTypeSetupContext<F>) {
+        let stack = &self.stack;
+
+        ctx.transition(eq(stack.depth.next(), stack.depth));
+        self.setup_access(ctx, 0u64.expr(), false, 0u64.expr());
+    }
+
+    /// Ends the memory argument in the last step, which does not access the stack.
+    #[track_caller]
+    pub fn setup_end(&self, ctx: &mut StepTypeSetupContext<F>) {
+        let stack = &self.stack;
+        let access = stack.access(0u64.expr(), false, 0u64.expr());
+
+        stack.permutation.setup_end(ctx, access, stack.sorted());
+    }
+
+    #[track_caller]
+    fn setup_access(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        address: PIR<F>,
+        is_write: bool,
+        value: PIR<F>,
+    ) {
+        let stack = &self.stack;
+        ctx.transition(eq(stack.timestamp.next(), stack.timestamp + 1u64));
+
+        let access = stack.access(address, is_write, value);
+        stack.permutation.setup_absorb(ctx, access, stack.sorted());
+        self.order
+            .setup(ctx, stack.sorted_key, stack.params.range_table);
+
+        // a read has the address and the value of the sorted access before it
+        let is_read = 1u64.expr() - stack.sorted_is_write.next();
+        ctx.transition(eq(
+            is_read.clone() * (stack.sorted_address.next() - stack.sorted_address),
+            0u64,
+        ));
+        ctx.transition(eq(
+            is_read * (stack.sorted_value.next() - stack.sorted_value),
+            0u64,
+        ));
+    }
+}
+
+impl<F: Field + From<u64> + Hash> StackOp<F> {
+    /// Assigns the stack and the signals of the access for the witness of the step.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, step: &StackStep<F>) {
+        self.stack.wg(ctx, step);
+        ctx.assign(self.value, step.access.value);
+        ctx.assign(self.depth_inv, F::from(step.depth).mi());
+        if let Some(next_key) = step.next_sorted_key {
+            self.order.wg(ctx, step.sorted.key, next_key);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Access<F> {
+    address: u64,
+    timestamp: u64,
+    key: u64,
+    is_write: bool,
+    value: F,
+}
+
+/// Witness of a `Stack`, recording an access per step to compute the sorted accesses of the
+/// memory argument with `steps`.
+#[derive(Clone, Debug, Default)]
+pub struct StackState<F> {
+    values: Vec<F>,
+    /// Depth before the access and the access of every step.
+    accesses: Vec<(u64, bool, u64, F)>,
+}
+
+/// Witness of the `Stack` in a step, see `StackOp::wg`.
+#[derive(Clone, Debug)]
+pub struct StackStep<F> {
+    depth: u64,
+    access: Access<F>,
+    product: F,
+    sorted: Access<F>,
+    next_sorted_key: Option<u64>,
+}
+
+impl<F: Field> StackState<F> {
+    pub fn new() -> StackState<F> {
+        StackState {
+            values: Vec::new(),
+            accesses: Vec::new(),
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns the value `offset` positions below the top.
+    pub fn peek(&self, offset: usize) -> F {
+        assert!(offset < self.depth(), "stack underflow");
+
+        self.values[self.depth() - 1 - offset]
+    }
+
+    pub fn push(&mut self, value: F) {
+        let depth = self.depth() as u64;
+        self.accesses.push((depth, true, depth + 1, value));
+        self.values.push(value);
+    }
+
+    pub fn pop(&mut self) -> F {
+        let value = self.peek(0);
+        let depth = self.depth() as u64;
+        self.accesses.push((depth, false, depth, value));
+        self.values.pop();
+
+        value
+    }
+
+    /// Records a step that does not access the stack.
+    pub fn keep(&mut self) {
+        self.accesses.push((self.depth() as u64, false, 0, F::ZERO));
+    }
+}
+
+impl<F: Field + From<u64>> StackState<F> {
+    /// Returns the witness of every step recorded, sorting the accesses and accumulating the
+    /// grand product with the values of the challenges of `stack`.
+    pub fn steps(&self, stack: &Stack<F>, compression: F, challenge: F) -> Vec<StackStep<F>> {
+        let timestamp_bits = stack.params.timestamp_bits;
+        assert!(
+            self.accesses.len() as u64 <= 1 << timestamp_bits,
+            "too many steps for the stack timestamps"
+        );
+
+        let accesses: Vec<Access<F>> = self
+            .accesses
+            .iter()
+            .enumerate()
+            .map(|(timestamp, (_, is_write, address, value))| Access {
+                address: *address,
+                timestamp: timestamp as u64,
+                key: (address << timestamp_bits) | timestamp as u64,
+                is_write: *is_write,
+                value: *value,
+            })
+            .collect();
+        let mut sorted = accesses.clone();
+        sorted.sort_by_key(|access| access.key);
+
+        let compress = |access: &Access<F>| -> F {
+            [
+                F::from(access.address),
+                F::from(access.key),
+                F::from(access.is_write as u64),
+            ]
+            .iter()
+            .rev()
+            .fold(access.value, |acc, value| acc * compression + value)
+        };
+
+        let mut product = F::ONE;
+        accesses
+            .iter()
+            .zip(sorted.iter())
+            .enumerate()
+            .map(|(index, (access, sorted_access))| {
+                let step = StackStep {
+                    depth: self.accesses[index].0,
+                    access: *access,
+                    product,
+                    sorted: *sorted_access,
+                    next_sorted_key: sorted.get(index + 1).map(|next| next.key),
+                };
+                product *=
+                    (challenge - compress(access)) * (challenge - compress(sorted_access)).mi();
+
+                step
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        frontend::dsl::circuit, poly::Expr, sbpir::SBPIR, stdlib::range_table,
+        wit_gen::TraceGenerator,
+    };
+
+    #[derive(Clone, Copy)]
+    enum Instruction {
+        Push(u64),
+        Pop,
+        Add,
+    }
+
+    /// Program and how to tamper the witness of its first pop: 0 not at all, 1 the popped value,
+    /// 2 the popped value in the recorded access.
+    type Args = (Vec<Instruction>, u8);
+
+    const COMPRESSION: u64 = 0xbeef;
+    const CHALLENGE: u64 = 0xc0ffee;
+
+    fn satisfied(circuit: &SBPIR<Fr, Args>, args: Args) -> bool {
+        let num_steps = args
+            .0
+            .iter()
+            .map(|instruction| match instruction {
+                Instruction::Add => 3,
+                _ => 1,
+            })
+            .sum::<usize>()
+            + 2;
+        let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), num_steps).generate(args);
+
+        trace
+            .step_instances
+            .iter()
+            .enumerate()
+            .all(|(i, instance)| {
+                let step_type = &circuit.step_types[&instance.step_type_uuid];
+                let mut assignments = instance.assignments.clone();
+                if let Some(next) = trace.step_instances.get(i + 1) {
+                    for (signal, value) in next.assignments.iter() {
+                        if let Queriable::Forward(forward, false) = signal {
+                            assignments.insert(Queriable::Forward(*forward, true), *value);
+                        }
+                    }
+                }
+
+                let transitions = step_type
+                    .transition_constraints
+                    .iter()
+                    .filter(|_| i + 1 < num_steps);
+                step_type
+                    .constraints
+                    .iter()
+                    .map(|c| &c.expr)
+                    .chain(transitions.map(|c| &c.expr))
+                    .all(|expr| expr.eval(&assignments) == Some(Fr::ZERO))
+            })
+    }
+
+    #[test]
+    fn test_stack_state() {
+        let mut state = StackState::<Fr>::new();
+        state.push(Fr::from(1));
+        state.push(Fr::from(2));
+        state.keep();
+        assert_eq!(state.peek(1), Fr::from(1));
+        assert_eq!(state.pop(), Fr::from(2));
+        assert_eq!(state.depth(), 1);
+        assert_eq!(
+            state.accesses,
+            vec![
+                (0, true, 1, Fr::from(1)),
+                (1, true, 2, Fr::from(2)),
+                (2, false, 0, Fr::ZERO),
+                (2, false, 2, Fr::from(2)),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "stack underflow")]
+    fn test_stack_state_underflow() {
+        let mut state = StackState::<Fr>::new();
+        state.push(Fr::from(1));
+        state.pop();
+        state.pop();
+    }
+
+    fn params(range_table: LookupTable) -> StackParams<Fr> {
+        StackParams {
+            phase: 1,
+            compression: Expr::Const(Fr::from(COMPRESSION)),
+            challenge: Expr::Const(Fr::from(CHALLENGE)),
+            timestamp_bits: 8,
+            num_limbs: 2,
+            limb_bits: 8,
+            range_table,
+        }
+    }
+
+    #[test]
+    fn test_stack() {
+        use Instruction::*;
+
+        let circuit: SBPIR<Fr, Args> = circuit("stack", |ctx| {
+            let range_table = range_table(ctx, 8);
+            let stack = Stack::new(ctx, "stack", params(range_table));
+            let operand = ctx.forward("operand");
+
+            let push_stack = stack.clone();
+            let push = ctx.step_type_def("push", |ctx| {
+                let op = StackOp::new(ctx, "push", &push_stack);
+
+                let setup_op = op.clone();
+                ctx.setup(move |ctx| setup_op.setup_push(ctx, operand));
+
+                ctx.wg(move |ctx, (step, value): (StackStep<Fr>, Fr)| {
+                    op.wg(ctx, &step);
+                    ctx.assign(operand, value);
+                })
+            });
+
+            // pops a value into the operand
+            let pop_stack = stack.clone();
+            let pop = ctx.step_type_def("pop", |ctx| {
+                let op = StackOp::new(ctx, "pop", &pop_stack);
+
+                let setup_op = op.clone();
+                ctx.setup(move |ctx| {
+                    setup_op.setup_pop(ctx);
+                    ctx.constr(eq(operand, setup_op.value()));
+                });
+
+                ctx.wg(move |ctx, (step, value): (StackStep<Fr>, Fr)| {
+                    op.wg(ctx, &step);
+                    ctx.assign(operand, value);
+                })
+            });
+
+            // pops a value and adds it to the operand in the next step
+            let add_stack = stack.clone();
+            let add = ctx.step_type_def("add", |ctx| {
+                let op = StackOp::new(ctx, "add", &add_stack);
+
+                let setup_op = op.clone();
+                ctx.setup(move |ctx| {
+                    setup_op.setup_pop(ctx);
+                    ctx.transition(eq(operand.next(), operand + setup_op.value()));
+                });
+
+                ctx.wg(move |ctx, (step, value): (StackStep<Fr>, Fr)| {
+                    op.wg(ctx, &step);
+                    ctx.assign(operand, value);
+                })
+            });
+
+            let start_stack = stack.clone();
+            let start = ctx.step_type_def("start", |ctx| {
+                let op = StackOp::new(ctx, "start", &start_stack);
+
+                let (setup_stack, setup_op) = (start_stack.clone(), op.clone());
+                ctx.setup(move |ctx| {
+                    setup_stack.setup_start(ctx);
+                    setup_op.setup_keep(ctx);
+                });
+
+                ctx.wg(move |ctx, step: StackStep<Fr>| op.wg(ctx, &step))
+            });
+
+            let end_stack = stack.clone();
+            let end = ctx.step_type_def("end", |ctx| {
+                let op = StackOp::new(ctx, "end", &end_stack);
+
+                let setup_op = op.clone();
+                ctx.setup(move |ctx| setup_op.setup_end(ctx));
+
+                ctx.wg(move |ctx, step: StackStep<Fr>| op.wg(ctx, &step))
+            });
+
+            ctx.pragma_first_step(&start);
+            ctx.pragma_last_step(&end);
+
+            ctx.trace(move |ctx, (program, tamper): Args| {
+                // step types and operands of the steps, whose stack witness is known at the end
+                let mut state = StackState::new();
+                let mut steps = Vec::new();
+                let mut tampered = false;
+
+                state.keep();
+                steps.push((0, Fr::ZERO));
+                for instruction in program {
+                    match instruction {
+                        Push(value) => {
+                            state.push(Fr::from(value));
+                            steps.push((1, Fr::from(value)));
+                        }
+                        Pop => {
+                            let mut value = state.pop();
+                            if tamper > 0 && !tampered {
+                                value += Fr::ONE;
+                                if tamper == 2 {
+                                    state.accesses.last_mut().unwrap().3 = value;
+                                }
+                                tampered = true;
+                            }
+                            steps.push((2, value));
+                        }
+                        Add => {
+                            let lhs = state.pop();
+                            steps.push((2, lhs));
+                            let rhs = state.pop();
+                            steps.push((3, lhs));
+                            state.push(lhs + rhs);
+                            steps.push((1, lhs + rhs));
+                        }
+                    }
+                }
+                state.keep();
+                steps.push((4, Fr::ZERO));
+
+                let witness = state.steps(&stack, Fr::from(COMPRESSION), Fr::from(CHALLENGE));
+                for ((step_type, operand), mut step) in steps.into_iter().zip(witness) {
+                    if tamper == 1 && step_type == 2 && step.access.value != operand {
+                        step.access.value = operand;
+                    }
+                    match step_type {
+                        0 => ctx.add(&start, step),
+                        1 => ctx.add(&push, (step, operand)),
+                        2 => ctx.add(&pop, (step, operand)),
+                        3 => ctx.add(&add, (step, operand)),
+                        _ => ctx.add(&end, step),
+                    }
+                }
+            });
+        });
+
+        let program = vec![Push(3), Push(5), Push(7), Add, Pop, Pop];
+        assert!(satisfied(&circuit, (program.clone(), 0)));
+        // the popped value breaks the grand product
+        assert!(!satisfied(&circuit, (program.clone(), 1)));
+        // the popped value is absorbed and sorted, but it is not the value pushed to its slot
+        assert!(!satisfied(&circuit, (program, 2)));
+    }
+}
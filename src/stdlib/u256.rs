@@ -0,0 +1,503 @@
+use std::{
+    hash::Hash,
+    ops::{Add, Mul},
+};
+
+use num_bigint::BigUint;
+
+use crate::{
+    field::Field,
+    frontend::dsl::{cb::eq, lb::LookupTable, StepTypeContext, StepTypeSetupContext},
+    poly::{Expr, ToExpr},
+    sbpir::{query::Queriable, PIR},
+    wit_gen::StepInstance,
+};
+
+use super::IsZero;
+
+pub const U256_BYTES: usize = 32;
+/// Multiplication carries are lower than `2^67`, so they fit in nine bytes.
+const MUL_CARRY_BYTES: usize = 9;
+
+fn word_mask() -> BigUint {
+    (BigUint::from(1u64) << (8 * U256_BYTES)) - 1u64
+}
+
+fn bytes_expr<F: Field + From<u64>>(bytes: &[Queriable<F>]) -> PIR<F> {
+    bytes
+        .iter()
+        .enumerate()
+        .fold(0u64.expr(), |acc, (i, byte)| {
+            acc + Expr::Const(F::from(2).pow([8 * i as u64])) * *byte
+        })
+}
+
+fn assign_bytes<F: Field + From<u64> + Hash>(
+    ctx: &mut StepInstance<F>,
+    bytes: &[Queriable<F>],
+    value: &BigUint,
+) {
+    let mut values = value.to_bytes_le();
+    assert!(
+        values.len() <= bytes.len(),
+        "value does not fit in {} bytes",
+        bytes.len()
+    );
+    values.resize(bytes.len(), 0);
+
+    for (signal, value) in bytes.iter().zip(values) {
+        ctx.assign(*signal, F::from(value as u64));
+    }
+}
+
+#[track_caller]
+fn boolean<F: Field + From<u64>>(ctx: &mut StepTypeSetupContext<F>, signal: Queriable<F>) {
+    ctx.constr(eq(signal * (1u64.expr() - signal), 0u64));
+}
+
+/// 256-bit word, like the EVM ones, given by 32 little-endian byte signals. The bytes are range
+/// checked in a table with the values in `[0, 256)`, for example `range_table(ctx, 8)`.
+#[derive(Clone)]
+pub struct U256<F> {
+    bytes: Vec<Queriable<F>>,
+}
+
+impl<F: Field + From<u64>> U256<F> {
+    /// Adds the byte signals to the step type, annotated as `name[i]`.
+    pub fn new(ctx: &mut StepTypeContext<F>, name: &str) -> U256<F> {
+        let bytes = (0..U256_BYTES)
+            .map(|i| ctx.internal(format!("{}[{}]", name, i).as_str()))
+            .collect();
+
+        U256 { bytes }
+    }
+
+    /// Range checks the bytes.
+    #[track_caller]
+    pub fn setup(&self, ctx: &mut StepTypeSetupContext<F>, byte_table: LookupTable) {
+        for byte in self.bytes.iter() {
+            ctx.add_lookup(byte_table.apply(*byte));
+        }
+    }
+
+    /// Returns the byte signals, least significant first.
+    pub fn bytes(&self) -> &[Queriable<F>] {
+        &self.bytes
+    }
+
+    /// Returns the expression of the low 128 bits.
+    pub fn lo(&self) -> PIR<F> {
+        bytes_expr(&self.bytes[..U256_BYTES / 2])
+    }
+
+    /// Returns the expression of the high 128 bits.
+    pub fn hi(&self) -> PIR<F> {
+        bytes_expr(&self.bytes[U256_BYTES / 2..])
+    }
+
+    /// Returns the expressions of the four 64-bit limbs, least significant first.
+    pub fn limbs(&self) -> Vec<PIR<F>> {
+        self.bytes.chunks(8).map(bytes_expr).collect()
+    }
+}
+
+impl<F: Field + From<u64> + Hash> U256<F> {
+    /// Assigns the bytes of `value`. Panics if it does not fit in 256 bits.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, value: &BigUint) {
+        assign_bytes(ctx, &self.bytes, value);
+    }
+}
+
+/// Constrains `lhs + rhs = result + carry * 2^256`, going through the 128-bit halves with the
+/// intermediate carry `carry_lo`.
+#[track_caller]
+fn constrain_add<F: Field + From<u64>>(
+    ctx: &mut StepTypeSetupContext<F>,
+    (lhs, rhs, result): (&U256<F>, &U256<F>, &U256<F>),
+    carry_lo: Queriable<F>,
+    carry: Queriable<F>,
+) {
+    let base = Expr::Const(F::from(2).pow([128]));
+
+    boolean(ctx, carry_lo);
+    boolean(ctx, carry);
+    ctx.constr(eq(
+        lhs.lo() + rhs.lo(),
+        result.lo() + base.clone() * carry_lo,
+    ));
+    ctx.constr(eq(
+        lhs.hi() + rhs.hi() + carry_lo,
+        result.hi() + base * carry,
+    ));
+}
+
+fn assign_add<F: Field + From<u64> + Hash>(
+    ctx: &mut StepInstance<F>,
+    (lhs, rhs): (&BigUint, &BigUint),
+    carry_lo: Queriable<F>,
+    carry: Queriable<F>,
+) {
+    let mask = (BigUint::from(1u64) << 128) - 1u64;
+    let lo = (lhs & &mask) + (rhs & &mask);
+
+    ctx.assign(
+        carry_lo,
+        F::from((lo >> 128u32 != BigUint::from(0u64)) as u64),
+    );
+    ctx.assign(
+        carry,
+        F::from(((lhs + rhs) >> 256u32 != BigUint::from(0u64)) as u64),
+    );
+}
+
+/// In-step addition of two words modulo `2^256`, with the overflow bit as `carry`.
+#[derive(Clone)]
+pub struct U256Add<F> {
+    result: U256<F>,
+    carry_lo: Queriable<F>,
+    carry: Queriable<F>,
+}
+
+impl<F: Field + From<u64>> U256Add<F> {
+    pub fn new(ctx: &mut StepTypeContext<F>, name: &str) -> U256Add<F> {
+        U256Add {
+            result: U256::new(ctx, format!("{}.result", name).as_str()),
+            carry_lo: ctx.internal(format!("{}.carry_lo", name).as_str()),
+            carry: ctx.internal(format!("{}.carry", name).as_str()),
+        }
+    }
+
+    /// Constrains the result to be `lhs + rhs` modulo `2^256`. The result is range checked in
+    /// `byte_table`, the operands must be range checked elsewhere.
+    #[track_caller]
+    pub fn setup(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        lhs: &U256<F>,
+        rhs: &U256<F>,
+        byte_table: LookupTable,
+    ) {
+        self.result.setup(ctx, byte_table);
+        constrain_add(ctx, (lhs, rhs, &self.result), self.carry_lo, self.carry);
+    }
+
+    pub fn result(&self) -> &U256<F> {
+        &self.result
+    }
+
+    /// Returns the signal that is one when the addition overflows.
+    pub fn carry(&self) -> Queriable<F> {
+        self.carry
+    }
+}
+
+impl<F: Field + From<u64> + Hash> U256Add<F> {
+    pub fn wg(&self, ctx: &mut StepInstance<F>, lhs: &BigUint, rhs: &BigUint) -> BigUint {
+        let result = (lhs + rhs) & word_mask();
+        self.result.wg(ctx, &result);
+        assign_add(ctx, (lhs, rhs), self.carry_lo, self.carry);
+
+        result
+    }
+}
+
+/// In-step subtraction of two words modulo `2^256`, with the underflow bit as `borrow`. It is
+/// constrained as the addition `rhs + result = lhs + borrow * 2^256`.
+#[derive(Clone)]
+pub struct U256Sub<F> {
+    result: U256<F>,
+    borrow_lo: Queriable<F>,
+    borrow: Queriable<F>,
+}
+
+impl<F: Field + From<u64>> U256Sub<F> {
+    pub fn new(ctx: &mut StepTypeContext<F>, name: &str) -> U256Sub<F> {
+        U256Sub {
+            result: U256::new(ctx, format!("{}.result", name).as_str()),
+            borrow_lo: ctx.internal(format!("{}.borrow_lo", name).as_str()),
+            borrow: ctx.internal(format!("{}.borrow", name).as_str()),
+        }
+    }
+
+    /// Constrains the result to be `lhs - rhs` modulo `2^256`. The result is range checked in
+    /// `byte_table`, the operands must be range checked elsewhere.
+    #[track_caller]
+    pub fn setup(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        lhs: &U256<F>,
+        rhs: &U256<F>,
+        byte_table: LookupTable,
+    ) {
+        self.result.setup(ctx, byte_table);
+        constrain_add(ctx, (rhs, &self.result, lhs), self.borrow_lo, self.borrow);
+    }
+
+    pub fn result(&self) -> &U256<F> {
+        &self.result
+    }
+
+    /// Returns the signal that is one when `lhs < rhs`.
+    pub fn borrow(&self) -> Queriable<F> {
+        self.borrow
+    }
+}
+
+impl<F: Field + From<u64> + Hash> U256Sub<F> {
+    pub fn wg(&self, ctx: &mut StepInstance<F>, lhs: &BigUint, rhs: &BigUint) -> BigUint {
+        let result = ((BigUint::from(1u64) << 256) + lhs - rhs) & word_mask();
+        self.result.wg(ctx, &result);
+        assign_add(ctx, (rhs, &result), self.borrow_lo, self.borrow);
+
+        result
+    }
+}
+
+/// In-step multiplication of two words modulo `2^256`, like the EVM `MUL`. The product of the
+/// 64-bit limbs is split in the low and high 128 bits, with range checked carries between them.
+#[derive(Clone)]
+pub struct U256Mul<F> {
+    result: U256<F>,
+    carry_lo: Vec<Queriable<F>>,
+    carry_hi: Vec<Queriable<F>>,
+}
+
+impl<F: Field + From<u64>> U256Mul<F> {
+    pub fn new(ctx: &mut StepTypeContext<F>, name: &str) -> U256Mul<F> {
+        let mut carry = |half: &str| -> Vec<Queriable<F>> {
+            (0..MUL_CARRY_BYTES)
+                .map(|i| ctx.internal(format!("{}.carry_{}[{}]", name, half, i).as_str()))
+                .collect()
+        };
+        let carry_lo = carry("lo");
+        let carry_hi = carry("hi");
+
+        U256Mul {
+            result: U256::new(ctx, format!("{}.result", name).as_str()),
+            carry_lo,
+            carry_hi,
+        }
+    }
+
+    /// Constrains the result to be `lhs * rhs` modulo `2^256`. The result and the carries are range
+    /// checked in `byte_table`, the operands must be range checked elsewhere.
+    #[track_caller]
+    pub fn setup(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        lhs: &U256<F>,
+        rhs: &U256<F>,
+        byte_table: LookupTable,
+    ) {
+        let (a, b) = (lhs.limbs(), rhs.limbs());
+        let t = mul_terms(&a, &b);
+        let base_64 = Expr::Const(F::from(2).pow([64]));
+        let base_128 = Expr::Const(F::from(2).pow([128]));
+        let carry_lo = bytes_expr(&self.carry_lo);
+        let carry_hi = bytes_expr(&self.carry_hi);
+
+        self.result.setup(ctx, byte_table);
+        for byte in self.carry_lo.iter().chain(self.carry_hi.iter()) {
+            ctx.add_lookup(byte_table.apply(*byte));
+        }
+
+        ctx.constr(eq(
+            t[0].clone() + base_64.clone() * t[1].clone(),
+            self.result.lo() + base_128.clone() * carry_lo.clone(),
+        ));
+        ctx.constr(eq(
+            carry_lo + t[2].clone() + base_64 * t[3].clone(),
+            self.result.hi() + base_128 * carry_hi,
+        ));
+    }
+
+    pub fn result(&self) -> &U256<F> {
+        &self.result
+    }
+}
+
+/// Returns `t[k] = sum(a[i] * b[j])` for `i + j = k < 4`.
+fn mul_terms<T: Clone + Add<Output = T> + Mul<Output = T>>(a: &[T], b: &[T]) -> Vec<T> {
+    (0..4)
+        .map(|k| {
+            (1..=k).fold(a[0].clone() * b[k].clone(), |acc, i| {
+                acc + a[i].clone() * b[k - i].clone()
+            })
+        })
+        .collect()
+}
+
+impl<F: Field + From<u64> + Hash> U256Mul<F> {
+    pub fn wg(&self, ctx: &mut StepInstance<F>, lhs: &BigUint, rhs: &BigUint) -> BigUint {
+        let limbs = |value: &BigUint| -> Vec<BigUint> {
+            (0..4)
+                .map(|i| (value >> (64 * i)) & BigUint::from(u64::MAX))
+                .collect()
+        };
+        let t = mul_terms(&limbs(lhs), &limbs(rhs));
+
+        let result = (lhs * rhs) & word_mask();
+        let lo = &t[0] + (&t[1] << 64u32);
+        let carry_lo = lo >> 128u32;
+        let carry_hi = (&carry_lo + &t[2] + (&t[3] << 64u32)) >> 128u32;
+
+        self.result.wg(ctx, &result);
+        assign_bytes(ctx, &self.carry_lo, &carry_lo);
+        assign_bytes(ctx, &self.carry_hi, &carry_hi);
+
+        result
+    }
+}
+
+/// In-step comparison of two words, computing `lhs - rhs` to get `lt` from the borrow and `eq` from
+/// the difference being zero.
+#[derive(Clone)]
+pub struct U256Cmp<F> {
+    diff: U256Sub<F>,
+    is_zero: IsZero<F>,
+}
+
+impl<F: Field + From<u64>> U256Cmp<F> {
+    pub fn new(ctx: &mut StepTypeContext<F>, name: &str) -> U256Cmp<F> {
+        let diff = U256Sub::new(ctx, format!("{}.diff", name).as_str());
+        let inv = ctx.internal(format!("{}.diff_inv", name).as_str());
+        // the bytes are range checked, so their sum is zero only if they are all zero
+        let sum = diff
+            .result()
+            .bytes()
+            .iter()
+            .fold(0u64.expr(), |acc, byte| acc + *byte);
+        let is_zero = IsZero::setup(ctx, sum, inv);
+
+        U256Cmp { diff, is_zero }
+    }
+
+    #[track_caller]
+    pub fn setup(
+        &self,
+        ctx: &mut StepTypeSetupContext<F>,
+        lhs: &U256<F>,
+        rhs: &U256<F>,
+        byte_table: LookupTable,
+    ) {
+        self.diff.setup(ctx, lhs, rhs, byte_table);
+    }
+
+    /// Returns the boolean signal that is one when `lhs < rhs`.
+    pub fn lt(&self) -> Queriable<F> {
+        self.diff.borrow()
+    }
+
+    /// Returns the boolean expression that is one when `lhs == rhs`.
+    pub fn eq(&self) -> PIR<F> {
+        self.is_zero.is_zero().expr
+    }
+}
+
+impl<F: Field + From<u64> + Hash> U256Cmp<F> {
+    /// Assigns the comparison and returns `(lhs < rhs, lhs == rhs)`.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, lhs: &BigUint, rhs: &BigUint) -> (bool, bool) {
+        let diff = self.diff.wg(ctx, lhs, rhs);
+        let sum: u64 = diff.to_bytes_le().iter().map(|byte| *byte as u64).sum();
+        self.is_zero.wg::<()>(ctx, F::from(sum));
+
+        (lhs < rhs, lhs == rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        frontend::dsl::circuit, sbpir::SBPIR, stdlib::range_table, wit_gen::TraceGenerator,
+    };
+
+    type Args = (BigUint, BigUint, bool);
+
+    fn satisfied(circuit: &SBPIR<Fr, Args>, args: Args) -> bool {
+        let step_type = circuit.step_types.values().next().unwrap();
+        let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), 1).generate(args);
+        let assignments = &trace.step_instances[0].assignments;
+
+        step_type
+            .constraints
+            .iter()
+            .all(|c| c.expr.eval(assignments) == Some(Fr::ZERO))
+    }
+
+    fn u256_circuit() -> SBPIR<Fr, Args> {
+        circuit("u256", |ctx| {
+            let byte_table = range_table(ctx, 8);
+
+            let step = ctx.step_type_def("step", |ctx| {
+                let a = U256::new(ctx, "a");
+                let b = U256::new(ctx, "b");
+                let add = U256Add::new(ctx, "add");
+                let sub = U256Sub::new(ctx, "sub");
+                let mul = U256Mul::new(ctx, "mul");
+                let cmp = U256Cmp::new(ctx, "cmp");
+                let lt = ctx.internal("lt");
+                let is_eq = ctx.internal("eq");
+
+                let (setup_a, setup_b) = (a.clone(), b.clone());
+                let (setup_add, setup_sub) = (add.clone(), sub.clone());
+                let (setup_mul, setup_cmp) = (mul.clone(), cmp.clone());
+                ctx.setup(move |ctx| {
+                    let (a, b) = (&setup_a, &setup_b);
+                    a.setup(ctx, byte_table);
+                    b.setup(ctx, byte_table);
+                    setup_add.setup(ctx, a, b, byte_table);
+                    setup_sub.setup(ctx, a, b, byte_table);
+                    setup_mul.setup(ctx, a, b, byte_table);
+                    setup_cmp.setup(ctx, a, b, byte_table);
+                    ctx.constr(eq(lt, setup_cmp.lt()));
+                    ctx.constr(eq(is_eq, setup_cmp.eq()));
+                });
+
+                ctx.wg(move |ctx, (a_value, b_value, tamper): Args| {
+                    let modulus = BigUint::from(1u64) << 256;
+                    a.wg(ctx, &a_value);
+                    b.wg(ctx, &b_value);
+
+                    let sum = add.wg(ctx, &a_value, &b_value);
+                    assert_eq!(sum, (&a_value + &b_value) % &modulus);
+                    let diff = sub.wg(ctx, &a_value, &b_value);
+                    assert_eq!(diff, (&modulus + &a_value - &b_value) % &modulus);
+                    let product = mul.wg(ctx, &a_value, &b_value);
+                    assert_eq!(product, &a_value * &b_value % &modulus);
+
+                    let (lt_value, eq_value) = cmp.wg(ctx, &a_value, &b_value);
+                    ctx.assign(lt, Fr::from(lt_value as u64));
+                    ctx.assign(is_eq, Fr::from(eq_value as u64));
+
+                    if tamper {
+                        mul.result().wg(ctx, &(product ^ BigUint::from(1u64)));
+                    }
+                })
+            });
+
+            ctx.pragma_num_steps(1);
+            ctx.trace(move |ctx, args| ctx.add(&step, args));
+        })
+    }
+
+    #[test]
+    fn test_u256_ops() {
+        let circuit = u256_circuit();
+        let max = word_mask();
+        let a = BigUint::parse_bytes(
+            b"f0e1d2c3b4a5968778695a4b3c2d1e0f00112233445566778899aabbccddeeff",
+            16,
+        )
+        .unwrap();
+        let b = BigUint::parse_bytes(b"123456789abcdef0fedcba9876543210", 16).unwrap();
+
+        assert!(satisfied(&circuit, (a.clone(), b.clone(), false)));
+        assert!(satisfied(&circuit, (b.clone(), a.clone(), false)));
+        assert!(satisfied(&circuit, (max.clone(), max.clone(), false)));
+        assert!(satisfied(&circuit, (BigUint::from(0u64), max, false)));
+        assert!(!satisfied(&circuit, (a, b, true)));
+    }
+}
@@ -0,0 +1,355 @@
+use std::hash::Hash;
+
+use ff::PrimeField;
+
+use crate::{
+    field::Field,
+    frontend::dsl::{cb::eq, lb::LookupTable, StepTypeContext},
+    poly::{Expr, ToExpr},
+    sbpir::{query::Queriable, PIR},
+    wit_gen::StepInstance,
+};
+
+use super::LimbDecomposition;
+
+/// Unsigned word of `BITS` bits, a multiple of 8 up to 64, in a signal that is range checked in
+/// the step type when it is declared. Words wider than a byte are decomposed in bytes, which adds
+/// `BITS / 8` signals, and every byte is looked up in a byte table. The arithmetic helpers check
+/// the widths of their operands and result when the circuit is built.
+#[derive(Clone)]
+pub struct WordSignal<F, const BITS: u32> {
+    signal: Queriable<F>,
+    bytes: Option<LimbDecomposition<F>>,
+}
+
+pub type U8Signal<F> = WordSignal<F, 8>;
+pub type U16Signal<F> = WordSignal<F, 16>;
+pub type U32Signal<F> = WordSignal<F, 32>;
+
+impl<F: PrimeField, const BITS: u32> WordSignal<F, BITS> {
+    /// Adds an internal signal annotated as `name` and range checks it. `byte_table` must contain
+    /// the values in `[0, 256)`.
+    pub fn internal(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        byte_table: LookupTable,
+    ) -> WordSignal<F, BITS> {
+        let signal = ctx.internal(name);
+
+        WordSignal::wrap(ctx, name, signal, byte_table)
+    }
+
+    /// Range checks an existing signal in the step type. Forward and shared signals are declared
+    /// in the circuit, so they must be wrapped in every step type that assigns them.
+    pub fn wrap(
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        signal: Queriable<F>,
+        byte_table: LookupTable,
+    ) -> WordSignal<F, BITS> {
+        assert_width(BITS);
+
+        if BITS == 8 {
+            ctx.setup(|ctx| ctx.add_lookup(byte_table.apply(signal)));
+
+            return WordSignal {
+                signal,
+                bytes: None,
+            };
+        }
+
+        let bytes = LimbDecomposition::new(
+            ctx,
+            format!("{}.bytes", name).as_str(),
+            BITS as usize / 8,
+            8,
+        );
+        ctx.setup(|ctx| bytes.setup(ctx, signal, byte_table));
+
+        WordSignal {
+            signal,
+            bytes: Some(bytes),
+        }
+    }
+
+    /// Adds an internal signal whose range follows from its constraints, so it is not checked.
+    fn implied(ctx: &mut StepTypeContext<F>, name: &str) -> WordSignal<F, BITS> {
+        assert_width(BITS);
+
+        WordSignal {
+            signal: ctx.internal(name),
+            bytes: None,
+        }
+    }
+
+    pub fn bits(&self) -> u32 {
+        BITS
+    }
+
+    pub fn signal(&self) -> Queriable<F> {
+        self.signal
+    }
+
+    /// Returns the byte signals, least significant first, if the word is decomposed.
+    pub fn bytes(&self) -> Option<&[Queriable<F>]> {
+        self.bytes.as_ref().map(|bytes| bytes.limbs())
+    }
+
+    /// Returns a word of `OUT` bits constrained to `self + rhs`. Panics if `OUT` is not wider than
+    /// both operands, as the sum could overflow it. The sum is not range checked.
+    pub fn add<const B: u32, const OUT: u32>(
+        &self,
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        rhs: &WordSignal<F, B>,
+    ) -> WordSignal<F, OUT> {
+        assert!(
+            OUT > BITS.max(B),
+            "sum of {} and {} bits does not fit in {} bits",
+            BITS,
+            B,
+            OUT
+        );
+
+        let sum = WordSignal::implied(ctx, name);
+        let (lhs, rhs, result) = (self.signal, rhs.signal, sum.signal);
+        ctx.setup(|ctx| ctx.constr(eq(result, lhs + rhs)));
+
+        sum
+    }
+
+    /// Returns a word of the same width constrained to `self + rhs`, which makes the step
+    /// unsatisfiable when the sum overflows. Panics if `rhs` is wider than `self`.
+    pub fn checked_add<const B: u32>(
+        &self,
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        rhs: &WordSignal<F, B>,
+        byte_table: LookupTable,
+    ) -> WordSignal<F, BITS> {
+        assert!(B <= BITS, "cannot add {} bits to {} bits", B, BITS);
+
+        let sum = WordSignal::internal(ctx, name, byte_table);
+        let (lhs, rhs, result) = (self.signal, rhs.signal, sum.signal);
+        ctx.setup(|ctx| ctx.constr(eq(result, lhs + rhs)));
+
+        sum
+    }
+
+    /// Returns a word of the same width and a carry constrained to `self + rhs = sum + carry *
+    /// 2^BITS`. Panics if `rhs` is wider than `self`.
+    pub fn wrapping_add<const B: u32>(
+        &self,
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        rhs: &WordSignal<F, B>,
+        byte_table: LookupTable,
+    ) -> (WordSignal<F, BITS>, BoolSignal<F>) {
+        assert!(B <= BITS, "cannot add {} bits to {} bits", B, BITS);
+
+        let sum = WordSignal::internal(ctx, name, byte_table);
+        let carry = BoolSignal::internal(ctx, format!("{}.carry", name).as_str());
+        let (lhs, rhs, result) = (self.signal, rhs.signal, sum.signal);
+        let modulus = Expr::Const(Field::pow(&F::from(2), [BITS as u64]));
+        ctx.setup(|ctx| ctx.constr(eq(result + modulus.clone() * carry.signal(), lhs + rhs)));
+
+        (sum, carry)
+    }
+
+    /// Returns a word of `OUT` bits constrained to `self * rhs`. Panics if `OUT` is narrower than
+    /// the width of both operands together, as the product could overflow it. The product is not
+    /// range checked.
+    pub fn mul<const B: u32, const OUT: u32>(
+        &self,
+        ctx: &mut StepTypeContext<F>,
+        name: &str,
+        rhs: &WordSignal<F, B>,
+    ) -> WordSignal<F, OUT> {
+        assert!(
+            OUT >= BITS + B,
+            "product of {} and {} bits does not fit in {} bits",
+            BITS,
+            B,
+            OUT
+        );
+
+        let product = WordSignal::implied(ctx, name);
+        let (lhs, rhs, result) = (self.signal, rhs.signal, product.signal);
+        ctx.setup(|ctx| ctx.constr(eq(result, lhs * rhs)));
+
+        product
+    }
+}
+
+impl<F: Field + From<u64> + Hash, const BITS: u32> WordSignal<F, BITS> {
+    /// Assigns the word and its bytes. Panics if `value` does not fit in `BITS` bits.
+    pub fn wg(&self, ctx: &mut StepInstance<F>, value: u64) {
+        assert!(
+            BITS == 64 || value >> BITS == 0,
+            "value {} does not fit in {} bits",
+            value,
+            BITS
+        );
+
+        ctx.assign(self.signal, F::from(value));
+        if let Some(bytes) = &self.bytes {
+            bytes.wg(ctx, value);
+        }
+    }
+}
+
+/// Panics if `bits` is not a valid word width.
+fn assert_width(bits: u32) {
+    assert!(
+        bits > 0 && bits <= 64 && bits % 8 == 0,
+        "words must have a multiple of 8 bits up to 64"
+    );
+}
+
+/// Boolean signal, constrained to be zero or one in the step type when it is declared.
+#[derive(Clone, Copy)]
+pub struct BoolSignal<F> {
+    signal: Queriable<F>,
+}
+
+impl<F: Field + From<u64>> BoolSignal<F> {
+    /// Adds an internal signal annotated as `name` and constrains it to be boolean.
+    pub fn internal(ctx: &mut StepTypeContext<F>, name: &str) -> BoolSignal<F> {
+        let signal = ctx.internal(name);
+
+        BoolSignal::wrap(ctx, signal)
+    }
+
+    /// Constrains an existing signal to be boolean in the step type. Forward and shared signals
+    /// are declared in the circuit, so they must be wrapped in every step type that assigns them.
+    pub fn wrap(ctx: &mut StepTypeContext<F>, signal: Queriable<F>) -> BoolSignal<F> {
+        ctx.setup(|ctx| ctx.constr(eq(signal * (1u64.expr() - signal), 0u64)));
+
+        BoolSignal { signal }
+    }
+
+    pub fn signal(&self) -> Queriable<F> {
+        self.signal
+    }
+
+    pub fn not(&self) -> PIR<F> {
+        1u64.expr() - self.signal
+    }
+
+    pub fn and(&self, other: &BoolSignal<F>) -> PIR<F> {
+        self.signal * other.signal
+    }
+
+    pub fn or(&self, other: &BoolSignal<F>) -> PIR<F> {
+        self.signal + other.signal - self.signal * other.signal
+    }
+}
+
+impl<F: Field + From<u64> + Hash> BoolSignal<F> {
+    pub fn wg(&self, ctx: &mut StepInstance<F>, value: bool) {
+        ctx.assign(self.signal, F::from(value as u64));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        frontend::dsl::circuit, sbpir::SBPIR, stdlib::range_table, wit_gen::TraceGenerator,
+    };
+
+    type Args = (u64, u64, bool);
+
+    fn satisfied(circuit: &SBPIR<Fr, Args>, args: Args) -> bool {
+        let step_type = circuit.step_types.values().next().unwrap();
+        let trace = TraceGenerator::new(circuit.trace.clone().unwrap(), 1).generate(args);
+        let assignments = &trace.step_instances[0].assignments;
+
+        let constraints = step_type
+            .constraints
+            .iter()
+            .all(|c| c.expr.eval(assignments) == Some(Fr::ZERO));
+        let lookups = step_type.lookups.iter().all(|lookup| {
+            let value = lookup.exprs[0].0.expr.eval(assignments).unwrap();
+            (0..256).any(|byte| Fr::from(byte) == value)
+        });
+
+        constraints && lookups
+    }
+
+    #[test]
+    fn test_word_signals() {
+        let circuit: SBPIR<Fr, Args> = circuit("words", |ctx| {
+            let byte_table = range_table(ctx, 8);
+            let lhs = ctx.forward("lhs");
+
+            let step = ctx.step_type_def("step", |ctx| {
+                let lhs: U16Signal<Fr> = WordSignal::wrap(ctx, "lhs", lhs, byte_table);
+                let rhs: U8Signal<Fr> = WordSignal::internal(ctx, "rhs", byte_table);
+                let sum: U32Signal<Fr> = lhs.add(ctx, "sum", &rhs);
+                let product: U32Signal<Fr> = lhs.mul(ctx, "product", &rhs);
+                let (wrapped, carry) = lhs.wrapping_add(ctx, "wrapped", &rhs, byte_table);
+                let flag = BoolSignal::internal(ctx, "flag");
+
+                ctx.wg(move |ctx, (a, b, tamper): Args| {
+                    lhs.wg(ctx, a);
+                    rhs.wg(ctx, b);
+                    sum.wg(ctx, a + b);
+                    product.wg(ctx, a * b);
+                    wrapped.wg(ctx, (a + b) & 0xffff);
+                    carry.wg(ctx, a + b > 0xffff);
+                    flag.wg(ctx, true);
+                    if tamper {
+                        ctx.assign(lhs.bytes().unwrap()[0], Fr::from(a & 0xff) + Fr::from(256));
+                        ctx.assign(lhs.bytes().unwrap()[1], Fr::from(a >> 8) - Fr::ONE);
+                    }
+                })
+            });
+
+            ctx.pragma_num_steps(1);
+            ctx.trace(move |ctx, args| ctx.add(&step, args));
+        });
+
+        assert!(satisfied(&circuit, (0x1234, 0x56, false)));
+        assert!(satisfied(&circuit, (0xffff, 0xff, false)));
+        assert!(!satisfied(&circuit, (0x1234, 0x56, true)));
+    }
+
+    #[test]
+    fn test_bool_signal() {
+        let circuit: SBPIR<Fr, Args> = circuit("bools", |ctx| {
+            let step = ctx.step_type_def("step", |ctx| {
+                let a = BoolSignal::internal(ctx, "a");
+                let b = BoolSignal::internal(ctx, "b");
+
+                ctx.wg(move |ctx, (x, y, _): Args| {
+                    ctx.assign(a.signal(), Fr::from(x));
+                    ctx.assign(b.signal(), Fr::from(y));
+                })
+            });
+
+            ctx.pragma_num_steps(1);
+            ctx.trace(move |ctx, args| ctx.add(&step, args));
+        });
+
+        assert!(satisfied(&circuit, (0, 1, false)));
+        assert!(!satisfied(&circuit, (2, 1, false)));
+    }
+
+    #[test]
+    #[should_panic(expected = "product of 16 and 8 bits does not fit in 16 bits")]
+    fn test_word_width_bug() {
+        let _: SBPIR<Fr, Args> = circuit("words", |ctx| {
+            let byte_table = range_table(ctx, 8);
+            ctx.step_type_def("step", |ctx| {
+                let lhs: U16Signal<Fr> = WordSignal::internal(ctx, "lhs", byte_table);
+                let rhs: U8Signal<Fr> = WordSignal::internal(ctx, "rhs", byte_table);
+                let _: U16Signal<Fr> = lhs.mul(ctx, "product", &rhs);
+
+                ctx.wg(|_, _: Args| {})
+            });
+        });
+    }
+}
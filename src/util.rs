@@ -6,3 +6,22 @@ pub type UUID = u128;
 pub fn uuid() -> UUID {
     Uuid::now_v1(&[10; 6]).as_u128()
 }
+
+/// Small deterministic pseudorandom generator, for the seeded generators of test data.
+pub struct SplitMix64(pub u64);
+
+impl SplitMix64 {
+    pub fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `[0, n)`.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
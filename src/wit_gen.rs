@@ -1,13 +1,22 @@
-use std::{collections::HashMap, fmt, hash::Hash, rc::Rc};
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::Hash,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
 use crate::{
     field::Field,
-    frontend::dsl::StepTypeWGHandler,
+    frontend::dsl::{StepTypeHandler, StepTypeWGHandler},
     poly::Expr,
-    sbpir::{query::Queriable, StepTypeUUID, PIR, SBPIR},
-    util::UUID,
+    sbpir::{query::Queriable, StepType, StepTypeUUID, PIR, SBPIR},
+    util::{SplitMix64, UUID},
 };
 
+pub mod binary;
+pub mod set;
+
 /// A struct that represents a witness generation context. It provides an interface for assigning
 /// values to witness columns in a circuit.
 #[derive(Debug, Default, Clone)]
@@ -35,6 +44,29 @@ impl<F> StepInstance<F> {
     }
 }
 
+impl<F: Clone + Eq + Hash> StepInstance<F> {
+    /// Returns a step instance of `padding_step` with the forward and shared signals of
+    /// `previous`, see `TraceWitness::pad`.
+    pub fn padding_after(padding_step: StepTypeUUID, previous: Option<&StepInstance<F>>) -> Self {
+        let mut padding = StepInstance::new(padding_step);
+        if let Some(previous) = previous {
+            padding.assignments = previous
+                .assignments
+                .iter()
+                .filter(|(signal, _)| {
+                    matches!(
+                        signal,
+                        Queriable::Forward(_, false) | Queriable::Shared(_, 0)
+                    )
+                })
+                .map(|(signal, value)| (signal.clone(), value.clone()))
+                .collect();
+        }
+
+        padding
+    }
+}
+
 impl<F: Eq + Hash> StepInstance<F> {
     /// Takes a `Queriable` object representing the witness column (lhs) and the value (rhs) to be
     /// assigned.
@@ -68,10 +100,371 @@ impl<F: fmt::Debug> fmt::Display for TraceWitness<F> {
     }
 }
 
+impl<F: Clone + Eq + Hash> TraceWitness<F> {
+    /// Adds step instances of `padding_step` until there are `num_steps`. Each of them has the
+    /// forward and shared signals of the previous one, so their values stay the ones of the last
+    /// step instance of the witness. Panics if the witness has more than `num_steps` instances.
+    pub fn pad(&mut self, padding_step: StepTypeUUID, num_steps: usize) {
+        assert!(
+            self.step_instances.len() <= num_steps,
+            "witness has {} step instances, more than the {} of the circuit",
+            self.step_instances.len(),
+            num_steps
+        );
+
+        while self.step_instances.len() < num_steps {
+            let padding = StepInstance::padding_after(padding_step, self.step_instances.last());
+            self.step_instances.push(padding);
+        }
+    }
+}
+
+/// Builder of a `TraceWitness` from witness data at hand, without a trace function. The signals
+/// assigned in each step instance are checked against the ones its step type can assign: its
+/// internal signals and the forward, shared and imported halo2 advice signals of the circuit.
+///
+/// ```ignore
+/// let witness = TraceWitnessBuilder::new(&circuit)
+///     .step(&fibo_step)
+///     .assign(a, Fr::from(1))
+///     .assign(b, Fr::from(1))
+///     .done()
+///     .build();
+/// ```
+pub struct TraceWitnessBuilder<'a, F, TraceArgs> {
+    circuit: &'a SBPIR<F, TraceArgs>,
+    witness: TraceWitness<F>,
+}
+
+impl<'a, F: Eq + Hash, TraceArgs> TraceWitnessBuilder<'a, F, TraceArgs> {
+    pub fn new(circuit: &'a SBPIR<F, TraceArgs>) -> Self {
+        Self {
+            circuit,
+            witness: TraceWitness {
+                step_instances: Vec::new(),
+            },
+        }
+    }
+
+    /// Starts a step instance of `step_type`, which is added to the witness by
+    /// `StepInstanceBuilder::done`. Panics if the step type is not in the circuit.
+    pub fn step<STH: Into<StepTypeHandler>>(
+        self,
+        step_type: STH,
+    ) -> StepInstanceBuilder<'a, F, TraceArgs> {
+        let step_type = step_type.into();
+        if !self.circuit.step_types.contains_key(&step_type.uuid()) {
+            panic!("step type {} is not in the circuit", step_type.annotation);
+        }
+
+        StepInstanceBuilder {
+            parent: self,
+            instance: StepInstance::new(step_type.uuid()),
+        }
+    }
+
+    pub fn build(self) -> TraceWitness<F> {
+        self.witness
+    }
+}
+
+/// Returns if the step instances of a step type can assign a signal: one of its internal signals,
+/// or a forward, shared or imported halo2 advice signal of the circuit.
+fn can_assign<F, TraceArgs>(
+    circuit: &SBPIR<F, TraceArgs>,
+    step_type: &StepType<F>,
+    signal: &Queriable<F>,
+) -> bool {
+    match signal {
+        Queriable::Internal(internal) => step_type.signals.contains(internal),
+        Queriable::Forward(forward, _) => circuit.forward_signals.contains(forward),
+        Queriable::Shared(shared, _) => circuit.shared_signals.contains(shared),
+        #[cfg(feature = "halo2")]
+        Queriable::Halo2AdviceQuery(advice, _) => circuit.halo2_advice.contains(advice),
+        _ => false,
+    }
+}
+
+/// Error in a step instance of a witness, found by `TraceWitness::validate`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WitnessError {
+    /// The step type of the step instance is not in the circuit.
+    UnknownStepType {
+        step: usize,
+        step_type_uuid: StepTypeUUID,
+    },
+    /// The step instance assigns a signal that its step type cannot assign, like an internal
+    /// signal of another step type or a fixed signal.
+    UnassignableSignal {
+        step: usize,
+        step_type: String,
+        signal: String,
+    },
+    /// The step instance assigns the next value of a forward signal, or a rotated shared signal,
+    /// and the step instance it falls in assigns a different value.
+    ConflictingAssignment {
+        step: usize,
+        step_type: String,
+        signal: String,
+        other_step: usize,
+    },
+    /// The step instance assigns a shared signal rotated to before the first step instance or
+    /// after the last one.
+    RotationOutOfWitness {
+        step: usize,
+        step_type: String,
+        signal: String,
+    },
+}
+
+impl fmt::Display for WitnessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WitnessError::UnknownStepType {
+                step,
+                step_type_uuid,
+            } => write!(f, "step {}: unknown step type {}", step, step_type_uuid),
+            WitnessError::UnassignableSignal {
+                step,
+                step_type,
+                signal,
+            } => write!(
+                f,
+                "step {}: signal {} cannot be assigned in step type {}",
+                step, signal, step_type
+            ),
+            WitnessError::ConflictingAssignment {
+                step,
+                step_type,
+                signal,
+                other_step,
+            } => write!(
+                f,
+                "step {} ({}): {} conflicts with the value assigned in step {}",
+                step, step_type, signal, other_step
+            ),
+            WitnessError::RotationOutOfWitness {
+                step,
+                step_type,
+                signal,
+            } => write!(
+                f,
+                "step {} ({}): {} is outside of the witness",
+                step, step_type, signal
+            ),
+        }
+    }
+}
+
+impl<F: Eq + Hash> TraceWitness<F> {
+    /// Validates the step instances against their step types in `circuit`: their step types are in
+    /// the circuit, they only assign signals their step types can assign, and the values they
+    /// assign in the rows of other step instances, through the next value of forward signals and
+    /// rotated shared signals, agree with the values assigned there. Returns all the errors found.
+    pub fn validate<TraceArgs>(
+        &self,
+        circuit: &SBPIR<F, TraceArgs>,
+    ) -> Result<(), Vec<WitnessError>> {
+        let mut errors = Vec::new();
+
+        for (step, instance) in self.step_instances.iter().enumerate() {
+            let step_type = match circuit.step_types.get(&instance.step_type_uuid) {
+                Some(step_type) => step_type,
+                None => {
+                    errors.push(WitnessError::UnknownStepType {
+                        step,
+                        step_type_uuid: instance.step_type_uuid,
+                    });
+                    continue;
+                }
+            };
+
+            let mut signals: Vec<&Queriable<F>> = instance.assignments.keys().collect();
+            signals.sort_by_key(|signal| signal.annotation());
+            for signal in signals {
+                if !can_assign(circuit, step_type, signal) {
+                    errors.push(WitnessError::UnassignableSignal {
+                        step,
+                        step_type: step_type.name.clone(),
+                        signal: signal.annotation(),
+                    });
+                    continue;
+                }
+
+                let (other_step, other_signal) = match signal {
+                    Queriable::Forward(forward, true) => {
+                        (Some(step + 1), Queriable::Forward(*forward, false))
+                    }
+                    Queriable::Shared(shared, rotation) if *rotation != 0 => (
+                        usize::try_from(step as i64 + *rotation as i64).ok(),
+                        Queriable::Shared(*shared, 0),
+                    ),
+                    _ => continue,
+                };
+                let is_forward = matches!(signal, Queriable::Forward(..));
+                match other_step.filter(|other| *other < self.step_instances.len()) {
+                    Some(other_step) => {
+                        let other = &self.step_instances[other_step].assignments;
+                        if matches!(other.get(&other_signal), Some(value) if *value != instance.assignments[signal])
+                        {
+                            errors.push(WitnessError::ConflictingAssignment {
+                                step,
+                                step_type: step_type.name.clone(),
+                                signal: signal.annotation(),
+                                other_step,
+                            });
+                        }
+                    }
+                    // the next value of a forward signal in the last step instance is allowed
+                    None if is_forward => {}
+                    None => errors.push(WitnessError::RotationOutOfWitness {
+                        step,
+                        step_type: step_type.name.clone(),
+                        signal: signal.annotation(),
+                    }),
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Step instance being built by a `TraceWitnessBuilder`.
+pub struct StepInstanceBuilder<'a, F, TraceArgs> {
+    parent: TraceWitnessBuilder<'a, F, TraceArgs>,
+    instance: StepInstance<F>,
+}
+
+impl<'a, F: Eq + Hash, TraceArgs> StepInstanceBuilder<'a, F, TraceArgs> {
+    /// Assigns `value` to `signal`. Panics if the step type cannot assign the signal.
+    pub fn assign(mut self, signal: Queriable<F>, value: F) -> Self {
+        let circuit = self.parent.circuit;
+        let step_type = &circuit.step_types[&self.instance.step_type_uuid];
+        if !can_assign(circuit, step_type, &signal) {
+            panic!(
+                "signal {} cannot be assigned in step type {}",
+                signal.annotation(),
+                step_type.name
+            );
+        }
+
+        self.instance.assign(signal, value);
+
+        self
+    }
+
+    /// Adds the step instance to the witness.
+    pub fn done(mut self) -> TraceWitnessBuilder<'a, F, TraceArgs> {
+        self.parent.witness.step_instances.push(self.instance);
+
+        self.parent
+    }
+}
+
+/// Seeded generator of witnesses that are structurally valid for a circuit, but do not
+/// necessarily satisfy its constraints: the step instances are of step types of the circuit,
+/// honoring its first, last and padding steps, and each assigns the internal signals of its step
+/// type and the forward and shared signals of the circuit with random values. The same seed and
+/// circuit give the same witnesses. Useful to fuzz the compiler, the serializers and the
+/// assignment generation.
+pub struct RandomWitnessGenerator<'a, F, TraceArgs> {
+    circuit: &'a SBPIR<F, TraceArgs>,
+    rng: SplitMix64,
+}
+
+impl<'a, F: Field + Hash, TraceArgs> RandomWitnessGenerator<'a, F, TraceArgs> {
+    pub fn new(circuit: &'a SBPIR<F, TraceArgs>, seed: u64) -> Self {
+        Self {
+            circuit,
+            rng: SplitMix64(seed),
+        }
+    }
+
+    /// Returns a witness of `num_steps` step instances, ending in a random number of padding
+    /// steps if the circuit has a padding step. Panics if the circuit has no step types.
+    pub fn generate(&mut self, num_steps: usize) -> TraceWitness<F> {
+        let padding_step = self.circuit.padding_step;
+        let mut step_types: Vec<StepTypeUUID> = self
+            .circuit
+            .step_types
+            .keys()
+            .copied()
+            .filter(|uuid| Some(*uuid) != padding_step || self.circuit.step_types.len() == 1)
+            .collect();
+        step_types.sort();
+        assert!(!step_types.is_empty(), "circuit has no step types");
+
+        let real_steps = match padding_step {
+            Some(_) if num_steps > 0 => 1 + self.rng.below(num_steps),
+            _ => num_steps,
+        };
+        let mut witness = TraceWitness {
+            step_instances: Vec::with_capacity(num_steps),
+        };
+        for index in 0..num_steps {
+            let step_type_uuid = if index >= real_steps {
+                padding_step.unwrap()
+            } else if let (0, Some(first_step)) = (index, self.circuit.first_step) {
+                first_step
+            } else if let (true, Some(last_step)) =
+                (index + 1 == real_steps, self.circuit.last_step)
+            {
+                last_step
+            } else {
+                step_types[self.rng.below(step_types.len())]
+            };
+
+            witness
+                .step_instances
+                .push(self.step_instance(step_type_uuid));
+        }
+
+        witness
+    }
+
+    fn step_instance(&mut self, step_type_uuid: StepTypeUUID) -> StepInstance<F> {
+        let circuit = self.circuit;
+        let signals = circuit.step_types[&step_type_uuid]
+            .signals
+            .iter()
+            .map(|signal| Queriable::Internal(*signal))
+            .chain(
+                circuit
+                    .forward_signals
+                    .iter()
+                    .map(|signal| Queriable::Forward(*signal, false)),
+            )
+            .chain(
+                circuit
+                    .shared_signals
+                    .iter()
+                    .map(|signal| Queriable::Shared(*signal, 0)),
+            );
+
+        let mut step_instance = StepInstance::new(step_type_uuid);
+        for signal in signals {
+            step_instance.assign(signal, F::from(self.rng.next()));
+        }
+
+        step_instance
+    }
+}
+
 #[derive(Debug)]
 pub struct TraceContext<F> {
     witness: TraceWitness<F>,
     num_steps: usize,
+    /// Number of step instances generated before the ones in `witness`, taken out or generated
+    /// before a checkpoint.
+    offset: usize,
+    /// Forward and shared signals after the step instances before `witness`.
+    carried: Option<StepInstance<F>>,
+    profile: Option<WitnessProfile>,
 }
 
 impl<F: Default> TraceContext<F> {
@@ -79,6 +472,21 @@ impl<F: Default> TraceContext<F> {
         Self {
             witness: TraceWitness::default(),
             num_steps,
+            offset: 0,
+            carried: None,
+            profile: None,
+        }
+    }
+
+    /// Returns a context that continues the trace from a checkpoint, see
+    /// `TraceContext::checkpoint`. Its witness only has the step instances added after it.
+    pub fn resume(checkpoint: TraceCheckpoint<F>) -> Self {
+        Self {
+            witness: TraceWitness::default(),
+            num_steps: checkpoint.num_steps,
+            offset: checkpoint.steps,
+            carried: checkpoint.last,
+            profile: None,
         }
     }
 
@@ -87,6 +495,81 @@ impl<F: Default> TraceContext<F> {
     }
 }
 
+/// State of the generation of a trace, to resume it later or in another process. It has the number
+/// of step instances generated and the values of the forward and shared signals after the last
+/// of them, so they do not need to be kept. See `binary::write_checkpoint` to store it.
+#[derive(Debug, Clone)]
+pub struct TraceCheckpoint<F> {
+    pub num_steps: usize,
+    /// Number of step instances generated.
+    pub steps: usize,
+    /// Values of the forward signals after the last step instance, its next values if it assigns
+    /// them, and of its shared signals.
+    pub last: Option<StepInstance<F>>,
+}
+
+impl<F: Clone + Eq + Hash> TraceContext<F> {
+    /// Returns the number of step instances generated, including the ones before resuming from
+    /// a checkpoint and the ones taken out with `take_witness`.
+    pub fn steps(&self) -> usize {
+        self.offset + self.witness.step_instances.len()
+    }
+
+    /// Returns the value of a forward or shared signal after the last step instance generated,
+    /// also across checkpoints, to continue the trace from it.
+    pub fn last_value(&self, signal: Queriable<F>) -> Option<F> {
+        match self.witness.step_instances.last() {
+            Some(step_instance) => Self::carry(step_instance).assignments.get(&signal).cloned(),
+            None => self
+                .carried
+                .as_ref()
+                .and_then(|carried| carried.assignments.get(&signal).cloned()),
+        }
+    }
+
+    /// Takes out the step instances generated so far, to store them before the trace is
+    /// finished. The context keeps counting them.
+    pub fn take_witness(&mut self) -> TraceWitness<F> {
+        if let Some(last) = self.witness.step_instances.last() {
+            self.carried = Some(Self::carry(last));
+        }
+        self.offset += self.witness.step_instances.len();
+
+        TraceWitness {
+            step_instances: std::mem::take(&mut self.witness.step_instances),
+        }
+    }
+
+    /// Returns a checkpoint of the generation of the trace, see `TraceContext::resume`.
+    pub fn checkpoint(&self) -> TraceCheckpoint<F> {
+        TraceCheckpoint {
+            num_steps: self.num_steps,
+            steps: self.steps(),
+            last: self
+                .witness
+                .step_instances
+                .last()
+                .map(Self::carry)
+                .or_else(|| self.carried.clone()),
+        }
+    }
+
+    /// Returns the forward and shared signals after a step instance.
+    fn carry(step_instance: &StepInstance<F>) -> StepInstance<F> {
+        let mut carried =
+            StepInstance::padding_after(step_instance.step_type_uuid, Some(step_instance));
+        for (signal, value) in step_instance.assignments.iter() {
+            if let Queriable::Forward(forward, true) = signal {
+                carried
+                    .assignments
+                    .insert(Queriable::Forward(*forward, false), value.clone());
+            }
+        }
+
+        carried
+    }
+}
+
 impl<F> TraceContext<F> {
     pub fn add<Args, WG: Fn(&mut StepInstance<F>, Args) + 'static>(
         &mut self,
@@ -95,23 +578,69 @@ impl<F> TraceContext<F> {
     ) {
         let mut witness = StepInstance::new(step.uuid());
 
+        let start = self.profile.is_some().then(Instant::now);
         (*step.wg)(&mut witness, args);
+        if let (Some(profile), Some(start)) = (self.profile.as_mut(), start) {
+            let step_profile = profile.step_mut(step.uuid());
+            step_profile.annotation = step.annotation.to_string();
+            step_profile.instances += 1;
+            step_profile.trace_time += start.elapsed();
+        }
 
         self.witness.step_instances.push(witness);
     }
 
+    /// Adds a step instance of `step` for each of the arguments pulled from `source`, until it is
+    /// exhausted. Returns the number of instances added.
+    pub fn add_from<Args, WG, S>(
+        &mut self,
+        step: &StepTypeWGHandler<F, Args, WG>,
+        mut source: S,
+    ) -> usize
+    where
+        WG: Fn(&mut StepInstance<F>, Args) + 'static,
+        S: WitnessSource<Item = Args>,
+    {
+        let mut count = 0;
+        while let Some(args) = source.next_step() {
+            self.add(step, args);
+            count += 1;
+        }
+
+        count
+    }
+
     // This function pads the rest of the circuit with the given StepTypeWGHandler
     pub fn padding<Args, WG: Fn(&mut StepInstance<F>, Args) + 'static>(
         &mut self,
         step: &StepTypeWGHandler<F, Args, WG>,
         args_fn: impl Fn() -> Args,
     ) {
-        while self.witness.step_instances.len() < self.num_steps {
+        while self.offset + self.witness.step_instances.len() < self.num_steps {
             self.add(step, (args_fn)());
         }
     }
 }
 
+/// Source of the witness data of a trace, pulled one step at a time, so it does not need to be in
+/// memory up front in the trace arguments. It can be read from a file, a database cursor or a
+/// channel, and it is implemented for all iterators. See `TraceContext::add_from`.
+pub trait WitnessSource {
+    /// Arguments of the witness generation of a step.
+    type Item;
+
+    /// Returns the arguments of the next step, or `None` when the source is exhausted.
+    fn next_step(&mut self) -> Option<Self::Item>;
+}
+
+impl<I: Iterator> WitnessSource for I {
+    type Item = I::Item;
+
+    fn next_step(&mut self) -> Option<I::Item> {
+        self.next()
+    }
+}
+
 pub type Trace<F, TraceArgs> = dyn Fn(&mut TraceContext<F>, TraceArgs) + 'static;
 
 pub struct TraceGenerator<F, TraceArgs> {
@@ -149,17 +678,122 @@ impl<F: Default, TraceArgs> TraceGenerator<F, TraceArgs> {
 
         ctx.get_witness()
     }
+
+    /// Generates the witness as `generate`, and reports the time spent in the witness generation
+    /// of each step type.
+    pub fn generate_profiled(&self, args: TraceArgs) -> (TraceWitness<F>, WitnessProfile) {
+        let mut ctx = TraceContext::new(self.num_steps);
+        ctx.profile = Some(WitnessProfile::default());
+
+        (self.trace)(&mut ctx, args);
+
+        let profile = ctx.profile.take().unwrap_or_default();
+        (ctx.get_witness(), profile)
+    }
+
+    /// Generates the rest of a trace from a checkpoint, see `TraceContext::resume`. The trace
+    /// function is called with the resumed context, and can continue from
+    /// `TraceContext::steps` and `TraceContext::last_value`.
+    pub fn resume(&self, checkpoint: TraceCheckpoint<F>, args: TraceArgs) -> TraceWitness<F> {
+        let mut ctx = TraceContext::resume(checkpoint);
+
+        (self.trace)(&mut ctx, args);
+
+        ctx.get_witness()
+    }
+}
+
+/// Time spent and rows produced generating the witness of each step type, to find the step types
+/// whose witness generation is the bottleneck. See `TraceGenerator::generate_profiled` and
+/// `AssignmentGenerator::generate_profiled`.
+#[derive(Debug, Clone, Default)]
+pub struct WitnessProfile {
+    pub steps: HashMap<StepTypeUUID, StepProfile>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StepProfile {
+    /// Annotation of the step type, empty if it only has padding step instances.
+    pub annotation: String,
+    /// Number of step instances, including the padding ones.
+    pub instances: usize,
+    /// Time spent in the witness generation function of the step type.
+    pub trace_time: Duration,
+    /// Time spent generating the auto signals of the step instances and assigning them.
+    pub assignment_time: Duration,
+    /// Number of rows assigned by the step instances.
+    pub rows: usize,
+}
+
+impl StepProfile {
+    pub fn total_time(&self) -> Duration {
+        self.trace_time + self.assignment_time
+    }
+}
+
+impl WitnessProfile {
+    pub fn total_time(&self) -> Duration {
+        self.steps.values().map(StepProfile::total_time).sum()
+    }
+
+    /// Returns the profiles of the step types, from the one that takes the most time.
+    pub fn sorted(&self) -> Vec<(StepTypeUUID, &StepProfile)> {
+        let mut steps: Vec<(StepTypeUUID, &StepProfile)> = self
+            .steps
+            .iter()
+            .map(|(uuid, profile)| (*uuid, profile))
+            .collect();
+        steps.sort_by(|(a_uuid, a), (b_uuid, b)| {
+            b.total_time().cmp(&a.total_time()).then(a_uuid.cmp(b_uuid))
+        });
+
+        steps
+    }
+
+    pub(crate) fn step_mut(&mut self, step_uuid: StepTypeUUID) -> &mut StepProfile {
+        self.steps.entry(step_uuid).or_default()
+    }
+}
+
+impl fmt::Display for WitnessProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<24} {:>10} {:>14} {:>14} {:>10}",
+            "step type", "instances", "trace", "assignment", "rows"
+        )?;
+        for (uuid, step) in self.sorted() {
+            let name = if step.annotation.is_empty() {
+                uuid.to_string()
+            } else {
+                step.annotation.clone()
+            };
+            writeln!(
+                f,
+                "{:<24} {:>10} {:>14} {:>14} {:>10}",
+                name,
+                step.instances,
+                format!("{:.2?}", step.trace_time),
+                format!("{:.2?}", step.assignment_time),
+                step.rows
+            )?;
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct AutoTraceGenerator<F> {
     auto_signals: HashMap<UUID, HashMap<Queriable<F>, PIR<F>>>,
+    padding: Option<(StepTypeUUID, usize)>,
 }
 
 impl<F> Default for AutoTraceGenerator<F> {
     fn default() -> Self {
         Self {
             auto_signals: Default::default(),
+            padding: None,
         }
     }
 }
@@ -171,8 +805,14 @@ impl<F: Clone, TraceArgs> From<&SBPIR<F, TraceArgs>> for AutoTraceGenerator<F> {
             .iter()
             .map(|(&uuid, step_type)| (uuid, step_type.auto_signals.clone()))
             .collect();
+        let padding = circuit
+            .padding_step
+            .map(|padding_step| (padding_step, circuit.num_steps));
 
-        Self { auto_signals }
+        Self {
+            auto_signals,
+            padding,
+        }
     }
 }
 
@@ -214,19 +854,51 @@ pub(crate) fn calc_auto_signals<F: Field + Hash, V: Clone + Eq + PartialEq + Has
     }
 }
 
+impl<F> AutoTraceGenerator<F> {
+    /// Pads the witnesses with fewer than `num_steps` step instances with `padding_step`, see
+    /// `TraceWitness::pad`. It is the padding step of the circuit by default.
+    pub fn with_padding(mut self, padding_step: StepTypeUUID, num_steps: usize) -> Self {
+        self.padding = Some((padding_step, num_steps));
+
+        self
+    }
+
+    /// Does not pad the witnesses.
+    pub fn without_padding(mut self) -> Self {
+        self.padding = None;
+
+        self
+    }
+
+    /// Returns the padding step type and the number of steps the witnesses are padded to.
+    pub fn padding(&self) -> Option<(StepTypeUUID, usize)> {
+        self.padding
+    }
+}
+
 impl<F: Field + Eq + PartialEq + Hash + Clone> AutoTraceGenerator<F> {
+    /// Pads the witness, if there is a padding step, and assigns the auto signals of its step
+    /// instances.
     pub fn generate(&self, mut witness: TraceWitness<F>) -> TraceWitness<F> {
+        if let Some((padding_step, num_steps)) = self.padding {
+            witness.pad(padding_step, num_steps);
+        }
         for step_instance in witness.step_instances.iter_mut() {
-            let uuid = step_instance.step_type_uuid;
-
-            if let Some(auto_signals) = self.auto_signals.get(&uuid) {
-                self.step_gen(auto_signals, step_instance)
-            }
+            self.generate_step(step_instance);
         }
 
         witness
     }
 
+    /// Assigns the auto signals of a step instance.
+    pub fn generate_step(&self, step_instance: &mut StepInstance<F>) {
+        let uuid = step_instance.step_type_uuid;
+
+        if let Some(auto_signals) = self.auto_signals.get(&uuid) {
+            self.step_gen(auto_signals, step_instance)
+        }
+    }
+
     fn step_gen(
         &self,
         auto_signals: &HashMap<Queriable<F>, PIR<F>>,
@@ -253,28 +925,76 @@ impl<F: Field + Hash> FixedGenContext<F> {
         }
     }
 
+    pub fn num_steps(&self) -> usize {
+        self.num_steps
+    }
+
     /// Takes a `Queriable` object representing the fixed column (lhs) and the value (rhs) to be
     /// assigned.
     pub fn assign(&mut self, offset: usize, lhs: Queriable<F>, rhs: F) {
-        if !Self::is_fixed_queriable(lhs) {
-            panic!("trying to assign non-fixed signal");
-        }
+        self.column(lhs)[offset] = rhs;
+    }
 
-        if let Some(assignments) = self.assignments.get_mut(&lhs) {
-            assignments[offset] = rhs;
-        } else {
-            let mut assignments = vec![F::ZERO; self.num_steps];
-            assignments[offset] = rhs;
-            self.assignments.insert(lhs, assignments);
+    /// Assigns the values of an iterator from the first offset, stopping at the number of steps.
+    /// The offsets after a shorter iterator keep their values.
+    pub fn assign_iter<I: IntoIterator<Item = F>>(&mut self, lhs: Queriable<F>, values: I) {
+        for (assignment, value) in self.column(lhs).iter_mut().zip(values) {
+            *assignment = value;
         }
     }
 
+    /// Assigns `value(offset)` to every offset. It is only evaluated for the number of steps of the
+    /// circuit.
+    pub fn assign_with<V: Fn(usize) -> F>(&mut self, lhs: Queriable<F>, value: V) {
+        self.assign_iter(lhs, (0..self.num_steps).map(value));
+    }
+
+    /// Assigns `pattern` repeatedly, so offset `i` has `pattern[i % pattern.len()]`.
+    pub fn assign_repeat(&mut self, lhs: Queriable<F>, pattern: &[F]) {
+        assert!(!pattern.is_empty(), "cannot repeat an empty pattern");
+
+        self.assign_iter(lhs, pattern.iter().copied().cycle());
+    }
+
+    /// Assigns the values of several iterators in turns, so offset `i` has the next value of
+    /// `sources[i % sources.len()]`. It stops at the number of steps or when the next source to
+    /// take a value from is exhausted.
+    pub fn assign_interleave<I: IntoIterator<Item = F>>(
+        &mut self,
+        lhs: Queriable<F>,
+        sources: Vec<I>,
+    ) {
+        assert!(!sources.is_empty(), "cannot interleave without sources");
+
+        let mut sources: Vec<I::IntoIter> = sources.into_iter().map(|s| s.into_iter()).collect();
+        let count = sources.len();
+        let values = (0..).map_while(move |i| sources[i % count].next());
+        self.assign_iter(lhs, values);
+    }
+
     pub fn get_assignments(self) -> FixedAssignment<F> {
         self.assignments
     }
 
+    /// Returns the assignments of a fixed column, with a value per step.
+    fn column(&mut self, lhs: Queriable<F>) -> &mut Vec<F> {
+        if !Self::is_fixed_queriable(lhs) {
+            panic!("trying to assign non-fixed signal");
+        }
+
+        let num_steps = self.num_steps;
+        self.assignments
+            .entry(lhs)
+            .or_insert_with(|| vec![F::ZERO; num_steps])
+    }
+
     fn is_fixed_queriable(q: Queriable<F>) -> bool {
-        matches!(q, Queriable::Halo2FixedQuery(_, _) | Queriable::Fixed(_, _))
+        match q {
+            Queriable::Fixed(_, _) => true,
+            #[cfg(feature = "halo2")]
+            Queriable::Halo2FixedQuery(_, _) => true,
+            _ => false,
+        }
     }
 }
 
@@ -283,7 +1003,7 @@ mod tests {
     use super::*;
     use crate::{
         frontend::dsl::StepTypeWGHandler,
-        sbpir::{query::Queriable, FixedSignal, ForwardSignal},
+        sbpir::{query::Queriable, FixedSignal, ForwardSignal, InternalSignal, SharedSignal},
         util::uuid,
     };
     use halo2_proofs::halo2curves::bn256::Fr;
@@ -315,6 +1035,319 @@ mod tests {
         assert_eq!(ctx.witness.step_instances.len(), 5);
     }
 
+    struct Countdown(u64);
+
+    impl WitnessSource for Countdown {
+        type Item = u64;
+
+        fn next_step(&mut self) -> Option<u64> {
+            self.0 = self.0.checked_sub(1)?;
+            Some(self.0)
+        }
+    }
+
+    #[test]
+    fn test_add_from_witness_source() {
+        let signal = Queriable::Forward(ForwardSignal::new_with_phase(0, "a".into()), false);
+        let step = StepTypeWGHandler::new(uuid(), "step", move |ctx, value: u64| {
+            ctx.assign(signal, Fr::from(value))
+        });
+        let values = |ctx: TraceContext<Fr>| -> Vec<Fr> {
+            ctx.get_witness()
+                .step_instances
+                .iter()
+                .map(|instance| instance.assignments[&signal])
+                .collect()
+        };
+
+        let mut ctx = TraceContext::new(5);
+        assert_eq!(ctx.add_from(&step, Countdown(3)), 3);
+        assert_eq!(values(ctx), [2, 1, 0].map(Fr::from));
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let producer = std::thread::spawn(move || {
+            for value in 0..4 {
+                sender.send(value * 10).unwrap();
+            }
+        });
+        let mut ctx = TraceContext::new(5);
+        assert_eq!(ctx.add_from(&step, receiver.iter()), 4);
+        producer.join().unwrap();
+        assert_eq!(values(ctx), [0, 10, 20, 30].map(Fr::from));
+    }
+
+    type Handlers = (
+        Queriable<Fr>,
+        Queriable<Fr>,
+        Queriable<Fr>,
+        StepTypeHandler,
+        StepTypeHandler,
+    );
+
+    fn builder_circuit() -> (SBPIR<Fr, ()>, Handlers) {
+        let handlers = std::cell::RefCell::new(None);
+        let circuit = crate::frontend::dsl::circuit("builder", |ctx| {
+            let a = ctx.forward("a");
+            let (mut b, mut c) = (None, None);
+            let first = ctx.step_type_def("first", |ctx| {
+                b = Some(ctx.internal("b"));
+                ctx.wg(|_, _: ()| {})
+            });
+            let second = ctx.step_type_def("second", |ctx| {
+                c = Some(ctx.internal("c"));
+                ctx.wg(|_, _: ()| {})
+            });
+            *handlers.borrow_mut() =
+                Some((a, b.unwrap(), c.unwrap(), (&first).into(), (&second).into()));
+        });
+
+        (circuit, handlers.into_inner().unwrap())
+    }
+
+    #[test]
+    fn test_trace_witness_builder() {
+        let (circuit, (a, b, c, first, second)) = builder_circuit();
+
+        let witness = TraceWitnessBuilder::new(&circuit)
+            .step(first)
+            .assign(a, Fr::from(1))
+            .assign(b, Fr::from(2))
+            .done()
+            .step(second)
+            .assign(a, Fr::from(3))
+            .assign(a.next(), Fr::from(4))
+            .assign(c, Fr::from(5))
+            .done()
+            .build();
+
+        assert_eq!(witness.step_instances.len(), 2);
+        assert_eq!(witness.step_instances[0].step_type_uuid, first.uuid());
+        assert_eq!(
+            witness.step_instances[0].assignments,
+            HashMap::from([(a, Fr::from(1)), (b, Fr::from(2))])
+        );
+        assert_eq!(witness.step_instances[1].step_type_uuid, second.uuid());
+        assert_eq!(
+            witness.step_instances[1].assignments[&a.next()],
+            Fr::from(4)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot be assigned in step type second")]
+    fn test_trace_witness_builder_wrong_signal() {
+        let (circuit, (_, b, _, _, second)) = builder_circuit();
+
+        TraceWitnessBuilder::new(&circuit)
+            .step(second)
+            .assign(b, Fr::ONE);
+    }
+
+    #[test]
+    fn test_trace_witness_validate() {
+        let (circuit, (a, b, c, first, second)) = builder_circuit();
+
+        let witness = TraceWitnessBuilder::new(&circuit)
+            .step(first)
+            .assign(a, Fr::from(1))
+            .assign(a.next(), Fr::from(3))
+            .done()
+            .step(second)
+            .assign(a, Fr::from(3))
+            .done()
+            .build();
+        assert_eq!(witness.validate(&circuit), Ok(()));
+
+        let witness = TraceWitness {
+            step_instances: vec![
+                StepInstance {
+                    step_type_uuid: first.uuid(),
+                    assignments: HashMap::from([(a.next(), Fr::from(4)), (c, Fr::ONE)]),
+                },
+                StepInstance {
+                    step_type_uuid: second.uuid(),
+                    assignments: HashMap::from([(a, Fr::from(3)), (b, Fr::ONE)]),
+                },
+                StepInstance::new(0),
+            ],
+        };
+        let errors = witness.validate(&circuit).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                WitnessError::UnassignableSignal {
+                    step: 0,
+                    step_type: "first".to_string(),
+                    signal: "c".to_string(),
+                },
+                WitnessError::ConflictingAssignment {
+                    step: 0,
+                    step_type: "first".to_string(),
+                    signal: "next(a)".to_string(),
+                    other_step: 1,
+                },
+                WitnessError::UnassignableSignal {
+                    step: 1,
+                    step_type: "second".to_string(),
+                    signal: "b".to_string(),
+                },
+                WitnessError::UnknownStepType {
+                    step: 2,
+                    step_type_uuid: 0,
+                },
+            ]
+        );
+        assert_eq!(
+            errors[1].to_string(),
+            "step 0 (first): next(a) conflicts with the value assigned in step 1"
+        );
+    }
+
+    #[test]
+    fn test_random_witness_generator() {
+        let (circuit, (a, b, c, first, second)) = builder_circuit();
+        let generate = |seed| RandomWitnessGenerator::new(&circuit, seed).generate(6);
+
+        let witness = generate(3);
+        assert_eq!(witness.step_instances.len(), 6);
+        assert_eq!(witness.validate(&circuit), Ok(()));
+        for step_instance in witness.step_instances.iter() {
+            let internal = if step_instance.step_type_uuid == first.uuid() {
+                b
+            } else {
+                assert_eq!(step_instance.step_type_uuid, second.uuid());
+                c
+            };
+            let mut signals: Vec<_> = step_instance.assignments.keys().copied().collect();
+            signals.sort_by_key(|signal| signal.annotation());
+            assert_eq!(signals, vec![a, internal]);
+        }
+
+        let assignments = |witness: TraceWitness<Fr>| -> Vec<_> {
+            witness
+                .step_instances
+                .into_iter()
+                .map(|step_instance| (step_instance.step_type_uuid, step_instance.assignments))
+                .collect()
+        };
+        assert_eq!(assignments(generate(3)), assignments(witness.clone()));
+        assert_ne!(assignments(generate(4)), assignments(witness));
+    }
+
+    #[test]
+    fn test_trace_checkpoint() {
+        use crate::frontend::dsl::circuit;
+
+        let circuit = circuit::<Fr, u64, _>("fibo", |ctx| {
+            let a = ctx.forward("a");
+            let b = ctx.forward("b");
+            let fibo = ctx.step_type_def("fibo", |ctx| {
+                ctx.wg(move |ctx, (x, y): (Fr, Fr)| {
+                    ctx.assign(a, x);
+                    ctx.assign(b, y);
+                })
+            });
+
+            ctx.trace(move |ctx, n: u64| {
+                let (mut x, mut y) = match (ctx.last_value(a), ctx.last_value(b)) {
+                    (Some(x), Some(y)) => (y, x + y),
+                    _ => (Fr::ONE, Fr::ONE),
+                };
+                for _ in ctx.steps() as u64..n {
+                    ctx.add(&fibo, (x, y));
+                    (x, y) = (y, x + y);
+                }
+            });
+        });
+        let trace = circuit.trace.clone().unwrap();
+        let generator = TraceGenerator::new(trace.clone(), 6);
+
+        let mut ctx = TraceContext::new(6);
+        trace(&mut ctx, 2);
+        let first = ctx.take_witness();
+        trace(&mut ctx, 4);
+        assert_eq!(ctx.steps(), 4);
+
+        let mut bytes = Vec::new();
+        binary::write_checkpoint(&ctx.checkpoint(), &mut bytes).unwrap();
+        let checkpoint = binary::read_checkpoint(bytes.as_slice()).unwrap();
+        assert_eq!(checkpoint.steps, 4);
+        let rest = generator.resume(checkpoint, 6);
+
+        let steps: Vec<_> = first
+            .step_instances
+            .into_iter()
+            .chain(ctx.get_witness().step_instances)
+            .chain(rest.step_instances)
+            .map(|step_instance| step_instance.assignments)
+            .collect();
+        let expected: Vec<_> = generator
+            .generate(6)
+            .step_instances
+            .into_iter()
+            .map(|step_instance| step_instance.assignments)
+            .collect();
+        assert_eq!(steps, expected);
+    }
+
+    #[test]
+    fn test_trace_witness_pad() {
+        let forward = Queriable::Forward(ForwardSignal::new_with_phase(0, "a".into()), false);
+        let shared = Queriable::Shared(SharedSignal::new_with_phase(0, "b".into()), 0);
+        let internal = Queriable::Internal(InternalSignal::new("c"));
+        let mut witness = TraceWitness::<i32> {
+            step_instances: vec![StepInstance {
+                step_type_uuid: 9,
+                assignments: HashMap::from([(forward, 1), (shared, 2), (internal, 3)]),
+            }],
+        };
+
+        witness.pad(10, 3);
+
+        assert_eq!(witness.step_instances.len(), 3);
+        for padding in witness.step_instances[1..].iter() {
+            assert_eq!(padding.step_type_uuid, 10);
+            assert_eq!(
+                padding.assignments,
+                HashMap::from([(forward, 1), (shared, 2)])
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_trace_witness_pad_too_long() {
+        let mut witness = TraceWitness::<i32> {
+            step_instances: vec![StepInstance::new(9), StepInstance::new(9)],
+        };
+
+        witness.pad(10, 1);
+    }
+
+    #[test]
+    fn test_fixed_gen_context_generators() {
+        let a = Queriable::Fixed(FixedSignal::new("a".into()), 0);
+        let b = Queriable::Fixed(FixedSignal::new("b".into()), 0);
+        let c = Queriable::Fixed(FixedSignal::new("c".into()), 0);
+        let d = Queriable::Fixed(FixedSignal::new("d".into()), 0);
+        let mut ctx = FixedGenContext::<Fr>::new(6);
+
+        ctx.assign_iter(a, (1..).map(Fr::from));
+        ctx.assign_with(b, |i| Fr::from((i * i) as u64));
+        ctx.assign_repeat(c, &[Fr::ONE, Fr::ZERO]);
+        ctx.assign_interleave(d, vec![vec![Fr::from(1), Fr::from(3)], vec![Fr::from(2)]]);
+        ctx.assign(5, d, Fr::from(7));
+
+        let assignments = ctx.get_assignments();
+        let column = |q, values: [u64; 6]| {
+            assert_eq!(assignments[&q], values.map(Fr::from).to_vec());
+        };
+        column(a, [1, 2, 3, 4, 5, 6]);
+        column(b, [0, 1, 4, 9, 16, 25]);
+        column(c, [1, 0, 1, 0, 1, 0]);
+        column(d, [1, 2, 3, 0, 0, 7]);
+    }
+
     #[test]
     fn test_trace_witness_display() {
         let display = format!(
@@ -397,6 +1430,7 @@ mod tests {
 
         let generator = AutoTraceGenerator {
             auto_signals: HashMap::from([(step_uuid, HashMap::from([(c, a + b)]))]),
+            padding: None,
         };
 
         let witness = generator.generate(witness);
@@ -410,6 +1444,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_auto_trace_gen_padding() {
+        let a = Queriable::Forward(
+            ForwardSignal::new_with_id(uuid(), 0, "a".to_string()),
+            false,
+        );
+        let b = Queriable::Internal(InternalSignal::new("b"));
+        let step_uuid = uuid();
+        let padding_uuid = uuid();
+        let mut witness = TraceWitness::default();
+        witness.step_instances.push(StepInstance {
+            step_type_uuid: step_uuid,
+            assignments: HashMap::from([(a, Fr::from(3))]),
+        });
+
+        let generator = AutoTraceGenerator {
+            auto_signals: HashMap::from([(padding_uuid, HashMap::from([(b, a + a)]))]),
+            padding: None,
+        }
+        .with_padding(padding_uuid, 3);
+
+        let witness = generator.generate(witness);
+        assert_eq!(witness.step_instances.len(), 3);
+        for padding in witness.step_instances[1..].iter() {
+            assert_eq!(padding.step_type_uuid, padding_uuid);
+            assert_eq!(
+                padding.assignments,
+                HashMap::from([(a, Fr::from(3)), (b, Fr::from(6))])
+            );
+        }
+
+        let witness = generator
+            .without_padding()
+            .generate(TraceWitness::default());
+        assert!(witness.step_instances.is_empty());
+    }
+
     #[test]
     #[should_panic]
     fn test_auto_trace_gen_panic() {
@@ -434,6 +1505,7 @@ mod tests {
 
         let generator = AutoTraceGenerator {
             auto_signals: HashMap::from([(step_uuid, HashMap::from([(c, a + b)]))]),
+            padding: None,
         };
 
         generator.generate(witness);
@@ -466,6 +1538,7 @@ mod tests {
 
         let generator = AutoTraceGenerator {
             auto_signals: HashMap::from([(step_uuid, HashMap::from([(c, a + b), (b, a + 1)]))]),
+            padding: None,
         };
 
         let witness = generator.generate(witness);
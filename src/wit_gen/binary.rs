@@ -0,0 +1,364 @@
+//! Compact binary encoding of a `TraceWitness`, to cache witnesses on disk or share them between
+//! processes. It is also the binary witness format of the frontend bindings, produced by
+//! `TraceWitness.get_witness_bytes` in Python.
+//!
+//! All integers are little-endian. After the header come the table of step type UUIDs and the
+//! step type of each step instance, as an index into the table. Then the values of each signal,
+//! with its UUID, kind and annotation up front, column-major: a bitmap of the step instances
+//! assigning it and the field elements they assign, in their canonical representation.
+//!
+//! Checkpoints of trace generation are encoded with their step counts and the witness of their
+//! carried step instance.
+//!
+//! ```text
+//! "CHQW" | version: u8 | field element size: u16
+//! step types: u32 | UUID: u128 ...
+//! steps: u32 | step type index: u32 ...
+//! signals: u32 | (kind: u8 | UUID: u128 | phase: u32 | rotation: i32 | annotation | bitmap | values) ...
+//! ```
+//!
+//! The signal kinds are 0 for internal, 1 for forward, 2 for shared and 3 for fixed signals. The
+//! rotation of a forward signal is 1 for its next value, and an annotation is a u32 length and
+//! UTF-8 bytes.
+
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    io::{self, Read, Write},
+};
+
+use ff::PrimeField;
+
+use crate::{
+    sbpir::{query::Queriable, FixedSignal, ForwardSignal, InternalSignal, SharedSignal},
+    util::UUID,
+    wit_gen::{StepInstance, TraceCheckpoint, TraceWitness},
+};
+
+/// Magic bytes at the start of an encoded witness.
+pub const MAGIC: &[u8; 4] = b"CHQW";
+const CHECKPOINT_MAGIC: &[u8; 4] = b"CHQC";
+const VERSION: u8 = 1;
+
+/// Most items allocated up front for a count read from the input, which is not trusted. Longer
+/// lists grow as their items are read.
+const MAX_PREALLOCATION: usize = 1 << 16;
+
+const INTERNAL: u8 = 0;
+const FORWARD: u8 = 1;
+const SHARED: u8 = 2;
+const FIXED: u8 = 3;
+
+/// Writes `witness` in the binary encoding. Fails with `InvalidInput` if it assigns signals other
+/// than internal, forward, shared and fixed signals.
+pub fn write<F: PrimeField + Hash, W: Write>(
+    witness: &TraceWitness<F>,
+    mut writer: W,
+) -> io::Result<()> {
+    let repr_size = F::Repr::default().as_ref().len();
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[VERSION])?;
+    writer.write_all(&(repr_size as u16).to_le_bytes())?;
+
+    let mut step_types: Vec<UUID> = Vec::new();
+    let mut step_type_indexes: HashMap<UUID, usize> = HashMap::new();
+    let mut signals: Vec<Queriable<F>> = Vec::new();
+    let mut seen_signals: HashSet<Queriable<F>> = HashSet::new();
+    for step_instance in witness.step_instances.iter() {
+        step_type_indexes
+            .entry(step_instance.step_type_uuid)
+            .or_insert_with(|| {
+                step_types.push(step_instance.step_type_uuid);
+                step_types.len() - 1
+            });
+        for signal in step_instance.assignments.keys() {
+            if seen_signals.insert(*signal) {
+                signals.push(*signal);
+            }
+        }
+    }
+
+    write_u32(&mut writer, step_types.len())?;
+    for step_type in step_types.iter() {
+        writer.write_all(&step_type.to_le_bytes())?;
+    }
+
+    write_u32(&mut writer, witness.step_instances.len())?;
+    for step_instance in witness.step_instances.iter() {
+        write_u32(
+            &mut writer,
+            step_type_indexes[&step_instance.step_type_uuid],
+        )?;
+    }
+
+    write_u32(&mut writer, signals.len())?;
+    for signal in signals.iter() {
+        write_signal(&mut writer, signal)?;
+
+        let mut bitmap = vec![0u8; witness.step_instances.len().div_ceil(8)];
+        let mut values = Vec::new();
+        for (index, step_instance) in witness.step_instances.iter().enumerate() {
+            if let Some(value) = step_instance.assignments.get(signal) {
+                bitmap[index / 8] |= 1 << (index % 8);
+                values.push(value.to_repr());
+            }
+        }
+        writer.write_all(&bitmap)?;
+        for value in values {
+            writer.write_all(value.as_ref())?;
+        }
+    }
+
+    writer.flush()
+}
+
+/// Reads a witness in the binary encoding. Fails with `InvalidData` if it is malformed.
+pub fn read<F: PrimeField + Hash, R: Read>(mut reader: R) -> io::Result<TraceWitness<F>> {
+    let mut magic = [0u8; 4];
+    read_exact(&mut reader, &mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("not a chiquito witness"));
+    }
+    let version = read_array::<1, _>(&mut reader)?[0];
+    if version != VERSION {
+        return Err(invalid_data(format!("unsupported version {}", version)));
+    }
+    let repr_size = u16::from_le_bytes(read_array(&mut reader)?) as usize;
+    if repr_size != F::Repr::default().as_ref().len() {
+        return Err(invalid_data(format!(
+            "field elements of {} bytes, expected {}",
+            repr_size,
+            F::Repr::default().as_ref().len()
+        )));
+    }
+
+    let num_step_types = read_u32(&mut reader)?;
+    let mut step_types: Vec<UUID> = Vec::with_capacity(num_step_types.min(MAX_PREALLOCATION));
+    for _ in 0..num_step_types {
+        step_types.push(u128::from_le_bytes(read_array(&mut reader)?));
+    }
+
+    let num_steps = read_u32(&mut reader)?;
+    let mut step_instances = Vec::with_capacity(num_steps.min(MAX_PREALLOCATION));
+    for _ in 0..num_steps {
+        let index = read_u32(&mut reader)?;
+        let step_type_uuid = *step_types
+            .get(index)
+            .ok_or_else(|| invalid_data(format!("unknown step type index {}", index)))?;
+        step_instances.push(StepInstance::<F> {
+            step_type_uuid,
+            assignments: HashMap::new(),
+        });
+    }
+
+    for _ in 0..read_u32(&mut reader)? {
+        let signal = read_signal(&mut reader)?;
+
+        let mut bitmap = vec![0u8; num_steps.div_ceil(8)];
+        read_exact(&mut reader, &mut bitmap)?;
+        for (index, step_instance) in step_instances.iter_mut().enumerate() {
+            if bitmap[index / 8] & (1 << (index % 8)) == 0 {
+                continue;
+            }
+
+            let mut repr = F::Repr::default();
+            read_exact(&mut reader, repr.as_mut())?;
+            let value = Option::from(F::from_repr(repr))
+                .ok_or_else(|| invalid_data("non canonical field element"))?;
+            step_instance.assignments.insert(signal, value);
+        }
+    }
+
+    Ok(TraceWitness { step_instances })
+}
+
+/// Writes a checkpoint of the generation of a trace, see `TraceContext::checkpoint`.
+pub fn write_checkpoint<F: PrimeField + Hash, W: Write>(
+    checkpoint: &TraceCheckpoint<F>,
+    mut writer: W,
+) -> io::Result<()> {
+    writer.write_all(CHECKPOINT_MAGIC)?;
+    writer.write_all(&[VERSION])?;
+    writer.write_all(&(checkpoint.num_steps as u64).to_le_bytes())?;
+    writer.write_all(&(checkpoint.steps as u64).to_le_bytes())?;
+
+    let last = TraceWitness {
+        step_instances: checkpoint.last.iter().cloned().collect(),
+    };
+    write(&last, writer)
+}
+
+/// Reads a checkpoint of the generation of a trace, to resume it with `TraceContext::resume`.
+pub fn read_checkpoint<F: PrimeField + Hash, R: Read>(
+    mut reader: R,
+) -> io::Result<TraceCheckpoint<F>> {
+    if &read_array::<4, _>(&mut reader)? != CHECKPOINT_MAGIC {
+        return Err(invalid_data("not a chiquito checkpoint"));
+    }
+    let version = read_array::<1, _>(&mut reader)?[0];
+    if version != VERSION {
+        return Err(invalid_data(format!("unsupported version {}", version)));
+    }
+    let num_steps = u64::from_le_bytes(read_array(&mut reader)?) as usize;
+    let steps = u64::from_le_bytes(read_array(&mut reader)?) as usize;
+    let last = read(reader)?.step_instances.pop();
+
+    Ok(TraceCheckpoint {
+        num_steps,
+        steps,
+        last,
+    })
+}
+
+fn write_signal<F, W: Write>(writer: &mut W, signal: &Queriable<F>) -> io::Result<()> {
+    let (kind, uuid, phase, rotation, annotation) = match signal {
+        Queriable::Internal(s) => (INTERNAL, s.uuid(), 0, 0, s.annotation()),
+        Queriable::Forward(s, next) => (FORWARD, s.uuid(), s.phase(), *next as i32, s.annotation()),
+        Queriable::Shared(s, rot) => (SHARED, s.uuid(), s.phase(), *rot, s.annotation()),
+        Queriable::Fixed(s, rot) => (FIXED, s.uuid(), 0, *rot, s.annotation()),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("cannot encode the assignment of {:?}", signal),
+            ))
+        }
+    };
+
+    writer.write_all(&[kind])?;
+    writer.write_all(&uuid.to_le_bytes())?;
+    write_u32(writer, phase)?;
+    writer.write_all(&rotation.to_le_bytes())?;
+    write_u32(writer, annotation.len())?;
+    writer.write_all(annotation.as_bytes())
+}
+
+fn read_signal<F, R: Read>(reader: &mut R) -> io::Result<Queriable<F>> {
+    let kind = read_array::<1, _>(reader)?[0];
+    let uuid = u128::from_le_bytes(read_array(reader)?);
+    let phase = read_u32(reader)?;
+    let rotation = i32::from_le_bytes(read_array(reader)?);
+    let len = read_u32(reader)?;
+    let mut annotation = Vec::with_capacity(len.min(MAX_PREALLOCATION));
+    reader
+        .by_ref()
+        .take(len as u64)
+        .read_to_end(&mut annotation)?;
+    if annotation.len() != len {
+        return Err(invalid_data("truncated input"));
+    }
+    let annotation = String::from_utf8(annotation).map_err(invalid_data)?;
+
+    match kind {
+        INTERNAL => Ok(Queriable::Internal(InternalSignal::new_with_id(
+            uuid, annotation,
+        ))),
+        FORWARD => Ok(Queriable::Forward(
+            ForwardSignal::new_with_id(uuid, phase, annotation),
+            rotation != 0,
+        )),
+        SHARED => Ok(Queriable::Shared(
+            SharedSignal::new_with_id(uuid, phase, annotation),
+            rotation,
+        )),
+        FIXED => Ok(Queriable::Fixed(
+            FixedSignal::new_with_id(uuid, annotation),
+            rotation,
+        )),
+        _ => Err(invalid_data(format!("unknown signal kind {}", kind))),
+    }
+}
+
+fn write_u32<W: Write>(writer: &mut W, value: usize) -> io::Result<()> {
+    let value = u32::try_from(value)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "length over u32"))?;
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<usize> {
+    Ok(u32::from_le_bytes(read_array(reader)?) as usize)
+}
+
+fn read_array<const N: usize, R: Read>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    read_exact(reader, &mut bytes)?;
+
+    Ok(bytes)
+}
+
+/// Fills `bytes`, failing with `InvalidData` if the input ends first.
+fn read_exact<R: Read>(reader: &mut R, bytes: &mut [u8]) -> io::Result<()> {
+    reader.read_exact(bytes).map_err(|error| {
+        if error.kind() == io::ErrorKind::UnexpectedEof {
+            invalid_data("truncated input")
+        } else {
+            error
+        }
+    })
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+
+    #[test]
+    fn test_binary_roundtrip() {
+        let a = Queriable::Forward(ForwardSignal::new_with_phase(1, "a".into()), false);
+        let b = Queriable::Shared(SharedSignal::new_with_phase(0, "b".into()), -1);
+        let c = Queriable::Internal(InternalSignal::new("c"));
+        let d = Queriable::Fixed(FixedSignal::new("d".into()), 2);
+        let witness = TraceWitness {
+            step_instances: vec![
+                StepInstance {
+                    step_type_uuid: 7,
+                    assignments: HashMap::from([(a, Fr::from(1)), (c, -Fr::from(2))]),
+                },
+                StepInstance {
+                    step_type_uuid: 8,
+                    assignments: HashMap::from([(a.next(), Fr::from(3)), (b, Fr::from(4))]),
+                },
+                StepInstance {
+                    step_type_uuid: 7,
+                    assignments: HashMap::from([(d, Fr::from(5))]),
+                },
+            ],
+        };
+
+        let mut bytes = Vec::new();
+        write(&witness, &mut bytes).unwrap();
+        let decoded: TraceWitness<Fr> = read(bytes.as_slice()).unwrap();
+
+        assert_eq!(decoded.step_instances.len(), 3);
+        for (decoded, step_instance) in decoded.step_instances.iter().zip(witness.step_instances) {
+            assert_eq!(decoded.step_type_uuid, step_instance.step_type_uuid);
+            assert_eq!(decoded.assignments, step_instance.assignments);
+        }
+        assert!(read::<Fr, _>(&bytes[..bytes.len() - 1]).is_err());
+        assert!(read::<Fr, _>(&b"CHQX"[..]).is_err());
+    }
+
+    #[test]
+    fn test_binary_huge_counts() {
+        let mut bytes = Vec::new();
+        let empty = TraceWitness::<Fr> {
+            step_instances: vec![],
+        };
+        write(&empty, &mut bytes).unwrap();
+        let header = bytes[..7].to_vec();
+
+        // counts far over the input fail when it ends, without allocating them up front
+        for counts in [[u32::MAX, 0], [0, u32::MAX]] {
+            let mut bytes = header.clone();
+            for count in counts {
+                bytes.extend(count.to_le_bytes());
+            }
+            let error = read::<Fr, _>(bytes.as_slice()).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! Sets of witnesses of one compiled circuit, for services proving many instances of it.
+//!
+//! A `WitnessSet` is a directory with the UUID of the circuit in a `circuit` file and one witness
+//! per file, in the binary encoding of `wit_gen::binary`, numbered in order of insertion. Only the
+//! witnesses being processed are in memory: they are generated and written one at a time, and
+//! `WitnessSet::pipeline` reads them ahead in another thread, up to a bound, while the caller
+//! assigns and proves them.
+
+use std::{
+    fs::{self, File},
+    hash::Hash,
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+};
+
+use ff::PrimeField;
+
+use crate::{
+    field::Field,
+    plonkish::ir::assignments::{AssignmentGenerator, Assignments},
+    sbpir::SBPIR,
+    util::UUID,
+    wit_gen::{binary, TraceWitness, WitnessError},
+};
+
+const CIRCUIT_FILE: &str = "circuit";
+const WITNESS_EXTENSION: &str = "chqw";
+
+/// Witnesses of the compiled circuit with UUID `circuit`, persisted in a directory.
+pub struct WitnessSet {
+    dir: PathBuf,
+    circuit: UUID,
+    len: usize,
+}
+
+impl WitnessSet {
+    /// Creates an empty set in `dir` for the compiled circuit with UUID `circuit`, see
+    /// `AssignmentGenerator::uuid`. Fails with `AlreadyExists` if `dir` already has a set.
+    pub fn create<P: AsRef<Path>>(dir: P, circuit: UUID) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let circuit_file = dir.join(CIRCUIT_FILE);
+        if circuit_file.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already has a witness set", dir.display()),
+            ));
+        }
+        fs::write(circuit_file, circuit.to_string())?;
+
+        Ok(Self {
+            dir,
+            circuit,
+            len: 0,
+        })
+    }
+
+    /// Opens the set in `dir`, with the witnesses written to it before.
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        let circuit = fs::read_to_string(dir.join(CIRCUIT_FILE))?
+            .trim()
+            .parse::<UUID>()
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        let mut len = 0;
+        while witness_path(&dir, len).exists() {
+            len += 1;
+        }
+
+        Ok(Self { dir, circuit, len })
+    }
+
+    /// UUID of the compiled circuit of the witnesses.
+    pub fn circuit(&self) -> UUID {
+        self.circuit
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Writes `witness` at the end of the set and returns its index.
+    pub fn push<F: PrimeField + Hash>(&mut self, witness: &TraceWitness<F>) -> io::Result<usize> {
+        let index = self.len;
+        let path = witness_path(&self.dir, index);
+        // written aside and renamed, so an interrupted write does not leave a truncated witness
+        let tmp = path.with_extension("tmp");
+        binary::write(witness, BufWriter::new(File::create(&tmp)?))?;
+        fs::rename(tmp, path)?;
+        self.len += 1;
+
+        Ok(index)
+    }
+
+    /// Reads the witness at `index`.
+    pub fn get<F: PrimeField + Hash>(&self, index: usize) -> io::Result<TraceWitness<F>> {
+        if index >= self.len {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no witness {} in a set of {}", index, self.len),
+            ));
+        }
+
+        binary::read(BufReader::new(File::open(witness_path(&self.dir, index))?))
+    }
+
+    /// Generates the witness of each of `args` with the trace of `generator` and writes them at
+    /// the end of the set, one at a time. Returns the index of the first one.
+    pub fn generate<F, TraceArgs, I>(
+        &mut self,
+        generator: &AssignmentGenerator<F, TraceArgs>,
+        args: I,
+    ) -> io::Result<usize>
+    where
+        F: PrimeField + Field + Hash,
+        I: IntoIterator<Item = TraceArgs>,
+    {
+        self.check_circuit(generator.uuid())?;
+
+        let first = self.len;
+        for args in args {
+            self.push(&generator.generate_trace_witness(args))?;
+        }
+
+        Ok(first)
+    }
+
+    /// Validates each witness against `circuit` with `TraceWitness::validate`, reading one at a
+    /// time. Returns the index and errors of the invalid witnesses.
+    pub fn validate<F: PrimeField + Hash, TraceArgs>(
+        &self,
+        circuit: &SBPIR<F, TraceArgs>,
+    ) -> io::Result<Vec<(usize, Vec<WitnessError>)>> {
+        let mut invalid = Vec::new();
+        for index in 0..self.len {
+            if let Err(errors) = self.get::<F>(index)?.validate(circuit) {
+                invalid.push((index, errors));
+            }
+        }
+
+        Ok(invalid)
+    }
+
+    /// Feeds the assignments of every witness, generated by `generator`, to `prove` with the
+    /// index of the witness. The witnesses are read and decoded in another thread, at most
+    /// `read_ahead` of them ahead of the one being proven, so memory is bounded regardless of the
+    /// size of the set. Stops at the first witness that cannot be read.
+    pub fn pipeline<F, TraceArgs, P>(
+        &self,
+        generator: &AssignmentGenerator<F, TraceArgs>,
+        read_ahead: usize,
+        mut prove: P,
+    ) -> io::Result<()>
+    where
+        F: PrimeField + Field + Hash,
+        P: FnMut(usize, Assignments<F>),
+    {
+        self.check_circuit(generator.uuid())?;
+
+        let (sender, receiver) = mpsc::sync_channel(read_ahead);
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                for index in 0..self.len {
+                    let witness = self.get::<F>(index);
+                    let failed = witness.is_err();
+                    // the receiver hangs up when proving stops early
+                    if sender.send((index, witness)).is_err() || failed {
+                        break;
+                    }
+                }
+            });
+
+            for (index, witness) in receiver {
+                prove(index, generator.generate_with_witness(witness?));
+            }
+
+            Ok(())
+        })
+    }
+
+    fn check_circuit(&self, circuit: UUID) -> io::Result<()> {
+        if circuit != self.circuit {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("witness set of circuit {}, not {}", self.circuit, circuit),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn witness_path(dir: &Path, index: usize) -> PathBuf {
+    dir.join(format!("{:08}.{}", index, WITNESS_EXTENSION))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        sbpir::{query::Queriable, InternalSignal},
+        util::uuid,
+        wit_gen::StepInstance,
+    };
+
+    #[test]
+    fn test_witness_set_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("chiquito-witness-set-{}", uuid()));
+        let a = Queriable::Internal(InternalSignal::new("a"));
+
+        let mut set = WitnessSet::create(&dir, 42).unwrap();
+        assert!(WitnessSet::create(&dir, 42).is_err());
+        for value in 0..3 {
+            let witness = TraceWitness {
+                step_instances: vec![StepInstance {
+                    step_type_uuid: 7,
+                    assignments: HashMap::from([(a, Fr::from(value))]),
+                }],
+            };
+            assert_eq!(set.push(&witness).unwrap(), value as usize);
+        }
+
+        let set = WitnessSet::open(&dir).unwrap();
+        assert_eq!(set.circuit(), 42);
+        assert_eq!(set.len(), 3);
+        let witness: TraceWitness<Fr> = set.get(2).unwrap();
+        assert_eq!(witness.step_instances[0].assignments[&a], Fr::from(2));
+        assert!(set.get::<Fr>(3).is_err());
+
+        let generator = AssignmentGenerator::<Fr, ()>::empty(43);
+        assert!(set.pipeline(&generator, 1, |_, _| {}).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}